@@ -0,0 +1,89 @@
+//! 自旋锁在 8 个线程竞争下的吞吐量：退避前（紧凑的 `compare_exchange` 循环）与退避后
+//!
+//! ```text
+//! cargo bench -p hello-rust --bench bench_spinlock
+//! ```
+
+use std::{
+    cell::UnsafeCell,
+    hint::{black_box, spin_loop},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+
+// 所在的包只有二进制目标，直接引入源文件
+#[allow(dead_code, unused_imports)]
+#[path = "../src/concurrency_tests.rs"]
+mod concurrency_tests;
+
+use concurrency_tests::SpinLock;
+
+const THREADS: usize = 8;
+const LOCKS_PER_THREAD: u64 = 10_000;
+
+/// 退避前的实现：获取失败时立即重试 `compare_exchange`，保护一个计数器
+struct TightSpinLock {
+    locked: AtomicBool,
+    count: UnsafeCell<u64>,
+}
+
+// SAFETY: 计数器只在持有锁时访问
+unsafe impl Sync for TightSpinLock {}
+
+impl TightSpinLock {
+    fn increment(&self) {
+        while self
+            .locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            spin_loop();
+        }
+        // SAFETY: 当前线程持有锁
+        unsafe { *self.count.get() += 1 };
+        self.locked.store(false, Ordering::Release);
+    }
+}
+
+/// `THREADS` 个线程各自调用 `critical_section` `LOCKS_PER_THREAD` 次
+fn contend(critical_section: impl Fn() + Sync) {
+    thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|| {
+                for _ in 0..LOCKS_PER_THREAD {
+                    critical_section();
+                }
+            });
+        }
+    });
+}
+
+fn bench_spinlock(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spinlock/8_threads");
+    group.throughput(Throughput::Elements(THREADS as u64 * LOCKS_PER_THREAD));
+
+    group.bench_function("tight_spin", |b| {
+        let counter = TightSpinLock { locked: AtomicBool::new(false), count: UnsafeCell::new(0) };
+        b.iter(|| contend(|| counter.increment()));
+        black_box(counter.count.into_inner());
+    });
+
+    group.bench_function("backoff", |b| {
+        let counter = SpinLock::new(0u64);
+        b.iter(|| contend(|| *counter.lock() += 1));
+        black_box(counter.into_inner());
+    });
+
+    group.bench_function("backoff_park", |b| {
+        let counter = SpinLock::with_park(0u64);
+        b.iter(|| contend(|| *counter.lock() += 1));
+        black_box(counter.into_inner());
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_spinlock);
+criterion_main!(benches);
@@ -0,0 +1,87 @@
+//! 线程池与 rayon、每个任务启动一个线程的对比
+//!
+//! 三种负载：大量极小的任务（调度开销为主）、少量耗时的任务（计算为主）以及两者混合。
+//! 三种实现使用相同的线程数，用于量化评估调度策略的改动（如工作窃取、批量出队）。
+//!
+//! ```text
+//! cargo bench -p hello-rust --bench bench_threadpool
+//! ```
+
+use std::{hint::black_box, thread};
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+// 所在的包只有二进制目标，直接引入源文件：内联模块使子模块在 `src/threadpool/` 下查找，
+// 再导入到根模块，源文件中的 `crate::threadpool` 路径保持有效
+#[allow(dead_code, unused_imports)]
+#[path = "../src"]
+mod src {
+    pub mod threadpool;
+}
+
+use src::threadpool::{self, ThreadPool, WaitGroup};
+
+/// 极小的任务与耗时的任务的循环次数
+const SMALL: u64 = 100;
+const LARGE: u64 = 200_000;
+
+/// 一个任务：循环 `iterations` 次
+fn work(iterations: u64) -> u64 {
+    (0..iterations).fold(0, |acc, i| black_box(acc ^ i.wrapping_mul(0x9E37_79B9)))
+}
+
+/// 各负载的任务列表（每个任务的循环次数）
+fn workloads() -> Vec<(&'static str, Vec<u64>)> {
+    let mixed = (0..1_000).map(|i| if i % 64 == 0 { LARGE } else { SMALL }).collect();
+    vec![("small", vec![SMALL; 1_000]), ("large", vec![LARGE; 64]), ("mixed", mixed)]
+}
+
+fn bench_threadpool(c: &mut Criterion) {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let pool = ThreadPool::new(threads);
+    let rayon = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+
+    for (name, tasks) in workloads() {
+        let mut group = c.benchmark_group(format!("threadpool/{name}"));
+        group.throughput(Throughput::Elements(tasks.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("ThreadPool", threads), &tasks, |b, tasks| {
+            b.iter(|| {
+                let wait_group = WaitGroup::new();
+                for &iterations in tasks {
+                    pool.execute_in_group(&wait_group, move || {
+                        black_box(work(iterations));
+                    });
+                }
+                wait_group.wait();
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("rayon", threads), &tasks, |b, tasks| {
+            b.iter(|| {
+                rayon.scope(|scope| {
+                    for &iterations in tasks {
+                        scope.spawn(move |_| {
+                            black_box(work(iterations));
+                        });
+                    }
+                })
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("spawn_per_task", threads), &tasks, |b, tasks| {
+            b.iter(|| {
+                thread::scope(|scope| {
+                    for &iterations in tasks {
+                        scope.spawn(move || black_box(work(iterations)));
+                    }
+                })
+            })
+        });
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_threadpool);
+criterion_main!(benches);
@@ -1,14 +1,20 @@
 use std::{
+    cell::UnsafeCell,
     hint::spin_loop,
+    ops::{Deref, DerefMut},
     sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+    time::Duration,
 };
 
 /// 一个简单的自旋锁（SpinLock）实现
 ///
 /// # 特点
 /// - 基于 `AtomicBool` 实现，线程安全
-/// - 忙等待（spin）式锁，不会让出 CPU
+/// - 忙等待（spin）式锁，短暂等待时不让出 CPU；竞争激烈时逐步退避（见 [`Backoff`]）
 /// - 适用于锁持有时间极短的场景（如计数器、自定义同步原语）
+/// - 与 `Mutex<T>` 一样保护一份数据：只有通过 `lock()` 返回的守卫（guard）才能访问，
+///   守卫销毁时自动解锁，不会忘记解锁
 ///
 /// # 内存语义
 /// - `Ordering::Acquire`：确保在成功获取锁后，后续操作看到锁之前的所有写入。
@@ -22,19 +28,15 @@ use std::{
 /// // 定义自旋锁
 /// use crate::concurrency_tests::SpinLock; // 实际使用时替换为正确模块路径
 ///
-/// let lock = Arc::new(SpinLock::new());
-/// let counter = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+/// let counter = Arc::new(SpinLock::new(0));
 ///
 /// // 启动多个线程竞争锁
 /// let mut handles = vec![];
 /// for _ in 0..4 {
-///     let lock_clone = Arc::clone(&lock);
 ///     let counter_clone = Arc::clone(&counter);
 ///     handles.push(thread::spawn(move || {
 ///         for _ in 0..1000 {
-///             lock_clone.lock();              // 加锁
-///             counter_clone.fetch_add(1, Ordering::Relaxed);
-///             lock_clone.unlock();            // 解锁
+///             *counter_clone.lock() += 1; // 加锁，语句结束时守卫销毁、自动解锁
 ///         }
 ///     }));
 /// }
@@ -43,32 +45,50 @@ use std::{
 ///     h.join().unwrap();
 /// }
 ///
-/// assert_eq!(counter.load(Ordering::Relaxed), 4000);
-/// println!("最终计数结果：{}", counter.load(Ordering::Relaxed));
+/// assert_eq!(*counter.lock(), 4000);
+/// println!("最终计数结果：{}", *counter.lock());
 /// ```
 ///
 /// 输出（顺序可能不同）：
 /// ```text
 /// 最终计数结果：4000
 /// ```
+///
+/// 8 个线程竞争时退避前后的吞吐量对比见 `benches/bench_spinlock.rs`。
 #[allow(dead_code)]
-struct SpinLock {
+pub(crate) struct SpinLock<T> {
     /// 是否已上锁
     locked: AtomicBool,
+    /// 退避到最后是否挂起线程，见 [`Backoff`]
+    park: bool,
+    /// 被保护的数据，只在持有锁时访问
+    data: UnsafeCell<T>,
 }
 
+// SAFETY: 锁保证同一时刻只有一个线程访问 `data`，因此只要 `T` 可以在线程间移动，
+// 锁本身就可以在线程间共享（与 `Mutex<T>` 的约束相同）。
+unsafe impl<T: Send> Send for SpinLock<T> {}
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+
 #[allow(dead_code)]
-impl SpinLock {
+impl<T> SpinLock<T> {
     /// 创建一个未上锁的自旋锁
-    fn new() -> Self {
-        Self { locked: AtomicBool::new(false) }
+    pub(crate) fn new(data: T) -> Self {
+        Self { locked: AtomicBool::new(false), park: false, data: UnsafeCell::new(data) }
     }
 
-    /// 获取锁（阻塞直到成功）
+    /// 创建一个未上锁的自旋锁，长时间等待时短暂挂起线程而不是一直让出 CPU
+    pub(crate) fn with_park(data: T) -> Self {
+        Self { park: true, ..Self::new(data) }
+    }
+
+    /// 获取锁（阻塞直到成功），返回的守卫销毁时释放锁
     ///
     /// - 使用 `compare_exchange` 将 `locked` 从 `false` 改为 `true`。
-    /// - 如果失败（锁已被占用），进入自旋等待。
-    fn lock(&self) {
+    /// - 如果失败（锁已被占用），只读取 `locked` 等待它变为 `false`，期间按 [`Backoff`] 退避，
+    ///   不反复写入同一缓存行（test-and-test-and-set）。
+    pub(crate) fn lock(&self) -> SpinLockGuard<'_, T> {
+        let mut backoff = Backoff::new(self.park);
         while self
             .locked
             .compare_exchange(
@@ -79,17 +99,104 @@ impl SpinLock {
             )
             .is_err()
         {
-            // 自旋等待（忙等）
-            std::hint::spin_loop();
+            while self.locked.load(Ordering::Relaxed) {
+                backoff.snooze();
+            }
         }
+        SpinLockGuard { lock: self }
+    }
+
+    /// 取出被保护的数据
+    pub(crate) fn into_inner(self) -> T {
+        self.data.into_inner()
     }
+}
 
+/// 自旋等待的退避策略
+///
+/// 1. 前 [`Backoff::SPIN_LIMIT`] 轮自旋，每轮的 `spin_loop` 次数翻倍，锁很快释放时延迟最低
+/// 2. 之后改为 `thread::yield_now` 让出 CPU，持有锁的线程被调度出去时不再白白消耗时间片
+/// 3. 超过 [`Backoff::YIELD_LIMIT`] 轮后，开启 `park` 时每轮挂起 [`Backoff::PARK_TIMEOUT`]
+///    （带超时的 `park`，不需要解锁方唤醒），否则继续让出 CPU
+struct Backoff {
+    step: u32,
+    park: bool,
+}
+
+impl Backoff {
+    const SPIN_LIMIT: u32 = 6;
+    const YIELD_LIMIT: u32 = 10;
+    const PARK_TIMEOUT: Duration = Duration::from_micros(50);
+
+    fn new(park: bool) -> Self {
+        Self { step: 0, park }
+    }
+
+    /// 等待一轮，等待的时长逐轮增加
+    fn snooze(&mut self) {
+        if self.step <= Self::SPIN_LIMIT {
+            for _ in 0..1 << self.step {
+                spin_loop();
+            }
+        } else if self.step <= Self::YIELD_LIMIT || !self.park {
+            thread::yield_now();
+        } else {
+            thread::park_timeout(Self::PARK_TIMEOUT);
+        }
+        if self.step <= Self::YIELD_LIMIT {
+            self.step += 1;
+        }
+    }
+}
+
+/// 持有自旋锁期间访问数据的守卫，销毁时释放锁
+///
+/// 守卫借用了锁，因此解锁之后不可能再访问数据。
+///
+/// 共享守卫（`&SpinLockGuard`）就能通过 `Deref` 读取数据，因此只有 `T: Sync` 时守卫才能在线程间共享，
+/// 与 `MutexGuard` 相同。否则 `Cell` 这类 `Send` 但不 `Sync` 的数据会被多个线程同时访问：
+///
+/// ```compile_fail
+/// use std::cell::Cell;
+///
+/// let lock = SpinLock::new(Cell::new(0));
+/// let guard = lock.lock();
+/// std::thread::scope(|s| {
+///     s.spawn(|| guard.set(1)); // 编译报错：`Cell<i32>` cannot be shared between threads safely
+///     guard.set(2);
+/// });
+/// ```
+pub(crate) struct SpinLockGuard<'a, T> {
+    lock: &'a SpinLock<T>,
+}
+
+// SAFETY: `&SpinLockGuard<T>` 只能得到 `&T`，`T: Sync` 时可以在线程间共享。
+// 显式实现取代了自动实现：后者只要求 `&SpinLock<T>: Sync`（即 `T: Send`），并不够。
+unsafe impl<T: Sync> Sync for SpinLockGuard<'_, T> {}
+
+impl<T> Deref for SpinLockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: 守卫存在期间当前线程持有锁
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for SpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: 守卫存在期间当前线程持有锁，`&mut self` 保证可变借用唯一
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for SpinLockGuard<'_, T> {
     /// 释放锁
     ///
     /// - 直接将 `locked` 置为 `false`。
     /// - 使用 `Ordering::Release` 保证写入可见性。
-    fn unlock(&self) {
-        self.locked.store(false, Ordering::Release); // `Ordering::Release`：确保在释放锁前的写入对之后获取锁的线程可见。
+    fn drop(&mut self) {
+        self.lock.locked.store(false, Ordering::Release); // `Ordering::Release`：确保在释放锁前的写入对之后获取锁的线程可见。
     }
 }
 
@@ -132,7 +239,6 @@ mod tests {
         pin::Pin,
         sync::{
             Arc, Mutex, RwLock,
-            atomic::{AtomicUsize, Ordering},
             mpsc::{RecvError, SendError, channel},
         },
         task::{Context, Poll},
@@ -425,19 +531,35 @@ mod tests {
 
     #[test]
     fn test_concurrency_spinlock() {
-        let lock = Arc::new(SpinLock::new());
-        let counter = Arc::new(AtomicUsize::new(0));
+        // 编译期断言类型不是 `Sync`：是 `Sync` 时下面两个实现都适用，类型推断有歧义，无法编译
+        //
+        // 必须用具体类型展开；在泛型函数中 `T: Sync` 无法证明，总是只有第一个实现适用。
+        macro_rules! assert_not_sync {
+            ($ty:ty) => {{
+                trait AmbiguousIfSync<A> {
+                    fn check() {}
+                }
+                impl<T> AmbiguousIfSync<()> for T {}
+                impl<T: Sync> AmbiguousIfSync<u8> for T {}
+                <$ty as AmbiguousIfSync<_>>::check();
+            }};
+        }
+
+        // 守卫的 `Sync` 取决于 `T: Sync`（对应类型文档中的 compile_fail 示例）
+        fn assert_sync<T: Sync>() {}
+        assert_sync::<super::SpinLockGuard<'_, usize>>();
+        assert_not_sync!(super::SpinLockGuard<'_, std::cell::Cell<usize>>);
+
+        let counter = Arc::new(SpinLock::new(0));
 
         let handles: Vec<_> = (0..4)
             .map(|_| {
-                let lock = Arc::clone(&lock);
                 let counter = Arc::clone(&counter);
                 std::thread::spawn(move || {
                     for _ in 0..10000 {
-                        lock.lock();
-                        counter.fetch_add(1, Ordering::Relaxed); // 这里可以使用 Ordering::Relexed，因为已经上锁了。
-                        lock.unlock();
-                    }
+                        let mut guard = counter.lock();
+                        *guard += 1; // 持有锁期间普通的读写即可，不需要原子操作
+                    } // 守卫在这里销毁，自动解锁
                 })
             })
             .collect();
@@ -446,7 +568,37 @@ mod tests {
             handle.join().unwrap();
         }
 
-        assert_eq!(counter.load(Ordering::Relaxed), 40000); // 这里可以使用 Ordering::Relexed，因为已经上锁了。
+        assert_eq!(*counter.lock(), 40000);
+
+        // 开启 park 时结果相同，只是长时间等待的线程会短暂挂起
+        let counter = Arc::new(SpinLock::with_park(0));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let counter = Arc::clone(&counter);
+                std::thread::spawn(move || {
+                    for _ in 0..10000 {
+                        *counter.lock() += 1;
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(*counter.lock(), 80000);
+
+        // 持有锁的线程 panic 时，守卫在展开过程中销毁，锁同样被释放
+        let lock = Arc::new(SpinLock::new(vec![1]));
+        let cloned = Arc::clone(&lock);
+        let result = std::thread::spawn(move || {
+            cloned.lock().push(2);
+            let _guard = cloned.lock();
+            panic!("持有锁时 panic");
+        })
+        .join();
+        assert!(result.is_err());
+        assert_eq!(*lock.lock(), [1, 2]);
+        assert_eq!(Arc::into_inner(lock).unwrap().into_inner(), [1, 2]);
     }
 
     #[test]
@@ -18,7 +18,13 @@
 //! ## 共享队列
 //!
 //! * 多线程同时读写任务队列 -> 需要同步/互斥机制
-//! * 采用的方案： `crossbeam::channel` （无锁队列）
+//! * 采用的方案： `Mutex<BinaryHeap>` + `Condvar`，按优先级出队（见 `queue` 模块）
+//!
+//! ## 任务优先级
+//!
+//! 任务分为 [`Priority::Low`]、[`Priority::Normal`]（默认）与 [`Priority::High`] 三级，
+//! 通过 [`ThreadPool::execute_with_priority`] 与 [`ThreadPool::submit_with_priority`] 指定。
+//! 优先级高的任务先执行；等待较久的低优先级任务会逐渐“老化”提前，不会被持续的高优先级任务饿死。
 //!
 //! ## 线程循环
 //!
@@ -38,9 +44,70 @@
 //! * 避免频繁创建/消费线程
 //! * 任务完成后线程继续等待下一个任务
 //!
+//! 线程数默认固定。通过 [`Builder::core_threads`] 与 [`Builder::max_threads`] 可以让线程池伸缩：
+//! 队列积压时按需启动额外的线程，额外的线程空闲 [`Builder::keep_alive`] 后退出，适合突发的负载。
+//!
 //! ## 关闭线程池
 //!
-//! 调用者希望线程池优雅关闭：`drop` 任务队列（所有接收端关闭）
+//! 调用者希望线程池优雅关闭：关闭任务队列，工作线程执行完已入队的任务后退出（[`ThreadPool::shutdown`]）。
+//! 需要尽快退出时：
+//!
+//! * [`ThreadPool::shutdown_now`]：取出还没有开始执行的任务交还调用方，不等待正在执行的任务
+//! * [`ThreadPool::shutdown_timeout`]：最多等待给定时长，超时后丢弃剩余任务，不再等待工作线程
+//!
+//! ## 异步任务
+//!
+//! [`ThreadPool::spawn_future`] 在线程池中运行 `Future`：任务被唤醒时才放入队列 poll，
+//! 挂起期间不占用工作线程，CPU 密集的任务与异步任务可以共用一个线程池（见 `future` 模块）。
+//!
+//! ## tracing
+//!
+//! 开启 `tracing` 特性后，每个任务在以提交方当前 span 为父的 span 中执行，并记录排队与执行时长，
+//! 调用链可以跨越线程池（见 `instrument` 模块）。
+//!
+//! ## 默认线程池
+//!
+//! [`ThreadPool::global`] 是按需创建的进程级线程池，可以在启动时用 [`Builder::build_global`] 配置一次；
+//! 自由函数 [`spawn`] 与 [`join`] 直接使用它（见 `global` 模块）。
+//!
+//! ## 数据并行
+//!
+//! [`ThreadPool::map`] 与 [`ThreadPool::for_each_chunked`] 把输入切块后分给各工作线程，
+//! 按输入顺序汇总结果；它们等待全部任务结束才返回，任务可以借用调用方的数据（见 `parallel` 模块）。
+//!
+//! ## 运行指标
+//!
+//! [`ThreadPool::stats`] 返回排队的任务数、忙碌的工作线程数、完成与 panic 的任务数以及排队时长的直方图，
+//! 计数器都是原子变量，统计几乎没有开销（见 `stats` 模块）。
+//!
+//! ## 队列水位
+//!
+//! [`Builder::queue_watermarks`] 设置排队任务数的高、低水位，升到高水位与随后降到低水位时
+//! 分别调用 [`Builder::on_queue_high`] 与 [`Builder::on_queue_low`] 的回调，用于限流或告警；
+//! 检查只读取原子变量，几乎不增加提交任务的开销（见 `watermark` 模块）。
+//!
+//! ## 健康检查
+//!
+//! [`ThreadPool::health`] 报告每个工作线程最近的心跳、正在执行的任务已执行多久、是否超出
+//! [`Builder::task_budget`] 设置的预算，以及没有接替者就退出的线程；
+//! [`Builder::watchdog`] 启动一个定期检查并输出卡住的工作线程的看门狗线程（见 `health` 模块）。
+//!
+//! ## 任务 panic
+//!
+//! 工作线程捕获任务的 panic，记录次数（[`ThreadPool::panic_count`]）并调用可选的处理函数，
+//! 然后由新线程接替，线程池的容量保持不变（见 `worker` 模块）。
+//!
+//! ## 取回任务结果
+//!
+//! [`ThreadPool::execute`] 只负责执行；需要返回值时使用 [`ThreadPool::submit`]，
+//! 它返回的 [`TaskHandle`] 可以等待任务完成并取回返回值或 panic 的载荷（见 `task` 模块）。
+//! 只需等待一批任务结束时，用 [`ThreadPool::execute_in_group`] 把它们计入同一个 [`WaitGroup`]。
+//!
+//! ## 嵌套并行
+//!
+//! 任务可以向所在的线程池提交子任务并等待结果：工作线程等待 [`TaskHandle`] 或 [`WaitGroup`] 期间
+//! 执行队列中的其他任务，不会因为所有线程都在等待而死锁。[`ThreadPool::install`] 在线程池中运行一段代码，
+//! 其中的 `map` 等调用即使只有一个工作线程也能完成。在任务中调用 [`ThreadPool::join`] 会 panic 并给出原因。
 //!
 //! # 示例
 //!
@@ -60,37 +127,235 @@
 //! println!("所有任务完成");
 //! ```
 
-use std::thread;
+mod future;
+mod global;
+mod health;
+#[cfg(feature = "tracing")]
+mod instrument;
+mod parallel;
+mod queue;
+mod stats;
+mod task;
+mod wait_group;
+mod watermark;
+mod worker;
 
-use crossbeam::channel::{self, Sender};
+use std::{
+    any::Any,
+    sync::{Arc, atomic::Ordering},
+    thread,
+    time::Duration,
+};
+
+#[allow(unused_imports)]
+pub use self::future::{JoinFuture, block_on};
+#[allow(unused_imports)]
+pub use self::global::{GlobalPoolAlreadyInitialized, join, spawn};
+use self::health::Monitor;
+#[allow(unused_imports)]
+pub use self::health::{Health, WorkerHealth};
+pub use self::queue::Priority;
+#[allow(unused_imports)]
+pub use self::stats::{Stats, WaitHistogram};
+#[allow(unused_imports)]
+pub use self::task::{TaskError, TaskHandle};
+#[allow(unused_imports)]
+pub use self::wait_group::WaitGroup;
+#[allow(unused_imports)]
+pub use self::watermark::WatermarkHook;
+use self::watermark::Watermarks;
+use self::worker::{Hooks, Shared, Sizing};
+#[allow(unused_imports)]
+pub use self::worker::{PanicHandler, ThreadHook};
 
 #[allow(dead_code)]
 pub type Job = Box<dyn FnOnce() + Send + 'static>;
 
+/// 额外的工作线程默认的空闲时长
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(10);
+
+/// 线程池的构建器
+///
+/// ```
+/// let pool = ThreadPool::builder()
+///     .num_threads(4)
+///     .on_thread_start(|| println!("{:?} started", thread::current().name()))
+///     .on_task_panic(|payload| eprintln!("task panicked: {payload:?}"))
+///     .build();
+/// ```
+#[allow(dead_code)]
+#[derive(Default)]
+pub struct Builder {
+    core_threads: Option<usize>,
+    max_threads: Option<usize>,
+    keep_alive: Option<Duration>,
+    hooks: Hooks,
+    watermarks: Watermarks,
+    task_budget: Option<Duration>,
+    watchdog: Option<Duration>,
+}
+
+#[allow(dead_code)]
+impl Builder {
+    /// 固定的工作线程数，等同于把 `core_threads` 与 `max_threads` 设为相同的值
+    pub fn num_threads(self, num_threads: usize) -> Self {
+        self.core_threads(num_threads).max_threads(num_threads)
+    }
+
+    /// 始终保留的工作线程数，默认为可用的并行度（不超过 `max_threads`）
+    pub fn core_threads(mut self, core_threads: usize) -> Self {
+        self.core_threads = Some(core_threads);
+        self
+    }
+
+    /// 工作线程数的上限，默认与 `core_threads` 相同，即不伸缩
+    pub fn max_threads(mut self, max_threads: usize) -> Self {
+        self.max_threads = Some(max_threads);
+        self
+    }
+
+    /// 多于 `core_threads` 的工作线程空闲多久后退出，默认 10 秒
+    pub fn keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    /// 每个工作线程启动后、执行任务前在该线程上调用 `hook`，可以用来初始化线程局部变量
+    ///
+    /// 接替 panic 线程的新线程同样会调用。
+    pub fn on_thread_start<F>(mut self, hook: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.hooks.on_thread_start = Some(Box::new(hook));
+        self
+    }
+
+    /// 每个工作线程退出前在该线程上调用 `hook`，包括因任务 panic 而被替换的线程
+    pub fn on_thread_stop<F>(mut self, hook: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.hooks.on_thread_stop = Some(Box::new(hook));
+        self
+    }
+
+    /// 任务 panic 时在执行它的工作线程上调用 `handler`，参数为 panic 的载荷
+    ///
+    /// 通过 [`ThreadPool::submit`] 提交的任务的 panic 由句柄返回，不会调用处理函数。
+    pub fn on_task_panic<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&(dyn Any + Send)) + Send + Sync + 'static,
+    {
+        self.hooks.on_task_panic = Some(Box::new(handler));
+        self
+    }
+
+    /// 排队的任务数升到 `high` 时调用 [`on_queue_high`](Builder::on_queue_high) 的回调，
+    /// 之后降到 `low` 时调用 [`on_queue_low`](Builder::on_queue_low) 的回调，默认不设水位
+    ///
+    /// # Panics
+    ///
+    /// `low` 不小于 `high` 时 panic。
+    pub fn queue_watermarks(mut self, high: usize, low: usize) -> Self {
+        assert!(low < high, "low watermark must be less than high watermark");
+        self.watermarks.high = high;
+        self.watermarks.low = low;
+        self
+    }
+
+    /// 排队的任务数升到高水位时在提交任务的线程上调用 `hook`，参数为排队的任务数
+    ///
+    /// ```
+    /// let pool = ThreadPool::builder()
+    ///     .queue_watermarks(10_000, 1_000)
+    ///     .on_queue_high(|queued| eprintln!("{queued} tasks queued, shedding load"))
+    ///     .on_queue_low(|queued| eprintln!("queue drained to {queued}"))
+    ///     .build();
+    /// ```
+    pub fn on_queue_high<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(usize) + Send + Sync + 'static,
+    {
+        self.watermarks.on_high = Some(Box::new(hook));
+        self
+    }
+
+    /// 触发高水位后排队的任务数降到低水位时在工作线程上调用 `hook`，参数为排队的任务数
+    pub fn on_queue_low<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(usize) + Send + Sync + 'static,
+    {
+        self.watermarks.on_low = Some(Box::new(hook));
+        self
+    }
+
+    /// 任务的执行时长预算，执行超过这么久的任务在 [`ThreadPool::health`] 中标记为超出预算，
+    /// 默认没有预算
+    pub fn task_budget(mut self, budget: Duration) -> Self {
+        self.task_budget = Some(budget);
+        self
+    }
+
+    /// 启动看门狗线程，每隔 `interval` 检查一次，把超出预算的任务与死亡的工作线程输出到标准错误
+    ///
+    /// ```
+    /// let pool = ThreadPool::builder()
+    ///     .task_budget(Duration::from_secs(30))
+    ///     .watchdog(Duration::from_secs(5))
+    ///     .build();
+    /// ```
+    pub fn watchdog(mut self, interval: Duration) -> Self {
+        self.watchdog = Some(interval);
+        self
+    }
+
+    /// # Panics
+    ///
+    /// `max_threads` 为 0 或小于 `core_threads` 时 panic。
+    pub fn build(self) -> ThreadPool {
+        let parallelism = thread::available_parallelism().map_or(1, |n| n.get());
+        let core_threads = self
+            .core_threads
+            .unwrap_or_else(|| self.max_threads.map_or(parallelism, |max| max.min(parallelism)));
+        let max_threads = self.max_threads.unwrap_or(core_threads);
+        assert!(max_threads > 0, "max_threads must be greater than zero");
+        assert!(max_threads >= core_threads, "max_threads must not be less than core_threads");
+
+        let keep_alive = self.keep_alive.unwrap_or(DEFAULT_KEEP_ALIVE);
+        let sizing = Sizing { core_threads, max_threads, keep_alive };
+        let monitor = Monitor::new(self.task_budget);
+        let shared = Arc::new(Shared::new(self.hooks, sizing, self.watermarks, monitor));
+        for _ in 0..core_threads {
+            worker::grow(&shared);
+        }
+        if let Some(interval) = self.watchdog {
+            health::spawn_watchdog(&shared, interval);
+        }
+
+        ThreadPool { shared }
+    }
+}
+
 #[allow(dead_code)]
 pub struct ThreadPool {
-    /// 工作线程组
-    workers: Vec<thread::JoinHandle<()>>,
-    /// 任务发送者
-    sender: Option<Sender<Job>>,
+    /// 工作线程共享的状态，包括任务队列
+    shared: Arc<Shared>,
 }
 
 #[allow(dead_code)]
 impl ThreadPool {
     pub fn new(num_threads: usize) -> Self {
-        let (sender, receiver) = channel::unbounded::<Job>();
-        let workers: Vec<thread::JoinHandle<()>> = (0..num_threads)
-            .map(|_| {
-                let receiver = receiver.clone();
-                thread::spawn(move || {
-                    while let Ok(job) = receiver.recv() {
-                        job();
-                    }
-                })
-            })
-            .collect();
+        Self::builder().num_threads(num_threads).build()
+    }
+
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
 
-        Self { workers, sender: Some(sender) }
+    /// 存活的工作线程数
+    pub fn num_threads(&self) -> usize {
+        self.shared.threads.load(Ordering::Relaxed)
     }
 
     /// 在线程池中执行 `task` 方法。
@@ -113,22 +378,120 @@ impl ThreadPool {
     where
         F: FnOnce() + Send + 'static,
     {
-        if let Some(sender) = &self.sender {
-            sender
-                .send(Box::new(task))
-                .expect("ThreadPool::execute unable to send job into queue.");
-        }
+        self.execute_with_priority(Priority::Normal, task);
+    }
+
+    /// 以给定优先级在线程池中执行 `task`
+    pub fn execute_with_priority<F>(&self, priority: Priority, task: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.send(Box::new(task), priority);
+    }
+
+    /// 在线程池中执行 `task`，返回可以取回其返回值的句柄
+    ///
+    /// 任务中的 panic 被捕获并通过句柄返回，不会影响执行它的工作线程。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// let pool = ThreadPool::new(2);
+    /// let handle = pool.submit(|| 6 * 7);
+    /// assert_eq!(handle.join().unwrap(), 42);
+    /// ```
+    pub fn submit<F, R>(&self, task: F) -> TaskHandle<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.submit_with_priority(Priority::Normal, task)
+    }
+
+    /// 以给定优先级在线程池中执行 `task`，返回可以取回其返回值的句柄
+    pub fn submit_with_priority<F, R>(&self, priority: Priority, task: F) -> TaskHandle<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (job, handle) = task::with_handle(task);
+        self.send(job, priority);
+        handle
+    }
+
+    /// 把任务放入队列；线程池已关闭时丢弃任务
+    fn send(&self, job: Job, priority: Priority) {
+        #[cfg(feature = "tracing")]
+        let job = instrument::instrument(job);
+        // 交还的任务直接丢弃，`submit` 的句柄因此得到 `Cancelled`
+        let _ = worker::push(&self.shared, job, priority);
+    }
+
+    /// 阻塞直到队列为空且所有工作线程都空闲，之后线程池仍可继续使用
+    ///
+    /// 等待期间其他线程提交的任务也要执行完才返回。
+    ///
+    /// # Panics
+    ///
+    /// 在本线程池的任务中调用时 panic：调用它的任务本身还没有结束，等待永远不会返回。
+    pub fn join(&self) {
+        assert!(
+            !worker::is_worker_of(&self.shared),
+            "ThreadPool::join called from one of the pool's own tasks would deadlock; \
+             wait on a TaskHandle or WaitGroup instead"
+        );
+        self.shared.queue.wait_idle();
+    }
+
+    /// 至今 panic 的任务数（不含通过 [`ThreadPool::submit`] 提交的任务）
+    pub fn panic_count(&self) -> u64 {
+        self.shared.metrics.panicked()
+    }
+
+    /// 当前运行指标的快照
+    pub fn stats(&self) -> Stats {
+        self.shared.metrics.snapshot(self.shared.queue.len(), self.num_threads())
     }
 
     pub fn shutdown(&mut self) {
-        // 取出 sender 并 drop
-        self.sender.take();
+        // 关闭队列，不再接受新任务
+        self.shared.queue.close();
 
-        // 所有 worker 都会在 recv() 出错后推出循环
-        for worker in self.workers.drain(..) {
-            worker.join().unwrap();
+        // 所有 worker 都会在队列取空后退出循环；
+        // 接替 panic 线程的 worker 在前任退出前登记，逐个取出直到为空；
+        // 回调 panic 而死亡的线程在 `health` 中报告，这里不再重新抛出
+        while let Some(worker) = self.shared.take_handle() {
+            let _ = worker.join();
         }
     }
+
+    /// 关闭线程池，按本应执行的顺序返回还没有开始执行的任务
+    ///
+    /// 不等待正在执行的任务，之后调用 `shutdown` 或销毁线程池也不再等待，工作线程在任务结束后自行退出。
+    /// 返回的任务可以直接调用，
+    /// 丢弃时通过 [`ThreadPool::submit`] 提交的任务的句柄得到 [`TaskError::Cancelled`]。
+    pub fn shutdown_now(&mut self) -> Vec<Job> {
+        self.shared.queue.close();
+        let jobs = self.shared.queue.drain();
+        // 之后的 `shutdown`（包括 `Drop`）不再等待正在执行的任务
+        self.shared.detach();
+        jobs
+    }
+
+    /// 关闭线程池，最多等待 `timeout` 让已入队与正在执行的任务完成
+    ///
+    /// 按时完成时返回 `true`。超时则丢弃还没有开始执行的任务、不再等待正在执行的任务
+    /// （工作线程在它们结束后自行退出），返回 `false`。
+    pub fn shutdown_timeout(&mut self, timeout: Duration) -> bool {
+        self.shared.queue.close();
+        if self.shared.queue.wait_idle_timeout(timeout) {
+            self.shutdown();
+            return true;
+        }
+        drop(self.shared.queue.drain());
+        self.shared.detach();
+        false
+    }
 }
 
 impl Drop for ThreadPool {
@@ -139,11 +502,18 @@ impl Drop for ThreadPool {
 
 #[cfg(test)]
 mod tests {
-    use std::{thread, time::Duration};
+    use std::{
+        sync::{
+            Arc, Barrier, Mutex,
+            atomic::{AtomicUsize, Ordering},
+        },
+        thread,
+        time::{Duration, Instant},
+    };
 
     use crossbeam::channel;
 
-    use super::ThreadPool;
+    use super::{Priority, TaskError, ThreadPool};
 
     #[test]
     fn test_execute_task_in_threadpool() {
@@ -175,6 +545,290 @@ mod tests {
         assert_eq!(result, 55);
     }
 
+    #[test]
+    fn test_submit_returns_result() {
+        let thread_pool = ThreadPool::new(2);
+
+        let handles: Vec<_> = (0..8).map(|i| thread_pool.submit(move || i * i)).collect();
+        let squares: Vec<i32> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+        assert_eq!(squares, [0, 1, 4, 9, 16, 25, 36, 49]);
+
+        // panic 通过句柄返回，工作线程继续执行后续任务
+        let panicked = thread_pool.submit(|| -> i32 { panic!("boom") });
+        let err = panicked.join().unwrap_err();
+        assert_eq!(err.panic_message(), Some("boom"));
+        assert_eq!(thread_pool.submit(|| "still alive").join().unwrap(), "still alive");
+
+        // 超时后可以继续等待
+        let slow = thread_pool.submit(|| thread::sleep(Duration::from_millis(50)));
+        let slow = slow.join_timeout(Duration::from_millis(1)).unwrap_err();
+        assert!(slow.join_timeout(Duration::from_secs(5)).unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_submit_after_shutdown_is_cancelled() {
+        let mut thread_pool = ThreadPool::new(1);
+        thread_pool.shutdown();
+
+        let handle = thread_pool.submit(|| 1);
+        assert!(matches!(handle.join(), Err(TaskError::Cancelled)));
+    }
+
+    #[test]
+    fn test_priority() {
+        let thread_pool = ThreadPool::new(1);
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        // 先占住唯一的工作线程，使后续任务都在队列中等待
+        let (release, gate) = channel::bounded::<()>(0);
+        thread_pool.execute(move || gate.recv().unwrap());
+        let handles: Vec<_> = [Priority::Low, Priority::Normal, Priority::High]
+            .into_iter()
+            .map(|priority| {
+                let order = order.clone();
+                thread_pool.submit_with_priority(priority, move || {
+                    order.lock().unwrap().push(priority);
+                })
+            })
+            .collect();
+        release.send(()).unwrap();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), [Priority::High, Priority::Normal, Priority::Low]);
+    }
+
+    #[test]
+    fn test_panicking_task_keeps_capacity() {
+        let handled = Arc::new(AtomicUsize::new(0));
+        let counter = handled.clone();
+        let thread_pool = ThreadPool::builder()
+            .num_threads(2)
+            .on_task_panic(move |payload| {
+                assert_eq!(payload.downcast_ref::<&str>(), Some(&"boom"));
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .build();
+
+        for _ in 0..4 {
+            thread_pool.execute(|| panic!("boom"));
+        }
+
+        // 两个任务互相等待，只有两个工作线程都还在时才能完成
+        let barrier = Arc::new(Barrier::new(2));
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let barrier = barrier.clone();
+                thread_pool.submit(move || {
+                    barrier.wait();
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.join_timeout(Duration::from_secs(5)).unwrap().is_ok());
+        }
+        assert_eq!(thread_pool.panic_count(), 4);
+        assert_eq!(handled.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_join_keeps_pool_usable() {
+        let thread_pool = ThreadPool::new(4);
+        let count = Arc::new(AtomicUsize::new(0));
+
+        for round in 1..=3 {
+            for _ in 0..16 {
+                let count = count.clone();
+                thread_pool.execute(move || {
+                    thread::sleep(Duration::from_millis(1));
+                    count.fetch_add(1, Ordering::SeqCst);
+                });
+            }
+            thread_pool.execute(|| panic!("boom"));
+            thread_pool.join();
+            assert_eq!(count.load(Ordering::SeqCst), round * 16);
+            let stats = thread_pool.stats();
+            assert_eq!((stats.queued, stats.active), (0, 0));
+        }
+
+        // 没有任务时立即返回
+        thread_pool.join();
+    }
+
+    #[test]
+    fn test_elastic_threads() {
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let on_stop = stopped.clone();
+        let thread_pool = ThreadPool::builder()
+            .core_threads(1)
+            .max_threads(4)
+            .keep_alive(Duration::from_millis(20))
+            .on_thread_stop(move || {
+                on_stop.fetch_add(1, Ordering::SeqCst);
+            })
+            .build();
+        assert_eq!(thread_pool.num_threads(), 1);
+
+        // 四个任务互相等待，只有线程数增长到上限才能完成
+        let barrier = Arc::new(Barrier::new(4));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let barrier = barrier.clone();
+                thread_pool.submit(move || {
+                    barrier.wait();
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.join_timeout(Duration::from_secs(5)).unwrap().is_ok());
+        }
+        assert_eq!(thread_pool.num_threads(), 4);
+
+        // 额外的线程空闲后退出，只保留核心线程
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while thread_pool.num_threads() > 1 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(thread_pool.stats().threads, 1);
+        assert_eq!(thread_pool.submit(|| 1).join().unwrap(), 1);
+        // 退出回调在减少线程数之后调用，等待它们完成
+        while stopped.load(Ordering::SeqCst) < 3 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(stopped.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_thread_hooks() {
+        use std::cell::Cell;
+
+        thread_local! {
+            static INITIALIZED: Cell<bool> = const { Cell::new(false) };
+        }
+
+        let started = Arc::new(AtomicUsize::new(0));
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let (on_start, on_stop) = (started.clone(), stopped.clone());
+        let mut thread_pool = ThreadPool::builder()
+            .num_threads(2)
+            .on_thread_start(move || {
+                INITIALIZED.set(true);
+                on_start.fetch_add(1, Ordering::SeqCst);
+            })
+            .on_thread_stop(move || {
+                on_stop.fetch_add(1, Ordering::SeqCst);
+            })
+            .build();
+
+        // 任务能看到启动回调初始化的线程局部变量
+        assert!(thread_pool.submit(|| INITIALIZED.get()).join().unwrap());
+
+        // panic 的线程退出，接替的线程启动
+        thread_pool.execute(|| panic!("boom"));
+        thread_pool.join();
+        thread_pool.shutdown();
+        assert_eq!(started.load(Ordering::SeqCst), 3);
+        assert_eq!(stopped.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut thread_pool = ThreadPool::new(1);
+        let (release, gate) = channel::bounded::<()>(0);
+        let (started_tx, started) = channel::bounded::<()>(0);
+        thread_pool.execute(move || {
+            started_tx.send(()).unwrap();
+            gate.recv().unwrap();
+        });
+        started.recv().unwrap();
+
+        let handles: Vec<_> = (0..3).map(|i| thread_pool.submit(move || i)).collect();
+        thread_pool.execute(|| panic!("boom"));
+        let stats = thread_pool.stats();
+        assert_eq!((stats.queued, stats.active), (4, 1));
+
+        release.send(()).unwrap();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        // 关闭线程池，等待全部任务执行完毕、计数器更新
+        thread_pool.shutdown();
+
+        let stats = thread_pool.stats();
+        assert_eq!((stats.queued, stats.active), (0, 0));
+        assert_eq!((stats.completed, stats.panicked), (4, 1));
+        assert_eq!(stats.queue_wait.count(), 5);
+    }
+
+    #[test]
+    fn test_shutdown_now() {
+        let mut thread_pool = ThreadPool::new(1);
+        let (release, gate) = channel::bounded::<()>(0);
+        let (started_tx, started) = channel::bounded::<()>(0);
+        thread_pool.execute(move || {
+            started_tx.send(()).unwrap();
+            gate.recv().unwrap();
+        });
+        started.recv().unwrap();
+
+        let first = thread_pool.submit(|| 1);
+        let second = thread_pool.submit(|| 2);
+        let mut queued = thread_pool.shutdown_now();
+        assert_eq!(queued.len(), 2);
+        assert_eq!(thread_pool.stats().queued, 0);
+
+        // 返回的任务可以由调用方执行，丢弃的任务被取消
+        queued.remove(0)();
+        drop(queued);
+        assert_eq!(first.join().unwrap(), 1);
+        assert!(matches!(second.join(), Err(TaskError::Cancelled)));
+
+        release.send(()).unwrap();
+        thread_pool.shutdown();
+    }
+
+    #[test]
+    fn test_drop_after_shutdown_now() {
+        let mut thread_pool = ThreadPool::new(1);
+        let (release, gate) = channel::bounded::<()>(0);
+        let (started_tx, started) = channel::bounded::<()>(0);
+        thread_pool.execute(move || {
+            started_tx.send(()).unwrap();
+            gate.recv().unwrap();
+        });
+        started.recv().unwrap();
+
+        // 任务 1 秒后才结束，销毁线程池不等待它
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_secs(1));
+            release.send(()).unwrap();
+        });
+        drop(thread_pool.shutdown_now());
+        let start = Instant::now();
+        drop(thread_pool);
+        assert!(start.elapsed() < Duration::from_millis(500));
+        releaser.join().unwrap();
+    }
+
+    #[test]
+    fn test_shutdown_timeout() {
+        let mut thread_pool = ThreadPool::new(2);
+        for _ in 0..4 {
+            thread_pool.execute(|| thread::sleep(Duration::from_millis(5)));
+        }
+        assert!(thread_pool.shutdown_timeout(Duration::from_secs(5)));
+
+        // 超时后丢弃排队的任务，不再等待卡住的任务
+        let mut thread_pool = ThreadPool::new(1);
+        let (release, gate) = channel::bounded::<()>(0);
+        thread_pool.execute(move || gate.recv().unwrap());
+        let queued = thread_pool.submit(|| 1);
+        assert!(!thread_pool.shutdown_timeout(Duration::from_millis(20)));
+        assert!(matches!(queued.join(), Err(TaskError::Cancelled)));
+        drop(thread_pool);
+        release.send(()).unwrap();
+    }
+
     #[test]
     fn test_shutdown_threadpool() {
         let mut thread_pool = ThreadPool::new(4);
@@ -0,0 +1,298 @@
+//! 在线程池中运行异步任务
+//!
+//! [`ThreadPool::spawn_future`] 把 `Future` 包装为一个可被唤醒的任务（[`FutureTask`]）：
+//! 任务被唤醒时作为普通的 [`Job`] 放入队列，由某个工作线程 poll 一次；返回 `Pending` 后不占用线程，
+//! 直到 `Waker` 再次唤醒。因此 CPU 密集的任务与异步任务可以共用同一个线程池。
+//!
+//! 与 `concurrency_tests` 中手写的 `run_future` 不同（它用一个什么都不做的 waker 不停地 poll），
+//! 这里的 `Waker` 真正负责重新调度：
+//!
+//! * `scheduled` 标记保证同一时刻队列中最多只有一个该任务的 poll，多次唤醒只调度一次
+//! * poll 前清除标记，poll 期间的唤醒会再调度一次，不会丢失
+//! * 线程池已关闭时唤醒不再调度，任务随之销毁，等待方得到 [`TaskError::Cancelled`]
+//!
+//! 任务的结果通过 [`JoinFuture`] 取回，它本身也是一个 `Future`，可以在其他异步任务中 `.await`，
+//! 也可以在普通线程上用 [`block_on`] 等待。
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{
+        Arc, Mutex, Weak,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+use super::{Job, Priority, TaskError, ThreadPool, worker, worker::Shared};
+
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// 可被唤醒的异步任务
+struct FutureTask {
+    /// 还没有完成的 future，完成或取消后为 `None`
+    future: Mutex<Option<BoxFuture>>,
+    /// 是否已经在队列中等待 poll
+    scheduled: AtomicBool,
+    /// 不让挂起的任务阻止线程池销毁
+    pool: Weak<Shared>,
+}
+
+impl FutureTask {
+    /// 放入队列等待 poll；已经在队列中时什么也不做
+    fn schedule(self: Arc<Self>) {
+        if self.scheduled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let Some(shared) = self.pool.upgrade() else {
+            self.cancel();
+            return;
+        };
+        let task = self.clone();
+        let job: Job = Box::new(move || task.poll());
+        if worker::push(&shared, job, Priority::Normal).is_err() {
+            self.cancel();
+        }
+    }
+
+    fn poll(self: Arc<Self>) {
+        self.scheduled.store(false, Ordering::Release);
+        let mut slot = self.future.lock().unwrap();
+        let Some(future) = slot.as_mut() else {
+            return;
+        };
+        let waker = Waker::from(self.clone());
+        if future.as_mut().poll(&mut Context::from_waker(&waker)).is_ready() {
+            *slot = None;
+        }
+    }
+
+    /// 线程池已关闭，销毁 future
+    fn cancel(&self) {
+        // 正在被 poll 时锁被占用，poll 结束后线程池的关闭同样会让任务被销毁
+        if let Ok(mut slot) = self.future.try_lock() {
+            slot.take();
+        }
+    }
+}
+
+impl Wake for FutureTask {
+    fn wake(self: Arc<Self>) {
+        self.schedule();
+    }
+}
+
+/// 异步任务结果的交接状态
+enum Slot<T> {
+    /// 还没有结果，保存等待方最近一次 poll 的 waker
+    Pending(Option<Waker>),
+    Ready(Result<T, TaskError>),
+    /// 结果已被取走
+    Taken,
+}
+
+/// 任务一侧：写入结果；没有写入就被销毁（任务被取消）时写入 [`TaskError::Cancelled`]
+struct Completion<T> {
+    slot: Arc<Mutex<Slot<T>>>,
+}
+
+impl<T> Completion<T> {
+    fn complete(&self, result: Result<T, TaskError>) {
+        let mut slot = self.slot.lock().unwrap();
+        if let Slot::Pending(waker) = std::mem::replace(&mut *slot, Slot::Ready(result)) {
+            waker.into_iter().for_each(Waker::wake);
+        }
+    }
+}
+
+impl<T> Drop for Completion<T> {
+    fn drop(&mut self) {
+        if matches!(*self.slot.lock().unwrap(), Slot::Pending(_)) {
+            self.complete(Err(TaskError::Cancelled));
+        }
+    }
+}
+
+/// [`ThreadPool::spawn_future`] 返回的句柄，完成时得到任务的输出或 panic 的载荷
+///
+/// 丢弃句柄不会取消任务。
+#[must_use = "丢弃句柄后无法再取回任务的结果"]
+pub struct JoinFuture<T> {
+    slot: Arc<Mutex<Slot<T>>>,
+}
+
+impl<T> Future for JoinFuture<T> {
+    type Output = Result<T, TaskError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.lock().unwrap();
+        match std::mem::replace(&mut *slot, Slot::Taken) {
+            Slot::Ready(result) => Poll::Ready(result),
+            Slot::Pending(_) => {
+                *slot = Slot::Pending(Some(cx.waker().clone()));
+                Poll::Pending
+            }
+            Slot::Taken => panic!("JoinFuture polled after completion"),
+        }
+    }
+}
+
+/// 捕获内部 future 在 poll 时的 panic
+struct CatchUnwind<F> {
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = thread::Result<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match panic::catch_unwind(AssertUnwindSafe(|| self.future.as_mut().poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl ThreadPool {
+    /// 在线程池中运行 `future`，返回可以 `.await` 或用 [`block_on`] 等待的句柄
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// let pool = ThreadPool::new(2);
+    /// let handle = pool.spawn_future(async { 6 * 7 });
+    /// assert_eq!(block_on(handle).unwrap(), 42);
+    /// ```
+    pub fn spawn_future<F>(&self, future: F) -> JoinFuture<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        #[cfg(feature = "tracing")]
+        let future =
+            tracing::Instrument::instrument(future, tracing::info_span!("threadpool.future"));
+
+        let slot = Arc::new(Mutex::new(Slot::Pending(None)));
+        let completion = Completion { slot: slot.clone() };
+        let wrapped = async move {
+            let result = CatchUnwind { future: Box::pin(future) }.await;
+            completion.complete(result.map_err(TaskError::Panicked));
+        };
+
+        let task = Arc::new(FutureTask {
+            future: Mutex::new(Some(Box::pin(wrapped))),
+            scheduled: AtomicBool::new(false),
+            pool: Arc::downgrade(&self.shared),
+        });
+        task.schedule();
+        JoinFuture { slot }
+    }
+}
+
+/// 唤醒时 unpark 等待的线程
+#[allow(dead_code)]
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// 在当前线程上运行 `future` 直到完成，`Pending` 时挂起线程等待唤醒
+///
+/// 不要在线程池的任务中等待同一线程池中的异步任务，否则可能占满工作线程而互相等待。
+#[allow(dead_code)]
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crossbeam::channel;
+
+    use super::*;
+
+    /// 在另一个线程上等待一段时间后完成的 future
+    struct Sleep {
+        state: Arc<Mutex<(bool, Option<Waker>)>>,
+    }
+
+    impl Sleep {
+        fn new(duration: Duration) -> Self {
+            let state = Arc::new(Mutex::new((false, None::<Waker>)));
+            let timer = state.clone();
+            thread::spawn(move || {
+                thread::sleep(duration);
+                let mut state = timer.lock().unwrap();
+                state.0 = true;
+                if let Some(waker) = state.1.take() {
+                    waker.wake();
+                }
+            });
+            Self { state }
+        }
+    }
+
+    impl Future for Sleep {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            let mut state = self.state.lock().unwrap();
+            if state.0 {
+                return Poll::Ready(());
+            }
+            state.1 = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn test_spawn_future() {
+        let pool = ThreadPool::new(1);
+        assert_eq!(block_on(pool.spawn_future(async { 6 * 7 })).unwrap(), 42);
+
+        // 挂起的异步任务不占用工作线程：唯一的线程在等待期间执行了普通任务
+        let (tx, rx) = channel::unbounded();
+        let sleeping = pool.spawn_future(async move {
+            Sleep::new(Duration::from_millis(50)).await;
+            rx.try_recv().is_ok()
+        });
+        pool.execute(move || tx.send(()).unwrap());
+        assert!(block_on(sleeping).unwrap());
+
+        // 异步任务可以等待其他异步任务
+        let inner = pool.spawn_future(async { "inner" });
+        let outer = pool.spawn_future(async move { inner.await.unwrap().len() });
+        assert_eq!(block_on(outer).unwrap(), 5);
+    }
+
+    #[test]
+    fn test_spawn_future_panic_and_cancel() {
+        let mut pool = ThreadPool::new(1);
+        let panicked = pool.spawn_future(async { panic!("async boom") });
+        let err = block_on(panicked).unwrap_err();
+        assert_eq!(err.panic_message(), Some("async boom"));
+
+        // 线程池关闭后，挂起的任务被唤醒时取消
+        let pending = pool.spawn_future(async {
+            Sleep::new(Duration::from_millis(50)).await;
+        });
+        pool.shutdown();
+        assert!(matches!(block_on(pending), Err(TaskError::Cancelled)));
+        assert!(matches!(block_on(pool.spawn_future(async {})), Err(TaskError::Cancelled)));
+    }
+}
@@ -0,0 +1,76 @@
+//! 进程级的默认线程池
+//!
+//! [`ThreadPool::global`] 在第一次使用时按默认配置创建线程池（线程数为可用的并行度），之后一直存在；
+//! 需要其他配置时在启动阶段、第一次使用之前调用 [`Builder::build_global`]，只能成功一次。
+//! [`spawn`] 与 [`join`] 是使用默认线程池的便捷函数，小工具不必各自创建线程池。
+
+use std::{fmt, sync::OnceLock};
+
+use super::{Builder, TaskHandle, ThreadPool};
+
+static GLOBAL: OnceLock<ThreadPool> = OnceLock::new();
+
+/// 默认线程池已经创建，无法再配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlobalPoolAlreadyInitialized;
+
+impl fmt::Display for GlobalPoolAlreadyInitialized {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the global thread pool has already been initialized")
+    }
+}
+
+impl std::error::Error for GlobalPoolAlreadyInitialized {}
+
+#[allow(dead_code)]
+impl Builder {
+    /// 以当前配置创建默认线程池；默认线程池已经创建（包括已经被使用过）时返回错误
+    pub fn build_global(self) -> Result<(), GlobalPoolAlreadyInitialized> {
+        if GLOBAL.get().is_some() {
+            return Err(GlobalPoolAlreadyInitialized);
+        }
+        // 并发初始化时输的一方创建的线程池在这里销毁
+        GLOBAL.set(self.build()).map_err(|_| GlobalPoolAlreadyInitialized)
+    }
+}
+
+#[allow(dead_code)]
+impl ThreadPool {
+    /// 进程级的默认线程池，第一次调用时按默认配置创建
+    pub fn global() -> &'static ThreadPool {
+        GLOBAL.get_or_init(|| ThreadPool::builder().build())
+    }
+}
+
+/// 在默认线程池中执行 `task`，返回可以取回其返回值的句柄
+#[allow(dead_code)]
+pub fn spawn<F, R>(task: F) -> TaskHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    ThreadPool::global().submit(task)
+}
+
+/// 等待默认线程池中的任务全部执行完（包括其他调用方提交的任务）
+#[allow(dead_code)]
+pub fn join() {
+    ThreadPool::global().join();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_global_pool() {
+        let handles: Vec<_> = (0..4).map(|i| spawn(move || i * 10)).collect();
+        let results: Vec<_> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+        assert_eq!(results, [0, 10, 20, 30]);
+        join();
+
+        assert!(std::ptr::eq(ThreadPool::global(), ThreadPool::global()));
+        // 已经使用过，无法再配置
+        assert_eq!(ThreadPool::builder().build_global(), Err(GlobalPoolAlreadyInitialized));
+    }
+}
@@ -0,0 +1,329 @@
+//! 健康检查与看门狗
+//!
+//! 每个工作线程在 [`Monitor`] 中登记一个 [`Heartbeat`]，开始与结束任务时记录心跳，
+//! 执行任务期间记录任务开始的时间。[`ThreadPool::health`] 据此报告每个工作线程距上次心跳的时长、
+//! 正在执行的任务已经执行了多久，以及是否超过 [`Builder::task_budget`] 设置的执行时长预算。
+//!
+//! 任务的 panic 不会让工作线程死掉（见 `worker` 模块），但 `on_thread_start` 等回调 panic 时，
+//! 线程会在没有接替者的情况下退出。它的登记保留下来并标记为死亡，提示线程池的容量已经变小。
+//!
+//! 设置 [`Builder::watchdog`] 后，看门狗线程定期检查，把超出预算的任务与死亡的工作线程输出到标准错误，
+//! 每个任务只报告一次。Linux 上同时附上该线程的调度状态与内核等待点（`/proc/self/task/<tid>/wchan`），
+//! 可以大致看出它是在计算还是阻塞在锁或 I/O 上。
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{ThreadPool, worker::Shared};
+
+/// [`Heartbeat::started`] 表示空闲的取值
+const IDLE: u64 = u64::MAX;
+
+/// 一个工作线程的心跳，时间均为相对于 [`Monitor`] 创建时刻的微秒数
+pub(super) struct Heartbeat {
+    /// 最近一次开始或结束任务的时间
+    last_beat: AtomicU64,
+    /// 正在执行的任务开始的时间，空闲时为 [`IDLE`]
+    started: AtomicU64,
+    /// 线程在没有接替者的情况下因 panic 退出
+    dead: AtomicBool,
+    /// 线程在 `/proc` 中的编号
+    tid: Option<u32>,
+}
+
+/// 全部工作线程的心跳
+pub(super) struct Monitor {
+    epoch: Instant,
+    task_budget: Option<Duration>,
+    /// 按工作线程编号登记，接替的线程覆盖前任的登记
+    workers: Mutex<BTreeMap<usize, Arc<Heartbeat>>>,
+}
+
+impl Monitor {
+    pub(super) fn new(task_budget: Option<Duration>) -> Self {
+        Self { epoch: Instant::now(), task_budget, workers: Mutex::default() }
+    }
+
+    fn now(&self) -> u64 {
+        u64::try_from(self.epoch.elapsed().as_micros()).unwrap_or(u64::MAX - 1)
+    }
+
+    /// 在工作线程上调用，登记编号为 `id` 的线程，返回的登记随线程退出而销毁
+    pub(super) fn register(&self, id: usize) -> Registration<'_> {
+        let heartbeat = Arc::new(Heartbeat {
+            last_beat: AtomicU64::new(self.now()),
+            started: AtomicU64::new(IDLE),
+            dead: AtomicBool::new(false),
+            tid: current_tid(),
+        });
+        self.workers.lock().unwrap().insert(id, heartbeat.clone());
+        Registration { monitor: self, id, heartbeat }
+    }
+
+    /// 开始执行任务，返回外层任务开始的时间（等待期间就地执行任务时），结束时交给 [`Monitor::end`]
+    pub(super) fn begin(&self, heartbeat: &Heartbeat) -> u64 {
+        let now = self.now();
+        heartbeat.last_beat.store(now, Ordering::Relaxed);
+        heartbeat.started.swap(now, Ordering::Relaxed)
+    }
+
+    /// 任务执行结束，恢复外层任务开始的时间
+    pub(super) fn end(&self, heartbeat: &Heartbeat, outer: u64) {
+        heartbeat.last_beat.store(self.now(), Ordering::Relaxed);
+        heartbeat.started.store(outer, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> Vec<(usize, Arc<Heartbeat>)> {
+        let workers = self.workers.lock().unwrap();
+        workers.iter().map(|(&id, heartbeat)| (id, heartbeat.clone())).collect()
+    }
+
+    fn health(&self) -> Health {
+        let now = self.now();
+        let workers = self
+            .snapshot()
+            .into_iter()
+            .map(|(id, heartbeat)| {
+                let micros = |since: u64| Duration::from_micros(now.saturating_sub(since));
+                let started = heartbeat.started.load(Ordering::Relaxed);
+                let running_for = (started != IDLE).then(|| micros(started));
+                WorkerHealth {
+                    id,
+                    alive: !heartbeat.dead.load(Ordering::Relaxed),
+                    since_heartbeat: micros(heartbeat.last_beat.load(Ordering::Relaxed)),
+                    running_for,
+                    over_budget: self.over_budget(running_for),
+                }
+            })
+            .collect();
+        Health { workers, task_budget: self.task_budget }
+    }
+
+    fn over_budget(&self, running_for: Option<Duration>) -> bool {
+        self.task_budget.zip(running_for).is_some_and(|(budget, running)| running > budget)
+    }
+
+    /// 看门狗的一次检查，返回还没有报告过的问题；`reported` 记录已经报告过的（线程编号, 任务开始时间）
+    fn watch(&self, reported: &mut HashSet<(usize, u64)>) -> Vec<String> {
+        let now = self.now();
+        let workers = self.snapshot();
+        // 只保留仍然存在的问题，集合不会无限增长
+        reported.retain(|&(id, started)| {
+            workers.iter().any(|(worker, heartbeat)| {
+                *worker == id && heartbeat.started.load(Ordering::Relaxed) == started
+            })
+        });
+
+        let mut reports = Vec::new();
+        for (id, heartbeat) in workers {
+            let started = heartbeat.started.load(Ordering::Relaxed);
+            let hint = heartbeat.tid.and_then(stack_hint).map(|hint| format!(" ({hint})"));
+            let hint = hint.unwrap_or_default();
+            if heartbeat.dead.load(Ordering::Relaxed) {
+                if reported.insert((id, started)) {
+                    reports.push(format!("threadpool-worker-{id} died without a replacement"));
+                }
+            } else if started != IDLE {
+                let running = Duration::from_micros(now.saturating_sub(started));
+                if self.over_budget(Some(running)) && reported.insert((id, started)) {
+                    reports.push(format!(
+                        "threadpool-worker-{id} has been running a task for {running:?}{hint}"
+                    ));
+                }
+            }
+        }
+        reports
+    }
+}
+
+/// 工作线程的登记；线程正常退出时注销，因 panic 退出时标记为死亡
+pub(super) struct Registration<'a> {
+    monitor: &'a Monitor,
+    id: usize,
+    pub(super) heartbeat: Arc<Heartbeat>,
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        let mut workers = self.monitor.workers.lock().unwrap_or_else(|e| e.into_inner());
+        // 接替的线程已经覆盖了登记时什么也不做
+        if workers.get(&self.id).is_some_and(|heartbeat| Arc::ptr_eq(heartbeat, &self.heartbeat)) {
+            if thread::panicking() {
+                self.heartbeat.dead.store(true, Ordering::Relaxed);
+            } else {
+                workers.remove(&self.id);
+            }
+        }
+    }
+}
+
+/// 启动看门狗线程，每隔 `interval` 检查一次，线程池销毁后退出
+pub(super) fn spawn_watchdog(shared: &Arc<Shared>, interval: Duration) {
+    let pool = Arc::downgrade(shared);
+    thread::Builder::new()
+        .name("threadpool-watchdog".to_string())
+        .spawn(move || {
+            let mut reported = HashSet::new();
+            loop {
+                thread::sleep(interval);
+                let Some(shared) = pool.upgrade() else {
+                    break;
+                };
+                for report in shared.monitor.watch(&mut reported) {
+                    eprintln!("{report}");
+                }
+            }
+        })
+        .expect("ThreadPool unable to spawn watchdog thread.");
+}
+
+/// 当前线程在 `/proc` 中的编号
+#[cfg(target_os = "linux")]
+fn current_tid() -> Option<u32> {
+    // `/proc/thread-self` 指向 `<pid>/task/<tid>`
+    let path = std::fs::read_link("/proc/thread-self").ok()?;
+    path.file_name()?.to_str()?.parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn current_tid() -> Option<u32> {
+    None
+}
+
+/// 线程的调度状态与内核等待点，例如 `state S, waiting in futex_wait_queue`
+fn stack_hint(tid: u32) -> Option<String> {
+    let task = format!("/proc/self/task/{tid}");
+    let stat = std::fs::read_to_string(format!("{task}/stat")).ok()?;
+    // 状态紧跟在括号括起的线程名之后，线程名本身可能包含空格与括号
+    let state = stat.rsplit_once(')')?.1.split_whitespace().next()?;
+    let wchan = std::fs::read_to_string(format!("{task}/wchan")).unwrap_or_default();
+    Some(match wchan.trim() {
+        "" | "0" => format!("state {state}"),
+        wchan => format!("state {state}, waiting in {wchan}"),
+    })
+}
+
+/// [`ThreadPool::health`] 返回的健康状况
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+pub struct Health {
+    /// 按编号排列的工作线程
+    pub workers: Vec<WorkerHealth>,
+    /// 任务的执行时长预算，见 [`Builder::task_budget`](super::Builder::task_budget)
+    pub task_budget: Option<Duration>,
+}
+
+#[allow(dead_code)]
+impl Health {
+    /// 没有死亡的工作线程，也没有超出预算的任务
+    pub fn is_healthy(&self) -> bool {
+        self.workers.iter().all(|worker| worker.alive && !worker.over_budget)
+    }
+
+    /// 在没有接替者的情况下退出的工作线程
+    pub fn dead_workers(&self) -> impl Iterator<Item = &WorkerHealth> {
+        self.workers.iter().filter(|worker| !worker.alive)
+    }
+
+    /// 正在执行的任务超出预算的工作线程
+    pub fn over_budget(&self) -> impl Iterator<Item = &WorkerHealth> {
+        self.workers.iter().filter(|worker| worker.over_budget)
+    }
+}
+
+/// 一个工作线程的健康状况
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+pub struct WorkerHealth {
+    /// 工作线程的编号，与线程名 `threadpool-worker-<id>` 一致
+    pub id: usize,
+    pub alive: bool,
+    /// 距离最近一次开始或结束任务的时长
+    pub since_heartbeat: Duration,
+    /// 正在执行的任务已经执行的时长，空闲时为 `None`
+    pub running_for: Option<Duration>,
+    /// `running_for` 超过了执行时长预算
+    pub over_budget: bool,
+}
+
+#[allow(dead_code)]
+impl ThreadPool {
+    /// 各工作线程的心跳、正在执行的任务与死亡的线程
+    pub fn health(&self) -> Health {
+        self.shared.monitor.health()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossbeam::channel;
+
+    use super::*;
+
+    #[test]
+    fn test_task_budget() {
+        let pool = ThreadPool::builder()
+            .num_threads(2)
+            .task_budget(Duration::from_millis(20))
+            .watchdog(Duration::from_millis(5))
+            .build();
+        let (release, gate) = channel::bounded::<()>(0);
+        let (started_tx, started) = channel::bounded::<()>(0);
+        pool.execute(move || {
+            started_tx.send(()).unwrap();
+            gate.recv().unwrap();
+        });
+        started.recv().unwrap();
+        thread::sleep(Duration::from_millis(40));
+
+        let health = pool.health();
+        assert_eq!(health.workers.len(), 2);
+        assert!(!health.is_healthy());
+        let stuck: Vec<_> = health.over_budget().collect();
+        assert_eq!(stuck.len(), 1);
+        assert!(stuck[0].running_for.unwrap() >= Duration::from_millis(40));
+
+        // 每个任务只报告一次
+        let mut reported = HashSet::new();
+        let reports = pool.shared.monitor.watch(&mut reported);
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].starts_with(&format!("threadpool-worker-{}", stuck[0].id)));
+        assert!(pool.shared.monitor.watch(&mut reported).is_empty());
+
+        release.send(()).unwrap();
+        pool.join();
+        let health = pool.health();
+        assert!(health.is_healthy());
+        assert!(health.workers.iter().all(|worker| worker.running_for.is_none()));
+    }
+
+    #[test]
+    fn test_dead_worker() {
+        let first = AtomicBool::new(true);
+        let pool = ThreadPool::builder()
+            .num_threads(2)
+            .on_thread_start(move || {
+                if first.swap(false, Ordering::SeqCst) {
+                    panic!("start hook failed");
+                }
+            })
+            .build();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while pool.health().dead_workers().count() == 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        let health = pool.health();
+        assert_eq!(health.dead_workers().count(), 1);
+        assert!(!health.is_healthy());
+        // 剩下的线程仍然可以执行任务
+        assert_eq!(pool.submit(|| 1).join().unwrap(), 1);
+    }
+}
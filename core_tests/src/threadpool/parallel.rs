@@ -0,0 +1,266 @@
+//! 数据并行的辅助方法
+//!
+//! [`ThreadPool::map`]、[`ThreadPool::try_map`] 与 [`ThreadPool::for_each_chunked`] 把输入切分为若干块，
+//! 每块作为一个任务提交，等待全部任务结束后按输入顺序拼接结果，调用方不必自己处理通道与句柄。
+//!
+//! 这些方法都阻塞到全部任务结束才返回，因此任务可以借用调用方的数据（闭包与切片不要求 `'static`）。
+//! 任务 panic 时同样先等其余任务结束，再在调用线程上重新抛出第一个 panic。
+//! 在线程池的任务中调用时，等待期间工作线程会执行队列中的任务（见 `worker` 模块），嵌套使用不会死锁；
+//! [`ThreadPool::install`] 把一段代码放到线程池中运行，其中的并行调用都以这种方式执行。
+
+use std::{
+    mem, panic,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use super::{Job, Priority, TaskError, ThreadPool, task, worker};
+
+/// 每个工作线程平均分到的块数，多于 1 块以便快慢不均时互相补位
+const CHUNKS_PER_THREAD: usize = 4;
+
+type ScopedTask<'a, R> = Box<dyn FnOnce() -> R + Send + 'a>;
+
+#[allow(dead_code)]
+impl ThreadPool {
+    /// 在线程池的一个工作线程上运行 `f` 并返回它的结果，`f` 中的 panic 在调用线程上重新抛出
+    ///
+    /// 已经在本线程池的工作线程上时直接调用 `f`。`f` 中的 `map` 等并行调用在等待时就地执行任务，
+    /// 即使只有一个工作线程也不会死锁。
+    ///
+    /// ```
+    /// let pool = ThreadPool::new(1);
+    /// let sum: i32 = pool.install(|| pool.map(1..=4, |i| i * 2).into_iter().sum());
+    /// assert_eq!(sum, 20);
+    /// ```
+    pub fn install<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R + Send,
+        R: Send,
+    {
+        if worker::is_worker_of(&self.shared) {
+            return f();
+        }
+        let task: ScopedTask<'_, R> = Box::new(f);
+        self.run_scoped(vec![task]).pop().expect("one task yields one result")
+    }
+
+    /// 并行地对每个元素调用 `f`，按输入顺序返回结果
+    ///
+    /// ```
+    /// let pool = ThreadPool::new(4);
+    /// assert_eq!(pool.map(1..=4, |i| i * i), [1, 4, 9, 16]);
+    /// ```
+    pub fn map<I, F, R>(&self, items: I, f: F) -> Vec<R>
+    where
+        I: IntoIterator,
+        I::Item: Send,
+        F: Fn(I::Item) -> R + Sync,
+        R: Send,
+    {
+        let chunks = self.map_chunks(items, |chunk| chunk.into_iter().map(&f).collect::<Vec<_>>());
+        chunks.into_iter().flatten().collect()
+    }
+
+    /// 并行地对每个元素调用可能失败的 `f`，全部成功时按输入顺序返回结果，否则返回一个错误
+    ///
+    /// 与 `collect::<Result<Vec<_>, _>>()` 类似，出错后其余任务不再对剩下的元素调用 `f`；
+    /// 但多个元素并行执行，返回的是实际执行过的元素中位置最靠前的错误，不一定是输入中的第一个错误。
+    ///
+    /// ```
+    /// let pool = ThreadPool::new(4);
+    /// assert_eq!(pool.try_map(["1", "2"], str::parse::<i32>), Ok(vec![1, 2]));
+    /// assert!(pool.try_map(["1", "x"], str::parse::<i32>).is_err());
+    /// ```
+    pub fn try_map<I, F, R, E>(&self, items: I, f: F) -> Result<Vec<R>, E>
+    where
+        I: IntoIterator,
+        I::Item: Send,
+        F: Fn(I::Item) -> Result<R, E> + Sync,
+        R: Send,
+        E: Send,
+    {
+        let failed = AtomicBool::new(false);
+        // 块的结果：`Err(None)` 表示因其他块出错而放弃
+        let chunks = self.map_chunks(items, |chunk| -> Result<Vec<R>, Option<E>> {
+            let mut values = Vec::with_capacity(chunk.len());
+            for item in chunk {
+                if failed.load(Ordering::Relaxed) {
+                    return Err(None);
+                }
+                match f(item) {
+                    Ok(value) => values.push(value),
+                    Err(e) => {
+                        failed.store(true, Ordering::Relaxed);
+                        return Err(Some(e));
+                    }
+                }
+            }
+            Ok(values)
+        });
+
+        let mut values = Vec::new();
+        for chunk in chunks {
+            match chunk {
+                Ok(chunk) => values.extend(chunk),
+                Err(Some(e)) => return Err(e),
+                Err(None) => {}
+            }
+        }
+        Ok(values)
+    }
+
+    /// 把 `slice` 切分为长度为 `chunk_size` 的块（最后一块可能较短），并行地对每块调用 `f`
+    ///
+    /// # Panics
+    ///
+    /// `chunk_size` 为 0 时 panic。
+    pub fn for_each_chunked<T, F>(&self, slice: &[T], chunk_size: usize, f: F)
+    where
+        T: Sync,
+        F: Fn(&[T]) + Sync,
+    {
+        assert!(chunk_size > 0, "chunk_size must be greater than zero");
+        let f = &f;
+        let tasks = slice
+            .chunks(chunk_size)
+            .map(|chunk| -> ScopedTask<'_, ()> { Box::new(move || f(chunk)) })
+            .collect();
+        self.run_scoped(tasks);
+    }
+
+    /// 把 `items` 按顺序切块，并行地对每块调用 `f`，按顺序返回各块的结果
+    fn map_chunks<I, F, R>(&self, items: I, f: F) -> Vec<R>
+    where
+        I: IntoIterator,
+        I::Item: Send,
+        F: Fn(Vec<I::Item>) -> R + Sync,
+        R: Send,
+    {
+        let items: Vec<_> = items.into_iter().collect();
+        let chunk_size =
+            items.len().div_ceil(self.shared.sizing.max_threads * CHUNKS_PER_THREAD).max(1);
+        let f = &f;
+
+        let mut items = items.into_iter();
+        let tasks = std::iter::from_fn(|| {
+            let chunk: Vec<_> = items.by_ref().take(chunk_size).collect();
+            (!chunk.is_empty()).then_some(chunk)
+        })
+        .map(|chunk| -> ScopedTask<'_, R> { Box::new(move || f(chunk)) })
+        .collect();
+        self.run_scoped(tasks)
+    }
+
+    /// 提交借用了调用方数据的任务，等待全部结束后按顺序返回结果
+    fn run_scoped<'a, R: Send + 'a>(&self, tasks: Vec<ScopedTask<'a, R>>) -> Vec<R> {
+        let handles: Vec<_> = tasks
+            .into_iter()
+            .map(|task| {
+                let (job, handle) = task::with_handle(task);
+                // SAFETY: 任务只在 `'a` 内有效。返回前下面逐个等待全部句柄，而句柄只在任务执行完
+                // 或任务被销毁时返回，因此任务不会在 `'a` 结束后运行或被访问。
+                // 线程池只有通过 `&mut self` 的关闭方法才会交出未执行的任务，此时不可能有 `&self` 借用。
+                let job = unsafe { mem::transmute::<Box<dyn FnOnce() + Send + 'a>, Job>(job) };
+                self.send(job, Priority::Normal);
+                handle
+            })
+            .collect();
+
+        // 先等待全部任务，再处理 panic
+        let results: Vec<_> = handles.into_iter().map(|handle| handle.join()).collect();
+        results
+            .into_iter()
+            .map(|result| match result {
+                Ok(value) => value,
+                Err(TaskError::Panicked(payload)) => panic::resume_unwind(payload),
+                Err(TaskError::Cancelled) => panic!("ThreadPool has been shut down"),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        panic::AssertUnwindSafe,
+        sync::atomic::{AtomicU64, Ordering},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_map_keeps_order() {
+        let pool = ThreadPool::new(3);
+        // 闭包借用局部变量
+        let offset = 100;
+        let result = pool.map(0..1000, |i| i + offset);
+        assert_eq!(result, (100..1100).collect::<Vec<_>>());
+
+        let words = vec!["a".to_string(), "bb".to_string(), "ccc".to_string()];
+        assert_eq!(pool.map(&words, String::len), [1, 2, 3]);
+        assert!(pool.map(Vec::<i32>::new(), |i| i).is_empty());
+    }
+
+    #[test]
+    fn test_for_each_chunked() {
+        let pool = ThreadPool::new(4);
+        let data: Vec<u64> = (1..=1000).collect();
+        let sum = AtomicU64::new(0);
+        let chunks = AtomicU64::new(0);
+        pool.for_each_chunked(&data, 64, |chunk| {
+            assert!(chunk.len() <= 64);
+            sum.fetch_add(chunk.iter().sum(), Ordering::Relaxed);
+            chunks.fetch_add(1, Ordering::Relaxed);
+        });
+        assert_eq!(sum.into_inner(), 500_500);
+        assert_eq!(chunks.into_inner(), 16);
+    }
+
+    #[test]
+    fn test_try_map() {
+        let pool = ThreadPool::new(4);
+        let parsed = pool.try_map((0..100).map(|i| i.to_string()), |s| s.parse::<u8>());
+        assert_eq!(parsed, Ok((0..100).collect::<Vec<_>>()));
+
+        // 出错后不再处理剩下的元素
+        let calls = AtomicU64::new(0);
+        let result = pool.try_map(0..10_000, |i| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            if i == 10 { Err(format!("bad item {i}")) } else { Ok(i) }
+        });
+        assert_eq!(result, Err("bad item 10".to_string()));
+        assert!(calls.into_inner() < 10_000);
+    }
+
+    #[test]
+    fn test_nested_parallelism() {
+        // 唯一的工作线程在等待时执行自己提交的任务
+        let pool = ThreadPool::new(1);
+        let nested = pool.install(|| pool.map(0..4, |i| pool.map(0..i, |j| j).len()));
+        assert_eq!(nested, [0, 1, 2, 3]);
+
+        let pool = std::sync::Arc::new(pool);
+        let inner = pool.clone();
+        let handle = pool.submit(move || inner.submit(|| 21).join().unwrap() * 2);
+        assert_eq!(handle.join().unwrap(), 42);
+
+        // 在任务中调用 join 会永远等待自己，直接 panic
+        let inner = pool.clone();
+        let err = pool.submit(move || inner.join()).join().unwrap_err();
+        assert!(err.panic_message().unwrap().contains("would deadlock"));
+    }
+
+    #[test]
+    fn test_panic_is_propagated() {
+        let pool = ThreadPool::new(2);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.map(0..10, |i| if i == 7 { panic!("bad item") } else { i })
+        }));
+        let payload = result.unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"bad item"));
+
+        // 线程池不受影响
+        assert_eq!(pool.map(0..3, |i| i), [0, 1, 2]);
+        assert_eq!(pool.panic_count(), 0);
+    }
+}
@@ -0,0 +1,264 @@
+//! 按优先级出队的任务队列
+//!
+//! 队列用 `Mutex<BinaryHeap>` 加条件变量实现，按“截止序号”从小到大出队：
+//! 每个任务入队时得到一个递增的序号，截止序号为序号加上由优先级决定的延后量
+//! （[`Priority::High`] 为 0，每低一级多延后 [`AGING_INTERVAL`]），截止序号相同时先入队的先出队。
+//!
+//! 这样高优先级的任务通常先执行，但延后量有上限：一个低优先级任务入队后，
+//! 从第 `2 * AGING_INTERVAL` 个起再入队的任务都排在它后面（老化），
+//! 持续不断的高优先级任务不会让它永远等下去。
+
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    sync::{
+        Condvar, Mutex,
+        atomic::{self, AtomicUsize},
+    },
+    time::{Duration, Instant},
+};
+
+use super::Job;
+
+/// 每低一级优先级延后的入队数
+pub(super) const AGING_INTERVAL: u64 = 32;
+
+/// 任务的优先级
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    /// 相对于 [`Priority::High`] 延后的入队数
+    fn delay(self) -> u64 {
+        match self {
+            Priority::High => 0,
+            Priority::Normal => AGING_INTERVAL,
+            Priority::Low => 2 * AGING_INTERVAL,
+        }
+    }
+}
+
+struct Entry {
+    deadline: u64,
+    seq: u64,
+    enqueued: Instant,
+    job: Job,
+}
+
+impl Entry {
+    fn key(&self) -> (u64, u64) {
+        (self.deadline, self.seq)
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    /// `BinaryHeap` 是大顶堆，反转比较使截止序号最小的任务位于堆顶
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.key().cmp(&self.key())
+    }
+}
+
+#[derive(Default)]
+struct State {
+    heap: BinaryHeap<Entry>,
+    /// 下一个入队任务的序号
+    seq: u64,
+    /// 已入队但还没有执行完的任务数
+    unfinished: usize,
+    /// 在 [`Queue::pop`] 中等待任务的工作线程数
+    idle: usize,
+    closed: bool,
+}
+
+/// [`Queue::pop`] 的结果
+pub(super) enum Popped {
+    /// 取到的任务与它的排队时长
+    Job(Job, Duration),
+    /// 等待超时，队列仍为空
+    TimedOut,
+    /// 队列已关闭且为空
+    Closed,
+}
+
+/// 工作线程共享的任务队列
+#[derive(Default)]
+pub(super) struct Queue {
+    state: Mutex<State>,
+    available: Condvar,
+    /// 全部任务执行完时通知
+    idle: Condvar,
+    /// 队列中的任务数，供统计时不加锁读取
+    len: AtomicUsize,
+}
+
+impl Queue {
+    /// 放入任务，返回排队的任务是否多于空闲的工作线程（即队列积压）；队列已关闭时交还任务
+    pub(super) fn push(&self, job: Job, priority: Priority) -> Result<bool, Job> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return Err(job);
+        }
+        let seq = state.seq;
+        state.seq += 1;
+        state.unfinished += 1;
+        let entry = Entry { deadline: seq + priority.delay(), seq, enqueued: Instant::now(), job };
+        state.heap.push(entry);
+        self.len.fetch_add(1, atomic::Ordering::Relaxed);
+        let backlog = state.heap.len() > state.idle;
+        drop(state);
+        self.available.notify_one();
+        Ok(backlog)
+    }
+
+    /// 取出下一个任务，队列为空时阻塞，给定 `timeout` 时最多等待这么久
+    pub(super) fn pop(&self, timeout: Option<Duration>) -> Popped {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(entry) = state.heap.pop() {
+                self.len.fetch_sub(1, atomic::Ordering::Relaxed);
+                return Popped::Job(entry.job, entry.enqueued.elapsed());
+            }
+            if state.closed {
+                return Popped::Closed;
+            }
+            state.idle += 1;
+            state = match deadline {
+                None => self.available.wait(state).unwrap(),
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        state.idle -= 1;
+                        return Popped::TimedOut;
+                    }
+                    self.available.wait_timeout(state, remaining).unwrap().0
+                }
+            };
+            state.idle -= 1;
+        }
+    }
+
+    /// 工作线程执行完 [`Queue::pop`] 取出的任务后调用
+    pub(super) fn done(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.unfinished -= 1;
+        if state.unfinished == 0 {
+            self.idle.notify_all();
+        }
+    }
+
+    /// 阻塞直到已入队的任务全部执行完
+    pub(super) fn wait_idle(&self) {
+        let state = self.state.lock().unwrap();
+        drop(self.idle.wait_while(state, |state| state.unfinished > 0).unwrap());
+    }
+
+    /// 最多等待 `timeout` 直到已入队的任务全部执行完，返回是否执行完
+    pub(super) fn wait_idle_timeout(&self, timeout: Duration) -> bool {
+        let state = self.state.lock().unwrap();
+        let (state, _) =
+            self.idle.wait_timeout_while(state, timeout, |state| state.unfinished > 0).unwrap();
+        state.unfinished == 0
+    }
+
+    /// 按出队顺序取出全部还没有开始执行的任务
+    pub(super) fn drain(&self) -> Vec<Job> {
+        let mut state = self.state.lock().unwrap();
+        let jobs: Vec<Job> =
+            std::iter::from_fn(|| state.heap.pop()).map(|entry| entry.job).collect();
+        self.len.fetch_sub(jobs.len(), atomic::Ordering::Relaxed);
+        state.unfinished -= jobs.len();
+        if state.unfinished == 0 {
+            self.idle.notify_all();
+        }
+        jobs
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.len.load(atomic::Ordering::Relaxed)
+    }
+
+    /// 关闭队列：之后不再接受任务，已入队的任务照常取出
+    pub(super) fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.available.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// 按给定优先级依次入队，再全部取出执行，返回执行顺序（入队时的下标）
+    fn run_order(priorities: &[Priority]) -> Vec<usize> {
+        let queue = Queue::default();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        for (i, &priority) in priorities.iter().enumerate() {
+            let order = order.clone();
+            let job: Job = Box::new(move || order.lock().unwrap().push(i));
+            assert!(matches!(queue.push(job, priority), Ok(true)));
+        }
+        queue.close();
+        assert_eq!(queue.len(), priorities.len());
+        while let Popped::Job(job, _) = queue.pop(None) {
+            job();
+        }
+        Arc::try_unwrap(order).unwrap().into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_priority_order() {
+        use Priority::*;
+
+        // 优先级高的先出队，同一优先级先进先出
+        assert_eq!(run_order(&[Low, Normal, High, Normal, High, Low]), [2, 4, 1, 3, 0, 5]);
+
+        // 老化：低优先级任务只排在此后入队的前 2 * AGING_INTERVAL - 1 个高优先级任务后面
+        let highs = 2 * AGING_INTERVAL as usize + 5;
+        let mut priorities = vec![Low];
+        priorities.extend(std::iter::repeat_n(High, highs));
+        let order = run_order(&priorities);
+        let low = order.iter().position(|&i| i == 0).unwrap();
+        assert_eq!(low, 2 * AGING_INTERVAL as usize - 1);
+        assert_eq!(order.len(), highs + 1);
+    }
+
+    #[test]
+    fn test_pop_timeout() {
+        let queue = Queue::default();
+        assert!(matches!(queue.pop(Some(Duration::from_millis(5))), Popped::TimedOut));
+        // 没有空闲的工作线程，入队即积压
+        assert!(matches!(queue.push(Box::new(|| {}), Priority::Normal), Ok(true)));
+        assert!(matches!(queue.pop(Some(Duration::from_millis(5))), Popped::Job(..)));
+    }
+
+    #[test]
+    fn test_closed_queue_rejects_jobs() {
+        let queue = Queue::default();
+        queue.close();
+        assert!(queue.push(Box::new(|| {}), Priority::High).is_err());
+        assert!(matches!(queue.pop(None), Popped::Closed));
+    }
+}
@@ -0,0 +1,147 @@
+//! 线程池的运行指标
+//!
+//! 工作线程在取出与执行完任务时更新 [`Metrics`] 中的原子计数器（`Relaxed`，不加锁），
+//! [`ThreadPool::stats`](super::ThreadPool::stats) 读取各计数器得到一份 [`Stats`] 快照。
+//! 各项分别读取，任务并发执行时快照中的数字之间可能有细微的不一致。
+//!
+//! 排队时长（从入队到被工作线程取出）按 2 的幂微秒分桶统计：
+//! 第 0 桶为不足 1µs，第 i 桶为 `[2^(i-1), 2^i)` µs，最后一桶包含更长的全部时长。
+
+use std::{
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+/// 排队时长直方图的桶数
+const BUCKETS: usize = 32;
+
+/// 工作线程更新的计数器
+#[derive(Default)]
+pub(super) struct Metrics {
+    /// 正在执行任务的工作线程数
+    active: AtomicUsize,
+    /// 正常返回的任务数
+    completed: AtomicU64,
+    /// panic 的任务数
+    panicked: AtomicU64,
+    queue_wait: [AtomicU64; BUCKETS],
+}
+
+impl Metrics {
+    /// 工作线程取出一个排队了 `waited` 的任务，开始执行
+    pub(super) fn start(&self, waited: Duration) {
+        self.queue_wait[bucket(waited)].fetch_add(1, Ordering::Relaxed);
+        self.active.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 任务执行结束，`panicked` 表示任务是否 panic
+    pub(super) fn finish(&self, panicked: bool) {
+        self.active.fetch_sub(1, Ordering::Relaxed);
+        let counter = if panicked { &self.panicked } else { &self.completed };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn panicked(&self) -> u64 {
+        self.panicked.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn snapshot(&self, queued: usize, threads: usize) -> Stats {
+        Stats {
+            queued,
+            threads,
+            active: self.active.load(Ordering::Relaxed),
+            completed: self.completed.load(Ordering::Relaxed),
+            panicked: self.panicked(),
+            queue_wait: WaitHistogram {
+                buckets: self.queue_wait.each_ref().map(|count| count.load(Ordering::Relaxed)),
+            },
+        }
+    }
+}
+
+/// `waited` 所在的桶
+fn bucket(waited: Duration) -> usize {
+    let micros = u64::try_from(waited.as_micros()).unwrap_or(u64::MAX);
+    ((u64::BITS - micros.leading_zeros()) as usize).min(BUCKETS - 1)
+}
+
+/// 线程池运行指标的快照
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+pub struct Stats {
+    /// 在队列中等待的任务数
+    pub queued: usize,
+    /// 存活的工作线程数
+    pub threads: usize,
+    /// 正在执行任务的工作线程数
+    pub active: usize,
+    /// 正常返回的任务数（通过 `submit` 提交的任务 panic 时也算作正常返回）
+    pub completed: u64,
+    /// panic 的任务数
+    pub panicked: u64,
+    /// 已开始执行的任务的排队时长
+    pub queue_wait: WaitHistogram,
+}
+
+/// 排队时长直方图
+#[derive(Clone, Debug, PartialEq)]
+pub struct WaitHistogram {
+    buckets: [u64; BUCKETS],
+}
+
+#[allow(dead_code)]
+impl WaitHistogram {
+    /// 统计的任务数
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// 各桶的上界（不含）与任务数，最后一桶的上界为 [`Duration::MAX`]
+    pub fn buckets(&self) -> impl Iterator<Item = (Duration, u64)> + '_ {
+        self.buckets.iter().enumerate().map(|(i, &count)| {
+            let bound =
+                if i == BUCKETS - 1 { Duration::MAX } else { Duration::from_micros(1 << i) };
+            (bound, count)
+        })
+    }
+
+    /// 第 `percentile`（0~100）百分位所在桶的上界，没有任务时返回 `None`
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let rank = ((percentile / 100.0 * count as f64).ceil() as u64).clamp(1, count);
+        let mut seen = 0;
+        self.buckets().find_map(|(bound, n)| {
+            seen += n;
+            (seen >= rank).then_some(bound)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wait_histogram() {
+        assert_eq!(bucket(Duration::ZERO), 0);
+        assert_eq!(bucket(Duration::from_micros(1)), 1);
+        assert_eq!(bucket(Duration::from_micros(3)), 2);
+        assert_eq!(bucket(Duration::from_micros(4)), 3);
+        assert_eq!(bucket(Duration::MAX), BUCKETS - 1);
+
+        let metrics = Metrics::default();
+        assert_eq!(metrics.snapshot(0, 0).queue_wait.percentile(50.0), None);
+        for micros in [0, 3, 3, 100] {
+            metrics.start(Duration::from_micros(micros));
+            metrics.finish(false);
+        }
+        let histogram = metrics.snapshot(0, 0).queue_wait;
+        assert_eq!(histogram.count(), 4);
+        assert_eq!(histogram.percentile(0.0), Some(Duration::from_micros(1)));
+        assert_eq!(histogram.percentile(50.0), Some(Duration::from_micros(4)));
+        assert_eq!(histogram.percentile(100.0), Some(Duration::from_micros(128)));
+    }
+}
@@ -0,0 +1,116 @@
+//! 带返回值的任务
+//!
+//! [`ThreadPool::submit`](super::ThreadPool::submit) 把闭包包装成普通的 [`Job`](super::Job)：执行时捕获 panic，
+//! 把返回值或 panic 的载荷通过容量为 1 的通道（一次性通道）发送给 [`TaskHandle`]，
+//! 调用方通过句柄等待任务完成并取回结果。
+//!
+//! * 句柄被丢弃时任务照常执行，结果直接丢弃
+//! * 任务在执行前被丢弃（例如线程池已经关闭）时，发送端随之销毁，等待方得到 [`TaskError::Cancelled`]
+
+use std::{
+    any::Any,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    thread,
+    time::Duration,
+};
+
+use crossbeam::channel::{self, Receiver, RecvTimeoutError, TryRecvError};
+
+use super::worker;
+
+/// 任务没有正常返回的原因
+#[derive(Debug)]
+pub enum TaskError {
+    /// 任务 panic，附带 panic 的载荷（与 [`thread::JoinHandle::join`] 的错误相同）
+    Panicked(Box<dyn Any + Send + 'static>),
+    /// 任务在执行前被丢弃
+    Cancelled,
+}
+
+impl TaskError {
+    /// panic 的消息：载荷为字符串时返回它
+    pub fn panic_message(&self) -> Option<&str> {
+        let TaskError::Panicked(payload) = self else {
+            return None;
+        };
+        payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+    }
+}
+
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskError::Panicked(_) => match self.panic_message() {
+                Some(message) => write!(f, "task panicked: {message}"),
+                None => f.write_str("task panicked"),
+            },
+            TaskError::Cancelled => f.write_str("task was cancelled before running"),
+        }
+    }
+}
+
+impl std::error::Error for TaskError {}
+
+/// 已提交任务的句柄，用于等待任务完成并取回返回值
+#[derive(Debug)]
+#[must_use = "丢弃句柄后无法再取回任务的结果"]
+pub struct TaskHandle<R> {
+    receiver: Receiver<thread::Result<R>>,
+}
+
+#[allow(dead_code)]
+impl<R> TaskHandle<R> {
+    /// 阻塞直到任务结束，返回闭包的返回值
+    ///
+    /// 在工作线程上调用时，等待期间执行队列中的其他任务，避免所有线程都在等待而死锁。
+    pub fn join(self) -> Result<R, TaskError> {
+        let mut handle = self;
+        loop {
+            match handle.receiver.try_recv() {
+                Ok(result) => return result.map_err(TaskError::Panicked),
+                Err(TryRecvError::Disconnected) => return Err(TaskError::Cancelled),
+                Err(TryRecvError::Empty) => {}
+            }
+            match worker::help() {
+                Some(true) => {}
+                Some(false) => match handle.join_timeout(worker::HELP_INTERVAL) {
+                    Ok(result) => return result,
+                    Err(pending) => handle = pending,
+                },
+                None => break,
+            }
+        }
+        match handle.receiver.recv() {
+            Ok(result) => result.map_err(TaskError::Panicked),
+            Err(_) => Err(TaskError::Cancelled),
+        }
+    }
+
+    /// 最多等待 `timeout`，超时时交还句柄以便继续等待
+    pub fn join_timeout(self, timeout: Duration) -> Result<Result<R, TaskError>, Self> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(result) => Ok(result.map_err(TaskError::Panicked)),
+            Err(RecvTimeoutError::Disconnected) => Ok(Err(TaskError::Cancelled)),
+            Err(RecvTimeoutError::Timeout) => Err(self),
+        }
+    }
+}
+
+/// 把闭包包装为任务，返回任务与它的句柄；`'a` 为 `'static` 时任务即 [`Job`]
+pub(super) fn with_handle<'a, F, R>(f: F) -> (Box<dyn FnOnce() + Send + 'a>, TaskHandle<R>)
+where
+    F: FnOnce() -> R + Send + 'a,
+    R: Send + 'a,
+{
+    let (sender, receiver) = channel::bounded(1);
+    let job = Box::new(move || {
+        let result = panic::catch_unwind(AssertUnwindSafe(f));
+        // 句柄已被丢弃时没有人关心结果
+        let _ = sender.send(result);
+    });
+    (job, TaskHandle { receiver })
+}
@@ -0,0 +1,168 @@
+//! 等待一组任务完成
+//!
+//! 通过 [`ThreadPool::execute_in_group`] 提交的任务计入 [`WaitGroup`]，任务结束时计数减一，
+//! 调用方用 [`WaitGroup::wait`] 等待计数归零，不必再为“提交 N 个任务再等待”自己搭建通道。
+//!
+//! 计数在任务被销毁时减少，因此任务 panic 或线程池关闭时被丢弃的任务都不会让等待方永远等下去。
+//! 与 [`ThreadPool::join`] 不同，它只等待组内的任务，不受其他调用方提交的任务影响。
+
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::Duration,
+};
+
+use super::{Priority, ThreadPool, worker};
+
+#[derive(Default)]
+struct Inner {
+    /// 还没有结束的任务数
+    pending: Mutex<usize>,
+    done: Condvar,
+}
+
+/// 一组任务，克隆得到的是同一组
+#[derive(Clone, Default)]
+pub struct WaitGroup {
+    inner: Arc<Inner>,
+}
+
+#[allow(dead_code)]
+impl WaitGroup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 还没有结束的任务数
+    pub fn pending(&self) -> usize {
+        *self.inner.pending.lock().unwrap()
+    }
+
+    /// 阻塞直到组内的任务全部结束；之后加入的任务可以再次等待
+    ///
+    /// 在工作线程上调用时，等待期间执行队列中的其他任务。
+    pub fn wait(&self) {
+        while self.pending() > 0 {
+            match worker::help() {
+                Some(true) => {}
+                Some(false) => {
+                    self.wait_timeout(worker::HELP_INTERVAL);
+                }
+                None => break,
+            }
+        }
+        let pending = self.inner.pending.lock().unwrap();
+        drop(self.inner.done.wait_while(pending, |pending| *pending > 0).unwrap());
+    }
+
+    /// 最多等待 `timeout`，返回组内的任务是否已全部结束
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let pending = self.inner.pending.lock().unwrap();
+        let (pending, _) =
+            self.inner.done.wait_timeout_while(pending, timeout, |pending| *pending > 0).unwrap();
+        *pending == 0
+    }
+
+    /// 加入一个任务，返回的成员被销毁时任务结束
+    fn enter(&self) -> Member {
+        *self.inner.pending.lock().unwrap() += 1;
+        Member { inner: self.inner.clone() }
+    }
+}
+
+/// 组内的一个任务，随任务一起销毁
+struct Member {
+    inner: Arc<Inner>,
+}
+
+impl Drop for Member {
+    fn drop(&mut self) {
+        let mut pending = self.inner.pending.lock().unwrap();
+        *pending -= 1;
+        if *pending == 0 {
+            self.inner.done.notify_all();
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl ThreadPool {
+    /// 在线程池中执行 `task` 并把它计入 `group`
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// let pool = ThreadPool::new(4);
+    /// let group = WaitGroup::new();
+    /// for i in 0..8 {
+    ///     pool.execute_in_group(&group, move || println!("任务 {i}"));
+    /// }
+    /// group.wait();
+    /// ```
+    pub fn execute_in_group<F>(&self, group: &WaitGroup, task: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let member = group.enter();
+        self.send(
+            Box::new(move || {
+                let _member = member;
+                task();
+            }),
+            Priority::Normal,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_wait_group() {
+        let pool = ThreadPool::new(4);
+        let group = WaitGroup::new();
+        let count = Arc::new(AtomicUsize::new(0));
+
+        for _ in 0..16 {
+            let count = count.clone();
+            pool.execute_in_group(&group, move || {
+                thread::sleep(Duration::from_millis(1));
+                count.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        // panic 的任务同样计为结束
+        pool.execute_in_group(&group, || panic!("boom"));
+        group.wait();
+        assert_eq!(count.load(Ordering::SeqCst), 16);
+        assert_eq!(group.pending(), 0);
+
+        // 只等待组内的任务
+        let (release, gate) = crossbeam::channel::bounded::<()>(0);
+        pool.execute(move || gate.recv().unwrap());
+        let other = WaitGroup::new();
+        pool.execute_in_group(&other, || {});
+        assert!(other.wait_timeout(Duration::from_secs(5)));
+        release.send(()).unwrap();
+    }
+
+    #[test]
+    fn test_wait_group_with_shutdown() {
+        let mut pool = ThreadPool::new(1);
+        let group = WaitGroup::new();
+        let (release, gate) = crossbeam::channel::bounded::<()>(0);
+        pool.execute_in_group(&group, move || gate.recv().unwrap());
+        pool.execute_in_group(&group, || {});
+        assert_eq!(group.pending(), 2);
+        assert!(!group.wait_timeout(Duration::from_millis(10)));
+
+        // 被丢弃的任务同样计为结束
+        drop(pool.shutdown_now());
+        release.send(()).unwrap();
+        group.wait();
+    }
+}
@@ -0,0 +1,113 @@
+//! 队列水位回调
+//!
+//! 通过 [`Builder::queue_watermarks`](super::Builder::queue_watermarks) 设置高、低两条水位：
+//! 排队的任务数升到高水位时调用 `on_queue_high`，之后降到低水位时调用 `on_queue_low`，
+//! 可以用来限流或告警。两条水位之间留有间隔，队列长度在高水位附近抖动时不会反复触发。
+//!
+//! 检查只读取两个原子变量：放入任务后在提交方线程上检查是否升到高水位，
+//! 工作线程取出任务后检查是否降到低水位，因此回调可能在提交方或工作线程上调用，应当尽快返回。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 队列升到高水位或降到低水位时调用的函数，参数为当时排队的任务数
+pub type WatermarkHook = Box<dyn Fn(usize) + Send + Sync + 'static>;
+
+/// 水位与对应的回调，以及当前是否处于高水位
+pub(super) struct Watermarks {
+    pub(super) high: usize,
+    pub(super) low: usize,
+    pub(super) on_high: Option<WatermarkHook>,
+    pub(super) on_low: Option<WatermarkHook>,
+    /// 已经触发高水位、还没有降到低水位
+    above: AtomicBool,
+}
+
+impl Default for Watermarks {
+    /// 没有设置水位时高水位永远达不到
+    fn default() -> Self {
+        Self {
+            high: usize::MAX,
+            low: 0,
+            on_high: None,
+            on_low: None,
+            above: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Watermarks {
+    /// 队列长度变为 `depth` 后调用，跨过水位时调用对应的回调
+    pub(super) fn check(&self, depth: usize) {
+        // 先读再交换：处于高水位期间的提交只有一次读取
+        if depth >= self.high
+            && !self.above.load(Ordering::Relaxed)
+            && !self.above.swap(true, Ordering::AcqRel)
+            && let Some(hook) = &self.on_high
+        {
+            hook(depth);
+        } else if depth <= self.low
+            && self.above.load(Ordering::Relaxed)
+            && self.above.swap(false, Ordering::AcqRel)
+            && let Some(hook) = &self.on_low
+        {
+            hook(depth);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crossbeam::channel;
+
+    use super::*;
+    use crate::threadpool::ThreadPool;
+
+    #[test]
+    fn test_watermark_hysteresis() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let (high, low) = (events.clone(), events.clone());
+        let watermarks = Watermarks {
+            high: 4,
+            low: 1,
+            on_high: Some(Box::new(move |depth| high.lock().unwrap().push(("high", depth)))),
+            on_low: Some(Box::new(move |depth| low.lock().unwrap().push(("low", depth)))),
+            ..Watermarks::default()
+        };
+
+        // 两条水位之间的抖动不会重复触发
+        for depth in [3, 4, 5, 3, 4, 2, 1, 0, 2, 4] {
+            watermarks.check(depth);
+        }
+        assert_eq!(*events.lock().unwrap(), [("high", 4), ("low", 1), ("high", 4)]);
+    }
+
+    #[test]
+    fn test_queue_watermarks() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let (high, low) = (events.clone(), events.clone());
+        let pool = ThreadPool::builder()
+            .num_threads(1)
+            .queue_watermarks(4, 1)
+            .on_queue_high(move |depth| high.lock().unwrap().push(("high", depth)))
+            .on_queue_low(move |depth| low.lock().unwrap().push(("low", depth)))
+            .build();
+
+        let (release, gate) = channel::bounded::<()>(0);
+        let (started_tx, started) = channel::bounded::<()>(0);
+        pool.execute(move || {
+            started_tx.send(()).unwrap();
+            gate.recv().unwrap();
+        });
+        started.recv().unwrap();
+        for _ in 0..5 {
+            pool.execute(|| {});
+        }
+        assert_eq!(*events.lock().unwrap(), [("high", 4)]);
+
+        release.send(()).unwrap();
+        pool.join();
+        assert_eq!(*events.lock().unwrap(), [("high", 4), ("low", 1)]);
+    }
+}
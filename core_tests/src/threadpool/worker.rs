@@ -0,0 +1,237 @@
+//! 工作线程
+//!
+//! 每个工作线程循环从共享队列中取任务执行，队列关闭且为空时退出。
+//!
+//! 线程数在 `core_threads` 与 `max_threads` 之间伸缩：放入任务后队列积压（排队的任务多于空闲线程）
+//! 且线程数未达上限时启动一个新线程（[`grow`]）；线程空等 `keep_alive` 后，线程数多于
+//! `core_threads` 时退出。两者相等时线程数固定，空闲的线程一直等待。
+//! 进入循环前与退出后分别在该线程上调用 [`Hooks`] 中的 `on_thread_start` 与 `on_thread_stop`，
+//! 执行任务前后更新运行指标（见 `stats` 模块）与心跳（见 `health` 模块），
+//! 放入与取出任务后检查队列水位（见 `watermark` 模块）。
+//!
+//! 任务的 panic 不会让线程池悄悄变小：工作线程捕获 panic 后记录次数，启动一个同编号的新线程接替自己，
+//! 再调用 [`on_task_panic`](super::Builder::on_task_panic) 处理函数后退出（退出时同样调用 `on_thread_stop`）。
+//! 换新线程而不是原地继续，是为了丢弃 panic 的任务可能留下的线程局部状态；
+//! 先接替再调用处理函数，处理函数本身 panic 时容量也不受影响。
+//!
+//! 任务在工作线程上等待同一线程池中的其他任务（[`TaskHandle::join`](super::TaskHandle::join)、
+//! [`WaitGroup::wait`](super::WaitGroup::wait) 以及基于它们的 `map` 等）时，如果所有线程都在等待，
+//! 被等待的任务永远没有线程执行。因此等待期间工作线程通过 [`help`] 取出队列中的任务就地执行，
+//! 队列为空时才短暂阻塞（[`HELP_INTERVAL`]）后再检查。
+
+use std::{
+    any::Any,
+    cell::RefCell,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use super::{
+    Job,
+    health::{Heartbeat, Monitor},
+    queue::{Popped, Priority, Queue},
+    stats::Metrics,
+    watermark::Watermarks,
+};
+
+/// 工作线程等待其他任务期间、队列为空时每次阻塞的时长
+pub(super) const HELP_INTERVAL: Duration = Duration::from_millis(1);
+
+thread_local! {
+    /// 当前线程所属线程池的共享状态与它的心跳，不是工作线程时为 `None`
+    static CURRENT: RefCell<Option<(Arc<Shared>, Arc<Heartbeat>)>> = const { RefCell::new(None) };
+}
+
+/// 任务 panic 时调用的处理函数，参数为 panic 的载荷
+pub type PanicHandler = Box<dyn Fn(&(dyn Any + Send)) + Send + Sync + 'static>;
+
+/// 工作线程启动或退出时调用的函数
+pub type ThreadHook = Box<dyn Fn() + Send + Sync + 'static>;
+
+/// 由 [`Builder`](super::Builder) 设置的回调
+#[derive(Default)]
+pub(super) struct Hooks {
+    pub(super) on_thread_start: Option<ThreadHook>,
+    pub(super) on_thread_stop: Option<ThreadHook>,
+    pub(super) on_task_panic: Option<PanicHandler>,
+}
+
+/// 线程数的范围
+pub(super) struct Sizing {
+    pub(super) core_threads: usize,
+    pub(super) max_threads: usize,
+    /// 多于 `core_threads` 的线程空等多久后退出
+    pub(super) keep_alive: Duration,
+}
+
+/// 全部工作线程共享的状态
+pub(super) struct Shared {
+    pub(super) queue: Queue,
+    pub(super) hooks: Hooks,
+    pub(super) metrics: Metrics,
+    pub(super) sizing: Sizing,
+    pub(super) watermarks: Watermarks,
+    pub(super) monitor: Monitor,
+    /// 存活的工作线程数
+    pub(super) threads: AtomicUsize,
+    /// 下一个新线程的编号
+    next_id: AtomicUsize,
+    /// 工作线程的句柄，接替的线程在前任退出前登记
+    handles: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl Shared {
+    pub(super) fn new(
+        hooks: Hooks,
+        sizing: Sizing,
+        watermarks: Watermarks,
+        monitor: Monitor,
+    ) -> Self {
+        Self {
+            queue: Queue::default(),
+            hooks,
+            metrics: Metrics::default(),
+            sizing,
+            watermarks,
+            monitor,
+            threads: AtomicUsize::new(0),
+            next_id: AtomicUsize::new(0),
+            handles: Mutex::default(),
+        }
+    }
+
+    /// 线程数多于 `core_threads` 时减少一个，返回调用的线程是否应当退出
+    fn try_retire(&self) -> bool {
+        self.threads
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n > self.sizing.core_threads).then_some(n - 1)
+            })
+            .is_ok()
+    }
+
+    /// 取出一个工作线程的句柄，全部取完时返回 `None`
+    pub(super) fn take_handle(&self) -> Option<JoinHandle<()>> {
+        self.handles.lock().unwrap().pop()
+    }
+
+    /// 丢弃全部工作线程的句柄，不再等待它们退出
+    pub(super) fn detach(&self) {
+        self.handles.lock().unwrap().clear();
+    }
+}
+
+/// 把任务放入队列，队列积压时尝试启动新线程；线程池已关闭时交还任务
+pub(super) fn push(shared: &Arc<Shared>, job: Job, priority: Priority) -> Result<(), Job> {
+    let backlog = shared.queue.push(job, priority)?;
+    shared.watermarks.check(shared.queue.len());
+    if backlog {
+        grow(shared);
+    }
+    Ok(())
+}
+
+/// 线程数未达上限时启动一个新的工作线程，返回是否启动
+pub(super) fn grow(shared: &Arc<Shared>) -> bool {
+    let grown = shared
+        .threads
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+            (n < shared.sizing.max_threads).then_some(n + 1)
+        })
+        .is_ok();
+    if grown {
+        spawn(shared, shared.next_id.fetch_add(1, Ordering::Relaxed));
+    }
+    grown
+}
+
+/// 当前线程是否是 `shared` 所属线程池的工作线程
+pub(super) fn is_worker_of(shared: &Arc<Shared>) -> bool {
+    CURRENT.with_borrow(|current| {
+        current.as_ref().is_some_and(|(current, _)| Arc::ptr_eq(current, shared))
+    })
+}
+
+/// 在工作线程上等待时调用：执行所属线程池队列中的一个任务，返回是否执行了
+///
+/// 不是工作线程时返回 `None`，调用方应当直接阻塞等待。
+pub(super) fn help() -> Option<bool> {
+    let (shared, heartbeat) = CURRENT.with_borrow(Option::clone)?;
+    let Popped::Job(job, waited) = shared.queue.pop(Some(Duration::ZERO)) else {
+        return Some(false);
+    };
+    // 执行任务的是正在等待的线程，它不退出，panic 后不需要接替
+    if let Err(payload) = execute(&shared, &heartbeat, job, waited)
+        && let Some(handler) = &shared.hooks.on_task_panic
+    {
+        handler(payload.as_ref());
+    }
+    Some(true)
+}
+
+/// 执行一个任务并更新指标，返回任务 panic 的载荷
+fn execute(
+    shared: &Shared,
+    heartbeat: &Heartbeat,
+    job: Job,
+    waited: Duration,
+) -> thread::Result<()> {
+    shared.watermarks.check(shared.queue.len());
+    shared.metrics.start(waited);
+    let outer = shared.monitor.begin(heartbeat);
+    let result = panic::catch_unwind(AssertUnwindSafe(job));
+    shared.monitor.end(heartbeat, outer);
+    shared.metrics.finish(result.is_err());
+    shared.queue.done();
+    result
+}
+
+/// 启动编号为 `id` 的工作线程，调用方负责计入线程数
+fn spawn(shared: &Arc<Shared>, id: usize) {
+    let worker = Arc::clone(shared);
+    let handle = thread::Builder::new()
+        .name(format!("threadpool-worker-{id}"))
+        .spawn(move || run(worker, id))
+        .expect("ThreadPool unable to spawn worker thread.");
+    shared.handles.lock().unwrap().push(handle);
+}
+
+fn run(shared: Arc<Shared>, id: usize) {
+    // 在启动回调之前登记，回调 panic 时线程被标记为死亡
+    let registration = shared.monitor.register(id);
+    let heartbeat = &registration.heartbeat;
+    CURRENT.set(Some((shared.clone(), heartbeat.clone())));
+    if let Some(hook) = &shared.hooks.on_thread_start {
+        hook();
+    }
+    let sizing = &shared.sizing;
+    let keep_alive = (sizing.max_threads > sizing.core_threads).then_some(sizing.keep_alive);
+    loop {
+        let (job, waited) = match shared.queue.pop(keep_alive) {
+            Popped::Job(job, waited) => (job, waited),
+            Popped::TimedOut if shared.try_retire() => {
+                // 退出的线程不再需要等待，丢弃自己的句柄
+                let current = thread::current().id();
+                shared.handles.lock().unwrap().retain(|handle| handle.thread().id() != current);
+                break;
+            }
+            Popped::TimedOut => continue,
+            Popped::Closed => break,
+        };
+        if let Err(payload) = execute(&shared, heartbeat, job, waited) {
+            spawn(&shared, id);
+            if let Some(handler) = &shared.hooks.on_task_panic {
+                handler(payload.as_ref());
+            }
+            break;
+        }
+    }
+    if let Some(hook) = &shared.hooks.on_thread_stop {
+        hook();
+    }
+    CURRENT.set(None);
+}
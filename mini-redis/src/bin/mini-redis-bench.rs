@@ -0,0 +1,321 @@
+//! 压测工具
+//!
+//! 用法与 redis-benchmark 类似：
+//! `mini-redis-bench [-h host] [-p port] [-c clients] [-n requests] [-P pipeline]
+//! [-r keyspace] [-d size] [-m get=80,set=15,incr=5]`。
+//!
+//! 打开 `-c` 个并发连接，按 `-m` 给出的比例随机发送 GET / SET / INCR，每次发送 `-P` 条
+//! 命令后再读取回复；全部请求完成后输出吞吐量与延迟分位数。一条命令的延迟是从它所在的
+//! 一批命令发出到读到它的回复的时间。
+
+use std::{
+    env, fmt, io, process,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use mini_redis_server::{connection::Connection, frame::Frame};
+use tokio::net::TcpStream;
+
+const USAGE: &str = "usage: mini-redis-bench [-h host] [-p port] [-c clients] [-n requests] \
+                     [-P pipeline] [-r keyspace] [-d size] [-m get=80,set=15,incr=5]";
+
+/// 压测的命令
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Op {
+    Get,
+    Set,
+    Incr,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Op::Get => "GET",
+            Op::Set => "SET",
+            Op::Incr => "INCR",
+        })
+    }
+}
+
+/// 命令行选项
+#[derive(Clone, PartialEq, Debug)]
+struct Options {
+    addr: String,
+    /// 并发连接数
+    clients: usize,
+    /// 请求总数
+    requests: u64,
+    /// 每批发送的命令数
+    pipeline: usize,
+    /// 随机键的数量，键名为 `key:0` .. `key:{keyspace-1}`
+    keyspace: u64,
+    /// SET 写入的值的字节数
+    data_size: usize,
+    /// 各命令的权重
+    mix: Vec<(Op, u32)>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            addr: "127.0.0.1:6379".into(),
+            clients: 50,
+            requests: 100_000,
+            pipeline: 1,
+            keyspace: 10_000,
+            data_size: 3,
+            mix: vec![(Op::Get, 50), (Op::Set, 50)],
+        }
+    }
+}
+
+/// 一个连接的压测结果
+#[derive(Default)]
+struct Report {
+    /// 各命令的延迟（微秒）
+    latencies: Vec<(Op, u64)>,
+    errors: u64,
+}
+
+#[tokio::main]
+async fn main() {
+    let options = parse_args(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("mini-redis-bench: {e}");
+        eprintln!("{USAGE}");
+        process::exit(1);
+    });
+
+    // 各连接从共享的计数中领取请求，直到请求总数用完
+    let remaining = Arc::new(AtomicU64::new(options.requests));
+    let options = Arc::new(options);
+    let started = Instant::now();
+    let workers: Vec<_> = (0..options.clients)
+        .map(|id| tokio::spawn(run_client(options.clone(), remaining.clone(), id as u64)))
+        .collect();
+
+    let mut report = Report::default();
+    for worker in workers {
+        match worker.await.unwrap() {
+            Ok(part) => {
+                report.latencies.extend(part.latencies);
+                report.errors += part.errors;
+            }
+            Err(e) => {
+                eprintln!("Could not benchmark mini-redis at {}: {e}", options.addr);
+                process::exit(1);
+            }
+        }
+    }
+    print_report(&options, &mut report, started.elapsed());
+}
+
+/// 解析命令行参数
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let (mut host, mut port) = ("127.0.0.1".to_string(), 6379u16);
+    let mut args = args.into_iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("option '{flag}' requires a value"))?;
+        let invalid = || format!("invalid value '{value}' for option '{flag}'");
+        match flag.as_str() {
+            "-h" => host = value,
+            "-p" => port = value.parse().map_err(|_| invalid())?,
+            "-c" => options.clients = value.parse().map_err(|_| invalid())?,
+            "-n" => options.requests = value.parse().map_err(|_| invalid())?,
+            "-P" => options.pipeline = value.parse().map_err(|_| invalid())?,
+            "-r" => options.keyspace = value.parse().map_err(|_| invalid())?,
+            "-d" => options.data_size = value.parse().map_err(|_| invalid())?,
+            "-m" => options.mix = parse_mix(&value).ok_or_else(invalid)?,
+            _ => return Err(format!("unknown option '{flag}'")),
+        }
+    }
+    if options.clients == 0 || options.pipeline == 0 || options.keyspace == 0 {
+        return Err("-c, -P and -r must be positive".into());
+    }
+    options.addr = format!("{host}:{port}");
+    Ok(options)
+}
+
+/// 解析 `get=80,set=15,incr=5` 形式的命令比例，权重之和必须为正
+fn parse_mix(s: &str) -> Option<Vec<(Op, u32)>> {
+    let mix = s
+        .split(',')
+        .map(|part| {
+            let (name, weight) = part.split_once('=')?;
+            let op = match name.to_ascii_lowercase().as_str() {
+                "get" => Op::Get,
+                "set" => Op::Set,
+                "incr" => Op::Incr,
+                _ => return None,
+            };
+            Some((op, weight.parse().ok()?))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    (mix.iter().map(|(_, weight)| weight).sum::<u32>() > 0).then_some(mix)
+}
+
+/// 一个连接的压测循环
+async fn run_client(
+    options: Arc<Options>,
+    remaining: Arc<AtomicU64>,
+    id: u64,
+) -> io::Result<Report> {
+    let mut conn = Connection::new(TcpStream::connect(&options.addr).await?);
+    let mut rng = Rng::new(id);
+    let value = "x".repeat(options.data_size);
+    let total_weight: u32 = options.mix.iter().map(|(_, weight)| weight).sum();
+    let mut report = Report::default();
+    let mut batch = Vec::with_capacity(options.pipeline);
+
+    loop {
+        // 每次领取一批，剩余不足一批时领取剩下的全部
+        let want = options.pipeline as u64;
+        let Ok(prev) = remaining.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+            (n > 0).then(|| n - n.min(want))
+        }) else {
+            break;
+        };
+        let count = prev.min(want);
+
+        batch.clear();
+        for _ in 0..count {
+            let op = pick(&options.mix, rng.next() % total_weight as u64);
+            let key = format!("key:{}", rng.next() % options.keyspace);
+            let args = match op {
+                Op::Get => vec!["get".to_string(), key],
+                Op::Set => vec!["set".to_string(), key, value.clone()],
+                Op::Incr => vec!["incr".to_string(), format!("counter:{key}")],
+            };
+            conn.feed_frame(&Frame::from(args)).await?;
+            batch.push(op);
+        }
+        let sent = Instant::now();
+        conn.flush().await?;
+
+        for &op in &batch {
+            match conn.read_frame().await? {
+                Some(Frame::Error(_)) => report.errors += 1,
+                Some(_) => {}
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "server closed the connection",
+                    ));
+                }
+            }
+            report.latencies.push((op, sent.elapsed().as_micros() as u64));
+        }
+    }
+    Ok(report)
+}
+
+/// 按权重选取命令，`roll` 小于权重之和
+fn pick(mix: &[(Op, u32)], mut roll: u64) -> Op {
+    for &(op, weight) in mix {
+        if roll < weight as u64 {
+            return op;
+        }
+        roll -= weight as u64;
+    }
+    unreachable!("roll exceeds total weight")
+}
+
+/// 输出吞吐量与延迟分位数，先输出全部命令的汇总，再按命令分别输出
+fn print_report(options: &Options, report: &mut Report, elapsed: Duration) {
+    let total = report.latencies.len();
+    println!(
+        "{total} requests completed in {:.2} seconds ({} clients, pipeline {}, {} bytes payload)",
+        elapsed.as_secs_f64(),
+        options.clients,
+        options.pipeline,
+        options.data_size,
+    );
+    if report.errors > 0 {
+        println!("{} error replies", report.errors);
+    }
+    println!("throughput: {:.2} requests per second", total as f64 / elapsed.as_secs_f64());
+
+    let mut all: Vec<u64> = report.latencies.iter().map(|&(_, usec)| usec).collect();
+    print_latencies("ALL", &mut all);
+    for (op, _) in &options.mix {
+        let mut latencies: Vec<u64> =
+            report.latencies.iter().filter(|(kind, _)| kind == op).map(|&(_, usec)| usec).collect();
+        print_latencies(&op.to_string(), &mut latencies);
+    }
+}
+
+fn print_latencies(label: &str, latencies: &mut [u64]) {
+    if latencies.is_empty() {
+        return;
+    }
+    latencies.sort_unstable();
+    let ms = |usec: u64| usec as f64 / 1000.0;
+    println!(
+        "{label:<5} {:>8} requests  latency (msec): p50={:.3} p95={:.3} p99={:.3} max={:.3}",
+        latencies.len(),
+        ms(percentile(latencies, 50.0)),
+        ms(percentile(latencies, 95.0)),
+        ms(percentile(latencies, 99.0)),
+        ms(latencies[latencies.len() - 1]),
+    );
+}
+
+/// 已排序样本的分位数（最近秩法）
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// xorshift 随机数，压测只需要均匀分布，不需要密码学强度
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_args() {
+        let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+
+        assert_eq!(parse_args(args("")), Ok(Options::default()));
+        let options = parse_args(args("-p 7000 -c 4 -n 1000 -P 16 -m get=8,incr=2")).unwrap();
+        assert_eq!(options.addr, "127.0.0.1:7000");
+        assert_eq!((options.clients, options.requests, options.pipeline), (4, 1000, 16));
+        assert_eq!(options.mix, [(Op::Get, 8), (Op::Incr, 2)]);
+
+        assert!(parse_args(args("-P 0")).is_err());
+        assert!(parse_args(args("-m get=0")).is_err());
+        assert!(parse_args(args("-m del=1")).is_err());
+        assert!(parse_args(args("-c")).is_err());
+    }
+
+    #[test]
+    fn test_pick_and_percentile() {
+        let mix = [(Op::Get, 8), (Op::Set, 0), (Op::Incr, 2)];
+        assert_eq!(pick(&mix, 0), Op::Get);
+        assert_eq!(pick(&mix, 7), Op::Get);
+        assert_eq!(pick(&mix, 8), Op::Incr);
+
+        let sorted: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&sorted, 50.0), 50);
+        assert_eq!(percentile(&sorted, 99.0), 99);
+        assert_eq!(percentile(&sorted[..1], 99.0), 1);
+    }
+}
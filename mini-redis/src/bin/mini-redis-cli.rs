@@ -0,0 +1,367 @@
+//! 命令行客户端
+//!
+//! 用法与 redis-cli 相同：`mini-redis-cli [-h host] [-p port] [command [arg ...]]`。
+//! 给出命令时执行一次并输出回复（便于脚本使用），否则进入交互模式：支持行编辑与历史记录，
+//! 输入的一行按内联命令的规则切分参数（可以使用引号与转义）。
+//! `--bigkeys` / `--hotkeys` 分别执行 BIGKEYS / HOTKEYS，列出每种类型最大的键与访问最频繁的键。
+//! `--export` 把当前数据库以每键一行 JSON 的形式输出到标准输出，`--import` 从标准输入读取
+//! 同样格式的记录写入服务器，两者配合可以在实例之间搬运数据，或者交给 jq 查看。
+//!
+//! 回复按 redis-cli 的格式输出：字符串带引号（INFO 这类多行的字符串回复原样输出）、
+//! 嵌套的数组逐层缩进、错误以 `(error)` 开头。
+//! 执行 SUBSCRIBE / PSUBSCRIBE / MONITOR 后持续输出服务器推送的消息，直到连接关闭。
+
+use std::{
+    env,
+    io::{self, Write},
+    mem,
+    path::PathBuf,
+    process,
+};
+
+use mini_redis_server::{connection::Connection, frame::Frame, inline};
+use rustyline::{DefaultEditor, error::ReadlineError};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    net::TcpStream,
+};
+
+/// 交互模式的历史记录文件（位于用户主目录）
+const HISTORY_FILE: &str = ".mini_redis_cli_history";
+
+/// `--import` 每条 IMPORT 命令携带的记录数
+const IMPORT_BATCH: usize = 100;
+
+/// 用法说明：`--help` 时输出到标准输出，参数有误时输出到标准错误
+const USAGE: &str = "usage: mini-redis-cli [-h host] [-p port] \
+                     [--bigkeys | --hotkeys | --export | --import | command [arg ...]]
+       mini-redis-cli --help";
+
+/// 命令行参数决定的运行方式
+#[derive(Debug, PartialEq)]
+enum Mode {
+    /// 交互模式
+    Repl,
+    /// 执行一条命令并输出回复
+    Command(Vec<String>),
+    /// 执行 EXPORT，每条记录输出一行
+    Export,
+    /// 从标准输入读取记录，分批执行 IMPORT
+    Import,
+    /// 输出用法说明后退出
+    Help,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let (addr, mode) = parse_args(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("mini-redis-cli: {e}");
+        eprintln!("{USAGE}");
+        process::exit(1);
+    });
+    if mode == Mode::Help {
+        println!("{USAGE}");
+        return;
+    }
+    let mut conn = match TcpStream::connect(&addr).await {
+        Ok(stream) => Connection::new(stream),
+        Err(e) => {
+            eprintln!("Could not connect to mini-redis at {addr}: {e}");
+            process::exit(1);
+        }
+    };
+
+    let result = match mode {
+        Mode::Repl => repl(&mut conn, &addr).await,
+        Mode::Command(args) => run_command(&mut conn, args).await,
+        Mode::Export => export(&mut conn).await,
+        Mode::Import => import(&mut conn).await,
+        Mode::Help => unreachable!("handled before connecting"),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {e}");
+        process::exit(1);
+    }
+}
+
+/// 解析命令行参数，返回服务器地址与运行方式（没有命令时进入交互模式）
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<(String, Mode), String> {
+    let (mut host, mut port) = ("127.0.0.1".to_string(), 6379u16);
+    let mut mode = None;
+    let mut args = args.into_iter().peekable();
+    let is_option = |arg: &String| {
+        ["-h", "-p", "--bigkeys", "--hotkeys", "--export", "--import", "--help"]
+            .contains(&arg.as_str())
+    };
+    while let Some(flag) = args.next_if(is_option) {
+        mode = match flag.as_str() {
+            "--help" => return Ok((format!("{host}:{port}"), Mode::Help)),
+            "--bigkeys" => Some(Mode::Command(vec!["bigkeys".into()])),
+            "--hotkeys" => Some(Mode::Command(vec!["hotkeys".into()])),
+            "--export" => Some(Mode::Export),
+            "--import" => Some(Mode::Import),
+            _ => {
+                let value =
+                    args.next().ok_or_else(|| format!("option '{flag}' requires a value"))?;
+                match flag.as_str() {
+                    "-h" => host = value,
+                    _ => port = value.parse().map_err(|_| format!("invalid port '{value}'"))?,
+                }
+                continue;
+            }
+        };
+    }
+    let command: Vec<_> = args.collect();
+    let mode = match mode {
+        Some(_) if !command.is_empty() => {
+            return Err(format!("unexpected argument '{}'", command[0]));
+        }
+        Some(mode) => mode,
+        None if command.is_empty() => Mode::Repl,
+        None => Mode::Command(command),
+    };
+    Ok((format!("{host}:{port}"), mode))
+}
+
+/// 交互模式：逐行读取命令并输出回复，输入 `quit` / `exit` 或 Ctrl-D 退出
+async fn repl(conn: &mut Connection, addr: &str) -> io::Result<()> {
+    let mut editor = DefaultEditor::new().map_err(io::Error::other)?;
+    let history = env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+    if let Some(path) = &history {
+        let _ = editor.load_history(path);
+    }
+
+    let prompt = format!("{addr}> ");
+    loop {
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(e) => return Err(io::Error::other(e)),
+        };
+        let Some(args) = inline::split_args(&line) else {
+            println!("Invalid argument(s)");
+            continue;
+        };
+        if args.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line.as_str());
+        if args[0].eq_ignore_ascii_case("exit") {
+            break;
+        }
+        let quit = args[0].eq_ignore_ascii_case("quit");
+        run_command(conn, args).await?;
+        if quit {
+            break;
+        }
+    }
+
+    if let Some(path) = &history {
+        let _ = editor.save_history(path);
+    }
+    Ok(())
+}
+
+/// 发送一条命令并输出回复；订阅与监视命令之后持续输出推送的消息
+async fn run_command(conn: &mut Connection, args: Vec<String>) -> io::Result<()> {
+    let streaming = ["subscribe", "psubscribe", "ssubscribe", "monitor"]
+        .iter()
+        .any(|name| args[0].eq_ignore_ascii_case(name));
+    conn.write_frame(&Frame::from(args)).await?;
+
+    loop {
+        let reply = read_reply(conn).await?;
+        println!("{}", format_reply(&reply));
+        if !streaming || matches!(reply, Frame::Error(_)) {
+            return Ok(());
+        }
+    }
+}
+
+/// 执行 EXPORT，每条记录输出一行
+async fn export(conn: &mut Connection) -> io::Result<()> {
+    conn.write_frame(&Frame::from(vec!["export".to_string()])).await?;
+    let Frame::Array(lines) = read_reply(conn).await? else {
+        return Err(io::Error::other("unexpected reply to EXPORT"));
+    };
+    let mut out = io::stdout().lock();
+    for line in lines {
+        match line {
+            Frame::Error(e) => return Err(io::Error::other(e)),
+            line => writeln!(out, "{line}")?,
+        }
+    }
+    out.flush()
+}
+
+/// 从标准输入逐行读取 EXPORT 格式的记录（跳过空行），每 [`IMPORT_BATCH`] 条执行一次 IMPORT，
+/// 最后输出写入的键数；某一批出错时停止，之前的批次已经写入
+async fn import(conn: &mut Connection) -> io::Result<()> {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let (mut imported, mut line_no, mut batch_start) = (0, 0, 1);
+    let mut batch = vec!["import".to_string()];
+    loop {
+        let line = lines.next_line().await?;
+        if let Some(line) = &line {
+            line_no += 1;
+            if !line.trim().is_empty() {
+                batch.push(line.clone());
+            }
+        }
+        if batch.len() > IMPORT_BATCH || (line.is_none() && batch.len() > 1) {
+            let args = mem::replace(&mut batch, vec!["import".to_string()]);
+            conn.write_frame(&Frame::from(args)).await?;
+            match read_reply(conn).await? {
+                Frame::Integer(n) => imported += n,
+                reply => {
+                    let reply = format_reply(&reply);
+                    return Err(io::Error::other(format!(
+                        "{reply} (batch from line {batch_start})"
+                    )));
+                }
+            }
+            batch_start = line_no + 1;
+        }
+        if line.is_none() {
+            break;
+        }
+    }
+    println!("(integer) {imported}");
+    Ok(())
+}
+
+/// 读取一个回复，连接已关闭时返回错误
+async fn read_reply(conn: &mut Connection) -> io::Result<Frame> {
+    conn.read_frame()
+        .await?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "server closed the connection"))
+}
+
+/// 按 redis-cli 的格式输出回复；与 redis-cli 一样，含换行的字符串回复不加引号原样输出
+fn format_reply(frame: &Frame) -> String {
+    if let Frame::Bulk(s) = frame
+        && s.contains('\n')
+    {
+        return s.clone();
+    }
+    let mut out = String::new();
+    write_reply(&mut out, frame, 0);
+    out
+}
+
+/// 输出一个回复，`indent` 为嵌套元素第二行起的缩进宽度
+fn write_reply(out: &mut String, frame: &Frame, indent: usize) {
+    match frame {
+        Frame::Simple(s) | Frame::BigNumber(s) => out.push_str(s),
+        Frame::Error(e) => {
+            out.push_str("(error) ");
+            out.push_str(e);
+        }
+        Frame::Bulk(s) => out.push_str(&format!("{s:?}")),
+        Frame::Binary(bytes) => out.push_str(&format!("{:?}", String::from_utf8_lossy(bytes))),
+        Frame::Integer(n) => out.push_str(&format!("(integer) {n}")),
+        Frame::Double(n) => out.push_str(&format!("(double) {n}")),
+        Frame::Boolean(b) => out.push_str(if *b { "(true)" } else { "(false)" }),
+        Frame::Null => out.push_str("(nil)"),
+        Frame::Array(items) | Frame::Push(items) => write_items(out, items.iter(), ")", indent),
+        Frame::Set(items) => write_items(out, items.iter(), "~", indent),
+        Frame::Map(pairs) if pairs.is_empty() => out.push_str("(empty hash)"),
+        Frame::Map(pairs) => {
+            let width = pairs.len().to_string().len();
+            for (i, (key, value)) in pairs.iter().enumerate() {
+                let label = format!("{:>width$}# ", i + 1);
+                if i > 0 {
+                    out.push('\n');
+                    out.push_str(&" ".repeat(indent));
+                }
+                out.push_str(&label);
+                write_reply(out, key, indent + label.len());
+                out.push_str(" => ");
+                write_reply(out, value, indent + label.len());
+            }
+        }
+    }
+}
+
+/// 输出 `1) item` 形式的列表，序号右对齐，嵌套的元素缩进到序号之后
+fn write_items<'a>(
+    out: &mut String,
+    items: impl ExactSizeIterator<Item = &'a Frame>,
+    mark: &str,
+    indent: usize,
+) {
+    if items.len() == 0 {
+        out.push_str(if mark == "~" { "(empty set)" } else { "(empty array)" });
+        return;
+    }
+    let width = items.len().to_string().len();
+    for (i, item) in items.enumerate() {
+        let label = format!("{:>width$}{mark} ", i + 1);
+        if i > 0 {
+            out.push('\n');
+            out.push_str(&" ".repeat(indent));
+        }
+        out.push_str(&label);
+        write_reply(out, item, indent + label.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bulk(s: &str) -> Frame {
+        Frame::Bulk(s.into())
+    }
+
+    #[test]
+    fn test_format_reply() {
+        assert_eq!(format_reply(&Frame::Simple("OK".into())), "OK");
+        assert_eq!(format_reply(&bulk("say \"hi\"")), r#""say \"hi\"""#);
+        assert_eq!(
+            format_reply(&bulk("# Server\r\nrole:master\r\n")),
+            "# Server\r\nrole:master\r\n"
+        );
+        assert_eq!(format_reply(&Frame::Error("ERR oops".into())), "(error) ERR oops");
+        assert_eq!(format_reply(&Frame::Null), "(nil)");
+        assert_eq!(format_reply(&Frame::Array(vec![])), "(empty array)");
+
+        let nested = Frame::Array(vec![
+            bulk("a"),
+            Frame::Array(vec![Frame::Integer(1), Frame::Null]),
+            bulk("b"),
+        ]);
+        assert_eq!(format_reply(&nested), "1) \"a\"\n2) 1) (integer) 1\n   2) (nil)\n3) \"b\"");
+
+        let long = Frame::Array((0..10).map(Frame::Integer).collect());
+        assert!(format_reply(&long).starts_with(" 1) (integer) 0\n 2) "));
+        assert!(format_reply(&long).ends_with("\n10) (integer) 9"));
+
+        let map = Frame::Map(vec![(bulk("k"), Frame::Set(vec![bulk("v")]))]);
+        assert_eq!(format_reply(&map), "1# \"k\" => 1~ \"v\"");
+
+        let lines = Frame::Array(vec![bulk("a\nb")]);
+        assert_eq!(format_reply(&lines), "1) \"a\\nb\"");
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+
+        assert_eq!(parse_args(args("")), Ok(("127.0.0.1:6379".into(), Mode::Repl)));
+        assert_eq!(
+            parse_args(args("-p 7000 -h example set foo bar")),
+            Ok(("example:7000".into(), Mode::Command(args("set foo bar"))))
+        );
+        assert!(parse_args(args("-p nope")).is_err());
+        assert!(parse_args(args("-h")).is_err());
+        assert_eq!(
+            parse_args(args("--hotkeys -p 7000")),
+            Ok(("127.0.0.1:7000".into(), Mode::Command(args("hotkeys"))))
+        );
+        assert_eq!(parse_args(args("--export")), Ok(("127.0.0.1:6379".into(), Mode::Export)));
+        assert_eq!(parse_args(args("--import")), Ok(("127.0.0.1:6379".into(), Mode::Import)));
+        assert!(parse_args(args("--bigkeys get k")).is_err());
+        assert_eq!(parse_args(args("-p 7000 --help")), Ok(("127.0.0.1:7000".into(), Mode::Help)));
+    }
+}
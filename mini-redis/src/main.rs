@@ -1,9 +1,106 @@
-use mini_redis_server::{db::Db, handler::process_command};
+use std::{
+    env, fs,
+    io::{self, IsTerminal},
+    process::{self, Command, Stdio},
+};
 
+use mini_redis_server::{
+    config::{self, Config, LogFormat},
+    db::Db,
+    persistence, server,
+};
+use tokio::net::TcpListener;
+use tracing_subscriber::EnvFilter;
+
+/// 用法：`mini-redis [配置文件] [--name value ...]`，与 redis-server 相同；`--help` 列出全部参数
 #[tokio::main]
-async fn main() {
-    let db = Db::new();
-    println!("mini-redis (testing mode)");
-    println!("{}", process_command(&db, "SET foo bar").await);
-    println!("{}", process_command(&db, "GET foo").await);
+async fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "-h") || args.iter().any(|arg| arg == "--help") {
+        print!("{}", usage());
+        return Ok(());
+    }
+    let config = Config::from_args(args).unwrap_or_else(|e| {
+        eprintln!("mini-redis: {e}");
+        eprintln!("Try 'mini-redis --help' for more information.");
+        process::exit(1);
+    });
+    if config.daemonize {
+        return daemonize();
+    }
+    if !config.pidfile.is_empty() {
+        fs::write(&config.pidfile, format!("{}\n", process::id()))?;
+    }
+    init_logging(&config);
+
+    let db = Db::with_config(config.clone());
+    persistence::open(&db, &config.persistence).await?;
+
+    let listener = TcpListener::bind((config.bind.as_str(), config.port)).await?;
+    tracing::info!(addr = %listener.local_addr()?, "mini-redis listening");
+    if let Some((host, port)) = config.replicaof.clone() {
+        // 握手时向主节点报告监听端口，因此在开始复制前设置
+        db.replication().set_listening_port(listener.local_addr()?.port());
+        db.replicaof(host, port);
+    }
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    {
+        // io_uring 前端运行自己的单线程运行时，在阻塞线程上运行；后台任务仍在当前运行时上
+        let listener = listener.into_std()?;
+        tokio::task::spawn_blocking(move || server::uring::run(listener, db)).await?
+    }
+    #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+    server::run(listener, db).await
+}
+
+/// 帮助信息：用法、示例以及全部配置参数的名称
+fn usage() -> String {
+    let mut text = String::from(
+        "Usage: mini-redis [/path/to/redis.conf] [--name value ...]
+       mini-redis -h | --help
+
+Options are the same as the redis.conf directives; command line options
+override the config file, and a value may span several words.
+
+Examples:
+  mini-redis --port 7000
+  mini-redis /etc/redis/redis.conf --loglevel verbose
+  mini-redis --replicaof 127.0.0.1 6379
+  mini-redis --save 900 1 300 10 --appendonly yes
+
+Options:
+",
+    );
+    for name in config::names() {
+        text.push_str(&format!("  --{name}\n"));
+    }
+    text
+}
+
+/// 按 `loglevel` 与 `log-format` 初始化日志输出（标准错误）；设置了 `RUST_LOG` 时以它为准。
+/// 标准错误重定向到文件时不输出颜色控制码
+fn init_logging(config: &Config) {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(config.loglevel.level().as_str()));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal());
+    match config.log_format {
+        LogFormat::Pretty => builder.pretty().init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+/// 以 `--daemonize no` 在后台重新启动自身（不继承终端的标准输入输出），当前进程随即退出
+fn daemonize() -> io::Result<()> {
+    let child = Command::new(env::current_exe()?)
+        .args(env::args_os().skip(1))
+        .args(["--daemonize", "no"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    println!("mini-redis started in background, pid {}", child.id());
+    Ok(())
 }
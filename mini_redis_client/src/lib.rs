@@ -0,0 +1,294 @@
+//! mini-redis 的异步客户端
+//!
+//! 在 `mini_redis_server` 的帧与连接模块之上提供类型化的 API：
+//!
+//! ```no_run
+//! # async fn demo() -> mini_redis_client::Result<()> {
+//! let mut client = mini_redis_client::Client::connect("127.0.0.1:6379").await?;
+//! client.set("foo", "bar").await?;
+//! assert_eq!(client.get("foo").await?, Some("bar".to_string()));
+//!
+//! let mut subscriber = client.subscribe(&["news"]).await?;
+//! while let Some(message) = subscriber.next_message().await? {
+//!     println!("{message:?}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! 服务器返回的错误回复转换为 [`Error::Server`]；订阅后连接只能收发订阅相关的命令，
+//! 因此 [`Client::subscribe`] 消耗客户端，返回 [`Subscriber`]。
+
+use std::{fmt, io};
+
+pub use mini_redis_server::pubsub::Message;
+use mini_redis_server::{connection::Connection, frame::Frame};
+use tokio::net::{TcpStream, ToSocketAddrs};
+
+/// 客户端错误
+#[derive(Debug)]
+pub enum Error {
+    /// 网络错误，或服务器关闭了连接
+    Io(io::Error),
+    /// 服务器返回的错误回复
+    Server(String),
+    /// 回复的类型与命令不符
+    UnexpectedReply(Frame),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{e}"),
+            Error::Server(e) => f.write_str(e),
+            Error::UnexpectedReply(frame) => write!(f, "unexpected reply: {frame:?}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// 与服务器的一个连接
+pub struct Client {
+    conn: Connection,
+}
+
+impl Client {
+    /// 连接到服务器
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Client> {
+        let stream = TcpStream::connect(addr).await?;
+        Ok(Client { conn: Connection::new(stream) })
+    }
+
+    /// PING：检查连接，返回 `PONG`
+    pub async fn ping(&mut self) -> Result<String> {
+        match self.request(&["ping"]).await? {
+            Frame::Simple(s) | Frame::Bulk(s) => Ok(s),
+            frame => Err(Error::UnexpectedReply(frame)),
+        }
+    }
+
+    /// GET：读取键的值，键不存在时返回 `None`
+    pub async fn get(&mut self, key: &str) -> Result<Option<String>> {
+        match self.request(&["get", key]).await? {
+            Frame::Bulk(value) => Ok(Some(value)),
+            Frame::Binary(value) => Ok(Some(String::from_utf8_lossy(&value).into_owned())),
+            Frame::Null => Ok(None),
+            frame => Err(Error::UnexpectedReply(frame)),
+        }
+    }
+
+    /// SET：写入键的值
+    pub async fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        self.request(&["set", key, value]).await.and_then(expect_ok)
+    }
+
+    /// INCR：将键的整数值加 1，返回新值
+    pub async fn incr(&mut self, key: &str) -> Result<i64> {
+        self.request(&["incr", key]).await.and_then(expect_integer)
+    }
+
+    /// PUBLISH：发布消息，返回收到消息的订阅者数量
+    pub async fn publish(&mut self, channel: &str, message: &str) -> Result<u64> {
+        let receivers = self.request(&["publish", channel, message]).await.and_then(expect_integer);
+        receivers.map(|n| n as u64)
+    }
+
+    /// SUBSCRIBE：订阅频道，之后连接只用于接收消息
+    pub async fn subscribe(mut self, channels: &[&str]) -> Result<Subscriber> {
+        subscribe(&mut self.conn, "subscribe", channels).await?;
+        let channels = channels.iter().map(|channel| channel.to_string()).collect();
+        Ok(Subscriber { conn: self.conn, channels })
+    }
+
+    /// 发送任意命令，返回原始的回复帧；错误回复转换为 [`Error::Server`]
+    pub async fn request(&mut self, args: &[&str]) -> Result<Frame> {
+        send(&mut self.conn, args).await?;
+        match read(&mut self.conn).await? {
+            Frame::Error(e) => Err(Error::Server(e)),
+            frame => Ok(frame),
+        }
+    }
+}
+
+/// 处于订阅状态的连接
+pub struct Subscriber {
+    conn: Connection,
+    /// 当前订阅的频道
+    channels: Vec<String>,
+}
+
+impl Subscriber {
+    /// 当前订阅的频道
+    pub fn channels(&self) -> &[String] {
+        &self.channels
+    }
+
+    /// 等待下一条消息，服务器关闭连接时返回 `None`
+    pub async fn next_message(&mut self) -> Result<Option<Message>> {
+        let Some(frame) = self.conn.read_frame().await? else {
+            return Ok(None);
+        };
+        let parts = match frame {
+            Frame::Array(items) | Frame::Push(items) => strings(items),
+            frame => return Err(Error::UnexpectedReply(frame)),
+        };
+        match <[String; 3]>::try_from(parts) {
+            Ok([kind, channel, payload]) if kind == "message" => {
+                Ok(Some(Message::Channel { channel, payload }))
+            }
+            Ok(parts) => Err(Error::UnexpectedReply(bulk_array(parts.into()))),
+            Err(parts) => match <[String; 4]>::try_from(parts) {
+                Ok([kind, pattern, channel, payload]) if kind == "pmessage" => {
+                    Ok(Some(Message::Pattern { pattern, channel, payload }))
+                }
+                Ok(parts) => Err(Error::UnexpectedReply(bulk_array(parts.into()))),
+                Err(parts) => Err(Error::UnexpectedReply(bulk_array(parts))),
+            },
+        }
+    }
+
+    /// 追加订阅频道
+    pub async fn subscribe(&mut self, channels: &[&str]) -> Result<()> {
+        subscribe(&mut self.conn, "subscribe", channels).await?;
+        self.channels.extend(channels.iter().map(|channel| channel.to_string()));
+        Ok(())
+    }
+
+    /// 退订频道，`channels` 为空时退订全部频道
+    pub async fn unsubscribe(&mut self, channels: &[&str]) -> Result<()> {
+        let count = if channels.is_empty() { self.channels.len().max(1) } else { channels.len() };
+        send(&mut self.conn, &[&["unsubscribe"], channels].concat()).await?;
+        // 每个频道一条确认
+        for _ in 0..count {
+            expect_confirmation(&mut self.conn, "unsubscribe").await?;
+        }
+        match channels {
+            [] => self.channels.clear(),
+            _ => self.channels.retain(|channel| !channels.contains(&channel.as_str())),
+        }
+        Ok(())
+    }
+}
+
+async fn send(conn: &mut Connection, args: &[&str]) -> Result<()> {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    conn.write_frame(&Frame::from(args)).await?;
+    Ok(())
+}
+
+async fn read(conn: &mut Connection) -> Result<Frame> {
+    match conn.read_frame().await? {
+        Some(frame) => Ok(frame),
+        None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed").into()),
+    }
+}
+
+/// 发送订阅命令并读取每个频道的确认
+async fn subscribe(conn: &mut Connection, command: &str, channels: &[&str]) -> Result<()> {
+    send(conn, &[&[command], channels].concat()).await?;
+    for _ in channels {
+        expect_confirmation(conn, command).await?;
+    }
+    Ok(())
+}
+
+/// 读取一条 `[kind, channel, count]` 形式的订阅确认
+async fn expect_confirmation(conn: &mut Connection, kind: &str) -> Result<()> {
+    match read(conn).await? {
+        Frame::Array(items) | Frame::Push(items) if matches!(items.first(), Some(Frame::Bulk(s)) if s == kind) => {
+            Ok(())
+        }
+        Frame::Error(e) => Err(Error::Server(e)),
+        frame => Err(Error::UnexpectedReply(frame)),
+    }
+}
+
+fn expect_ok(frame: Frame) -> Result<()> {
+    match frame {
+        Frame::Simple(s) if s == "OK" => Ok(()),
+        frame => Err(Error::UnexpectedReply(frame)),
+    }
+}
+
+fn expect_integer(frame: Frame) -> Result<i64> {
+    match frame {
+        Frame::Integer(n) => Ok(n),
+        frame => Err(Error::UnexpectedReply(frame)),
+    }
+}
+
+/// 把批量字符串数组转换为字符串列表，其他类型的元素转换为文本形式
+fn strings(items: Vec<Frame>) -> Vec<String> {
+    items
+        .into_iter()
+        .map(|item| match item {
+            Frame::Bulk(s) | Frame::Simple(s) => s,
+            item => item.to_string(),
+        })
+        .collect()
+}
+
+fn bulk_array(parts: Vec<String>) -> Frame {
+    Frame::Array(parts.into_iter().map(Frame::Bulk).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use mini_redis_server::{db::Db, server};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    async fn start() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(server::run(listener, Db::new()));
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_get_set_incr() {
+        let mut client = Client::connect(start().await).await.unwrap();
+
+        assert_eq!(client.ping().await.unwrap(), "PONG");
+        assert_eq!(client.get("foo").await.unwrap(), None);
+        client.set("foo", "bar").await.unwrap();
+        assert_eq!(client.get("foo").await.unwrap(), Some("bar".into()));
+
+        assert_eq!(client.incr("n").await.unwrap(), 1);
+        assert_eq!(client.incr("n").await.unwrap(), 2);
+        let err = client.incr("foo").await.unwrap_err();
+        assert_eq!(err.to_string(), "ERR value is not an integer or out of range");
+        assert!(matches!(client.request(&["sadd", "s", "a"]).await, Ok(Frame::Integer(1))));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let addr = start().await;
+        let mut publisher = Client::connect(&addr).await.unwrap();
+        let subscriber = Client::connect(&addr).await.unwrap();
+
+        let mut subscriber = subscriber.subscribe(&["a", "b"]).await.unwrap();
+        assert_eq!(publisher.publish("b", "hi").await.unwrap(), 1);
+        assert_eq!(
+            subscriber.next_message().await.unwrap(),
+            Some(Message::Channel { channel: "b".into(), payload: "hi".into() })
+        );
+
+        subscriber.unsubscribe(&["b"]).await.unwrap();
+        assert_eq!(subscriber.channels(), ["a"]);
+        assert_eq!(publisher.publish("b", "ignored").await.unwrap(), 0);
+        subscriber.unsubscribe(&[]).await.unwrap();
+        assert!(subscriber.channels().is_empty());
+        assert_eq!(publisher.publish("a", "ignored").await.unwrap(), 0);
+    }
+}
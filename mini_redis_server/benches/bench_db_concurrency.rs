@@ -0,0 +1,60 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+#[cfg(feature = "actor-db")]
+use mini_redis_server::db::ActorDb;
+use mini_redis_server::db::Db;
+
+const TASKS: usize = 64;
+const SETS_PER_TASK: usize = 1_000;
+
+/// 被比较的两种数据库实现共有的写入接口
+trait SetStore: Clone + Send + Sync + 'static {
+    fn set(&self, key: String, value: String) -> impl Future<Output = ()> + Send;
+}
+
+impl SetStore for Db {
+    fn set(&self, key: String, value: String) -> impl Future<Output = ()> + Send {
+        Db::set(self, key, value)
+    }
+}
+
+#[cfg(feature = "actor-db")]
+impl SetStore for ActorDb {
+    fn set(&self, key: String, value: String) -> impl Future<Output = ()> + Send {
+        ActorDb::set(self, key, value)
+    }
+}
+
+/// 多个任务并发写入互不相同的键
+async fn concurrent_sets(db: impl SetStore) {
+    let tasks: Vec<_> = (0..TASKS)
+        .map(|t| {
+            let db = db.clone();
+            tokio::spawn(async move {
+                for i in 0..SETS_PER_TASK {
+                    db.set(format!("key:{t}:{i}"), i.to_string()).await;
+                }
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        task.await.unwrap();
+    }
+}
+
+/// 加锁的分片实现与 actor 实现的对比，actor 一项需要开启 `actor-db` feature；
+/// 分片数量的对比见 `bench_db_shards`
+fn bench_db_concurrency(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+    let mut group = c.benchmark_group("db_concurrency");
+
+    group.bench_function("locked", |b| b.iter(|| rt.block_on(concurrent_sets(Db::new()))));
+    #[cfg(feature = "actor-db")]
+    group.bench_function("actor", |b| {
+        b.iter(|| rt.block_on(async { concurrent_sets(ActorDb::spawn()).await }))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_db_concurrency);
+criterion_main!(benches);
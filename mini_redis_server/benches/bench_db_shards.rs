@@ -0,0 +1,38 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use mini_redis_server::db::{DEFAULT_SHARDS, Db};
+
+const TASKS: usize = 64;
+const SETS_PER_TASK: usize = 1_000;
+
+/// 多个任务并发写入互不相同的键
+async fn concurrent_sets(db: Db) {
+    let tasks: Vec<_> = (0..TASKS)
+        .map(|t| {
+            let db = db.clone();
+            tokio::spawn(async move {
+                for i in 0..SETS_PER_TASK {
+                    db.set(format!("key:{t}:{i}"), i.to_string()).await;
+                }
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        task.await.unwrap();
+    }
+}
+
+fn bench_db_shards(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+    let mut group = c.benchmark_group("concurrent_set");
+
+    for shards in [1, DEFAULT_SHARDS] {
+        group.bench_with_input(BenchmarkId::from_parameter(shards), &shards, |b, &shards| {
+            b.iter(|| rt.block_on(concurrent_sets(Db::with_shards(shards))))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_db_shards);
+criterion_main!(benches);
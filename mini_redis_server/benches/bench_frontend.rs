@@ -0,0 +1,92 @@
+//! 比较默认的 epoll 前端与 io_uring 前端
+//!
+//! 两个服务器各自运行在单独的线程上（都是单线程运行时），客户端通过 TCP 连接发送命令：
+//! - `round_trip`：逐条发送 SET 并等待回复，主要反映单次请求的延迟
+//! - `pipeline`：一次发送一批 SET 后再读取全部回复，主要反映吞吐量
+//!
+//! 运行：`cargo bench -p mini_redis_server --features io-uring --bench bench_frontend`
+
+use std::{net::SocketAddr, thread};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use mini_redis_server::{connection::Connection, db::Db, frame::Frame, server};
+use tokio::{net::TcpStream, runtime::Runtime};
+
+const ROUND_TRIPS: usize = 100;
+const PIPELINE: usize = 1_000;
+
+/// 在单独线程的单线程 tokio 运行时上启动默认前端
+fn start_epoll() -> SocketAddr {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async {
+            listener.set_nonblocking(true).unwrap();
+            let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+            server::run(listener, Db::new()).await
+        })
+    });
+    addr
+}
+
+/// 在单独的线程上启动 io_uring 前端
+fn start_uring() -> SocketAddr {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let db = rt.block_on(async { Db::new() });
+        server::uring::run(listener, db)
+    });
+    addr
+}
+
+fn set(i: usize) -> Frame {
+    Frame::from(vec!["set".to_string(), format!("key:{i}"), i.to_string()])
+}
+
+async fn round_trips(conn: &mut Connection) {
+    for i in 0..ROUND_TRIPS {
+        conn.write_frame(&set(i)).await.unwrap();
+        conn.read_frame().await.unwrap().unwrap();
+    }
+}
+
+async fn pipeline(conn: &mut Connection) {
+    for i in 0..PIPELINE {
+        conn.feed_frame(&set(i)).await.unwrap();
+    }
+    conn.flush().await.unwrap();
+    for _ in 0..PIPELINE {
+        conn.read_frame().await.unwrap().unwrap();
+    }
+}
+
+fn bench_frontend(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let servers = [("epoll", start_epoll()), ("io_uring", start_uring())];
+    let connect = |addr| {
+        rt.block_on(async {
+            // 服务器线程可能还没有开始接受连接，内核的监听队列会先接住连接
+            Connection::new(TcpStream::connect(addr).await.unwrap())
+        })
+    };
+
+    let mut group = c.benchmark_group("round_trip");
+    for (name, addr) in servers {
+        let mut conn = connect(addr);
+        group.bench_function(name, |b| b.iter(|| rt.block_on(round_trips(&mut conn))));
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("pipeline");
+    for (name, addr) in servers {
+        let mut conn = connect(addr);
+        group.bench_function(name, |b| b.iter(|| rt.block_on(pipeline(&mut conn))));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_frontend);
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+//! RESP 解析的模糊测试
+//!
+//! 把任意字节交给帧解码（[`Frame::decode`]）与内联请求解码（[`inline::decode`]），
+//! 要求只返回错误而不会 panic；解码成功的帧按 RESP3 重新编码后再次解码，结果必须一致。
+//!
+//! 运行：`cargo fuzz run resp`（在 `mini_redis_server` 目录下，需要 nightly 工具链）。
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mini_redis_server::{
+    command::Command,
+    frame::{Frame, Protocol},
+    inline,
+};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(Some((frame, len))) = Frame::decode(data) {
+        assert!(len <= data.len());
+        let _ = frame.to_string();
+
+        // 比较编码后的字节而不是帧本身：NaN 与自身不相等
+        let encoded = frame.encode_with(Protocol::Resp3);
+        let Ok(Some((decoded, decoded_len))) = Frame::decode(&encoded) else {
+            panic!("re-encoded frame does not decode: {frame:?}");
+        };
+        assert_eq!(decoded_len, encoded.len());
+        assert_eq!(decoded.encode_with(Protocol::Resp3), encoded);
+        let _ = frame.encode();
+
+        if let Ok(args) = frame.into_args() {
+            parse_command(&args);
+        }
+    }
+
+    if let Ok(Some((args, len))) = inline::decode(data, inline::MAX_INLINE_LEN) {
+        assert!(len <= data.len());
+        parse_command(&args);
+    }
+    if let Ok(line) = std::str::from_utf8(data) {
+        let _ = Command::parse(line);
+    }
+});
+
+fn parse_command(args: &[String]) {
+    let parts: Vec<_> = args.iter().map(String::as_str).collect();
+    if !parts.is_empty() {
+        let _ = Command::from_args(&parts);
+    }
+}
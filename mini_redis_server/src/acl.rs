@@ -0,0 +1,382 @@
+//! 访问控制列表（ACL）
+//!
+//! 每个用户有一组规则，与 Redis 的 `ACL SETUSER` 语法一致：
+//! - `on` / `off`：启用或禁用用户
+//! - `>password` / `<password` / `nopass` / `resetpass`：添加、删除密码，或允许任意密码
+//! - `~pattern` / `allkeys` / `resetkeys`：允许访问的键（glob 模式）
+//! - `+command` / `-command` / `+@category` / `-@category` / `allcommands` / `nocommands`：
+//!   允许或禁止的命令与命令分类（见 [`Command::categories`]）
+//! - `reset`：恢复为新用户的状态（禁用、无密码、无权限）
+//!
+//! 命令规则按添加顺序求值，最后一条匹配的规则决定是否允许；`+@all` / `-@all` 会清除此前的规则。
+//! 内置的 `default` 用户允许任意密码并拥有全部权限，新连接以它的身份登录；
+//! 修改它的规则后，新连接需要先 AUTH。
+//!
+//! 权限在命令执行前由客户端会话检查（见 [`Session`](crate::session::Session)）。
+//! 列表中不显示密码原文，只显示它的校验和。
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::RwLock,
+};
+
+use crate::{command::Command, db::DbError, frame::Frame, glob, handler, persistence::crc64};
+
+/// 内置的默认用户
+pub const DEFAULT_USER: &str = "default";
+
+/// 规则中可以使用的命令分类
+pub const CATEGORIES: &[&str] = &[
+    "admin",
+    "all",
+    "bitmap",
+    "blocking",
+    "connection",
+    "dangerous",
+    "geo",
+    "hash",
+    "keyspace",
+    "pubsub",
+    "read",
+    "set",
+    "sortedset",
+    "stream",
+    "string",
+    "write",
+];
+
+/// 命令规则匹配的对象
+#[derive(Clone, Debug, PartialEq)]
+enum Selector {
+    /// 分类，`all` 匹配所有命令
+    Category(String),
+    /// 命令名，父命令（如 `config`）同时匹配它的所有子命令
+    Name(String),
+}
+
+impl Selector {
+    fn matches(&self, command: &Command) -> bool {
+        match self {
+            Selector::Category(category) => {
+                category == "all" || command.categories().contains(&category.as_str())
+            }
+            Selector::Name(name) => {
+                let full = command.name();
+                full == name || full.split('|').next() == Some(name.as_str())
+            }
+        }
+    }
+}
+
+/// 一个用户的认证信息与权限
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct User {
+    enabled: bool,
+    /// 允许任意密码
+    nopass: bool,
+    passwords: BTreeSet<String>,
+    /// 命令规则：是否允许、匹配对象
+    commands: Vec<(bool, Selector)>,
+    /// 允许访问的键模式
+    keys: Vec<String>,
+}
+
+impl User {
+    /// 拥有全部权限、允许任意密码的用户（`default` 用户的初始状态）
+    fn superuser() -> Self {
+        Self {
+            enabled: true,
+            nopass: true,
+            passwords: BTreeSet::new(),
+            commands: vec![(true, Selector::Category("all".into()))],
+            keys: vec!["*".into()],
+        }
+    }
+
+    /// 应用一条规则
+    fn apply(&mut self, rule: &str) -> Result<(), String> {
+        let lower = rule.to_ascii_lowercase();
+        match lower.as_str() {
+            "on" => self.enabled = true,
+            "off" => self.enabled = false,
+            "nopass" => {
+                self.nopass = true;
+                self.passwords.clear();
+            }
+            "resetpass" => {
+                self.nopass = false;
+                self.passwords.clear();
+            }
+            "allkeys" => self.keys = vec!["*".into()],
+            "resetkeys" => self.keys.clear(),
+            "allcommands" => return self.apply("+@all"),
+            "nocommands" => return self.apply("-@all"),
+            "reset" => *self = User::default(),
+            _ => {
+                if let Some(password) = rule.strip_prefix('>') {
+                    self.nopass = false;
+                    self.passwords.insert(password.to_string());
+                } else if let Some(password) = rule.strip_prefix('<') {
+                    if !self.passwords.remove(password) {
+                        return Err("no such password".into());
+                    }
+                } else if let Some(pattern) = rule.strip_prefix('~') {
+                    self.keys.push(pattern.to_string());
+                } else if let Some(rest) = lower.strip_prefix(['+', '-']) {
+                    self.add_command_rule(lower.starts_with('+'), rest)?;
+                } else {
+                    return Err("Syntax error".into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn add_command_rule(&mut self, allow: bool, target: &str) -> Result<(), String> {
+        let selector = match target.strip_prefix('@') {
+            Some(category) if CATEGORIES.contains(&category) => {
+                Selector::Category(category.to_string())
+            }
+            None if handler::specs()
+                .any(|spec| spec.name == target || spec.name.split('|').next() == Some(target)) =>
+            {
+                Selector::Name(target.to_string())
+            }
+            _ => return Err("Unknown command or category name in ACL".into()),
+        };
+        if selector == Selector::Category("all".into()) {
+            self.commands.clear();
+        }
+        self.commands.push((allow, selector));
+        Ok(())
+    }
+
+    /// 是否允许执行这条命令（只看命令规则，不看键）
+    pub fn can_run(&self, command: &Command) -> bool {
+        self.commands
+            .iter()
+            .rev()
+            .find(|(_, selector)| selector.matches(command))
+            .is_some_and(|(allow, _)| *allow)
+    }
+
+    /// 是否允许访问这个键
+    pub fn can_access(&self, key: &str) -> bool {
+        self.keys.iter().any(|pattern| glob::matches(pattern, key))
+    }
+
+    fn check_password(&self, password: &str) -> bool {
+        self.nopass || self.passwords.contains(password)
+    }
+
+    fn flags(&self) -> Vec<&'static str> {
+        let mut flags = vec![if self.enabled { "on" } else { "off" }];
+        if self.nopass {
+            flags.push("nopass");
+        }
+        flags
+    }
+
+    /// 密码的校验和（十六进制），不暴露原文
+    fn password_digests(&self) -> Vec<String> {
+        self.passwords.iter().map(|p| format!("#{:016x}", crc64(p.as_bytes()))).collect()
+    }
+
+    fn command_rules(&self) -> String {
+        if self.commands.is_empty() {
+            return "-@all".into();
+        }
+        let rules = self.commands.iter().map(|(allow, selector)| {
+            let sign = if *allow { '+' } else { '-' };
+            match selector {
+                Selector::Category(category) => format!("{sign}@{category}"),
+                Selector::Name(name) => format!("{sign}{name}"),
+            }
+        });
+        rules.collect::<Vec<_>>().join(" ")
+    }
+
+    fn key_rules(&self) -> String {
+        self.keys.iter().map(|pattern| format!("~{pattern}")).collect::<Vec<_>>().join(" ")
+    }
+
+    /// ACL GETUSER 的回复：flags、passwords、commands、keys 四项
+    pub fn to_frame(&self) -> Frame {
+        let strings =
+            |items: Vec<String>| Frame::Array(items.into_iter().map(Frame::Bulk).collect());
+        Frame::Array(vec![
+            Frame::Bulk("flags".into()),
+            strings(self.flags().into_iter().map(String::from).collect()),
+            Frame::Bulk("passwords".into()),
+            strings(self.password_digests()),
+            Frame::Bulk("commands".into()),
+            Frame::Bulk(self.command_rules()),
+            Frame::Bulk("keys".into()),
+            Frame::Bulk(self.key_rules()),
+        ])
+    }
+
+    /// ACL LIST 中的一行，例如 `user default on nopass ~* +@all`
+    fn describe(&self, name: &str) -> String {
+        let mut parts = vec![format!("user {name}")];
+        parts.extend(self.flags().into_iter().map(String::from));
+        parts.extend(self.password_digests());
+        parts.extend(Some(self.key_rules()).filter(|rules| !rules.is_empty()));
+        parts.push(self.command_rules());
+        parts.join(" ")
+    }
+}
+
+/// 所有用户，可在连接之间共享
+pub struct Acl {
+    users: RwLock<BTreeMap<String, User>>,
+}
+
+impl Default for Acl {
+    fn default() -> Self {
+        let users = BTreeMap::from([(DEFAULT_USER.to_string(), User::superuser())]);
+        Self { users: RwLock::new(users) }
+    }
+}
+
+impl Acl {
+    /// 创建或修改用户（ACL SETUSER），任意一条规则无效时不做任何修改
+    pub fn set_user(&self, name: &str, rules: &[String]) -> Result<(), DbError> {
+        let mut users = self.users.write().unwrap();
+        let mut user = users.get(name).cloned().unwrap_or_default();
+        for rule in rules {
+            user.apply(rule).map_err(|reason| DbError::AclRule(rule.clone(), reason))?;
+        }
+        users.insert(name.to_string(), user);
+        Ok(())
+    }
+
+    /// 查询用户
+    pub fn user(&self, name: &str) -> Option<User> {
+        self.users.read().unwrap().get(name).cloned()
+    }
+
+    /// 以 ACL 规则的形式描述所有用户（ACL LIST），按用户名排序
+    pub fn list(&self) -> Vec<String> {
+        let users = self.users.read().unwrap();
+        users.iter().map(|(name, user)| user.describe(name)).collect()
+    }
+
+    /// 校验用户名与密码，用户不存在、被禁用或密码错误时返回 `WrongPass`
+    pub fn authenticate(&self, name: &str, password: &str) -> Result<(), DbError> {
+        let users = self.users.read().unwrap();
+        match users.get(name) {
+            Some(user) if user.enabled && user.check_password(password) => Ok(()),
+            _ => Err(DbError::WrongPass),
+        }
+    }
+
+    /// 新连接自动登录的用户：`default` 用户已启用且允许任意密码时为它，否则需要先 AUTH
+    pub fn default_login(&self) -> Option<String> {
+        let users = self.users.read().unwrap();
+        let user = users.get(DEFAULT_USER)?;
+        (user.enabled && user.nopass).then(|| DEFAULT_USER.to_string())
+    }
+
+    /// 检查用户能否执行这条命令：先检查命令规则，再检查命令访问的每个键
+    pub fn check(&self, name: &str, command: &Command) -> Result<(), DbError> {
+        let users = self.users.read().unwrap();
+        let user = users
+            .get(name)
+            .filter(|user| user.enabled && user.can_run(command))
+            .ok_or_else(|| DbError::NoPermCommand(name.to_string(), command.name().to_string()))?;
+        if command.keys().iter().all(|key| user.can_access(key)) {
+            Ok(())
+        } else {
+            Err(DbError::NoPermKey)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(rules: &str) -> Vec<String> {
+        rules.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_command_rules() {
+        let acl = Acl::default();
+        acl.set_user("alice", &rules("on nopass allkeys +@read -smembers +set")).unwrap();
+
+        let check = |input: &str| acl.check("alice", &Command::parse(input).unwrap());
+        assert_eq!(check("get k"), Ok(()));
+        assert_eq!(check("set k v"), Ok(()));
+        assert_eq!(
+            check("smembers s"),
+            Err(DbError::NoPermCommand("alice".into(), "smembers".into()))
+        );
+        assert!(check("sadd s m").is_err());
+        assert!(check("config get *").is_err());
+
+        acl.set_user("alice", &rules("+config")).unwrap();
+        assert_eq!(check("config get *"), Ok(()));
+        acl.set_user("alice", &rules("nocommands")).unwrap();
+        assert!(check("get k").is_err());
+    }
+
+    #[test]
+    fn test_key_rules() {
+        let acl = Acl::default();
+        acl.set_user("bob", &rules("on nopass +@all ~cache:* ~session:*")).unwrap();
+
+        let check = |input: &str| acl.check("bob", &Command::parse(input).unwrap());
+        assert_eq!(check("get cache:1"), Ok(()));
+        assert_eq!(check("del cache:1 session:2"), Ok(()));
+        assert_eq!(check("del cache:1 other"), Err(DbError::NoPermKey));
+        assert_eq!(check("publish ch m"), Ok(()));
+    }
+
+    #[test]
+    fn test_authenticate() {
+        let acl = Acl::default();
+        assert_eq!(acl.default_login(), Some(DEFAULT_USER.into()));
+        acl.set_user("carol", &rules(">secret")).unwrap();
+
+        // 新用户默认禁用
+        assert_eq!(acl.authenticate("carol", "secret"), Err(DbError::WrongPass));
+        acl.set_user("carol", &rules("on")).unwrap();
+        assert_eq!(acl.authenticate("carol", "secret"), Ok(()));
+        assert_eq!(acl.authenticate("carol", "wrong"), Err(DbError::WrongPass));
+        assert_eq!(acl.authenticate("nobody", "secret"), Err(DbError::WrongPass));
+
+        acl.set_user(DEFAULT_USER, &rules(">pw")).unwrap();
+        assert_eq!(acl.default_login(), None);
+    }
+
+    #[test]
+    fn test_invalid_rules_are_atomic() {
+        let acl = Acl::default();
+        let err = acl.set_user("dave", &rules("on +@nosuch")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR Error in ACL SETUSER modifier '+@nosuch': Unknown command or category name in ACL"
+        );
+        assert!(acl.user("dave").is_none());
+        assert!(acl.set_user("dave", &rules("+nosuchcommand")).is_err());
+        assert!(acl.set_user("dave", &rules("bogus")).is_err());
+    }
+
+    #[test]
+    fn test_list_and_getuser() {
+        let acl = Acl::default();
+        acl.set_user("eve", &rules("on >pw ~k* +get")).unwrap();
+
+        let list = acl.list();
+        assert_eq!(list[0], "user default on nopass ~* +@all");
+        assert!(list[1].starts_with("user eve on #"), "{}", list[1]);
+        assert!(list[1].ends_with(" ~k* +get"));
+        assert!(!list[1].contains("pw "));
+
+        let user = acl.user("eve").unwrap().to_frame().to_string();
+        assert!(user.starts_with("1) flags\n2) 1) on\n3) passwords\n"), "{user}");
+        assert!(user.ends_with("5) commands\n6) +get\n7) keys\n8) ~k*"), "{user}");
+    }
+}
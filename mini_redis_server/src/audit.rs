@@ -0,0 +1,229 @@
+//! 写命令审计日志
+//!
+//! 设置 `audit-log-file` 后，客户端执行的每条写命令追加一行到审计日志，与 AOF 相互独立：
+//! AOF 用于恢复数据，只保存命令本身；审计日志用于事后追查，记录谁在什么时候改了哪些键。
+//! 每行的格式为：
+//!
+//! ```text
+//! 1339518083.107412 id=7 addr=127.0.0.1:60866 name=app user=default db=0 cmd=set result=ok "foo"
+//! ```
+//!
+//! 依次为时间戳（秒，精确到微秒）、客户端 id、对端地址（不经过网络的会话为空）、客户端名称、
+//! 认证的用户、数据库、命令名、执行结果（`ok` 或 `err`）以及命令涉及的键。
+//! 键的引用与转义方式与 MONITOR 相同。被拒绝执行的写命令（权限不足、只读等）同样记录，结果为 `err`。
+//!
+//! 文件超过 `audit-log-max-size` 时轮转：`file` 改名为 `file.1`，原有的 `file.1` 改名为 `file.2`，
+//! 依此类推，最多保留 `audit-log-max-files` 个旧文件。复制流与 AOF 回放执行的命令不经过客户端，
+//! 不记录。
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::client::{Client, push_quoted};
+
+/// 审计日志设置
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// 日志文件路径，空字符串表示不记录
+    pub file: String,
+    /// 文件达到该大小（字节）后轮转，0 表示不轮转
+    pub max_size: u64,
+    /// 轮转时保留的旧文件数量
+    pub max_files: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { file: String::new(), max_size: 64 << 20, max_files: 4 }
+    }
+}
+
+/// 一条审计记录
+pub struct Entry<'a> {
+    pub client: &'a Client,
+    /// 认证的用户，`None` 表示尚未认证
+    pub user: Option<&'a str>,
+    pub db: usize,
+    pub cmd: &'a str,
+    pub keys: &'a [String],
+    /// 命令是否执行成功
+    pub ok: bool,
+}
+
+impl Entry<'_> {
+    /// 格式化为一行（含换行符）
+    fn line(&self, now: SystemTime) -> String {
+        let now = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let addr = self.client.addr().map(|addr| addr.to_string()).unwrap_or_default();
+        let mut line = format!(
+            "{}.{:06} id={} addr={addr} name={} user={} db={} cmd={} result={}",
+            now.as_secs(),
+            now.subsec_micros(),
+            self.client.id(),
+            self.client.name(),
+            self.user.unwrap_or(""),
+            self.db,
+            self.cmd,
+            if self.ok { "ok" } else { "err" },
+        );
+        for key in self.keys {
+            line.push(' ');
+            push_quoted(&mut line, key);
+        }
+        line.push('\n');
+        line
+    }
+}
+
+#[derive(Default)]
+struct State {
+    config: Config,
+    /// 打开的日志文件，未设置路径或打开失败时为 `None`
+    file: Option<File>,
+    /// 当前文件的大小
+    size: u64,
+}
+
+/// 审计日志
+#[derive(Default)]
+pub struct AuditLog {
+    state: Mutex<State>,
+}
+
+impl AuditLog {
+    /// 应用设置，路径变化时关闭旧文件并打开新文件；打开失败时记录警告并停止记录
+    pub fn configure(&self, config: &Config) {
+        let mut state = self.state.lock().unwrap();
+        if state.config.file != config.file {
+            state.file = None;
+            state.size = 0;
+            if !config.file.is_empty() {
+                match open(&config.file) {
+                    Ok((file, size)) => {
+                        state.file = Some(file);
+                        state.size = size;
+                    }
+                    Err(e) => {
+                        tracing::warn!(error = %e, file = config.file, "failed to open audit log")
+                    }
+                }
+            }
+        }
+        state.config = config.clone();
+    }
+
+    /// 是否正在记录，关闭时调用方可以跳过准备记录的开销
+    pub fn is_enabled(&self) -> bool {
+        self.state.lock().unwrap().file.is_some()
+    }
+
+    /// 追加一条记录，写入失败时记录警告
+    pub fn record(&self, entry: &Entry<'_>) {
+        let line = entry.line(SystemTime::now());
+        let mut state = self.state.lock().unwrap();
+        if state.file.is_none() {
+            return;
+        }
+        if let Err(e) = state.append(line.as_bytes()) {
+            tracing::warn!(error = %e, file = state.config.file, "failed to write audit log");
+        }
+    }
+}
+
+impl State {
+    fn append(&mut self, line: &[u8]) -> io::Result<()> {
+        let max_size = self.config.max_size;
+        if max_size > 0 && self.size > 0 && self.size + line.len() as u64 > max_size {
+            self.rotate()?;
+        }
+        let Some(file) = &mut self.file else { return Ok(()) };
+        file.write_all(line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// 依次改名旧文件，超出保留数量的被覆盖或删除，然后重新打开空文件
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        let path = &self.config.file;
+        let rotated = |i: usize| format!("{path}.{i}");
+        if self.config.max_files == 0 {
+            fs::remove_file(path)?;
+        } else {
+            for i in (1..self.config.max_files).rev() {
+                match fs::rename(rotated(i), rotated(i + 1)) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+            }
+            fs::rename(path, rotated(1))?;
+        }
+        let (file, size) = open(path)?;
+        self.file = Some(file);
+        self.size = size;
+        Ok(())
+    }
+}
+
+/// 以追加方式打开（不存在时创建）日志文件，返回文件及其当前大小
+fn open(path: &str) -> io::Result<(File, u64)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok((file, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Db;
+
+    #[tokio::test]
+    async fn test_record_and_rotate() {
+        let dir = std::env::temp_dir().join(format!("mini-redis-{}-audit", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit.log").to_str().unwrap().to_string();
+        let rotated = |i: usize| fs::read_to_string(format!("{path}.{i}"));
+
+        let db = Db::new();
+        let client = db.clients().register(Some("127.0.0.1:5000".parse().unwrap()));
+        client.set_name("app").unwrap();
+        let keys = ["foo".to_string(), "a b".to_string()];
+        let entry = Entry {
+            client: &client,
+            user: Some("default"),
+            db: 2,
+            cmd: "del",
+            keys: &keys,
+            ok: true,
+        };
+        let line = entry.line(UNIX_EPOCH + std::time::Duration::from_micros(1_500_000));
+        assert_eq!(
+            line,
+            "1.500000 id=1 addr=127.0.0.1:5000 name=app user=default db=2 cmd=del result=ok \
+             \"foo\" \"a b\"\n"
+        );
+
+        let audit = AuditLog::default();
+        assert!(!audit.is_enabled());
+        audit.record(&entry);
+        // 每个文件只放得下两条记录，最多保留两个旧文件
+        let max_size = 2 * entry.line(SystemTime::now()).len() as u64;
+        audit.configure(&Config { file: path.clone(), max_size, max_files: 2 });
+        assert!(audit.is_enabled());
+        for _ in 0..7 {
+            audit.record(&entry);
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+        assert_eq!(rotated(1).unwrap().lines().count(), 2);
+        assert_eq!(rotated(2).unwrap().lines().count(), 2);
+        assert!(rotated(3).is_err());
+
+        audit.configure(&Config::default());
+        assert!(!audit.is_enabled());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,210 @@
+//! 位图模块
+//!
+//! 位图不是独立的类型，而是把字符串值看作位数组：第 0 位是第一个字节的最高位。
+//! 读取超出字符串末尾的位得到 0，写入时按需在末尾补零字节。
+//!
+//! 统计与查找都按字节处理，只有区间首尾不足一个字节的部分逐位处理。
+
+/// BITCOUNT / BITPOS 区间的单位
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BitUnit {
+    /// 区间下标按字节计算（默认）
+    #[default]
+    Byte,
+    /// 区间下标按位计算
+    Bit,
+}
+
+/// BITCOUNT / BITPOS 的区间，下标可以为负数，表示从末尾倒数
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BitRange {
+    pub start: i64,
+    /// `None` 表示到字符串末尾
+    pub end: Option<i64>,
+    pub unit: BitUnit,
+}
+
+impl BitRange {
+    /// 把区间换算为闭区间的位下标，区间为空时返回 `None`
+    fn resolve(&self, byte_len: usize) -> Option<(usize, usize)> {
+        let (len, scale) = match self.unit {
+            BitUnit::Byte => (byte_len as i64, 8),
+            BitUnit::Bit => (byte_len as i64 * 8, 1),
+        };
+        let normalize = |i: i64| if i < 0 { (len + i).max(0) } else { i };
+        let start = normalize(self.start);
+        let end = normalize(self.end.unwrap_or(-1)).min(len - 1);
+        if start > end {
+            return None;
+        }
+        Some(((start * scale) as usize, ((end + 1) * scale - 1) as usize))
+    }
+}
+
+/// BITOP 的运算
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BitOp {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+/// 读取第 `offset` 位，超出末尾的位为 0
+pub fn get_bit(bytes: &[u8], offset: usize) -> bool {
+    bytes.get(offset / 8).is_some_and(|byte| byte & (0x80 >> (offset % 8)) != 0)
+}
+
+/// 设置第 `offset` 位并返回原来的值，字符串不够长时在末尾补零字节
+pub fn set_bit(bytes: &mut Vec<u8>, offset: usize, value: bool) -> bool {
+    let index = offset / 8;
+    if bytes.len() <= index {
+        bytes.resize(index + 1, 0);
+    }
+    let mask = 0x80 >> (offset % 8);
+    let old = bytes[index] & mask != 0;
+    if value {
+        bytes[index] |= mask;
+    } else {
+        bytes[index] &= !mask;
+    }
+    old
+}
+
+/// 统计区间内被设置的位数，`range` 为 `None` 时统计整个字符串
+pub fn count(bytes: &[u8], range: Option<&BitRange>) -> usize {
+    let Some(range) = range else {
+        return bytes.iter().map(|byte| byte.count_ones() as usize).sum();
+    };
+    let Some((start, end)) = range.resolve(bytes.len()) else {
+        return 0;
+    };
+
+    let (first, last) = (start / 8, end / 8);
+    // 首尾字节只保留区间内的位
+    let head = 0xffu8 >> (start % 8);
+    let tail = 0xffu8 << (7 - end % 8);
+    if first == last {
+        return (bytes[first] & head & tail).count_ones() as usize;
+    }
+    let middle: usize = bytes[first + 1..last].iter().map(|byte| byte.count_ones() as usize).sum();
+    middle
+        + (bytes[first] & head).count_ones() as usize
+        + (bytes[last] & tail).count_ones() as usize
+}
+
+/// 返回区间内第一个值为 `bit` 的位的下标，找不到时返回 `None`
+///
+/// 查找 0 且没有指定区间终点时，字符串被看作右侧补满了 0，
+/// 因此全为 1 时返回区间之后的第一位。
+pub fn position(bytes: &[u8], bit: bool, range: Option<&BitRange>) -> Option<usize> {
+    let whole = BitRange { start: 0, end: None, unit: BitUnit::Byte };
+    let range = range.unwrap_or(&whole);
+    let (start, end) = range.resolve(bytes.len())?;
+
+    // 整字节全为 0（找 1 时）或全为 1（找 0 时）可以直接跳过
+    let skip = if bit { 0x00 } else { 0xff };
+    let mut i = start;
+    while i <= end {
+        if i % 8 == 0 && i + 7 <= end && bytes[i / 8] == skip {
+            i += 8;
+            continue;
+        }
+        if get_bit(bytes, i) == bit {
+            return Some(i);
+        }
+        i += 1;
+    }
+    (!bit && range.end.is_none()).then_some(end + 1)
+}
+
+/// 对多个字符串按字节执行位运算，较短的字符串视为右侧补 0；NOT 只使用第一个字符串
+pub fn op(op: BitOp, sources: &[&[u8]]) -> Vec<u8> {
+    if op == BitOp::Not {
+        return sources.first().map_or_else(Vec::new, |src| src.iter().map(|b| !b).collect());
+    }
+
+    let len = sources.iter().map(|src| src.len()).max().unwrap_or(0);
+    (0..len)
+        .map(|i| {
+            let mut bytes = sources.iter().map(|src| src.get(i).copied().unwrap_or(0));
+            let first = bytes.next().unwrap_or(0);
+            bytes.fold(first, |acc, byte| match op {
+                BitOp::And => acc & byte,
+                BitOp::Or => acc | byte,
+                BitOp::Xor => acc ^ byte,
+                BitOp::Not => unreachable!("NOT handled above"),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitOp, BitRange, BitUnit, count, get_bit, op, position, set_bit};
+
+    fn bytes(range: (i64, i64)) -> BitRange {
+        BitRange { start: range.0, end: Some(range.1), unit: BitUnit::Byte }
+    }
+
+    fn bits(range: (i64, i64)) -> BitRange {
+        BitRange { start: range.0, end: Some(range.1), unit: BitUnit::Bit }
+    }
+
+    #[test]
+    fn test_get_and_set_bit() {
+        let mut value = Vec::new();
+        assert!(!set_bit(&mut value, 7, true));
+        assert_eq!(value, [0x01]);
+        assert!(!set_bit(&mut value, 17, true));
+        assert_eq!(value, [0x01, 0x00, 0x40]);
+        assert!(set_bit(&mut value, 7, false));
+        assert_eq!(value, [0x00, 0x00, 0x40]);
+
+        assert!(get_bit(&value, 17));
+        assert!(!get_bit(&value, 16));
+        assert!(!get_bit(&value, 1000));
+    }
+
+    #[test]
+    fn test_count() {
+        let value = b"foobar";
+        assert_eq!(count(value, None), 26);
+        assert_eq!(count(value, Some(&bytes((0, 0)))), 4);
+        assert_eq!(count(value, Some(&bytes((1, 1)))), 6);
+        assert_eq!(count(value, Some(&bytes((-2, -1)))), 7);
+        assert_eq!(count(value, Some(&bytes((5, 1)))), 0);
+        assert_eq!(count(value, Some(&bytes((0, 100)))), 26);
+        // 'f' = 0b0110_0110
+        assert_eq!(count(value, Some(&bits((1, 5)))), 3);
+        assert_eq!(count(value, Some(&bits((5, 30)))), 17);
+        assert_eq!(count(b"", Some(&bytes((0, -1)))), 0);
+    }
+
+    #[test]
+    fn test_position() {
+        let value = [0xff, 0xf0, 0x00];
+        assert_eq!(position(&value, false, None), Some(12));
+        assert_eq!(position(&value, true, Some(&bytes((2, -1)))), None);
+        assert_eq!(position(&value, true, Some(&bits((3, 20)))), Some(3));
+        assert_eq!(position(&value, false, Some(&bits((0, 11)))), None);
+
+        // 全为 1 时，没有终点的查找返回末尾之后的位置
+        let ones = [0xff, 0xff];
+        assert_eq!(position(&ones, false, None), Some(16));
+        assert_eq!(position(&ones, false, Some(&bytes((0, -1)))), None);
+        let open = BitRange { start: 1, end: None, unit: BitUnit::Byte };
+        assert_eq!(position(&ones, false, Some(&open)), Some(16));
+        assert_eq!(position(&[], false, None), None);
+    }
+
+    #[test]
+    fn test_op() {
+        let (a, b): (&[u8], &[u8]) = (&[0b1100, 0xff], &[0b1010]);
+        assert_eq!(op(BitOp::And, &[a, b]), [0b1000, 0x00]);
+        assert_eq!(op(BitOp::Or, &[a, b]), [0b1110, 0xff]);
+        assert_eq!(op(BitOp::Xor, &[a, b]), [0b0110, 0xff]);
+        assert_eq!(op(BitOp::Not, &[b]), [!0b1010u8]);
+        assert!(op(BitOp::Or, &[]).is_empty());
+    }
+}
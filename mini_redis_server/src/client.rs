@@ -0,0 +1,483 @@
+//! 客户端注册表
+//!
+//! 每个连接在创建会话时注册（见 [`Session`](crate::session::Session)），分配一个从 1 开始递增的 id，
+//! 并记录名称、对端地址、创建时间、最近执行的命令及其所在的数据库，以及客户端通过 CLIENT SETINFO
+//! 报告的库名称与版本；会话销毁时自动注销。
+//!
+//! CLIENT LIST 列出全部连接；CLIENT KILL 按 id 或地址找到连接后发出关闭通知，
+//! 网络层等待该通知（[`Client::killed`]）并在收到后断开连接。空闲超时也通过同样的通知关闭连接
+//! （见 [`Clients::close_idle`]）。
+//!
+//! 注册表同时负责 MONITOR 的推送：会话在执行每条命令前把它的时间戳、数据库、
+//! 客户端地址与参数推送给所有处于监视状态的连接（见 [`Clients::feed_monitors`]）。
+//!
+//! 每个客户端有一个[输出缓冲区](crate::output)，记录推送给它、尚未写出的字节数；
+//! 服务器周期性地调用 [`Clients::enforce_output_limits`]，关闭超出限制的客户端。
+
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::sync::{
+    Notify,
+    mpsc::{self, UnboundedReceiver, UnboundedSender},
+};
+
+use crate::{
+    command::ClientKill,
+    db::DbError,
+    output::{ClientClass, OutputBuffer, OutputLimits},
+};
+
+/// 全部已连接客户端的注册表
+#[derive(Clone, Default)]
+pub struct Clients {
+    inner: Arc<Mutex<Registry>>,
+}
+
+#[derive(Default)]
+struct Registry {
+    /// 上一个分配的 id
+    last_id: u64,
+    clients: BTreeMap<u64, Info>,
+}
+
+/// 注册表中一个客户端的信息
+struct Info {
+    addr: Option<SocketAddr>,
+    name: String,
+    /// CLIENT SETINFO 报告的库名称与版本
+    lib_name: String,
+    lib_ver: String,
+    created: Instant,
+    last_active: Instant,
+    db: usize,
+    /// 最近执行的命令名（见 [`Command::name`](crate::command::Command::name)）
+    cmd: &'static str,
+    /// 正在执行命令（包括阻塞等待中的命令），不受空闲超时影响
+    busy: bool,
+    kill: Arc<Notify>,
+    class: ClientClass,
+    output: Arc<OutputBuffer>,
+    /// 输出缓冲区开始超出软限制的时间
+    soft_limit_since: Option<Instant>,
+    /// 处于 MONITOR 状态时推送命令流的发送端
+    monitor: Option<UnboundedSender<String>>,
+}
+
+/// 一个已注册的客户端，销毁时从注册表中注销
+pub struct Client {
+    id: u64,
+    addr: Option<SocketAddr>,
+    kill: Arc<Notify>,
+    output: Arc<OutputBuffer>,
+    clients: Clients,
+}
+
+/// MONITOR 输出的接收端，取出的字节从客户端的输出缓冲区中扣除
+pub struct Monitor {
+    receiver: UnboundedReceiver<String>,
+    output: Arc<OutputBuffer>,
+}
+
+impl Clients {
+    /// 注册一个新客户端，`addr` 为对端地址（不经过网络的会话为 `None`）
+    pub fn register(&self, addr: Option<SocketAddr>) -> Client {
+        let mut registry = self.inner.lock().unwrap();
+        registry.last_id += 1;
+        let id = registry.last_id;
+        let kill = Arc::new(Notify::new());
+        let output = Arc::new(OutputBuffer::default());
+        let now = Instant::now();
+        let info = Info {
+            addr,
+            name: String::new(),
+            lib_name: String::new(),
+            lib_ver: String::new(),
+            created: now,
+            last_active: now,
+            db: 0,
+            cmd: "NULL",
+            busy: false,
+            kill: kill.clone(),
+            class: ClientClass::Normal,
+            output: output.clone(),
+            soft_limit_since: None,
+            monitor: None,
+        };
+        registry.clients.insert(id, info);
+        Client { id, addr, kill, output, clients: self.clone() }
+    }
+
+    /// 已注册的客户端数量
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().clients.len()
+    }
+
+    /// 是否没有任何已注册的客户端
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// CLIENT LIST 的输出：每个客户端一行，按 id 排序
+    pub fn list(&self) -> String {
+        let registry = self.inner.lock().unwrap();
+        registry
+            .clients
+            .iter()
+            .map(|(id, info)| {
+                let addr = info.addr.map(|addr| addr.to_string()).unwrap_or_default();
+                format!(
+                    "id={id} addr={addr} name={} age={} idle={} db={} omem={} cmd={} \
+                     lib-name={} lib-ver={}\n",
+                    info.name,
+                    info.created.elapsed().as_secs(),
+                    info.last_active.elapsed().as_secs(),
+                    info.db,
+                    info.output.pending(),
+                    info.cmd,
+                    info.lib_name,
+                    info.lib_ver,
+                )
+            })
+            .collect()
+    }
+
+    /// 通知所有满足过滤条件的客户端关闭，返回被关闭的数量
+    ///
+    /// `caller` 是执行命令的客户端，过滤条件带 SKIPME 时不关闭它。
+    pub fn kill(&self, filter: &ClientKill, caller: u64) -> usize {
+        let registry = self.inner.lock().unwrap();
+        let targets = registry.clients.iter().filter(|(id, info)| {
+            filter.id.is_none_or(|target| target == **id)
+                && filter
+                    .addr
+                    .as_deref()
+                    .is_none_or(|target| info.addr.is_some_and(|addr| addr.to_string() == target))
+                && !(filter.skip_me && **id == caller)
+        });
+
+        let mut killed = 0;
+        for (_, info) in targets {
+            info.kill.notify_one();
+            killed += 1;
+        }
+        killed
+    }
+
+    /// 关闭空闲超过 `timeout` 的网络连接，返回被关闭的数量
+    ///
+    /// 订阅与监视状态的连接、副本连接以及正在执行（阻塞）命令的连接不受空闲超时影响。
+    pub fn close_idle(&self, timeout: Duration, now: Instant) -> usize {
+        let registry = self.inner.lock().unwrap();
+        let mut closed = 0;
+        for info in registry.clients.values() {
+            let idle = now.saturating_duration_since(info.last_active);
+            if info.addr.is_none()
+                || info.busy
+                || info.class != ClientClass::Normal
+                || info.monitor.is_some()
+                || idle < timeout
+            {
+                continue;
+            }
+            info.kill.notify_one();
+            closed += 1;
+        }
+        closed
+    }
+
+    /// 关闭输出缓冲区超出限制的客户端，返回被关闭的数量
+    ///
+    /// 超出硬限制时立即关闭；超出软限制时开始计时，持续 `soft_seconds` 秒后关闭，
+    /// 期间降到软限制以下则重新计时。
+    pub fn enforce_output_limits(&self, limits: &OutputLimits) -> usize {
+        let now = Instant::now();
+        let mut registry = self.inner.lock().unwrap();
+        let mut closed = 0;
+        for (id, info) in &mut registry.clients {
+            let pending = info.output.pending();
+            if !limits.get(info.class).exceeded(pending, &mut info.soft_limit_since, now) {
+                continue;
+            }
+            tracing::warn!(
+                id,
+                class = %info.class,
+                omem = pending,
+                "client closed for overcoming of output buffer limits"
+            );
+            info.kill.notify_one();
+            closed += 1;
+        }
+        closed
+    }
+
+    /// 把客户端即将执行的命令推送给所有监视者，格式与 Redis 一致：
+    /// `1339518083.107412 [0 127.0.0.1:60866] "keys" "*"`
+    pub fn feed_monitors(&self, client: &Client, db: usize, args: &[String]) {
+        let mut registry = self.inner.lock().unwrap();
+        if registry.clients.values().all(|info| info.monitor.is_none()) {
+            return;
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let addr = client.addr.map_or_else(|| "unknown".to_string(), |addr| addr.to_string());
+        let mut line = format!("{}.{:06} [{db} {addr}]", now.as_secs(), now.subsec_micros());
+        for arg in args {
+            line.push(' ');
+            push_quoted(&mut line, arg);
+        }
+        for info in registry.clients.values_mut() {
+            let Some(monitor) = &info.monitor else { continue };
+            info.output.queued(line.len());
+            // 监视者已退出 MONITOR 状态（RESET）
+            if monitor.send(line.clone()).is_err() {
+                info.output.drained(line.len());
+                info.monitor = None;
+            }
+        }
+    }
+}
+
+/// 以双引号包裹并转义参数，不可打印的字符写成 `\xHH`
+pub(crate) fn push_quoted(out: &mut String, arg: &str) {
+    out.push('"');
+    for byte in arg.bytes() {
+        match byte {
+            b'\\' => out.push_str("\\\\"),
+            b'"' => out.push_str("\\\""),
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            b if b.is_ascii_graphic() || b == b' ' => out.push(b as char),
+            b => out.push_str(&format!("\\x{b:02x}")),
+        }
+    }
+    out.push('"');
+}
+
+impl Client {
+    /// 客户端 id
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// 对端地址，不经过网络的会话为 `None`
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.addr
+    }
+
+    /// 客户端名称，未设置时为空字符串
+    pub fn name(&self) -> String {
+        self.with_info(|info| info.name.clone())
+    }
+
+    /// 设置客户端名称，空字符串表示清除；名称不能包含空格、换行等字符
+    pub fn set_name(&self, name: &str) -> Result<(), DbError> {
+        if name.chars().any(|c| !c.is_ascii_graphic()) {
+            return Err(DbError::InvalidClientName);
+        }
+        self.with_info(|info| info.name = name.to_string());
+        Ok(())
+    }
+
+    /// 记录客户端使用的库的信息，`attr` 为 `lib-name` 或 `lib-ver`，空字符串表示清除；
+    /// 与名称一样不能包含空格、换行等字符
+    pub fn set_lib_info(&self, attr: &str, value: &str) -> Result<(), DbError> {
+        if value.chars().any(|c| !c.is_ascii_graphic()) {
+            return Err(DbError::InvalidClientInfo(attr.to_string()));
+        }
+        self.with_info(|info| match attr {
+            "lib-name" => info.lib_name = value.to_string(),
+            _ => info.lib_ver = value.to_string(),
+        });
+        Ok(())
+    }
+
+    /// 记录即将执行的命令
+    pub fn record_command(&self, cmd: &'static str) {
+        self.with_info(|info| {
+            info.cmd = cmd;
+            info.busy = true;
+            info.last_active = Instant::now();
+        });
+    }
+
+    /// 记录命令执行完毕，空闲时间从此刻起算
+    pub fn command_done(&self) {
+        self.with_info(|info| {
+            info.busy = false;
+            info.last_active = Instant::now();
+        });
+    }
+
+    /// 记录连接切换到的数据库
+    pub fn set_db(&self, db: usize) {
+        self.with_info(|info| info.db = db);
+    }
+
+    /// 设置客户端类别，决定适用的输出缓冲区限制
+    pub fn set_class(&self, class: ClientClass) {
+        self.with_info(|info| info.class = class);
+    }
+
+    /// 客户端的输出缓冲区，推送给客户端的队列在这里记录排队的字节数
+    pub fn output(&self) -> Arc<OutputBuffer> {
+        self.output.clone()
+    }
+
+    /// 进入 MONITOR 状态，返回接收命令流的通道；再次调用时替换之前的通道
+    pub fn monitor(&self) -> Monitor {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.with_info(|info| info.monitor = Some(sender));
+        Monitor { receiver, output: self.output.clone() }
+    }
+
+    /// 等待 CLIENT KILL 的关闭通知
+    ///
+    /// 返回的 future 不借用客户端，可以与会话的其他操作一起放在 `select!` 中；
+    /// 通知先于等待发出时也不会丢失。
+    pub fn killed(&self) -> impl Future<Output = ()> + Send + use<> {
+        let kill = self.kill.clone();
+        async move { kill.notified().await }
+    }
+
+    fn with_info<T>(&self, f: impl FnOnce(&mut Info) -> T) -> T {
+        let mut registry = self.clients.inner.lock().unwrap();
+        f(registry.clients.get_mut(&self.id).expect("registered until dropped"))
+    }
+}
+
+impl Monitor {
+    /// 等待下一行 MONITOR 输出
+    pub async fn recv(&mut self) -> Option<String> {
+        let line = self.receiver.recv().await?;
+        self.output.drained(line.len());
+        Some(line)
+    }
+}
+
+/// 尚未取出的输出不会再写出，从输出缓冲区中扣除
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        self.receiver.close();
+        while let Ok(line) = self.receiver.try_recv() {
+            self.output.drained(line.len());
+        }
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.clients.inner.lock().unwrap().clients.remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Clients;
+    use crate::{
+        command::ClientKill,
+        output::{ClientClass, OutputLimit, OutputLimits},
+    };
+
+    #[test]
+    fn test_register_and_list() {
+        let clients = Clients::default();
+        let a = clients.register(Some("127.0.0.1:5000".parse().unwrap()));
+        let b = clients.register(None);
+        assert_eq!((a.id(), b.id()), (1, 2));
+
+        b.set_name("worker").unwrap();
+        b.record_command("get");
+        b.set_db(3);
+        b.set_lib_info("lib-name", "redis-py").unwrap();
+        b.set_lib_info("lib-ver", "5.0.1").unwrap();
+        assert!(b.set_name("bad name").is_err());
+        assert!(b.set_lib_info("lib-ver", "5.0\n").is_err());
+        assert_eq!(b.name(), "worker");
+        assert_eq!(
+            clients.list(),
+            "id=1 addr=127.0.0.1:5000 name= age=0 idle=0 db=0 omem=0 cmd=NULL lib-name= lib-ver=\n\
+             id=2 addr= name=worker age=0 idle=0 db=3 omem=0 cmd=get lib-name=redis-py \
+             lib-ver=5.0.1\n"
+        );
+
+        drop(a);
+        assert_eq!(clients.len(), 1);
+        assert!(clients.list().starts_with("id=2 "));
+    }
+
+    #[tokio::test]
+    async fn test_feed_monitors() {
+        let clients = Clients::default();
+        let client = clients.register(Some("127.0.0.1:5000".parse().unwrap()));
+        let watcher = clients.register(None);
+        clients.feed_monitors(&client, 0, &["get".into(), "k".into()]);
+
+        let mut monitor = watcher.monitor();
+        let args = ["set".into(), "k".into(), "a \"b\"\n\u{1}".into()];
+        clients.feed_monitors(&client, 2, &args);
+        assert!(watcher.output().pending() > 0);
+        let line = monitor.recv().await.unwrap();
+        let (time, rest) = line.split_once(' ').unwrap();
+        assert!(time.parse::<f64>().is_ok(), "{line}");
+        assert_eq!(rest, r#"[2 127.0.0.1:5000] "set" "k" "a \"b\"\n\x01""#);
+        assert_eq!(watcher.output().pending(), 0);
+
+        // 未取出的输出在接收端销毁时扣除
+        clients.feed_monitors(&client, 0, &["ping".into()]);
+        drop(monitor);
+        assert_eq!(watcher.output().pending(), 0);
+        clients.feed_monitors(&client, 0, &["ping".into()]);
+        assert_eq!(watcher.output().pending(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_enforce_output_limits() {
+        let clients = Clients::default();
+        let normal = clients.register(None);
+        let subscriber = clients.register(None);
+        subscriber.set_class(ClientClass::PubSub);
+        normal.output().queued(1000);
+        subscriber.output().queued(1000);
+
+        let mut limits = OutputLimits::default();
+        assert_eq!(clients.enforce_output_limits(&limits), 0);
+        limits.pubsub = OutputLimit { hard: 1000, soft: 0, soft_seconds: 0 };
+        assert_eq!(clients.enforce_output_limits(&limits), 1);
+        subscriber.killed().await;
+        drop(subscriber);
+
+        // 软限制的持续时间为 0 时第一次检查就关闭
+        limits.normal = OutputLimit { hard: 0, soft: 500, soft_seconds: 0 };
+        normal.output().drained(400);
+        assert!(clients.list().contains(" omem=600 "));
+        assert_eq!(clients.enforce_output_limits(&limits), 1);
+        normal.killed().await;
+    }
+
+    #[tokio::test]
+    async fn test_kill() {
+        let clients = Clients::default();
+        let a = clients.register(Some("127.0.0.1:5000".parse().unwrap()));
+        let b = clients.register(Some("127.0.0.1:5001".parse().unwrap()));
+
+        let by_addr = ClientKill { addr: Some("127.0.0.1:5001".into()), ..ClientKill::default() };
+        assert_eq!(clients.kill(&by_addr, a.id()), 1);
+        b.killed().await;
+
+        let everyone = ClientKill { skip_me: true, ..ClientKill::default() };
+        assert_eq!(clients.kill(&everyone, a.id()), 1);
+        let by_id = ClientKill { id: Some(a.id()), ..ClientKill::default() };
+        assert_eq!(clients.kill(&by_id, a.id()), 1);
+        a.killed().await;
+        assert_eq!(clients.kill(&by_id, b.id()), 1);
+        let missing = ClientKill { id: Some(99), ..ClientKill::default() };
+        assert_eq!(clients.kill(&missing, a.id()), 0);
+    }
+}
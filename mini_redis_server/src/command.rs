@@ -1,45 +1,2493 @@
 //! 命令解析模块
 //!
 //! 负责从字符串解析出 Redis 命令的抽象结构。
-//! 当前仅支持 GET / SET / Unknown 三类命令。
+//! 当前支持字符串（GET / SET）、哈希（HSET / HGET / HINCRBY 等）、集合（SADD / SREM 等）
+//! 有序集合（ZADD / ZRANGE 等）、地理位置（GEOADD / GEOSEARCH 等）与流（XADD / XRANGE 等）命令，
+//! 无法识别的命令、参数个数不符（见 [`arity`]）或格式错误的参数解析为 [`ParseError`]，
+//! 其 `Display` 输出即为回复给客户端的错误信息。
 //!
 //! 在未来可扩展为 RESP 协议解析层。
 
+use std::{fmt, str::FromStr};
+
+use crate::{
+    bitmap::{BitOp, BitRange, BitUnit},
+    db::{ExpireFlags, unix_time_ms},
+    geo::{Order, Origin, Query, Shape, Unit},
+    handler, inline,
+    sorted_set::{AddFlags, LexRange, ScoreRange},
+    stream::{ClaimOptions, IdRange, IdSpec, StreamId},
+};
+
+/// HOTKEYS 不带 COUNT 时返回的键数
+const DEFAULT_HOTKEYS: usize = 10;
+/// SCAN 不带 COUNT 时每次大约返回的键数
+const DEFAULT_SCAN_COUNT: usize = 10;
+
+/// SCAN 的参数
+#[derive(Clone, PartialEq, Debug)]
+pub struct Scan {
+    /// 上一次返回的游标，首次为 0
+    pub cursor: u64,
+    /// MATCH 的 glob 模式
+    pub pattern: Option<String>,
+    /// 每次大约遍历的键数
+    pub count: usize,
+    /// TYPE 指定的值类型
+    pub key_type: Option<String>,
+}
+
+/// SET 的选项：`[GET] [EX|PX|EXAT|PXAT <time> | KEEPTTL]`
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct SetOptions {
+    /// 设置的过期时间，与 `keep_ttl` 都没有给出时清除原有的过期时间
+    pub expiry: Option<Expiry>,
+    /// KEEPTTL：保留原有的过期时间
+    pub keep_ttl: bool,
+    /// GET：返回旧值
+    pub get: bool,
+}
+
+/// MIGRATE 的参数
+#[derive(Clone, PartialEq, Debug)]
+pub struct Migrate {
+    pub host: String,
+    pub port: u16,
+    pub keys: Vec<String>,
+    /// 目标数据库编号
+    pub db: u32,
+    /// 与目标节点通信的超时（毫秒）
+    pub timeout: u64,
+    /// 迁移后保留本地的键
+    pub copy: bool,
+    /// 覆盖目标节点上已存在的键
+    pub replace: bool,
+}
+
+/// CLIENT KILL 的过滤条件，给出的条件需同时满足
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct ClientKill {
+    pub id: Option<u64>,
+    /// 对端地址，形如 `ip:port`
+    pub addr: Option<String>,
+    /// 不关闭执行命令的连接自身（SKIPME，默认为 yes）
+    pub skip_me: bool,
+}
+
+/// HELLO 的参数：`HELLO [<protover> [AUTH <username> <password>] [SETNAME <name>]]`
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Hello {
+    /// 切换到的协议版本，`None` 表示保持不变
+    pub protover: Option<i64>,
+    /// AUTH 给出的用户名与密码
+    pub auth: Option<(String, String)>,
+    /// SETNAME 给出的连接名称
+    pub name: Option<String>,
+}
+
+/// XREAD 的参数
+#[derive(Clone, PartialEq, Debug)]
+pub struct XRead {
+    /// 每个流最多返回的消息数量
+    pub count: Option<usize>,
+    /// BLOCK 的超时（毫秒），0 表示一直等待；`None` 表示不阻塞
+    pub block: Option<u64>,
+    /// 读取的流及起始 ID（不含），`None` 表示 `$`，即只读取此后添加的消息
+    pub streams: Vec<(String, Option<StreamId>)>,
+}
+
+/// XREADGROUP 的参数
+#[derive(Clone, PartialEq, Debug)]
+pub struct XReadGroup {
+    pub group: String,
+    pub consumer: String,
+    /// 每个流最多返回的消息数量
+    pub count: Option<usize>,
+    /// BLOCK 的超时（毫秒），0 表示一直等待；`None` 表示不阻塞
+    pub block: Option<u64>,
+    /// 读取的新消息不登记到待确认列表
+    pub noack: bool,
+    /// 读取的流及起始 ID（不含），`None` 表示 `>`，即读取从未投递给本组的新消息
+    pub streams: Vec<(String, Option<StreamId>)>,
+}
+
+/// GEOSEARCH 的参数
+#[derive(Clone, PartialEq, Debug)]
+pub struct GeoSearch {
+    pub query: Query,
+    /// 搜索范围使用的单位，返回的距离也使用该单位
+    pub unit: Unit,
+    /// 返回成员的经纬度
+    pub with_coord: bool,
+    /// 返回成员到中心的距离
+    pub with_dist: bool,
+    /// 返回成员的 geohash 分值
+    pub with_hash: bool,
+}
+
+/// XPENDING 扩展形式的参数
+#[derive(Clone, PartialEq, Debug)]
+pub struct XPendingRange {
+    /// 只返回空闲时间（毫秒）不小于该值的消息
+    pub min_idle: Option<u64>,
+    pub range: IdRange,
+    pub count: usize,
+    /// 只返回该消费者名下的消息
+    pub consumer: Option<String>,
+}
+
+/// 过期时间参数
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Expiry {
+    /// 相对时间（秒），对应 EX / EXPIRE
+    Seconds(i64),
+    /// 相对时间（毫秒），对应 PX / PEXPIRE
+    Millis(i64),
+    /// Unix 时间戳（秒），对应 EXAT
+    UnixSeconds(i64),
+    /// Unix 时间戳（毫秒），对应 PXAT / PEXPIREAT
+    UnixMillis(i64),
+}
+
+impl Expiry {
+    /// 换算为 Unix 毫秒时间戳，负数时间视为已经过期
+    pub fn deadline_ms(self, now_ms: u64) -> u64 {
+        let now = now_ms as i64;
+        let at = match self {
+            Expiry::Seconds(secs) => now.saturating_add(secs.saturating_mul(1000)),
+            Expiry::Millis(ms) => now.saturating_add(ms),
+            Expiry::UnixSeconds(secs) => secs.saturating_mul(1000),
+            Expiry::UnixMillis(ms) => ms,
+        };
+        at.max(0) as u64
+    }
+
+    /// 与 [`Expiry::deadline_ms`] 相同，换算过程溢出时返回 `None`
+    pub fn checked_deadline_ms(self, now_ms: u64) -> Option<u64> {
+        let now = now_ms as i64;
+        let at = match self {
+            Expiry::Seconds(secs) => now.checked_add(secs.checked_mul(1000)?)?,
+            Expiry::Millis(ms) => now.checked_add(ms)?,
+            Expiry::UnixSeconds(secs) => secs.checked_mul(1000)?,
+            Expiry::UnixMillis(ms) => ms,
+        };
+        Some(at.max(0) as u64)
+    }
+}
+
+/// 命令解析失败的原因，`Display` 输出与 Redis 相同的错误回复
+#[derive(Clone, PartialEq, Debug)]
+pub enum ParseError {
+    /// 不存在的命令：命令名、参数
+    UnknownCommand(String, Vec<String>),
+    /// 参数个数不符合要求：命令名（见 [`Command::name`]）
+    WrongArity(String),
+    /// 参数不是整数或超出范围
+    InvalidInteger,
+    /// 数量参数为负数
+    NegativeCount,
+    /// 位偏移量不是整数或超出 2^32 位
+    InvalidBitOffset,
+    /// 过期时间不是正数或换算为截止时间时溢出：命令名
+    InvalidExpireTime(String),
+    /// 参数不是合法的浮点数
+    InvalidFloat,
+    /// 参数不是合法的消息 ID
+    InvalidStreamId,
+    /// SCAN 的游标不是无符号整数
+    InvalidCursor,
+    /// 不存在的子命令：命令名、子命令名
+    UnknownSubcommand(String, String),
+    /// 无法识别的选项或参数组合
+    Syntax,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            ParseError::UnknownCommand(name, args) => {
+                write!(f, "ERR unknown command '{name}', with args beginning with: ")?;
+                return args.iter().try_for_each(|arg| write!(f, "'{arg}' "));
+            }
+            ParseError::WrongArity(name) => {
+                return write!(f, "ERR wrong number of arguments for '{name}' command");
+            }
+            ParseError::InvalidInteger => "ERR value is not an integer or out of range",
+            ParseError::NegativeCount => "ERR value is out of range, must be positive",
+            ParseError::InvalidBitOffset => "ERR bit offset is not an integer or out of range",
+            ParseError::InvalidExpireTime(name) => {
+                return write!(f, "ERR invalid expire time in '{name}' command");
+            }
+            ParseError::InvalidFloat => "ERR value is not a valid float",
+            ParseError::InvalidStreamId => {
+                "ERR Invalid stream ID specified as stream command argument"
+            }
+            ParseError::InvalidCursor => "ERR invalid cursor",
+            ParseError::UnknownSubcommand(name, sub) => {
+                let name = name.to_ascii_uppercase();
+                return write!(f, "ERR unknown subcommand '{sub}'. Try {name} HELP.");
+            }
+            ParseError::Syntax => "ERR syntax error",
+        };
+        write!(f, "{msg}")
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// 命令的参数个数要求（含命令名，子命令还包括子命令名），`name` 为小写的命令名
+/// （见 [`Command::name`]），不存在的命令返回 `None`
+///
+/// 与 Redis 的约定相同：正数表示参数个数必须相等，负数表示至少为其绝对值。
+/// 各命令的要求登记在命令表中（见 [`handler::spec`]），带子命令的命令本身没有登记时
+/// 至少需要两个参数（如 CONFIG），登记了的可以不带子命令执行（如 COMMAND）。
+pub fn arity(name: &str) -> Option<i64> {
+    match (name, handler::spec(name)) {
+        ("slaveof", _) => arity("replicaof"),
+        (_, Some(spec)) => Some(spec.arity),
+        (name, None) => has_subcommands(name).then_some(-2),
+    }
+}
+
 /// 代表 mini-redis 支持的命令
-#[derive(PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub enum Command {
     /// GET <key>: 获取键的值
     Get(String),
-    /// SET <key> <value>: 设置键的值
+    /// SET <key> <value>: 设置键的值，清除原有的过期时间
     Set(String, String),
-    /// 未知命令
-    Unknown,
+    /// SET <key> <value> [GET] [EX|PX|EXAT|PXAT <time> | KEEPTTL]: 带选项写入字符串值
+    SetWith(String, String, SetOptions),
+    /// GETSET <key> <value>: 写入字符串值并返回旧值，清除原有的过期时间
+    GetSet(String, String),
+    /// INCR <key>: 将键的整数值加 1
+    Incr(String),
+    /// INCRBY <key> <increment>: 将键的整数值加上给定的增量
+    IncrBy(String, i64),
+    /// SETBIT <key> <offset> <0|1>: 设置字符串中的一位，返回原来的值
+    SetBit(String, usize, bool),
+    /// GETBIT <key> <offset>: 读取字符串中的一位
+    GetBit(String, usize),
+    /// BITCOUNT <key> [<start> <end> [BYTE|BIT]]: 统计被设置的位数
+    BitCount(String, Option<BitRange>),
+    /// BITPOS <key> <0|1> [<start> [<end> [BYTE|BIT]]]: 查找第一个值为 0 或 1 的位
+    BitPos(String, bool, Option<BitRange>),
+    /// BITOP <AND|OR|XOR|NOT> <destkey> <key> [<key> ...]: 位运算，结果写入 destkey
+    BitOp(BitOp, String, Vec<String>),
+    /// HSET <key> <field> <value> [<field> <value> ...]: 设置哈希字段
+    HSet(String, Vec<(String, String)>),
+    /// HGET <key> <field>: 获取哈希字段的值
+    HGet(String, String),
+    /// HINCRBY <key> <field> <increment>: 哈希字段整数自增
+    HIncrBy(String, String, i64),
+    /// HINCRBYFLOAT <key> <field> <increment>: 哈希字段浮点数自增
+    HIncrByFloat(String, String, f64),
+    /// HRANDFIELD <key> [<count> [WITHVALUES]]: 随机获取哈希字段
+    HRandField(String, Option<i64>, bool),
+    /// SADD <key> <member> [<member> ...]: 向集合添加成员
+    SAdd(String, Vec<String>),
+    /// SREM <key> <member> [<member> ...]: 从集合移除成员
+    SRem(String, Vec<String>),
+    /// SMEMBERS <key>: 获取集合全部成员
+    SMembers(String),
+    /// SISMEMBER <key> <member>: 判断成员是否在集合中
+    SIsMember(String, String),
+    /// SCARD <key>: 获取集合成员数量
+    SCard(String),
+    /// SMISMEMBER <key> <member> [<member> ...]: 判断多个成员是否在集合中
+    SMIsMember(String, Vec<String>),
+    /// SPOP <key> [<count>]: 随机移除并返回成员
+    SPop(String, Option<usize>),
+    /// SRANDMEMBER <key> [<count>]: 随机获取集合成员
+    SRandMember(String, Option<i64>),
+    /// SINTER <key> [<key> ...]: 求多个集合的交集
+    SInter(Vec<String>),
+    /// SUNION <key> [<key> ...]: 求多个集合的并集
+    SUnion(Vec<String>),
+    /// SDIFF <key> [<key> ...]: 求第一个集合与其余集合的差集
+    SDiff(Vec<String>),
+    /// SINTERSTORE <destination> <key> [<key> ...]: 求交集并保存
+    SInterStore(String, Vec<String>),
+    /// SUNIONSTORE <destination> <key> [<key> ...]: 求并集并保存
+    SUnionStore(String, Vec<String>),
+    /// SDIFFSTORE <destination> <key> [<key> ...]: 求差集并保存
+    SDiffStore(String, Vec<String>),
+    /// SINTERCARD <numkeys> <key> [<key> ...] [LIMIT <limit>]: 求交集的成员数量
+    SInterCard(Vec<String>, usize),
+    /// ZADD <key> [NX|XX] [GT|LT] [CH] <score> <member> [<score> <member> ...]: 写入有序集合成员
+    ZAdd(String, AddFlags, Vec<(f64, String)>),
+    /// ZSCORE <key> <member>: 获取有序集合成员的分值
+    ZScore(String, String),
+    /// ZCARD <key>: 获取有序集合成员数量
+    ZCard(String),
+    /// ZRANGE <key> <start> <stop> [REV] [WITHSCORES]: 按排名范围获取成员，
+    /// 参数依次为键、起止排名、是否逆序、是否返回分值
+    ZRange(String, i64, i64, bool, bool),
+    /// ZRANGEBYSCORE <key> <min> <max> [WITHSCORES] [LIMIT <offset> <count>]: 按分值范围获取成员，
+    /// 参数依次为键、分值范围、是否返回分值、LIMIT 的 offset 与 count
+    ZRangeByScore(String, ScoreRange, bool, i64, i64),
+    /// ZRANGEBYLEX <key> <min> <max> [LIMIT <offset> <count>]: 按字典序范围获取成员
+    ZRangeByLex(String, LexRange, i64, i64),
+    /// ZRANK <key> <member>: 获取成员按分值从小到大的排名
+    ZRank(String, String),
+    /// ZINCRBY <key> <increment> <member>: 有序集合成员分值自增
+    ZIncrBy(String, f64, String),
+    /// ZPOPMIN <key> [<count>]: 弹出分值最小的成员
+    ZPopMin(String, Option<usize>),
+    /// ZPOPMAX <key> [<count>]: 弹出分值最大的成员
+    ZPopMax(String, Option<usize>),
+    /// BZPOPMIN <key> [<key> ...] <timeout>: ZPOPMIN 的阻塞版本，timeout 以秒为单位，0 表示一直等待
+    BZPopMin(Vec<String>, f64),
+    /// BZPOPMAX <key> [<key> ...] <timeout>: ZPOPMAX 的阻塞版本
+    BZPopMax(Vec<String>, f64),
+    /// GEOADD <key> [NX|XX] [CH] <longitude> <latitude> <member> [...]: 写入成员的位置
+    GeoAdd(String, AddFlags, Vec<(f64, f64, String)>),
+    /// GEODIST <key> <member1> <member2> [M|KM|FT|MI]: 返回两个成员之间的距离
+    GeoDist(String, String, String, Unit),
+    /// GEOPOS <key> [<member> ...]: 返回成员的经纬度
+    GeoPos(String, Vec<String>),
+    /// GEOSEARCH <key> <FROMMEMBER <member>|FROMLONLAT <lon> <lat>>
+    /// <BYRADIUS <radius>|BYBOX <width> <height>> <unit> [ASC|DESC] [COUNT <count> [ANY]]
+    /// [WITHCOORD] [WITHDIST] [WITHHASH]: 返回位于范围内的成员
+    GeoSearch(String, GeoSearch),
+    /// XADD <key> <*|ms-*|ms-seq> <field> <value> [<field> <value> ...]: 向流追加一条消息
+    XAdd(String, IdSpec, Vec<(String, String)>),
+    /// XLEN <key>: 获取流的消息数量
+    XLen(String),
+    /// XRANGE <key> <start> <end> [COUNT <count>]: 按 ID 区间获取流中的消息
+    XRange(String, IdRange, Option<usize>),
+    /// XREAD [COUNT <count>] [BLOCK <ms>] STREAMS <key> [<key> ...] <id> [<id> ...]:
+    /// 读取多个流中指定 ID 之后的消息，带 BLOCK 时在没有新消息时等待
+    XRead(XRead),
+    /// XGROUP CREATE <key> <group> <id|$> [MKSTREAM]: 创建消费者组，`None` 表示 `$`
+    XGroupCreate(String, String, Option<StreamId>, bool),
+    /// XGROUP SETID <key> <group> <id|$>: 设置消费者组的最后投递 ID
+    XGroupSetId(String, String, Option<StreamId>),
+    /// XGROUP DESTROY <key> <group>: 删除消费者组
+    XGroupDestroy(String, String),
+    /// XGROUP CREATECONSUMER <key> <group> <consumer>: 在消费者组中创建消费者
+    XGroupCreateConsumer(String, String, String),
+    /// XREADGROUP GROUP <group> <consumer> [COUNT <count>] [BLOCK <ms>] [NOACK]
+    /// STREAMS <key> [<key> ...] <id> [<id> ...]: 以消费者组的身份读取消息
+    XReadGroup(XReadGroup),
+    /// XACK <key> <group> <id> [<id> ...]: 确认消息，返回确认的数量
+    XAck(String, String, Vec<StreamId>),
+    /// XPENDING <key> <group> [[IDLE <ms>] <start> <end> <count> [<consumer>]]:
+    /// 查看待确认列表，不带范围时返回汇总信息
+    XPending(String, String, Option<XPendingRange>),
+    /// XCLAIM <key> <group> <consumer> <min-idle-time> <id> [<id> ...] [IDLE <ms>] [TIME <ms>]
+    /// [RETRYCOUNT <count>] [FORCE] [JUSTID] [LASTID <id>]: 把待确认消息转移给另一个消费者
+    XClaim(String, String, String, Vec<StreamId>, ClaimOptions),
+    /// KEYS <pattern>: 返回所有匹配 glob 模式的键
+    Keys(String),
+    /// SCAN <cursor> [MATCH <pattern>] [COUNT <count>] [TYPE <type>]: 从游标开始遍历一部分键，
+    /// 返回下一个游标与遍历到的键
+    Scan(Scan),
+    /// PUBLISH <channel> <message>: 向频道发布消息
+    Publish(String, String),
+    /// SUBSCRIBE <channel> [<channel> ...]: 订阅频道（仅限会话中使用）
+    Subscribe(Vec<String>),
+    /// UNSUBSCRIBE [<channel> ...]: 退订频道，不带参数时退订全部频道
+    Unsubscribe(Vec<String>),
+    /// PSUBSCRIBE <pattern> [<pattern> ...]: 按 glob 模式订阅频道
+    PSubscribe(Vec<String>),
+    /// PUNSUBSCRIBE [<pattern> ...]: 退订模式，不带参数时退订全部模式
+    PUnsubscribe(Vec<String>),
+    /// PUBSUB CHANNELS [<pattern>]: 列出当前有订阅者的频道
+    PubSubChannels(Option<String>),
+    /// PUBSUB NUMSUB [<channel> ...]: 返回各频道的订阅者数量
+    PubSubNumSub(Vec<String>),
+    /// PUBSUB NUMPAT: 返回被订阅的模式数量
+    PubSubNumPat,
+    /// SPUBLISH <shardchannel> <message>: 向分片频道发布消息
+    SPublish(String, String),
+    /// SSUBSCRIBE <shardchannel> [<shardchannel> ...]: 订阅分片频道，频道须属于同一个哈希槽
+    /// （仅限会话中使用）
+    SSubscribe(Vec<String>),
+    /// SUNSUBSCRIBE [<shardchannel> ...]: 退订分片频道，不带参数时退订全部分片频道
+    SUnsubscribe(Vec<String>),
+    /// PUBSUB SHARDCHANNELS [<pattern>]: 列出当前有订阅者的分片频道
+    PubSubShardChannels(Option<String>),
+    /// PUBSUB SHARDNUMSUB [<shardchannel> ...]: 返回各分片频道的订阅者数量
+    PubSubShardNumSub(Vec<String>),
+    /// BGREWRITEAOF: 在后台重写 AOF 文件
+    BgRewriteAof,
+    /// SAVE: 同步保存 RDB 快照
+    Save,
+    /// BGSAVE: 在后台保存 RDB 快照
+    BgSave,
+    /// LASTSAVE: 最近一次成功保存快照的 Unix 时间（秒）
+    LastSave,
+    /// EXPIRE <key> <seconds> / PEXPIRE <key> <ms> / EXPIREAT <key> <unix-secs> /
+    /// PEXPIREAT <key> <unix-ms> [NX|XX|GT|LT]: 设置过期时间
+    Expire(String, Expiry, ExpireFlags),
+    /// PERSIST <key>: 清除过期时间
+    Persist(String),
+    /// TTL <key>: 剩余生存时间（秒），键不存在返回 -2，没有过期时间返回 -1
+    Ttl(String),
+    /// PTTL <key>: 剩余生存时间（毫秒）
+    PTtl(String),
+    /// EXPIRETIME <key>: 过期时间的 Unix 时间戳（秒），键不存在返回 -2，没有过期时间返回 -1
+    ExpireTime(String),
+    /// PEXPIRETIME <key>: 过期时间的 Unix 时间戳（毫秒）
+    PExpireTime(String),
+    /// REPLICAOF <host> <port>: 成为指定主节点的副本；REPLICAOF NO ONE 恢复为主节点（别名 SLAVEOF）
+    ReplicaOf(Option<(String, u16)>),
+    /// ROLE: 返回本节点的复制角色与状态
+    Role,
+    /// REPLCONF <option> <value> ...: 副本在同步前告知主节点的配置
+    ReplConf(Vec<String>),
+    /// PSYNC <replid> <offset>: 副本请求同步
+    Psync(String, i64),
+    /// SYNC: 旧版的全量同步请求
+    Sync,
+    /// SENTINEL MONITOR <name> <ip> <port> <quorum>: 开始监控主节点
+    SentinelMonitor(String, String, u16, usize),
+    /// SENTINEL REMOVE <name>: 停止监控主节点
+    SentinelRemove(String),
+    /// SENTINEL SET <name> <option> <value> [<option> <value> ...]: 修改监控参数
+    SentinelSet(String, Vec<(String, String)>),
+    /// SENTINEL MASTERS: 全部主节点的状态
+    SentinelMasters,
+    /// SENTINEL MASTER <name>: 一个主节点的状态
+    SentinelMaster(String),
+    /// SENTINEL REPLICAS <name>: 主节点的副本（别名 SENTINEL SLAVES）
+    SentinelReplicas(String),
+    /// SENTINEL SENTINELS <name>: 监控同一主节点的其他哨兵
+    SentinelSentinels(String),
+    /// SENTINEL GET-MASTER-ADDR-BY-NAME <name>: 主节点的当前地址
+    SentinelGetMasterAddr(String),
+    /// SENTINEL IS-MASTER-DOWN-BY-ADDR <ip> <port> <epoch> <runid>: 询问主节点是否下线并请求投票
+    SentinelIsMasterDownByAddr(String, u16, u64, String),
+    /// SENTINEL FAILOVER <name>: 立即执行一次故障转移
+    SentinelFailover(String),
+    /// SENTINEL MYID: 本哨兵的 id
+    SentinelMyId,
+    /// DEL <key> [<key> ...]: 删除键，返回实际删除的数量
+    Del(Vec<String>),
+    /// UNLINK <key> [<key> ...]: 删除键并在后台释放值，返回实际删除的数量
+    Unlink(Vec<String>),
+    /// OBJECT ENCODING <key>: 值的内部编码
+    ObjectEncoding(String),
+    /// OBJECT REFCOUNT <key>: 值的引用计数
+    ObjectRefCount(String),
+    /// OBJECT IDLETIME <key>: 键自最近一次访问以来的空闲秒数
+    ObjectIdleTime(String),
+    /// DUMP <key>: 把键的值序列化为载荷，键不存在时返回空
+    Dump(String),
+    /// RESTORE <key> <ttl> <payload> [REPLACE] [ABSTTL]: 用 DUMP 载荷恢复键，
+    /// ttl 为 0 表示不过期，带 ABSTTL 时 ttl 是 Unix 毫秒时间戳
+    Restore(String, u64, String, bool, bool),
+    /// MIGRATE <host> <port> <key>|"" <db> <timeout> [COPY] [REPLACE] [KEYS <key> ...]:
+    /// 把键迁移到另一个节点
+    Migrate(Migrate),
+    /// WAIT <numreplicas> <timeout>: 等待指定数量的副本确认此前的写命令，超时（毫秒）为 0 时一直等待
+    Wait(usize, u64),
+    /// CONFIG GET <pattern>: 读取名称匹配 glob 模式的配置参数
+    ConfigGet(String),
+    /// CONFIG SET <parameter> <value> [<parameter> <value> ...]: 在运行时修改配置参数
+    ConfigSet(Vec<(String, String)>),
+    /// CONFIG RESETSTAT: 清零 INFO 中累计的统计
+    ConfigResetStat,
+    /// LATENCY LATEST: 每个事件的最近一次与最大延迟
+    LatencyLatest,
+    /// LATENCY HISTORY <event>: 一个事件的延迟样本
+    LatencyHistory(String),
+    /// LATENCY RESET [<event> ...]: 清除指定事件（不指定时清除全部事件）的延迟记录
+    LatencyReset(Vec<String>),
+    /// DEBUG SLEEP <seconds>: 等待给定时长后回复（以下 DEBUG 子命令见 `fault` 模块）
+    DebugSleep(f64),
+    /// DEBUG DELAY <command|*> <ms>: 之后每次执行该命令前先等待，0 表示取消
+    DebugDelay(String, u64),
+    /// DEBUG DROP <command|*> [<count>]: 之后 count 次收到该命令时直接关闭连接
+    DebugDrop(String, u64),
+    /// DEBUG CONTEND <ms>: 对当前数据库的全部分片加写锁并保持给定时长
+    DebugContend(u64),
+    /// DEBUG FAULTS: 列出生效的故障注入设置
+    DebugFaults,
+    /// DEBUG RESET: 清除全部故障注入设置
+    DebugReset,
+    /// INFO [<section>]: 服务器状态，不带参数时输出全部分节
+    Info(Option<String>),
+    /// DBSIZE: 键的数量
+    DbSize,
+    /// RANDOMKEY: 随机返回一个键
+    RandomKey,
+    /// BIGKEYS: 当前数据库中每种类型最大的键
+    BigKeys,
+    /// HOTKEYS [COUNT <count>]: 当前数据库中访问频率最高的键，默认 10 个
+    HotKeys(usize),
+    /// EXPORT: 当前数据库中的全部键，每个键一行 JSON
+    Export,
+    /// IMPORT <json> [<json> ...]: 写入 EXPORT 格式的记录，覆盖同名的键
+    Import(Vec<String>),
+    /// TOUCH <key> [<key> ...]: 更新键的访问时间，返回存在的键的数量
+    Touch(Vec<String>),
+    /// FLUSHDB [ASYNC|SYNC]: 清空当前数据库，带 ASYNC 时在后台释放内存
+    FlushDb(bool),
+    /// FLUSHALL [ASYNC|SYNC]: 清空所有数据库
+    FlushAll(bool),
+    /// SELECT <index>: 切换当前连接使用的数据库
+    Select(usize),
+    /// MOVE <key> <db>: 把键移动到另一个数据库
+    Move(String, usize),
+    /// SWAPDB <index1> <index2>: 交换两个数据库的内容
+    SwapDb(usize, usize),
+    /// AUTH [<username>] <password>: 以指定用户身份认证，省略用户名时为 default 用户
+    Auth(Option<String>, String),
+    /// ACL SETUSER <username> [<rule> ...]: 创建或修改用户
+    AclSetUser(String, Vec<String>),
+    /// ACL GETUSER <username>: 查看用户的规则
+    AclGetUser(String),
+    /// ACL LIST: 以 ACL 规则的形式列出所有用户
+    AclList,
+    /// ACL WHOAMI: 当前连接认证的用户名
+    AclWhoAmI,
+    /// CLIENT ID: 当前连接的 id
+    ClientId,
+    /// CLIENT SETNAME <name>: 设置当前连接的名称
+    ClientSetName(String),
+    /// CLIENT SETINFO <LIB-NAME | LIB-VER> <value>: 记录客户端使用的库的名称或版本，属性名为小写
+    ClientSetInfo(String, String),
+    /// CLIENT GETNAME: 当前连接的名称，未设置时返回空
+    ClientGetName,
+    /// CLIENT LIST: 列出全部连接
+    ClientList,
+    /// CLIENT KILL <ID id | ADDR ip:port | SKIPME yes/no> ...: 关闭满足条件的连接，返回关闭的数量
+    ClientKill(ClientKill),
+    /// CLIENT KILL <ip:port>: 旧版写法，关闭指定地址的连接（包括自身）
+    ClientKillAddr(String),
+    /// MONITOR: 进入监视状态，实时接收服务器执行的每条命令
+    Monitor,
+    /// HELLO [<protover> [AUTH <username> <password>] [SETNAME <name>]]: 在一次往返中完成认证、
+    /// 设置名称并切换协议版本（2 或 3），返回服务器信息
+    Hello(Hello),
+    /// PING [<message>]: 回复 PONG，带参数时原样返回参数
+    Ping(Option<String>),
+    /// ECHO <message>: 原样返回参数
+    Echo(String),
+    /// QUIT: 回复 OK 后关闭连接
+    Quit,
+    /// RESET: 将连接恢复到初始状态（退出订阅与监视、清除只读标记、选中 0 号数据库、协议版本与用户恢复默认）
+    Reset,
+    /// READONLY: 将连接标记为只读，此后拒绝它的写命令
+    ReadOnly,
+    /// READWRITE: 清除连接的只读标记
+    ReadWrite,
+    /// <command> HELP: 列出带子命令的命令的全部子命令，值为 HELP 子命令的命令名（如 `config|help`）
+    Help(&'static str),
+    /// COMMAND: 全部命令的详细信息
+    Commands,
+    /// COMMAND COUNT: 命令的数量
+    CommandCount,
+    /// COMMAND INFO [<name> ...]: 指定命令的详细信息，不指定时为全部命令
+    CommandInfo(Vec<String>),
+    /// COMMAND DOCS [<name> ...]: 指定命令的文档，不指定时为全部命令
+    CommandDocs(Vec<String>),
 }
 
 impl Command {
-    /// 从用户输入（如 `SET foo bar`）解析出命令结构
-    pub fn parse(input: &str) -> Self {
-        let parts: Vec<_> = input.trim().split_whitespace().collect();
+    /// 从用户输入（如 `SET greeting "hello world"`）解析出命令结构
+    ///
+    /// 参数的切分规则与内联协议相同（见 [`inline::split_args`]），支持引号与转义；
+    /// 引号不匹配时返回语法错误。
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let args = inline::split_args(input).ok_or(ParseError::Syntax)?;
+        let parts: Vec<_> = args.iter().map(String::as_str).collect();
+        Self::from_args(&parts)
+    }
+
+    /// 从已经切分好的参数列表（命令名 + 参数）解析出命令结构
+    ///
+    /// 先按 [`arity`] 检查参数个数，再解析各个参数。
+    pub fn from_args(parts: &[&str]) -> Result<Self, ParseError> {
+        check_arity(parts)?;
 
-        match parts.as_slice() {
+        let command = match parts {
+            // 各命令的 HELP 子命令由命令表生成，子命令存在已由 check_arity 保证
+            [name, sub]
+                if sub.eq_ignore_ascii_case("help")
+                    && has_subcommands(&name.to_ascii_lowercase()) =>
+            {
+                let full = format!("{}|help", name.to_ascii_lowercase());
+                Command::Help(handler::spec(&full).ok_or(ParseError::Syntax)?.name)
+            }
             [name, key] if name.eq_ignore_ascii_case("get") => Command::Get(key.to_string()),
+            [name, key] if name.eq_ignore_ascii_case("incr") => Command::Incr(key.to_string()),
+            [name, key, delta] if name.eq_ignore_ascii_case("incrby") => {
+                Command::IncrBy(key.to_string(), int(delta)?)
+            }
             [name, key, value] if name.eq_ignore_ascii_case("set") => {
                 Command::Set(key.to_string(), value.to_string())
             }
-            _ => Command::Unknown,
+            [name, key, value, options @ ..] if name.eq_ignore_ascii_case("set") => {
+                Command::SetWith(key.to_string(), value.to_string(), parse_set_options(options)?)
+            }
+            [name, key, value] if name.eq_ignore_ascii_case("getset") => {
+                Command::GetSet(key.to_string(), value.to_string())
+            }
+            [name, key, offset, value] if name.eq_ignore_ascii_case("setbit") => {
+                Command::SetBit(key.to_string(), parse_bit_offset(offset)?, parse_bit(value)?)
+            }
+            [name, key, offset] if name.eq_ignore_ascii_case("getbit") => {
+                Command::GetBit(key.to_string(), parse_bit_offset(offset)?)
+            }
+            [name, key, range @ ..] if name.eq_ignore_ascii_case("bitcount") => {
+                let range = match range {
+                    [] => None,
+                    [_, _] | [_, _, _] => Some(parse_bit_range(range)?),
+                    _ => return Err(ParseError::Syntax),
+                };
+                Command::BitCount(key.to_string(), range)
+            }
+            [name, key, bit, range @ ..] if name.eq_ignore_ascii_case("bitpos") => {
+                let bit = parse_bit(bit)?;
+                let range = match range {
+                    [] => None,
+                    [start] => {
+                        let range = parse_bit_range(&[start, "-1"])?;
+                        Some(BitRange { end: None, ..range })
+                    }
+                    _ => Some(parse_bit_range(range)?),
+                };
+                Command::BitPos(key.to_string(), bit, range)
+            }
+            [name, op, dest, keys @ ..]
+                if name.eq_ignore_ascii_case("bitop") && !keys.is_empty() =>
+            {
+                let op = match op.to_ascii_lowercase().as_str() {
+                    "and" => BitOp::And,
+                    "or" => BitOp::Or,
+                    "xor" => BitOp::Xor,
+                    // NOT 只接受一个源键
+                    "not" if keys.len() == 1 => BitOp::Not,
+                    _ => return Err(ParseError::Syntax),
+                };
+                Command::BitOp(op, dest.to_string(), to_strings(keys))
+            }
+            [name, key, rest @ ..] if name.eq_ignore_ascii_case("hset") => {
+                if !rest.len().is_multiple_of(2) {
+                    return Err(ParseError::WrongArity("hset".into()));
+                }
+                let pairs = rest.chunks(2).map(|p| (p[0].to_string(), p[1].to_string()));
+                Command::HSet(key.to_string(), pairs.collect())
+            }
+            [name, key, field] if name.eq_ignore_ascii_case("hget") => {
+                Command::HGet(key.to_string(), field.to_string())
+            }
+            [name, key, field, delta] if name.eq_ignore_ascii_case("hincrby") => {
+                Command::HIncrBy(key.to_string(), field.to_string(), int(delta)?)
+            }
+            [name, key, field, delta] if name.eq_ignore_ascii_case("hincrbyfloat") => {
+                Command::HIncrByFloat(key.to_string(), field.to_string(), float(delta)?)
+            }
+            [name, key] if name.eq_ignore_ascii_case("hrandfield") => {
+                Command::HRandField(key.to_string(), None, false)
+            }
+            [name, key, count] if name.eq_ignore_ascii_case("hrandfield") => {
+                Command::HRandField(key.to_string(), Some(int(count)?), false)
+            }
+            [name, key, count, option]
+                if name.eq_ignore_ascii_case("hrandfield")
+                    && option.eq_ignore_ascii_case("withvalues") =>
+            {
+                Command::HRandField(key.to_string(), Some(int(count)?), true)
+            }
+            [name, key, members @ ..] if name.eq_ignore_ascii_case("sadd") => {
+                Command::SAdd(key.to_string(), to_strings(members))
+            }
+            [name, key, members @ ..] if name.eq_ignore_ascii_case("srem") => {
+                Command::SRem(key.to_string(), to_strings(members))
+            }
+            [name, key] if name.eq_ignore_ascii_case("smembers") => {
+                Command::SMembers(key.to_string())
+            }
+            [name, key, member] if name.eq_ignore_ascii_case("sismember") => {
+                Command::SIsMember(key.to_string(), member.to_string())
+            }
+            [name, key] if name.eq_ignore_ascii_case("scard") => Command::SCard(key.to_string()),
+            [name, key, members @ ..] if name.eq_ignore_ascii_case("smismember") => {
+                Command::SMIsMember(key.to_string(), to_strings(members))
+            }
+            [name, key] if name.eq_ignore_ascii_case("spop") => {
+                Command::SPop(key.to_string(), None)
+            }
+            [name, key, count] if name.eq_ignore_ascii_case("spop") => {
+                Command::SPop(key.to_string(), Some(parse_count(count)?))
+            }
+            [name, key] if name.eq_ignore_ascii_case("srandmember") => {
+                Command::SRandMember(key.to_string(), None)
+            }
+            [name, key, count] if name.eq_ignore_ascii_case("srandmember") => {
+                Command::SRandMember(key.to_string(), Some(int(count)?))
+            }
+            [name, keys @ ..] if name.eq_ignore_ascii_case("sinter") => {
+                Command::SInter(to_strings(keys))
+            }
+            [name, keys @ ..] if name.eq_ignore_ascii_case("sunion") => {
+                Command::SUnion(to_strings(keys))
+            }
+            [name, keys @ ..] if name.eq_ignore_ascii_case("sdiff") => {
+                Command::SDiff(to_strings(keys))
+            }
+            [name, dest, keys @ ..] if name.eq_ignore_ascii_case("sinterstore") => {
+                Command::SInterStore(dest.to_string(), to_strings(keys))
+            }
+            [name, dest, keys @ ..] if name.eq_ignore_ascii_case("sunionstore") => {
+                Command::SUnionStore(dest.to_string(), to_strings(keys))
+            }
+            [name, dest, keys @ ..] if name.eq_ignore_ascii_case("sdiffstore") => {
+                Command::SDiffStore(dest.to_string(), to_strings(keys))
+            }
+            [name, numkeys, rest @ ..] if name.eq_ignore_ascii_case("sintercard") => {
+                parse_sintercard(numkeys, rest)?
+            }
+            [name, key, args @ ..] if name.eq_ignore_ascii_case("zadd") => parse_zadd(key, args)?,
+            [name, key, member] if name.eq_ignore_ascii_case("zscore") => {
+                Command::ZScore(key.to_string(), member.to_string())
+            }
+            [name, key] if name.eq_ignore_ascii_case("zcard") => Command::ZCard(key.to_string()),
+            [name, key, start, stop, options @ ..] if name.eq_ignore_ascii_case("zrange") => {
+                parse_zrange(key, start, stop, options)?
+            }
+            [name, key, min, max, options @ ..] if name.eq_ignore_ascii_case("zrangebyscore") => {
+                parse_zrangebyscore(key, min, max, options)?
+            }
+            [name, key, min, max, options @ ..] if name.eq_ignore_ascii_case("zrangebylex") => {
+                parse_zrangebylex(key, min, max, options)?
+            }
+            [name, key, member] if name.eq_ignore_ascii_case("zrank") => {
+                Command::ZRank(key.to_string(), member.to_string())
+            }
+            [name, key, delta, member] if name.eq_ignore_ascii_case("zincrby") => {
+                Command::ZIncrBy(key.to_string(), float(delta)?, member.to_string())
+            }
+            [name, key] if name.eq_ignore_ascii_case("zpopmin") => {
+                Command::ZPopMin(key.to_string(), None)
+            }
+            [name, key, count] if name.eq_ignore_ascii_case("zpopmin") => {
+                Command::ZPopMin(key.to_string(), Some(parse_count(count)?))
+            }
+            [name, key] if name.eq_ignore_ascii_case("zpopmax") => {
+                Command::ZPopMax(key.to_string(), None)
+            }
+            [name, key, count] if name.eq_ignore_ascii_case("zpopmax") => {
+                Command::ZPopMax(key.to_string(), Some(parse_count(count)?))
+            }
+            [name, keys @ .., timeout] if name.eq_ignore_ascii_case("bzpopmin") => {
+                Command::BZPopMin(to_strings(keys), parse_timeout(timeout)?)
+            }
+            [name, keys @ .., timeout] if name.eq_ignore_ascii_case("bzpopmax") => {
+                Command::BZPopMax(to_strings(keys), parse_timeout(timeout)?)
+            }
+            [name, key, args @ ..] if name.eq_ignore_ascii_case("geoadd") => {
+                parse_geoadd(key, args)?
+            }
+            [name, key, from, to] if name.eq_ignore_ascii_case("geodist") => {
+                Command::GeoDist(key.to_string(), from.to_string(), to.to_string(), Unit::M)
+            }
+            [name, key, from, to, unit] if name.eq_ignore_ascii_case("geodist") => {
+                let unit = Unit::parse(unit).ok_or(ParseError::Syntax)?;
+                Command::GeoDist(key.to_string(), from.to_string(), to.to_string(), unit)
+            }
+            [name, key, members @ ..] if name.eq_ignore_ascii_case("geopos") => {
+                Command::GeoPos(key.to_string(), to_strings(members))
+            }
+            [name, key, args @ ..] if name.eq_ignore_ascii_case("geosearch") => {
+                Command::GeoSearch(key.to_string(), parse_geosearch(args)?)
+            }
+            [name, key, id, fields @ ..] if name.eq_ignore_ascii_case("xadd") => {
+                if !fields.len().is_multiple_of(2) {
+                    return Err(ParseError::WrongArity("xadd".into()));
+                }
+                let id = IdSpec::parse(id).ok_or(ParseError::InvalidStreamId)?;
+                let pairs = fields.chunks(2).map(|p| (p[0].to_string(), p[1].to_string()));
+                Command::XAdd(key.to_string(), id, pairs.collect())
+            }
+            [name, key] if name.eq_ignore_ascii_case("xlen") => Command::XLen(key.to_string()),
+            [name, key, start, end, options @ ..] if name.eq_ignore_ascii_case("xrange") => {
+                parse_xrange(key, start, end, options)?
+            }
+            [name, args @ ..] if name.eq_ignore_ascii_case("xread") => {
+                Command::XRead(parse_xread(args)?)
+            }
+            [name, sub, args @ ..] if name.eq_ignore_ascii_case("xgroup") => {
+                parse_xgroup(sub, args)?
+            }
+            [name, group, group_name, consumer, args @ ..]
+                if name.eq_ignore_ascii_case("xreadgroup")
+                    && group.eq_ignore_ascii_case("group") =>
+            {
+                Command::XReadGroup(parse_xreadgroup(group_name, consumer, args)?)
+            }
+            [name, key, group, ids @ ..] if name.eq_ignore_ascii_case("xack") => {
+                let ids = ids.iter().map(|id| stream_id(id)).collect::<Result<_, _>>()?;
+                Command::XAck(key.to_string(), group.to_string(), ids)
+            }
+            [name, key, group, args @ ..] if name.eq_ignore_ascii_case("xpending") => {
+                parse_xpending(key, group, args)?
+            }
+            [name, key, group, consumer, min_idle, args @ ..]
+                if name.eq_ignore_ascii_case("xclaim") =>
+            {
+                parse_xclaim(key, group, consumer, min_idle, args)?
+            }
+            [name, pattern] if name.eq_ignore_ascii_case("keys") => {
+                Command::Keys(pattern.to_string())
+            }
+            [name, cursor, args @ ..] if name.eq_ignore_ascii_case("scan") => {
+                Command::Scan(parse_scan(cursor, args)?)
+            }
+            [name, channel, message] if name.eq_ignore_ascii_case("publish") => {
+                Command::Publish(channel.to_string(), message.to_string())
+            }
+            [name, channels @ ..] if name.eq_ignore_ascii_case("subscribe") => {
+                Command::Subscribe(to_strings(channels))
+            }
+            [name, channels @ ..] if name.eq_ignore_ascii_case("unsubscribe") => {
+                Command::Unsubscribe(to_strings(channels))
+            }
+            [name, patterns @ ..] if name.eq_ignore_ascii_case("psubscribe") => {
+                Command::PSubscribe(to_strings(patterns))
+            }
+            [name, patterns @ ..] if name.eq_ignore_ascii_case("punsubscribe") => {
+                Command::PUnsubscribe(to_strings(patterns))
+            }
+            [name, sub]
+                if name.eq_ignore_ascii_case("pubsub") && sub.eq_ignore_ascii_case("channels") =>
+            {
+                Command::PubSubChannels(None)
+            }
+            [name, sub, pattern]
+                if name.eq_ignore_ascii_case("pubsub") && sub.eq_ignore_ascii_case("channels") =>
+            {
+                Command::PubSubChannels(Some(pattern.to_string()))
+            }
+            [name, sub, channels @ ..]
+                if name.eq_ignore_ascii_case("pubsub") && sub.eq_ignore_ascii_case("numsub") =>
+            {
+                Command::PubSubNumSub(to_strings(channels))
+            }
+            [name, sub]
+                if name.eq_ignore_ascii_case("pubsub") && sub.eq_ignore_ascii_case("numpat") =>
+            {
+                Command::PubSubNumPat
+            }
+            [name, channel, message] if name.eq_ignore_ascii_case("spublish") => {
+                Command::SPublish(channel.to_string(), message.to_string())
+            }
+            [name, channels @ ..] if name.eq_ignore_ascii_case("ssubscribe") => {
+                Command::SSubscribe(to_strings(channels))
+            }
+            [name, channels @ ..] if name.eq_ignore_ascii_case("sunsubscribe") => {
+                Command::SUnsubscribe(to_strings(channels))
+            }
+            [name, sub, pattern @ ..]
+                if name.eq_ignore_ascii_case("pubsub")
+                    && sub.eq_ignore_ascii_case("shardchannels")
+                    && pattern.len() <= 1 =>
+            {
+                Command::PubSubShardChannels(pattern.first().map(|pattern| pattern.to_string()))
+            }
+            [name, sub, channels @ ..]
+                if name.eq_ignore_ascii_case("pubsub")
+                    && sub.eq_ignore_ascii_case("shardnumsub") =>
+            {
+                Command::PubSubShardNumSub(to_strings(channels))
+            }
+            [name] if name.eq_ignore_ascii_case("bgrewriteaof") => Command::BgRewriteAof,
+            [name] if name.eq_ignore_ascii_case("save") => Command::Save,
+            [name] if name.eq_ignore_ascii_case("bgsave") => Command::BgSave,
+            [name] if name.eq_ignore_ascii_case("lastsave") => Command::LastSave,
+            [name, key, time, options @ ..]
+                if ["expire", "pexpire", "expireat", "pexpireat"]
+                    .iter()
+                    .any(|expire| name.eq_ignore_ascii_case(expire)) =>
+            {
+                parse_expire(name, key, time, options)?
+            }
+            [name, key] if name.eq_ignore_ascii_case("persist") => {
+                Command::Persist(key.to_string())
+            }
+            [name, key] if name.eq_ignore_ascii_case("ttl") => Command::Ttl(key.to_string()),
+            [name, key] if name.eq_ignore_ascii_case("pttl") => Command::PTtl(key.to_string()),
+            [name, key] if name.eq_ignore_ascii_case("expiretime") => {
+                Command::ExpireTime(key.to_string())
+            }
+            [name, key] if name.eq_ignore_ascii_case("pexpiretime") => {
+                Command::PExpireTime(key.to_string())
+            }
+            [name, no, one]
+                if (name.eq_ignore_ascii_case("replicaof")
+                    || name.eq_ignore_ascii_case("slaveof"))
+                    && no.eq_ignore_ascii_case("no")
+                    && one.eq_ignore_ascii_case("one") =>
+            {
+                Command::ReplicaOf(None)
+            }
+            [name, host, port]
+                if name.eq_ignore_ascii_case("replicaof")
+                    || name.eq_ignore_ascii_case("slaveof") =>
+            {
+                Command::ReplicaOf(Some((host.to_string(), int(port)?)))
+            }
+            [name] if name.eq_ignore_ascii_case("role") => Command::Role,
+            [name, args @ ..] if name.eq_ignore_ascii_case("replconf") => {
+                Command::ReplConf(to_strings(args))
+            }
+            [name, replid, offset] if name.eq_ignore_ascii_case("psync") => {
+                Command::Psync(replid.to_string(), int(offset)?)
+            }
+            [name] if name.eq_ignore_ascii_case("sync") => Command::Sync,
+            [name, sub, args @ ..] if name.eq_ignore_ascii_case("sentinel") => {
+                parse_sentinel(sub, args)?
+            }
+            [name, keys @ ..] if name.eq_ignore_ascii_case("del") => Command::Del(to_strings(keys)),
+            [name, keys @ ..] if name.eq_ignore_ascii_case("unlink") => {
+                Command::Unlink(to_strings(keys))
+            }
+            [name, sub, key] if name.eq_ignore_ascii_case("object") => {
+                match sub.to_ascii_lowercase().as_str() {
+                    "encoding" => Command::ObjectEncoding(key.to_string()),
+                    "refcount" => Command::ObjectRefCount(key.to_string()),
+                    "idletime" => Command::ObjectIdleTime(key.to_string()),
+                    _ => return Err(ParseError::Syntax),
+                }
+            }
+            [name, key] if name.eq_ignore_ascii_case("dump") => Command::Dump(key.to_string()),
+            [name, key, ttl, payload, options @ ..] if name.eq_ignore_ascii_case("restore") => {
+                parse_restore(key, ttl, payload, options)?
+            }
+            [name, args @ ..] if name.eq_ignore_ascii_case("migrate") => parse_migrate(args)?,
+            [name, numreplicas, timeout] if name.eq_ignore_ascii_case("wait") => {
+                Command::Wait(int(numreplicas)?, int(timeout)?)
+            }
+            [name, sub, pattern]
+                if name.eq_ignore_ascii_case("config") && sub.eq_ignore_ascii_case("get") =>
+            {
+                Command::ConfigGet(pattern.to_string())
+            }
+            [name, sub, args @ ..]
+                if name.eq_ignore_ascii_case("config") && sub.eq_ignore_ascii_case("set") =>
+            {
+                if !args.len().is_multiple_of(2) {
+                    return Err(ParseError::WrongArity("config|set".into()));
+                }
+                let pairs = args.chunks(2).map(|p| (p[0].to_string(), p[1].to_string()));
+                Command::ConfigSet(pairs.collect())
+            }
+            [name, sub]
+                if name.eq_ignore_ascii_case("config") && sub.eq_ignore_ascii_case("resetstat") =>
+            {
+                Command::ConfigResetStat
+            }
+            [name, sub]
+                if name.eq_ignore_ascii_case("latency") && sub.eq_ignore_ascii_case("latest") =>
+            {
+                Command::LatencyLatest
+            }
+            [name, sub, event]
+                if name.eq_ignore_ascii_case("latency") && sub.eq_ignore_ascii_case("history") =>
+            {
+                Command::LatencyHistory(event.to_ascii_lowercase())
+            }
+            [name, sub, events @ ..]
+                if name.eq_ignore_ascii_case("latency") && sub.eq_ignore_ascii_case("reset") =>
+            {
+                Command::LatencyReset(events.iter().map(|e| e.to_ascii_lowercase()).collect())
+            }
+            [name, sub, seconds]
+                if name.eq_ignore_ascii_case("debug") && sub.eq_ignore_ascii_case("sleep") =>
+            {
+                Command::DebugSleep(parse_timeout(seconds)?)
+            }
+            [name, sub, command, ms]
+                if name.eq_ignore_ascii_case("debug") && sub.eq_ignore_ascii_case("delay") =>
+            {
+                Command::DebugDelay(command.to_ascii_lowercase(), int(ms)?)
+            }
+            [name, sub, command, count @ ..]
+                if name.eq_ignore_ascii_case("debug") && sub.eq_ignore_ascii_case("drop") =>
+            {
+                let count = match count {
+                    [] => 1,
+                    [count] => match int(count)? {
+                        0 => return Err(ParseError::InvalidInteger),
+                        count => count,
+                    },
+                    _ => return Err(ParseError::Syntax),
+                };
+                Command::DebugDrop(command.to_ascii_lowercase(), count)
+            }
+            [name, sub, ms]
+                if name.eq_ignore_ascii_case("debug") && sub.eq_ignore_ascii_case("contend") =>
+            {
+                Command::DebugContend(int(ms)?)
+            }
+            [name, sub]
+                if name.eq_ignore_ascii_case("debug") && sub.eq_ignore_ascii_case("faults") =>
+            {
+                Command::DebugFaults
+            }
+            [name, sub]
+                if name.eq_ignore_ascii_case("debug") && sub.eq_ignore_ascii_case("reset") =>
+            {
+                Command::DebugReset
+            }
+            [name] if name.eq_ignore_ascii_case("info") => Command::Info(None),
+            [name, section] if name.eq_ignore_ascii_case("info") => {
+                Command::Info(Some(section.to_string()))
+            }
+            [name] if name.eq_ignore_ascii_case("dbsize") => Command::DbSize,
+            [name] if name.eq_ignore_ascii_case("randomkey") => Command::RandomKey,
+            [name] if name.eq_ignore_ascii_case("bigkeys") => Command::BigKeys,
+            [name, rest @ ..] if name.eq_ignore_ascii_case("hotkeys") => match rest {
+                [] => Command::HotKeys(DEFAULT_HOTKEYS),
+                [option, count] if option.eq_ignore_ascii_case("count") => match int(count)? {
+                    0 => return Err(ParseError::InvalidInteger),
+                    count => Command::HotKeys(count),
+                },
+                _ => return Err(ParseError::Syntax),
+            },
+            [name] if name.eq_ignore_ascii_case("export") => Command::Export,
+            [name, lines @ ..] if name.eq_ignore_ascii_case("import") => {
+                Command::Import(to_strings(lines))
+            }
+            [name, keys @ ..] if name.eq_ignore_ascii_case("touch") => {
+                Command::Touch(to_strings(keys))
+            }
+            [name, mode @ ..] if name.eq_ignore_ascii_case("flushdb") => {
+                Command::FlushDb(parse_flush_mode(mode)?)
+            }
+            [name, mode @ ..] if name.eq_ignore_ascii_case("flushall") => {
+                Command::FlushAll(parse_flush_mode(mode)?)
+            }
+            [name, index] if name.eq_ignore_ascii_case("select") => Command::Select(int(index)?),
+            [name, key, db] if name.eq_ignore_ascii_case("move") => {
+                Command::Move(key.to_string(), int(db)?)
+            }
+            [name, a, b] if name.eq_ignore_ascii_case("swapdb") => {
+                Command::SwapDb(int(a)?, int(b)?)
+            }
+            [name, password] if name.eq_ignore_ascii_case("auth") => {
+                Command::Auth(None, password.to_string())
+            }
+            [name, user, password] if name.eq_ignore_ascii_case("auth") => {
+                Command::Auth(Some(user.to_string()), password.to_string())
+            }
+            [name, sub, user, rules @ ..]
+                if name.eq_ignore_ascii_case("acl") && sub.eq_ignore_ascii_case("setuser") =>
+            {
+                Command::AclSetUser(user.to_string(), to_strings(rules))
+            }
+            [name, sub, user]
+                if name.eq_ignore_ascii_case("acl") && sub.eq_ignore_ascii_case("getuser") =>
+            {
+                Command::AclGetUser(user.to_string())
+            }
+            [name, sub] if name.eq_ignore_ascii_case("acl") && sub.eq_ignore_ascii_case("list") => {
+                Command::AclList
+            }
+            [name, sub]
+                if name.eq_ignore_ascii_case("acl") && sub.eq_ignore_ascii_case("whoami") =>
+            {
+                Command::AclWhoAmI
+            }
+            [name, sub] if name.eq_ignore_ascii_case("client") => {
+                match sub.to_ascii_lowercase().as_str() {
+                    "id" => Command::ClientId,
+                    "getname" => Command::ClientGetName,
+                    "list" => Command::ClientList,
+                    _ => return Err(ParseError::Syntax),
+                }
+            }
+            [name, sub, client_name]
+                if name.eq_ignore_ascii_case("client") && sub.eq_ignore_ascii_case("setname") =>
+            {
+                Command::ClientSetName(client_name.to_string())
+            }
+            [name, sub, attr, value]
+                if name.eq_ignore_ascii_case("client") && sub.eq_ignore_ascii_case("setinfo") =>
+            {
+                let attr = attr.to_ascii_lowercase();
+                if attr != "lib-name" && attr != "lib-ver" {
+                    return Err(ParseError::Syntax);
+                }
+                Command::ClientSetInfo(attr, value.to_string())
+            }
+            [name, sub, addr]
+                if name.eq_ignore_ascii_case("client") && sub.eq_ignore_ascii_case("kill") =>
+            {
+                Command::ClientKillAddr(addr.to_string())
+            }
+            [name, sub, filters @ ..]
+                if name.eq_ignore_ascii_case("client") && sub.eq_ignore_ascii_case("kill") =>
+            {
+                Command::ClientKill(parse_client_kill(filters)?)
+            }
+            [name] if name.eq_ignore_ascii_case("monitor") => Command::Monitor,
+            [name] if name.eq_ignore_ascii_case("hello") => Command::Hello(Hello::default()),
+            [name, protover, options @ ..] if name.eq_ignore_ascii_case("hello") => {
+                Command::Hello(parse_hello(protover, options)?)
+            }
+            [name] if name.eq_ignore_ascii_case("ping") => Command::Ping(None),
+            [name, message] if name.eq_ignore_ascii_case("ping") => {
+                Command::Ping(Some(message.to_string()))
+            }
+            [name, ..] if name.eq_ignore_ascii_case("ping") => {
+                return Err(ParseError::WrongArity("ping".into()));
+            }
+            [name, message] if name.eq_ignore_ascii_case("echo") => {
+                Command::Echo(message.to_string())
+            }
+            // 与 Redis 相同，QUIT 忽略多余的参数
+            [name, ..] if name.eq_ignore_ascii_case("quit") => Command::Quit,
+            [name] if name.eq_ignore_ascii_case("reset") => Command::Reset,
+            [name] if name.eq_ignore_ascii_case("readonly") => Command::ReadOnly,
+            [name] if name.eq_ignore_ascii_case("readwrite") => Command::ReadWrite,
+            [name] if name.eq_ignore_ascii_case("command") => Command::Commands,
+            [name, sub]
+                if name.eq_ignore_ascii_case("command") && sub.eq_ignore_ascii_case("count") =>
+            {
+                Command::CommandCount
+            }
+            [name, sub, names @ ..]
+                if name.eq_ignore_ascii_case("command") && sub.eq_ignore_ascii_case("info") =>
+            {
+                Command::CommandInfo(to_strings(names))
+            }
+            [name, sub, names @ ..]
+                if name.eq_ignore_ascii_case("command") && sub.eq_ignore_ascii_case("docs") =>
+            {
+                Command::CommandDocs(to_strings(names))
+            }
+            _ => return Err(ParseError::Syntax),
+        };
+        Ok(command)
+    }
+
+    /// 命令名（小写），子命令以 `|` 连接，例如 `config|get`
+    ///
+    /// 同一个变体对应多个命令时（如 EXPIRE / PEXPIRE）按参数区分，得到客户端实际使用的命令名。
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Get(..) => "get",
+            Command::Incr(..) => "incr",
+            Command::IncrBy(..) => "incrby",
+            Command::SetBit(..) => "setbit",
+            Command::GetBit(..) => "getbit",
+            Command::BitCount(..) => "bitcount",
+            Command::BitPos(..) => "bitpos",
+            Command::BitOp(..) => "bitop",
+            Command::Set(..) | Command::SetWith(..) => "set",
+            Command::GetSet(..) => "getset",
+            Command::HSet(..) => "hset",
+            Command::HGet(..) => "hget",
+            Command::HIncrBy(..) => "hincrby",
+            Command::HIncrByFloat(..) => "hincrbyfloat",
+            Command::HRandField(..) => "hrandfield",
+            Command::SAdd(..) => "sadd",
+            Command::SRem(..) => "srem",
+            Command::SMembers(..) => "smembers",
+            Command::SIsMember(..) => "sismember",
+            Command::SCard(..) => "scard",
+            Command::SMIsMember(..) => "smismember",
+            Command::SPop(..) => "spop",
+            Command::SRandMember(..) => "srandmember",
+            Command::SInter(..) => "sinter",
+            Command::SUnion(..) => "sunion",
+            Command::SDiff(..) => "sdiff",
+            Command::SInterStore(..) => "sinterstore",
+            Command::SUnionStore(..) => "sunionstore",
+            Command::SDiffStore(..) => "sdiffstore",
+            Command::SInterCard(..) => "sintercard",
+            Command::ZAdd(..) => "zadd",
+            Command::ZScore(..) => "zscore",
+            Command::ZCard(..) => "zcard",
+            Command::ZRange(..) => "zrange",
+            Command::ZRangeByScore(..) => "zrangebyscore",
+            Command::ZRangeByLex(..) => "zrangebylex",
+            Command::ZRank(..) => "zrank",
+            Command::ZIncrBy(..) => "zincrby",
+            Command::ZPopMin(..) => "zpopmin",
+            Command::ZPopMax(..) => "zpopmax",
+            Command::BZPopMin(..) => "bzpopmin",
+            Command::BZPopMax(..) => "bzpopmax",
+            Command::GeoAdd(..) => "geoadd",
+            Command::GeoDist(..) => "geodist",
+            Command::GeoPos(..) => "geopos",
+            Command::GeoSearch(..) => "geosearch",
+            Command::XAdd(..) => "xadd",
+            Command::XLen(..) => "xlen",
+            Command::XRange(..) => "xrange",
+            Command::XRead(..) => "xread",
+            Command::XGroupCreate(..) => "xgroup|create",
+            Command::XGroupSetId(..) => "xgroup|setid",
+            Command::XGroupDestroy(..) => "xgroup|destroy",
+            Command::XGroupCreateConsumer(..) => "xgroup|createconsumer",
+            Command::XReadGroup(..) => "xreadgroup",
+            Command::XAck(..) => "xack",
+            Command::XPending(..) => "xpending",
+            Command::XClaim(..) => "xclaim",
+            Command::Keys(..) => "keys",
+            Command::Scan(..) => "scan",
+            Command::Publish(..) => "publish",
+            Command::Subscribe(..) => "subscribe",
+            Command::Unsubscribe(..) => "unsubscribe",
+            Command::PSubscribe(..) => "psubscribe",
+            Command::PUnsubscribe(..) => "punsubscribe",
+            Command::PubSubChannels(..) => "pubsub|channels",
+            Command::PubSubNumSub(..) => "pubsub|numsub",
+            Command::PubSubNumPat => "pubsub|numpat",
+            Command::SPublish(..) => "spublish",
+            Command::SSubscribe(..) => "ssubscribe",
+            Command::SUnsubscribe(..) => "sunsubscribe",
+            Command::PubSubShardChannels(..) => "pubsub|shardchannels",
+            Command::PubSubShardNumSub(..) => "pubsub|shardnumsub",
+            Command::BgRewriteAof => "bgrewriteaof",
+            Command::Save => "save",
+            Command::BgSave => "bgsave",
+            Command::LastSave => "lastsave",
+            Command::Expire(_, Expiry::Seconds(_), _) => "expire",
+            Command::Expire(_, Expiry::Millis(_), _) => "pexpire",
+            Command::Expire(_, Expiry::UnixSeconds(_), _) => "expireat",
+            Command::Expire(_, Expiry::UnixMillis(_), _) => "pexpireat",
+            Command::Persist(..) => "persist",
+            Command::Ttl(..) => "ttl",
+            Command::PTtl(..) => "pttl",
+            Command::ExpireTime(..) => "expiretime",
+            Command::PExpireTime(..) => "pexpiretime",
+            Command::ReplicaOf(..) => "replicaof",
+            Command::Role => "role",
+            Command::ReplConf(..) => "replconf",
+            Command::Psync(..) => "psync",
+            Command::Sync => "sync",
+            Command::SentinelMonitor(..) => "sentinel|monitor",
+            Command::SentinelRemove(..) => "sentinel|remove",
+            Command::SentinelSet(..) => "sentinel|set",
+            Command::SentinelMasters => "sentinel|masters",
+            Command::SentinelMaster(..) => "sentinel|master",
+            Command::SentinelReplicas(..) => "sentinel|replicas",
+            Command::SentinelSentinels(..) => "sentinel|sentinels",
+            Command::SentinelGetMasterAddr(..) => "sentinel|get-master-addr-by-name",
+            Command::SentinelIsMasterDownByAddr(..) => "sentinel|is-master-down-by-addr",
+            Command::SentinelFailover(..) => "sentinel|failover",
+            Command::SentinelMyId => "sentinel|myid",
+            Command::Del(..) => "del",
+            Command::Unlink(..) => "unlink",
+            Command::ObjectEncoding(..) => "object|encoding",
+            Command::ObjectRefCount(..) => "object|refcount",
+            Command::ObjectIdleTime(..) => "object|idletime",
+            Command::Dump(..) => "dump",
+            Command::Restore(..) => "restore",
+            Command::Migrate(..) => "migrate",
+            Command::Wait(..) => "wait",
+            Command::ConfigGet(..) => "config|get",
+            Command::ConfigSet(..) => "config|set",
+            Command::ConfigResetStat => "config|resetstat",
+            Command::LatencyLatest => "latency|latest",
+            Command::LatencyHistory(..) => "latency|history",
+            Command::LatencyReset(..) => "latency|reset",
+            Command::DebugSleep(..) => "debug|sleep",
+            Command::DebugDelay(..) => "debug|delay",
+            Command::DebugDrop(..) => "debug|drop",
+            Command::DebugContend(..) => "debug|contend",
+            Command::DebugFaults => "debug|faults",
+            Command::DebugReset => "debug|reset",
+            Command::Info(..) => "info",
+            Command::DbSize => "dbsize",
+            Command::RandomKey => "randomkey",
+            Command::BigKeys => "bigkeys",
+            Command::HotKeys(..) => "hotkeys",
+            Command::Export => "export",
+            Command::Import(..) => "import",
+            Command::Touch(..) => "touch",
+            Command::FlushDb(..) => "flushdb",
+            Command::FlushAll(..) => "flushall",
+            Command::Select(..) => "select",
+            Command::Move(..) => "move",
+            Command::SwapDb(..) => "swapdb",
+            Command::Auth(..) => "auth",
+            Command::AclSetUser(..) => "acl|setuser",
+            Command::AclGetUser(..) => "acl|getuser",
+            Command::AclList => "acl|list",
+            Command::AclWhoAmI => "acl|whoami",
+            Command::ClientId => "client|id",
+            Command::ClientSetName(..) => "client|setname",
+            Command::ClientSetInfo(..) => "client|setinfo",
+            Command::ClientGetName => "client|getname",
+            Command::ClientList => "client|list",
+            Command::ClientKill(..) | Command::ClientKillAddr(..) => "client|kill",
+            Command::Monitor => "monitor",
+            Command::Hello(..) => "hello",
+            Command::Ping(..) => "ping",
+            Command::Echo(..) => "echo",
+            Command::Quit => "quit",
+            Command::Reset => "reset",
+            Command::ReadOnly => "readonly",
+            Command::ReadWrite => "readwrite",
+            Command::Help(name) => name,
+            Command::Commands => "command",
+            Command::CommandCount => "command|count",
+            Command::CommandInfo(..) => "command|info",
+            Command::CommandDocs(..) => "command|docs",
+        }
+    }
+
+    /// 命令访问的键，用于 ACL 的键权限检查
+    pub fn keys(&self) -> Vec<&str> {
+        match self {
+            Command::Get(key)
+            | Command::Incr(key)
+            | Command::IncrBy(key, _)
+            | Command::SetBit(key, ..)
+            | Command::GetBit(key, _)
+            | Command::BitCount(key, _)
+            | Command::BitPos(key, ..)
+            | Command::Set(key, _)
+            | Command::SetWith(key, ..)
+            | Command::GetSet(key, _)
+            | Command::HSet(key, _)
+            | Command::HGet(key, _)
+            | Command::HIncrBy(key, ..)
+            | Command::HIncrByFloat(key, ..)
+            | Command::HRandField(key, ..)
+            | Command::SAdd(key, _)
+            | Command::SRem(key, _)
+            | Command::SMembers(key)
+            | Command::SIsMember(key, _)
+            | Command::SCard(key)
+            | Command::SMIsMember(key, _)
+            | Command::SPop(key, _)
+            | Command::SRandMember(key, _)
+            | Command::ZAdd(key, ..)
+            | Command::ZScore(key, _)
+            | Command::ZCard(key)
+            | Command::ZRange(key, ..)
+            | Command::ZRangeByScore(key, ..)
+            | Command::ZRangeByLex(key, ..)
+            | Command::ZRank(key, _)
+            | Command::ZIncrBy(key, ..)
+            | Command::ZPopMin(key, _)
+            | Command::ZPopMax(key, _)
+            | Command::GeoAdd(key, ..)
+            | Command::GeoDist(key, ..)
+            | Command::GeoPos(key, _)
+            | Command::GeoSearch(key, _)
+            | Command::XAdd(key, ..)
+            | Command::XLen(key)
+            | Command::XRange(key, ..)
+            | Command::XGroupCreate(key, ..)
+            | Command::XGroupSetId(key, ..)
+            | Command::XGroupDestroy(key, _)
+            | Command::XGroupCreateConsumer(key, ..)
+            | Command::XAck(key, ..)
+            | Command::XPending(key, ..)
+            | Command::XClaim(key, ..)
+            | Command::Expire(key, ..)
+            | Command::Persist(key)
+            | Command::Ttl(key)
+            | Command::PTtl(key)
+            | Command::ExpireTime(key)
+            | Command::PExpireTime(key)
+            | Command::ObjectEncoding(key)
+            | Command::ObjectRefCount(key)
+            | Command::ObjectIdleTime(key)
+            | Command::Dump(key)
+            | Command::Restore(key, ..)
+            | Command::Move(key, _) => vec![key],
+            Command::SInter(keys)
+            | Command::SUnion(keys)
+            | Command::SDiff(keys)
+            | Command::SInterCard(keys, _)
+            | Command::BZPopMin(keys, _)
+            | Command::BZPopMax(keys, _)
+            | Command::Touch(keys)
+            | Command::Del(keys)
+            | Command::Unlink(keys) => keys.iter().map(String::as_str).collect(),
+            Command::SInterStore(dest, keys)
+            | Command::SUnionStore(dest, keys)
+            | Command::SDiffStore(dest, keys)
+            | Command::BitOp(_, dest, keys) => {
+                std::iter::once(dest).chain(keys).map(String::as_str).collect()
+            }
+            Command::Migrate(migrate) => migrate.keys.iter().map(String::as_str).collect(),
+            Command::XRead(XRead { streams, .. })
+            | Command::XReadGroup(XReadGroup { streams, .. }) => {
+                streams.iter().map(|(key, _)| key.as_str()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// 命令所属的 ACL 分类（不含 `all`），分类名与 Redis 一致
+    pub fn categories(&self) -> Vec<&'static str> {
+        let data_type = match self {
+            Command::Get(..)
+            | Command::Set(..)
+            | Command::SetWith(..)
+            | Command::GetSet(..)
+            | Command::Incr(..)
+            | Command::IncrBy(..) => Some("string"),
+            Command::SetBit(..)
+            | Command::GetBit(..)
+            | Command::BitCount(..)
+            | Command::BitPos(..)
+            | Command::BitOp(..) => Some("bitmap"),
+            Command::HSet(..)
+            | Command::HGet(..)
+            | Command::HIncrBy(..)
+            | Command::HIncrByFloat(..)
+            | Command::HRandField(..) => Some("hash"),
+            Command::SAdd(..)
+            | Command::SRem(..)
+            | Command::SMembers(..)
+            | Command::SIsMember(..)
+            | Command::SCard(..)
+            | Command::SMIsMember(..)
+            | Command::SPop(..)
+            | Command::SRandMember(..)
+            | Command::SInter(..)
+            | Command::SUnion(..)
+            | Command::SDiff(..)
+            | Command::SInterStore(..)
+            | Command::SUnionStore(..)
+            | Command::SDiffStore(..)
+            | Command::SInterCard(..) => Some("set"),
+            Command::ZAdd(..)
+            | Command::ZScore(..)
+            | Command::ZCard(..)
+            | Command::ZRange(..)
+            | Command::ZRangeByScore(..)
+            | Command::ZRangeByLex(..)
+            | Command::ZRank(..)
+            | Command::ZIncrBy(..)
+            | Command::ZPopMin(..)
+            | Command::ZPopMax(..)
+            | Command::BZPopMin(..)
+            | Command::BZPopMax(..) => Some("sortedset"),
+            Command::GeoAdd(..)
+            | Command::GeoDist(..)
+            | Command::GeoPos(..)
+            | Command::GeoSearch(..) => Some("geo"),
+            Command::XAdd(..)
+            | Command::XLen(..)
+            | Command::XRange(..)
+            | Command::XRead(..)
+            | Command::XGroupCreate(..)
+            | Command::XGroupSetId(..)
+            | Command::XGroupDestroy(..)
+            | Command::XGroupCreateConsumer(..)
+            | Command::XReadGroup(..)
+            | Command::XAck(..)
+            | Command::XPending(..)
+            | Command::XClaim(..) => Some("stream"),
+            Command::Keys(..)
+            | Command::Scan(..)
+            | Command::Expire(..)
+            | Command::Persist(..)
+            | Command::Ttl(..)
+            | Command::PTtl(..)
+            | Command::ExpireTime(..)
+            | Command::PExpireTime(..)
+            | Command::Del(..)
+            | Command::Unlink(..)
+            | Command::ObjectEncoding(..)
+            | Command::ObjectRefCount(..)
+            | Command::ObjectIdleTime(..)
+            | Command::Dump(..)
+            | Command::Restore(..)
+            | Command::Migrate(..)
+            | Command::DbSize
+            | Command::RandomKey
+            | Command::BigKeys
+            | Command::HotKeys(..)
+            | Command::Export
+            | Command::Import(..)
+            | Command::Touch(..)
+            | Command::FlushDb(..)
+            | Command::FlushAll(..)
+            | Command::Select(..)
+            | Command::Move(..)
+            | Command::SwapDb(..) => Some("keyspace"),
+            Command::Publish(..)
+            | Command::Subscribe(..)
+            | Command::Unsubscribe(..)
+            | Command::PSubscribe(..)
+            | Command::PUnsubscribe(..)
+            | Command::PubSubChannels(..)
+            | Command::PubSubNumSub(..)
+            | Command::PubSubNumPat
+            | Command::SPublish(..)
+            | Command::SSubscribe(..)
+            | Command::SUnsubscribe(..)
+            | Command::PubSubShardChannels(..)
+            | Command::PubSubShardNumSub(..) => Some("pubsub"),
+            _ => None,
+        };
+
+        let mut categories: Vec<_> = data_type.into_iter().collect();
+        if self.is_write() {
+            categories.push("write");
+        } else if !self.keys().is_empty()
+            || matches!(
+                self,
+                Command::Keys(..)
+                    | Command::Scan(..)
+                    | Command::DbSize
+                    | Command::RandomKey
+                    | Command::BigKeys
+                    | Command::HotKeys(..)
+                    | Command::Export
+                    | Command::Info(..)
+            )
+        {
+            categories.push("read");
+        }
+        if matches!(
+            self,
+            Command::BZPopMin(..)
+                | Command::BZPopMax(..)
+                | Command::XRead(XRead { block: Some(_), .. })
+                | Command::XReadGroup(XReadGroup { block: Some(_), .. })
+        ) {
+            categories.push("blocking");
+        }
+        if matches!(
+            self,
+            Command::BgRewriteAof
+                | Command::Save
+                | Command::BgSave
+                | Command::LastSave
+                | Command::ReplicaOf(..)
+                | Command::Role
+                | Command::ReplConf(..)
+                | Command::Psync(..)
+                | Command::Sync
+                | Command::SentinelMonitor(..)
+                | Command::SentinelRemove(..)
+                | Command::SentinelSet(..)
+                | Command::SentinelMasters
+                | Command::SentinelMaster(..)
+                | Command::SentinelReplicas(..)
+                | Command::SentinelSentinels(..)
+                | Command::SentinelGetMasterAddr(..)
+                | Command::SentinelIsMasterDownByAddr(..)
+                | Command::SentinelFailover(..)
+                | Command::SentinelMyId
+                | Command::ConfigGet(..)
+                | Command::ConfigSet(..)
+                | Command::ConfigResetStat
+                | Command::LatencyLatest
+                | Command::LatencyHistory(..)
+                | Command::LatencyReset(..)
+                | Command::DebugSleep(..)
+                | Command::DebugDelay(..)
+                | Command::DebugDrop(..)
+                | Command::DebugContend(..)
+                | Command::DebugFaults
+                | Command::DebugReset
+                | Command::AclSetUser(..)
+                | Command::AclGetUser(..)
+                | Command::AclList
+                | Command::ClientList
+                | Command::ClientKill(..)
+                | Command::ClientKillAddr(..)
+                | Command::Monitor
+                | Command::Export
+                | Command::Import(..)
+        ) {
+            categories.extend(["admin", "dangerous"]);
+        } else if matches!(
+            self,
+            Command::Keys(..)
+                | Command::FlushDb(..)
+                | Command::FlushAll(..)
+                | Command::SwapDb(..)
+                | Command::Migrate(..)
+                | Command::Restore(..)
+                | Command::Info(..)
+        ) {
+            categories.push("dangerous");
+        }
+        if matches!(
+            self,
+            Command::Select(..)
+                | Command::Auth(..)
+                | Command::Wait(..)
+                | Command::AclWhoAmI
+                | Command::ClientId
+                | Command::ClientSetName(..)
+                | Command::ClientSetInfo(..)
+                | Command::ClientGetName
+                | Command::ClientList
+                | Command::ClientKill(..)
+                | Command::ClientKillAddr(..)
+                | Command::Hello(..)
+                | Command::Ping(..)
+                | Command::Echo(..)
+                | Command::Quit
+                | Command::Reset
+                | Command::ReadOnly
+                | Command::ReadWrite
+                | Command::Commands
+                | Command::CommandCount
+                | Command::CommandInfo(..)
+                | Command::CommandDocs(..)
+        ) {
+            categories.push("connection");
+        }
+        categories
+    }
+
+    /// 是否为可能增加内存占用的写命令：超出 maxmemory 且无法淘汰时拒绝执行
+    ///
+    /// 只删除数据的写命令（DEL、SPOP 等）始终允许，便于在内存不足时腾出空间。
+    pub fn is_denyoom(&self) -> bool {
+        self.is_write()
+            && !matches!(
+                self,
+                Command::Expire(..)
+                    | Command::Persist(..)
+                    | Command::SRem(..)
+                    | Command::SPop(..)
+                    | Command::ZPopMin(..)
+                    | Command::ZPopMax(..)
+                    | Command::BZPopMin(..)
+                    | Command::BZPopMax(..)
+                    | Command::XGroupSetId(..)
+                    | Command::XGroupDestroy(..)
+                    | Command::XReadGroup(..)
+                    | Command::XAck(..)
+                    | Command::XClaim(..)
+                    | Command::Del(..)
+                    | Command::Unlink(..)
+                    | Command::Migrate(..)
+                    | Command::FlushDb(..)
+                    | Command::FlushAll(..)
+                    | Command::Move(..)
+                    | Command::SwapDb(..)
+            )
+    }
+
+    /// 是否为会修改数据的写命令
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            Command::Set(..)
+                | Command::SetWith(..)
+                | Command::GetSet(..)
+                | Command::Incr(..)
+                | Command::IncrBy(..)
+                | Command::SetBit(..)
+                | Command::BitOp(..)
+                | Command::Expire(..)
+                | Command::Persist(..)
+                | Command::HSet(..)
+                | Command::HIncrBy(..)
+                | Command::HIncrByFloat(..)
+                | Command::SAdd(..)
+                | Command::SRem(..)
+                | Command::SPop(..)
+                | Command::SInterStore(..)
+                | Command::SUnionStore(..)
+                | Command::SDiffStore(..)
+                | Command::ZAdd(..)
+                | Command::ZIncrBy(..)
+                | Command::ZPopMin(..)
+                | Command::ZPopMax(..)
+                | Command::BZPopMin(..)
+                | Command::BZPopMax(..)
+                | Command::GeoAdd(..)
+                | Command::XAdd(..)
+                | Command::XGroupCreate(..)
+                | Command::XGroupSetId(..)
+                | Command::XGroupDestroy(..)
+                | Command::XGroupCreateConsumer(..)
+                | Command::XReadGroup(..)
+                | Command::XAck(..)
+                | Command::XClaim(..)
+                | Command::Del(..)
+                | Command::Unlink(..)
+                | Command::Restore(..)
+                | Command::Import(..)
+                | Command::Migrate(..)
+                | Command::FlushDb(..)
+                | Command::FlushAll(..)
+                | Command::Move(..)
+                | Command::SwapDb(..)
+        )
+    }
+}
+
+/// 按 [`arity`] 检查参数个数；带子命令的命令先检查命令本身，再检查子命令
+fn check_arity(parts: &[&str]) -> Result<(), ParseError> {
+    let [name, args @ ..] = parts else {
+        return Err(ParseError::UnknownCommand(String::new(), Vec::new()));
+    };
+    let lower = name.to_ascii_lowercase();
+    let Some(expected) = arity(&lower) else {
+        return Err(ParseError::UnknownCommand(name.to_string(), to_strings(args)));
+    };
+    if !arity_matches(expected, parts.len()) {
+        return Err(ParseError::WrongArity(lower));
+    }
+    if !has_subcommands(&lower) {
+        return Ok(());
+    }
+
+    let Some(sub) = args.first() else {
+        return Ok(());
+    };
+    let full = format!("{lower}|{}", sub.to_ascii_lowercase());
+    match arity(&full) {
+        Some(expected) if !arity_matches(expected, parts.len()) => {
+            Err(ParseError::WrongArity(full))
+        }
+        Some(_) => Ok(()),
+        None => Err(ParseError::UnknownSubcommand(lower, sub.to_string())),
+    }
+}
+
+/// 参数个数（含命令名）是否满足要求
+fn arity_matches(arity: i64, len: usize) -> bool {
+    let len = len as i64;
+    if arity >= 0 { len == arity } else { len >= -arity }
+}
+
+/// 命令是否由子命令组成（如 CONFIG GET），即命令表中有 `name|...` 形式的条目
+fn has_subcommands(name: &str) -> bool {
+    handler::specs()
+        .any(|spec| spec.name.strip_prefix(name).is_some_and(|sub| sub.starts_with('|')))
+}
+
+/// 解析整数参数
+fn int<T: FromStr>(arg: &str) -> Result<T, ParseError> {
+    arg.parse().map_err(|_| ParseError::InvalidInteger)
+}
+
+/// 解析弹出的数量，负数与 Redis 一样返回 `NegativeCount`
+fn parse_count(arg: &str) -> Result<usize, ParseError> {
+    match int::<i128>(arg)? {
+        count if count < 0 => Err(ParseError::NegativeCount),
+        count => usize::try_from(count).map_err(|_| ParseError::InvalidInteger),
+    }
+}
+
+/// 解析浮点数参数，拒绝 NaN
+fn float(arg: &str) -> Result<f64, ParseError> {
+    arg.parse::<f64>().ok().filter(|n| !n.is_nan()).ok_or(ParseError::InvalidFloat)
+}
+
+/// 解析形如 `ms-seq` 的消息 ID
+fn stream_id(arg: &str) -> Result<StreamId, ParseError> {
+    StreamId::parse(arg, 0).ok_or(ParseError::InvalidStreamId)
+}
+
+/// 解析 CLIENT KILL 的 `<filter> <value>` 过滤条件，至少需要一个 ID 或 ADDR 条件
+/// 解析 SENTINEL 的子命令，`sub` 为子命令名，`args` 为其后的参数
+fn parse_sentinel(sub: &str, args: &[&str]) -> Result<Command, ParseError> {
+    Ok(match (sub.to_ascii_lowercase().as_str(), args) {
+        ("monitor", [name, host, port, quorum]) => {
+            Command::SentinelMonitor(name.to_string(), host.to_string(), int(port)?, int(quorum)?)
+        }
+        ("remove", [name]) => Command::SentinelRemove(name.to_string()),
+        ("set", [name, options @ ..]) if !options.is_empty() && options.len().is_multiple_of(2) => {
+            let options =
+                options.chunks(2).map(|pair| (pair[0].to_string(), pair[1].to_string())).collect();
+            Command::SentinelSet(name.to_string(), options)
+        }
+        ("masters", []) => Command::SentinelMasters,
+        ("master", [name]) => Command::SentinelMaster(name.to_string()),
+        ("replicas", [name]) => Command::SentinelReplicas(name.to_string()),
+        ("sentinels", [name]) => Command::SentinelSentinels(name.to_string()),
+        ("get-master-addr-by-name", [name]) => Command::SentinelGetMasterAddr(name.to_string()),
+        ("is-master-down-by-addr", [host, port, epoch, runid]) => {
+            Command::SentinelIsMasterDownByAddr(
+                host.to_string(),
+                int(port)?,
+                int(epoch)?,
+                runid.to_string(),
+            )
+        }
+        ("failover", [name]) => Command::SentinelFailover(name.to_string()),
+        ("myid", []) => Command::SentinelMyId,
+        _ => return Err(ParseError::Syntax),
+    })
+}
+
+fn parse_client_kill(args: &[&str]) -> Result<ClientKill, ParseError> {
+    if args.is_empty() || !args.len().is_multiple_of(2) {
+        return Err(ParseError::Syntax);
+    }
+
+    let mut filter = ClientKill { skip_me: true, ..ClientKill::default() };
+    for pair in args.chunks(2) {
+        let value = pair[1];
+        match pair[0].to_ascii_lowercase().as_str() {
+            "id" => filter.id = Some(int(value)?),
+            "addr" => filter.addr = Some(value.to_string()),
+            "skipme" => {
+                filter.skip_me = match value.to_ascii_lowercase().as_str() {
+                    "yes" => true,
+                    "no" => false,
+                    _ => return Err(ParseError::Syntax),
+                }
+            }
+            _ => return Err(ParseError::Syntax),
+        }
+    }
+    if filter.id.is_none() && filter.addr.is_none() {
+        return Err(ParseError::Syntax);
+    }
+    Ok(filter)
+}
+
+/// 解析 HELLO 的协议版本与 AUTH、SETNAME 选项
+fn parse_hello(protover: &str, options: &[&str]) -> Result<Hello, ParseError> {
+    let mut hello = Hello { protover: Some(int(protover)?), ..Hello::default() };
+    let mut rest = options;
+    loop {
+        rest = match rest {
+            [] => return Ok(hello),
+            [option, user, password, rest @ ..] if option.eq_ignore_ascii_case("auth") => {
+                hello.auth = Some((user.to_string(), password.to_string()));
+                rest
+            }
+            [option, name, rest @ ..] if option.eq_ignore_ascii_case("setname") => {
+                hello.name = Some(name.to_string());
+                rest
+            }
+            _ => return Err(ParseError::Syntax),
+        };
+    }
+}
+
+/// 解析 RESTORE 的 ttl 与选项
+fn parse_restore(
+    key: &str,
+    ttl: &str,
+    payload: &str,
+    options: &[&str],
+) -> Result<Command, ParseError> {
+    let (mut replace, mut absttl) = (false, false);
+    for option in options {
+        match option.to_ascii_lowercase().as_str() {
+            "replace" => replace = true,
+            "absttl" => absttl = true,
+            _ => return Err(ParseError::Syntax),
+        }
+    }
+    Ok(Command::Restore(key.to_string(), int(ttl)?, payload.to_string(), replace, absttl))
+}
+
+/// 解析 MIGRATE 的参数：使用 KEYS 选项时单个键的位置必须为空字符串
+fn parse_migrate(args: &[&str]) -> Result<Command, ParseError> {
+    let [host, port, key, db, timeout, options @ ..] = args else {
+        return Err(ParseError::WrongArity("migrate".into()));
+    };
+    let mut migrate = Migrate {
+        host: host.to_string(),
+        port: int(port)?,
+        keys: Vec::new(),
+        db: int(db)?,
+        timeout: int(timeout)?,
+        copy: false,
+        replace: false,
+    };
+
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match option.to_ascii_lowercase().as_str() {
+            "copy" => migrate.copy = true,
+            "replace" => migrate.replace = true,
+            "keys" if key.is_empty() => {
+                migrate.keys = to_strings(options.as_slice());
+                break;
+            }
+            _ => return Err(ParseError::Syntax),
         }
     }
+
+    if key.is_empty() {
+        if migrate.keys.is_empty() {
+            return Err(ParseError::Syntax);
+        }
+    } else {
+        migrate.keys.push(key.to_string());
+    }
+    Ok(Command::Migrate(migrate))
+}
+
+/// 解析 SET 的选项，过期时间与 KEEPTTL 只能给出一个
+fn parse_set_options(args: &[&str]) -> Result<SetOptions, ParseError> {
+    let mut options = SetOptions::default();
+    let mut rest = args;
+    while let [option, tail @ ..] = rest {
+        rest = tail;
+        match option.to_ascii_lowercase().as_str() {
+            "get" => options.get = true,
+            "keepttl" if options.expiry.is_none() => options.keep_ttl = true,
+            "ex" | "px" | "exat" | "pxat" if options.expiry.is_none() && !options.keep_ttl => {
+                let [time, tail @ ..] = rest else {
+                    return Err(ParseError::Syntax);
+                };
+                options.expiry = Some(parse_set_expiry(option, time)?);
+                rest = tail;
+            }
+            _ => return Err(ParseError::Syntax),
+        }
+    }
+    Ok(options)
+}
+
+/// 解析 SET 的过期选项，时间必须为正数，换算为截止时间时不能溢出
+fn parse_set_expiry(option: &str, time: &str) -> Result<Expiry, ParseError> {
+    let unit = match option.to_ascii_lowercase().as_str() {
+        "ex" => Expiry::Seconds,
+        "px" => Expiry::Millis,
+        "exat" => Expiry::UnixSeconds,
+        "pxat" => Expiry::UnixMillis,
+        _ => return Err(ParseError::Syntax),
+    };
+    let time = int(time)?;
+    let expiry = unit(time);
+    if time <= 0 || expiry.checked_deadline_ms(unix_time_ms()).is_none() {
+        return Err(ParseError::InvalidExpireTime("set".into()));
+    }
+    Ok(expiry)
+}
+
+/// 解析位偏移量，与 Redis 一样限制在 2^32 位（512MB）以内
+fn parse_bit_offset(arg: &str) -> Result<usize, ParseError> {
+    arg.parse::<u32>().map(|offset| offset as usize).map_err(|_| ParseError::InvalidBitOffset)
+}
+
+/// 解析位的值，只接受 `0` 或 `1`
+fn parse_bit(arg: &str) -> Result<bool, ParseError> {
+    match arg {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => Err(ParseError::InvalidInteger),
+    }
+}
+
+/// 解析 `start end [BYTE|BIT]`
+fn parse_bit_range(args: &[&str]) -> Result<BitRange, ParseError> {
+    let (start, end, unit) = match args {
+        [start, end] => (start, end, BitUnit::Byte),
+        [start, end, unit] if unit.eq_ignore_ascii_case("byte") => (start, end, BitUnit::Byte),
+        [start, end, unit] if unit.eq_ignore_ascii_case("bit") => (start, end, BitUnit::Bit),
+        _ => return Err(ParseError::Syntax),
+    };
+    Ok(BitRange { start: int(start)?, end: Some(int(end)?), unit })
+}
+
+/// 解析阻塞命令的超时时间（秒），必须是非负数
+fn parse_timeout(arg: &str) -> Result<f64, ParseError> {
+    Some(float(arg)?)
+        .filter(|timeout| timeout.is_finite() && *timeout >= 0.0)
+        .ok_or(ParseError::InvalidFloat)
+}
+
+/// 解析 `ZADD key [NX|XX] [GT|LT] [CH] score member [score member ...]` 中 key 之后的部分
+fn parse_zadd(key: &str, mut args: &[&str]) -> Result<Command, ParseError> {
+    let mut flags = AddFlags::default();
+    while let [option, rest @ ..] = args {
+        match option.to_ascii_lowercase().as_str() {
+            "nx" => flags.nx = true,
+            "xx" => flags.xx = true,
+            "gt" => flags.gt = true,
+            "lt" => flags.lt = true,
+            "ch" => flags.ch = true,
+            _ => break,
+        }
+        args = rest;
+    }
+
+    let conflicting =
+        (flags.nx && flags.xx) || (flags.gt && flags.lt) || (flags.nx && (flags.gt || flags.lt));
+    if conflicting || args.is_empty() || !args.len().is_multiple_of(2) {
+        return Err(ParseError::Syntax);
+    }
+
+    let pairs = args.chunks(2).map(|pair| Ok((float(pair[0])?, pair[1].to_string())));
+    Ok(Command::ZAdd(key.to_string(), flags, pairs.collect::<Result<_, _>>()?))
+}
+
+/// 解析 `EXPIRE|PEXPIRE|EXPIREAT|PEXPIREAT key time [NX|XX|GT|LT]`
+fn parse_expire(
+    name: &str,
+    key: &str,
+    time: &str,
+    options: &[&str],
+) -> Result<Command, ParseError> {
+    let time = int(time)?;
+    let name = name.to_ascii_lowercase();
+    let expiry = match name.as_str() {
+        "expire" => Expiry::Seconds(time),
+        "pexpire" => Expiry::Millis(time),
+        "expireat" => Expiry::UnixSeconds(time),
+        "pexpireat" => Expiry::UnixMillis(time),
+        _ => return Err(ParseError::Syntax),
+    };
+    // 负数时间表示立即过期，换算为截止时间溢出的才是非法的
+    if expiry.checked_deadline_ms(unix_time_ms()).is_none() {
+        return Err(ParseError::InvalidExpireTime(name));
+    }
+
+    let mut flags = ExpireFlags::default();
+    for option in options {
+        match option.to_ascii_lowercase().as_str() {
+            "nx" => flags.nx = true,
+            "xx" => flags.xx = true,
+            "gt" => flags.gt = true,
+            "lt" => flags.lt = true,
+            _ => return Err(ParseError::Syntax),
+        }
+    }
+    // NX 不能与其他条件同时使用，GT 与 LT 互斥
+    if (flags.nx && (flags.xx || flags.gt || flags.lt)) || (flags.gt && flags.lt) {
+        return Err(ParseError::Syntax);
+    }
+    Ok(Command::Expire(key.to_string(), expiry, flags))
+}
+
+/// 解析经度或纬度，范围在写入或搜索时检查
+fn parse_coord(arg: &str) -> Result<f64, ParseError> {
+    Some(float(arg)?).filter(|coord| coord.is_finite()).ok_or(ParseError::InvalidFloat)
+}
+
+/// 解析 GEOSEARCH 的半径或宽高，必须是非负数
+fn parse_length(arg: &str) -> Result<f64, ParseError> {
+    Some(float(arg)?)
+        .filter(|length| length.is_finite() && *length >= 0.0)
+        .ok_or(ParseError::InvalidFloat)
+}
+
+/// 解析距离单位
+fn parse_unit(arg: &str) -> Result<Unit, ParseError> {
+    Unit::parse(arg).ok_or(ParseError::Syntax)
+}
+
+/// 解析 `GEOADD key [NX|XX] [CH] longitude latitude member [...]` 中 key 之后的部分
+fn parse_geoadd(key: &str, mut args: &[&str]) -> Result<Command, ParseError> {
+    let mut flags = AddFlags::default();
+    while let [option, rest @ ..] = args {
+        match option.to_ascii_lowercase().as_str() {
+            "nx" => flags.nx = true,
+            "xx" => flags.xx = true,
+            "ch" => flags.ch = true,
+            _ => break,
+        }
+        args = rest;
+    }
+    if (flags.nx && flags.xx) || args.is_empty() || !args.len().is_multiple_of(3) {
+        return Err(ParseError::Syntax);
+    }
+
+    let locations = args.chunks(3).map(|location| {
+        Ok((parse_coord(location[0])?, parse_coord(location[1])?, location[2].to_string()))
+    });
+    Ok(Command::GeoAdd(key.to_string(), flags, locations.collect::<Result<_, _>>()?))
+}
+
+/// 解析 GEOSEARCH 中 key 之后的部分，中心与范围必须各给出一次
+fn parse_geosearch(mut args: &[&str]) -> Result<GeoSearch, ParseError> {
+    let (mut origin, mut shape, mut unit) = (None, None, Unit::M);
+    let (mut order, mut count, mut any) = (None, None, false);
+    let (mut with_coord, mut with_dist, mut with_hash) = (false, false, false);
+    while let [option, rest @ ..] = args {
+        args = match (option.to_ascii_lowercase().as_str(), rest) {
+            ("frommember", [member, rest @ ..]) if origin.is_none() => {
+                origin = Some(Origin::Member(member.to_string()));
+                rest
+            }
+            ("fromlonlat", [lon, lat, rest @ ..]) if origin.is_none() => {
+                origin = Some(Origin::LonLat(parse_coord(lon)?, parse_coord(lat)?));
+                rest
+            }
+            ("byradius", [radius, u, rest @ ..]) if shape.is_none() => {
+                unit = parse_unit(u)?;
+                shape = Some(Shape::Radius(parse_length(radius)? * unit.meters()));
+                rest
+            }
+            ("bybox", [width, height, u, rest @ ..]) if shape.is_none() => {
+                unit = parse_unit(u)?;
+                let (width, height) = (parse_length(width)?, parse_length(height)?);
+                shape = Some(Shape::Box {
+                    width: width * unit.meters(),
+                    height: height * unit.meters(),
+                });
+                rest
+            }
+            ("asc", rest) => {
+                order = Some(Order::Asc);
+                rest
+            }
+            ("desc", rest) => {
+                order = Some(Order::Desc);
+                rest
+            }
+            ("count", [n, rest @ ..]) => {
+                count = match int(n)? {
+                    0 => return Err(ParseError::InvalidInteger),
+                    n => Some(n),
+                };
+                rest
+            }
+            ("any", rest) => {
+                any = true;
+                rest
+            }
+            ("withcoord", rest) => {
+                with_coord = true;
+                rest
+            }
+            ("withdist", rest) => {
+                with_dist = true;
+                rest
+            }
+            ("withhash", rest) => {
+                with_hash = true;
+                rest
+            }
+            _ => return Err(ParseError::Syntax),
+        };
+    }
+    let (Some(origin), Some(shape)) = (origin, shape) else { return Err(ParseError::Syntax) };
+    // ANY 必须与 COUNT 一起使用
+    if any && count.is_none() {
+        return Err(ParseError::Syntax);
+    }
+
+    let query = Query { origin, shape, order, count, any };
+    Ok(GeoSearch { query, unit, with_coord, with_dist, with_hash })
+}
+
+/// 解析 `ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]`
+fn parse_zrangebyscore(
+    key: &str,
+    min: &str,
+    max: &str,
+    mut options: &[&str],
+) -> Result<Command, ParseError> {
+    let range = ScoreRange::parse(min, max).ok_or(ParseError::InvalidFloat)?;
+    let (mut with_scores, mut limit) = (false, (0, -1));
+    loop {
+        match options {
+            [] => break,
+            [option, rest @ ..] if option.eq_ignore_ascii_case("withscores") => {
+                with_scores = true;
+                options = rest;
+            }
+            [option, offset, count, rest @ ..] if option.eq_ignore_ascii_case("limit") => {
+                limit = (int(offset)?, int(count)?);
+                options = rest;
+            }
+            _ => return Err(ParseError::Syntax),
+        }
+    }
+    Ok(Command::ZRangeByScore(key.to_string(), range, with_scores, limit.0, limit.1))
+}
+
+/// 解析 `ZRANGEBYLEX key min max [LIMIT offset count]`
+fn parse_zrangebylex(
+    key: &str,
+    min: &str,
+    max: &str,
+    options: &[&str],
+) -> Result<Command, ParseError> {
+    let range = LexRange::parse(min, max).ok_or(ParseError::Syntax)?;
+    let (offset, count) = match options {
+        [] => (0, -1),
+        [option, offset, count] if option.eq_ignore_ascii_case("limit") => {
+            (int(offset)?, int(count)?)
+        }
+        _ => return Err(ParseError::Syntax),
+    };
+    Ok(Command::ZRangeByLex(key.to_string(), range, offset, count))
+}
+
+/// 解析 `ZRANGE key start stop [REV] [WITHSCORES]`
+fn parse_zrange(
+    key: &str,
+    start: &str,
+    stop: &str,
+    options: &[&str],
+) -> Result<Command, ParseError> {
+    let (mut rev, mut with_scores) = (false, false);
+    for option in options {
+        match option.to_ascii_lowercase().as_str() {
+            "rev" => rev = true,
+            "withscores" => with_scores = true,
+            _ => return Err(ParseError::Syntax),
+        }
+    }
+    Ok(Command::ZRange(key.to_string(), int(start)?, int(stop)?, rev, with_scores))
+}
+
+/// 解析 `XRANGE key start end [COUNT count]`
+fn parse_xrange(
+    key: &str,
+    start: &str,
+    end: &str,
+    options: &[&str],
+) -> Result<Command, ParseError> {
+    let range = IdRange::parse(start, end).ok_or(ParseError::InvalidStreamId)?;
+    let count = match options {
+        [] => None,
+        [option, count] if option.eq_ignore_ascii_case("count") => Some(int(count)?),
+        _ => return Err(ParseError::Syntax),
+    };
+    Ok(Command::XRange(key.to_string(), range, count))
+}
+
+/// 解析 `XREAD [COUNT count] [BLOCK ms] STREAMS key [key ...] id [id ...]` 中命令名之后的部分
+fn parse_scan(cursor: &str, mut args: &[&str]) -> Result<Scan, ParseError> {
+    let cursor = cursor.parse().map_err(|_| ParseError::InvalidCursor)?;
+    let mut scan = Scan { cursor, pattern: None, count: DEFAULT_SCAN_COUNT, key_type: None };
+    loop {
+        match args {
+            [] => return Ok(scan),
+            [option, pattern, rest @ ..] if option.eq_ignore_ascii_case("match") => {
+                scan.pattern = Some(pattern.to_string());
+                args = rest;
+            }
+            [option, n, rest @ ..] if option.eq_ignore_ascii_case("count") => {
+                scan.count = match int(n)? {
+                    0 => return Err(ParseError::Syntax),
+                    n => n,
+                };
+                args = rest;
+            }
+            [option, key_type, rest @ ..] if option.eq_ignore_ascii_case("type") => {
+                scan.key_type = Some(key_type.to_ascii_lowercase());
+                args = rest;
+            }
+            _ => return Err(ParseError::Syntax),
+        }
+    }
+}
+
+fn parse_xread(mut args: &[&str]) -> Result<XRead, ParseError> {
+    let (mut count, mut block) = (None, None);
+    let streams = loop {
+        match args {
+            [option, n, rest @ ..] if option.eq_ignore_ascii_case("count") => {
+                count = Some(int(n)?);
+                args = rest;
+            }
+            [option, ms, rest @ ..] if option.eq_ignore_ascii_case("block") => {
+                block = Some(int(ms)?);
+                args = rest;
+            }
+            [option, rest @ ..] if option.eq_ignore_ascii_case("streams") => break rest,
+            _ => return Err(ParseError::Syntax),
+        }
+    };
+    Ok(XRead { count, block, streams: parse_streams(streams, "$")? })
+}
+
+/// 解析 `STREAMS` 之后的 `key [key ...] id [id ...]`，ID 为 `special`（`$` 或 `>`）时记为 `None`
+fn parse_streams(
+    args: &[&str],
+    special: &str,
+) -> Result<Vec<(String, Option<StreamId>)>, ParseError> {
+    if args.is_empty() || !args.len().is_multiple_of(2) {
+        return Err(ParseError::Syntax);
+    }
+
+    let (keys, ids) = args.split_at(args.len() / 2);
+    let ids = ids.iter().map(|id| match *id {
+        id if id == special => Ok(None),
+        id => stream_id(id).map(Some),
+    });
+    let ids = ids.collect::<Result<Vec<_>, _>>()?;
+    Ok(keys.iter().map(|key| key.to_string()).zip(ids).collect())
+}
+
+/// 解析 XGROUP 的子命令及其参数，`$` 记为 `None`
+fn parse_xgroup(sub: &str, args: &[&str]) -> Result<Command, ParseError> {
+    let id = |arg: &str| match arg {
+        "$" => Ok(None),
+        arg => stream_id(arg).map(Some),
+    };
+    let command = match (sub.to_ascii_lowercase().as_str(), args) {
+        ("create", [key, group, last_id]) => {
+            Command::XGroupCreate(key.to_string(), group.to_string(), id(last_id)?, false)
+        }
+        ("create", [key, group, last_id, option]) if option.eq_ignore_ascii_case("mkstream") => {
+            Command::XGroupCreate(key.to_string(), group.to_string(), id(last_id)?, true)
+        }
+        ("setid", [key, group, last_id]) => {
+            Command::XGroupSetId(key.to_string(), group.to_string(), id(last_id)?)
+        }
+        ("destroy", [key, group]) => Command::XGroupDestroy(key.to_string(), group.to_string()),
+        ("createconsumer", [key, group, consumer]) => {
+            Command::XGroupCreateConsumer(key.to_string(), group.to_string(), consumer.to_string())
+        }
+        _ => return Err(ParseError::Syntax),
+    };
+    Ok(command)
+}
+
+/// 解析 `XREADGROUP GROUP group consumer` 之后的选项与 `STREAMS` 部分
+fn parse_xreadgroup(
+    group: &str,
+    consumer: &str,
+    mut args: &[&str],
+) -> Result<XReadGroup, ParseError> {
+    let (mut count, mut block, mut noack) = (None, None, false);
+    let streams = loop {
+        match args {
+            [option, n, rest @ ..] if option.eq_ignore_ascii_case("count") => {
+                count = Some(int(n)?);
+                args = rest;
+            }
+            [option, ms, rest @ ..] if option.eq_ignore_ascii_case("block") => {
+                block = Some(int(ms)?);
+                args = rest;
+            }
+            [option, rest @ ..] if option.eq_ignore_ascii_case("noack") => {
+                noack = true;
+                args = rest;
+            }
+            [option, rest @ ..] if option.eq_ignore_ascii_case("streams") => break rest,
+            _ => return Err(ParseError::Syntax),
+        }
+    };
+    Ok(XReadGroup {
+        group: group.to_string(),
+        consumer: consumer.to_string(),
+        count,
+        block,
+        noack,
+        streams: parse_streams(streams, ">")?,
+    })
+}
+
+/// 解析 `XPENDING key group [[IDLE min-idle-time] start end count [consumer]]`
+fn parse_xpending(key: &str, group: &str, args: &[&str]) -> Result<Command, ParseError> {
+    let (min_idle, args) = match args {
+        [option, idle, rest @ ..] if option.eq_ignore_ascii_case("idle") => {
+            (Some(int(idle)?), rest)
+        }
+        args => (None, args),
+    };
+    let range = match args {
+        [] if min_idle.is_none() => None,
+        [start, end, count, consumer @ ..] if consumer.len() <= 1 => Some(XPendingRange {
+            min_idle,
+            range: IdRange::parse(start, end).ok_or(ParseError::InvalidStreamId)?,
+            count: int(count)?,
+            consumer: consumer.first().map(|consumer| consumer.to_string()),
+        }),
+        _ => return Err(ParseError::Syntax),
+    };
+    Ok(Command::XPending(key.to_string(), group.to_string(), range))
+}
+
+/// 解析 `XCLAIM key group consumer min-idle-time id [id ...] [options]` 中 min-idle-time 之后的部分
+fn parse_xclaim(
+    key: &str,
+    group: &str,
+    consumer: &str,
+    min_idle: &str,
+    args: &[&str],
+) -> Result<Command, ParseError> {
+    let mut options = ClaimOptions { min_idle: int(min_idle)?, ..ClaimOptions::default() };
+    let ids: Vec<_> = args.iter().map_while(|arg| StreamId::parse(arg, 0)).collect();
+    if ids.is_empty() {
+        return Err(ParseError::InvalidStreamId);
+    }
+
+    let mut args = &args[ids.len()..];
+    loop {
+        match args {
+            [] => break,
+            [option, rest @ ..] if option.eq_ignore_ascii_case("force") => {
+                options.force = true;
+                args = rest;
+            }
+            [option, rest @ ..] if option.eq_ignore_ascii_case("justid") => {
+                options.just_id = true;
+                args = rest;
+            }
+            [option, value, rest @ ..] => {
+                match option.to_ascii_lowercase().as_str() {
+                    "idle" => options.idle = Some(int(value)?),
+                    "time" => options.time = Some(int(value)?),
+                    "retrycount" => options.retry_count = Some(int(value)?),
+                    "lastid" => options.last_id = Some(stream_id(value)?),
+                    _ => return Err(ParseError::Syntax),
+                }
+                args = rest;
+            }
+            _ => return Err(ParseError::Syntax),
+        }
+    }
+    Ok(Command::XClaim(key.to_string(), group.to_string(), consumer.to_string(), ids, options))
+}
+
+/// 将参数切片转换为字符串列表
+fn to_strings(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// 解析 FLUSHDB / FLUSHALL 的可选参数，返回是否异步释放
+fn parse_flush_mode(mode: &[&str]) -> Result<bool, ParseError> {
+    match mode {
+        [] => Ok(false),
+        [mode] if mode.eq_ignore_ascii_case("sync") => Ok(false),
+        [mode] if mode.eq_ignore_ascii_case("async") => Ok(true),
+        _ => Err(ParseError::Syntax),
+    }
+}
+
+/// 解析 `SINTERCARD numkeys key [key ...] [LIMIT limit]` 中 numkeys 之后的部分
+fn parse_sintercard(numkeys: &str, rest: &[&str]) -> Result<Command, ParseError> {
+    let numkeys: usize = int(numkeys)?;
+    if numkeys == 0 {
+        return Err(ParseError::InvalidInteger);
+    }
+    if rest.len() < numkeys {
+        return Err(ParseError::Syntax);
+    }
+
+    let (keys, options) = rest.split_at(numkeys);
+    let limit = match options {
+        [] => 0,
+        [option, limit] if option.eq_ignore_ascii_case("limit") => int(limit)?,
+        _ => return Err(ParseError::Syntax),
+    };
+    Ok(Command::SInterCard(to_strings(keys), limit))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Command;
+    use super::{
+        ClientKill, Command, Expiry, GeoSearch, Hello, Migrate, ParseError, Scan, SetOptions,
+        XPendingRange, XRead, XReadGroup, arity,
+    };
+    use crate::{
+        bitmap::{BitOp, BitRange, BitUnit},
+        db::ExpireFlags,
+        geo::{Order, Origin, Query, Shape, Unit},
+        handler,
+        sorted_set::{AddFlags, LexRange, ScoreRange},
+        stream::{ClaimOptions, IdRange, IdSpec, StreamId},
+    };
+
+    fn parse(input: &str) -> Command {
+        Command::parse(input).unwrap_or_else(|e| panic!("{input}: {e}"))
+    }
 
     #[test]
     fn test_parse_get_command() {
         let expected = Command::Get("foo".to_string());
 
-        let actual = Command::parse("get foo");
+        let actual = parse("get foo");
 
         assert_eq!(actual, expected);
     }
@@ -48,37 +2496,882 @@ mod tests {
     fn test_parse_set_command() {
         let expected = Command::Set("foo".to_string(), "bar".to_string());
 
-        let actual = Command::parse("set foo bar");
+        let actual = parse("set foo bar");
 
         assert_eq!(actual, expected);
+        assert_eq!(parse("getset foo bar"), Command::GetSet("foo".into(), "bar".into()));
+        assert_eq!(
+            parse("set foo bar KEEPTTL get"),
+            Command::SetWith(
+                "foo".into(),
+                "bar".into(),
+                SetOptions { expiry: None, keep_ttl: true, get: true }
+            )
+        );
+        assert!(Command::parse("set foo bar keepttl ex 10").is_err());
+        assert!(Command::parse("set foo bar ex 10 keepttl").is_err());
+        assert!(Command::parse("set foo bar ex 10 px 10").is_err());
+        assert!(Command::parse("set foo bar ex").is_err());
+    }
+
+    #[test]
+    fn test_parse_incr() {
+        assert_eq!(parse("incr n"), Command::Incr("n".into()));
+        assert_eq!(parse("INCRBY n -5"), Command::IncrBy("n".into(), -5));
+        assert!(Command::parse("incrby n x").is_err());
     }
 
     #[test]
     fn test_parse_unknown_command() {
-        let expected = Command::Unknown;
+        let expected = ParseError::UnknownCommand("abc".into(), vec!["abc".into(), "abc".into()]);
 
         let actual = Command::parse("abc abc abc");
 
-        assert_eq!(expected, actual);
+        assert_eq!(actual, Err(expected));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |input: &str| Command::parse(input).unwrap_err().to_string();
+        assert_eq!(
+            error("nosuch a b"),
+            "ERR unknown command 'nosuch', with args beginning with: 'a' 'b' "
+        );
+        assert_eq!(error("set k"), "ERR wrong number of arguments for 'set' command");
+        assert_eq!(error("GET"), "ERR wrong number of arguments for 'get' command");
+        assert_eq!(error("hset h f"), "ERR wrong number of arguments for 'hset' command");
+        assert_eq!(error("config"), "ERR wrong number of arguments for 'config' command");
+        assert_eq!(error("config get"), "ERR wrong number of arguments for 'config|get' command");
+        assert_eq!(error("expire k soon"), "ERR value is not an integer or out of range");
+        assert_eq!(error("zincrby z x m"), "ERR value is not a valid float");
+        assert_eq!(
+            error("xack s g 1-x"),
+            "ERR Invalid stream ID specified as stream command argument"
+        );
+        assert_eq!(error("set k v ex"), "ERR syntax error");
+        assert_eq!(error("flushdb later"), "ERR syntax error");
+        assert_eq!(Command::parse(""), Err(ParseError::UnknownCommand(String::new(), Vec::new())));
+    }
+
+    #[test]
+    fn test_arity() {
+        for spec in handler::specs() {
+            assert_eq!(arity(spec.name), Some(spec.arity), "{}", spec.name);
+        }
+        assert_eq!(arity("get"), Some(2));
+        assert_eq!(arity("set"), Some(-3));
+        assert_eq!(arity("config"), Some(-2));
+        assert_eq!(arity("slaveof"), Some(3));
+        assert_eq!(arity("nosuch"), None);
     }
 
     #[test]
     fn test_parse_ignore_multiwhitespaces() {
-        let expected = Command::parse("get foo");
+        let expected = parse("get foo");
 
-        let actual = Command::parse("get    foo ");
+        let actual = parse("get    foo ");
 
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn test_parse_quoted_arguments() {
+        assert_eq!(
+            parse(r#"SET greeting "hello world""#),
+            Command::Set("greeting".into(), "hello world".into())
+        );
+        assert_eq!(
+            parse(r#"set 'it\'s' "say \"hi\"\n\x41""#),
+            Command::Set("it's".into(), "say \"hi\"\nA".into())
+        );
+        assert_eq!(parse(r#"get """#), Command::Get(String::new()));
+        assert_eq!(Command::parse(r#"set k "unterminated"#), Err(ParseError::Syntax));
+        assert!(Command::parse(r#"set k "v"x"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_hash_commands() {
+        assert_eq!(
+            parse("hset h a 1 b 2"),
+            Command::HSet("h".into(), vec![("a".into(), "1".into()), ("b".into(), "2".into())])
+        );
+        assert!(Command::parse("hset h a").is_err());
+        assert_eq!(parse("hincrby h n -3"), Command::HIncrBy("h".into(), "n".into(), -3));
+        assert!(Command::parse("hincrby h n abc").is_err());
+        assert_eq!(
+            parse("hincrbyfloat h n 1.5"),
+            Command::HIncrByFloat("h".into(), "n".into(), 1.5)
+        );
+        assert_eq!(
+            parse("HRANDFIELD h -2 WITHVALUES"),
+            Command::HRandField("h".into(), Some(-2), true)
+        );
+    }
+
+    #[test]
+    fn test_parse_set_commands() {
+        assert_eq!(parse("sadd s a b"), Command::SAdd("s".into(), vec!["a".into(), "b".into()]));
+        assert!(Command::parse("sadd s").is_err());
+        assert_eq!(parse("srem s a"), Command::SRem("s".into(), vec!["a".into()]));
+        assert_eq!(parse("SISMEMBER s a"), Command::SIsMember("s".into(), "a".into()));
+    }
+
+    #[test]
+    fn test_parse_set_sampling_commands() {
+        assert_eq!(parse("spop s"), Command::SPop("s".into(), None));
+        assert_eq!(parse("spop s 2"), Command::SPop("s".into(), Some(2)));
+        assert_eq!(Command::parse("spop s -2"), Err(ParseError::NegativeCount));
+        assert_eq!(
+            ParseError::NegativeCount.to_string(),
+            "ERR value is out of range, must be positive"
+        );
+        assert_eq!(Command::parse("spop s x"), Err(ParseError::InvalidInteger));
+        assert_eq!(parse("srandmember s -2"), Command::SRandMember("s".into(), Some(-2)));
+        assert_eq!(
+            parse("smismember s a b"),
+            Command::SMIsMember("s".into(), vec!["a".into(), "b".into()])
+        );
+    }
+
+    #[test]
+    fn test_parse_set_algebra_commands() {
+        assert_eq!(parse("sinter a b"), Command::SInter(vec!["a".into(), "b".into()]));
+        assert_eq!(
+            parse("sdiffstore d a b"),
+            Command::SDiffStore("d".into(), vec!["a".into(), "b".into()])
+        );
+        assert!(Command::parse("sunionstore d").is_err());
+        assert_eq!(
+            parse("sintercard 2 a b limit 3"),
+            Command::SInterCard(vec!["a".into(), "b".into()], 3)
+        );
+        assert_eq!(parse("sintercard 1 a"), Command::SInterCard(vec!["a".into()], 0));
+        assert!(Command::parse("sintercard 3 a b").is_err());
+        assert!(Command::parse("sintercard 1 a limit -1").is_err());
+    }
+
+    #[test]
+    fn test_parse_zset_commands() {
+        assert_eq!(
+            parse("zadd z 1 a 2.5 b"),
+            Command::ZAdd(
+                "z".into(),
+                AddFlags::default(),
+                vec![(1.0, "a".into()), (2.5, "b".into())]
+            )
+        );
+        assert_eq!(
+            parse("zadd z XX ch -inf a"),
+            Command::ZAdd(
+                "z".into(),
+                AddFlags { xx: true, ch: true, ..Default::default() },
+                vec![(f64::NEG_INFINITY, "a".into())]
+            )
+        );
+        assert!(Command::parse("zadd z nx xx 1 a").is_err());
+        assert!(Command::parse("zadd z 1 a 2").is_err());
+        assert!(Command::parse("zadd z nan a").is_err());
+        assert_eq!(
+            parse("zrange z 0 -1 withscores rev"),
+            Command::ZRange("z".into(), 0, -1, true, true)
+        );
+        assert!(Command::parse("zrange z 0 -1 bogus").is_err());
+    }
+
+    #[test]
+    fn test_parse_zset_range_commands() {
+        let range = ScoreRange::parse("(1", "+inf").unwrap();
+        assert_eq!(
+            parse("zrangebyscore z (1 +inf limit 1 2 withscores"),
+            Command::ZRangeByScore("z".into(), range, true, 1, 2)
+        );
+        assert!(Command::parse("zrangebyscore z 1 x").is_err());
+        assert!(Command::parse("zrangebyscore z 1 2 limit 1").is_err());
+
+        let range = LexRange::parse("[a", "-").unwrap();
+        assert_eq!(parse("zrangebylex z [a -"), Command::ZRangeByLex("z".into(), range, 0, -1));
+        assert!(Command::parse("zrangebylex z a b").is_err());
+        assert_eq!(parse("zincrby z 2 a"), Command::ZIncrBy("z".into(), 2.0, "a".into()));
+        assert!(Command::parse("zincrby z x a").is_err());
+    }
+
+    #[test]
+    fn test_parse_zpop_commands() {
+        assert_eq!(parse("zpopmin z"), Command::ZPopMin("z".into(), None));
+        assert_eq!(parse("zpopmax z 3"), Command::ZPopMax("z".into(), Some(3)));
+        assert_eq!(Command::parse("zpopmax z -1"), Err(ParseError::NegativeCount));
+        assert_eq!(Command::parse("zpopmin z -1"), Err(ParseError::NegativeCount));
+        assert_eq!(Command::parse("zpopmin z x"), Err(ParseError::InvalidInteger));
+        assert_eq!(parse("bzpopmin a b 0.5"), Command::BZPopMin(vec!["a".into(), "b".into()], 0.5));
+        assert!(Command::parse("bzpopmax a -1").is_err());
+        assert!(Command::parse("bzpopmax 1").is_err());
+    }
+
+    #[test]
+    fn test_parse_pubsub_commands() {
+        assert_eq!(parse("keys user:*"), Command::Keys("user:*".into()));
+        assert_eq!(parse("publish news hi"), Command::Publish("news".into(), "hi".into()));
+        assert_eq!(parse("subscribe a b"), Command::Subscribe(vec!["a".into(), "b".into()]));
+        assert!(Command::parse("subscribe").is_err());
+        assert_eq!(parse("unsubscribe"), Command::Unsubscribe(vec![]));
+        assert_eq!(parse("psubscribe n*"), Command::PSubscribe(vec!["n*".into()]));
+        assert!(Command::parse("psubscribe").is_err());
+        assert_eq!(parse("punsubscribe n*"), Command::PUnsubscribe(vec!["n*".into()]));
+    }
+
+    #[test]
+    fn test_parse_pubsub_introspection() {
+        assert_eq!(parse("pubsub channels"), Command::PubSubChannels(None));
+        assert_eq!(parse("PUBSUB CHANNELS n*"), Command::PubSubChannels(Some("n*".into())));
+        assert_eq!(parse("pubsub numsub a b"), Command::PubSubNumSub(vec!["a".into(), "b".into()]));
+        assert_eq!(parse("pubsub numsub"), Command::PubSubNumSub(vec![]));
+        assert_eq!(parse("pubsub numpat"), Command::PubSubNumPat);
+        assert_eq!(parse("spublish news hi"), Command::SPublish("news".into(), "hi".into()));
+        assert_eq!(parse("SSUBSCRIBE a"), Command::SSubscribe(vec!["a".into()]));
+        assert_eq!(parse("sunsubscribe"), Command::SUnsubscribe(vec![]));
+        assert_eq!(parse("pubsub shardchannels"), Command::PubSubShardChannels(None));
+        assert_eq!(
+            parse("pubsub shardchannels n*"),
+            Command::PubSubShardChannels(Some("n*".into()))
+        );
+        assert_eq!(parse("pubsub shardnumsub a"), Command::PubSubShardNumSub(vec!["a".into()]));
+        assert!(Command::parse("ssubscribe").is_err());
+        assert!(Command::parse("pubsub numpat x").is_err());
+        assert!(Command::parse("pubsub").is_err());
+    }
+
+    #[test]
+    fn test_parse_expire_commands() {
+        let set = |expiry| {
+            let options = SetOptions { expiry: Some(expiry), ..SetOptions::default() };
+            Command::SetWith("k".into(), "v".into(), options)
+        };
+        assert_eq!(parse("set k v EX 10"), set(Expiry::Seconds(10)));
+        assert_eq!(parse("set k v pxat 1700000000000"), set(Expiry::UnixMillis(1_700_000_000_000)));
+        // 非正数与换算为截止时间时溢出的过期时间
+        for input in [
+            "set k v ex 0",
+            "set k v px -1",
+            "set k v ex 9223372036854775",
+            "set k v px 9223372036854775807",
+            "set k v exat 9223372036854775807",
+        ] {
+            let error = Command::parse(input).unwrap_err();
+            assert_eq!(error, ParseError::InvalidExpireTime("set".into()), "{input}");
+            assert_eq!(error.to_string(), "ERR invalid expire time in 'set' command");
+        }
+        assert!(Command::parse("set k v keep 1").is_err());
+        let expire = |expiry| Command::Expire("k".into(), expiry, ExpireFlags::default());
+        assert_eq!(parse("expire k -1"), expire(Expiry::Seconds(-1)));
+        assert_eq!(parse("pexpire k 50"), expire(Expiry::Millis(50)));
+        assert_eq!(parse("expireat k 1700000000"), expire(Expiry::UnixSeconds(1_700_000_000)));
+        assert!(Command::parse("expire k soon").is_err());
+        for (input, name) in [
+            ("expire k 9223372036854775", "expire"),
+            ("PEXPIRE k 9223372036854775807", "pexpire"),
+            ("expireat k 9223372036854775807", "expireat"),
+        ] {
+            let error = Command::parse(input).unwrap_err();
+            assert_eq!(error.to_string(), format!("ERR invalid expire time in '{name}' command"));
+        }
+        assert!(Command::parse("pexpireat k 9223372036854775807").is_ok());
+        assert_eq!(
+            parse("PEXPIREAT k 5 xx GT"),
+            Command::Expire(
+                "k".into(),
+                Expiry::UnixMillis(5),
+                ExpireFlags { xx: true, gt: true, ..Default::default() }
+            )
+        );
+        assert!(Command::parse("expire k 5 nx xx").is_err());
+        assert!(Command::parse("expire k 5 gt lt").is_err());
+        assert!(Command::parse("expire k 5 later").is_err());
+        assert_eq!(parse("persist k"), Command::Persist("k".into()));
+        assert!(parse("persist k").is_write());
+        assert_eq!(parse("ttl k"), Command::Ttl("k".into()));
+        assert_eq!(parse("pttl k"), Command::PTtl("k".into()));
+        assert_eq!(parse("expiretime k"), Command::ExpireTime("k".into()));
+        assert_eq!(parse("PEXPIRETIME k"), Command::PExpireTime("k".into()));
+    }
+
+    #[test]
+    fn test_expiry_deadline() {
+        assert_eq!(Expiry::Seconds(2).deadline_ms(1_000), 3_000);
+        assert_eq!(Expiry::Millis(-5_000).deadline_ms(1_000), 0);
+        assert_eq!(Expiry::UnixSeconds(7).deadline_ms(1_000), 7_000);
+        assert_eq!(Expiry::UnixMillis(i64::MAX).deadline_ms(1_000), i64::MAX as u64);
+        assert_eq!(Expiry::Seconds(2).checked_deadline_ms(1_000), Some(3_000));
+        assert_eq!(Expiry::Seconds(i64::MAX / 1000).checked_deadline_ms(1_000), None);
+        assert_eq!(Expiry::Millis(i64::MAX).checked_deadline_ms(1_000), None);
+        assert_eq!(Expiry::UnixSeconds(i64::MAX).checked_deadline_ms(1_000), None);
+    }
+
+    #[test]
+    fn test_parse_persistence_commands() {
+        assert_eq!(parse("BGREWRITEAOF"), Command::BgRewriteAof);
+        assert!(Command::parse("bgrewriteaof now").is_err());
+        assert_eq!(parse("save"), Command::Save);
+        assert_eq!(parse("BGSAVE"), Command::BgSave);
+        assert_eq!(parse("lastsave"), Command::LastSave);
+    }
+
+    #[test]
+    fn test_from_args_and_is_write() {
+        assert_eq!(
+            Command::from_args(&["set", "k", "a b"]).unwrap(),
+            Command::Set("k".into(), "a b".into())
+        );
+        assert!(parse("spop s").is_write());
+        assert!(parse("zadd z 1 a").is_write());
+        assert!(!parse("get k").is_write());
+        assert!(!parse("publish c m").is_write());
+        assert!(parse("set k v").is_denyoom());
+        assert!(!parse("del k").is_denyoom());
+        assert!(!parse("get k").is_denyoom());
+    }
+
+    #[test]
+    fn test_parse_flush() {
+        assert_eq!(parse("dbsize"), Command::DbSize);
+        assert_eq!(
+            parse("scan 12 match user:* COUNT 100 type Hash"),
+            Command::Scan(Scan {
+                cursor: 12,
+                pattern: Some("user:*".into()),
+                count: 100,
+                key_type: Some("hash".into()),
+            })
+        );
+        assert_eq!(
+            parse("scan 0"),
+            Command::Scan(Scan { cursor: 0, pattern: None, count: 10, key_type: None })
+        );
+        assert_eq!(Command::parse("scan -1"), Err(ParseError::InvalidCursor));
+        assert_eq!(Command::parse("scan 0 count 0"), Err(ParseError::Syntax));
+        assert_eq!(Command::parse("scan 0 match"), Err(ParseError::Syntax));
+        assert_eq!(parse("RANDOMKEY"), Command::RandomKey);
+        assert!(Command::parse("randomkey k").is_err());
+        assert_eq!(parse("touch a b"), Command::Touch(vec!["a".into(), "b".into()]));
+        assert!(Command::parse("touch").is_err());
+        assert_eq!(parse("touch a b").keys(), vec!["a", "b"]);
+        assert_eq!(parse("randomkey").categories(), vec!["keyspace", "read"]);
+        assert_eq!(parse("bigkeys"), Command::BigKeys);
+        assert_eq!(parse("hotkeys"), Command::HotKeys(10));
+        assert_eq!(parse("HOTKEYS count 3"), Command::HotKeys(3));
+        assert!(Command::parse("hotkeys count 0").is_err());
+        assert!(Command::parse("hotkeys 3").is_err());
+        assert_eq!(parse("hotkeys").categories(), vec!["keyspace", "read"]);
+        assert_eq!(parse("export"), Command::Export);
+        assert_eq!(parse("import a b"), Command::Import(vec!["a".into(), "b".into()]));
+        assert!(Command::parse("import").is_err());
+        assert!(Command::parse("export x").is_err());
+        assert!(parse("import a").is_write());
+        assert_eq!(parse("export").categories(), vec!["keyspace", "read", "admin", "dangerous"]);
+        assert_eq!(parse("flushdb"), Command::FlushDb(false));
+        assert_eq!(parse("FLUSHALL async"), Command::FlushAll(true));
+        assert_eq!(parse("flushall sync"), Command::FlushAll(false));
+        assert!(Command::parse("flushall later").is_err());
+        assert!(parse("flushdb").is_write());
+    }
+
+    #[test]
+    fn test_command_metadata() {
+        for input in ["get k", "pexpire k 10", "config get *", "acl whoami", "object encoding k"] {
+            assert!(handler::spec(parse(input).name()).is_some(), "{input}");
+        }
+        assert_eq!(parse("sunionstore d a b").keys(), vec!["d", "a", "b"]);
+        assert!(parse("publish c m").keys().is_empty());
+        assert_eq!(parse("set k v").categories(), vec!["string", "write"]);
+        assert_eq!(parse("bzpopmin z 0").categories(), vec!["sortedset", "write", "blocking"]);
+        assert_eq!(parse("flushall").categories(), vec!["keyspace", "write", "dangerous"]);
+        assert_eq!(parse("save").categories(), vec!["admin", "dangerous"]);
+    }
+
+    #[test]
+    fn test_parse_acl() {
+        assert_eq!(parse("auth secret"), Command::Auth(None, "secret".into()));
+        assert_eq!(
+            parse("AUTH alice secret"),
+            Command::Auth(Some("alice".into()), "secret".into())
+        );
+        assert_eq!(
+            parse("acl setuser alice on >pw ~k* +get"),
+            Command::AclSetUser(
+                "alice".into(),
+                vec!["on".into(), ">pw".into(), "~k*".into(), "+get".into()]
+            )
+        );
+        assert_eq!(parse("acl getuser alice"), Command::AclGetUser("alice".into()));
+        assert_eq!(parse("ACL LIST"), Command::AclList);
+        assert_eq!(parse("acl whoami"), Command::AclWhoAmI);
+        assert!(Command::parse("acl").is_err());
+    }
+
+    #[test]
+    fn test_parse_client() {
+        assert_eq!(parse("CLIENT ID"), Command::ClientId);
+        assert_eq!(parse("client setname app"), Command::ClientSetName("app".into()));
+        assert_eq!(parse("client getname"), Command::ClientGetName);
+        assert_eq!(parse("client list"), Command::ClientList);
+        assert_eq!(
+            parse("client kill 127.0.0.1:6380"),
+            Command::ClientKillAddr("127.0.0.1:6380".into())
+        );
+        assert_eq!(
+            parse("client kill id 3 skipme no"),
+            Command::ClientKill(ClientKill { id: Some(3), addr: None, skip_me: false })
+        );
+        assert_eq!(
+            parse("client kill addr 127.0.0.1:6380"),
+            Command::ClientKill(ClientKill {
+                id: None,
+                addr: Some("127.0.0.1:6380".into()),
+                skip_me: true
+            })
+        );
+        assert!(Command::parse("client kill skipme yes").is_err());
+        assert_eq!(parse("MONITOR"), Command::Monitor);
+        assert_eq!(parse("hello"), Command::Hello(Hello::default()));
+        assert_eq!(
+            parse("HELLO 3"),
+            Command::Hello(Hello { protover: Some(3), ..Hello::default() })
+        );
+        assert_eq!(
+            parse("hello 2 setname app AUTH alice secret"),
+            Command::Hello(Hello {
+                protover: Some(2),
+                auth: Some(("alice".into(), "secret".into())),
+                name: Some("app".into()),
+            })
+        );
+        assert!(Command::parse("hello 3 auth alice").is_err());
+        assert!(Command::parse("hello 3 setname").is_err());
+        assert_eq!(
+            parse("client setinfo LIB-NAME redis-py"),
+            Command::ClientSetInfo("lib-name".into(), "redis-py".into())
+        );
+        assert!(Command::parse("client setinfo lib-os linux").is_err());
+        assert_eq!(parse("ping"), Command::Ping(None));
+        assert_eq!(parse("PING hi"), Command::Ping(Some("hi".into())));
+        assert_eq!(Command::parse("ping a b"), Err(ParseError::WrongArity("ping".into())));
+        assert_eq!(parse("echo hi"), Command::Echo("hi".into()));
+        assert_eq!(parse("quit"), Command::Quit);
+        assert_eq!(parse("quit now"), Command::Quit);
+        assert_eq!(parse("reset"), Command::Reset);
+        assert_eq!(parse("READONLY"), Command::ReadOnly);
+        assert_eq!(parse("readwrite"), Command::ReadWrite);
+        assert_eq!(parse("command"), Command::Commands);
+        assert_eq!(parse("COMMAND count"), Command::CommandCount);
+        assert_eq!(parse("command info"), Command::CommandInfo(Vec::new()));
+        assert_eq!(
+            parse("command info get SET"),
+            Command::CommandInfo(vec!["get".into(), "SET".into()])
+        );
+        assert_eq!(parse("command docs get"), Command::CommandDocs(vec!["get".into()]));
+        assert_eq!(
+            Command::parse("command count x"),
+            Err(ParseError::WrongArity("command|count".into()))
+        );
+        assert_eq!(
+            Command::parse("command nosuch"),
+            Err(ParseError::UnknownSubcommand("command".into(), "nosuch".into()))
+        );
+        assert_eq!(parse("CLIENT HELP"), Command::Help("client|help"));
+        assert_eq!(parse("xgroup help").name(), "xgroup|help");
+        assert!(Command::parse("reset all").is_err());
+        assert!(Command::parse("hello three").is_err());
+        assert!(Command::parse("client kill id x").is_err());
+        assert!(Command::parse("client kill id 1 addr").is_err());
+        assert_eq!(parse("client list").categories(), vec!["admin", "dangerous", "connection"]);
+    }
+
+    #[test]
+    fn test_parse_streams() {
+        assert_eq!(
+            parse("XADD s * f v"),
+            Command::XAdd("s".into(), IdSpec::Auto, vec![("f".into(), "v".into())])
+        );
+        assert_eq!(
+            parse("xadd s 5-* f v"),
+            Command::XAdd("s".into(), IdSpec::Seq(5), vec![("f".into(), "v".into())])
+        );
+        assert!(Command::parse("xadd s * f").is_err());
+        assert!(Command::parse("xadd s x-1 f v").is_err());
+        assert_eq!(parse("xlen s"), Command::XLen("s".into()));
+        assert_eq!(
+            parse("xrange s - + COUNT 2"),
+            Command::XRange("s".into(), IdRange::parse("-", "+").unwrap(), Some(2))
+        );
+        assert!(Command::parse("xrange s - + limit 2").is_err());
+        assert_eq!(
+            parse("XREAD COUNT 1 STREAMS a b 0-1 $"),
+            Command::XRead(XRead {
+                count: Some(1),
+                block: None,
+                streams: vec![("a".into(), Some(StreamId::new(0, 1))), ("b".into(), None)],
+            })
+        );
+        assert!(Command::parse("xread streams a b 0").is_err());
+        assert!(Command::parse("xread a 0").is_err());
+        assert_eq!(
+            parse("xread block 0 streams a $"),
+            Command::XRead(XRead {
+                count: None,
+                block: Some(0),
+                streams: vec![("a".into(), None)]
+            })
+        );
+        assert!(Command::parse("xread block -1 streams a $").is_err());
+        assert_eq!(
+            parse("xread block 10 streams a $").categories(),
+            vec!["stream", "read", "blocking"]
+        );
+        assert_eq!(parse("xread streams a b 0 1").keys(), vec!["a", "b"]);
+        assert_eq!(parse("xadd s * f v").categories(), vec!["stream", "write"]);
+        assert_eq!(parse("xlen s").categories(), vec!["stream", "read"]);
+    }
+
+    #[test]
+    fn test_parse_bitmaps() {
+        assert_eq!(parse("SETBIT b 7 1"), Command::SetBit("b".into(), 7, true));
+        assert!(Command::parse("setbit b 7 2").is_err());
+        assert_eq!(Command::parse("setbit b 4294967296 1"), Err(ParseError::InvalidBitOffset));
+        assert_eq!(Command::parse("getbit b -1"), Err(ParseError::InvalidBitOffset));
+        assert_eq!(Command::parse("getbit b x"), Err(ParseError::InvalidBitOffset));
+        assert_eq!(
+            ParseError::InvalidBitOffset.to_string(),
+            "ERR bit offset is not an integer or out of range"
+        );
+        assert_eq!(parse("getbit b 4294967295"), Command::GetBit("b".into(), u32::MAX as usize));
+        assert_eq!(parse("bitcount b"), Command::BitCount("b".into(), None));
+        assert_eq!(
+            parse("bitcount b 1 -1 BIT"),
+            Command::BitCount(
+                "b".into(),
+                Some(BitRange { start: 1, end: Some(-1), unit: BitUnit::Bit })
+            )
+        );
+        assert!(Command::parse("bitcount b 1").is_err());
+        assert!(Command::parse("bitcount b 1 2 word").is_err());
+        assert_eq!(
+            parse("bitpos b 0 2"),
+            Command::BitPos(
+                "b".into(),
+                false,
+                Some(BitRange { start: 2, end: None, unit: BitUnit::Byte })
+            )
+        );
+        assert_eq!(parse("bitpos b 1").keys(), vec!["b"]);
+        assert_eq!(
+            parse("BITOP xor dest a b"),
+            Command::BitOp(BitOp::Xor, "dest".into(), vec!["a".into(), "b".into()])
+        );
+        assert!(Command::parse("bitop not dest a b").is_err());
+        assert!(Command::parse("bitop nand dest a").is_err());
+        assert_eq!(parse("bitop and dest a b").keys(), vec!["dest", "a", "b"]);
+        assert_eq!(parse("setbit b 0 1").categories(), vec!["bitmap", "write"]);
+        assert_eq!(parse("bitcount b").categories(), vec!["bitmap", "read"]);
+    }
+
+    #[test]
+    fn test_parse_geo() {
+        assert_eq!(
+            parse("GEOADD g XX CH 13.361389 38.115556 Palermo 15 37 Catania"),
+            Command::GeoAdd(
+                "g".into(),
+                AddFlags { xx: true, ch: true, ..Default::default() },
+                vec![(13.361389, 38.115556, "Palermo".into()), (15.0, 37.0, "Catania".into())]
+            )
+        );
+        assert!(Command::parse("geoadd g gt 1 2 m").is_err());
+        assert!(Command::parse("geoadd g nx xx 1 2 m").is_err());
+        assert!(Command::parse("geoadd g 1 2").is_err());
+        assert_eq!(
+            parse("geodist g a b KM"),
+            Command::GeoDist("g".into(), "a".into(), "b".into(), Unit::Km)
+        );
+        assert!(Command::parse("geodist g a b yd").is_err());
+        assert_eq!(
+            parse("geopos g a b"),
+            Command::GeoPos("g".into(), vec!["a".into(), "b".into()])
+        );
+
+        assert_eq!(
+            parse("GEOSEARCH g FROMLONLAT 15 37 BYBOX 2 1 km DESC COUNT 3 ANY WITHDIST"),
+            Command::GeoSearch(
+                "g".into(),
+                GeoSearch {
+                    query: Query {
+                        origin: Origin::LonLat(15.0, 37.0),
+                        shape: Shape::Box { width: 2000.0, height: 1000.0 },
+                        order: Some(Order::Desc),
+                        count: Some(3),
+                        any: true,
+                    },
+                    unit: Unit::Km,
+                    with_coord: false,
+                    with_dist: true,
+                    with_hash: false,
+                }
+            )
+        );
+        let search = |input| match Command::parse(input) {
+            Ok(Command::GeoSearch(_, search)) => Some(search),
+            _ => None,
+        };
+        let by_member =
+            search("geosearch g frommember a byradius 10 m withcoord withhash").unwrap();
+        assert_eq!(by_member.query.origin, Origin::Member("a".into()));
+        assert_eq!(by_member.query.shape, Shape::Radius(10.0));
+        assert!(by_member.with_coord && by_member.with_hash && !by_member.with_dist);
+        // 中心与范围必须各给出一次，ANY 需要 COUNT
+        assert_eq!(search("geosearch g byradius 10 m"), None);
+        assert_eq!(search("geosearch g frommember a"), None);
+        assert_eq!(search("geosearch g frommember a frommember b byradius 1 m"), None);
+        assert_eq!(search("geosearch g frommember a byradius 1 m any"), None);
+        assert_eq!(search("geosearch g frommember a byradius -1 m"), None);
+        assert_eq!(search("geosearch g frommember a byradius 1 m count 0"), None);
+
+        assert_eq!(parse("geoadd g 1 2 m").categories(), vec!["geo", "write"]);
+        assert_eq!(parse("geopos g m").categories(), vec!["geo", "read"]);
+        assert!(parse("geoadd g 1 2 m").is_denyoom());
+    }
+
+    #[test]
+    fn test_parse_consumer_groups() {
+        assert_eq!(
+            parse("XGROUP CREATE s g $ MKSTREAM"),
+            Command::XGroupCreate("s".into(), "g".into(), None, true)
+        );
+        assert_eq!(
+            parse("xgroup setid s g 5"),
+            Command::XGroupSetId("s".into(), "g".into(), Some(StreamId::new(5, 0)))
+        );
+        assert!(Command::parse("xgroup create s g 0 nomkstream").is_err());
+        assert_eq!(parse("xgroup destroy s g").name(), "xgroup|destroy");
+        assert_eq!(
+            parse("xreadgroup group g alice count 2 noack streams a b > 0"),
+            Command::XReadGroup(XReadGroup {
+                group: "g".into(),
+                consumer: "alice".into(),
+                count: Some(2),
+                block: None,
+                noack: true,
+                streams: vec![("a".into(), None), ("b".into(), Some(StreamId::MIN))],
+            })
+        );
+        assert!(Command::parse("xreadgroup group g alice streams a $").is_err());
+        assert_eq!(
+            parse("xreadgroup group g c block 0 streams a >").categories(),
+            vec!["stream", "write", "blocking"]
+        );
+        assert_eq!(
+            parse("xack s g 1-1 2"),
+            Command::XAck("s".into(), "g".into(), vec![StreamId::new(1, 1), StreamId::new(2, 0)])
+        );
+        assert!(Command::parse("xack s g").is_err());
+
+        assert_eq!(parse("xpending s g"), Command::XPending("s".into(), "g".into(), None));
+        assert_eq!(
+            parse("xpending s g idle 100 - + 10 alice"),
+            Command::XPending(
+                "s".into(),
+                "g".into(),
+                Some(XPendingRange {
+                    min_idle: Some(100),
+                    range: IdRange::parse("-", "+").unwrap(),
+                    count: 10,
+                    consumer: Some("alice".into()),
+                })
+            )
+        );
+        assert!(Command::parse("xpending s g idle 100").is_err());
+        assert!(Command::parse("xpending s g - +").is_err());
+
+        assert_eq!(
+            parse("xclaim s g bob 10 1-0 2-0 idle 5 retrycount 3 force justid lastid 2"),
+            Command::XClaim(
+                "s".into(),
+                "g".into(),
+                "bob".into(),
+                vec![StreamId::new(1, 0), StreamId::new(2, 0)],
+                ClaimOptions {
+                    min_idle: 10,
+                    idle: Some(5),
+                    retry_count: Some(3),
+                    force: true,
+                    just_id: true,
+                    last_id: Some(StreamId::new(2, 0)),
+                    ..ClaimOptions::default()
+                }
+            )
+        );
+        assert!(Command::parse("xclaim s g bob 10 justid").is_err());
+        assert!(Command::parse("xclaim s g bob 10 1-0 time").is_err());
+        assert!(parse("xclaim s g bob 10 1-0").is_write());
+        assert!(!parse("xpending s g").is_write());
+    }
+
+    #[test]
+    fn test_parse_databases() {
+        assert_eq!(parse("SELECT 3"), Command::Select(3));
+        assert!(Command::parse("select -1").is_err());
+        assert_eq!(parse("move k 1"), Command::Move("k".into(), 1));
+        assert_eq!(parse("swapdb 0 1"), Command::SwapDb(0, 1));
+        assert!(Command::parse("swapdb 0 x").is_err());
+        assert!(parse("move k 1").is_write());
+        assert!(!parse("move k 1").is_denyoom());
+        assert!(!parse("select 1").is_write());
+    }
+
+    #[test]
+    fn test_parse_config() {
+        assert_eq!(parse("config get max*"), Command::ConfigGet("max*".into()));
+        assert_eq!(
+            parse("CONFIG SET maxmemory 1mb timeout 10"),
+            Command::ConfigSet(vec![
+                ("maxmemory".into(), "1mb".into()),
+                ("timeout".into(), "10".into())
+            ])
+        );
+        assert!(Command::parse("config set maxmemory").is_err());
+        assert_eq!(parse("config RESETSTAT"), Command::ConfigResetStat);
+        assert!(Command::parse("config resetstat all").is_err());
+    }
+
+    #[test]
+    fn test_parse_latency() {
+        assert_eq!(parse("latency latest"), Command::LatencyLatest);
+        assert_eq!(parse("LATENCY history Command"), Command::LatencyHistory("command".into()));
+        assert_eq!(parse("latency reset"), Command::LatencyReset(vec![]));
+        assert_eq!(
+            parse("latency reset command snapshot"),
+            Command::LatencyReset(vec!["command".into(), "snapshot".into()])
+        );
+        assert!(Command::parse("latency history").is_err());
+    }
+
+    #[test]
+    fn test_parse_debug() {
+        assert_eq!(parse("debug sleep 0.5"), Command::DebugSleep(0.5));
+        assert_eq!(parse("DEBUG delay GET 100"), Command::DebugDelay("get".into(), 100));
+        assert_eq!(parse("debug drop *"), Command::DebugDrop("*".into(), 1));
+        assert_eq!(parse("debug drop set 3"), Command::DebugDrop("set".into(), 3));
+        assert_eq!(parse("debug contend 50"), Command::DebugContend(50));
+        assert_eq!(parse("debug faults"), Command::DebugFaults);
+        assert_eq!(parse("debug reset"), Command::DebugReset);
+        assert_eq!(Command::parse("debug sleep -1"), Err(ParseError::InvalidFloat));
+        assert_eq!(Command::parse("debug drop set 0"), Err(ParseError::InvalidInteger));
+        assert!(Command::parse("debug").is_err());
+        assert_eq!(parse("debug drop set 1").categories(), ["admin", "dangerous"]);
+    }
+
+    #[test]
+    fn test_parse_replication_commands() {
+        assert_eq!(
+            parse("replicaof 127.0.0.1 6379"),
+            Command::ReplicaOf(Some(("127.0.0.1".into(), 6379)))
+        );
+        assert_eq!(parse("SLAVEOF no ONE"), Command::ReplicaOf(None));
+        assert!(Command::parse("replicaof host port").is_err());
+        assert_eq!(parse("role"), Command::Role);
+        assert_eq!(
+            parse("sentinel monitor mymaster 127.0.0.1 6379 2"),
+            Command::SentinelMonitor("mymaster".into(), "127.0.0.1".into(), 6379, 2)
+        );
+        assert_eq!(
+            parse("SENTINEL set m down-after-milliseconds 500 quorum 1"),
+            Command::SentinelSet(
+                "m".into(),
+                vec![
+                    ("down-after-milliseconds".into(), "500".into()),
+                    ("quorum".into(), "1".into())
+                ]
+            )
+        );
+        assert_eq!(
+            parse("sentinel is-master-down-by-addr 127.0.0.1 6379 3 *"),
+            Command::SentinelIsMasterDownByAddr("127.0.0.1".into(), 6379, 3, "*".into())
+        );
+        assert_eq!(parse("sentinel myid"), Command::SentinelMyId);
+        assert!(Command::parse("sentinel set m quorum").is_err());
+        assert_eq!(
+            parse("replconf listening-port 6380"),
+            Command::ReplConf(vec!["listening-port".into(), "6380".into()])
+        );
+        assert!(Command::parse("replconf").is_err());
+        assert_eq!(parse("psync ? -1"), Command::Psync("?".into(), -1));
+        assert_eq!(parse("sync"), Command::Sync);
+        assert_eq!(parse("wait 2 500"), Command::Wait(2, 500));
+        assert!(Command::parse("wait 1 -1").is_err());
+    }
+
+    #[test]
+    fn test_parse_key_transfer_commands() {
+        assert_eq!(parse("del a b"), Command::Del(vec!["a".into(), "b".into()]));
+        assert!(Command::parse("del").is_err());
+        assert_eq!(parse("UNLINK a b"), Command::Unlink(vec!["a".into(), "b".into()]));
+        assert!(Command::parse("unlink").is_err());
+        assert_eq!(parse("unlink a").categories(), vec!["keyspace", "write"]);
+        assert!(!parse("unlink a").is_denyoom());
+        assert_eq!(parse("DUMP k"), Command::Dump("k".into()));
+        assert_eq!(parse("object encoding k"), Command::ObjectEncoding("k".into()));
+        assert_eq!(parse("OBJECT REFCOUNT k"), Command::ObjectRefCount("k".into()));
+        assert_eq!(parse("object idletime k"), Command::ObjectIdleTime("k".into()));
+        assert!(Command::parse("object freq k").is_err());
+        assert!(!parse("dump k").is_write());
+        assert_eq!(
+            parse("restore k 0 00ff replace"),
+            Command::Restore("k".into(), 0, "00ff".into(), true, false)
+        );
+        assert_eq!(
+            parse("RESTORE k 1700000000000 00ff ABSTTL"),
+            Command::Restore("k".into(), 1_700_000_000_000, "00ff".into(), false, true)
+        );
+        assert!(Command::parse("restore k -1 00ff").is_err());
+
+        let migrate = Migrate {
+            host: "127.0.0.1".into(),
+            port: 6380,
+            keys: vec!["k".into()],
+            db: 0,
+            timeout: 1000,
+            copy: true,
+            replace: false,
+        };
+        assert_eq!(
+            parse("migrate 127.0.0.1 6380 k 0 1000 copy"),
+            Command::Migrate(migrate.clone())
+        );
+        let keys = Command::from_args(&[
+            "migrate",
+            "127.0.0.1",
+            "6380",
+            "",
+            "0",
+            "1000",
+            "copy",
+            "keys",
+            "a",
+            "b",
+        ]);
+        assert_eq!(
+            keys,
+            Ok(Command::Migrate(Migrate { keys: vec!["a".into(), "b".into()], ..migrate }))
+        );
+        assert!(Command::parse("migrate h 6380 k 0 1000 keys a").is_err());
+        assert!(Command::from_args(&["migrate", "h", "6380", "", "0", "1000"]).is_err());
+        assert!(parse("del a").is_write());
+    }
+
     #[test]
     fn test_parse_ignore_case() {
-        let expected = Command::parse("get foo");
-        let actual = Command::parse("Get foo");
+        let expected = parse("get foo");
+        let actual = parse("Get foo");
         assert_eq!(expected, actual);
 
-        let expected = Command::parse("set foo bar");
-        let actual = Command::parse("SET foo bar");
+        let expected = parse("set foo bar");
+        let actual = parse("SET foo bar");
         assert_eq!(expected, actual);
     }
 }
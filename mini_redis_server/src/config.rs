@@ -0,0 +1,1003 @@
+//! 运行时配置
+//!
+//! [`ServerConfig`] 保存服务器的全部可配置参数（[`Config`]），CONFIG GET 按 glob 模式读取，
+//! CONFIG SET 在运行时修改。参数表（`PARAMS`）为每个参数提供名称、读写函数以及是否可在运行时修改。
+//!
+//! 修改后的配置通过 `watch` 通道广播：
+//! - 数据库内部的子系统（内存淘汰、AOF fsync、自动快照等）由 [`Db::config_set`] 直接同步
+//! - 其他消费方（如连接处理）通过 [`ServerConfig::subscribe`] 订阅变化后自行生效
+//!
+//! 启动时的配置由 [`Config::from_args`] 解析：与 `redis-server` 一样，可以先给出一个
+//! redis.conf 格式的配置文件，再用 `--name value` 形式的命令行参数覆盖其中的设置。
+
+use std::{fmt, fs, path::PathBuf, str::FromStr};
+
+use tokio::sync::watch;
+
+use crate::{
+    audit,
+    connection::Limits,
+    db::{DEFAULT_DATABASES, Db, DbError, EvictionPolicy, Storage},
+    glob,
+    handler::Renames,
+    output::{OutputLimit, OutputLimits},
+    persistence::{self, SavePoint},
+    ratelimit::RateLimit,
+    server::SocketOptions,
+};
+
+/// 周期任务默认每秒执行的次数
+pub const DEFAULT_HZ: u64 = 10;
+
+/// `hz` 的上限
+const MAX_HZ: u64 = 500;
+
+/// 服务器配置，字段名与默认值对应 Redis 的同名配置
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// 监听地址
+    pub bind: String,
+    /// 监听端口
+    pub port: u16,
+    /// 是否以后台进程运行
+    pub daemonize: bool,
+    /// 写入进程号的文件，空字符串表示不写
+    pub pidfile: String,
+    /// 客户端空闲超时（秒），0 表示不超时
+    pub timeout: u64,
+    /// 周期任务每秒执行的次数
+    pub hz: u64,
+    /// 同时连接的客户端数量上限，超出时新连接收到错误后被关闭
+    pub maxclients: u64,
+    /// 同时服务的连接数量上限，达到上限时暂停接受新连接，直到有连接关闭
+    pub max_connections: u64,
+    /// 内存上限（字节），0 表示不限制
+    pub maxmemory: u64,
+    /// 超出内存上限时的淘汰策略
+    pub maxmemory_policy: EvictionPolicy,
+    /// 逻辑数据库数量
+    pub databases: usize,
+    /// 日志级别
+    pub loglevel: LogLevel,
+    /// 日志格式
+    pub log_format: LogFormat,
+    /// 记录到延迟监控的最小耗时（毫秒），0 表示关闭延迟监控
+    pub latency_monitor_threshold: u64,
+    /// 读取客户端请求时的协议限制
+    pub limits: Limits,
+    /// 各类客户端的输出缓冲区限制
+    pub client_output_buffer_limit: OutputLimits,
+    /// 每个客户端的命令速率限制
+    pub rate_limit: RateLimit,
+    /// 启动后复制的主节点地址，`None` 表示以主节点启动；运行时通过 REPLICAOF 命令切换
+    pub replicaof: Option<(String, u16)>,
+    /// 作为副本时是否拒绝客户端的写命令
+    pub replica_read_only: bool,
+    /// 命令改名表：每次设置 `rename-command` 改名或禁用一个命令
+    pub rename_command: Renames,
+    /// 新连接的套接字选项
+    pub socket: SocketOptions,
+    /// 持久化设置
+    pub persistence: persistence::Config,
+    /// 写命令审计日志设置
+    pub audit: audit::Config,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind: "127.0.0.1".into(),
+            port: 6379,
+            daemonize: false,
+            pidfile: String::new(),
+            timeout: 0,
+            hz: DEFAULT_HZ,
+            maxclients: 10000,
+            max_connections: 16384,
+            maxmemory: 0,
+            maxmemory_policy: EvictionPolicy::default(),
+            databases: DEFAULT_DATABASES,
+            latency_monitor_threshold: 0,
+            limits: Limits::default(),
+            client_output_buffer_limit: OutputLimits::default(),
+            rate_limit: RateLimit::default(),
+            replicaof: None,
+            replica_read_only: true,
+            rename_command: Renames::default(),
+            socket: SocketOptions::default(),
+            loglevel: LogLevel::default(),
+            log_format: LogFormat::default(),
+            persistence: persistence::Config::default(),
+            audit: audit::Config::default(),
+        }
+    }
+}
+
+/// 日志级别，名称与 Redis 的 `loglevel` 一致
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LogLevel {
+    /// 每条命令的执行情况
+    Debug,
+    /// 连接的建立与关闭
+    Verbose,
+    /// 服务器启动、持久化等重要事件
+    #[default]
+    Notice,
+    /// 只记录警告与错误
+    Warning,
+}
+
+impl LogLevel {
+    /// 对应的 `tracing` 级别
+    pub fn level(self) -> tracing::Level {
+        match self {
+            LogLevel::Debug => tracing::Level::TRACE,
+            LogLevel::Verbose => tracing::Level::DEBUG,
+            LogLevel::Notice => tracing::Level::INFO,
+            LogLevel::Warning => tracing::Level::WARN,
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "debug" => Ok(LogLevel::Debug),
+            "verbose" => Ok(LogLevel::Verbose),
+            "notice" => Ok(LogLevel::Notice),
+            "warning" => Ok(LogLevel::Warning),
+            _ => Err("argument must be one of debug, verbose, notice, warning".into()),
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Verbose => "verbose",
+            LogLevel::Notice => "notice",
+            LogLevel::Warning => "warning",
+        })
+    }
+}
+
+/// 日志格式
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LogFormat {
+    /// 便于阅读的多行文本
+    #[default]
+    Pretty,
+    /// 每行一个 JSON 对象，便于日志系统采集
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err("argument must be 'pretty' or 'json'".into()),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogFormat::Pretty => "pretty",
+            LogFormat::Json => "json",
+        })
+    }
+}
+
+/// 一个配置参数
+struct Param {
+    name: &'static str,
+    /// 是否可以通过 CONFIG SET 在运行时修改
+    mutable: bool,
+    get: fn(&Config) -> String,
+    set: fn(&mut Config, &str) -> Result<(), String>,
+}
+
+/// 全部配置参数，按名称排序
+const PARAMS: &[Param] = &[
+    Param {
+        name: "appendfilename",
+        mutable: false,
+        get: |c| c.persistence.appendfilename.clone(),
+        set: |c, v| {
+            c.persistence.appendfilename = v.to_string();
+            Ok(())
+        },
+    },
+    Param {
+        name: "appendfsync",
+        mutable: true,
+        get: |c| c.persistence.appendfsync.to_string(),
+        set: |c, v| {
+            c.persistence.appendfsync = v.parse()?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "appendonly",
+        mutable: false,
+        get: |c| yes_no(c.persistence.appendonly),
+        set: |c, v| {
+            c.persistence.appendonly = parse_yes_no(v)?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "audit-log-file",
+        mutable: true,
+        get: |c| c.audit.file.clone(),
+        set: |c, v| {
+            c.audit.file = v.to_string();
+            Ok(())
+        },
+    },
+    Param {
+        name: "audit-log-max-files",
+        mutable: true,
+        get: |c| c.audit.max_files.to_string(),
+        set: |c, v| {
+            c.audit.max_files = v.parse().map_err(|_| "argument must be a non-negative integer")?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "audit-log-max-size",
+        mutable: true,
+        get: |c| c.audit.max_size.to_string(),
+        set: |c, v| {
+            c.audit.max_size = parse_memory(v).ok_or("argument must be a memory value")?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "bind",
+        mutable: false,
+        get: |c| c.bind.clone(),
+        set: |c, v| {
+            c.bind = v.to_string();
+            Ok(())
+        },
+    },
+    Param {
+        name: "client-output-buffer-limit",
+        mutable: true,
+        get: |c| c.client_output_buffer_limit.to_string(),
+        set: |c, v| parse_output_limits(&mut c.client_output_buffer_limit, v),
+    },
+    Param {
+        name: "client-query-buffer-limit",
+        mutable: true,
+        get: |c| c.limits.max_query_buffer.to_string(),
+        set: |c, v| {
+            c.limits.max_query_buffer = parse_limit(v)?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "client-rate-limit",
+        mutable: true,
+        get: |c| c.rate_limit.rate.to_string(),
+        set: |c, v| {
+            c.rate_limit.rate =
+                v.parse().map_err(|_| "argument couldn't be parsed into an integer")?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "client-rate-limit-burst",
+        mutable: true,
+        get: |c| c.rate_limit.burst.to_string(),
+        set: |c, v| {
+            c.rate_limit.burst =
+                v.parse().map_err(|_| "argument couldn't be parsed into an integer")?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "client-rate-limit-key",
+        mutable: true,
+        get: |c| c.rate_limit.key.to_string(),
+        set: |c, v| {
+            c.rate_limit.key = v.parse()?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "client-rate-limit-mode",
+        mutable: true,
+        get: |c| c.rate_limit.mode.to_string(),
+        set: |c, v| {
+            c.rate_limit.mode = v.parse()?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "daemonize",
+        mutable: false,
+        get: |c| yes_no(c.daemonize),
+        set: |c, v| {
+            c.daemonize = parse_yes_no(v)?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "databases",
+        mutable: false,
+        get: |c| c.databases.to_string(),
+        set: |c, v| {
+            c.databases = match v.parse() {
+                Ok(n) if n > 0 => n,
+                _ => return Err("argument must be a positive integer".into()),
+            };
+            Ok(())
+        },
+    },
+    Param {
+        name: "dbfilename",
+        mutable: true,
+        get: |c| c.persistence.dbfilename.clone(),
+        set: |c, v| {
+            c.persistence.dbfilename = v.to_string();
+            Ok(())
+        },
+    },
+    Param {
+        name: "dir",
+        mutable: true,
+        get: |c| c.persistence.dir.display().to_string(),
+        set: |c, v| {
+            c.persistence.dir = PathBuf::from(v);
+            Ok(())
+        },
+    },
+    Param {
+        name: "hz",
+        mutable: true,
+        get: |c| c.hz.to_string(),
+        set: |c, v| {
+            // 与 Redis 一样，超出范围的值按边界处理
+            let hz: u64 = v.parse().map_err(|_| "argument couldn't be parsed into an integer")?;
+            c.hz = hz.clamp(1, MAX_HZ);
+            Ok(())
+        },
+    },
+    Param {
+        name: "latency-monitor-threshold",
+        mutable: true,
+        get: |c| c.latency_monitor_threshold.to_string(),
+        set: |c, v| {
+            c.latency_monitor_threshold =
+                v.parse().map_err(|_| "argument couldn't be parsed into an integer")?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "log-format",
+        mutable: false,
+        get: |c| c.log_format.to_string(),
+        set: |c, v| {
+            c.log_format = v.parse()?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "loglevel",
+        mutable: false,
+        get: |c| c.loglevel.to_string(),
+        set: |c, v| {
+            c.loglevel = v.parse()?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "max-connections",
+        mutable: true,
+        get: |c| c.max_connections.to_string(),
+        set: |c, v| {
+            c.max_connections = match v.parse() {
+                Ok(n) if n > 0 => n,
+                _ => return Err("argument must be a positive integer".into()),
+            };
+            Ok(())
+        },
+    },
+    Param {
+        name: "maxclients",
+        mutable: true,
+        get: |c| c.maxclients.to_string(),
+        set: |c, v| {
+            c.maxclients = match v.parse() {
+                Ok(n) if n > 0 => n,
+                _ => return Err("argument must be a positive integer".into()),
+            };
+            Ok(())
+        },
+    },
+    Param {
+        name: "maxmemory",
+        mutable: true,
+        get: |c| c.maxmemory.to_string(),
+        set: |c, v| {
+            c.maxmemory = parse_memory(v).ok_or("argument must be a memory value")?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "maxmemory-policy",
+        mutable: true,
+        get: |c| c.maxmemory_policy.to_string(),
+        set: |c, v| {
+            c.maxmemory_policy = v.parse()?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "pidfile",
+        mutable: false,
+        get: |c| c.pidfile.clone(),
+        set: |c, v| {
+            c.pidfile = v.to_string();
+            Ok(())
+        },
+    },
+    Param {
+        name: "port",
+        mutable: false,
+        get: |c| c.port.to_string(),
+        set: |c, v| {
+            c.port = v.parse().map_err(|_| "argument must be a port number")?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "proto-max-bulk-len",
+        mutable: true,
+        get: |c| c.limits.max_bulk_len.to_string(),
+        set: |c, v| {
+            c.limits.max_bulk_len = parse_limit(v)?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "proto-max-inline-len",
+        mutable: true,
+        get: |c| c.limits.max_inline_len.to_string(),
+        set: |c, v| {
+            c.limits.max_inline_len = parse_limit(v)?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "proto-max-multibulk-len",
+        mutable: true,
+        get: |c| c.limits.max_multibulk_len.to_string(),
+        set: |c, v| {
+            c.limits.max_multibulk_len = match v.parse() {
+                Ok(n) if n > 0 => n,
+                _ => return Err("argument must be a positive integer".into()),
+            };
+            Ok(())
+        },
+    },
+    Param {
+        name: "rename-command",
+        mutable: false,
+        get: |c| c.rename_command.to_string(),
+        set: |c, v| {
+            let words: Vec<_> = v.split_whitespace().map(unquote).collect();
+            match words.as_slice() {
+                [name] => c.rename_command.rename(name, ""),
+                [name, new_name] => c.rename_command.rename(name, new_name),
+                _ => Err("wrong number of arguments".into()),
+            }
+        },
+    },
+    Param {
+        name: "replica-read-only",
+        mutable: true,
+        get: |c| yes_no(c.replica_read_only),
+        set: |c, v| {
+            c.replica_read_only = parse_yes_no(v)?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "replicaof",
+        mutable: false,
+        get: |c| {
+            c.replicaof.as_ref().map_or(String::new(), |(host, port)| format!("{host} {port}"))
+        },
+        set: |c, v| {
+            let words: Vec<_> = v.split_whitespace().collect();
+            c.replicaof = match words.as_slice() {
+                [no, one] if no.eq_ignore_ascii_case("no") && one.eq_ignore_ascii_case("one") => {
+                    None
+                }
+                [host, port] => {
+                    Some((host.to_string(), port.parse().map_err(|_| "invalid master port")?))
+                }
+                _ => return Err("argument must be '<host> <port>' or 'no one'".into()),
+            };
+            Ok(())
+        },
+    },
+    Param {
+        name: "save",
+        mutable: true,
+        get: |c| {
+            let points = c.persistence.save.iter();
+            points.map(|p| format!("{} {}", p.seconds, p.changes)).collect::<Vec<_>>().join(" ")
+        },
+        set: |c, v| {
+            c.persistence.save = SavePoint::parse_list(v).ok_or("invalid save parameters")?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "socket-rcvbuf",
+        mutable: true,
+        get: |c| c.socket.recv_buffer_size.to_string(),
+        set: |c, v| {
+            c.socket.recv_buffer_size =
+                parse_memory(v).ok_or("argument must be a memory value")? as usize;
+            Ok(())
+        },
+    },
+    Param {
+        name: "socket-sndbuf",
+        mutable: true,
+        get: |c| c.socket.send_buffer_size.to_string(),
+        set: |c, v| {
+            c.socket.send_buffer_size =
+                parse_memory(v).ok_or("argument must be a memory value")? as usize;
+            Ok(())
+        },
+    },
+    Param {
+        name: "tcp-keepalive",
+        mutable: true,
+        get: |c| c.socket.keepalive.to_string(),
+        set: |c, v| {
+            c.socket.keepalive =
+                v.parse().map_err(|_| "argument couldn't be parsed into an integer")?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "tcp-nodelay",
+        mutable: true,
+        get: |c| yes_no(c.socket.nodelay),
+        set: |c, v| {
+            c.socket.nodelay = parse_yes_no(v)?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "timeout",
+        mutable: true,
+        get: |c| c.timeout.to_string(),
+        set: |c, v| {
+            c.timeout = v.parse().map_err(|_| "argument couldn't be parsed into an integer")?;
+            Ok(())
+        },
+    },
+];
+
+fn find(name: &str) -> Option<&'static Param> {
+    PARAMS.iter().find(|param| param.name.eq_ignore_ascii_case(name))
+}
+
+/// 全部配置参数的名称，按名称排序；用于命令行的帮助信息
+pub fn names() -> impl Iterator<Item = &'static str> {
+    PARAMS.iter().map(|param| param.name)
+}
+
+impl Config {
+    /// 解析命令行参数（不含程序名）：`[配置文件] [--name value ...]`
+    ///
+    /// 先加载配置文件，再依次应用命令行中的设置；一个参数的值可以由多个单词组成，
+    /// 例如 `--save 900 1 300 10`。
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Config, String> {
+        let mut args = args.into_iter().peekable();
+        let mut config = match args.next_if(|arg| !arg.starts_with("--")) {
+            Some(path) => {
+                let text = fs::read_to_string(&path)
+                    .map_err(|e| format!("can't open config file '{path}': {e}"))?;
+                Config::parse(&text).map_err(|e| format!("{path}: {e}"))?
+            }
+            None => Config::default(),
+        };
+
+        while let Some(arg) = args.next() {
+            let name =
+                arg.strip_prefix("--").ok_or_else(|| format!("unexpected argument '{arg}'"))?;
+            let mut words = Vec::new();
+            while let Some(word) = args.next_if(|arg| !arg.starts_with("--")) {
+                words.push(word);
+            }
+            config.set(name, &words.join(" "))?;
+        }
+        Ok(config)
+    }
+
+    /// 解析 redis.conf 格式的配置：每行 `name value`，`#` 开头的行为注释，
+    /// 值两侧的引号会被去掉
+    ///
+    /// 与 Redis 一样，多条 `save` 配置依次累加，`save ""` 清空已有的自动快照条件。
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        let mut save = None;
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = unquote(value.trim());
+            let result = if name.eq_ignore_ascii_case("save") {
+                let points = SavePoint::parse_list(value).ok_or("invalid save parameters");
+                points
+                    .map(|points| save.get_or_insert_with(Vec::new).extend(points))
+                    .map_err(String::from)
+            } else {
+                config.set(name, value)
+            };
+            result.map_err(|e| format!("line {}: {e}", n + 1))?;
+        }
+
+        if let Some(save) = save {
+            config.persistence.save = save;
+        }
+        Ok(config)
+    }
+
+    /// 按名称设置一个参数，不检查是否可以在运行时修改
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let param = find(name).ok_or_else(|| format!("unknown option '{name}'"))?;
+        (param.set)(self, value).map_err(|e| format!("'{}': {e}", param.name))
+    }
+}
+
+/// 去掉值两侧成对的引号
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|&q| value.strip_prefix(q).and_then(|v| v.strip_suffix(q)))
+        .unwrap_or(value)
+}
+
+/// 共享的服务器配置
+pub struct ServerConfig {
+    config: watch::Sender<Config>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
+
+impl ServerConfig {
+    /// 以给定的初始配置创建
+    pub fn new(config: Config) -> Self {
+        Self { config: watch::Sender::new(config) }
+    }
+
+    /// 当前配置的副本
+    pub fn current(&self) -> Config {
+        self.config.borrow().clone()
+    }
+
+    /// 订阅配置变化
+    pub fn subscribe(&self) -> watch::Receiver<Config> {
+        self.config.subscribe()
+    }
+
+    /// 名称匹配 glob 模式（不区分大小写）的参数及其当前值，按名称排序
+    pub fn get(&self, pattern: &str) -> Vec<(String, String)> {
+        let pattern = pattern.to_ascii_lowercase();
+        let config = self.config.borrow();
+        PARAMS
+            .iter()
+            .filter(|param| glob::matches(&pattern, param.name))
+            .map(|param| (param.name.to_string(), (param.get)(&config)))
+            .collect()
+    }
+
+    /// 修改一个或多个参数：全部校验通过后才一起生效，任何一个失败时配置保持不变
+    pub fn set(&self, pairs: &[(String, String)]) -> Result<(), DbError> {
+        let mut config = self.current();
+        for (name, value) in pairs {
+            let param = find(name).ok_or_else(|| DbError::UnknownConfig(name.clone()))?;
+            if !param.mutable {
+                return Err(DbError::InvalidConfig(
+                    param.name.into(),
+                    "can't set immutable config".into(),
+                ));
+            }
+            (param.set)(&mut config, value)
+                .map_err(|e| DbError::InvalidConfig(param.name.into(), e))?;
+        }
+        self.config.send_replace(config);
+        Ok(())
+    }
+}
+
+impl<S: Storage> Db<S> {
+    /// CONFIG SET：修改配置并同步到数据库内部的子系统
+    pub fn config_set(&self, pairs: &[(String, String)]) -> Result<(), DbError> {
+        self.config().set(pairs)?;
+        self.apply_config();
+        Ok(())
+    }
+
+    /// 把当前配置同步到内存淘汰、延迟监控、限流、复制与持久化子系统
+    pub(crate) fn apply_config(&self) {
+        let config = self.config().current();
+        self.latency().set_threshold(config.latency_monitor_threshold);
+        self.rate_limiter().set_limit(config.rate_limit);
+        self.set_maxmemory(config.maxmemory);
+        self.set_eviction_policy(config.maxmemory_policy);
+        self.replication().set_read_only(config.replica_read_only);
+        persistence::apply(self, &config.persistence);
+        self.audit().configure(&config.audit);
+    }
+}
+
+/// 解析协议限制：大于 0 的内存大小
+fn parse_limit(v: &str) -> Result<usize, String> {
+    match parse_memory(v) {
+        Some(n) if n > 0 => Ok(n as usize),
+        _ => Err("argument must be a positive memory value".into()),
+    }
+}
+
+/// 解析 `<class> <hard> <soft> <soft-seconds>`，可以一次给出多组；只修改给出的类别
+fn parse_output_limits(limits: &mut OutputLimits, v: &str) -> Result<(), String> {
+    let words: Vec<_> = v.split_whitespace().collect();
+    if words.is_empty() || words.len() % 4 != 0 {
+        return Err("wrong number of arguments in buffer limit configuration".into());
+    }
+
+    let mut updated = *limits;
+    for group in words.chunks(4) {
+        let class = group[0].parse()?;
+        let memory = |s: &str| parse_memory(s).map(|n| n as usize).ok_or("invalid memory value");
+        let limit = OutputLimit {
+            hard: memory(group[1])?,
+            soft: memory(group[2])?,
+            soft_seconds: group[3].parse().map_err(|_| "invalid soft limit seconds")?,
+        };
+        updated.set(class, limit);
+    }
+    *limits = updated;
+    Ok(())
+}
+
+fn yes_no(value: bool) -> String {
+    if value { "yes" } else { "no" }.into()
+}
+
+fn parse_yes_no(s: &str) -> Result<bool, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err("argument must be 'yes' or 'no'".into()),
+    }
+}
+
+/// 解析内存大小，支持 Redis 的单位后缀：`k` / `m` / `g` 为 1000 的幂，`kb` / `mb` / `gb` 为 1024 的幂
+pub fn parse_memory(s: &str) -> Option<u64> {
+    let s = s.to_ascii_lowercase();
+    let (digits, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let unit: u64 = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1 << 10,
+        "m" => 1_000_000,
+        "mb" => 1 << 20,
+        "g" => 1_000_000_000,
+        "gb" => 1 << 30,
+        _ => return None,
+    };
+    digits.parse::<u64>().ok()?.checked_mul(unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::process_command;
+
+    #[test]
+    fn test_params_sorted() {
+        assert!(PARAMS.windows(2).all(|pair| pair[0].name < pair[1].name));
+    }
+
+    #[test]
+    fn test_parse_file() {
+        let text = "
+            # 注释
+            port 7000
+            maxmemory 1mb
+            appendonly yes
+            dir \"/tmp/mini redis\"
+            save 900 1
+            save 60 100
+        ";
+        let config = Config::parse(text).unwrap();
+        assert_eq!(config.port, 7000);
+        assert_eq!(config.maxmemory, 1 << 20);
+        assert!(config.persistence.appendonly);
+        assert_eq!(config.persistence.dir, PathBuf::from("/tmp/mini redis"));
+        assert_eq!(
+            config.persistence.save,
+            vec![SavePoint { seconds: 900, changes: 1 }, SavePoint { seconds: 60, changes: 100 }]
+        );
+
+        assert!(Config::parse("save \"\"").unwrap().persistence.save.is_empty());
+        assert_eq!(
+            Config::parse("\nport x").unwrap_err(),
+            "line 2: 'port': argument must be a port number"
+        );
+        assert_eq!(Config::parse("nosuch 1").unwrap_err(), "line 1: unknown option 'nosuch'");
+    }
+
+    #[test]
+    fn test_from_args() {
+        let path = std::env::temp_dir().join(format!("mini-redis-{}.conf", std::process::id()));
+        fs::write(&path, "port 7000\nbind 0.0.0.0\n").unwrap();
+
+        let args = [path.display().to_string(), "--port".into(), "7001".into()];
+        let config = Config::from_args(args.map(String::from)).unwrap();
+        assert_eq!((config.bind.as_str(), config.port), ("0.0.0.0", 7001));
+
+        let args = ["--save", "900", "1", "--daemonize", "yes", "--log-format", "json"];
+        let config = Config::from_args(args.map(String::from)).unwrap();
+        assert_eq!(config.persistence.save, vec![SavePoint { seconds: 900, changes: 1 }]);
+        assert!(config.daemonize);
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert!(Config::from_args(["--loglevel", "loud"].map(String::from)).is_err());
+
+        let args = ["--replicaof", "127.0.0.1", "6380"];
+        let config = Config::from_args(args.map(String::from)).unwrap();
+        assert_eq!(config.replicaof, Some(("127.0.0.1".into(), 6380)));
+        assert_eq!(Config::parse("replicaof no one").unwrap().replicaof, None);
+        assert!(Config::from_args(["--replicaof", "127.0.0.1"].map(String::from)).is_err());
+        assert!(Config::from_args(["--replicaof", "localhost", "x"].map(String::from)).is_err());
+
+        assert!(Config::from_args(["--port".to_string()]).is_err());
+        assert!(Config::from_args(["/nonexistent/redis.conf".to_string()]).is_err());
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("100"), Some(100));
+        assert_eq!(parse_memory("1kb"), Some(1024));
+        assert_eq!(parse_memory("2M"), Some(2_000_000));
+        assert_eq!(parse_memory("1gb"), Some(1 << 30));
+        assert_eq!(parse_memory("mb"), None);
+        assert_eq!(parse_memory("1tb"), None);
+        assert_eq!(parse_memory("-1"), None);
+    }
+
+    #[test]
+    fn test_get_set() {
+        let config = ServerConfig::default();
+        let mut changes = config.subscribe();
+
+        assert_eq!(
+            config.get("maxmemory*"),
+            vec![
+                ("maxmemory".to_string(), "0".to_string()),
+                ("maxmemory-policy".to_string(), "noeviction".to_string()),
+            ]
+        );
+        assert_eq!(config.get("SAVE"), vec![("save".into(), "3600 1 300 100 60 10000".into())]);
+
+        config.set(&[("MAXMEMORY".into(), "1mb".into()), ("save".into(), "".into())]).unwrap();
+        assert!(changes.has_changed().unwrap());
+        assert_eq!(changes.borrow_and_update().maxmemory, 1 << 20);
+        assert!(config.current().persistence.save.is_empty());
+
+        // 任何一个参数出错时都不生效
+        let err = config.set(&[("timeout".into(), "10".into()), ("port".into(), "1".into())]);
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "ERR CONFIG SET failed (possibly related to argument 'port') - can't set immutable config"
+        );
+        assert_eq!(config.current().timeout, 0);
+        assert!(!changes.has_changed().unwrap());
+
+        // hz 超出范围时按边界处理
+        config.set(&[("hz".into(), "1000".into())]).unwrap();
+        assert_eq!(config.current().hz, 500);
+        config.set(&[("hz".into(), "0".into())]).unwrap();
+        assert_eq!(config.current().hz, 1);
+    }
+
+    #[tokio::test]
+    async fn test_config_commands() {
+        let db = Db::new();
+
+        assert_eq!(process_command(&db, "config get timeout").await, "1) timeout\n2) 0");
+        assert_eq!(process_command(&db, "config set maxmemory 100").await, "OK");
+        assert_eq!(process_command(&db, "config set maxmemory-policy allkeys-lru").await, "OK");
+        assert_eq!(db.maxmemory(), 100);
+        assert_eq!(db.eviction_policy(), EvictionPolicy::AllKeysLru);
+
+        db.config_set(&[("save".into(), "".into())]).unwrap();
+        assert!(db.save_points().is_empty());
+
+        assert_eq!(
+            process_command(&db, "config set nosuch 1").await,
+            "ERR Unknown option or number of arguments for CONFIG SET - 'nosuch'"
+        );
+        assert_eq!(
+            process_command(&db, "config set maxmemory lots").await,
+            "ERR CONFIG SET failed (possibly related to argument 'maxmemory') - argument must be a memory value"
+        );
+        assert_eq!(process_command(&db, "config get nosuch").await, "(empty array)");
+    }
+
+    #[test]
+    fn test_rename_command() {
+        let text = "rename-command CONFIG \"\"\nrename-command flushall \"wipe\"";
+        let config = Config::parse(text).unwrap();
+        assert_eq!(config.rename_command.to_string(), "config \"\" flushall wipe");
+        assert_eq!(
+            Config::parse("rename-command nosuch x").unwrap_err(),
+            "line 1: 'rename-command': no such command 'nosuch'"
+        );
+
+        let args = ["--rename-command", "nosuch", "--rename-command", "keys", ""];
+        assert!(Config::from_args(args.map(String::from)).is_err());
+        let args = ["--rename-command", "keys", "", "--port", "7000"];
+        let config = Config::from_args(args.map(String::from)).unwrap();
+        assert_eq!(config.rename_command.to_string(), "keys \"\"");
+
+        let db = Db::with_config(config);
+        assert!(db.config_set(&[("rename-command".into(), "get x".into())]).is_err());
+    }
+
+    #[test]
+    fn test_output_buffer_limit() {
+        let config = ServerConfig::default();
+        let set = |value: &str| config.set(&[("client-output-buffer-limit".into(), value.into())]);
+
+        set("pubsub 64mb 16mb 30 normal 1kb 0 0").unwrap();
+        let limits = config.current().client_output_buffer_limit;
+        assert_eq!(limits.pubsub, OutputLimit { hard: 64 << 20, soft: 16 << 20, soft_seconds: 30 });
+        assert_eq!(limits.normal, OutputLimit { hard: 1024, soft: 0, soft_seconds: 0 });
+        assert_eq!(limits.replica, OutputLimits::default().replica);
+        assert_eq!(
+            config.get("client-output-buffer-limit")[0].1,
+            "normal 1024 0 0 replica 268435456 67108864 60 pubsub 67108864 16777216 30"
+        );
+
+        // 任何一组出错时都不生效
+        assert!(set("normal 1 1 1 master 1 1 1").is_err());
+        assert!(set("normal 1 1").is_err());
+        assert!(set("normal x 1 1").is_err());
+        assert_eq!(config.current().client_output_buffer_limit, limits);
+
+        // 配置文件中可以分多行设置
+        let text =
+            "client-output-buffer-limit normal 1 2 3\nclient-output-buffer-limit slave 0 0 0";
+        let limits = Config::parse(text).unwrap().client_output_buffer_limit;
+        assert_eq!(limits.normal, OutputLimit { hard: 1, soft: 2, soft_seconds: 3 });
+        assert_eq!(limits.replica, OutputLimit::default());
+    }
+}
@@ -0,0 +1,356 @@
+//! 连接模块
+//!
+//! 在字节流（通常是 `TcpStream`）之上收发 RESP 帧：
+//! - 读取时把数据累积到缓冲区，直到能解码出一个完整的帧
+//! - 写入时把帧直接编码到发送缓冲区，flush 时用向量写一次写出，减少系统调用
+//!
+//! 帧按连接协商的协议版本编码（见 [`Connection::set_protocol`]），默认为 RESP2。
+//!
+//! 服务器读取客户端请求时使用 [`Connection::read_request`]：除 RESP 数组外还接受
+//! 内联格式的请求（见 [`inline`](crate::inline)）。请求受 [`Limits`] 约束，超出限制时
+//! 返回协议错误，不会为对端声明的长度无限制地分配缓冲区。
+//!
+//! 为了支持流水线，[`Connection::try_read_frame`] 只从缓冲区中取出已经完整到达的帧，
+//! [`Connection::feed_frame`] 只把帧写入缓冲区：调用方可以连续处理一批命令，最后一次 flush。
+//!
+//! 复制流程中主节点发送的 RDB 快照是不以 CRLF 结尾的二进制载荷，
+//! 由 [`Connection::read_payload`] / [`Connection::write_payload`] 单独处理。
+
+use std::io::{self, IoSlice};
+
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::{
+    frame::{Frame, Protocol, ProtocolError},
+    inline,
+};
+
+/// 读取客户端请求时的协议限制，默认值与 Redis 相同
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    /// 内联请求一行的最大长度（`proto-max-inline-len`）
+    pub max_inline_len: usize,
+    /// 批量字符串的最大长度（`proto-max-bulk-len`）
+    pub max_bulk_len: usize,
+    /// 一个请求的最大参数个数（`proto-max-multibulk-len`）
+    pub max_multibulk_len: usize,
+    /// 尚未解码出完整请求的数据的最大长度（`client-query-buffer-limit`）
+    pub max_query_buffer: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_inline_len: inline::MAX_INLINE_LEN,
+            max_bulk_len: 512 * 1024 * 1024,
+            max_multibulk_len: 1024 * 1024,
+            max_query_buffer: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+/// 发送缓冲区超过该长度时不等 flush 先写出，避免一大批回复占用过多内存
+const WRITE_HIGH_WATER: usize = 64 * 1024;
+
+/// 每次从底层流读取时缓冲区至少预留的空间
+const READ_CHUNK: usize = 16 * 1024;
+
+/// 一个 RESP 连接
+pub struct Connection<S = TcpStream> {
+    stream: S,
+    buffer: Vec<u8>,
+    /// 尚未写出的数据
+    out: Vec<u8>,
+    /// 写出帧时使用的协议版本
+    protocol: Protocol,
+    /// 读取请求时的协议限制
+    limits: Limits,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Connection<S> {
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            buffer: Vec::with_capacity(4 * 1024),
+            out: Vec::with_capacity(4 * 1024),
+            protocol: Protocol::default(),
+            limits: Limits::default(),
+        }
+    }
+
+    /// 修改读取请求时的协议限制
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// 切换写出帧时使用的协议版本
+    pub fn set_protocol(&mut self, protocol: Protocol) {
+        self.protocol = protocol;
+    }
+
+    /// 读取一个完整的帧，对端正常关闭时返回 `None`
+    ///
+    /// 格式错误返回 `InvalidData` 错误，连接在帧中途被关闭返回 `ConnectionReset` 错误。
+    pub async fn read_frame(&mut self) -> io::Result<Option<Frame>> {
+        loop {
+            if let Some(frame) = self.try_read_frame()? {
+                return Ok(Some(frame));
+            }
+            if !self.fill_buffer().await? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// 从已读取的数据中解码一个完整的帧，不读取底层流；数据不足一个帧时返回 `None`
+    pub fn try_read_frame(&mut self) -> io::Result<Option<Frame>> {
+        let decoded = Frame::decode(&self.buffer).map_err(invalid_data)?;
+        Ok(decoded.map(|(frame, len)| {
+            self.buffer.drain(..len);
+            frame
+        }))
+    }
+
+    /// 读取一个客户端请求，内联请求转换为批量字符串数组；对端正常关闭时返回 `None`
+    pub async fn read_request(&mut self) -> io::Result<Option<Frame>> {
+        loop {
+            if let Some(frame) = self.try_read_request()? {
+                return Ok(Some(frame));
+            }
+            if !self.fill_buffer().await? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// 与 [`Connection::try_read_frame`] 相同，但同时接受内联请求，空请求直接跳过；
+    /// 超出 [`Limits`] 时返回 `InvalidData` 错误
+    pub fn try_read_request(&mut self) -> io::Result<Option<Frame>> {
+        let limits = self.limits;
+        loop {
+            let decoded = match self.buffer.first() {
+                None | Some(b'*') => {
+                    Frame::decode_with(&self.buffer, limits.max_bulk_len, limits.max_multibulk_len)
+                        .map_err(invalid_data)?
+                }
+                Some(_) => inline::decode(&self.buffer, limits.max_inline_len)
+                    .map_err(invalid_data)?
+                    .map(|(args, len)| (Frame::from(args), len)),
+            };
+            let Some((frame, len)) = decoded else {
+                if self.buffer.len() > limits.max_query_buffer {
+                    return Err(invalid_data(ProtocolError("query buffer limit exceeded".into())));
+                }
+                return Ok(None);
+            };
+            self.buffer.drain(..len);
+            // 与 Redis 一样跳过空请求（内联格式的空行与 `*0`）
+            if frame != Frame::Array(vec![]) {
+                return Ok(Some(frame));
+            }
+        }
+    }
+
+    /// 写入一个帧并 flush
+    pub async fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        self.feed_frame(frame).await?;
+        self.flush().await
+    }
+
+    /// 把一个帧写入发送缓冲区，不 flush
+    pub async fn feed_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        frame.encode_into(&mut self.out, self.protocol);
+        if self.out.len() >= WRITE_HIGH_WATER {
+            self.write_out(&[]).await?;
+        }
+        Ok(())
+    }
+
+    /// 把发送缓冲区中的数据全部写出
+    pub async fn flush(&mut self) -> io::Result<()> {
+        self.write_out(&[]).await?;
+        self.stream.flush().await
+    }
+
+    /// 读取 `$<len>\r\n<bytes>` 形式的二进制载荷（末尾没有 CRLF）
+    pub async fn read_payload(&mut self) -> io::Result<Vec<u8>> {
+        let header = loop {
+            if let Some(end) = self.buffer.windows(2).position(|w| w == b"\r\n") {
+                let header: Vec<u8> = self.buffer.drain(..end + 2).collect();
+                break header;
+            }
+            if !self.fill_buffer().await? {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        };
+
+        let len = std::str::from_utf8(&header[..header.len() - 2])
+            .ok()
+            .and_then(|line| line.strip_prefix('$'))
+            .and_then(|len| len.parse::<usize>().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid payload header"))?;
+
+        while self.buffer.len() < len {
+            if !self.fill_buffer().await? {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+        Ok(self.buffer.drain(..len).collect())
+    }
+
+    /// 写入 `$<len>\r\n<bytes>` 形式的二进制载荷并 flush
+    pub async fn write_payload(&mut self, payload: &[u8]) -> io::Result<()> {
+        self.out.extend_from_slice(format!("${}\r\n", payload.len()).as_bytes());
+        self.write_out(payload).await?;
+        self.stream.flush().await
+    }
+
+    /// 原样写入已经编码好的字节并 flush，用于向副本转发命令流
+    pub async fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.write_out(bytes).await?;
+        self.stream.flush().await
+    }
+
+    /// 把发送缓冲区与 `tail` 一起用向量写写出，`tail` 不复制到发送缓冲区（用于较大的载荷）
+    async fn write_out(&mut self, tail: &[u8]) -> io::Result<()> {
+        let mut slices = [IoSlice::new(&self.out), IoSlice::new(tail)];
+        let mut slices = &mut slices[..];
+        IoSlice::advance_slices(&mut slices, 0);
+        while !slices.is_empty() {
+            let n = self.stream.write_vectored(slices).await?;
+            if n == 0 {
+                return Err(io::ErrorKind::WriteZero.into());
+            }
+            IoSlice::advance_slices(&mut slices, n);
+        }
+        self.out.clear();
+        Ok(())
+    }
+
+    /// 从底层流读取更多数据，对端在帧边界关闭时返回 `false`
+    async fn fill_buffer(&mut self) -> io::Result<bool> {
+        self.buffer.reserve(READ_CHUNK);
+        let n = self.stream.read_buf(&mut self.buffer).await?;
+        if n == 0 {
+            return if self.buffer.is_empty() {
+                Ok(false)
+            } else {
+                Err(io::Error::new(io::ErrorKind::ConnectionReset, "connection reset by peer"))
+            };
+        }
+        Ok(true)
+    }
+}
+
+fn invalid_data(e: ProtocolError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Connection, Limits};
+    use crate::frame::Frame;
+
+    #[tokio::test]
+    async fn test_frame_roundtrip() {
+        let (client, server) = tokio::io::duplex(64);
+        let mut client = Connection::new(client);
+        let mut server = Connection::new(server);
+
+        // 帧比管道缓冲区大，读端会分多次收到
+        let frame = Frame::from(vec!["set".to_string(), "key".into(), "x".repeat(100)]);
+        let sent = frame.clone();
+        tokio::spawn(async move {
+            client.write_frame(&sent).await.unwrap();
+            client.write_frame(&Frame::Simple("OK".into())).await.unwrap();
+        });
+
+        assert_eq!(server.read_frame().await.unwrap(), Some(frame));
+        assert_eq!(server.read_frame().await.unwrap(), Some(Frame::Simple("OK".into())));
+        assert_eq!(server.read_frame().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_frames() {
+        let (client, server) = tokio::io::duplex(1024);
+        let mut client = Connection::new(client);
+        let mut server = Connection::new(server);
+
+        client.feed_frame(&Frame::Integer(1)).await.unwrap();
+        client.feed_frame(&Frame::Integer(2)).await.unwrap();
+        client.write_bytes(b":3\r").await.unwrap();
+
+        assert_eq!(server.read_frame().await.unwrap(), Some(Frame::Integer(1)));
+        assert_eq!(server.try_read_frame().unwrap(), Some(Frame::Integer(2)));
+        // 第三个帧还没有完整到达
+        assert_eq!(server.try_read_frame().unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_inline_requests() {
+        let (client, server) = tokio::io::duplex(1024);
+        let mut client = Connection::new(client);
+        let mut server = Connection::new(server);
+
+        client.write_bytes(b"set k \"a b\"\r\n\r\n*1\r\n$4\r\nping\r\nget k\n").await.unwrap();
+        drop(client);
+
+        let args = |args: &[&str]| {
+            Some(Frame::from(args.iter().map(|a| a.to_string()).collect::<Vec<_>>()))
+        };
+        assert_eq!(server.read_request().await.unwrap(), args(&["set", "k", "a b"]));
+        assert_eq!(server.read_request().await.unwrap(), args(&["ping"]));
+        assert_eq!(server.read_request().await.unwrap(), args(&["get", "k"]));
+        assert_eq!(server.read_request().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_request_limits() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        let mut server = Connection::new(server);
+        server.set_limits(Limits { max_bulk_len: 8, ..Limits::default() });
+
+        tokio::io::AsyncWriteExt::write_all(&mut client, b"*2\r\n$3\r\nget\r\n$9\r\n")
+            .await
+            .unwrap();
+        let err = server.read_request().await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "ERR Protocol error: invalid bulk length");
+
+        // 没有读到完整请求的数据超过上限
+        let (mut client, server) = tokio::io::duplex(1024);
+        let mut server = Connection::new(server);
+        server.set_limits(Limits { max_query_buffer: 16, ..Limits::default() });
+        tokio::io::AsyncWriteExt::write_all(&mut client, b"*1\r\n$20\r\n0123456789").await.unwrap();
+        let err = server.read_request().await.unwrap_err();
+        assert_eq!(err.to_string(), "ERR Protocol error: query buffer limit exceeded");
+    }
+
+    #[tokio::test]
+    async fn test_payload_then_frame() {
+        let (client, server) = tokio::io::duplex(64);
+        let mut client = Connection::new(client);
+        let mut server = Connection::new(server);
+
+        client.write_payload(&[0, 159, 146, 150]).await.unwrap();
+        client.write_frame(&Frame::Integer(1)).await.unwrap();
+
+        assert_eq!(server.read_payload().await.unwrap(), vec![0, 159, 146, 150]);
+        assert_eq!(server.read_frame().await.unwrap(), Some(Frame::Integer(1)));
+    }
+
+    #[tokio::test]
+    async fn test_reset_mid_frame() {
+        let (mut client, server) = tokio::io::duplex(64);
+        let mut server = Connection::new(server);
+
+        tokio::io::AsyncWriteExt::write_all(&mut client, b"*2\r\n$3\r\nget").await.unwrap();
+        drop(client);
+
+        let err = server.read_frame().await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::ConnectionReset);
+    }
+}
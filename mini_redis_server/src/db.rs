@@ -1,42 +1,870 @@
 //! 内存数据库模块
 //!
-//! 封装一个基于 `RwLock<HashMap>` 的简单键值数据库。
-//! 支持异步 get / set 操作。
+//! 封装一个按键哈希分片、每个分片一把 `RwLock` 的简单键值数据库。
+//! 支持异步 get / set 操作，以及哈希等复合类型的操作（见各子模块）。
+//! 键值的实际存取通过 [`Storage`] trait 完成，默认使用内存中的 [`Keyspace`]（见 `storage` 模块）。
 //!
 //! 特点：
 //! - 多任务共享（通过 `Arc` 实现）
-//! - 并发安全（通过 `RwLock` 实现）
+//! - 并发安全（通过 `RwLock` 实现），访问不同分片的命令互不阻塞（见 `shards` 模块）
+//! - 同一个键上的写命令按执行的顺序传播到追加日志与副本（见 `order` 模块）
 //! - 异步友好
+//! - 可设置内存上限，超出时按策略淘汰键（见 `memory` 模块）
+//! - 嵌入使用时 GET 未命中可以回源加载（见 `loader` 模块）
+//!
+//! 开启 `actor-db` feature 后另有一个基于消息传递的对照实现 `ActorDb`，由单个任务独占存储后端，
+//! 用于对比两种并发设计（见 `actor` 模块）。
+
+#[cfg(feature = "actor-db")]
+mod actor;
+mod bitmap;
+mod dump;
+mod expire;
+mod export;
+mod geo;
+mod hash;
+mod hooks;
+mod keyspace;
+mod keystats;
+mod lazyfree;
+mod loader;
+mod memory;
+mod notify;
+mod object;
+mod order;
+mod select;
+mod set;
+mod shards;
+mod storage;
+mod stream;
+mod zset;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, io, mem,
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+#[cfg(feature = "actor-db")]
+pub use self::actor::ActorDb;
+pub(crate) use self::hooks::KeyEvent;
+pub use self::{
+    expire::{ExpireFlags, TtlPolicy},
+    hooks::KeyObserver,
+    keyspace::{Keyspace, unix_time_ms},
+    keystats::BigKey,
+    loader::Loaded,
+    memory::EvictionPolicy,
+    select::DEFAULT_DATABASES,
+    shards::DEFAULT_SHARDS,
+    storage::Storage,
+};
+use self::{
+    hooks::Hooks,
+    loader::Loader,
+    memory::MemoryLimit,
+    notify::KeyNotifier,
+    order::{KeyOrder, Ordered},
+    shards::Shards,
+};
+use crate::{
+    acl::Acl,
+    audit::AuditLog,
+    client::Clients,
+    config::{Config, ServerConfig},
+    fault::Faults,
+    glob,
+    handler::Registry,
+    latency::LatencyMonitor,
+    persistence::{Aof, RdbState},
+    pubsub::PubSub,
+    random::Rng,
+    ratelimit::RateLimiter,
+    replication::Replication,
+    sentinel::Sentinel,
+    sorted_set::SortedSet,
+    stats::Stats,
+    stream::Stream,
+};
+
+/// RANDOMKEY 采到已过期的键时重新采样的最大次数
+const RANDOM_KEY_TRIES: usize = 100;
+
+/// 数据库中存储的值
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// 字符串，按字节保存；位图命令可能写入不是 UTF-8 的内容
+    String(Vec<u8>),
+    /// 哈希表：field -> value
+    Hash(HashMap<String, String>),
+    /// 集合
+    Set(HashSet<String>),
+    /// 有序集合
+    ZSet(SortedSet),
+    /// 流
+    Stream(Stream),
+}
+
+/// 持久化使用的键值记录：键、值以及可选的过期时间（Unix 毫秒）
+pub(crate) type Record = (String, Value, Option<u64>);
+
+/// 所有数据库的记录，下标为数据库编号
+pub(crate) type Snapshot = Vec<Vec<Record>>;
+
+/// 数据库操作错误
+#[derive(Debug, PartialEq)]
+pub enum DbError {
+    /// 对保存了其他类型值的键执行操作
+    WrongType,
+    /// 字符串值不是整数
+    NotInteger,
+    /// 哈希字段的值不是整数
+    HashValueNotInteger,
+    /// 哈希字段的值不是浮点数
+    HashValueNotFloat,
+    /// 整数自增/自减溢出
+    Overflow,
+    /// 浮点数运算结果为 NaN 或无穷大
+    NanOrInfinity,
+    /// 有序集合分值运算结果为 NaN
+    ScoreNan,
+    /// 未开启 AOF 持久化
+    AofDisabled,
+    /// 已有 AOF 重写正在进行
+    AofRewriteInProgress,
+    /// 写入 AOF 文件失败
+    Aof(String),
+    /// 已有后台快照正在进行
+    BgSaveInProgress,
+    /// 保存快照失败
+    Save(String),
+    /// DUMP 载荷格式、版本或校验和错误
+    BadPayload,
+    /// IMPORT 的记录格式错误：序号（从 1 开始）、原因
+    BadImport(usize, String),
+    /// 数值参数超出允许范围
+    OutOfRange,
+    /// RESTORE 的目标键已存在
+    BusyKey,
+    /// 与 MIGRATE 目标节点通信失败或超时
+    MigrateIo(String),
+    /// MIGRATE 目标节点返回错误
+    MigrateTarget(String),
+    /// 内存占用超出 maxmemory 且无法淘汰
+    Oom,
+    /// CONFIG SET 的参数不存在
+    UnknownConfig(String),
+    /// CONFIG SET 的参数值无效或参数不能在运行时修改：参数名、原因
+    InvalidConfig(String, String),
+    /// SELECT、MOVE、SWAPDB 的数据库编号超出范围
+    DbIndexOutOfRange,
+    /// MOVE 的源数据库与目标数据库相同
+    SameObject,
+    /// 连接尚未认证
+    NoAuth,
+    /// 用户名或密码错误，或用户已被禁用
+    WrongPass,
+    /// 用户无权执行命令：用户名、命令名
+    NoPermCommand(String, String),
+    /// 用户无权访问命令涉及的键
+    NoPermKey,
+    /// ACL SETUSER 的规则无效：规则、原因
+    AclRule(String, String),
+    /// 客户端名称包含空格、换行等字符
+    InvalidClientName,
+    /// CLIENT SETINFO 的值包含空格、换行等字符：属性名
+    InvalidClientInfo(String),
+    /// CLIENT KILL 找不到指定的连接
+    NoSuchClient,
+    /// XADD 的 ID 为 0-0
+    StreamIdZero,
+    /// XADD 的 ID 不大于流中最后一条消息的 ID
+    StreamIdTooSmall,
+    /// 流或消费者组不存在：键、组名
+    NoGroup(String, String),
+    /// XGROUP CREATE 的组名已存在
+    BusyGroup,
+    /// XGROUP 的目标键不存在
+    StreamKeyRequired,
+    /// GEOADD 的经纬度超出范围：经度、纬度
+    InvalidLonLat(f64, f64),
+    /// GEOSEARCH 的中心成员不存在
+    GeoMemberMissing,
+    /// 哨兵没有监控该名称的主节点
+    NoSuchMaster,
+    /// SENTINEL MONITOR 的名称已被使用
+    DuplicateMaster,
+    /// SENTINEL MONITOR 的 quorum 为 0
+    InvalidQuorum,
+    /// SENTINEL SET 的选项或值无效
+    InvalidSentinelOption(String),
+    /// SENTINEL FAILOVER 时没有可以提升的副本
+    NoGoodReplica,
+    /// 已有故障转移正在等待执行
+    FailoverInProgress,
+    /// 一条命令涉及的键或分片频道不属于同一个哈希槽
+    CrossSlot,
+    /// 客户端超出了 `client-rate-limit` 限制的命令速率
+    Throttled,
+    /// 未开启 `fault-injection` 特性时执行 DEBUG 命令
+    DebugDisabled,
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            DbError::WrongType => {
+                "WRONGTYPE Operation against a key holding the wrong kind of value"
+            }
+            DbError::NotInteger => "ERR value is not an integer or out of range",
+            DbError::HashValueNotInteger => "ERR hash value is not an integer",
+            DbError::HashValueNotFloat => "ERR hash value is not a float",
+            DbError::Overflow => "ERR increment or decrement would overflow",
+            DbError::NanOrInfinity => "ERR increment would produce NaN or Infinity",
+            DbError::ScoreNan => "ERR resulting score is not a number (NaN)",
+            DbError::AofDisabled => "ERR AOF is not enabled",
+            DbError::AofRewriteInProgress => {
+                "ERR Background append only file rewriting already in progress"
+            }
+            DbError::Aof(e) => return write!(f, "MISCONF Errors writing to the AOF file: {e}"),
+            DbError::BgSaveInProgress => "ERR Background save already in progress",
+            DbError::Save(e) => return write!(f, "ERR Failed to save the RDB snapshot: {e}"),
+            DbError::BadPayload => "ERR DUMP payload version or checksum are wrong",
+            DbError::BadImport(n, reason) => {
+                return write!(f, "ERR invalid IMPORT record {n}: {reason}");
+            }
+            DbError::OutOfRange => "ERR value is out of range",
+            DbError::BusyKey => "BUSYKEY Target key name already exists.",
+            DbError::MigrateIo(e) => {
+                return write!(f, "IOERR error or timeout talking to target instance: {e}");
+            }
+            DbError::MigrateTarget(e) => {
+                return write!(f, "ERR Target instance replied with error: {e}");
+            }
+            DbError::Oom => "OOM command not allowed when used memory > 'maxmemory'.",
+            DbError::UnknownConfig(name) => {
+                return write!(
+                    f,
+                    "ERR Unknown option or number of arguments for CONFIG SET - '{name}'"
+                );
+            }
+            DbError::InvalidConfig(name, reason) => {
+                return write!(
+                    f,
+                    "ERR CONFIG SET failed (possibly related to argument '{name}') - {reason}"
+                );
+            }
+            DbError::DbIndexOutOfRange => "ERR DB index is out of range",
+            DbError::SameObject => "ERR source and destination objects are the same",
+            DbError::NoAuth => "NOAUTH Authentication required.",
+            DbError::WrongPass => "WRONGPASS invalid username-password pair or user is disabled.",
+            DbError::NoPermCommand(user, command) => {
+                return write!(
+                    f,
+                    "NOPERM User {user} has no permissions to run the '{command}' command"
+                );
+            }
+            DbError::NoPermKey => "NOPERM No permissions to access a key",
+            DbError::AclRule(rule, reason) => {
+                return write!(f, "ERR Error in ACL SETUSER modifier '{rule}': {reason}");
+            }
+            DbError::InvalidClientName => {
+                "ERR Client names cannot contain spaces, newlines or special characters."
+            }
+            DbError::InvalidClientInfo(attr) => {
+                return write!(
+                    f,
+                    "ERR {attr} cannot contain spaces, newlines or special characters."
+                );
+            }
+            DbError::NoSuchClient => "ERR No such client",
+            DbError::StreamIdZero => "ERR The ID specified in XADD must be greater than 0-0",
+            DbError::StreamIdTooSmall => {
+                "ERR The ID specified in XADD is equal or smaller than the target stream top item"
+            }
+            DbError::NoGroup(key, group) => {
+                return write!(f, "NOGROUP No such key '{key}' or consumer group '{group}'");
+            }
+            DbError::BusyGroup => "BUSYGROUP Consumer Group name already exists",
+            DbError::StreamKeyRequired => {
+                "ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may \
+                 want to use the MKSTREAM option to create an empty stream automatically."
+            }
+            DbError::InvalidLonLat(lon, lat) => {
+                return write!(f, "ERR invalid longitude,latitude pair {lon:.6},{lat:.6}");
+            }
+            DbError::GeoMemberMissing => "ERR could not decode requested zset member",
+            DbError::NoSuchMaster => "ERR No such master with that name",
+            DbError::DuplicateMaster => "ERR Duplicated master name",
+            DbError::InvalidQuorum => "ERR Quorum must be 1 or greater.",
+            DbError::InvalidSentinelOption(option) => {
+                return write!(f, "ERR Invalid argument '{option}' for SENTINEL SET");
+            }
+            DbError::NoGoodReplica => "NOGOODSLAVE No suitable replica to promote",
+            DbError::FailoverInProgress => "INPROG Failover already in progress",
+            DbError::CrossSlot => "CROSSSLOT Keys in request don't hash to the same slot",
+            DbError::Throttled => "THROTTLED Command rate limit exceeded, try again later",
+            DbError::DebugDisabled => {
+                "ERR DEBUG command not allowed, rebuild with the fault-injection feature"
+            }
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for DbError {}
+
+impl From<io::Error> for DbError {
+    fn from(e: io::Error) -> Self {
+        DbError::Aof(e.to_string())
+    }
+}
 
-use std::{collections::HashMap, sync::Arc};
+/// 异步可共享的数据库类型，`S` 为存储后端
+pub struct Db<S: Storage = Keyspace> {
+    /// 各个逻辑数据库，每个数据库按键哈希分片，每个分片各有一把 RwLock
+    databases: Arc<[Shards<S>]>,
+    /// 当前选中的数据库编号，键操作都作用于这个数据库（见 [`Db::select`]）
+    index: usize,
+    /// 最近一次传播的命令所在的数据库，与当前不同时先传播一条 SELECT
+    propagated_db: Arc<Mutex<Option<usize>>>,
+    /// 随机采样类命令使用的随机数生成器
+    rng: Arc<Mutex<Rng>>,
+    /// 阻塞命令的按键唤醒注册表
+    notifier: Arc<KeyNotifier>,
+    /// 发布/订阅注册表
+    pubsub: PubSub,
+    /// 开启 AOF 持久化后的追加日志
+    aof: Arc<OnceLock<Aof>>,
+    /// 写命令在执行与传播期间持有读锁，持久化快照持有写锁，
+    /// 保证快照与传播的命令流之间没有重叠或遗漏
+    write_gate: Arc<RwLock<()>>,
+    /// 每个键的修改与传播顺序（见 `order` 模块）
+    order: Arc<KeyOrder>,
+    /// RDB 快照状态
+    rdb: Arc<RdbState>,
+    /// 脏计数：自上次成功保存快照以来的写入次数
+    dirty: Arc<AtomicU64>,
+    /// 主从复制状态
+    replication: Arc<Replication>,
+    /// 哨兵状态
+    sentinel: Arc<Sentinel>,
+    /// 内存上限与淘汰策略
+    memory: Arc<MemoryLimit>,
+    /// 运行时配置
+    config: Arc<ServerConfig>,
+    /// 运行统计
+    stats: Arc<Stats>,
+    /// 延迟监控
+    latency: Arc<LatencyMonitor>,
+    /// 用户与权限
+    acl: Arc<Acl>,
+    /// 已连接的客户端
+    clients: Clients,
+    /// 客户端限流
+    rate_limiter: Arc<RateLimiter>,
+    /// 写命令审计日志
+    audit: Arc<AuditLog>,
+    /// 故障注入设置
+    faults: Arc<Faults>,
+    /// 键变更钩子
+    hooks: Arc<Hooks>,
+    /// 读穿透加载
+    loader: Arc<Loader>,
+    /// 命令注册表
+    commands: Arc<Registry<S>>,
+}
 
-use tokio::sync::RwLock;
+// 手动实现 Clone / Default：派生会额外要求后端 `S` 实现同样的 trait
+impl<S: Storage> Clone for Db<S> {
+    fn clone(&self) -> Self {
+        Self {
+            databases: self.databases.clone(),
+            index: self.index,
+            propagated_db: self.propagated_db.clone(),
+            rng: self.rng.clone(),
+            notifier: self.notifier.clone(),
+            pubsub: self.pubsub.clone(),
+            aof: self.aof.clone(),
+            write_gate: self.write_gate.clone(),
+            order: self.order.clone(),
+            rdb: self.rdb.clone(),
+            dirty: self.dirty.clone(),
+            replication: self.replication.clone(),
+            sentinel: self.sentinel.clone(),
+            memory: self.memory.clone(),
+            config: self.config.clone(),
+            stats: self.stats.clone(),
+            latency: self.latency.clone(),
+            acl: self.acl.clone(),
+            clients: self.clients.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+            audit: self.audit.clone(),
+            faults: self.faults.clone(),
+            hooks: self.hooks.clone(),
+            loader: self.loader.clone(),
+            commands: self.commands.clone(),
+        }
+    }
+}
 
-/// 异步可共享的数据库类型
-#[derive(Clone, Default)]
-pub struct Db {
-    /// 内部存储结构： RwLock 确保并发安全
-    inner: Arc<RwLock<HashMap<String, String>>>,
+impl<S: Storage> Default for Db<S> {
+    fn default() -> Self {
+        Self {
+            databases: databases(DEFAULT_DATABASES, DEFAULT_SHARDS),
+            index: 0,
+            propagated_db: Arc::default(),
+            rng: Arc::default(),
+            notifier: Arc::default(),
+            pubsub: PubSub::default(),
+            aof: Arc::default(),
+            write_gate: Arc::default(),
+            order: Arc::new(KeyOrder::new(DEFAULT_SHARDS)),
+            rdb: Arc::default(),
+            dirty: Arc::default(),
+            replication: Arc::default(),
+            sentinel: Arc::default(),
+            memory: Arc::default(),
+            config: Arc::default(),
+            stats: Arc::default(),
+            latency: Arc::default(),
+            acl: Arc::default(),
+            clients: Clients::default(),
+            rate_limiter: Arc::default(),
+            audit: Arc::default(),
+            faults: Arc::default(),
+            hooks: Arc::default(),
+            loader: Arc::default(),
+            commands: Arc::default(),
+        }
+    }
 }
 
 impl Db {
-    /// 创建一个新的空数据库
+    /// 创建一个使用内存后端的空数据库
     pub fn new() -> Self {
         Self::default()
     }
 
-    /// 异步读取键的值
-    pub async fn get(&self, key: &str) -> Option<String> {
-        let guard = self.inner.read().await;
-        guard.get(key).cloned()
+    /// 创建一个每个数据库使用 `count` 个分片的空数据库（至少一个分片）
+    pub fn with_shards(count: usize) -> Self {
+        Self {
+            databases: databases(DEFAULT_DATABASES, count),
+            order: Arc::new(KeyOrder::new(count)),
+            ..Self::default()
+        }
+    }
+
+    /// 以启动配置创建一个空数据库，配置中的数据库数量、内存上限、命令改名等设置立即生效
+    pub fn with_config(config: Config) -> Self {
+        let mut commands = Registry::default();
+        commands.set_renames(config.rename_command.clone());
+        let db = Self {
+            databases: databases(config.databases, DEFAULT_SHARDS),
+            config: Arc::new(ServerConfig::new(config)),
+            commands: Arc::new(commands),
+            ..Self::default()
+        };
+        db.apply_config();
+        db
     }
 
-    /// 异步写入键的值
+    /// 创建一个使用固定随机种子的空数据库，使随机类命令的结果可复现（主要用于测试）
+    pub fn with_seed(seed: u64) -> Self {
+        Self { rng: Arc::new(Mutex::new(Rng::seeded(seed))), ..Self::default() }
+    }
+}
+
+impl<S: Storage> Db<S> {
+    /// 创建一个使用指定存储后端的空数据库，例如 `Db::<MyStorage>::with_storage()`
+    pub fn with_storage() -> Self {
+        Self::default()
+    }
+
+    /// 异步读取键的值，键不存在时尝试读穿透加载（见 [`Db::set_loader`]）
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, DbError> {
+        {
+            let guard = self.shards().read(key).await;
+
+            self.stats.keyspace_lookup(guard.contains_key(key));
+            match guard.get(key) {
+                Some(Value::String(value)) => return Ok(Some(value.clone())),
+                Some(_) => return Err(DbError::WrongType),
+                None => {}
+            }
+        }
+        Ok(self.load(key).await)
+    }
+
+    /// 异步写入键的值，清除原有的过期时间
     pub async fn set(&self, key: String, value: String) {
-        let mut guard = self.inner.write().await;
+        self.set_with_policy(key, value, TtlPolicy::Clear).await;
+    }
+
+    /// 将字符串值按整数加上 `delta`（INCR / INCRBY），键不存在时视为 0，返回新值；保留原有的过期时间
+    pub async fn incr_by(&self, key: String, delta: i64) -> Result<i64, DbError> {
+        let mut guard = self.shards().write(&key).await;
+
+        let Some(value) = guard.get_mut(&key) else {
+            guard.set(key, Value::String(delta.to_string().into_bytes()));
+            return Ok(delta);
+        };
+        let Value::String(bytes) = value else {
+            return Err(DbError::WrongType);
+        };
+        let current = std::str::from_utf8(bytes).ok().and_then(|s| s.parse::<i64>().ok());
+        let new =
+            current.ok_or(DbError::NotInteger)?.checked_add(delta).ok_or(DbError::Overflow)?;
+        *bytes = new.to_string().into_bytes();
+        Ok(new)
+    }
+
+    /// 删除键，返回实际删除的数量
+    pub async fn del(&self, keys: &[String]) -> usize {
+        let mut guard = self.shards().write_many(keys).await;
+
+        let mut removed = 0;
+        for key in keys {
+            let shard = guard.shard_mut(key);
+            // 已过期但尚未清理的键不计入
+            if shard.contains_key(key) {
+                removed += 1;
+            }
+            shard.remove(key);
+        }
+        removed
+    }
+
+    /// 当前选中的数据库
+    fn shards(&self) -> &Shards<S> {
+        &self.databases[self.index]
+    }
+
+    /// 键的数量（与 Redis 一样可能包含已过期但尚未清理的键）
+    pub async fn dbsize(&self) -> usize {
+        let guard = self.shards().read_all().await;
+
+        guard.iter().map(Storage::len).sum()
+    }
+
+    /// 清空当前数据库（FLUSHDB）
+    ///
+    /// `lazy` 为 `true` 时只把旧的存储整体换成空的，在阻塞线程池中释放旧数据，
+    /// 避免清空大量键时长时间占用锁和运行时线程。
+    pub async fn flush(&self, lazy: bool) {
+        self.flush_shards(self.shards(), lazy).await;
+    }
+
+    /// 清空所有数据库（FLUSHALL）
+    pub async fn flush_all(&self, lazy: bool) {
+        for shards in self.databases.iter() {
+            self.flush_shards(shards, lazy).await;
+        }
+    }
+
+    /// 清空一个数据库，`lazy` 为 `true` 时在后台释放旧数据（见 `lazyfree` 模块）
+    async fn flush_shards(&self, shards: &Shards<S>, lazy: bool) {
+        let mut guard = shards.write_all().await;
+
+        if lazy {
+            self.free_storage(guard.iter_mut().map(mem::take).collect());
+        } else {
+            guard.iter_mut().for_each(|shard| shard.clear());
+        }
+    }
+
+    /// 对当前数据库的全部分片加写锁并保持 `duration`，制造锁竞争（DEBUG CONTEND）
+    pub async fn contend(&self, duration: Duration) {
+        let _guard = self.shards().write_all().await;
+        tokio::time::sleep(duration).await;
+    }
+
+    /// 返回所有匹配 glob 模式的键（按字典序排列）
+    pub async fn keys(&self, pattern: &str) -> Vec<String> {
+        let guard = self.shards().read_all().await;
+
+        let mut keys: Vec<_> = guard
+            .iter()
+            .flat_map(|shard| shard.keys())
+            .filter(|key| glob::matches(pattern, key))
+            .cloned()
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// 从游标开始遍历一部分键（SCAN），大约 `count` 个，返回下一个游标（0 表示结束）与其中
+    /// 匹配 glob 模式 `pattern`、类型为 `key_type` 的键
+    ///
+    /// 游标同时编码分片编号与分片内的游标：`分片内游标 * 分片数 + 分片编号`，各分片依次遍历，
+    /// 每次只对一个分片加读锁。遍历全程都存在的键至少返回一次，可能重复返回。
+    pub async fn scan(
+        &self,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&str>,
+        key_type: Option<&str>,
+    ) -> (u64, Vec<String>) {
+        let shards = self.shards().len() as u64;
+        let (mut shard, mut cursor) = (cursor % shards, cursor / shards);
+        let (mut keys, mut scanned) = (Vec::new(), 0);
+        loop {
+            {
+                let guard = self.shards().read_index(shard as usize).await;
+                let mut batch = Vec::new();
+                cursor = guard.scan_keys(cursor, count - scanned, &mut batch);
+                scanned += batch.len();
+                keys.extend(batch.into_iter().filter(|key| {
+                    pattern.is_none_or(|pattern| glob::matches(pattern, key))
+                        && key_type.is_none_or(|key_type| {
+                            guard.peek(key).is_some_and(|value| value.type_name() == key_type)
+                        })
+                }));
+            }
+            if cursor == 0 {
+                shard += 1;
+                if shard == shards {
+                    return (0, keys);
+                }
+            }
+            if scanned >= count {
+                return (cursor * shards + shard, keys);
+            }
+        }
+    }
+
+    /// 随机返回一个未过期的键，数据库为空时返回 `None`
+    ///
+    /// 按各分片的键数量加权选出分片，再由存储后端随机采样，每个键被选中的概率相同。
+    /// 采到已过期的键时重新采样；多次失败或后端不支持随机采样时退化为遍历取第一个键。
+    pub async fn randomkey(&self) -> Option<String> {
+        let guard = self.shards().read_all().await;
+
+        let total: usize = guard.iter().map(Storage::len).sum();
+        if total == 0 {
+            return None;
+        }
+        for _ in 0..RANDOM_KEY_TRIES {
+            let (mut pick, random) = {
+                let mut rng = self.rng.lock().unwrap();
+                (rng.below(total), rng.next_u64())
+            };
+            let Some(shard) = guard.iter().find(|shard| {
+                let found = pick < shard.len();
+                pick = pick.saturating_sub(shard.len());
+                found
+            }) else {
+                break;
+            };
+            match shard.random_key(random, false) {
+                Some(key) if shard.contains_key(key) => return Some(key.clone()),
+                Some(_) => continue,
+                None => break,
+            }
+        }
+        guard.iter().flat_map(|shard| shard.keys()).next().cloned()
+    }
+
+    /// 更新键的访问时间（TOUCH），返回存在的键的数量，重复给出的键重复计数
+    pub async fn touch(&self, keys: &[String]) -> usize {
+        let guard = self.shards().read_many(keys).await;
+
+        keys.iter()
+            .filter(|key| {
+                let found = guard.shard(key).get(key).is_some();
+                self.stats.keyspace_lookup(found);
+                found
+            })
+            .count()
+    }
+
+    /// 运行时配置
+    pub fn config(&self) -> &ServerConfig {
+        &self.config
+    }
+
+    /// 运行统计
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// 延迟监控
+    pub fn latency(&self) -> &LatencyMonitor {
+        &self.latency
+    }
+
+    /// 用户与权限
+    pub fn acl(&self) -> &Acl {
+        &self.acl
+    }
+
+    /// 已连接客户端的注册表
+    pub fn clients(&self) -> &Clients {
+        &self.clients
+    }
+
+    /// 客户端限流
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
+
+    /// 写命令审计日志
+    pub fn audit(&self) -> &AuditLog {
+        &self.audit
+    }
+
+    /// 故障注入设置
+    pub fn faults(&self) -> &Faults {
+        &self.faults
+    }
+
+    /// 记录读命令查找键的结果（命中或未命中），原样返回查找结果；类型错误不计入
+    fn lookup<T>(&self, found: Result<Option<T>, DbError>) -> Result<Option<T>, DbError> {
+        if let Ok(found) = &found {
+            self.stats.keyspace_lookup(found.is_some());
+        }
+        found
+    }
+
+    /// 数据库共享的发布/订阅注册表
+    pub fn pubsub(&self) -> &PubSub {
+        &self.pubsub
+    }
+
+    /// 命令注册表，命令按名称分发到其中的处理器
+    pub fn commands(&self) -> &Registry<S> {
+        &self.commands
+    }
+
+    /// 复制所有数据库的全部键值及过期时间（每个数据库内按键排序），
+    /// 用于 AOF 重写、RDB 快照、全量复制等持久化操作
+    ///
+    /// 调用方需要暂停写命令（见 [`Db::pause_writes`]）：快照之后传播的命令流从头开始，
+    /// 第一条命令前总会带上 SELECT。
+    pub(crate) async fn snapshot(&self) -> Snapshot {
+        *self.propagated_db.lock().unwrap() = None;
+        let started = Instant::now();
+
+        let mut snapshot = Vec::with_capacity(self.databases.len());
+        for shards in self.databases.iter() {
+            let guard = shards.read_all().await;
+            let mut entries: Vec<_> = guard
+                .iter()
+                .flat_map(|shard| {
+                    shard
+                        .scan()
+                        .map(|(key, value)| (key.clone(), value.clone(), shard.expire_at(key)))
+                })
+                .collect();
+            entries.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+            snapshot.push(entries);
+        }
+        self.latency.record("snapshot", started.elapsed());
+        snapshot
+    }
+
+    /// 用持久化文件中的数据替换所有数据库，超出数据库数量的记录被忽略
+    pub(crate) async fn restore(&self, snapshot: Snapshot) {
+        self.flush_all(false).await;
+        for (shards, entries) in self.databases.iter().zip(snapshot) {
+            let mut guard = shards.write_all().await;
+            for (key, value, expire_at) in entries {
+                let shard = guard.shard_mut(&key);
+                shard.set(key.clone(), value);
+                if let Some(at) = expire_at {
+                    shard.set_expire_at(&key, at);
+                }
+            }
+        }
+    }
+
+    /// 开启 AOF 持久化，之后成功执行的写命令都会追加到日志中（只能设置一次）
+    pub(crate) fn attach_aof(&self, aof: Aof) {
+        let _ = self.aof.set(aof);
+    }
+
+    /// 已开启的追加日志
+    pub(crate) fn aof(&self) -> Option<&Aof> {
+        self.aof.get()
+    }
+
+    /// RDB 快照状态
+    pub(crate) fn rdb(&self) -> &RdbState {
+        &self.rdb
+    }
 
-        guard.insert(key, value);
+    /// 主从复制状态
+    pub fn replication(&self) -> &Replication {
+        &self.replication
     }
+
+    /// 哨兵状态
+    pub fn sentinel(&self) -> &Sentinel {
+        &self.sentinel
+    }
+
+    /// 写命令执行并传播期间持有的门闩
+    pub(crate) async fn enter_write(&self) -> RwLockReadGuard<'_, ()> {
+        self.write_gate.read().await
+    }
+
+    /// 锁住键的修改与传播顺序，持有到传播完成为止；`keys` 为空时锁住全部键
+    ///
+    /// 需要在写门闩之后、分片锁之前获取（见 `order` 模块）。
+    pub(crate) async fn lock_order<K: AsRef<str>>(&self, keys: &[K]) -> Ordered<'_> {
+        self.order.lock(keys).await
+    }
+
+    /// 暂停写命令，等待正在执行的写命令完成传播
+    pub(crate) async fn pause_writes(&self) -> RwLockWriteGuard<'_, ()> {
+        self.write_gate.write().await
+    }
+
+    /// 将一条写命令传播到追加日志和已连接的副本
+    ///
+    /// 每次传播都会使脏计数加一，用于判断是否触发自动快照。
+    /// 命令所在的数据库与上一条传播的命令不同时，先传播一条 SELECT。
+    pub(crate) fn propagate(&self, args: &[String]) -> io::Result<()> {
+        self.dirty.fetch_add(1, Ordering::SeqCst);
+
+        // 持有锁直到命令写完，保证 SELECT 与命令之间不会插入其他数据库的命令
+        let mut propagated_db = self.propagated_db.lock().unwrap();
+        if *propagated_db != Some(self.index) {
+            self.feed(&["select".to_string(), self.index.to_string()])?;
+            *propagated_db = Some(self.index);
+        }
+        self.feed(args)
+    }
+
+    /// 把一条命令写入复制流和追加日志
+    fn feed(&self, args: &[String]) -> io::Result<()> {
+        self.replication.feed(args);
+        match self.aof.get() {
+            Some(aof) => aof.append(args),
+            None => Ok(()),
+        }
+    }
+
+    /// 自上次成功保存快照以来的写入次数
+    pub fn dirty(&self) -> u64 {
+        self.dirty.load(Ordering::SeqCst)
+    }
+
+    /// 保存成功后扣除快照开始时已计入的写入次数，保存期间的新写入继续保留
+    pub(crate) fn clear_dirty(&self, saved: u64) {
+        self.dirty.fetch_sub(saved, Ordering::SeqCst);
+    }
+}
+
+/// 创建 `count` 个（至少一个）数据库，每个数据库 `shards` 个分片
+fn databases<S: Storage>(count: usize, shards: usize) -> Arc<[Shards<S>]> {
+    (0..count.max(1)).map(|_| Shards::new(shards)).collect()
 }
 
 #[cfg(test)]
@@ -46,7 +874,7 @@ mod tests {
     #[tokio::test]
     async fn test_db_missing_key() {
         let db = Db::new();
-        assert_eq!(db.get("nope").await, None);
+        assert_eq!(db.get("nope").await, Ok(None));
     }
 
     #[tokio::test]
@@ -54,6 +882,125 @@ mod tests {
         let db = Db::new();
 
         db.set("foo".into(), "bar".into()).await;
-        assert_eq!(db.get("foo").await, Some("bar".into()));
+        assert_eq!(db.get("foo").await, Ok(Some("bar".into())));
+    }
+
+    #[tokio::test]
+    async fn test_db_get_wrong_type() {
+        let db = Db::new();
+
+        db.hset("h".into(), vec![("f".into(), "v".into())]).await.unwrap();
+        assert_eq!(db.get("h").await, Err(DbError::WrongType));
+    }
+
+    #[tokio::test]
+    async fn test_dbsize_and_flush() {
+        let db = Db::new();
+        for i in 0..100 {
+            db.set(format!("key:{i}"), "v".into()).await;
+        }
+        assert_eq!(db.dbsize().await, 100);
+
+        db.flush(false).await;
+        assert_eq!(db.dbsize().await, 0);
+
+        db.set("a".into(), "1".into()).await;
+        db.flush(true).await;
+        assert_eq!(db.dbsize().await, 0);
+        assert_eq!(db.used_memory().await, 0);
+        db.set("b".into(), "2".into()).await;
+        assert_eq!(db.keys("*").await, vec!["b"]);
+    }
+
+    #[tokio::test]
+    async fn test_db_keys() {
+        let db = Db::new();
+
+        db.set("user:2".into(), "b".into()).await;
+        db.set("user:1".into(), "a".into()).await;
+        db.set("order:1".into(), "c".into()).await;
+        assert_eq!(db.keys("user:*").await, vec!["user:1", "user:2"]);
+        assert_eq!(db.keys("*:1").await, vec!["order:1", "user:1"]);
+        assert!(db.keys("none*").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_randomkey() {
+        let db = Db::with_seed(7);
+        assert_eq!(db.randomkey().await, None);
+
+        for key in ["a", "b", "c"] {
+            db.set(key.into(), "v".into()).await;
+        }
+        let mut seen: Vec<_> = Vec::new();
+        for _ in 0..100 {
+            let key = db.randomkey().await.unwrap();
+            if !seen.contains(&key) {
+                seen.push(key);
+            }
+        }
+        seen.sort();
+        assert_eq!(seen, ["a", "b", "c"]);
+
+        // 跳过已过期但尚未清理的键
+        db.flush(false).await;
+        db.set("stale".into(), "v".into()).await;
+        db.set("live".into(), "v".into()).await;
+        db.expire_at("stale", unix_time_ms() + 10).await;
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        for _ in 0..20 {
+            assert_eq!(db.randomkey().await.as_deref(), Some("live"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan() {
+        let db = Db::new();
+        for i in 0..500 {
+            db.set(format!("key:{i}"), "v".into()).await;
+        }
+        crate::handler::process_command(&db, "sadd set:1 a").await;
+
+        // 遍历期间写入 1000 个新键，各分片的表多次扩容，原有的键仍然全部返回
+        let (mut cursor, mut seen, mut i) = (0, HashSet::new(), 0);
+        loop {
+            let (next, keys) = db.scan(cursor, 10, Some("key:*"), None).await;
+            assert!(keys.iter().all(|key| key.starts_with("key:")));
+            seen.extend(keys);
+            let end = (i + 20).min(1000);
+            for j in i..end {
+                db.set(format!("new:{j}"), "v".into()).await;
+            }
+            i = end;
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert!((0..500).all(|i| seen.contains(&format!("key:{i}"))));
+
+        let mut cursor = 0;
+        let mut sets = Vec::new();
+        loop {
+            let (next, keys) = db.scan(cursor, 1000, None, Some("set")).await;
+            sets.extend(keys);
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert_eq!(sets, ["set:1"]);
+        assert_eq!(Db::new().scan(0, 10, None, None).await, (0, Vec::new()));
+    }
+
+    #[tokio::test]
+    async fn test_touch() {
+        let db = Db::new();
+        db.set("a".into(), "1".into()).await;
+        db.set("b".into(), "2".into()).await;
+
+        let keys = ["a", "missing", "b", "a"].map(String::from);
+        assert_eq!(db.touch(&keys).await, 3);
+        assert_eq!(db.touch(&["missing".to_string()]).await, 0);
     }
 }
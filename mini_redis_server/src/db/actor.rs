@@ -0,0 +1,286 @@
+//! 基于消息传递的数据库实现（actor 模型）
+//!
+//! 与加锁的 [`Db`](super::Db) 相对照：存储后端（[`Storage`]）由一个专门的任务独占，
+//! 其他任务通过 mpsc 通道发送请求，并在请求附带的 oneshot 通道上等待回复。
+//! 所有请求按到达顺序串行执行，因此不需要任何锁。
+//!
+//! 请求是在后台任务上执行的闭包（见 [`ActorDb::call`]），可以使用 [`Storage`] 的全部方法；
+//! 常用的操作另有对应的异步方法。后台任务按 [`DEFAULT_HZ`] 主动删除已过期的键，
+//! 与 [`Db`](super::Db) 的主动过期相同。
+//!
+//! 命令处理（`handler`）、会话与服务器运行在 [`Db`](super::Db) 上，[`ActorDb`] 用于在同一个
+//! 存储后端上对比两种并发设计（见 `benches/bench_db_concurrency.rs`），需要开启 `actor-db` feature。
+//! 所有 [`ActorDb`] 句柄被丢弃后，后台任务随之退出。
+
+use std::time::Duration;
+
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::{self, MissedTickBehavior},
+};
+
+use super::{
+    DbError, Keyspace, Storage, Value, expire::ACTIVE_EXPIRE_KEYS_PER_SHARD, unix_time_ms,
+};
+use crate::{config::DEFAULT_HZ, glob};
+
+/// 请求通道的容量，通道满时发送方等待，形成背压
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// 发送给后台任务的请求：在独占的存储后端上执行，并自行发送回复
+type Request<S> = Box<dyn FnOnce(&mut S) + Send>;
+
+/// actor 数据库的句柄，可廉价克隆
+pub struct ActorDb<S: Storage = Keyspace> {
+    sender: mpsc::Sender<Request<S>>,
+}
+
+// 手动实现 Clone：派生会额外要求后端 `S` 实现 Clone
+impl<S: Storage> Clone for ActorDb<S> {
+    fn clone(&self) -> Self {
+        Self { sender: self.sender.clone() }
+    }
+}
+
+impl ActorDb {
+    /// 以内存后端启动独占键空间的后台任务，必须在 tokio 运行时中调用
+    pub fn spawn() -> Self {
+        Self::with_storage(Keyspace::default())
+    }
+}
+
+impl<S: Storage> ActorDb<S> {
+    /// 以给定的存储后端启动后台任务，例如 `ActorDb::with_storage(MyStorage::default())`
+    pub fn with_storage(storage: S) -> Self {
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        tokio::spawn(run(storage, receiver));
+        Self { sender }
+    }
+
+    /// 在后台任务上以独占方式访问存储后端，返回 `f` 的结果
+    ///
+    /// `f` 执行期间其他请求都在排队，应当尽快返回；`f` panic 会使后台任务退出。
+    pub async fn call<R, F>(&self, f: F) -> R
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut S) -> R + Send + 'static,
+    {
+        let (reply, response) = oneshot::channel();
+        let request: Request<S> = Box::new(move |storage| {
+            // 请求方可能已经放弃等待，回复失败可以忽略
+            let _ = reply.send(f(storage));
+        });
+        // 后台任务只会在所有句柄都被丢弃后退出，发送与接收失败意味着它已崩溃
+        self.sender.send(request).await.expect("db actor stopped");
+        response.await.expect("db actor stopped")
+    }
+
+    /// 读取字符串键的值
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, DbError> {
+        let key = key.to_string();
+        self.call(move |storage| match storage.get(&key) {
+            Some(Value::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err(DbError::WrongType),
+            None => Ok(None),
+        })
+        .await
+    }
+
+    /// 写入字符串键的值，清除原有的过期时间
+    pub async fn set(&self, key: String, value: String) {
+        self.call(move |storage| {
+            storage.set(key, Value::String(value.into_bytes()));
+        })
+        .await
+    }
+
+    /// 读取任意类型的值
+    pub async fn get_value(&self, key: &str) -> Option<Value> {
+        let key = key.to_string();
+        self.call(move |storage| storage.get(&key).cloned()).await
+    }
+
+    /// 写入任意类型的值并清除原有的过期时间，返回旧值
+    pub async fn set_value(&self, key: String, value: Value) -> Option<Value> {
+        self.call(move |storage| storage.set(key, value)).await
+    }
+
+    /// 删除键，返回实际删除的数量
+    pub async fn del(&self, keys: &[String]) -> usize {
+        let keys = keys.to_vec();
+        self.call(move |storage| {
+            let removed = keys.iter().filter(|key| {
+                let live = storage.contains_key(key);
+                storage.remove(key);
+                live
+            });
+            removed.count()
+        })
+        .await
+    }
+
+    /// 键是否存在
+    pub async fn exists(&self, key: &str) -> bool {
+        let key = key.to_string();
+        self.call(move |storage| storage.contains_key(&key)).await
+    }
+
+    /// 返回所有匹配 glob 模式的键（按字典序排列）
+    pub async fn keys(&self, pattern: &str) -> Vec<String> {
+        let pattern = pattern.to_string();
+        self.call(move |storage| {
+            let mut keys: Vec<_> = storage
+                .keys()
+                .filter(|key| glob::matches(&pattern, key) && storage.contains_key(key))
+                .cloned()
+                .collect();
+            keys.sort();
+            keys
+        })
+        .await
+    }
+
+    /// 键的过期时间（Unix 毫秒），键不存在或没有设置过期时间时返回 `None`
+    pub async fn expire_at(&self, key: &str) -> Option<u64> {
+        let key = key.to_string();
+        self.call(move |storage| storage.expire_at(&key)).await
+    }
+
+    /// 设置键的过期时间，键不存在时返回 `false`；时间已过去时直接删除键
+    pub async fn set_expire_at(&self, key: &str, at: u64) -> bool {
+        let key = key.to_string();
+        self.call(move |storage| storage.set_expire_at(&key, at)).await
+    }
+
+    /// 清除键的过期时间，键不存在或没有设置过期时间时返回 `false`
+    pub async fn persist(&self, key: &str) -> bool {
+        let key = key.to_string();
+        self.call(move |storage| storage.persist(&key)).await
+    }
+
+    /// 键的数量，可能包含已过期但尚未清理的键
+    pub async fn len(&self) -> usize {
+        self.call(|storage| storage.len()).await
+    }
+
+    /// 是否没有任何键
+    pub async fn is_empty(&self) -> bool {
+        self.call(|storage| storage.is_empty()).await
+    }
+
+    /// 清空所有键
+    pub async fn clear(&self) {
+        self.call(|storage| storage.clear()).await
+    }
+
+    /// 估算的内存占用（字节），不统计内存的后端返回 0
+    pub async fn used_memory(&self) -> usize {
+        self.call(|storage| storage.used_memory()).await
+    }
+}
+
+/// 后台任务：逐个处理请求并周期性地删除已过期的键，直到所有句柄都被丢弃
+async fn run<S: Storage>(mut storage: S, mut receiver: mpsc::Receiver<Request<S>>) {
+    let mut expire = time::interval(Duration::from_millis(1000 / DEFAULT_HZ));
+    expire.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            request = receiver.recv() => match request {
+                Some(request) => request(&mut storage),
+                None => break,
+            },
+            _ = expire.tick() => {
+                storage.pop_expired(unix_time_ms(), ACTIVE_EXPIRE_KEYS_PER_SHARD);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_actor_db() {
+        let db = ActorDb::spawn();
+
+        assert_eq!(db.get("a").await, Ok(None));
+        db.set("a".into(), "1".into()).await;
+        db.set("b".into(), "2".into()).await;
+        assert_eq!(db.get("a").await, Ok(Some("1".into())));
+        assert_eq!(db.keys("*").await, vec!["a", "b"]);
+
+        assert_eq!(db.del(&["a".into(), "missing".into()]).await, 1);
+        assert_eq!(db.keys("*").await, vec!["b"]);
+
+        let set = Value::Set(HashSet::from(["x".to_string()]));
+        assert_eq!(db.set_value("s".into(), set.clone()).await, None);
+        assert_eq!(db.get_value("s").await, Some(set));
+        assert_eq!(db.get("s").await, Err(DbError::WrongType));
+        assert_eq!(db.len().await, 2);
+
+        db.clear().await;
+        assert!(db.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_expire() {
+        let db = ActorDb::spawn();
+        db.set("a".into(), "1".into()).await;
+        db.set("b".into(), "2".into()).await;
+
+        let at = unix_time_ms() + 60_000;
+        assert!(db.set_expire_at("a", at).await);
+        assert!(!db.set_expire_at("missing", at).await);
+        assert_eq!(db.expire_at("a").await, Some(at));
+        assert!(db.persist("a").await);
+        assert_eq!(db.expire_at("a").await, None);
+
+        // 已过期的键立即不可见，随后被后台任务主动删除
+        assert!(db.set_expire_at("b", unix_time_ms() + 20).await);
+        time::sleep(Duration::from_millis(50)).await;
+        assert!(!db.exists("b").await);
+        time::sleep(Duration::from_millis(1000 / DEFAULT_HZ * 2)).await;
+        assert_eq!(db.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_call() {
+        let db = ActorDb::spawn();
+        db.set("a".into(), "1".into()).await;
+
+        // 闭包在后台任务上独占存储后端，可以组合多个操作而不被其他请求打断
+        let renamed = db
+            .call(|storage| {
+                let value = storage.remove("a")?;
+                storage.set("b".into(), value);
+                Some(storage.used_memory())
+            })
+            .await;
+        assert!(renamed.is_some());
+        assert_eq!(db.keys("*").await, vec!["b"]);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_clients() {
+        let db = ActorDb::spawn();
+
+        let tasks: Vec<_> = (0..8)
+            .map(|t| {
+                let db = db.clone();
+                tokio::spawn(async move {
+                    for i in 0..100 {
+                        db.set(format!("{t}:{i}"), i.to_string()).await;
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(db.keys("*").await.len(), 800);
+    }
+}
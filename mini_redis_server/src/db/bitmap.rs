@@ -0,0 +1,158 @@
+//! 位图操作
+//!
+//! 位图保存在字符串值（[`Value::String`]）中，位运算的实现见 `bitmap` 模块。
+//! SETBIT 按需延长字符串并保留原有的过期时间；BITOP 像 SET 一样覆盖目标键。
+
+use super::{Db, DbError, Storage, Value};
+use crate::bitmap::{self, BitOp, BitRange};
+
+/// 取出键对应的字符串，键不存在时创建一个空字符串
+fn string_mut<S: Storage>(map: &mut S, key: String) -> Result<&mut Vec<u8>, DbError> {
+    match map.get_or_insert_with(key, || Value::String(Vec::new())) {
+        Value::String(value) => Ok(value),
+        _ => Err(DbError::WrongType),
+    }
+}
+
+/// 以只读方式取出键对应的字符串，键不存在时返回 `None`
+fn string_ref<'a, S: Storage>(map: &'a S, key: &str) -> Result<Option<&'a [u8]>, DbError> {
+    match map.get(key) {
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(DbError::WrongType),
+        None => Ok(None),
+    }
+}
+
+impl<S: Storage> Db<S> {
+    /// 设置第 `offset` 位，返回原来的值
+    pub async fn setbit(&self, key: String, offset: usize, value: bool) -> Result<bool, DbError> {
+        let mut guard = self.shards().write(&key).await;
+
+        Ok(bitmap::set_bit(string_mut(&mut *guard, key)?, offset, value))
+    }
+
+    /// 读取第 `offset` 位，键不存在时为 0
+    pub async fn getbit(&self, key: &str, offset: usize) -> Result<bool, DbError> {
+        let guard = self.shards().read(key).await;
+
+        let value = self.lookup(string_ref(&*guard, key))?;
+        Ok(value.is_some_and(|value| bitmap::get_bit(value, offset)))
+    }
+
+    /// 统计被设置的位数，语义见 [`bitmap::count`]
+    pub async fn bitcount(&self, key: &str, range: Option<&BitRange>) -> Result<usize, DbError> {
+        let guard = self.shards().read(key).await;
+
+        let value = self.lookup(string_ref(&*guard, key))?;
+        Ok(value.map_or(0, |value| bitmap::count(value, range)))
+    }
+
+    /// 查找第一个值为 `bit` 的位，语义见 [`bitmap::position`]
+    ///
+    /// 键不存在时看作全为 0 的无限长字符串：查找 0 返回 0，查找 1 返回 `None`。
+    pub async fn bitpos(
+        &self,
+        key: &str,
+        bit: bool,
+        range: Option<&BitRange>,
+    ) -> Result<Option<usize>, DbError> {
+        let guard = self.shards().read(key).await;
+
+        match self.lookup(string_ref(&*guard, key))? {
+            Some(value) => Ok(bitmap::position(value, bit, range)),
+            None => Ok((!bit).then_some(0)),
+        }
+    }
+
+    /// 对多个键执行位运算并把结果写入 `dest`，返回结果的长度；结果为空时删除 `dest`
+    ///
+    /// 不存在的键看作空字符串。
+    pub async fn bitop(&self, op: BitOp, dest: String, keys: &[String]) -> Result<usize, DbError> {
+        let locked_keys: Vec<_> = keys.iter().chain([&dest]).collect();
+        let mut guard = self.shards().write_many(&locked_keys).await;
+        let sources = keys
+            .iter()
+            .map(|key| Ok(string_ref(guard.shard(key), key)?.unwrap_or_default()))
+            .collect::<Result<Vec<_>, DbError>>()?;
+        let result = bitmap::op(op, &sources);
+        let len = result.len();
+
+        let shard = guard.shard_mut(&dest);
+        if result.is_empty() {
+            shard.remove(&dest);
+        } else {
+            shard.set(dest, Value::String(result));
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bitmap::{BitOp, BitRange, BitUnit},
+        db::{Db, DbError},
+    };
+
+    #[tokio::test]
+    async fn test_setbit_getbit() {
+        let db = Db::new();
+
+        assert_eq!(db.setbit("b".into(), 7, true).await, Ok(false));
+        assert_eq!(db.setbit("b".into(), 7, true).await, Ok(true));
+        assert_eq!(db.get("b").await, Ok(Some(vec![0x01])));
+        assert_eq!(db.getbit("b", 7).await, Ok(true));
+        assert_eq!(db.getbit("b", 100).await, Ok(false));
+        assert_eq!(db.getbit("missing", 0).await, Ok(false));
+
+        // 延长字符串时保留过期时间
+        db.expire_at("b", u64::MAX).await;
+        db.setbit("b".into(), 100, true).await.unwrap();
+        assert_eq!(db.get("b").await.unwrap().unwrap().len(), 13);
+        assert_eq!(db.expire_time("b").await, Some(Some(u64::MAX)));
+
+        db.sadd("s".into(), vec!["m".into()]).await.unwrap();
+        assert_eq!(db.setbit("s".into(), 0, true).await, Err(DbError::WrongType));
+        assert_eq!(db.getbit("s", 0).await, Err(DbError::WrongType));
+    }
+
+    #[tokio::test]
+    async fn test_bitcount_bitpos() {
+        let db = Db::new();
+        db.set("s".into(), "foobar".into()).await;
+
+        assert_eq!(db.bitcount("s", None).await, Ok(26));
+        let range = BitRange { start: 1, end: Some(1), unit: BitUnit::Byte };
+        assert_eq!(db.bitcount("s", Some(&range)).await, Ok(6));
+        assert_eq!(db.bitcount("missing", None).await, Ok(0));
+
+        assert_eq!(db.bitpos("s", true, None).await, Ok(Some(1)));
+        assert_eq!(db.bitpos("missing", false, None).await, Ok(Some(0)));
+        assert_eq!(db.bitpos("missing", true, None).await, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn test_bitop() {
+        let db = Db::new();
+        db.set("a".into(), "abc".into()).await;
+        db.set("b".into(), "a".into()).await;
+
+        let keys = ["a".to_string(), "b".to_string(), "missing".to_string()];
+        assert_eq!(db.bitop(BitOp::Or, "dest".into(), &keys[..2]).await, Ok(3));
+        assert_eq!(db.get("dest").await, Ok(Some(b"abc".to_vec())));
+        assert_eq!(db.bitop(BitOp::And, "dest".into(), &keys).await, Ok(3));
+        assert_eq!(db.get("dest").await, Ok(Some(vec![0, 0, 0])));
+        assert_eq!(db.bitop(BitOp::Not, "dest".into(), &keys[1..2]).await, Ok(1));
+        assert_eq!(db.get("dest").await, Ok(Some(vec![!b'a'])));
+
+        // 结果为空时删除目标键
+        assert_eq!(db.bitop(BitOp::Xor, "dest".into(), &keys[2..]).await, Ok(0));
+        assert_eq!(db.get("dest").await, Ok(None));
+
+        db.sadd("s".into(), vec!["m".into()]).await.unwrap();
+        assert_eq!(
+            db.bitop(BitOp::Or, "dest".into(), &["s".into()]).await,
+            Err(DbError::WrongType)
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! 单个键的序列化与恢复
+//!
+//! 载荷格式见 `persistence::dump` 模块，过期时间不在载荷中，由调用方单独传递。
+
+use super::{Db, DbError, Storage, TtlPolicy};
+use crate::persistence::{deserialize_value, serialize_value};
+
+impl<S: Storage> Db<S> {
+    /// 序列化键的值，返回载荷与过期时间（Unix 毫秒）；键不存在时返回 `None`
+    pub async fn dump(&self, key: &str) -> Option<(String, Option<u64>)> {
+        let guard = self.shards().read(key).await;
+
+        self.stats.keyspace_lookup(guard.contains_key(key));
+        let value = guard.get(key)?;
+        Some((serialize_value(value), guard.expire_at(key)))
+    }
+
+    /// 用载荷恢复键的值，`expire_at` 为过期时间（Unix 毫秒）
+    ///
+    /// 键已存在且 `replace` 为 `false` 时返回 `BusyKey` 错误；过期时间已过去时不会留下键。
+    pub async fn restore_key(
+        &self,
+        key: String,
+        payload: &str,
+        expire_at: Option<u64>,
+        replace: bool,
+    ) -> Result<(), DbError> {
+        let value = deserialize_value(payload).map_err(|_| DbError::BadPayload)?;
+        let mut guard = self.shards().write(&key).await;
+        if !replace && guard.contains_key(&key) {
+            return Err(DbError::BusyKey);
+        }
+
+        let policy = expire_at.map_or(TtlPolicy::Clear, TtlPolicy::ExpireAt);
+        policy.set(&mut *guard, key.clone(), value);
+        self.notifier.notify(&key);
+        Ok(())
+    }
+
+    /// 删除自 [`Db::dump`] 以来值和过期时间都没有变化的键，返回实际删除的键
+    ///
+    /// MIGRATE 在与目标节点通信期间不持有锁，期间被改写的键不能删除，否则这次写入会丢失。
+    pub(crate) async fn del_unchanged(
+        &self,
+        dumped: &[(String, String, Option<u64>)],
+    ) -> Vec<String> {
+        let keys: Vec<_> = dumped.iter().map(|(key, ..)| key.clone()).collect();
+        let mut guard = self.shards().write_many(&keys).await;
+
+        let mut removed = Vec::new();
+        for (key, payload, expire_at) in dumped {
+            let shard = guard.shard_mut(key);
+            let unchanged = shard.get(key).is_some_and(|value| serialize_value(value) == *payload)
+                && shard.expire_at(key) == *expire_at;
+            if unchanged {
+                shard.remove(key);
+                removed.push(key.clone());
+            }
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{Db, DbError, unix_time_ms};
+
+    #[tokio::test]
+    async fn test_del_unchanged() {
+        let db = Db::new();
+        let mut dumped = Vec::new();
+        for key in ["a", "b", "c"] {
+            db.set(key.into(), "1".into()).await;
+            let (payload, at) = db.dump(key).await.unwrap();
+            dumped.push((key.to_string(), payload, at));
+        }
+
+        // dump 之后被改写或设置了过期时间的键不会被删除
+        db.set("b".into(), "2".into()).await;
+        db.expire_at("c", unix_time_ms() + 60_000).await;
+
+        assert_eq!(db.del_unchanged(&dumped).await, vec!["a"]);
+        assert_eq!(db.keys("*").await, vec!["b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_dump_and_restore() {
+        let db = Db::new();
+        db.sadd("s".into(), vec!["a".into(), "b".into()]).await.unwrap();
+        let at = unix_time_ms() + 60_000;
+        db.expire_at("s", at).await;
+
+        let (payload, expire_at) = db.dump("s").await.unwrap();
+        assert_eq!(expire_at, Some(at));
+        assert_eq!(db.dump("missing").await, None);
+
+        let target = Db::new();
+        target.restore_key("t".into(), &payload, expire_at, false).await.unwrap();
+        assert_eq!(target.smembers("t").await.unwrap(), vec!["a", "b"]);
+        assert_eq!(target.expire_time("t").await, Some(Some(at)));
+
+        let busy = target.restore_key("t".into(), &payload, None, false).await;
+        assert_eq!(busy, Err(DbError::BusyKey));
+        target.restore_key("t".into(), &payload, None, true).await.unwrap();
+        assert_eq!(target.expire_time("t").await, Some(None));
+
+        let bad = target.restore_key("u".into(), "00", None, false).await;
+        assert_eq!(bad, Err(DbError::BadPayload));
+        assert_eq!(target.dump("u").await, None);
+    }
+}
@@ -0,0 +1,366 @@
+//! 过期时间操作
+//!
+//! 过期时间以 Unix 毫秒时间戳保存（见 `keyspace` 模块），
+//! 相对时间（EXPIRE / SET EX 等）由调用方换算为绝对时间后传入。
+//!
+//! EXPIRE 系列命令的 NX / XX / GT / LT 条件见 [`ExpireFlags`]：
+//! 比较时没有过期时间的键视为永不过期，因此 GT 对它总是失败，LT 总是成功。
+//!
+//! 写入值时对原有过期时间的处理与 Redis 一致，由 [`TtlPolicy`] 显式给出：
+//! - 用新值整体替换键的命令清除过期时间：SET、GETSET，以及 BITOP、SINTERSTORE 等写入的目标键
+//! - SET KEEPTTL 保留，SET EX / PX / EXAT / PXAT 与 RESTORE 设置新的过期时间，MOVE 随键一起移动
+//! - 原地修改值的命令（INCR、SETBIT、HSET、SADD、ZADD、XADD 等）保留过期时间
+//!
+//! 已过期的键除了在访问时惰性删除，还由服务器的周期任务调用 [`Db::active_expire_cycle`]
+//! 主动删除：后端按到期时间分桶保存过期时间，每轮只取出到期的键，开销与过期键的总数无关。
+
+use std::{io, time::Instant};
+
+use super::{Db, DbError, KeyEvent, Storage, Value, unix_time_ms};
+
+/// 主动过期每轮在每个分片最多删除的键数，避免一轮占用写锁太久
+pub(super) const ACTIVE_EXPIRE_KEYS_PER_SHARD: usize = 200;
+
+/// EXPIRE 系列命令的条件选项，全部为 `false` 时无条件设置
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ExpireFlags {
+    /// 只在键没有过期时间时设置
+    pub nx: bool,
+    /// 只在键已有过期时间时设置
+    pub xx: bool,
+    /// 只在新的过期时间晚于原有的过期时间时设置
+    pub gt: bool,
+    /// 只在新的过期时间早于原有的过期时间时设置
+    pub lt: bool,
+}
+
+/// 写入值时如何处理键原有的过期时间
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TtlPolicy {
+    /// 清除原有的过期时间
+    Clear,
+    /// 保留原有的过期时间，键不存在（或已过期）时不设置
+    Keep,
+    /// 设置为给定的时间（Unix 毫秒）
+    ExpireAt(u64),
+}
+
+impl TtlPolicy {
+    /// 在已加写锁的存储中写入值并按策略设置过期时间，返回旧值
+    pub(crate) fn set<S: Storage>(
+        self,
+        storage: &mut S,
+        key: String,
+        value: Value,
+    ) -> Option<Value> {
+        let at = match self {
+            TtlPolicy::Clear => None,
+            TtlPolicy::Keep => {
+                storage.contains_key(&key).then(|| storage.expire_at(&key)).flatten()
+            }
+            TtlPolicy::ExpireAt(at) => Some(at),
+        };
+        let old = storage.set(key.clone(), value);
+        if let Some(at) = at {
+            storage.set_expire_at(&key, at);
+        }
+        old
+    }
+}
+
+impl ExpireFlags {
+    /// 原有过期时间为 `current` 的键能否设置为 `at`
+    fn allows(self, current: Option<u64>, at: u64) -> bool {
+        match current {
+            None => !self.xx && !self.gt,
+            Some(current) => !self.nx && (!self.gt || at > current) && (!self.lt || at < current),
+        }
+    }
+}
+
+impl<S: Storage> Db<S> {
+    /// 写入字符串值并设置过期时间（Unix 毫秒）
+    pub async fn set_with_expire(&self, key: String, value: String, at: u64) {
+        self.set_with_policy(key, value, TtlPolicy::ExpireAt(at)).await;
+    }
+
+    /// 写入字符串值（不论原来的类型），按 `policy` 处理过期时间
+    pub async fn set_with_policy(&self, key: String, value: String, policy: TtlPolicy) {
+        let mut guard = self.shards().write(&key).await;
+
+        policy.set(&mut *guard, key, Value::String(value.into_bytes()));
+    }
+
+    /// 写入字符串值并返回旧值（GETSET、SET GET），按 `policy` 处理过期时间；
+    /// 原来的值不是字符串时返回 `WrongType` 错误，不写入
+    pub async fn getset(
+        &self,
+        key: String,
+        value: String,
+        policy: TtlPolicy,
+    ) -> Result<Option<Vec<u8>>, DbError> {
+        let mut guard = self.shards().write(&key).await;
+
+        let old = match guard.get(&key) {
+            Some(Value::String(old)) => Some(old.clone()),
+            Some(_) => return Err(DbError::WrongType),
+            None => None,
+        };
+        policy.set(&mut *guard, key, Value::String(value.into_bytes()));
+        Ok(old)
+    }
+
+    /// 设置键的过期时间（Unix 毫秒），键不存在时返回 `false`；时间已过去时直接删除键
+    pub async fn expire_at(&self, key: &str, at: u64) -> bool {
+        self.expire_at_if(key, at, ExpireFlags::default()).await
+    }
+
+    /// 在满足 `flags` 条件时设置键的过期时间，返回是否设置成功
+    pub async fn expire_at_if(&self, key: &str, at: u64, flags: ExpireFlags) -> bool {
+        let mut guard = self.shards().write(key).await;
+
+        if !guard.contains_key(key) || !flags.allows(guard.expire_at(key), at) {
+            return false;
+        }
+        guard.set_expire_at(key, at)
+    }
+
+    /// 清除键的过期时间（PERSIST），键不存在或没有过期时间时返回 `false`
+    pub async fn persist(&self, key: &str) -> bool {
+        let mut guard = self.shards().write(key).await;
+
+        guard.persist(key)
+    }
+
+    /// 主动过期：删除所有数据库中已到期的键，返回删除的键数
+    ///
+    /// 每个删除的键传播一条 DEL，使追加日志与副本保持一致；副本不主动删除，等待主节点传播的 DEL。
+    /// 释放写门闩后通知键变更的观察者（见 `hooks` 模块）。
+    pub async fn active_expire_cycle(&self) -> io::Result<usize> {
+        if self.replication().is_replica() {
+            return Ok(0);
+        }
+        let result = self.expire_keys().await;
+        self.hooks.dispatch();
+        result
+    }
+
+    async fn expire_keys(&self) -> io::Result<usize> {
+        let _write = self.enter_write().await;
+        let started = Instant::now();
+        let now = unix_time_ms();
+
+        let mut expired = 0;
+        for (index, shards) in self.databases.iter().enumerate() {
+            for i in 0..shards.len() {
+                // 持有顺序锁直到 DEL 传播完成，不会与同一分片上的写命令交错
+                let _order = self.order.lock_index(i).await;
+                let keys =
+                    shards.write_index(i).await.pop_expired(now, ACTIVE_EXPIRE_KEYS_PER_SHARD);
+                expired += keys.len();
+                for (key, value) in keys {
+                    self.free_value(value);
+                    self.hooks.push(KeyEvent::Expire(index, key.clone()));
+                    self.view(index).propagate(&["DEL".to_string(), key])?;
+                }
+            }
+        }
+        if expired > 0 {
+            self.stats.keys_expired(expired as u64);
+            self.latency.record("expire-cycle", started.elapsed());
+        }
+        Ok(expired)
+    }
+
+    /// 查询键的过期时间：键不存在返回 `None`，未设置过期时间返回 `Some(None)`
+    pub async fn expire_time(&self, key: &str) -> Option<Option<u64>> {
+        let guard = self.shards().read(key).await;
+
+        guard.contains_key(key).then(|| guard.expire_at(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExpireFlags, TtlPolicy};
+    use crate::{
+        db::{Db, DbError, unix_time_ms},
+        handler::process_command,
+    };
+
+    #[tokio::test]
+    async fn test_expire() {
+        let db = Db::new();
+        let future = unix_time_ms() + 60_000;
+
+        assert!(!db.expire_at("a", future).await);
+        assert_eq!(db.expire_time("a").await, None);
+
+        db.set("a".into(), "1".into()).await;
+        assert_eq!(db.expire_time("a").await, Some(None));
+        assert!(db.expire_at("a", future).await);
+        assert_eq!(db.expire_time("a").await, Some(Some(future)));
+
+        // SET 清除过期时间
+        db.set("a".into(), "2".into()).await;
+        assert_eq!(db.expire_time("a").await, Some(None));
+    }
+
+    #[tokio::test]
+    async fn test_expire_flags() {
+        let db = Db::new();
+        let (soon, later) = (unix_time_ms() + 60_000, unix_time_ms() + 120_000);
+        let nx = ExpireFlags { nx: true, ..Default::default() };
+        let xx = ExpireFlags { xx: true, ..Default::default() };
+        let gt = ExpireFlags { gt: true, ..Default::default() };
+        let lt = ExpireFlags { lt: true, ..Default::default() };
+        db.set("a".into(), "1".into()).await;
+
+        // 没有过期时间的键视为永不过期
+        assert!(!db.expire_at_if("a", soon, xx).await);
+        assert!(!db.expire_at_if("a", soon, gt).await);
+        assert!(db.expire_at_if("a", later, lt).await);
+        assert!(!db.expire_at_if("a", soon, nx).await);
+        assert!(!db.expire_at_if("a", later, lt).await);
+        assert!(db.expire_at_if("a", soon, lt).await);
+        assert!(!db.expire_at_if("a", soon, gt).await);
+        assert!(
+            db.expire_at_if("a", later, ExpireFlags { xx: true, gt: true, ..Default::default() })
+                .await
+        );
+        assert_eq!(db.expire_time("a").await, Some(Some(later)));
+        assert!(!db.expire_at_if("missing", soon, lt).await);
+
+        assert!(db.persist("a").await);
+        assert!(!db.persist("a").await);
+        assert_eq!(db.expire_time("a").await, Some(None));
+        assert!(db.expire_at_if("a", soon, nx).await);
+    }
+
+    #[tokio::test]
+    async fn test_set_with_policy() {
+        let db = Db::new();
+        let at = unix_time_ms() + 60_000;
+
+        db.set_with_expire("a".into(), "1".into(), at).await;
+        db.set_with_policy("a".into(), "2".into(), TtlPolicy::Keep).await;
+        assert_eq!(db.expire_time("a").await, Some(Some(at)));
+        assert_eq!(db.getset("a".into(), "3".into(), TtlPolicy::Keep).await, Ok(Some("2".into())));
+        assert_eq!(db.expire_time("a").await, Some(Some(at)));
+        assert_eq!(db.getset("a".into(), "4".into(), TtlPolicy::Clear).await, Ok(Some("3".into())));
+        assert_eq!(db.expire_time("a").await, Some(None));
+
+        // 键不存在时 KEEPTTL 不设置过期时间，已过期的键视为不存在
+        db.set_with_policy("b".into(), "1".into(), TtlPolicy::Keep).await;
+        assert_eq!(db.expire_time("b").await, Some(None));
+        db.set_with_expire("c".into(), "1".into(), unix_time_ms() + 10).await;
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert_eq!(db.getset("c".into(), "2".into(), TtlPolicy::Keep).await, Ok(None));
+        assert_eq!(db.expire_time("c").await, Some(None));
+
+        // 类型不是字符串时 GETSET 报错且不写入，SET 则直接覆盖
+        process_command(&db, "sadd s x").await;
+        let wrong = db.getset("s".into(), "v".into(), TtlPolicy::Clear).await;
+        assert_eq!(wrong, Err(DbError::WrongType));
+        assert_eq!(db.get("s").await, Err(DbError::WrongType));
+        db.set_with_policy("s".into(), "v".into(), TtlPolicy::Keep).await;
+        assert_eq!(db.get("s").await, Ok(Some("v".into())));
+    }
+
+    /// 每个写命令对原有过期时间的处理：整体替换值的命令清除，原地修改的命令保留
+    #[tokio::test]
+    async fn test_write_commands_ttl() {
+        let cases = [
+            ("set k v", "set k w", false),
+            ("set k v", "set k w keepttl", true),
+            ("set k v", "set k w get", false),
+            ("set k v", "set k w get keepttl", true),
+            ("set k v", "getset k w", false),
+            ("set k 1", "incr k", true),
+            ("set k 1", "incrby k 5", true),
+            ("set k v", "setbit k 1 1", true),
+            ("set k v", "bitop not k k", false),
+            ("hset k f v", "hset k g v", true),
+            ("hset k f 1", "hincrby k f 1", true),
+            ("hset k f 1", "hincrbyfloat k f 0.5", true),
+            ("sadd k a b", "sadd k c", true),
+            ("sadd k a b", "srem k a", true),
+            ("sadd k a b", "spop k", true),
+            ("sadd k a", "sinterstore k k", false),
+            ("sadd k a", "sunionstore k k", false),
+            ("sadd k a", "sdiffstore k k missing", false),
+            ("zadd k 1 a 2 b", "zadd k 3 c", true),
+            ("zadd k 1 a 2 b", "zincrby k 1 a", true),
+            ("zadd k 1 a 2 b", "zpopmin k", true),
+            ("zadd k 1 a 2 b", "zpopmax k", true),
+            ("zadd k 1 a 2 b", "bzpopmin k 0", true),
+            ("geoadd k 13.36 38.11 a", "geoadd k 15.08 37.50 b", true),
+            ("xadd k * f v", "xadd k * g v", true),
+            ("xadd k * f v", "xgroup create k g 0", true),
+        ];
+        for (setup, write, kept) in cases {
+            let db = Db::new();
+            process_command(&db, setup).await;
+            process_command(&db, "pexpire k 100000").await;
+            let before = db.expire_time("k").await.flatten();
+            assert!(before.is_some(), "{setup}");
+
+            let reply = process_command(&db, write).await;
+            assert!(
+                !reply.starts_with("ERR") && !reply.starts_with("WRONGTYPE"),
+                "{write}: {reply}"
+            );
+            let expected = if kept { before } else { None };
+            assert_eq!(db.expire_time("k").await, Some(expected), "{write}");
+        }
+
+        // 设置新的过期时间：SET EX、RESTORE；MOVE 随键移动
+        let db = Db::new();
+        process_command(&db, "set k v").await;
+        process_command(&db, "pexpire k 100000").await;
+        process_command(&db, "set k w px 5000").await;
+        let at = db.expire_time("k").await.flatten().unwrap();
+        assert!(at <= unix_time_ms() + 5_000);
+        let dump = process_command(&db, "dump k").await;
+        let restore = format!("restore k 50000 {dump} replace");
+        assert_eq!(process_command(&db, &restore).await, "OK");
+        assert!(db.expire_time("k").await.flatten().unwrap() > at);
+        assert_eq!(process_command(&db, &format!("restore k 0 {dump} replace")).await, "OK");
+        assert_eq!(db.expire_time("k").await, Some(None));
+        process_command(&db, "pexpire k 100000").await;
+        let at = db.expire_time("k").await.flatten();
+        assert_eq!(process_command(&db, "move k 1").await, "(integer) 1");
+        assert_eq!(db.select(1).unwrap().expire_time("k").await, Some(at));
+    }
+
+    #[tokio::test]
+    async fn test_set_with_expire() {
+        let db = Db::new();
+
+        db.set_with_expire("a".into(), "1".into(), unix_time_ms() + 20).await;
+        assert_eq!(db.get("a").await, Ok(Some("1".into())));
+
+        tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+        assert_eq!(db.get("a").await, Ok(None));
+        assert_eq!(db.expire_time("a").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_active_expire_cycle() {
+        let db = Db::new();
+        let now = unix_time_ms();
+        for i in 0..100 {
+            db.set_with_expire(format!("k{i}"), "v".into(), now + 20).await;
+        }
+        db.set_with_expire("later".into(), "v".into(), now + 60_000).await;
+        db.set("kept".into(), "v".into()).await;
+        assert_eq!(db.active_expire_cycle().await.unwrap(), 0);
+
+        // 到期的键不经访问也会被删除
+        tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+        assert_eq!(db.active_expire_cycle().await.unwrap(), 100);
+        assert_eq!(db.dbsize().await, 2);
+        assert_eq!(db.stats().expired_keys(), 100);
+        assert_eq!(db.active_expire_cycle().await.unwrap(), 0);
+    }
+}
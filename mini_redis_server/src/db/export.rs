@@ -0,0 +1,100 @@
+//! 整个数据库的导出与导入（EXPORT / IMPORT）
+//!
+//! 记录格式见 `persistence::json` 模块。导出与 BIGKEYS 一样逐个分片遍历当前数据库，
+//! 每处理完一个分片就释放锁并让出执行权，不阻塞其他命令，代价是结果不是某一时刻的一致快照。
+
+use super::{Db, DbError, Storage, unix_time_ms};
+use crate::persistence::{decode_json, encode_json, serialize_value};
+
+impl<S: Storage> Db<S> {
+    /// 当前数据库中的全部键，每个键一行 JSON
+    pub async fn export(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for i in 0..self.shards().len() {
+            {
+                let shard = self.shards().read_index(i).await;
+                let now = unix_time_ms();
+                lines.extend(shard.scan().map(|(key, value)| {
+                    let ttl = shard.expire_at(key).map(|at| at.saturating_sub(now));
+                    encode_json(key, value, ttl)
+                }));
+            }
+            tokio::task::yield_now().await;
+        }
+        lines
+    }
+
+    /// 把 JSON 记录写入当前数据库，覆盖同名的键，返回记录数
+    ///
+    /// 先解析全部记录，任何一条格式错误时返回 `BadImport`，不写入任何键。
+    /// 每个键以 `RESTORE key <过期时间> <载荷> REPLACE ABSTTL` 的形式传播，
+    /// 副本与 AOF 回放得到与这里相同的过期时间。调用方不能持有写门闩。
+    pub async fn import(&self, lines: &[String]) -> Result<usize, DbError> {
+        let records = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                decode_json(line).map_err(|e| DbError::BadImport(i + 1, e.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // 写入与传播在同一个写门闩与顺序锁内完成
+        let _gate = self.enter_write().await;
+        self.free_memory().await?;
+        let keys: Vec<_> = records.iter().map(|(key, ..)| key).collect();
+        let _order = self.lock_order(&keys).await;
+        let now = unix_time_ms();
+        for (key, value, ttl) in &records {
+            let expire_at = ttl.map(|ttl| now.saturating_add(ttl));
+            {
+                let mut guard = self.shards().write(key).await;
+                guard.set(key.clone(), value.clone());
+                if let Some(at) = expire_at {
+                    guard.set_expire_at(key, at);
+                }
+            }
+            self.notifier.notify(key);
+
+            let payload = serialize_value(value);
+            let at = expire_at.unwrap_or(0).to_string();
+            let args = ["restore", key, &at, &payload, "replace", "absttl"];
+            self.propagate(&args.map(String::from))?;
+        }
+        Ok(records.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::process_command;
+
+    #[tokio::test]
+    async fn test_export_and_import() {
+        let db = Db::new();
+        for input in ["set a 1", "hset h f v", "sadd s x", "zadd z 1 m", "xadd x 1-1 f v"] {
+            process_command(&db, input).await;
+        }
+        process_command(&db, "pexpire a 60000").await;
+        let mut lines = db.export().await;
+        lines.sort();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], r#"{"type":"hash","key":"h","ttl":-1,"value":{"f":"v"}}"#);
+        assert!(lines[3].starts_with(r#"{"type":"string","key":"a","ttl":"#));
+
+        let target = Db::new();
+        target.set("h".into(), "old".into()).await;
+        assert_eq!(target.import(&lines).await, Ok(5));
+        for key in ["a", "h", "s", "z", "x"] {
+            assert_eq!(target.dump(key).await.unwrap().0, db.dump(key).await.unwrap().0);
+        }
+        let ttl = process_command(&target, "pttl a").await;
+        assert!(ttl.trim_start_matches("(integer) ").parse::<u64>().unwrap() > 59_000);
+
+        // 任何一条记录有误时都不写入
+        let lines = [r#"{"type":"string","key":"b","value":"1"}"#.to_string(), "{".to_string()];
+        let err = target.import(&lines).await.unwrap_err();
+        assert!(err.to_string().starts_with("ERR invalid IMPORT record 2: "));
+        assert_eq!(target.get("b").await, Ok(None));
+    }
+}
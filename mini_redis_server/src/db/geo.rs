@@ -0,0 +1,188 @@
+//! 地理位置操作
+//!
+//! 地理位置保存在有序集合中，分值为 geohash，编码与搜索的实现见 `geo` 模块。
+//! GEOADD 在校验全部坐标后复用 ZADD 写入，因此同样会唤醒阻塞的 BZPOPMIN / BZPOPMAX。
+
+use super::{Db, DbError, Storage, zset::zset_ref};
+use crate::{
+    geo::{self, Match, Order, Origin, Query},
+    sorted_set::{AddFlags, ScoreRange, SortedSet},
+};
+
+/// 成员的位置，成员不存在时返回 `None`
+fn position(zset: &SortedSet, member: &str) -> Option<(f64, f64)> {
+    zset.score(member).map(|score| geo::decode(score as u64))
+}
+
+impl<S: Storage> Db<S> {
+    /// 写入成员的位置，`locations` 为 `(lon, lat, member)`
+    ///
+    /// 返回值与 ZADD 相同；任何一个坐标超出范围时不写入任何成员。
+    pub async fn geoadd(
+        &self,
+        key: String,
+        flags: AddFlags,
+        locations: Vec<(f64, f64, String)>,
+    ) -> Result<usize, DbError> {
+        let pairs = locations
+            .into_iter()
+            .map(|(lon, lat, member)| match geo::encode(lon, lat) {
+                Some(hash) => Ok((hash as f64, member)),
+                None => Err(DbError::InvalidLonLat(lon, lat)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.zadd(key, flags, pairs).await
+    }
+
+    /// 返回两个成员之间的距离（米），键或任一成员不存在时返回 `None`
+    pub async fn geodist(&self, key: &str, from: &str, to: &str) -> Result<Option<f64>, DbError> {
+        let guard = self.shards().read(key).await;
+
+        let Some(zset) = self.lookup(zset_ref(&*guard, key))? else {
+            return Ok(None);
+        };
+        Ok(position(zset, from).zip(position(zset, to)).map(|(a, b)| geo::distance(a, b)))
+    }
+
+    /// 返回各成员的位置 `(lon, lat)`，不存在的成员对应 `None`
+    pub async fn geopos(
+        &self,
+        key: &str,
+        members: &[String],
+    ) -> Result<Vec<Option<(f64, f64)>>, DbError> {
+        let guard = self.shards().read(key).await;
+
+        let zset = self.lookup(zset_ref(&*guard, key))?;
+        Ok(members.iter().map(|member| zset.and_then(|zset| position(zset, member))).collect())
+    }
+
+    /// 返回位于搜索范围内的成员
+    ///
+    /// 以成员为中心而成员不存在时返回错误；键不存在时返回空列表。
+    pub async fn geosearch(&self, key: &str, query: &Query) -> Result<Vec<Match>, DbError> {
+        let guard = self.shards().read(key).await;
+
+        let Some(zset) = self.lookup(zset_ref(&*guard, key))? else {
+            return Ok(Vec::new());
+        };
+        let center = match &query.origin {
+            Origin::Member(member) => position(zset, member).ok_or(DbError::GeoMemberMissing)?,
+            Origin::LonLat(lon, lat) => (*lon, *lat),
+        };
+
+        let limit = if query.any { query.count.unwrap_or(usize::MAX) } else { usize::MAX };
+        let mut matches = Vec::new();
+        'search: for (min, max) in geo::search_ranges(center, &query.shape) {
+            let range = ScoreRange {
+                min: min as f64,
+                max: max as f64,
+                min_exclusive: false,
+                max_exclusive: true,
+            };
+            for (member, score) in zset.range_by_score(&range, 0, -1) {
+                let hash = score as u64;
+                let (lon, lat) = geo::decode(hash);
+                let Some(dist) = query.shape.contains(center, (lon, lat)) else {
+                    continue;
+                };
+                matches.push(Match { member, dist, hash, lon, lat });
+                if matches.len() >= limit {
+                    break 'search;
+                }
+            }
+        }
+
+        // 带 COUNT 而不带 ANY 时需要排序才能取出最近的成员
+        let order = match query.order {
+            None if query.count.is_some() && !query.any => Some(Order::Asc),
+            order => order,
+        };
+        match order {
+            Some(Order::Asc) => matches.sort_by(|a, b| a.dist.total_cmp(&b.dist)),
+            Some(Order::Desc) => matches.sort_by(|a, b| b.dist.total_cmp(&a.dist)),
+            None => {}
+        }
+        if let Some(count) = query.count {
+            matches.truncate(count);
+        }
+        Ok(matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::{Db, DbError},
+        geo::{Order, Origin, Query, Shape},
+        sorted_set::AddFlags,
+    };
+
+    async fn sicily() -> Db {
+        let db = Db::new();
+        let locations = vec![
+            (13.361389, 38.115556, "Palermo".to_string()),
+            (15.087269, 37.502669, "Catania".to_string()),
+            (12.758489, 38.788135, "edge1".to_string()),
+            (17.241510, 38.788135, "edge2".to_string()),
+        ];
+        assert_eq!(db.geoadd("Sicily".into(), AddFlags::default(), locations).await, Ok(4));
+        db
+    }
+
+    fn query(origin: Origin, shape: Shape) -> Query {
+        Query { origin, shape, order: Some(Order::Asc), count: None, any: false }
+    }
+
+    fn names(matches: Vec<crate::geo::Match>) -> Vec<String> {
+        matches.into_iter().map(|m| m.member).collect()
+    }
+
+    #[tokio::test]
+    async fn test_geoadd_geopos_geodist() {
+        let db = sicily().await;
+        assert_eq!(db.zscore("Sicily", "Palermo").await, Ok(Some(3479099956230698.0)));
+
+        let dist = db.geodist("Sicily", "Palermo", "Catania").await.unwrap().unwrap();
+        assert!((dist - 166274.1516).abs() < 0.01, "{dist}");
+        assert_eq!(db.geodist("Sicily", "Palermo", "missing").await, Ok(None));
+        assert_eq!(db.geodist("missing", "a", "b").await, Ok(None));
+
+        let positions = db.geopos("Sicily", &["Palermo".into(), "missing".into()]).await.unwrap();
+        let (lon, lat) = positions[0].unwrap();
+        assert!((lon - 13.361389).abs() < 1e-5 && (lat - 38.115556).abs() < 1e-5);
+        assert_eq!(positions[1], None);
+
+        // 任何一个坐标无效时不写入任何成员
+        let invalid = vec![(1.0, 1.0, "ok".to_string()), (200.0, 1.0, "bad".to_string())];
+        assert_eq!(
+            db.geoadd("Sicily".into(), AddFlags::default(), invalid).await,
+            Err(DbError::InvalidLonLat(200.0, 1.0))
+        );
+        assert_eq!(db.zcard("Sicily").await, Ok(4));
+    }
+
+    #[tokio::test]
+    async fn test_geosearch() {
+        let db = sicily().await;
+
+        let by_radius = query(Origin::LonLat(15.0, 37.0), Shape::Radius(200_000.0));
+        assert_eq!(
+            names(db.geosearch("Sicily", &by_radius).await.unwrap()),
+            ["Catania", "Palermo"]
+        );
+
+        let by_box =
+            query(Origin::LonLat(15.0, 37.0), Shape::Box { width: 400_000.0, height: 400_000.0 });
+        let found = db.geosearch("Sicily", &by_box).await.unwrap();
+        assert_eq!(names(found), ["Catania", "Palermo", "edge2", "edge1"]);
+
+        let mut nearest = query(Origin::Member("Palermo".into()), Shape::Radius(500_000.0));
+        nearest.order = Some(Order::Desc);
+        nearest.count = Some(2);
+        assert_eq!(names(db.geosearch("Sicily", &nearest).await.unwrap()), ["edge2", "Catania"]);
+
+        let missing = query(Origin::Member("missing".into()), Shape::Radius(1.0));
+        assert_eq!(db.geosearch("Sicily", &missing).await, Err(DbError::GeoMemberMissing));
+        assert_eq!(db.geosearch("nokey", &missing).await, Ok(vec![]));
+    }
+}
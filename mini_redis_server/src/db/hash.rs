@@ -0,0 +1,231 @@
+//! 哈希类型操作
+//!
+//! 哈希值以 `HashMap<String, String>` 形式保存在 [`Value::Hash`] 中。
+
+use std::collections::HashMap;
+
+use super::{Db, DbError, Storage, Value};
+use crate::random;
+
+/// 取出键对应的哈希表，键不存在时创建一个空哈希表
+fn hash_mut<S: Storage>(map: &mut S, key: String) -> Result<&mut HashMap<String, String>, DbError> {
+    match map.get_or_insert_with(key, || Value::Hash(HashMap::new())) {
+        Value::Hash(hash) => Ok(hash),
+        _ => Err(DbError::WrongType),
+    }
+}
+
+/// 以只读方式取出键对应的哈希表，键不存在时返回 `None`
+fn hash_ref<'a, S: Storage>(
+    map: &'a S,
+    key: &str,
+) -> Result<Option<&'a HashMap<String, String>>, DbError> {
+    match map.get(key) {
+        Some(Value::Hash(hash)) => Ok(Some(hash)),
+        Some(_) => Err(DbError::WrongType),
+        None => Ok(None),
+    }
+}
+
+impl<S: Storage> Db<S> {
+    /// 设置哈希字段，返回新增字段的数量
+    pub async fn hset(&self, key: String, pairs: Vec<(String, String)>) -> Result<usize, DbError> {
+        let mut guard = self.shards().write(&key).await;
+        let hash = hash_mut(&mut *guard, key)?;
+
+        let mut added = 0;
+        for (field, value) in pairs {
+            if hash.insert(field, value).is_none() {
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    /// 读取哈希字段的值
+    pub async fn hget(&self, key: &str, field: &str) -> Result<Option<String>, DbError> {
+        let guard = self.shards().read(key).await;
+
+        Ok(self.lookup(hash_ref(&*guard, key))?.and_then(|hash| hash.get(field).cloned()))
+    }
+
+    /// 将哈希字段的整数值加上 `delta`，字段不存在时视为 0，返回新值
+    pub async fn hincrby(&self, key: String, field: String, delta: i64) -> Result<i64, DbError> {
+        let mut guard = self.shards().write(&key).await;
+
+        // 先校验并计算新值再写入，出错时不会留下新建的空哈希
+        let current = match hash_ref(&*guard, &key)?.and_then(|hash| hash.get(&field)) {
+            Some(value) => value.parse::<i64>().map_err(|_| DbError::HashValueNotInteger)?,
+            None => 0,
+        };
+        let new = current.checked_add(delta).ok_or(DbError::Overflow)?;
+
+        hash_mut(&mut *guard, key)?.insert(field, new.to_string());
+        Ok(new)
+    }
+
+    /// 将哈希字段的浮点数值加上 `delta`，字段不存在时视为 0，返回新值的字符串形式
+    pub async fn hincrbyfloat(
+        &self,
+        key: String,
+        field: String,
+        delta: f64,
+    ) -> Result<String, DbError> {
+        let mut guard = self.shards().write(&key).await;
+
+        let current = match hash_ref(&*guard, &key)?.and_then(|hash| hash.get(&field)) {
+            Some(value) => value.parse::<f64>().map_err(|_| DbError::HashValueNotFloat)?,
+            None => 0.0,
+        };
+        let new = current + delta;
+        if !new.is_finite() {
+            return Err(DbError::NanOrInfinity);
+        }
+
+        let new = new.to_string();
+        hash_mut(&mut *guard, key)?.insert(field, new.clone());
+        Ok(new)
+    }
+
+    /// 随机返回哈希中的字段及其值
+    ///
+    /// - `count` 为 `None`：最多返回一个字段
+    /// - `count` 为正数：返回最多 `count` 个互不相同的字段
+    /// - `count` 为负数：返回 `|count|` 个字段，字段可能重复；
+    ///   绝对值超过 [`random::MAX_REPEATED_SAMPLES`] 时返回 [`DbError::OutOfRange`]
+    pub async fn hrandfield(
+        &self,
+        key: &str,
+        count: Option<i64>,
+    ) -> Result<Vec<(String, String)>, DbError> {
+        if !random::count_in_range(count) {
+            return Err(DbError::OutOfRange);
+        }
+
+        let guard = self.shards().read(key).await;
+        let Some(hash) = self.lookup(hash_ref(&*guard, key))? else {
+            return Ok(Vec::new());
+        };
+
+        // HashMap 的遍历顺序不固定，先排序以保证相同种子得到相同结果
+        let mut entries: Vec<_> = hash.iter().collect();
+        entries.sort();
+
+        let indices = self.rng.lock().unwrap().sample_with_count(entries.len(), count);
+        Ok(indices.into_iter().map(|i| (entries[i].0.clone(), entries[i].1.clone())).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{Db, DbError};
+
+    #[tokio::test]
+    async fn test_hset_hget() {
+        let db = Db::new();
+
+        let added = db.hset("h".into(), vec![("a".into(), "1".into()), ("b".into(), "2".into())]);
+        assert_eq!(added.await, Ok(2));
+        assert_eq!(db.hset("h".into(), vec![("a".into(), "3".into())]).await, Ok(0));
+
+        assert_eq!(db.hget("h", "a").await, Ok(Some("3".into())));
+        assert_eq!(db.hget("h", "c").await, Ok(None));
+        assert_eq!(db.hget("missing", "a").await, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn test_hincrby() {
+        let db = Db::new();
+
+        assert_eq!(db.hincrby("h".into(), "n".into(), 5).await, Ok(5));
+        assert_eq!(db.hincrby("h".into(), "n".into(), -7).await, Ok(-2));
+        assert_eq!(db.hget("h", "n").await, Ok(Some("-2".into())));
+    }
+
+    #[tokio::test]
+    async fn test_hincrby_errors() {
+        let db = Db::new();
+        db.hset("h".into(), vec![("s".into(), "abc".into()), ("max".into(), i64::MAX.to_string())])
+            .await
+            .unwrap();
+        db.set("str".into(), "1".into()).await;
+
+        assert_eq!(db.hincrby("h".into(), "s".into(), 1).await, Err(DbError::HashValueNotInteger));
+        assert_eq!(db.hincrby("h".into(), "max".into(), 1).await, Err(DbError::Overflow));
+        assert_eq!(db.hincrby("str".into(), "f".into(), 1).await, Err(DbError::WrongType));
+    }
+
+    #[tokio::test]
+    async fn test_hincrbyfloat() {
+        let db = Db::new();
+
+        assert_eq!(db.hincrbyfloat("h".into(), "f".into(), 10.5).await, Ok("10.5".into()));
+        assert_eq!(db.hincrbyfloat("h".into(), "f".into(), 0.5).await, Ok("11".into()));
+        assert_eq!(db.hincrby("h".into(), "f".into(), 1).await, Ok(12));
+
+        db.hset("h".into(), vec![("s".into(), "abc".into())]).await.unwrap();
+        assert_eq!(
+            db.hincrbyfloat("h".into(), "s".into(), 1.0).await,
+            Err(DbError::HashValueNotFloat)
+        );
+        assert_eq!(
+            db.hincrbyfloat("h".into(), "f".into(), f64::INFINITY).await,
+            Err(DbError::NanOrInfinity)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hrandfield_count() {
+        let db = Db::new();
+        let pairs = (0..5).map(|i| (format!("f{i}"), format!("v{i}"))).collect();
+        db.hset("h".into(), pairs).await.unwrap();
+
+        assert_eq!(db.hrandfield("h", None).await.unwrap().len(), 1);
+        assert_eq!(db.hrandfield("missing", None).await, Ok(vec![]));
+
+        let mut distinct = db.hrandfield("h", Some(10)).await.unwrap();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 5);
+
+        let repeated = db.hrandfield("h", Some(-20)).await.unwrap();
+        assert_eq!(repeated.len(), 20);
+        assert!(repeated.iter().all(|(f, v)| f[1..] == v[1..]));
+    }
+
+    #[tokio::test]
+    async fn test_hrandfield_seeded() {
+        let sample = |seed| async move {
+            let db = Db::with_seed(seed);
+            let pairs = (0..10).map(|i| (format!("f{i}"), i.to_string())).collect();
+            db.hset("h".into(), pairs).await.unwrap();
+            db.hrandfield("h", Some(-5)).await.unwrap()
+        };
+
+        assert_eq!(sample(1).await, sample(1).await);
+    }
+
+    #[tokio::test]
+    async fn test_hrandfield_count_out_of_range() {
+        let db = Db::new();
+        db.hset("h".into(), vec![("f".into(), "v".into())]).await.unwrap();
+
+        assert_eq!(db.hrandfield("h", Some(i64::MIN)).await, Err(DbError::OutOfRange));
+        assert_eq!(db.hrandfield("missing", Some(i64::MIN)).await, Err(DbError::OutOfRange));
+    }
+
+    #[tokio::test]
+    async fn test_hincr_error_leaves_no_key() {
+        let db = Db::new();
+
+        assert_eq!(
+            db.hincrbyfloat("newh".into(), "f".into(), f64::INFINITY).await,
+            Err(DbError::NanOrInfinity)
+        );
+        assert!(db.keys("*").await.is_empty());
+
+        db.hset("h".into(), vec![("n".into(), i64::MAX.to_string())]).await.unwrap();
+        assert_eq!(db.hincrby("h".into(), "n".into(), 1).await, Err(DbError::Overflow));
+        assert_eq!(db.keys("*").await, vec!["h"]);
+    }
+}
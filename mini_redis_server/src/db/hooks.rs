@@ -0,0 +1,202 @@
+//! 键变更钩子
+//!
+//! 嵌入 mini-redis 的程序可以实现 [`KeyObserver`] 并通过 [`Db::add_observer`] 注册，
+//! 把键的变化同步到其他系统（缓存失效、搜索索引等）：
+//! - `on_set`：写命令修改了键，修改后键仍然存在（写入值、增删元素、设置过期时间等）
+//! - `on_delete`：写命令删除了键（DEL、移除最后一个元素、MOVE 的源键等），或键被 maxmemory 淘汰
+//! - `on_expire`：键因过期被主动删除
+//! - `on_flush`：FLUSHDB / FLUSHALL / SWAPDB 整体替换了一个数据库的内容，不逐键报告
+//!
+//! 事件只包含数据库编号与键名，需要新值时由回调自行读取。只有经过命令处理层
+//! （[`handler::execute`](crate::handler::execute)）的写命令会产生事件，直接调用 `Db` 的方法不会。
+//!
+//! 事件在持有分片锁、写门闩期间先放入队列，全部释放后才依次调用回调，
+//! 因此回调中可以再次访问 `Db`（包括执行写命令）而不会死锁。回调在执行命令的任务中同步调用，
+//! 应当尽快返回，耗时的工作交给其他任务。
+
+use std::{
+    mem,
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use super::{Db, Storage};
+
+/// 键变更的观察者，各方法默认什么也不做
+pub trait KeyObserver: Send + Sync + 'static {
+    /// 数据库 `db` 中的键被写入或修改
+    fn on_set(&self, _db: usize, _key: &str) {}
+
+    /// 数据库 `db` 中的键被删除或淘汰
+    fn on_delete(&self, _db: usize, _key: &str) {}
+
+    /// 数据库 `db` 中的键因过期被删除
+    fn on_expire(&self, _db: usize, _key: &str) {}
+
+    /// 数据库 `db` 被清空或与其他数据库交换了内容
+    fn on_flush(&self, _db: usize) {}
+}
+
+/// 尚未通知观察者的事件
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum KeyEvent {
+    Set(usize, String),
+    Delete(usize, String),
+    Expire(usize, String),
+    Flush(usize),
+}
+
+/// 已注册的观察者与待通知的事件
+#[derive(Default)]
+pub(crate) struct Hooks {
+    observers: RwLock<Vec<Arc<dyn KeyObserver>>>,
+    /// 是否有观察者，没有时不记录事件
+    active: AtomicBool,
+    pending: Mutex<Vec<KeyEvent>>,
+}
+
+impl Hooks {
+    /// 是否注册了观察者
+    pub(crate) fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// 记录一个事件，可以在持有锁时调用；没有观察者时忽略
+    pub(crate) fn push(&self, event: KeyEvent) {
+        if self.is_active() {
+            self.pending.lock().unwrap().push(event);
+        }
+    }
+
+    /// 按发生顺序把待通知的事件交给全部观察者，调用方不能持有分片锁或写门闩
+    pub(crate) fn dispatch(&self) {
+        if !self.is_active() {
+            return;
+        }
+        let events = mem::take(&mut *self.pending.lock().unwrap());
+        if events.is_empty() {
+            return;
+        }
+        // 复制一份列表，回调中注册新的观察者不会死锁
+        let observers = self.observers.read().unwrap().clone();
+        for event in &events {
+            for observer in &observers {
+                match event {
+                    KeyEvent::Set(db, key) => observer.on_set(*db, key),
+                    KeyEvent::Delete(db, key) => observer.on_delete(*db, key),
+                    KeyEvent::Expire(db, key) => observer.on_expire(*db, key),
+                    KeyEvent::Flush(db) => observer.on_flush(*db),
+                }
+            }
+        }
+    }
+}
+
+impl<S: Storage> Db<S> {
+    /// 注册键变更的观察者，对所有数据库生效
+    pub fn add_observer(&self, observer: Arc<dyn KeyObserver>) {
+        self.hooks.observers.write().unwrap().push(observer);
+        self.hooks.active.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn hooks(&self) -> &Hooks {
+        &self.hooks
+    }
+
+    /// 写命令修改了当前数据库中的一组键之后调用：按修改后键是否存在记录写入或删除事件
+    pub(crate) async fn record_changes(&self, keys: impl IntoIterator<Item = String>) {
+        for key in keys {
+            let exists = self.shards().read(&key).await.contains_key(&key);
+            let event = if exists {
+                KeyEvent::Set(self.index, key)
+            } else {
+                KeyEvent::Delete(self.index, key)
+            };
+            self.hooks.push(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::process_command;
+
+    /// 把事件记录为字符串，回调中再次执行命令以验证不会死锁
+    #[derive(Default)]
+    struct Recorder {
+        db: Mutex<Option<Db>>,
+        events: Mutex<Vec<String>>,
+    }
+
+    impl KeyObserver for Recorder {
+        fn on_set(&self, db: usize, key: &str) {
+            self.events.lock().unwrap().push(format!("set {db} {key}"));
+            if key == "trigger" {
+                let db = self.db.lock().unwrap().clone().unwrap();
+                tokio::spawn(async move { process_command(&db, "set triggered 1").await });
+            }
+        }
+
+        fn on_delete(&self, db: usize, key: &str) {
+            self.events.lock().unwrap().push(format!("delete {db} {key}"));
+        }
+
+        fn on_expire(&self, db: usize, key: &str) {
+            self.events.lock().unwrap().push(format!("expire {db} {key}"));
+        }
+
+        fn on_flush(&self, db: usize) {
+            self.events.lock().unwrap().push(format!("flush {db}"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_observer() {
+        let db = Db::new();
+        let recorder = Arc::new(Recorder::default());
+        db.add_observer(recorder.clone());
+        *recorder.db.lock().unwrap() = Some(db.clone());
+        let take = || mem::take(&mut *recorder.events.lock().unwrap());
+
+        for input in [
+            "set a 1",
+            "sadd s x y",
+            "sadd t y",
+            "sinterstore d s t",
+            "srem t y",
+            "del missing",
+            "move a 2",
+            "pexpire d 1",
+            "flushdb",
+        ] {
+            process_command(&db, input).await;
+        }
+        assert_eq!(
+            take(),
+            [
+                "set 0 a",
+                "set 0 s",
+                "set 0 t",
+                "set 0 d",
+                "delete 0 t",
+                "set 2 a",
+                "delete 0 a",
+                "set 0 d",
+                "flush 0"
+            ]
+        );
+
+        // 主动过期与回调中执行的写命令
+        process_command(&db, "set e 1").await;
+        process_command(&db, "pexpire e 1").await;
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        take();
+        assert_eq!(db.active_expire_cycle().await.unwrap(), 1);
+        process_command(&db, "set trigger 1").await;
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(take(), ["expire 0 e", "set 0 trigger", "set 0 triggered"]);
+    }
+}
@@ -0,0 +1,516 @@
+//! 键空间：键值表 + 过期时间轮，默认的 [`Storage`] 实现
+//!
+//! 键值表是渐进式 rehash 的 [`Dict`]：扩容与收缩的搬迁工作分摊到各次写操作上，
+//! SCAN 游标（[`Storage::scan_keys`]）在表改变大小期间仍能保证遍历到全程存在的键。
+//!
+//! 过期时间放在 [`TimerWheel`] 中按到期时间分桶，主动过期（[`Storage::pop_expired`]）
+//! 只取出到期的键，不需要扫描全部设置了过期时间的键。此外在访问时惰性处理过期：
+//! - 只读访问（`get` / `scan` / `keys`）把已过期的键视为不存在
+//! - 可变访问（`get_mut` / `get_or_insert_with`）先删除已过期的键
+//!
+//! 过期时间以 Unix 毫秒时间戳保存，便于持久化后在重启时继续生效。
+//!
+//! 为支持 maxmemory 淘汰，每个键还记录：
+//! - 估算的内存占用：集合类的值只抽样少量元素估算（与 Redis 的 `MEMORY USAGE` 类似），
+//!   通过可变引用借出的值在下次统计时重新估算
+//! - 最近一次访问的时间，读访问也会更新（原子变量，无需写锁）
+//! - 访问频率计数：与 Redis 的 LFU 计数一样按对数增长、随空闲时间衰减，用于找出热点键
+//! - 所有键与设置了过期时间的键各保存一份数组，用于 O(1) 随机取键
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    mem,
+    sync::atomic::{AtomicU8, AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{Storage, Value};
+use crate::{dict::Dict, random::Rng, timer_wheel::TimerWheel};
+
+/// 当前的 Unix 毫秒时间戳
+pub fn unix_time_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
+
+/// 每个键的固定开销估算（表项、键值对象头等）
+const ENTRY_OVERHEAD: usize = 64;
+/// 集合类值中每个元素的固定开销估算
+const ELEMENT_OVERHEAD: usize = 32;
+/// 估算集合类值大小时抽样的元素个数
+const SIZE_SAMPLES: usize = 5;
+/// 新键的访问频率计数，使新键不会立即显得比旧键更冷（与 Redis 的 `LFU_INIT_VAL` 相同）
+const LFU_INIT: u8 = 5;
+/// 对数因子：计数越大，一次访问使它加一的概率越小（与 Redis 的 `lfu-log-factor` 默认值相同）
+const LFU_LOG_FACTOR: f64 = 10.0;
+/// 每空闲这么长时间（毫秒）计数减一（与 Redis 的 `lfu-decay-time` 默认值相同）
+const LFU_DECAY_MS: u64 = 60_000;
+
+thread_local! {
+    /// 访问频率计数按概率增长所用的随机数
+    static RNG: RefCell<Rng> = RefCell::new(Rng::from_entropy());
+}
+
+struct Entry {
+    value: Value,
+    /// 估算的内存占用（字节）
+    size: usize,
+    /// 最近一次访问的时间（Unix 毫秒）
+    last_access: AtomicU64,
+    /// 访问频率计数，截至 `last_access` 时的值
+    frequency: AtomicU8,
+}
+
+impl Entry {
+    fn new(value: Value, size: usize) -> Self {
+        let last_access = AtomicU64::new(unix_time_ms());
+        Self { value, size, last_access, frequency: AtomicU8::new(LFU_INIT) }
+    }
+
+    /// 记录一次访问：先按空闲时间衰减访问频率，再按概率加一
+    ///
+    /// 读访问只持有读锁，并发访问时计数可能少记，对近似统计没有影响。
+    fn touch(&self) {
+        let now = unix_time_ms();
+        let last = self.last_access.swap(now, Ordering::Relaxed);
+        let frequency = decay(self.frequency.load(Ordering::Relaxed), last, now);
+        self.frequency.store(increment(frequency), Ordering::Relaxed);
+    }
+
+    fn frequency(&self, now: u64) -> u8 {
+        decay(self.frequency.load(Ordering::Relaxed), self.last_access.load(Ordering::Relaxed), now)
+    }
+}
+
+/// 从 `last` 到 `now` 每经过 [`LFU_DECAY_MS`] 计数减一
+fn decay(frequency: u8, last: u64, now: u64) -> u8 {
+    let periods = now.saturating_sub(last) / LFU_DECAY_MS;
+    frequency.saturating_sub(periods.min(u8::MAX as u64) as u8)
+}
+
+/// 对数增长：超出初始值的部分越大，加一的概率越小，255 次方量级的访问才能到达上限
+fn increment(frequency: u8) -> u8 {
+    if frequency == u8::MAX {
+        return frequency;
+    }
+    let base = frequency.saturating_sub(LFU_INIT) as f64;
+    let random = RNG.with(|rng| rng.borrow_mut().next_u64()) as f64 / u64::MAX as f64;
+    if random < 1.0 / (base * LFU_LOG_FACTOR + 1.0) { frequency + 1 } else { frequency }
+}
+
+/// 默认的内存存储后端
+#[derive(Default)]
+pub struct Keyspace {
+    entries: Dict<Entry>,
+    /// key -> 过期时间（Unix 毫秒）
+    expires: TimerWheel<String>,
+    /// 所有键，用于随机取键
+    slots: KeySlots,
+    /// 设置了过期时间的键，用于随机取键
+    volatile: KeySlots,
+    /// 所有键估算的内存占用之和
+    used_memory: usize,
+    /// 以可变引用借出过、大小需要重新估算的键
+    resized: Vec<String>,
+}
+
+impl Keyspace {
+    fn is_expired(&self, key: &str, now: u64) -> bool {
+        self.expires.deadline(key).is_some_and(|at| at <= now)
+    }
+
+    /// 删除已过期的键
+    fn purge(&mut self, key: &str) {
+        if self.is_expired(key, unix_time_ms()) {
+            self.remove(key);
+        }
+    }
+
+    /// 记录值可能被修改的键
+    fn mark_resized(&mut self, key: &str) {
+        if self.resized.last().is_none_or(|last| last != key) {
+            self.resized.push(key.to_string());
+        }
+    }
+
+    /// 取出已存在的键并更新访问时间
+    fn entry_mut(&mut self, key: &str) -> Option<&mut Value> {
+        let entry = self.entries.get_mut(key)?;
+        entry.touch();
+        Some(&mut entry.value)
+    }
+}
+
+impl Storage for Keyspace {
+    fn get(&self, key: &str) -> Option<&Value> {
+        if self.is_expired(key, unix_time_ms()) {
+            return None;
+        }
+        let entry = self.entries.get(key)?;
+        entry.touch();
+        Some(&entry.value)
+    }
+
+    fn peek(&self, key: &str) -> Option<&Value> {
+        if self.is_expired(key, unix_time_ms()) {
+            return None;
+        }
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+
+    fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.purge(key);
+        if self.entries.contains_key(key) {
+            self.mark_resized(key);
+        }
+        self.entry_mut(key)
+    }
+
+    fn get_or_insert_with(&mut self, key: String, default: impl FnOnce() -> Value) -> &mut Value {
+        self.purge(&key);
+        if !self.entries.contains_key(&key) {
+            self.set(key.clone(), default());
+        }
+        self.mark_resized(&key);
+        self.entry_mut(&key).expect("key was just inserted")
+    }
+
+    fn set(&mut self, key: String, value: Value) -> Option<Value> {
+        self.expires.remove(&key);
+        self.volatile.remove(&key);
+        let size = estimate(&key, &value);
+        self.used_memory += size;
+        if let Some(entry) = self.entries.get_mut(&key) {
+            self.used_memory -= mem::replace(&mut entry.size, size);
+            entry.touch();
+            return Some(mem::replace(&mut entry.value, value));
+        }
+
+        self.slots.insert(&key);
+        self.entries.insert(key, Entry::new(value, size));
+        None
+    }
+
+    fn remove(&mut self, key: &str) -> Option<Value> {
+        self.expires.remove(key);
+        self.volatile.remove(key);
+        let entry = self.entries.remove(key)?;
+        self.used_memory -= entry.size;
+        self.slots.remove(key);
+        Some(entry.value)
+    }
+
+    /// 遍历所有未过期的键值
+    fn scan(&self) -> impl Iterator<Item = (&String, &Value)> {
+        let now = unix_time_ms();
+        self.entries
+            .iter()
+            .filter(move |(key, _)| !self.is_expired(key, now))
+            .map(|(key, entry)| (key, &entry.value))
+    }
+
+    fn scan_keys(&self, cursor: u64, count: usize, keys: &mut Vec<String>) -> u64 {
+        let now = unix_time_ms();
+        let start = keys.len();
+        // 与 Redis 一样限制访问的桶数，大部分桶为空时也不会一次遍历太久
+        let mut buckets = count.saturating_mul(10).max(1);
+        let mut cursor = cursor;
+        loop {
+            cursor = self.entries.scan(cursor, |key, _| {
+                if !self.is_expired(key, now) {
+                    keys.push(key.clone());
+                }
+            });
+            buckets -= 1;
+            if cursor == 0 || keys.len() - start >= count || buckets == 0 {
+                return cursor;
+            }
+        }
+    }
+
+    fn expire_at(&self, key: &str) -> Option<u64> {
+        self.get(key).and(self.expires.deadline(key))
+    }
+
+    fn set_expire_at(&mut self, key: &str, at: u64) -> bool {
+        if self.get_mut(key).is_none() {
+            return false;
+        }
+        if at <= unix_time_ms() {
+            self.remove(key);
+        } else {
+            self.expires.insert(key.to_string(), at);
+            self.volatile.insert(key);
+        }
+        true
+    }
+
+    fn persist(&mut self, key: &str) -> bool {
+        self.purge(key);
+        self.volatile.remove(key);
+        self.expires.remove(key).is_some()
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.expires.clear();
+        self.slots.clear();
+        self.volatile.clear();
+        self.resized.clear();
+        self.used_memory = 0;
+    }
+
+    fn pop_expired(&mut self, now: u64, limit: usize) -> Vec<(String, Value)> {
+        self.expires.advance(now);
+        let expired = self.expires.pop_expired(limit);
+        expired.into_iter().filter_map(|(key, _)| Some((key.clone(), self.remove(&key)?))).collect()
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn used_memory(&mut self) -> usize {
+        for key in mem::take(&mut self.resized) {
+            if let Some(entry) = self.entries.get_mut(&key) {
+                let size = estimate(&key, &entry.value);
+                self.used_memory = self.used_memory - entry.size + size;
+                entry.size = size;
+            }
+        }
+        self.used_memory
+    }
+
+    fn random_key(&self, random: u64, volatile: bool) -> Option<&String> {
+        if volatile { self.volatile.random(random) } else { self.slots.random(random) }
+    }
+
+    fn last_access(&self, key: &str) -> Option<u64> {
+        self.entries.get(key).map(|entry| entry.last_access.load(Ordering::Relaxed))
+    }
+
+    fn access_frequency(&self, key: &str) -> Option<u8> {
+        self.entries.get(key).map(|entry| entry.frequency(unix_time_ms()))
+    }
+}
+
+/// 支持 O(1) 插入、删除和随机选取的键集合
+#[derive(Default)]
+struct KeySlots {
+    keys: Vec<String>,
+    /// key -> 在 `keys` 中的位置
+    index: HashMap<String, usize>,
+}
+
+impl KeySlots {
+    fn insert(&mut self, key: &str) {
+        if !self.index.contains_key(key) {
+            self.index.insert(key.to_string(), self.keys.len());
+            self.keys.push(key.to_string());
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        let Some(slot) = self.index.remove(key) else {
+            return;
+        };
+        self.keys.swap_remove(slot);
+        if let Some(moved) = self.keys.get(slot) {
+            *self.index.get_mut(moved).expect("moved key is indexed") = slot;
+        }
+    }
+
+    fn random(&self, random: u64) -> Option<&String> {
+        if self.keys.is_empty() {
+            return None;
+        }
+        self.keys.get((random % self.keys.len() as u64) as usize)
+    }
+
+    fn clear(&mut self) {
+        self.keys.clear();
+        self.index.clear();
+    }
+}
+
+/// 估算一个键值的内存占用（字节）
+fn estimate(key: &str, value: &Value) -> usize {
+    let value_size = match value {
+        Value::String(s) => s.len(),
+        Value::Hash(hash) => {
+            sampled(hash.len(), hash.iter().map(|(field, value)| field.len() + value.len()))
+        }
+        Value::Set(set) => sampled(set.len(), set.iter().map(String::len)),
+        // 有序集合的成员同时保存在跳表和字典中
+        Value::ZSet(zset) => sampled(
+            zset.len(),
+            zset.range(0, SIZE_SAMPLES as i64 - 1, false)
+                .iter()
+                .map(|(member, _)| 2 * member.len()),
+        ),
+        Value::Stream(stream) => sampled(
+            stream.len(),
+            stream.iter().map(|(id, fields)| {
+                mem::size_of_val(id) + fields.iter().map(|(f, v)| f.len() + v.len()).sum::<usize>()
+            }),
+        ),
+    };
+    ENTRY_OVERHEAD + key.len() + value_size
+}
+
+/// 由前几个元素的平均大小估算全部 `len` 个元素的大小
+fn sampled(len: usize, sizes: impl Iterator<Item = usize>) -> usize {
+    let (count, total) = sizes
+        .take(SIZE_SAMPLES)
+        .fold((0, 0), |(count, total), size| (count + 1, total + size + ELEMENT_OVERHEAD));
+    (total * len).checked_div(count).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lazy_expiration() {
+        let mut keyspace = Keyspace::default();
+        keyspace.set("a".into(), Value::String("1".into()));
+        keyspace.set("b".into(), Value::String("2".into()));
+
+        let future = unix_time_ms() + 60_000;
+        assert!(keyspace.set_expire_at("a", future));
+        assert_eq!(keyspace.expire_at("a"), Some(future));
+        assert!(!keyspace.set_expire_at("missing", future));
+
+        // 已过期的键对只读访问不可见，可变访问时被删除
+        keyspace.expires.insert("b".into(), 1);
+        assert!(!keyspace.contains_key("b"));
+        assert_eq!(keyspace.keys().collect::<Vec<_>>(), vec!["a"]);
+        assert!(keyspace.get_mut("b").is_none());
+        assert!(!keyspace.entries.contains_key("b"));
+        assert_eq!(keyspace.slots.keys, vec!["a"]);
+        assert_eq!(keyspace.expires.deadline("b"), None);
+    }
+
+    #[test]
+    fn test_access_frequency() {
+        let mut keyspace = Keyspace::default();
+        keyspace.set("hot".into(), Value::String("1".into()));
+        keyspace.set("cold".into(), Value::String("1".into()));
+        assert_eq!(keyspace.access_frequency("hot"), Some(LFU_INIT));
+        assert_eq!(keyspace.access_frequency("missing"), None);
+
+        // 按对数增长：一千次访问只让计数增加十几
+        for _ in 0..1000 {
+            keyspace.get("hot");
+        }
+        let hot = keyspace.access_frequency("hot").unwrap();
+        assert!((LFU_INIT + 5..LFU_INIT + 30).contains(&hot), "{hot}");
+        assert_eq!(keyspace.access_frequency("cold"), Some(LFU_INIT));
+
+        // 空闲时间按分钟衰减
+        assert_eq!(decay(10, 0, 3 * LFU_DECAY_MS + 1), 7);
+        assert_eq!(decay(10, 0, 100 * LFU_DECAY_MS), 0);
+        assert_eq!(increment(u8::MAX), u8::MAX);
+    }
+
+    #[test]
+    fn test_pop_expired() {
+        let mut keyspace = Keyspace::default();
+        let now = unix_time_ms();
+        for (i, key) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            keyspace.set(key.into(), Value::String(key.into()));
+            keyspace.set_expire_at(key, now + 1_000 * (i as u64 + 1));
+        }
+        keyspace.persist("b");
+
+        // 只取出到期的键，并且不超过数量限制
+        assert!(keyspace.pop_expired(now + 500, 10).is_empty());
+        assert_eq!(keyspace.pop_expired(now + 3_000, 1).len(), 1);
+        assert_eq!(keyspace.pop_expired(now + 3_000, 10).len(), 1);
+        assert!(keyspace.pop_expired(now + 3_000, 10).is_empty());
+        assert!(!keyspace.entries.contains_key("a") && !keyspace.entries.contains_key("c"));
+        assert_eq!(keyspace.len(), 2);
+        assert_eq!(keyspace.volatile.keys, ["d"]);
+    }
+
+    #[test]
+    fn test_persist() {
+        let mut keyspace = Keyspace::default();
+        keyspace.set("a".into(), Value::String("1".into()));
+        keyspace.set("b".into(), Value::String("2".into()));
+        keyspace.set_expire_at("a", unix_time_ms() + 60_000);
+
+        assert!(keyspace.persist("a"));
+        assert_eq!(keyspace.expire_at("a"), None);
+        assert!(keyspace.volatile.keys.is_empty());
+        assert!(!keyspace.persist("a"));
+        assert!(!keyspace.persist("b"));
+        assert!(!keyspace.persist("missing"));
+
+        // 已过期的键被删除，不能再清除过期时间
+        keyspace.expires.insert("b".into(), 1);
+        assert!(!keyspace.persist("b"));
+        assert!(!keyspace.entries.contains_key("b"));
+    }
+
+    #[test]
+    fn test_insert_clears_expire() {
+        let mut keyspace = Keyspace::default();
+        keyspace.set("a".into(), Value::String("1".into()));
+        keyspace.set_expire_at("a", unix_time_ms() + 60_000);
+
+        keyspace.set("a".into(), Value::String("2".into()));
+        assert_eq!(keyspace.expire_at("a"), None);
+
+        // 过去的时间直接删除键
+        assert!(keyspace.set_expire_at("a", 1));
+        assert!(!keyspace.contains_key("a"));
+    }
+
+    #[test]
+    fn test_used_memory() {
+        let mut keyspace = Keyspace::default();
+        assert_eq!(keyspace.used_memory(), 0);
+
+        keyspace.set("a".into(), Value::String("x".repeat(100).into_bytes()));
+        let small = keyspace.used_memory();
+        assert!(small >= 100);
+
+        // 通过可变引用修改的值在下次统计时重新估算
+        if let Value::Set(set) =
+            keyspace.get_or_insert_with("s".into(), || Value::Set(Default::default()))
+        {
+            set.extend((0..100).map(|i| format!("member:{i}")));
+        }
+        assert!(keyspace.used_memory() > small + 100 * "member:0".len());
+
+        keyspace.remove("s");
+        assert_eq!(keyspace.used_memory(), small);
+        keyspace.clear();
+        assert_eq!(keyspace.used_memory(), 0);
+    }
+
+    #[test]
+    fn test_random_key() {
+        let mut keyspace = Keyspace::default();
+        assert_eq!(keyspace.random_key(0, false), None);
+
+        for key in ["a", "b", "c"] {
+            keyspace.set(key.into(), Value::String(key.into()));
+        }
+        keyspace.remove("a");
+        let mut keys: Vec<_> =
+            (0..2).filter_map(|i| keyspace.random_key(i, false)).cloned().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["b", "c"]);
+
+        // 只在设置了过期时间的键中选取
+        assert_eq!(keyspace.random_key(0, true), None);
+        keyspace.set_expire_at("c", unix_time_ms() + 60_000);
+        assert_eq!(keyspace.random_key(1, true).map(String::as_str), Some("c"));
+        keyspace.set("c".into(), Value::String("c".into()));
+        assert_eq!(keyspace.random_key(1, true), None);
+        assert!(keyspace.last_access("b").is_some());
+        assert_eq!(keyspace.last_access("a"), None);
+    }
+}
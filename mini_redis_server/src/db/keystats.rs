@@ -0,0 +1,136 @@
+//! 大键与热点键统计（BIGKEYS / HOTKEYS）
+//!
+//! 作用与 `redis-cli --bigkeys` / `--hotkeys` 相同，但在服务器端完成：逐个分片遍历当前数据库，
+//! 每处理完一个分片就释放锁并让出执行权，遍历期间其他命令照常执行，
+//! 代价是结果不是某一时刻的一致快照。
+//! - 大键：每种类型中最大的键，字符串按字节数比较，其他类型按元素个数比较
+//! - 热点键：访问频率计数（见 [`Storage::access_frequency`]）最高的键
+//!
+//! 统计本身不计为对键的访问。
+
+use super::{Db, Storage, Value};
+
+/// 一种类型中最大的键
+#[derive(Clone, Debug, PartialEq)]
+pub struct BigKey {
+    /// 类型名，与 TYPE 命令的回复相同
+    pub key_type: &'static str,
+    pub key: String,
+    /// 字符串的字节数，或集合类值的元素个数
+    pub size: usize,
+    /// 这种类型的键的总数
+    pub keys: usize,
+}
+
+impl Value {
+    /// 类型名，与 TYPE 命令的回复相同
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Hash(_) => "hash",
+            Value::Set(_) => "set",
+            Value::ZSet(_) => "zset",
+            Value::Stream(_) => "stream",
+        }
+    }
+
+    /// 字符串的字节数，或集合类值的元素个数
+    pub fn length(&self) -> usize {
+        match self {
+            Value::String(s) => s.len(),
+            Value::Hash(hash) => hash.len(),
+            Value::Set(set) => set.len(),
+            Value::ZSet(zset) => zset.len(),
+            Value::Stream(stream) => stream.len(),
+        }
+    }
+}
+
+impl<S: Storage> Db<S> {
+    /// 当前数据库中每种类型最大的键，按类型名排序
+    pub async fn big_keys(&self) -> Vec<BigKey> {
+        let mut biggest: Vec<BigKey> = Vec::new();
+        for i in 0..self.shards().len() {
+            {
+                let shard = self.shards().read_index(i).await;
+                for (key, value) in shard.scan() {
+                    let (key_type, size) = (value.type_name(), value.length());
+                    match biggest.iter_mut().find(|big| big.key_type == key_type) {
+                        Some(big) => {
+                            big.keys += 1;
+                            if size > big.size {
+                                big.key.clone_from(key);
+                                big.size = size;
+                            }
+                        }
+                        None => biggest.push(BigKey { key_type, key: key.clone(), size, keys: 1 }),
+                    }
+                }
+            }
+            tokio::task::yield_now().await;
+        }
+        biggest.sort_by_key(|big| big.key_type);
+        biggest
+    }
+
+    /// 当前数据库中访问频率最高的 `count` 个键及其计数，按计数从高到低排列；
+    /// 存储后端不记录访问频率时返回空列表
+    pub async fn hot_keys(&self, count: usize) -> Vec<(String, u8)> {
+        let mut hottest: Vec<(String, u8)> = Vec::new();
+        for i in 0..self.shards().len() {
+            {
+                let shard = self.shards().read_index(i).await;
+                let frequencies = shard
+                    .keys()
+                    .filter_map(|key| Some((key.clone(), shard.access_frequency(key)?)));
+                hottest.extend(frequencies);
+            }
+            // 每个分片之后只保留当前的前 `count` 个，内存占用不随键的总数增长
+            hottest.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.cmp(b)));
+            hottest.truncate(count);
+            tokio::task::yield_now().await;
+        }
+        hottest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::process_command;
+
+    #[tokio::test]
+    async fn test_big_keys() {
+        let db = Db::new();
+        for input in ["set a 123", "set b 1", "sadd s x y z", "sadd t x", "hset h f v"] {
+            process_command(&db, input).await;
+        }
+
+        let big =
+            |key_type, key: &str, size, keys| BigKey { key_type, key: key.into(), size, keys };
+        assert_eq!(
+            db.big_keys().await,
+            [big("hash", "h", 1, 1), big("set", "s", 3, 2), big("string", "a", 3, 2)]
+        );
+        assert!(db.view(1).big_keys().await.is_empty());
+        assert_eq!(process_command(&db, "bigkeys").await.lines().next(), Some("1) 1) hash"));
+    }
+
+    #[tokio::test]
+    async fn test_hot_keys() {
+        let db = Db::new();
+        for key in ["a", "b", "c"] {
+            db.set(key.into(), "v".into()).await;
+        }
+        for _ in 0..1000 {
+            db.get("b").await.unwrap();
+        }
+
+        let hot = db.hot_keys(2).await;
+        assert_eq!(hot.len(), 2);
+        assert_eq!(hot[0].0, "b");
+        assert!(hot[0].1 > hot[1].1);
+        // 统计本身不计为访问
+        assert_eq!(db.hot_keys(3).await[1..], [("a".to_string(), 5), ("c".to_string(), 5)]);
+    }
+}
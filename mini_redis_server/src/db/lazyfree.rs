@@ -0,0 +1,90 @@
+//! 惰性释放
+//!
+//! 释放一个包含大量元素的值（例如有数百万成员的集合）需要逐个释放元素，耗时与元素数量成正比。
+//! UNLINK、FLUSHDB / FLUSHALL ASYNC 与 maxmemory 淘汰在持锁期间只把值从存储中摘下，
+//! 由阻塞线程池在后台释放；元素较少的值释放很快，直接在当前线程释放，省去调度的开销。
+
+use super::{Db, Storage, Value};
+
+/// 释放时需要处理的元素超过该数量的值才交给后台释放，与 Redis 的 LAZYFREE_THRESHOLD 相同
+const LAZYFREE_THRESHOLD: usize = 64;
+
+/// 释放值需要处理的元素数量，字符串只有一块内存
+fn free_effort(value: &Value) -> usize {
+    match value {
+        Value::String(_) => 1,
+        Value::Hash(hash) => hash.len(),
+        Value::Set(set) => set.len(),
+        Value::ZSet(zset) => zset.len(),
+        Value::Stream(stream) => stream.len(),
+    }
+}
+
+impl<S: Storage> Db<S> {
+    /// 释放被删除的值：元素较多时交给后台释放，否则直接释放
+    pub(super) fn free_value(&self, value: Value) {
+        if free_effort(&value) > LAZYFREE_THRESHOLD {
+            self.stats.objects_lazyfreed(1);
+            tokio::task::spawn_blocking(move || drop(value));
+        }
+    }
+
+    /// 在后台释放整个存储（FLUSHDB / FLUSHALL ASYNC）
+    pub(super) fn free_storage(&self, storage: Vec<S>) {
+        let keys = storage.iter().map(Storage::len).sum::<usize>();
+        self.stats.objects_lazyfreed(keys as u64);
+        tokio::task::spawn_blocking(move || drop(storage));
+    }
+
+    /// 删除键并在后台释放它们的值（UNLINK），返回实际删除的数量
+    pub async fn unlink(&self, keys: &[String]) -> usize {
+        let mut guard = self.shards().write_many(keys).await;
+
+        let mut removed = 0;
+        for key in keys {
+            let shard = guard.shard_mut(key);
+            // 已过期但尚未清理的键不计入
+            if shard.contains_key(key) {
+                removed += 1;
+            }
+            if let Some(value) = shard.remove(key) {
+                self.free_value(value);
+            }
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::Db;
+
+    #[tokio::test]
+    async fn test_unlink() {
+        let db = Db::new();
+        let members: Vec<String> = (0..1000).map(|i| i.to_string()).collect();
+        db.sadd("big".into(), members).await.unwrap();
+        db.set("small".into(), "v".into()).await;
+
+        let keys = ["big", "small", "missing"].map(String::from);
+        assert_eq!(db.unlink(&keys).await, 2);
+        assert_eq!(db.dbsize().await, 0);
+        // 只有元素较多的值交给后台释放
+        assert_eq!(db.stats().lazyfreed_objects(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_flush_async_frees_in_background() {
+        let db = Db::new();
+        db.set("a".into(), "1".into()).await;
+        db.set("b".into(), "2".into()).await;
+
+        db.flush_all(true).await;
+        assert_eq!(db.dbsize().await, 0);
+        assert_eq!(db.stats().lazyfreed_objects(), 2);
+
+        db.set("c".into(), "3".into()).await;
+        db.flush(false).await;
+        assert_eq!(db.stats().lazyfreed_objects(), 2);
+    }
+}
@@ -0,0 +1,170 @@
+//! 读穿透加载
+//!
+//! 把 mini-redis 嵌入为缓存时，可以通过 [`Db::set_loader`] 注册一个异步加载函数：
+//! [`Db::get`]（即 GET 命令）读到不存在的键时调用它从数据源（SQL、远程服务等）取值，
+//! 取到的值按返回的过期时间写入缓存后再回复。
+//! - 同一个键同时只有一次加载，并发未命中的请求等待同一次加载的结果，避免大量请求同时打到数据源
+//! - 加载期间该键已被其他命令写入时不覆盖
+//! - 写入缓存与 `SET key value PXAT <ms>` 等价，同样传播到 AOF 与副本；副本上只返回加载的值，不写入
+//! - 数据源中也没有的键不缓存，下次未命中时重新加载
+//!
+//! 其他读命令（MGET、GETRANGE 等）与类型不是字符串的键不触发加载。
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+use tokio::sync::OnceCell;
+
+use super::{Db, Storage, TtlPolicy, Value, unix_time_ms};
+use crate::handler::BoxFuture;
+
+/// 加载函数取到的值
+#[derive(Clone, Debug, PartialEq)]
+pub struct Loaded {
+    pub value: String,
+    /// 写入缓存时设置的过期时间，`None` 表示不过期
+    pub ttl: Option<Duration>,
+}
+
+type LoadFn = dyn Fn(usize, String) -> BoxFuture<'static, Option<Loaded>> + Send + Sync;
+/// 一次加载的结果，并发未命中的请求共享
+type Pending = Arc<OnceCell<Option<Loaded>>>;
+
+/// 已注册的加载函数与进行中的加载
+#[derive(Default)]
+pub(crate) struct Loader {
+    load: RwLock<Option<Arc<LoadFn>>>,
+    /// (数据库, 键) -> 进行中的加载
+    inflight: Mutex<HashMap<(usize, String), Pending>>,
+}
+
+impl<S: Storage> Db<S> {
+    /// 注册读穿透加载函数，对所有数据库生效，替换之前注册的函数
+    ///
+    /// 函数的参数为数据库编号与键，返回 `None` 表示数据源中也没有这个键。
+    pub fn set_loader<F, Fut>(&self, load: F)
+    where
+        F: Fn(usize, String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<Loaded>> + Send + 'static,
+    {
+        let load: Arc<LoadFn> = Arc::new(move |db, key| Box::pin(load(db, key)));
+        *self.loader.load.write().unwrap() = Some(load);
+    }
+
+    /// 取消读穿透加载，进行中的加载不受影响
+    pub fn clear_loader(&self) {
+        *self.loader.load.write().unwrap() = None;
+    }
+
+    /// GET 未命中时调用：注册了加载函数时加载并写入缓存，返回加载到的值
+    pub(super) async fn load(&self, key: &str) -> Option<Vec<u8>> {
+        let load = self.loader.load.read().unwrap().clone()?;
+        let id = (self.index, key.to_string());
+        let cell = self.loader.inflight.lock().unwrap().entry(id.clone()).or_default().clone();
+
+        // 发起加载的请求被取消时，由等待中的下一个请求接着加载
+        let loaded = cell
+            .get_or_init(|| async {
+                let loaded = load(self.index, key.to_string()).await;
+                if let Some(loaded) = &loaded {
+                    self.store_loaded(key, loaded).await;
+                }
+                loaded
+            })
+            .await
+            .clone();
+
+        // 值已写入缓存，移除记录，之后的未命中（键过期或被删除后）重新加载
+        let mut inflight = self.loader.inflight.lock().unwrap();
+        if inflight.get(&id).is_some_and(|current| Arc::ptr_eq(current, &cell)) {
+            inflight.remove(&id);
+        }
+        loaded.map(|loaded| loaded.value.into_bytes())
+    }
+
+    /// 键仍不存在时写入加载到的值，并作为 SET PXAT 传播
+    async fn store_loaded(&self, key: &str, loaded: &Loaded) {
+        if self.replication.is_replica() {
+            return;
+        }
+        let _gate = self.enter_write().await;
+        let _order = self.lock_order(&[key]).await;
+        let at = loaded.ttl.map(|ttl| unix_time_ms() + ttl.as_millis() as u64);
+        {
+            let mut guard = self.shards().write(key).await;
+            if guard.contains_key(key) {
+                return;
+            }
+            let policy = at.map_or(TtlPolicy::Clear, TtlPolicy::ExpireAt);
+            policy.set(
+                &mut *guard,
+                key.to_string(),
+                Value::String(loaded.value.clone().into_bytes()),
+            );
+        }
+
+        let mut args = vec!["SET".to_string(), key.to_string(), loaded.value.clone()];
+        if let Some(at) = at {
+            args.extend(["PXAT".to_string(), at.to_string()]);
+        }
+        if let Err(e) = self.propagate(&args) {
+            tracing::warn!(error = %e, key, "failed to propagate loaded key");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_through() {
+        let db = Db::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        db.set_loader(move |db, key| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                // 模拟较慢的数据源，使并发的未命中重叠
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                key.starts_with("user:").then(|| Loaded {
+                    value: format!("{db}/{key}"),
+                    ttl: Some(Duration::from_secs(60)),
+                })
+            }
+        });
+
+        // 并发的未命中只加载一次，加载到的值写入缓存并设置过期时间
+        let gets: Vec<_> = (0..10)
+            .map(|_| {
+                let db = db.clone();
+                tokio::spawn(async move { db.get("user:1").await })
+            })
+            .collect();
+        for get in gets {
+            assert_eq!(get.await.unwrap(), Ok(Some(b"0/user:1".to_vec())));
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(db.expire_time("user:1").await.unwrap().is_some());
+        assert_eq!(db.get("user:1").await, Ok(Some(b"0/user:1".to_vec())));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // 数据源中没有的键不缓存；已存在的键不触发加载
+        assert_eq!(db.get("other").await, Ok(None));
+        assert_eq!(db.get("other").await, Ok(None));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        db.set("user:2".into(), "local".into()).await;
+        assert_eq!(db.get("user:2").await, Ok(Some(b"local".to_vec())));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let db2 = db.select(2).unwrap();
+        assert_eq!(db2.get("user:1").await, Ok(Some(b"2/user:1".to_vec())));
+        db.clear_loader();
+        assert_eq!(db.get("user:3").await, Ok(None));
+    }
+}
@@ -0,0 +1,244 @@
+//! 内存上限与近似 LRU 淘汰
+//!
+//! 设置 maxmemory 后，每条可能增加内存占用的写命令（见 [`Command::is_denyoom`]）执行前
+//! 检查存储后端估算的内存占用（见 [`Storage::used_memory`]），超出上限时按淘汰策略处理：
+//! - `noeviction`：拒绝执行，返回 OOM 错误
+//! - `allkeys-lru`：在所有键中淘汰
+//! - `volatile-lru`：只在设置了过期时间的键中淘汰
+//!
+//! 与 Redis 一样采用抽样 LRU：每次从一个分片随机抽取若干个键，淘汰其中最久未访问的一个，
+//! 直到内存占用回到上限以内。内存上限作用于所有数据库的总和，淘汰可能发生在任意数据库。
+//! 被淘汰的键以 DEL 传播到追加日志和副本。服务器的周期任务也会检查内存占用，
+//! 在调低上限后主动淘汰（见 [`Db::evict_over_limit`]）。
+//!
+//! [`Command::is_denyoom`]: crate::command::Command::is_denyoom
+
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use super::{Db, DbError, KeyEvent, Ordered, Storage};
+
+/// 每次淘汰时从分片中抽样的键数
+const EVICTION_SAMPLES: usize = 5;
+
+/// 超出 maxmemory 时的淘汰策略
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum EvictionPolicy {
+    /// 不淘汰，拒绝可能增加内存占用的写命令
+    #[default]
+    NoEviction,
+    /// 在所有键中淘汰最久未访问的键
+    AllKeysLru,
+    /// 在设置了过期时间的键中淘汰最久未访问的键
+    VolatileLru,
+}
+
+impl FromStr for EvictionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "noeviction" => Ok(EvictionPolicy::NoEviction),
+            "allkeys-lru" => Ok(EvictionPolicy::AllKeysLru),
+            "volatile-lru" => Ok(EvictionPolicy::VolatileLru),
+            _ => Err(format!("invalid maxmemory policy '{s}'")),
+        }
+    }
+}
+
+impl fmt::Display for EvictionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EvictionPolicy::NoEviction => "noeviction",
+            EvictionPolicy::AllKeysLru => "allkeys-lru",
+            EvictionPolicy::VolatileLru => "volatile-lru",
+        })
+    }
+}
+
+/// 内存上限设置
+#[derive(Default)]
+pub(crate) struct MemoryLimit {
+    /// 内存上限（字节），0 表示不限制
+    maxmemory: AtomicU64,
+    policy: Mutex<EvictionPolicy>,
+}
+
+impl<S: Storage> Db<S> {
+    /// 内存上限（字节），0 表示不限制
+    pub fn maxmemory(&self) -> u64 {
+        self.memory.maxmemory.load(Ordering::Relaxed)
+    }
+
+    /// 设置内存上限（字节），0 表示不限制
+    pub fn set_maxmemory(&self, bytes: u64) {
+        self.memory.maxmemory.store(bytes, Ordering::Relaxed);
+    }
+
+    /// 超出内存上限时的淘汰策略
+    pub fn eviction_policy(&self) -> EvictionPolicy {
+        *self.memory.policy.lock().unwrap()
+    }
+
+    /// 设置超出内存上限时的淘汰策略
+    pub fn set_eviction_policy(&self, policy: EvictionPolicy) {
+        *self.memory.policy.lock().unwrap() = policy;
+    }
+
+    /// 所有数据库所有分片估算的内存占用之和（字节）
+    ///
+    /// 逐个分片加锁统计，不是一致的快照，但不会阻塞其他分片上的命令。
+    pub async fn used_memory(&self) -> u64 {
+        let mut used = 0;
+        for shards in self.databases.iter() {
+            for i in 0..shards.len() {
+                used += shards.write_index(i).await.used_memory() as u64;
+            }
+        }
+        used
+    }
+
+    /// 内存占用超出上限时按淘汰策略腾出空间，无法腾出时返回 OOM 错误
+    ///
+    /// 调用方需要持有写门闩（见 [`Db::enter_write`]），使淘汰产生的 DEL 与写命令按顺序传播；
+    /// 调用方不能持有顺序锁（见 [`Db::lock_order`]）。
+    pub(crate) async fn free_memory(&self) -> Result<(), DbError> {
+        let maxmemory = self.maxmemory();
+        if maxmemory == 0 {
+            return Ok(());
+        }
+
+        while self.used_memory().await > maxmemory {
+            let key = match self.eviction_policy() {
+                EvictionPolicy::NoEviction => None,
+                EvictionPolicy::AllKeysLru => self.evict(false).await,
+                EvictionPolicy::VolatileLru => self.evict(true).await,
+            };
+            let Some((index, key, _order)) = key else {
+                return Err(DbError::Oom);
+            };
+            self.stats.key_evicted();
+            self.hooks.push(KeyEvent::Delete(index, key.clone()));
+            self.view(index).propagate(&["DEL".to_string(), key])?;
+        }
+        Ok(())
+    }
+
+    /// 周期任务调用：内存占用超出上限（例如调低了 maxmemory）时主动淘汰，不必等到下一条写命令
+    pub(crate) async fn evict_over_limit(&self) {
+        if self.maxmemory() == 0 || self.eviction_policy() == EvictionPolicy::NoEviction {
+            return;
+        }
+        let result = {
+            let _gate = self.enter_write().await;
+            self.free_memory().await
+        };
+        self.hooks.dispatch();
+        match result {
+            Ok(()) | Err(DbError::Oom) => {}
+            Err(e) => tracing::warn!(error = %e, "background eviction failed"),
+        }
+    }
+
+    /// 从随机选取的分片开始，淘汰抽样键中最久未访问的一个，返回它所在的数据库与键名；
+    /// 没有可淘汰的键时返回 `None`
+    ///
+    /// 同时返回键的顺序锁，调用方传播 DEL 之后再释放。
+    async fn evict(&self, volatile: bool) -> Option<(usize, String, Ordered<'_>)> {
+        let shards = self.shards().len();
+        let total = self.databases.len() * shards;
+        let start = self.rng.lock().unwrap().below(total);
+
+        for i in start..start + total {
+            let index = i / shards % self.databases.len();
+            let order = self.order.lock_index(i).await;
+            let mut shard = self.databases[index].write_index(i).await;
+            let mut oldest: Option<(u64, &String)> = None;
+            for _ in 0..EVICTION_SAMPLES {
+                let random = self.rng.lock().unwrap().next_u64();
+                let Some(key) = shard.random_key(random, volatile) else {
+                    break;
+                };
+                let last_access = shard.last_access(key).unwrap_or(0);
+                if oldest.is_none_or(|(oldest, _)| last_access < oldest) {
+                    oldest = Some((last_access, key));
+                }
+            }
+            if let Some((_, key)) = oldest {
+                let key = key.clone();
+                if let Some(value) = shard.remove(&key) {
+                    self.free_value(value);
+                }
+                return Some((index, key, order));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::unix_time_ms, handler::process_command};
+
+    #[tokio::test]
+    async fn test_noeviction_rejects_writes() {
+        let db = Db::new();
+        db.set("a".into(), "x".repeat(1000)).await;
+        db.set_maxmemory(100);
+
+        assert_eq!(process_command(&db, "set b v").await, DbError::Oom.to_string());
+        assert_eq!(process_command(&db, "get a").await, "x".repeat(1000));
+        // 删除类命令不受限制
+        assert_eq!(process_command(&db, "del a").await, "(integer) 1");
+        assert_eq!(process_command(&db, "set b v").await, "OK");
+    }
+
+    #[tokio::test]
+    async fn test_allkeys_lru_evicts_idle_keys() {
+        let db = Db::with_shards(1);
+        db.set_eviction_policy(EvictionPolicy::AllKeysLru);
+        for i in 0..4 {
+            db.set(format!("key:{i}"), "x".repeat(100)).await;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        // 抽样覆盖整个分片时，最近访问过的键不会被淘汰
+        db.get("key:0").await.unwrap();
+        db.set_maxmemory(db.used_memory().await - 1);
+
+        assert_eq!(process_command(&db, "set new v").await, "OK");
+        assert!(db.used_memory().await <= db.maxmemory());
+        assert_eq!(db.get("key:0").await, Ok(Some("x".repeat(100).into_bytes())));
+        assert_eq!(db.keys("*").await.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_volatile_lru_only_evicts_keys_with_expiry() {
+        let db = Db::with_shards(1);
+        db.set_eviction_policy(EvictionPolicy::VolatileLru);
+        db.set("persistent".into(), "x".repeat(100)).await;
+        db.set_with_expire("volatile".into(), "x".repeat(100), unix_time_ms() + 60_000).await;
+        db.set_maxmemory(db.used_memory().await - 1);
+
+        assert_eq!(process_command(&db, "set a v").await, "OK");
+        assert_eq!(db.keys("*").await, vec!["a", "persistent"]);
+
+        // 没有可淘汰的键时返回 OOM 错误
+        db.set_maxmemory(1);
+        assert_eq!(process_command(&db, "set b v").await, DbError::Oom.to_string());
+    }
+
+    #[test]
+    fn test_policy_names() {
+        for name in ["noeviction", "allkeys-lru", "volatile-lru"] {
+            assert_eq!(name.parse::<EvictionPolicy>().unwrap().to_string(), name);
+        }
+        assert!("allkeys-random".parse::<EvictionPolicy>().is_err());
+    }
+}
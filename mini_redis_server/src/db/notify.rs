@@ -0,0 +1,116 @@
+//! 阻塞命令的按键唤醒机制
+//!
+//! 阻塞命令（如 BZPOPMIN）在所有键都为空时挂起，直到其中某个键被写入。
+//! 等待方先通过 [`KeyNotifier::watch`] 在关心的键上登记自己的 `Notify`，
+//! 再检查数据；写入方在修改键后调用 [`KeyNotifier::notify`] 唤醒登记在该键上的等待方。
+//!
+//! 由于 `Notify::notify_one` 会在无人等待时保存一个许可，
+//! “先登记、后检查”的顺序保证了检查与等待之间发生的写入不会被错过。
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::Notify;
+
+/// 按键登记等待方的注册表
+#[derive(Default)]
+pub(crate) struct KeyNotifier {
+    waiters: Mutex<HashMap<String, Vec<Arc<Notify>>>>,
+}
+
+impl KeyNotifier {
+    /// 在一组键上登记等待，返回的 [`Watch`] 被丢弃时自动注销
+    pub(crate) fn watch(self: &Arc<Self>, keys: &[String]) -> Watch {
+        let notify = Arc::new(Notify::new());
+
+        let mut waiters = self.waiters.lock().unwrap();
+        for key in keys {
+            waiters.entry(key.clone()).or_default().push(notify.clone());
+        }
+
+        Watch { notifier: self.clone(), keys: keys.to_vec(), notify }
+    }
+
+    /// 唤醒所有在 `key` 上等待的一方
+    pub(crate) fn notify(&self, key: &str) {
+        let waiters = self.waiters.lock().unwrap();
+        for notify in waiters.get(key).into_iter().flatten() {
+            notify.notify_one();
+        }
+    }
+
+    /// 唤醒所有等待方（例如 SWAPDB 整体替换了数据库的内容）
+    pub(crate) fn notify_all(&self) {
+        let waiters = self.waiters.lock().unwrap();
+        for notify in waiters.values().flatten() {
+            notify.notify_one();
+        }
+    }
+}
+
+/// 一次登记的句柄
+pub(crate) struct Watch {
+    notifier: Arc<KeyNotifier>,
+    keys: Vec<String>,
+    notify: Arc<Notify>,
+}
+
+impl Watch {
+    /// 等待任意一个登记的键被写入
+    pub(crate) async fn notified(&self) {
+        self.notify.notified().await;
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        let mut waiters = self.notifier.waiters.lock().unwrap();
+        for key in &self.keys {
+            if let Some(list) = waiters.get_mut(key) {
+                list.retain(|notify| !Arc::ptr_eq(notify, &self.notify));
+                if list.is_empty() {
+                    waiters.remove(key);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::KeyNotifier;
+
+    #[tokio::test]
+    async fn test_notify_before_wait_is_not_lost() {
+        let notifier = Arc::new(KeyNotifier::default());
+        let watch = notifier.watch(&["a".into(), "b".into()]);
+
+        notifier.notify("b");
+
+        let woken = tokio::time::timeout(Duration::from_secs(1), watch.notified()).await;
+        assert!(woken.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_other_keys_do_not_wake() {
+        let notifier = Arc::new(KeyNotifier::default());
+        let watch = notifier.watch(&["a".into()]);
+
+        notifier.notify("b");
+
+        let woken = tokio::time::timeout(Duration::from_millis(20), watch.notified()).await;
+        assert!(woken.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_drop_unregisters() {
+        let notifier = Arc::new(KeyNotifier::default());
+        drop(notifier.watch(&["a".into()]));
+
+        assert!(notifier.waiters.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,139 @@
+//! 键的内部信息（OBJECT ENCODING / REFCOUNT / IDLETIME）
+//!
+//! 值的内部表示由值本身推算：与 Redis 一样，小的集合类值视为紧凑编码（listpack / intset），
+//! 超过阈值后视为哈希表或跳表；阈值取 Redis 的默认配置。
+//! 空闲时间来自存储后端记录的最近访问时间（见 [`Storage::last_access`]），
+//! 查询本身不会更新访问时间。
+
+use super::{Db, Storage, Value, unix_time_ms};
+
+/// 紧凑编码（listpack）允许的最大元素数量
+const LISTPACK_MAX_ENTRIES: usize = 128;
+/// 紧凑编码（listpack）允许的最大元素长度
+const LISTPACK_MAX_VALUE: usize = 64;
+/// 整数集合（intset）允许的最大元素数量
+const INTSET_MAX_ENTRIES: usize = 512;
+/// 以 embstr 编码保存的字符串的最大长度
+const EMBSTR_MAX_LEN: usize = 44;
+
+impl Value {
+    /// 与 Redis 对应的内部编码名称
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            Value::String(s) if str::from_utf8(s).is_ok_and(|s| s.parse::<i64>().is_ok()) => "int",
+            Value::String(s) if s.len() <= EMBSTR_MAX_LEN => "embstr",
+            Value::String(_) => "raw",
+            Value::Hash(hash) if compact(hash.len(), hash.iter().flat_map(|(f, v)| [f, v])) => {
+                "listpack"
+            }
+            Value::Hash(_) => "hashtable",
+            Value::Set(set)
+                if set.len() <= INTSET_MAX_ENTRIES
+                    && set.iter().all(|m| m.parse::<i64>().is_ok()) =>
+            {
+                "intset"
+            }
+            Value::Set(set) if compact(set.len(), set) => "listpack",
+            Value::Set(_) => "hashtable",
+            Value::ZSet(zset)
+                if zset.len() <= LISTPACK_MAX_ENTRIES
+                    && zset
+                        .range(0, -1, false)
+                        .iter()
+                        .all(|(m, _)| m.len() <= LISTPACK_MAX_VALUE) =>
+            {
+                "listpack"
+            }
+            Value::ZSet(_) => "skiplist",
+            Value::Stream(_) => "stream",
+        }
+    }
+}
+
+/// 元素数量与每个元素的长度都不超过 listpack 的阈值
+fn compact<'a>(len: usize, items: impl IntoIterator<Item = &'a String>) -> bool {
+    len <= LISTPACK_MAX_ENTRIES && items.into_iter().all(|item| item.len() <= LISTPACK_MAX_VALUE)
+}
+
+impl<S: Storage> Db<S> {
+    /// 键的内部编码（OBJECT ENCODING），键不存在时返回 `None`
+    pub async fn object_encoding(&self, key: &str) -> Option<&'static str> {
+        let guard = self.shards().read(key).await;
+
+        guard.peek(key).map(Value::encoding)
+    }
+
+    /// 值的引用计数（OBJECT REFCOUNT），值不会被多个键共享，存在的键总是 1
+    pub async fn object_refcount(&self, key: &str) -> Option<u64> {
+        let guard = self.shards().read(key).await;
+
+        guard.contains_key(key).then_some(1)
+    }
+
+    /// 键自最近一次访问以来的空闲秒数（OBJECT IDLETIME），键不存在时返回 `None`
+    ///
+    /// 存储后端不记录访问时间时视为刚刚访问过。
+    pub async fn object_idletime(&self, key: &str) -> Option<u64> {
+        let guard = self.shards().read(key).await;
+
+        if !guard.contains_key(key) {
+            return None;
+        }
+        let last_access = guard.last_access(key).unwrap_or_else(unix_time_ms);
+        Some(unix_time_ms().saturating_sub(last_access) / 1000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use crate::{
+        db::{Db, Value},
+        sorted_set::SortedSet,
+        stream::Stream,
+    };
+
+    #[test]
+    fn test_encoding() {
+        let string = |s: &str| Value::String(s.into());
+        assert_eq!(string("12345").encoding(), "int");
+        assert_eq!(string("hello").encoding(), "embstr");
+        assert_eq!(string(&"x".repeat(45)).encoding(), "raw");
+
+        let hash = |n: usize, len: usize| {
+            Value::Hash((0..n).map(|i| (i.to_string(), "v".repeat(len))).collect::<HashMap<_, _>>())
+        };
+        assert_eq!(hash(10, 1).encoding(), "listpack");
+        assert_eq!(hash(129, 1).encoding(), "hashtable");
+        assert_eq!(hash(1, 65).encoding(), "hashtable");
+
+        let set = |members: Vec<String>| Value::Set(members.into_iter().collect::<HashSet<_>>());
+        assert_eq!(set((0..600).map(|i| i.to_string()).collect()).encoding(), "hashtable");
+        assert_eq!(set((0..500).map(|i| i.to_string()).collect()).encoding(), "intset");
+        assert_eq!(set(vec!["a".into()]).encoding(), "listpack");
+
+        let mut zset = SortedSet::new();
+        zset.insert("a".into(), 1.0);
+        assert_eq!(Value::ZSet(zset.clone()).encoding(), "listpack");
+        zset.insert("x".repeat(65), 2.0);
+        assert_eq!(Value::ZSet(zset).encoding(), "skiplist");
+        assert_eq!(Value::Stream(Stream::new()).encoding(), "stream");
+    }
+
+    #[tokio::test]
+    async fn test_object_idletime_does_not_touch() {
+        let db = Db::new();
+        db.set("a".into(), "1".into()).await;
+        assert_eq!(db.object_idletime("a").await, Some(0));
+        assert_eq!(db.object_refcount("a").await, Some(1));
+        assert_eq!(db.object_encoding("missing").await, None);
+        assert_eq!(db.object_idletime("missing").await, None);
+
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        assert_eq!(db.object_idletime("a").await, Some(1));
+        assert_eq!(db.object_idletime("a").await, Some(1));
+        db.get("a").await.unwrap();
+        assert_eq!(db.object_idletime("a").await, Some(0));
+    }
+}
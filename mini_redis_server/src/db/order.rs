@@ -0,0 +1,85 @@
+//! 写命令的传播顺序
+//!
+//! 写命令在分片锁内修改数据，释放分片锁之后才传播到追加日志与副本；
+//! 如果只靠分片锁，修改同一个键的两条写命令可能以与执行相反的顺序传播，回放的结果与实际不同。
+//!
+//! 每个分片编号对应一把顺序锁，所有数据库共用：修改数据的一方从执行到传播完成一直持有
+//! 涉及的键的顺序锁，同一个键上的修改与传播因此按相同的顺序发生。
+//! 加锁顺序为写门闩 → 顺序锁（按编号升序）→ 分片锁，持有分片锁时不能等待顺序锁。
+
+use tokio::sync::{Mutex, MutexGuard};
+
+use super::shards;
+
+pub(crate) struct KeyOrder {
+    locks: Box<[Mutex<()>]>,
+}
+
+impl KeyOrder {
+    /// 创建 `count` 把顺序锁（至少一把），与每个数据库的分片数量相同
+    pub(crate) fn new(count: usize) -> Self {
+        Self { locks: (0..count.max(1)).map(|_| Mutex::default()).collect() }
+    }
+
+    /// 锁住多个键的顺序锁，`keys` 为空时锁住全部
+    pub(crate) async fn lock<K: AsRef<str>>(&self, keys: &[K]) -> Ordered<'_> {
+        if keys.is_empty() {
+            return self.lock_all().await;
+        }
+        let mut indices: Vec<_> =
+            keys.iter().map(|key| shards::index(key.as_ref(), self.locks.len())).collect();
+        indices.sort_unstable();
+        indices.dedup();
+
+        let mut guards = Vec::with_capacity(indices.len());
+        for i in indices {
+            guards.push(self.locks[i].lock().await);
+        }
+        Ordered { _guards: guards }
+    }
+
+    /// 锁住分片编号为 `i` 的顺序锁，`i` 对数量取模；覆盖所有数据库中该编号分片上的键
+    pub(crate) async fn lock_index(&self, i: usize) -> Ordered<'_> {
+        Ordered { _guards: vec![self.locks[i % self.locks.len()].lock().await] }
+    }
+
+    /// 锁住全部顺序锁，用于不指定键或修改整个数据库的命令
+    pub(crate) async fn lock_all(&self) -> Ordered<'_> {
+        let mut guards = Vec::with_capacity(self.locks.len());
+        for lock in self.locks.iter() {
+            guards.push(lock.lock().await);
+        }
+        Ordered { _guards: guards }
+    }
+}
+
+/// 持有的一组顺序锁，销毁时释放
+pub(crate) struct Ordered<'a> {
+    _guards: Vec<MutexGuard<'a, ()>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_same_shard_is_serialized() {
+        let order = KeyOrder::new(4);
+        let held = order.lock(&["a"]).await;
+        let wait = Duration::from_millis(10);
+
+        // 同一个键、全部锁都要等待，其他分片上的键不受影响
+        let other = (0..64)
+            .map(|i| format!("key:{i}"))
+            .find(|key| shards::index(key, 4) != shards::index("a", 4))
+            .unwrap();
+        assert!(tokio::time::timeout(wait, order.lock(&[other])).await.is_ok());
+        assert!(tokio::time::timeout(wait, order.lock(&["a"])).await.is_err());
+        assert!(tokio::time::timeout(wait, order.lock::<&str>(&[])).await.is_err());
+
+        drop(held);
+        assert!(tokio::time::timeout(wait, order.lock_all()).await.is_ok());
+    }
+}
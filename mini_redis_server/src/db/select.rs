@@ -0,0 +1,144 @@
+//! 多个逻辑数据库（SELECT / MOVE / SWAPDB）
+//!
+//! [`Db`] 内含若干个编号从 0 开始的数据库，数量由 `databases` 配置决定。
+//! 每个 `Db` 句柄都指向其中一个数据库，键操作只作用于这个数据库；
+//! [`Db::select`] 返回指向另一个数据库的句柄，客户端会话据此实现 SELECT。
+//!
+//! 写命令传播时带上数据库编号（见 [`Db::propagate`]），回放与复制按 SELECT 切换数据库。
+//!
+//! 跨数据库的操作按数据库编号升序加锁，同一数据库内再按分片编号升序，因此不会死锁。
+
+use std::mem;
+
+use super::{Db, DbError, Storage, TtlPolicy};
+
+/// 默认的数据库数量
+pub const DEFAULT_DATABASES: usize = 16;
+
+impl<S: Storage> Db<S> {
+    /// 当前选中的数据库编号
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// 数据库数量
+    pub fn database_count(&self) -> usize {
+        self.databases.len()
+    }
+
+    /// 返回指向第 `index` 个数据库的句柄，与当前句柄共享所有状态
+    pub fn select(&self, index: usize) -> Result<Self, DbError> {
+        self.check_index(index)?;
+        Ok(self.view(index))
+    }
+
+    /// 指向第 `index` 个数据库的句柄，调用方保证编号有效
+    pub(super) fn view(&self, index: usize) -> Self {
+        Self { index, ..self.clone() }
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), DbError> {
+        if index < self.databases.len() { Ok(()) } else { Err(DbError::DbIndexOutOfRange) }
+    }
+
+    /// 把键移动到另一个数据库（MOVE），键不存在或目标数据库已有同名键时返回 `false`
+    pub async fn move_key(&self, key: &str, index: usize) -> Result<bool, DbError> {
+        self.check_index(index)?;
+        if index == self.index {
+            return Err(DbError::SameObject);
+        }
+
+        let target = &self.databases[index];
+        let (mut source, mut target) = if self.index < index {
+            let source = self.shards().write(key).await;
+            (source, target.write(key).await)
+        } else {
+            let target = target.write(key).await;
+            (self.shards().write(key).await, target)
+        };
+        if !source.contains_key(key) || target.contains_key(key) {
+            return Ok(false);
+        }
+
+        let expire_at = source.expire_at(key);
+        let value = source.remove(key).expect("key exists");
+        let policy = expire_at.map_or(TtlPolicy::Clear, TtlPolicy::ExpireAt);
+        policy.set(&mut *target, key.to_string(), value);
+        self.notifier.notify(key);
+        Ok(true)
+    }
+
+    /// 交换两个数据库的全部内容（SWAPDB），已选中这两个数据库的客户端立即看到对方的数据
+    pub async fn swap_db(&self, a: usize, b: usize) -> Result<(), DbError> {
+        self.check_index(a)?;
+        self.check_index(b)?;
+        if a == b {
+            return Ok(());
+        }
+
+        let mut first = self.databases[a.min(b)].write_all().await;
+        let mut second = self.databases[a.max(b)].write_all().await;
+        for (x, y) in first.iter_mut().zip(second.iter_mut()) {
+            mem::swap(x, y);
+        }
+        // 阻塞在这两个数据库上的命令需要重新检查各自的键
+        self.notifier.notify_all();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{Db, DbError, unix_time_ms};
+
+    #[tokio::test]
+    async fn test_select_isolates_keys() {
+        let db = Db::new();
+        let other = db.select(1).unwrap();
+
+        db.set("a".into(), "0".into()).await;
+        other.set("a".into(), "1".into()).await;
+        assert_eq!(db.get("a").await, Ok(Some("0".into())));
+        assert_eq!(other.get("a").await, Ok(Some("1".into())));
+        assert_eq!(other.index(), 1);
+        assert_eq!(db.select(16).err(), Some(DbError::DbIndexOutOfRange));
+
+        other.flush(false).await;
+        assert_eq!(db.dbsize().await, 1);
+        db.flush_all(false).await;
+        assert_eq!(db.dbsize().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_move_key() {
+        let db = Db::new();
+        let other = db.select(2).unwrap();
+        let at = unix_time_ms() + 60_000;
+
+        db.set_with_expire("a".into(), "1".into(), at).await;
+        assert_eq!(db.move_key("a", 2).await, Ok(true));
+        assert_eq!(db.get("a").await, Ok(None));
+        assert_eq!(other.get("a").await, Ok(Some("1".into())));
+        assert_eq!(other.expire_time("a").await, Some(Some(at)));
+
+        // 目标已存在或源不存在时不移动
+        db.set("a".into(), "0".into()).await;
+        assert_eq!(db.move_key("a", 2).await, Ok(false));
+        assert_eq!(db.move_key("missing", 2).await, Ok(false));
+        assert_eq!(other.move_key("a", 0).await, Ok(false));
+        assert_eq!(db.move_key("a", 0).await, Err(DbError::SameObject));
+    }
+
+    #[tokio::test]
+    async fn test_swap_db() {
+        let db = Db::new();
+        let other = db.select(1).unwrap();
+        db.set("a".into(), "0".into()).await;
+        other.set("b".into(), "1".into()).await;
+
+        db.swap_db(0, 1).await.unwrap();
+        assert_eq!(db.keys("*").await, vec!["b"]);
+        assert_eq!(other.keys("*").await, vec!["a"]);
+        assert_eq!(db.swap_db(0, 99).await, Err(DbError::DbIndexOutOfRange));
+    }
+}
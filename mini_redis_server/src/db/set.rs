@@ -0,0 +1,434 @@
+//! 集合类型操作
+//!
+//! 集合值以 `HashSet<String>` 形式保存在 [`Value::Set`] 中。
+//! 与 Redis 一致，集合被删空后对应的键也会被删除。
+//!
+//! 交集、并集、差集运算先锁住涉及的全部分片再计算，`*STORE` 变体对源键与目标键所在的分片
+//! 一次性加写锁，保证“计算 + 写入目标键”这一过程是原子的。
+
+use std::{
+    collections::{BinaryHeap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+    ops::Deref,
+};
+
+use super::{Db, DbError, Storage, Value, shards::Locked};
+use crate::random;
+
+/// 取出键对应的集合，键不存在时创建一个空集合
+fn set_mut<S: Storage>(map: &mut S, key: String) -> Result<&mut HashSet<String>, DbError> {
+    match map.get_or_insert_with(key, || Value::Set(HashSet::new())) {
+        Value::Set(set) => Ok(set),
+        _ => Err(DbError::WrongType),
+    }
+}
+
+/// 以只读方式取出键对应的集合，键不存在时返回 `None`
+fn set_ref<'a, S: Storage>(map: &'a S, key: &str) -> Result<Option<&'a HashSet<String>>, DbError> {
+    match map.get(key) {
+        Some(Value::Set(set)) => Ok(Some(set)),
+        Some(_) => Err(DbError::WrongType),
+        None => Ok(None),
+    }
+}
+
+/// 不重复地随机选出 `count` 个成员
+///
+/// 以 `salt` 为盐对每个成员求哈希，取哈希值最小的 `count` 个：只需遍历一次集合，
+/// 不复制也不排序整个集合，且结果只取决于 `salt` 与集合内容，与 `HashSet` 的遍历顺序无关，
+/// 因此相同种子得到相同结果。
+fn sample_members(set: &HashSet<String>, count: usize, salt: u64) -> Vec<String> {
+    if count == 0 {
+        return Vec::new();
+    }
+
+    let mut heap = BinaryHeap::with_capacity(count + 1);
+    for member in set {
+        let mut hasher = DefaultHasher::new();
+        (salt, member).hash(&mut hasher);
+        heap.push((hasher.finish(), member));
+        if heap.len() > count {
+            heap.pop();
+        }
+    }
+    heap.into_sorted_vec().into_iter().map(|(_, member)| member.clone()).collect()
+}
+
+/// 集合运算类型
+#[derive(Clone, Copy)]
+enum SetOp {
+    /// 交集
+    Inter,
+    /// 并集
+    Union,
+    /// 差集：第一个集合减去其余集合
+    Diff,
+}
+
+/// 对多个键对应的集合执行集合运算，不存在的键视为空集合
+fn combine<G: Deref<Target: Storage>>(
+    locked: &Locked<G>,
+    op: SetOp,
+    keys: &[String],
+) -> Result<HashSet<String>, DbError> {
+    let sets =
+        keys.iter().map(|key| set_ref(locked.shard(key), key)).collect::<Result<Vec<_>, _>>()?;
+    let empty = HashSet::new();
+    let mut sets = sets.into_iter().map(|set| set.unwrap_or(&empty));
+
+    let Some(first) = sets.next() else {
+        return Ok(HashSet::new());
+    };
+    let mut result = first.clone();
+    for set in sets {
+        match op {
+            SetOp::Inter => result.retain(|member| set.contains(member)),
+            SetOp::Union => result.extend(set.iter().cloned()),
+            SetOp::Diff => result.retain(|member| !set.contains(member)),
+        }
+    }
+    Ok(result)
+}
+
+/// 将集合转换为按字典序排列的成员列表
+fn sorted(set: HashSet<String>) -> Vec<String> {
+    let mut members: Vec<_> = set.into_iter().collect();
+    members.sort();
+    members
+}
+
+impl<S: Storage> Db<S> {
+    /// 向集合中添加成员，返回新增成员的数量
+    pub async fn sadd(&self, key: String, members: Vec<String>) -> Result<usize, DbError> {
+        let mut guard = self.shards().write(&key).await;
+        let set = set_mut(&mut *guard, key)?;
+
+        Ok(members.into_iter().filter(|member| set.insert(member.clone())).count())
+    }
+
+    /// 从集合中移除成员，返回实际移除的数量
+    pub async fn srem(&self, key: &str, members: &[String]) -> Result<usize, DbError> {
+        let mut guard = self.shards().write(key).await;
+        let Some(Value::Set(set)) = guard.get_mut(key) else {
+            return set_ref(&*guard, key).map(|_| 0);
+        };
+
+        let removed = members.iter().filter(|member| set.remove(*member)).count();
+        if set.is_empty() {
+            guard.remove(key);
+        }
+        Ok(removed)
+    }
+
+    /// 返回集合的全部成员（按字典序排列）
+    pub async fn smembers(&self, key: &str) -> Result<Vec<String>, DbError> {
+        let guard = self.shards().read(key).await;
+
+        let mut members: Vec<_> =
+            self.lookup(set_ref(&*guard, key))?.into_iter().flatten().cloned().collect();
+        members.sort();
+        Ok(members)
+    }
+
+    /// 判断成员是否在集合中
+    pub async fn sismember(&self, key: &str, member: &str) -> Result<bool, DbError> {
+        let guard = self.shards().read(key).await;
+
+        Ok(self.lookup(set_ref(&*guard, key))?.is_some_and(|set| set.contains(member)))
+    }
+
+    /// 返回集合的成员数量
+    pub async fn scard(&self, key: &str) -> Result<usize, DbError> {
+        let guard = self.shards().read(key).await;
+
+        Ok(self.lookup(set_ref(&*guard, key))?.map_or(0, HashSet::len))
+    }
+
+    /// 判断多个成员是否在集合中，按参数顺序返回结果
+    pub async fn smismember(&self, key: &str, members: &[String]) -> Result<Vec<bool>, DbError> {
+        let guard = self.shards().read(key).await;
+        let set = self.lookup(set_ref(&*guard, key))?;
+
+        Ok(members.iter().map(|member| set.is_some_and(|set| set.contains(member))).collect())
+    }
+
+    /// 随机移除并返回最多 `count` 个互不相同的成员，集合被删空时删除键
+    pub async fn spop(&self, key: &str, count: usize) -> Result<Vec<String>, DbError> {
+        let mut guard = self.shards().write(key).await;
+        let Some(Value::Set(set)) = guard.get_mut(key) else {
+            return set_ref(&*guard, key).map(|_| Vec::new());
+        };
+
+        if count >= set.len() {
+            let popped = sorted(std::mem::take(set));
+            guard.remove(key);
+            return Ok(popped);
+        }
+
+        let salt = self.rng.lock().unwrap().next_u64();
+        let popped = sample_members(set, count, salt);
+        for member in &popped {
+            set.remove(member);
+        }
+        Ok(popped)
+    }
+
+    /// 随机返回集合成员，`count` 的语义与 [`Db::hrandfield`] 相同
+    pub async fn srandmember(&self, key: &str, count: Option<i64>) -> Result<Vec<String>, DbError> {
+        if !random::count_in_range(count) {
+            return Err(DbError::OutOfRange);
+        }
+
+        let guard = self.shards().read(key).await;
+        let Some(set) = self.lookup(set_ref(&*guard, key))? else {
+            return Ok(Vec::new());
+        };
+
+        let mut members: Vec<_> = set.iter().collect();
+        members.sort();
+
+        let indices = self.rng.lock().unwrap().sample_with_count(members.len(), count);
+        Ok(indices.into_iter().map(|i| members[i].clone()).collect())
+    }
+
+    /// 返回多个集合的交集
+    pub async fn sinter(&self, keys: &[String]) -> Result<Vec<String>, DbError> {
+        let guard = self.shards().read_many(keys).await;
+        combine(&guard, SetOp::Inter, keys).map(sorted)
+    }
+
+    /// 返回多个集合的并集
+    pub async fn sunion(&self, keys: &[String]) -> Result<Vec<String>, DbError> {
+        let guard = self.shards().read_many(keys).await;
+        combine(&guard, SetOp::Union, keys).map(sorted)
+    }
+
+    /// 返回第一个集合与其余集合的差集
+    pub async fn sdiff(&self, keys: &[String]) -> Result<Vec<String>, DbError> {
+        let guard = self.shards().read_many(keys).await;
+        combine(&guard, SetOp::Diff, keys).map(sorted)
+    }
+
+    /// 计算交集并保存到 `dest`，返回结果集合的成员数量
+    pub async fn sinterstore(&self, dest: String, keys: &[String]) -> Result<usize, DbError> {
+        self.store(SetOp::Inter, dest, keys).await
+    }
+
+    /// 计算并集并保存到 `dest`，返回结果集合的成员数量
+    pub async fn sunionstore(&self, dest: String, keys: &[String]) -> Result<usize, DbError> {
+        self.store(SetOp::Union, dest, keys).await
+    }
+
+    /// 计算差集并保存到 `dest`，返回结果集合的成员数量
+    pub async fn sdiffstore(&self, dest: String, keys: &[String]) -> Result<usize, DbError> {
+        self.store(SetOp::Diff, dest, keys).await
+    }
+
+    /// 返回交集的成员数量，`limit` 不为 0 时数到 `limit` 即停止
+    pub async fn sintercard(&self, keys: &[String], limit: usize) -> Result<usize, DbError> {
+        let guard = self.shards().read_many(keys).await;
+        let sets =
+            keys.iter().map(|key| set_ref(guard.shard(key), key)).collect::<Result<Vec<_>, _>>()?;
+        let Some(sets) = sets.into_iter().collect::<Option<Vec<_>>>() else {
+            return Ok(0);
+        };
+
+        // 从最小的集合出发逐个检查成员，满足 limit 后提前结束
+        let Some(smallest) = sets.iter().min_by_key(|set| set.len()) else {
+            return Ok(0);
+        };
+        let common = smallest.iter().filter(|member| sets.iter().all(|set| set.contains(*member)));
+
+        Ok(if limit == 0 { common.count() } else { common.take(limit).count() })
+    }
+
+    /// 在同一组写锁内完成集合运算与结果写入，结果为空时删除 `dest`
+    async fn store(&self, op: SetOp, dest: String, keys: &[String]) -> Result<usize, DbError> {
+        let locked_keys: Vec<_> = keys.iter().chain([&dest]).collect();
+        let mut guard = self.shards().write_many(&locked_keys).await;
+        let result = combine(&guard, op, keys)?;
+        let len = result.len();
+
+        let shard = guard.shard_mut(&dest);
+        if result.is_empty() {
+            shard.remove(&dest);
+        } else {
+            shard.set(dest, Value::Set(result));
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::sample_members;
+    use crate::db::{Db, DbError};
+
+    fn members(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_sadd_smembers() {
+        let db = Db::new();
+
+        assert_eq!(db.sadd("s".into(), members(&["b", "a", "b"])).await, Ok(2));
+        assert_eq!(db.sadd("s".into(), members(&["a", "c"])).await, Ok(1));
+
+        assert_eq!(db.smembers("s").await, Ok(members(&["a", "b", "c"])));
+        assert_eq!(db.smembers("missing").await, Ok(vec![]));
+        assert_eq!(db.scard("s").await, Ok(3));
+        assert_eq!(db.scard("missing").await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn test_sismember() {
+        let db = Db::new();
+        db.sadd("s".into(), members(&["a"])).await.unwrap();
+
+        assert_eq!(db.sismember("s", "a").await, Ok(true));
+        assert_eq!(db.sismember("s", "b").await, Ok(false));
+        assert_eq!(db.sismember("missing", "a").await, Ok(false));
+    }
+
+    #[tokio::test]
+    async fn test_srem_removes_empty_set() {
+        let db = Db::new();
+        db.sadd("s".into(), members(&["a", "b"])).await.unwrap();
+
+        assert_eq!(db.srem("s", &members(&["a", "x"])).await, Ok(1));
+        assert_eq!(db.srem("s", &members(&["b"])).await, Ok(1));
+        assert_eq!(db.srem("missing", &members(&["a"])).await, Ok(0));
+
+        // 集合被删空后键不再存在，可以被其他类型的值复用
+        db.set("s".into(), "v".into()).await;
+        assert_eq!(db.get("s").await, Ok(Some("v".into())));
+    }
+
+    async fn setup() -> Db {
+        let db = Db::new();
+        db.sadd("a".into(), members(&["1", "2", "3", "4"])).await.unwrap();
+        db.sadd("b".into(), members(&["3", "4", "5"])).await.unwrap();
+        db.sadd("c".into(), members(&["4", "6"])).await.unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn test_sinter_sunion_sdiff() {
+        let db = setup().await;
+
+        assert_eq!(db.sinter(&members(&["a", "b", "c"])).await, Ok(members(&["4"])));
+        assert_eq!(db.sinter(&members(&["a", "missing"])).await, Ok(vec![]));
+        assert_eq!(db.sunion(&members(&["b", "c"])).await, Ok(members(&["3", "4", "5", "6"])));
+        assert_eq!(db.sdiff(&members(&["a", "b"])).await, Ok(members(&["1", "2"])));
+        assert_eq!(db.sdiff(&members(&["a", "missing"])).await, Ok(members(&["1", "2", "3", "4"])));
+    }
+
+    #[tokio::test]
+    async fn test_store_variants() {
+        let db = setup().await;
+
+        assert_eq!(db.sinterstore("dest".into(), &members(&["a", "b"])).await, Ok(2));
+        assert_eq!(db.smembers("dest").await, Ok(members(&["3", "4"])));
+
+        assert_eq!(db.sunionstore("dest".into(), &members(&["c", "dest"])).await, Ok(3));
+        assert_eq!(db.smembers("dest").await, Ok(members(&["3", "4", "6"])));
+
+        assert_eq!(db.sdiffstore("dest".into(), &members(&["c", "a"])).await, Ok(1));
+        assert_eq!(db.smembers("dest").await, Ok(members(&["6"])));
+
+        // 空结果会删除目标键，即使目标键原本是其他类型
+        db.set("str".into(), "v".into()).await;
+        assert_eq!(db.sinterstore("str".into(), &members(&["a", "missing"])).await, Ok(0));
+        assert_eq!(db.get("str").await, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn test_sintercard() {
+        let db = setup().await;
+
+        assert_eq!(db.sintercard(&members(&["a", "b"]), 0).await, Ok(2));
+        assert_eq!(db.sintercard(&members(&["a", "b"]), 1).await, Ok(1));
+        assert_eq!(db.sintercard(&members(&["a", "missing"]), 0).await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn test_smismember() {
+        let db = setup().await;
+
+        assert_eq!(
+            db.smismember("a", &members(&["1", "9", "4"])).await,
+            Ok(vec![true, false, true])
+        );
+        assert_eq!(db.smismember("missing", &members(&["1"])).await, Ok(vec![false]));
+    }
+
+    #[tokio::test]
+    async fn test_spop() {
+        let db = setup().await;
+
+        let popped = db.spop("a", 3).await.unwrap();
+        assert_eq!(popped.len(), 3);
+        assert_eq!(db.scard("a").await, Ok(1));
+        for member in &popped {
+            assert_eq!(db.sismember("a", member).await, Ok(false));
+        }
+
+        assert_eq!(db.spop("a", 5).await.unwrap().len(), 1);
+        assert_eq!(db.spop("a", 1).await, Ok(vec![]));
+        assert_eq!(db.sadd("a".into(), members(&["x"])).await, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn test_srandmember() {
+        let db = setup().await;
+
+        assert_eq!(db.srandmember("a", None).await.unwrap().len(), 1);
+        assert_eq!(db.srandmember("a", Some(10)).await.unwrap().len(), 4);
+        assert_eq!(db.srandmember("a", Some(-10)).await.unwrap().len(), 10);
+        assert_eq!(db.srandmember("missing", Some(-10)).await, Ok(vec![]));
+        assert_eq!(db.srandmember("a", Some(i64::MIN)).await, Err(DbError::OutOfRange));
+        assert_eq!(db.scard("a").await, Ok(4));
+    }
+
+    #[tokio::test]
+    async fn test_spop_seeded() {
+        let pop = |seed| async move {
+            let db = Db::with_seed(seed);
+            db.sadd("s".into(), (0..20).map(|i| i.to_string()).collect()).await.unwrap();
+            db.spop("s", 5).await.unwrap()
+        };
+
+        assert_eq!(pop(3).await, pop(3).await);
+    }
+
+    #[test]
+    fn test_sample_members() {
+        let set: HashSet<_> = (0..20).map(|i| i.to_string()).collect();
+
+        let mut sample = sample_members(&set, 5, 42);
+        assert_eq!(sample.len(), 5);
+        sample.sort();
+        sample.dedup();
+        assert_eq!(sample.len(), 5);
+        assert!(sample.iter().all(|member| set.contains(member)));
+        assert!(sample_members(&set, 0, 42).is_empty());
+
+        // 结果与遍历顺序无关
+        let rebuilt: HashSet<_> = (0..20).rev().map(|i| i.to_string()).collect();
+        assert_eq!(sample_members(&set, 5, 7), sample_members(&rebuilt, 5, 7));
+    }
+
+    #[tokio::test]
+    async fn test_set_wrong_type() {
+        let db = Db::new();
+        db.set("str".into(), "v".into()).await;
+
+        assert_eq!(db.sadd("str".into(), members(&["a"])).await, Err(DbError::WrongType));
+        assert_eq!(db.srem("str", &members(&["a"])).await, Err(DbError::WrongType));
+        assert_eq!(db.scard("str").await, Err(DbError::WrongType));
+        assert_eq!(db.sunion(&members(&["missing", "str"])).await, Err(DbError::WrongType));
+    }
+}
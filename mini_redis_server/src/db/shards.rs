@@ -0,0 +1,208 @@
+//! 分片键空间（锁分段）
+//!
+//! 键按哈希值分布到固定数量的分片中，每个分片是一个独立加锁的存储后端（[`Storage`]），
+//! 访问不同分片的命令可以并发执行。
+//!
+//! - 单键操作只锁住键所在的分片
+//! - 多键操作通过 [`Shards::read_many`] / [`Shards::write_many`] 一次性锁住涉及的全部分片，
+//!   所有多分片加锁都按分片编号升序进行，因此不会死锁
+//! - 需要一致视图的全量操作（KEYS、快照、恢复）锁住所有分片
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+};
+
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use super::Storage;
+
+/// 默认的分片数量
+pub const DEFAULT_SHARDS: usize = 16;
+
+pub(crate) struct Shards<S> {
+    shards: Box<[RwLock<S>]>,
+}
+
+impl<S: Storage> Default for Shards<S> {
+    fn default() -> Self {
+        Self::new(DEFAULT_SHARDS)
+    }
+}
+
+impl<S: Storage> Shards<S> {
+    /// 创建 `count` 个分片（至少一个）
+    pub(crate) fn new(count: usize) -> Self {
+        Self { shards: (0..count.max(1)).map(|_| RwLock::default()).collect() }
+    }
+
+    /// 分片数量
+    pub(crate) fn len(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// 对键所在的分片加读锁
+    pub(crate) async fn read(&self, key: &str) -> RwLockReadGuard<'_, S> {
+        self.shards[index(key, self.len())].read().await
+    }
+
+    /// 对键所在的分片加写锁
+    pub(crate) async fn write(&self, key: &str) -> RwLockWriteGuard<'_, S> {
+        self.shards[index(key, self.len())].write().await
+    }
+
+    /// 按编号对分片加读锁，`i` 对分片数量取模
+    pub(crate) async fn read_index(&self, i: usize) -> RwLockReadGuard<'_, S> {
+        self.shards[i % self.len()].read().await
+    }
+
+    /// 按编号对分片加写锁，`i` 对分片数量取模
+    pub(crate) async fn write_index(&self, i: usize) -> RwLockWriteGuard<'_, S> {
+        self.shards[i % self.len()].write().await
+    }
+
+    /// 对多个键所在的分片加读锁
+    pub(crate) async fn read_many<K: AsRef<str>>(
+        &self,
+        keys: &[K],
+    ) -> Locked<RwLockReadGuard<'_, S>> {
+        let mut guards = Vec::new();
+        for i in self.indices(keys) {
+            guards.push((i, self.shards[i].read().await));
+        }
+        Locked { count: self.len(), guards }
+    }
+
+    /// 对多个键所在的分片加写锁
+    pub(crate) async fn write_many<K: AsRef<str>>(
+        &self,
+        keys: &[K],
+    ) -> Locked<RwLockWriteGuard<'_, S>> {
+        let mut guards = Vec::new();
+        for i in self.indices(keys) {
+            guards.push((i, self.shards[i].write().await));
+        }
+        Locked { count: self.len(), guards }
+    }
+
+    /// 对所有分片加读锁
+    pub(crate) async fn read_all(&self) -> Locked<RwLockReadGuard<'_, S>> {
+        let mut guards = Vec::with_capacity(self.len());
+        for (i, shard) in self.shards.iter().enumerate() {
+            guards.push((i, shard.read().await));
+        }
+        Locked { count: self.len(), guards }
+    }
+
+    /// 对所有分片加写锁
+    pub(crate) async fn write_all(&self) -> Locked<RwLockWriteGuard<'_, S>> {
+        let mut guards = Vec::with_capacity(self.len());
+        for (i, shard) in self.shards.iter().enumerate() {
+            guards.push((i, shard.write().await));
+        }
+        Locked { count: self.len(), guards }
+    }
+
+    /// 键所在分片的编号，升序去重
+    fn indices<K: AsRef<str>>(&self, keys: &[K]) -> Vec<usize> {
+        let mut indices: Vec<_> = keys.iter().map(|key| index(key.as_ref(), self.len())).collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+}
+
+/// 同时锁住的一组分片，按键找到对应的分片
+pub(crate) struct Locked<G> {
+    count: usize,
+    guards: Vec<(usize, G)>,
+}
+
+impl<G: Deref> Locked<G> {
+    /// 键所在的分片，该分片必须已被锁住
+    pub(crate) fn shard(&self, key: &str) -> &G::Target {
+        let i = index(key, self.count);
+        let (_, guard) = self.guards.iter().find(|(j, _)| *j == i).expect("shard is not locked");
+        guard
+    }
+
+    /// 遍历所有已锁住的分片
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &G::Target> {
+        self.guards.iter().map(|(_, guard)| guard.deref())
+    }
+}
+
+impl<G: DerefMut> Locked<G> {
+    /// 以可变方式取出键所在的分片，该分片必须已被锁住
+    pub(crate) fn shard_mut(&mut self, key: &str) -> &mut G::Target {
+        let i = index(key, self.count);
+        let (_, guard) =
+            self.guards.iter_mut().find(|(j, _)| *j == i).expect("shard is not locked");
+        guard
+    }
+
+    /// 以可变方式遍历所有已锁住的分片
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut G::Target> {
+        self.guards.iter_mut().map(|(_, guard)| guard.deref_mut())
+    }
+}
+
+/// 键所属的分片编号
+pub(super) fn index(key: &str, count: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    (hasher.finish() % count as u64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Keyspace, Value};
+
+    #[tokio::test]
+    async fn test_keys_spread_across_shards() {
+        let shards = Shards::<Keyspace>::new(4);
+        let keys: Vec<_> = (0..64).map(|i| format!("key:{i}")).collect();
+
+        let mut used: Vec<_> = keys.iter().map(|key| index(key, 4)).collect();
+        used.sort_unstable();
+        used.dedup();
+        assert_eq!(used, vec![0, 1, 2, 3]);
+
+        let mut locked = shards.write_many(&keys).await;
+        for key in &keys {
+            locked.shard_mut(key).set(key.clone(), Value::String("v".into()));
+        }
+        drop(locked);
+
+        assert!(shards.read(&keys[0]).await.contains_key(&keys[0]));
+        let total: usize = shards.read_all().await.iter().map(|shard| shard.keys().count()).sum();
+        assert_eq!(total, 64);
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_multi_key_locks() {
+        let shards = std::sync::Arc::new(Shards::<Keyspace>::new(8));
+        let keys: Vec<_> = (0..16).map(|i| format!("k{i}")).collect();
+
+        // 不同顺序的多键加锁并发执行也不会死锁
+        let tasks: Vec<_> = (0..8)
+            .map(|t| {
+                let shards = shards.clone();
+                let mut keys = keys.clone();
+                keys.rotate_left(t);
+                tokio::spawn(async move {
+                    for _ in 0..50 {
+                        let mut locked = shards.write_many(&keys).await;
+                        locked.shard_mut(&keys[0]).set(keys[0].clone(), Value::String("v".into()));
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(Shards::<Keyspace>::new(0).len(), 1);
+    }
+}
@@ -0,0 +1,213 @@
+//! 存储后端抽象
+//!
+//! [`Db`](super::Db) 负责加锁、分片、阻塞唤醒与持久化等协调工作，键值本身的存取
+//! 都通过 [`Storage`] 完成。默认后端是内存中的 [`Keyspace`](super::Keyspace)，
+//! 实现这个 trait 即可换用其他后端（文件、sled、dashmap 等），命令处理层不需要改动。
+//!
+//! 约定：
+//! - 每个分片各持有一个后端实例，方法调用时对应分片的锁已经加好，实现无需自行同步
+//! - 过期时间以 Unix 毫秒时间戳表示；已过期的键对所有方法都视为不存在
+//! - [`Storage::set`] 写入新值时清除原有的过期时间，与 Redis 的 SET 语义一致
+//! - 内存统计与随机采样（[`Storage::used_memory`] 等）是可选的，不实现时 maxmemory 淘汰不可用
+
+use super::Value;
+
+/// 键值存储后端
+pub trait Storage: Default + Send + Sync + 'static {
+    /// 读取键的值
+    fn get(&self, key: &str) -> Option<&Value>;
+
+    /// 以可变方式读取键的值
+    fn get_mut(&mut self, key: &str) -> Option<&mut Value>;
+
+    /// 读取键的值，键不存在时先写入 `default` 的返回值
+    fn get_or_insert_with(&mut self, key: String, default: impl FnOnce() -> Value) -> &mut Value;
+
+    /// 写入键的值并清除原有的过期时间，返回旧值
+    fn set(&mut self, key: String, value: Value) -> Option<Value>;
+
+    /// 删除键，返回旧值
+    fn remove(&mut self, key: &str) -> Option<Value>;
+
+    /// 遍历所有键值（顺序不作保证）
+    fn scan(&self) -> impl Iterator<Item = (&String, &Value)>;
+
+    /// 从游标 `cursor`（首次为 0）开始分批遍历未过期的键（SCAN），把大约 `count` 个键追加到
+    /// `keys`，返回下一个游标，0 表示遍历结束
+    ///
+    /// 遍历全程都存在的键至少返回一次，可能重复返回。默认实现不支持分批，一次返回全部键。
+    fn scan_keys(&self, _cursor: u64, _count: usize, keys: &mut Vec<String>) -> u64 {
+        keys.extend(self.keys().cloned());
+        0
+    }
+
+    /// 键的过期时间，键不存在或没有设置过期时间时返回 `None`
+    fn expire_at(&self, key: &str) -> Option<u64>;
+
+    /// 设置键的过期时间，键不存在时返回 `false`；时间已过去时直接删除键
+    fn set_expire_at(&mut self, key: &str, at: u64) -> bool;
+
+    /// 清除键的过期时间，键不存在或没有设置过期时间时返回 `false`
+    fn persist(&mut self, key: &str) -> bool;
+
+    /// 清空所有键
+    fn clear(&mut self);
+
+    /// 估算的内存占用（字节），不统计内存的后端返回 0
+    ///
+    /// 需要 `&mut self`：实现可以在这里补算通过可变引用修改过的值的大小。
+    fn used_memory(&mut self) -> usize {
+        0
+    }
+
+    /// 由随机数 `random` 均匀地选出一个键（可能已过期），供近似 LRU 淘汰与 RANDOMKEY 使用，
+    /// `volatile` 为 `true` 时只在设置了过期时间的键中选取；不支持随机采样的后端返回 `None`，
+    /// 此时 RANDOMKEY 退化为返回遍历到的第一个键
+    fn random_key(&self, _random: u64, _volatile: bool) -> Option<&String> {
+        None
+    }
+
+    /// 键最近一次被访问的时间（Unix 毫秒），不记录访问时间的后端返回 `None`
+    fn last_access(&self, _key: &str) -> Option<u64> {
+        None
+    }
+
+    /// 键的访问频率计数（0 ~ 255，含义与 Redis 的 LFU 计数相同：按对数增长、随空闲时间衰减），
+    /// 不记录访问频率的后端返回 `None`
+    fn access_frequency(&self, _key: &str) -> Option<u8> {
+        None
+    }
+
+    /// 推进到 `now`（Unix 毫秒），删除并返回最多 `limit` 个已过期的键值，供主动过期使用；
+    /// 不支持的后端返回空列表，过期的键只在访问时惰性删除
+    fn pop_expired(&mut self, _now: u64, _limit: usize) -> Vec<(String, Value)> {
+        Vec::new()
+    }
+
+    /// 键的数量，可能包含已过期但尚未清理的键
+    fn len(&self) -> usize {
+        self.keys().count()
+    }
+
+    /// 是否没有任何键
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 读取键的值但不计为一次访问（不更新访问时间），供 OBJECT 等内省命令使用
+    fn peek(&self, key: &str) -> Option<&Value> {
+        self.get(key)
+    }
+
+    /// 键是否存在，不计为一次访问
+    fn contains_key(&self, key: &str) -> bool {
+        self.peek(key).is_some()
+    }
+
+    /// 遍历所有键
+    fn keys(&self) -> impl Iterator<Item = &String> {
+        self.scan().map(|(key, _)| key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::Storage;
+    use crate::{
+        db::{Db, Value, unix_time_ms},
+        handler::process_command,
+    };
+
+    /// 基于 `BTreeMap` 的最小后端，验证命令处理层可以直接运行在其他后端上
+    #[derive(Default)]
+    struct BTreeStorage {
+        entries: BTreeMap<String, (Value, Option<u64>)>,
+    }
+
+    impl BTreeStorage {
+        fn live(&self, key: &str) -> bool {
+            self.entries.get(key).is_some_and(|(_, at)| at.is_none_or(|at| at > unix_time_ms()))
+        }
+    }
+
+    impl Storage for BTreeStorage {
+        fn get(&self, key: &str) -> Option<&Value> {
+            self.live(key).then(|| &self.entries[key].0)
+        }
+
+        fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+            if !self.live(key) {
+                self.entries.remove(key);
+            }
+            self.entries.get_mut(key).map(|(value, _)| value)
+        }
+
+        fn get_or_insert_with(
+            &mut self,
+            key: String,
+            default: impl FnOnce() -> Value,
+        ) -> &mut Value {
+            if !self.live(&key) {
+                self.entries.remove(&key);
+            }
+            &mut self.entries.entry(key).or_insert_with(|| (default(), None)).0
+        }
+
+        fn set(&mut self, key: String, value: Value) -> Option<Value> {
+            self.entries.insert(key, (value, None)).map(|(value, _)| value)
+        }
+
+        fn remove(&mut self, key: &str) -> Option<Value> {
+            self.entries.remove(key).map(|(value, _)| value)
+        }
+
+        fn scan(&self) -> impl Iterator<Item = (&String, &Value)> {
+            self.entries
+                .iter()
+                .filter(|(key, _)| self.live(key))
+                .map(|(key, (value, _))| (key, value))
+        }
+
+        fn expire_at(&self, key: &str) -> Option<u64> {
+            self.live(key).then(|| self.entries[key].1).flatten()
+        }
+
+        fn set_expire_at(&mut self, key: &str, at: u64) -> bool {
+            if self.get_mut(key).is_none() {
+                return false;
+            }
+            if at <= unix_time_ms() {
+                self.entries.remove(key);
+            } else if let Some((_, expire_at)) = self.entries.get_mut(key) {
+                *expire_at = Some(at);
+            }
+            true
+        }
+
+        fn persist(&mut self, key: &str) -> bool {
+            self.expire_at(key).is_some()
+                && self.entries.get_mut(key).is_some_and(|(_, at)| at.take().is_some())
+        }
+
+        fn clear(&mut self) {
+            self.entries.clear();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_backend() {
+        let db = Db::<BTreeStorage>::with_storage();
+
+        assert_eq!(process_command(&db, "set foo bar").await, "OK");
+        assert_eq!(process_command(&db, "get foo").await, "bar");
+        assert_eq!(process_command(&db, "sadd s a b").await, "(integer) 2");
+        assert_eq!(process_command(&db, "hincrby h n 3").await, "(integer) 3");
+        assert_eq!(process_command(&db, "keys *").await, "1) foo\n2) h\n3) s");
+        assert_eq!(process_command(&db, "set tmp v px 1").await, "OK");
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        assert_eq!(process_command(&db, "get tmp").await, "(nil)");
+        assert_eq!(process_command(&db, "del foo s").await, "(integer) 2");
+    }
+}
@@ -0,0 +1,558 @@
+//! 流类型操作
+//!
+//! 流以 [`Stream`] 形式保存在 [`Value::Stream`] 中。
+//! XADD 生成的 ID 依赖当前时间，传播时改写为实际使用的 ID（见 `handler` 模块）。
+//! 追加消息会唤醒在该键上阻塞的 XREAD BLOCK。
+//!
+//! XREADGROUP 与 XCLAIM 修改消费者组的投递状态，这些状态依赖当前时间，
+//! 因此由 `Db` 在修改时以 XCLAIM 的形式逐条传播（见 [`claim_command`]）。
+
+use std::{ops::Deref, time::Duration};
+
+use tokio::time::Instant;
+
+use super::{Db, DbError, Storage, Value, shards::Locked, unix_time_ms};
+use crate::{
+    persistence::claim_command,
+    stream::{
+        ClaimOptions, ConsumerGroup, Entry, Fields, IdRange, IdSpec, Pending, PendingSummary,
+        Stream, StreamId,
+    },
+};
+
+/// 取出键对应的流，键不存在时创建一个空流
+fn stream_mut<S: Storage>(map: &mut S, key: String) -> Result<&mut Stream, DbError> {
+    match map.get_or_insert_with(key, || Value::Stream(Stream::new())) {
+        Value::Stream(stream) => Ok(stream),
+        _ => Err(DbError::WrongType),
+    }
+}
+
+/// 以只读方式取出键对应的流，键不存在时返回 `None`
+fn stream_ref<'a, S: Storage>(map: &'a S, key: &str) -> Result<Option<&'a Stream>, DbError> {
+    match map.get(key) {
+        Some(Value::Stream(stream)) => Ok(Some(stream)),
+        Some(_) => Err(DbError::WrongType),
+        None => Ok(None),
+    }
+}
+
+/// 取出已存在的流，键不存在时返回 [`DbError::StreamKeyRequired`]
+fn existing_stream<'a, S: Storage>(map: &'a mut S, key: &str) -> Result<&'a mut Stream, DbError> {
+    match map.get_mut(key) {
+        Some(Value::Stream(stream)) => Ok(stream),
+        Some(_) => Err(DbError::WrongType),
+        None => Err(DbError::StreamKeyRequired),
+    }
+}
+
+/// 取出带有指定消费者组的流，键或组不存在时返回 [`DbError::NoGroup`]
+fn stream_with_group<'a, S: Storage>(
+    map: &'a mut S,
+    key: &str,
+    group: &str,
+) -> Result<&'a mut Stream, DbError> {
+    match map.get_mut(key) {
+        Some(Value::Stream(stream)) if stream.group(group).is_some() => Ok(stream),
+        Some(Value::Stream(_)) | None => Err(DbError::NoGroup(key.into(), group.into())),
+        Some(_) => Err(DbError::WrongType),
+    }
+}
+
+/// 以只读方式取出流中的消费者组，键或组不存在时返回 [`DbError::NoGroup`]
+fn group_ref<'a, S: Storage>(
+    map: &'a S,
+    key: &str,
+    group: &str,
+) -> Result<&'a ConsumerGroup, DbError> {
+    let stream = stream_ref(map, key)?;
+    stream
+        .and_then(|stream| stream.group(group))
+        .ok_or_else(|| DbError::NoGroup(key.into(), group.into()))
+}
+
+/// 重建 `ids` 在消费者组中投递状态的命令
+fn claim_commands(key: &str, name: &str, group: &ConsumerGroup, ids: &[Entry]) -> Vec<Vec<String>> {
+    let pending = ids.iter().filter_map(|(id, _)| group.pending_entry(id).map(|p| (id, p)));
+    pending
+        .map(|(id, pending)| claim_command(key, name, *id, pending, group.last_delivered()))
+        .collect()
+}
+
+/// 在已锁住的分片中按起始 ID 读取多个流，只返回有新消息的流
+///
+/// `None` 表示 `$`：只读取此后添加的消息，非阻塞读取时总是没有结果。
+fn read_streams<G: Deref<Target: Storage>>(
+    locked: &Locked<G>,
+    streams: &[(String, Option<StreamId>)],
+    count: Option<usize>,
+) -> Result<Vec<(String, Vec<Entry>)>, DbError> {
+    let mut result = Vec::new();
+    for (key, after) in streams {
+        let (Some(stream), Some(after)) = (stream_ref(locked.shard(key), key)?, after) else {
+            continue;
+        };
+        let entries = stream.read_after(*after, count);
+        if !entries.is_empty() {
+            result.push((key.clone(), entries));
+        }
+    }
+    Ok(result)
+}
+
+/// 把 `$` 解析为流当前的最后一个 ID，键不存在时为 `0-0`
+fn resolve_last_ids<G: Deref<Target: Storage>>(
+    locked: &Locked<G>,
+    streams: &[(String, Option<StreamId>)],
+) -> Result<Vec<(String, Option<StreamId>)>, DbError> {
+    streams
+        .iter()
+        .map(|(key, after)| {
+            let after = match after {
+                Some(after) => *after,
+                None => stream_ref(locked.shard(key), key)?.map_or(StreamId::MIN, Stream::last_id),
+            };
+            Ok((key.clone(), Some(after)))
+        })
+        .collect()
+}
+
+impl<S: Storage> Db<S> {
+    /// 向流追加一条消息，返回消息 ID；键不存在时创建流
+    pub async fn xadd(&self, key: String, id: IdSpec, fields: Fields) -> Result<StreamId, DbError> {
+        let mut guard = self.shards().write(&key).await;
+        // ID 不合法时不能留下空流
+        let created = !guard.contains_key(&key);
+        let stream = stream_mut(&mut *guard, key.clone())?;
+
+        match stream.add(id, unix_time_ms(), fields) {
+            Ok(id) => {
+                self.notifier.notify(&key);
+                Ok(id)
+            }
+            Err(e) => {
+                if created {
+                    guard.remove(&key);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// 返回流的消息数量
+    pub async fn xlen(&self, key: &str) -> Result<usize, DbError> {
+        let guard = self.shards().read(key).await;
+
+        Ok(self.lookup(stream_ref(&*guard, key))?.map_or(0, Stream::len))
+    }
+
+    /// 按 ID 区间返回流中的消息，语义见 [`Stream::range`]
+    pub async fn xrange(
+        &self,
+        key: &str,
+        range: &IdRange,
+        count: Option<usize>,
+    ) -> Result<Vec<Entry>, DbError> {
+        let guard = self.shards().read(key).await;
+
+        let stream = self.lookup(stream_ref(&*guard, key))?;
+        Ok(stream.map(|stream| stream.range(range, count)).unwrap_or_default())
+    }
+
+    /// 非阻塞地读取多个流中 ID 大于给定 ID 的消息，只返回有新消息的流
+    pub async fn xread(
+        &self,
+        streams: &[(String, Option<StreamId>)],
+        count: Option<usize>,
+    ) -> Result<Vec<(String, Vec<Entry>)>, DbError> {
+        let keys: Vec<_> = streams.iter().map(|(key, _)| key).collect();
+        let guard = self.shards().read_many(&keys).await;
+        read_streams(&guard, streams, count)
+    }
+
+    /// 阻塞版本的 [`Db::xread`]
+    ///
+    /// 所有流都没有新消息时挂起，直到某个键被写入；`$` 在开始等待时解析为流的最后一个 ID，
+    /// 因此只返回此后添加的消息。`timeout` 为 `None` 表示一直等待，超时后返回空列表。
+    pub async fn xread_block(
+        &self,
+        streams: &[(String, Option<StreamId>)],
+        count: Option<usize>,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<(String, Vec<Entry>)>, DbError> {
+        let keys: Vec<_> = streams.iter().map(|(key, _)| key.clone()).collect();
+        // 先登记再检查，避免错过检查与等待之间发生的写入
+        let watch = self.notifier.watch(&keys);
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let streams = resolve_last_ids(&self.shards().read_many(&keys).await, streams)?;
+
+        loop {
+            let found = self.xread(&streams, count).await?;
+            if !found.is_empty() {
+                return Ok(found);
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    if tokio::time::timeout_at(deadline, watch.notified()).await.is_err() {
+                        return Ok(Vec::new());
+                    }
+                }
+                None => watch.notified().await,
+            }
+        }
+    }
+
+    /// 创建消费者组，返回组的最后投递 ID；`id` 为 `None`（即 `$`）时取流的最后一个 ID
+    ///
+    /// 键不存在时，`mkstream` 为 `true` 则创建空流，否则返回 [`DbError::StreamKeyRequired`]。
+    pub async fn xgroup_create(
+        &self,
+        key: String,
+        group: &str,
+        id: Option<StreamId>,
+        mkstream: bool,
+    ) -> Result<StreamId, DbError> {
+        let mut guard = self.shards().write(&key).await;
+        if !mkstream && !guard.contains_key(&key) {
+            return Err(DbError::StreamKeyRequired);
+        }
+
+        let stream = stream_mut(&mut *guard, key)?;
+        let id = id.unwrap_or(stream.last_id());
+        if !stream.create_group(group, ConsumerGroup::new(id)) {
+            return Err(DbError::BusyGroup);
+        }
+        Ok(id)
+    }
+
+    /// 设置消费者组的最后投递 ID，返回设置的 ID；`id` 为 `None`（即 `$`）时取流的最后一个 ID
+    pub async fn xgroup_setid(
+        &self,
+        key: &str,
+        group: &str,
+        id: Option<StreamId>,
+    ) -> Result<StreamId, DbError> {
+        let mut guard = self.shards().write(key).await;
+
+        let stream = existing_stream(&mut *guard, key)?;
+        let id = id.unwrap_or(stream.last_id());
+        let group =
+            stream.group_mut(group).ok_or_else(|| DbError::NoGroup(key.into(), group.into()))?;
+        group.set_last_delivered(id);
+        Ok(id)
+    }
+
+    /// 删除消费者组，返回组是否存在；在该组上阻塞的 XREADGROUP 被唤醒并返回错误
+    pub async fn xgroup_destroy(&self, key: &str, group: &str) -> Result<bool, DbError> {
+        let mut guard = self.shards().write(key).await;
+
+        let destroyed = existing_stream(&mut *guard, key)?.destroy_group(group);
+        if destroyed {
+            self.notifier.notify(key);
+        }
+        Ok(destroyed)
+    }
+
+    /// 在消费者组中创建消费者，已存在时返回 `false`
+    pub async fn xgroup_createconsumer(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> Result<bool, DbError> {
+        let mut guard = self.shards().write(key).await;
+
+        let stream = existing_stream(&mut *guard, key)?;
+        let group =
+            stream.group_mut(group).ok_or_else(|| DbError::NoGroup(key.into(), group.into()))?;
+        Ok(group.create_consumer(consumer))
+    }
+
+    /// 以消费者组的身份读取多个流（XREADGROUP），语义见 [`Stream::read_group`]
+    ///
+    /// 读取 `>` 时只返回有新消息的流，读取待确认消息时每个流都有结果。
+    /// 任一流或组不存在时不读取任何流。调用方不能持有写门闩。
+    pub async fn xreadgroup(
+        &self,
+        group: &str,
+        consumer: &str,
+        streams: &[(String, Option<StreamId>)],
+        count: Option<usize>,
+        noack: bool,
+    ) -> Result<Vec<(String, Vec<Entry>)>, DbError> {
+        // 读取与传播在同一个写门闩与顺序锁内完成
+        let _gate = self.enter_write().await;
+        let keys: Vec<_> = streams.iter().map(|(key, _)| key).collect();
+        let _order = self.lock_order(&keys).await;
+        let mut guard = self.shards().write_many(&keys).await;
+        for (key, _) in streams {
+            stream_with_group(guard.shard_mut(key), key, group)?;
+        }
+
+        let now = unix_time_ms();
+        let (mut result, mut propagated) = (Vec::new(), Vec::new());
+        for (key, after) in streams {
+            let stream = stream_with_group(guard.shard_mut(key), key, group)?;
+            let state = stream.group_mut(group).expect("group exists");
+            if state.create_consumer(consumer) {
+                let args = ["xgroup", "createconsumer", key, group, consumer];
+                propagated.push(args.map(String::from).to_vec());
+            }
+
+            let entries = stream.read_group(group, consumer, *after, count, noack, now);
+            let entries = entries.expect("group exists");
+            let state = stream.group(group).expect("group exists");
+            propagated.extend(claim_commands(key, group, state, &entries));
+            if after.is_none() && noack && !entries.is_empty() {
+                let last_delivered = state.last_delivered().to_string();
+                let args = ["xgroup", "setid", key, group, &last_delivered];
+                propagated.push(args.map(String::from).to_vec());
+            }
+            if after.is_some() || !entries.is_empty() {
+                result.push((key.clone(), entries));
+            }
+        }
+        drop(guard);
+
+        for args in &propagated {
+            self.propagate(args)?;
+        }
+        Ok(result)
+    }
+
+    /// 阻塞版本的 [`Db::xreadgroup`]
+    ///
+    /// 所有流都读取 `>` 且都没有新消息时挂起，直到某个键被写入；
+    /// `timeout` 为 `None` 表示一直等待，超时后返回空列表。
+    pub async fn xreadgroup_block(
+        &self,
+        group: &str,
+        consumer: &str,
+        streams: &[(String, Option<StreamId>)],
+        count: Option<usize>,
+        noack: bool,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<(String, Vec<Entry>)>, DbError> {
+        let keys: Vec<_> = streams.iter().map(|(key, _)| key.clone()).collect();
+        // 先登记再检查，避免错过检查与等待之间发生的写入
+        let watch = self.notifier.watch(&keys);
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            let found = self.xreadgroup(group, consumer, streams, count, noack).await?;
+            if !found.is_empty() {
+                return Ok(found);
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    if tokio::time::timeout_at(deadline, watch.notified()).await.is_err() {
+                        return Ok(Vec::new());
+                    }
+                }
+                None => watch.notified().await,
+            }
+        }
+    }
+
+    /// 确认消息，返回实际确认的数量；键或组不存在时返回 0
+    pub async fn xack(&self, key: &str, group: &str, ids: &[StreamId]) -> Result<usize, DbError> {
+        let mut guard = self.shards().write(key).await;
+
+        match guard.get_mut(key) {
+            Some(Value::Stream(stream)) => Ok(stream.group_mut(group).map_or(0, |g| g.ack(ids))),
+            Some(_) => Err(DbError::WrongType),
+            None => Ok(0),
+        }
+    }
+
+    /// 消费者组待确认列表的汇总信息
+    pub async fn xpending(&self, key: &str, group: &str) -> Result<PendingSummary, DbError> {
+        let guard = self.shards().read(key).await;
+
+        Ok(group_ref(&*guard, key, group)?.summary())
+    }
+
+    /// 按 ID 区间返回消费者组的待确认消息，语义见 [`ConsumerGroup::pending_range`]
+    pub async fn xpending_range(
+        &self,
+        key: &str,
+        group: &str,
+        range: &IdRange,
+        count: usize,
+        consumer: Option<&str>,
+        min_idle: Option<u64>,
+    ) -> Result<Vec<(StreamId, Pending)>, DbError> {
+        let guard = self.shards().read(key).await;
+
+        let group = group_ref(&*guard, key, group)?;
+        let pending = group.pending_range(range, count, consumer, min_idle, unix_time_ms());
+        Ok(pending.into_iter().map(|(id, pending)| (id, pending.clone())).collect())
+    }
+
+    /// 把待确认消息转移给 `consumer`（XCLAIM），语义见 [`Stream::claim`]
+    ///
+    /// 调用方不能持有写门闩。
+    pub async fn xclaim(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        ids: &[StreamId],
+        options: &ClaimOptions,
+    ) -> Result<Vec<Entry>, DbError> {
+        // 认领与传播在同一个写门闩与顺序锁内完成
+        let _gate = self.enter_write().await;
+        let _order = self.lock_order(&[key]).await;
+        let mut guard = self.shards().write(key).await;
+
+        let stream = stream_with_group(&mut *guard, key, group)?;
+        let mut propagated = Vec::new();
+        if stream.group_mut(group).expect("group exists").create_consumer(consumer) {
+            let args = ["xgroup", "createconsumer", key, group, consumer];
+            propagated.push(args.map(String::from).to_vec());
+        }
+
+        let claimed = stream.claim(group, consumer, ids, options, unix_time_ms());
+        let claimed = claimed.expect("group exists");
+        let state = stream.group(group).expect("group exists");
+        propagated.extend(claim_commands(key, group, state, &claimed));
+        if claimed.is_empty() && options.last_id.is_some() {
+            let last_delivered = state.last_delivered().to_string();
+            let args = ["xgroup", "setid", key, group, &last_delivered];
+            propagated.push(args.map(String::from).to_vec());
+        }
+        drop(guard);
+
+        for args in &propagated {
+            self.propagate(args)?;
+        }
+        Ok(claimed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        db::{Db, DbError},
+        stream::{IdRange, IdSpec, StreamId},
+    };
+
+    fn fields(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(f, v)| (f.to_string(), v.to_string())).collect()
+    }
+
+    #[tokio::test]
+    async fn test_xadd_and_xrange() {
+        let db = Db::new();
+        let id = |ms, seq| IdSpec::Explicit(StreamId::new(ms, seq));
+
+        assert_eq!(
+            db.xadd("s".into(), id(1, 0), fields(&[("a", "1")])).await,
+            Ok(StreamId::new(1, 0))
+        );
+        assert_eq!(
+            db.xadd("s".into(), IdSpec::Seq(1), fields(&[("b", "2")])).await,
+            Ok(StreamId::new(1, 1))
+        );
+        assert_eq!(
+            db.xadd("s".into(), id(1, 1), fields(&[("c", "3")])).await,
+            Err(DbError::StreamIdTooSmall)
+        );
+        assert_eq!(db.xlen("s").await, Ok(2));
+
+        let all = IdRange::parse("-", "+").unwrap();
+        let entries = db.xrange("s", &all, None).await.unwrap();
+        assert_eq!(entries[1], (StreamId::new(1, 1), fields(&[("b", "2")])));
+        assert_eq!(db.xrange("missing", &all, None).await, Ok(vec![]));
+
+        // ID 不合法时不会留下空流
+        assert_eq!(
+            db.xadd("t".into(), id(0, 0), fields(&[("a", "1")])).await,
+            Err(DbError::StreamIdZero)
+        );
+        assert_eq!(db.xlen("t").await, Ok(0));
+        assert_eq!(db.dbsize().await, 1);
+
+        db.set("str".into(), "v".into()).await;
+        assert_eq!(db.xlen("str").await, Err(DbError::WrongType));
+    }
+
+    #[tokio::test]
+    async fn test_xread() {
+        let db = Db::new();
+        for ms in 1..=3 {
+            let id = IdSpec::Explicit(StreamId::new(ms, 0));
+            db.xadd("a".into(), id, fields(&[("n", &ms.to_string())])).await.unwrap();
+        }
+        db.xadd("b".into(), IdSpec::Seq(5), fields(&[("n", "5")])).await.unwrap();
+
+        let streams = [
+            ("a".to_string(), Some(StreamId::new(1, 0))),
+            ("b".to_string(), None),
+            ("missing".to_string(), Some(StreamId::MIN)),
+        ];
+        let result = db.xread(&streams, Some(1)).await.unwrap();
+        assert_eq!(result, vec![("a".into(), vec![(StreamId::new(2, 0), fields(&[("n", "2")]))])]);
+
+        let streams = [("b".to_string(), Some(StreamId::MIN))];
+        assert_eq!(db.xread(&streams, None).await.unwrap()[0].1.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_xread_block() {
+        let db = Db::new();
+        db.xadd("s".into(), IdSpec::Seq(1), fields(&[("old", "1")])).await.unwrap();
+
+        let streams = [("s".to_string(), None), ("other".to_string(), None)];
+        let timeout = Some(Duration::from_millis(10));
+        assert_eq!(db.xread_block(&streams, None, timeout).await, Ok(vec![]));
+
+        // `$` 只读取开始等待之后添加的消息
+        let reader = db.clone();
+        let blocked = tokio::spawn(async move { reader.xread_block(&streams, None, None).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!blocked.is_finished());
+
+        db.xadd("other".into(), IdSpec::Seq(2), fields(&[("new", "2")])).await.unwrap();
+        let result = blocked.await.unwrap().unwrap();
+        assert_eq!(
+            result,
+            vec![("other".into(), vec![(StreamId::new(2, 0), fields(&[("new", "2")]))])]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_xreadgroup_block() {
+        let db = Db::new();
+        db.xgroup_create("s".into(), "g", None, true).await.unwrap();
+        let streams = [("s".to_string(), None)];
+        let timeout = Some(Duration::from_millis(10));
+        assert_eq!(db.xreadgroup_block("g", "c", &streams, None, false, timeout).await, Ok(vec![]));
+
+        let reader = db.clone();
+        let blocked = tokio::spawn(async move {
+            reader.xreadgroup_block("g", "c", &streams, None, false, None).await
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!blocked.is_finished());
+
+        db.xadd("s".into(), IdSpec::Seq(1), fields(&[("a", "1")])).await.unwrap();
+        let result = blocked.await.unwrap().unwrap();
+        assert_eq!(result, vec![("s".into(), vec![(StreamId::new(1, 0), fields(&[("a", "1")]))])]);
+        assert_eq!(db.xpending("s", "g").await.unwrap().count, 1);
+
+        // 删除组会唤醒阻塞的读取者并返回错误
+        let reader = db.clone();
+        let blocked = tokio::spawn(async move {
+            let streams = [("s".to_string(), None)];
+            reader.xreadgroup_block("g", "c", &streams, None, false, None).await
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(db.xgroup_destroy("s", "g").await, Ok(true));
+        assert_eq!(blocked.await.unwrap(), Err(DbError::NoGroup("s".into(), "g".into())));
+    }
+}
@@ -0,0 +1,357 @@
+//! 有序集合类型操作
+//!
+//! 有序集合以 [`SortedSet`] 形式保存在 [`Value::ZSet`] 中。
+//! 写入成员的操作会唤醒在该键上阻塞的 BZPOPMIN / BZPOPMAX。
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use super::{Db, DbError, Storage, Value};
+use crate::sorted_set::{AddFlags, AddOutcome, LexRange, ScoreRange, SortedSet};
+
+/// 取出键对应的有序集合，键不存在时创建一个空有序集合
+fn zset_mut<S: Storage>(map: &mut S, key: String) -> Result<&mut SortedSet, DbError> {
+    match map.get_or_insert_with(key, || Value::ZSet(SortedSet::new())) {
+        Value::ZSet(zset) => Ok(zset),
+        _ => Err(DbError::WrongType),
+    }
+}
+
+/// 以只读方式取出键对应的有序集合，键不存在时返回 `None`
+pub(super) fn zset_ref<'a, S: Storage>(
+    map: &'a S,
+    key: &str,
+) -> Result<Option<&'a SortedSet>, DbError> {
+    match map.get(key) {
+        Some(Value::ZSet(zset)) => Ok(Some(zset)),
+        Some(_) => Err(DbError::WrongType),
+        None => Ok(None),
+    }
+}
+
+impl<S: Storage> Db<S> {
+    /// 向有序集合写入成员
+    ///
+    /// 返回新增成员的数量；`flags.ch` 为 `true` 时返回新增与更新成员的总数。
+    pub async fn zadd(
+        &self,
+        key: String,
+        flags: AddFlags,
+        pairs: Vec<(f64, String)>,
+    ) -> Result<usize, DbError> {
+        let mut guard = self.shards().write(&key).await;
+        if flags.xx && !guard.contains_key(&key) {
+            // XX 不会创建新键，但仍需检查类型
+            return zset_ref(&*guard, &key).map(|_| 0);
+        }
+        let zset = zset_mut(&mut *guard, key.clone())?;
+
+        let mut changed = 0;
+        for (score, member) in pairs {
+            match zset.add(member, score, flags) {
+                AddOutcome::Added => changed += 1,
+                AddOutcome::Updated if flags.ch => changed += 1,
+                _ => {}
+            }
+        }
+
+        if zset.is_empty() {
+            guard.remove(&key);
+        } else {
+            self.notifier.notify(&key);
+        }
+        Ok(changed)
+    }
+
+    /// 查询有序集合成员的分值
+    pub async fn zscore(&self, key: &str, member: &str) -> Result<Option<f64>, DbError> {
+        let guard = self.shards().read(key).await;
+
+        Ok(self.lookup(zset_ref(&*guard, key))?.and_then(|zset| zset.score(member)))
+    }
+
+    /// 返回有序集合的成员数量
+    pub async fn zcard(&self, key: &str) -> Result<usize, DbError> {
+        let guard = self.shards().read(key).await;
+
+        Ok(self.lookup(zset_ref(&*guard, key))?.map_or(0, SortedSet::len))
+    }
+
+    /// 按排名范围返回有序集合的成员及分值，语义见 [`SortedSet::range`]
+    pub async fn zrange(
+        &self,
+        key: &str,
+        start: i64,
+        stop: i64,
+        rev: bool,
+    ) -> Result<Vec<(String, f64)>, DbError> {
+        let guard = self.shards().read(key).await;
+
+        Ok(self
+            .lookup(zset_ref(&*guard, key))?
+            .map_or_else(Vec::new, |zset| zset.range(start, stop, rev)))
+    }
+
+    /// 按分值范围返回有序集合的成员及分值，语义见 [`SortedSet::range_by_score`]
+    pub async fn zrange_by_score(
+        &self,
+        key: &str,
+        range: &ScoreRange,
+        offset: i64,
+        count: i64,
+    ) -> Result<Vec<(String, f64)>, DbError> {
+        let guard = self.shards().read(key).await;
+
+        let zset = self.lookup(zset_ref(&*guard, key))?;
+        Ok(zset.map_or_else(Vec::new, |zset| zset.range_by_score(range, offset, count)))
+    }
+
+    /// 按字典序范围返回有序集合的成员及分值，语义见 [`SortedSet::range_by_lex`]
+    pub async fn zrange_by_lex(
+        &self,
+        key: &str,
+        range: &LexRange,
+        offset: i64,
+        count: i64,
+    ) -> Result<Vec<(String, f64)>, DbError> {
+        let guard = self.shards().read(key).await;
+
+        let zset = self.lookup(zset_ref(&*guard, key))?;
+        Ok(zset.map_or_else(Vec::new, |zset| zset.range_by_lex(range, offset, count)))
+    }
+
+    /// 返回成员按分值从小到大的排名（从 0 开始）
+    pub async fn zrank(&self, key: &str, member: &str) -> Result<Option<usize>, DbError> {
+        let guard = self.shards().read(key).await;
+
+        Ok(self.lookup(zset_ref(&*guard, key))?.and_then(|zset| zset.rank(member)))
+    }
+
+    /// 将成员的分值加上 `delta` 并返回新分值，键或成员不存在时自动创建
+    pub async fn zincrby(&self, key: String, delta: f64, member: String) -> Result<f64, DbError> {
+        let mut guard = self.shards().write(&key).await;
+        let zset = zset_mut(&mut *guard, key.clone())?;
+
+        let score = zset.incr(member, delta);
+        if zset.is_empty() {
+            guard.remove(&key);
+        } else {
+            self.notifier.notify(&key);
+        }
+        score.ok_or(DbError::ScoreNan)
+    }
+
+    /// 弹出分值最小（`max` 为 `true` 时为最大）的最多 `count` 个成员，有序集合被删空时删除键
+    pub async fn zpop(
+        &self,
+        key: &str,
+        count: usize,
+        max: bool,
+    ) -> Result<Vec<(String, f64)>, DbError> {
+        let mut guard = self.shards().write(key).await;
+        let Some(Value::ZSet(zset)) = guard.get_mut(key) else {
+            return zset_ref(&*guard, key).map(|_| Vec::new());
+        };
+
+        let popped = zset.pop(count, max);
+        if zset.is_empty() {
+            guard.remove(key);
+        }
+        Ok(popped)
+    }
+
+    /// 阻塞版本的 [`Db::zpop`]：从第一个非空的键中弹出一个成员，返回 `(key, member, score)`
+    ///
+    /// 所有键都为空时挂起，直到某个键被写入；`timeout` 为 `None` 表示一直等待，
+    /// 超时后返回 `Ok(None)`。弹出的结果以 ZPOPMIN / ZPOPMAX 的形式传播到追加日志。
+    pub async fn bzpop(
+        &self,
+        keys: &[String],
+        max: bool,
+        timeout: Option<Duration>,
+    ) -> Result<Option<(String, String, f64)>, DbError> {
+        // 先登记再检查，避免错过检查与等待之间发生的写入
+        let watch = self.notifier.watch(keys);
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            for key in keys {
+                // 弹出与传播在同一个写门闩与顺序锁内完成，阻塞等待期间不持有
+                let _gate = self.enter_write().await;
+                let _order = self.lock_order(&[key]).await;
+                if let Some((member, score)) = self.zpop(key, 1, max).await?.pop() {
+                    let name = if max { "zpopmax" } else { "zpopmin" };
+                    self.propagate(&[name.into(), key.clone()])?;
+                    return Ok(Some((key.clone(), member, score)));
+                }
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    if tokio::time::timeout_at(deadline, watch.notified()).await.is_err() {
+                        return Ok(None);
+                    }
+                }
+                None => watch.notified().await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        db::{Db, DbError},
+        sorted_set::{AddFlags, LexRange, ScoreRange},
+    };
+
+    fn pairs(items: &[(f64, &str)]) -> Vec<(f64, String)> {
+        items.iter().map(|(score, member)| (*score, member.to_string())).collect()
+    }
+
+    #[tokio::test]
+    async fn test_zadd_zscore_zcard() {
+        let db = Db::new();
+
+        let added = db.zadd("z".into(), AddFlags::default(), pairs(&[(1.0, "a"), (2.0, "b")]));
+        assert_eq!(added.await, Ok(2));
+        let added = db.zadd("z".into(), AddFlags::default(), pairs(&[(3.0, "a"), (4.0, "c")]));
+        assert_eq!(added.await, Ok(1));
+
+        assert_eq!(db.zscore("z", "a").await, Ok(Some(3.0)));
+        assert_eq!(db.zscore("z", "x").await, Ok(None));
+        assert_eq!(db.zcard("z").await, Ok(3));
+        assert_eq!(db.zcard("missing").await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn test_zadd_ch_and_xx() {
+        let db = Db::new();
+        let ch = AddFlags { ch: true, ..Default::default() };
+        let xx = AddFlags { xx: true, ..Default::default() };
+
+        assert_eq!(db.zadd("z".into(), xx, pairs(&[(1.0, "a")])).await, Ok(0));
+        assert_eq!(db.zcard("z").await, Ok(0));
+
+        db.zadd("z".into(), AddFlags::default(), pairs(&[(1.0, "a")])).await.unwrap();
+        assert_eq!(db.zadd("z".into(), ch, pairs(&[(2.0, "a"), (1.0, "b")])).await, Ok(2));
+    }
+
+    #[tokio::test]
+    async fn test_zrange() {
+        let db = Db::new();
+        db.zadd("z".into(), AddFlags::default(), pairs(&[(2.0, "b"), (1.0, "a"), (3.0, "c")]))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.zrange("z", 0, -1, false).await,
+            Ok(vec![("a".into(), 1.0), ("b".into(), 2.0), ("c".into(), 3.0)])
+        );
+        assert_eq!(db.zrange("z", 0, 0, true).await, Ok(vec![("c".into(), 3.0)]));
+        assert_eq!(db.zrange("missing", 0, -1, false).await, Ok(vec![]));
+    }
+
+    #[tokio::test]
+    async fn test_zrange_by_score_and_lex() {
+        let db = Db::new();
+        db.zadd("z".into(), AddFlags::default(), pairs(&[(1.0, "a"), (2.0, "b"), (3.0, "c")]))
+            .await
+            .unwrap();
+        db.zadd("lex".into(), AddFlags::default(), pairs(&[(0.0, "x"), (0.0, "y"), (0.0, "z")]))
+            .await
+            .unwrap();
+
+        let range = ScoreRange::parse("(1", "+inf").unwrap();
+        assert_eq!(
+            db.zrange_by_score("z", &range, 0, -1).await,
+            Ok(vec![("b".into(), 2.0), ("c".into(), 3.0)])
+        );
+        assert_eq!(db.zrange_by_score("missing", &range, 0, -1).await, Ok(vec![]));
+
+        let range = LexRange::parse("[y", "+").unwrap();
+        assert_eq!(db.zrange_by_lex("lex", &range, 1, 1).await, Ok(vec![("z".into(), 0.0)]));
+    }
+
+    #[tokio::test]
+    async fn test_zrank_zincrby() {
+        let db = Db::new();
+
+        assert_eq!(db.zincrby("z".into(), 2.0, "a".into()).await, Ok(2.0));
+        assert_eq!(db.zincrby("z".into(), 1.5, "b".into()).await, Ok(1.5));
+        assert_eq!(db.zincrby("z".into(), 1.0, "b".into()).await, Ok(2.5));
+
+        assert_eq!(db.zrank("z", "a").await, Ok(Some(0)));
+        assert_eq!(db.zrank("z", "b").await, Ok(Some(1)));
+        assert_eq!(db.zrank("z", "x").await, Ok(None));
+        assert_eq!(db.zrank("missing", "x").await, Ok(None));
+
+        db.zincrby("inf".into(), f64::INFINITY, "a".into()).await.unwrap();
+        assert_eq!(
+            db.zincrby("inf".into(), f64::NEG_INFINITY, "a".into()).await,
+            Err(DbError::ScoreNan)
+        );
+        assert_eq!(db.zincrby("nan".into(), f64::NAN, "a".into()).await, Err(DbError::ScoreNan));
+        assert_eq!(db.zcard("nan").await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn test_zpop() {
+        let db = Db::new();
+        db.zadd("z".into(), AddFlags::default(), pairs(&[(1.0, "a"), (2.0, "b"), (3.0, "c")]))
+            .await
+            .unwrap();
+
+        assert_eq!(db.zpop("z", 1, false).await, Ok(vec![("a".into(), 1.0)]));
+        assert_eq!(db.zpop("z", 1, true).await, Ok(vec![("c".into(), 3.0)]));
+        assert_eq!(db.zpop("z", 5, true).await, Ok(vec![("b".into(), 2.0)]));
+        assert_eq!(db.zpop("z", 1, true).await, Ok(vec![]));
+        assert_eq!(db.zcard("z").await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn test_bzpop_returns_immediately_when_available() {
+        let db = Db::new();
+        db.zadd("b".into(), AddFlags::default(), pairs(&[(1.0, "x"), (2.0, "y")])).await.unwrap();
+
+        let keys = ["a".to_string(), "b".to_string()];
+        assert_eq!(db.bzpop(&keys, true, None).await, Ok(Some(("b".into(), "y".into(), 2.0))));
+    }
+
+    #[tokio::test]
+    async fn test_bzpop_wakes_on_write() {
+        let db = Db::new();
+
+        let waiter = tokio::spawn({
+            let db = db.clone();
+            async move { db.bzpop(&["z".to_string()], false, Some(Duration::from_secs(5))).await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        db.zadd("z".into(), AddFlags::default(), pairs(&[(4.0, "m")])).await.unwrap();
+
+        assert_eq!(waiter.await.unwrap(), Ok(Some(("z".into(), "m".into(), 4.0))));
+        assert_eq!(db.zcard("z").await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn test_bzpop_timeout() {
+        let db = Db::new();
+
+        let popped = db.bzpop(&["z".to_string()], false, Some(Duration::from_millis(20))).await;
+        assert_eq!(popped, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn test_zset_wrong_type() {
+        let db = Db::new();
+        db.set("str".into(), "v".into()).await;
+
+        let added = db.zadd("str".into(), AddFlags::default(), pairs(&[(1.0, "a")]));
+        assert_eq!(added.await, Err(DbError::WrongType));
+        assert_eq!(db.zcard("str").await, Err(DbError::WrongType));
+    }
+}
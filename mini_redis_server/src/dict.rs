@@ -0,0 +1,351 @@
+//! 渐进式 rehash 的哈希表
+//!
+//! 结构与 Redis 的 dict 相同：桶数为 2 的幂，按哈希值的低位分桶，同一个桶中的键放在一个数组里。
+//! - 键数达到桶数时扩容为两倍，低于桶数的 1/8 时收缩
+//! - 改变大小时新建一张表，之后每次写操作只把旧表的一个桶搬到新表（[`Dict::rehash`]），
+//!   搬迁的开销分摊到各次写操作上，表再大也不会因为一次扩容停顿；搬迁期间查找同时检查两张表
+//!
+//! [`Dict::scan`] 实现 Redis SCAN 的游标遍历：游标是桶的编号，按“反向二进制”递增，即从高位加一。
+//! 表的大小是 2 的幂，游标在大表中依次对应的桶恰好是小表中同一个桶拆分出的全部桶，
+//! 因此在遍历的全过程中一直存在的键至少返回一次（可能重复），无论期间表扩容、收缩还是正在搬迁。
+//! 游标只是一个整数，遍历不占用表的任何状态。
+
+use std::{
+    hash::{BuildHasher, RandomState},
+    mem,
+};
+
+/// 最小的桶数
+const MIN_SIZE: usize = 4;
+/// 搬迁一个桶时最多跳过的空桶数（与 Redis 相同）
+const EMPTY_VISITS: usize = 10;
+
+type Bucket<V> = Vec<(String, V)>;
+
+/// 以字符串为键、渐进式 rehash 的哈希表
+pub struct Dict<V> {
+    /// 第二张表只在搬迁期间非空，搬迁完成后成为第一张表
+    tables: [Vec<Bucket<V>>; 2],
+    /// 第一张表中下一个要搬迁的桶，`None` 表示没有在搬迁
+    rehash_index: Option<usize>,
+    len: usize,
+    hasher: RandomState,
+}
+
+impl<V> Default for Dict<V> {
+    fn default() -> Self {
+        Self {
+            tables: [Vec::new(), Vec::new()],
+            rehash_index: None,
+            len: 0,
+            hasher: RandomState::new(),
+        }
+    }
+}
+
+impl<V> Dict<V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 是否正在搬迁
+    pub fn is_rehashing(&self) -> bool {
+        self.rehash_index.is_some()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        let (table, bucket, pos) = self.find(key)?;
+        Some(&self.tables[table][bucket][pos].1)
+    }
+
+    /// 以可变方式读取，顺带搬迁一个桶
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+        self.rehash(1);
+        let (table, bucket, pos) = self.find(key)?;
+        Some(&mut self.tables[table][bucket][pos].1)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.find(key).is_some()
+    }
+
+    /// 写入键值，返回旧值；顺带搬迁一个桶，键数达到桶数时开始扩容
+    pub fn insert(&mut self, key: String, value: V) -> Option<V> {
+        self.rehash(1);
+        if let Some((table, bucket, pos)) = self.find(&key) {
+            return Some(mem::replace(&mut self.tables[table][bucket][pos].1, value));
+        }
+        self.expand_if_needed();
+        // 搬迁期间新键直接放入新表
+        let table = self.is_rehashing() as usize;
+        let bucket = self.bucket(table, &key);
+        self.tables[table][bucket].push((key, value));
+        self.len += 1;
+        None
+    }
+
+    /// 删除键，返回旧值；顺带搬迁一个桶，键数过少时开始收缩
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        self.rehash(1);
+        let (table, bucket, pos) = self.find(key)?;
+        let (_, value) = self.tables[table][bucket].swap_remove(pos);
+        self.len -= 1;
+        self.shrink_if_needed();
+        Some(value)
+    }
+
+    /// 清空所有键并释放全部桶
+    pub fn clear(&mut self) {
+        self.tables = [Vec::new(), Vec::new()];
+        self.rehash_index = None;
+        self.len = 0;
+    }
+
+    /// 遍历所有键值（顺序不作保证）
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &V)> {
+        self.tables.iter().flatten().flatten().map(|(key, value)| (key, value))
+    }
+
+    /// 搬迁旧表中最多 `n` 个非空的桶（每个非空桶最多跳过 [`EMPTY_VISITS`] 个空桶），
+    /// 返回之后是否仍在搬迁
+    pub fn rehash(&mut self, n: usize) -> bool {
+        let Some(mut index) = self.rehash_index else {
+            return false;
+        };
+        let (mut n, mut empty_visits) = (n, n * EMPTY_VISITS);
+        while n > 0 && index < self.tables[0].len() {
+            if self.tables[0][index].is_empty() {
+                index += 1;
+                empty_visits -= 1;
+                if empty_visits == 0 {
+                    break;
+                }
+                continue;
+            }
+            for (key, value) in mem::take(&mut self.tables[0][index]) {
+                let bucket = self.bucket(1, &key);
+                self.tables[1][bucket].push((key, value));
+            }
+            index += 1;
+            n -= 1;
+        }
+        if index < self.tables[0].len() {
+            self.rehash_index = Some(index);
+            return true;
+        }
+        self.tables[0] = mem::take(&mut self.tables[1]);
+        self.rehash_index = None;
+        false
+    }
+
+    /// 从游标 `cursor`（首次为 0）开始遍历一个桶，对其中的键值依次调用 `f`，返回下一个游标，
+    /// 返回 0 表示遍历结束
+    ///
+    /// 搬迁期间先遍历小表中游标对应的桶，再遍历大表中由它拆分出的全部桶。
+    pub fn scan(&self, cursor: u64, mut f: impl FnMut(&String, &V)) -> u64 {
+        if self.len == 0 {
+            return 0;
+        }
+        let mut emit = |bucket: &Bucket<V>| bucket.iter().for_each(|(key, value)| f(key, value));
+        let mut v = cursor;
+        if !self.is_rehashing() {
+            let mask = mask(&self.tables[0]);
+            emit(&self.tables[0][(v & mask) as usize]);
+            return next_cursor(v, mask);
+        }
+
+        let (small, large) = if self.tables[0].len() <= self.tables[1].len() {
+            (&self.tables[0], &self.tables[1])
+        } else {
+            (&self.tables[1], &self.tables[0])
+        };
+        let (small_mask, large_mask) = (mask(small), mask(large));
+        emit(&small[(v & small_mask) as usize]);
+        // 依次遍历大表中低位与小表的桶相同的桶，直到高出小表掩码的位全部进位归零
+        loop {
+            emit(&large[(v & large_mask) as usize]);
+            v = next_cursor(v, large_mask);
+            if v & (small_mask ^ large_mask) == 0 {
+                return v;
+            }
+        }
+    }
+
+    /// 键所在的表、桶与桶中的位置；搬迁期间旧表中已搬迁的桶为空，两张表依次查找
+    fn find(&self, key: &str) -> Option<(usize, usize, usize)> {
+        if self.len == 0 {
+            return None;
+        }
+        (0..=self.is_rehashing() as usize).find_map(|table| {
+            let bucket = self.bucket(table, key);
+            let pos = self.tables[table][bucket].iter().position(|(k, _)| k == key)?;
+            Some((table, bucket, pos))
+        })
+    }
+
+    fn bucket(&self, table: usize, key: &str) -> usize {
+        (self.hasher.hash_one(key) & mask(&self.tables[table])) as usize
+    }
+
+    fn expand_if_needed(&mut self) {
+        if self.is_rehashing() {
+            return;
+        }
+        if self.tables[0].is_empty() {
+            self.tables[0] = buckets(MIN_SIZE);
+        } else if self.len >= self.tables[0].len() {
+            self.resize(self.tables[0].len() * 2);
+        }
+    }
+
+    fn shrink_if_needed(&mut self) {
+        let size = self.tables[0].len();
+        if !self.is_rehashing() && size > MIN_SIZE && self.len * 8 < size {
+            self.resize(self.len.next_power_of_two().max(MIN_SIZE));
+        }
+    }
+
+    /// 新建一张有 `size` 个桶的表并开始搬迁
+    fn resize(&mut self, size: usize) {
+        self.tables[1] = buckets(size);
+        self.rehash_index = Some(0);
+    }
+}
+
+fn buckets<V>(size: usize) -> Vec<Bucket<V>> {
+    (0..size).map(|_| Vec::new()).collect()
+}
+
+fn mask<V>(table: &[Bucket<V>]) -> u64 {
+    table.len() as u64 - 1
+}
+
+/// 反向二进制加一：把掩码以外的位置 1，反转后加一再反转回来，进位从掩码内的最高位开始
+fn next_cursor(cursor: u64, mask: u64) -> u64 {
+    (cursor | !mask).reverse_bits().wrapping_add(1).reverse_bits()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    /// 从头遍历到结束，每一步之后调用 `step` 修改表，返回遍历到的全部键
+    fn scan_all(dict: &mut Dict<u32>, mut step: impl FnMut(&mut Dict<u32>, usize)) -> Vec<String> {
+        let (mut cursor, mut keys, mut i) = (0, Vec::new(), 0);
+        loop {
+            cursor = dict.scan(cursor, |key, _| keys.push(key.clone()));
+            if cursor == 0 {
+                return keys;
+            }
+            step(dict, i);
+            i += 1;
+        }
+    }
+
+    fn filled(n: u32) -> Dict<u32> {
+        let mut dict = Dict::new();
+        for i in 0..n {
+            dict.insert(format!("key:{i}"), i);
+        }
+        dict
+    }
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut dict = filled(1000);
+        assert_eq!(dict.len(), 1000);
+        assert_eq!(dict.insert("key:7".into(), 70), Some(7));
+        assert_eq!(dict.get("key:7"), Some(&70));
+        *dict.get_mut("key:8").unwrap() += 1;
+        assert_eq!(dict.get("key:8"), Some(&9));
+        assert!(!dict.contains_key("missing"));
+
+        assert_eq!(dict.remove("key:7"), Some(70));
+        for i in (0..990).filter(|&i| i != 7) {
+            assert!(dict.remove(&format!("key:{i}")).is_some());
+        }
+        assert_eq!(dict.remove("key:0"), None);
+        assert_eq!(dict.len(), 10);
+        while dict.rehash(100) {}
+        assert!(dict.tables[0].len() <= 128);
+        let mut keys: Vec<_> = dict.iter().map(|(_, value)| *value).collect();
+        keys.sort();
+        assert_eq!(keys, (990..1000).collect::<Vec<_>>());
+
+        dict.clear();
+        assert!(dict.is_empty() && dict.get("key:995").is_none());
+    }
+
+    #[test]
+    fn test_incremental_rehash() {
+        let mut dict = filled(4);
+        assert_eq!(dict.tables[0].len(), 4);
+        // 第 5 个键触发扩容，之后每次写操作只搬迁一个桶
+        dict.insert("key:4".into(), 4);
+        assert!(dict.is_rehashing());
+        assert_eq!(dict.tables[1].len(), 8);
+        for i in 0..5 {
+            assert_eq!(dict.get(&format!("key:{i}")), Some(&i));
+        }
+        while dict.is_rehashing() {
+            dict.get_mut("key:0");
+        }
+        assert_eq!(dict.tables[0].len(), 8);
+        assert!(dict.tables[1].is_empty());
+        assert_eq!(dict.iter().count(), 5);
+    }
+
+    #[test]
+    fn test_scan() {
+        let mut dict = filled(100);
+        let keys = scan_all(&mut dict, |_, _| {});
+        assert_eq!(keys.len(), 100);
+        assert_eq!(keys.iter().collect::<HashSet<_>>().len(), 100);
+        assert_eq!(Dict::<u32>::new().scan(0, |_, _| panic!()), 0);
+    }
+
+    #[test]
+    fn test_scan_while_growing() {
+        let mut dict = filled(100);
+        let original: HashSet<_> = dict.iter().map(|(key, _)| key.clone()).collect();
+        // 遍历的前 100 步各写入 10 个新键，表经历多次扩容与搬迁
+        let keys: HashSet<_> = scan_all(&mut dict, |dict, i| {
+            for j in (0..10).filter(|_| i < 100) {
+                dict.insert(format!("new:{i}:{j}"), 0);
+            }
+        })
+        .into_iter()
+        .collect();
+        assert!(dict.tables[0].len() > 128);
+        assert!(original.is_subset(&keys));
+    }
+
+    #[test]
+    fn test_scan_while_shrinking() {
+        let mut dict = filled(2000);
+        while dict.rehash(100) {}
+        // 只保留 100 个键，其余在遍历期间删除，表经历多次收缩与搬迁
+        let kept: HashSet<_> = (0..100).map(|i| format!("key:{i}")).collect();
+        let mut doomed = (100..2000).map(|i| format!("key:{i}"));
+        let keys: HashSet<_> = scan_all(&mut dict, |dict, _| {
+            for key in doomed.by_ref().take(40) {
+                dict.remove(&key);
+            }
+            dict.get_mut("key:0");
+        })
+        .into_iter()
+        .collect();
+        assert_eq!(dict.tables[0].len(), 256);
+        assert!(kept.is_subset(&keys));
+    }
+}
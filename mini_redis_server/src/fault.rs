@@ -0,0 +1,151 @@
+//! 故障注入
+//!
+//! 供测试确定性地验证客户端的超时与重试逻辑。以 `fault-injection` 特性编译后可以使用 DEBUG 命令
+//! （未开启时 DEBUG 的各子命令一律返回错误）：
+//! - `DEBUG SLEEP <seconds>`：执行命令的连接等待给定时长后回复
+//! - `DEBUG DELAY <command|*> <ms>`：之后每次执行该命令（`*` 为全部命令）前先等待，0 表示取消
+//! - `DEBUG DROP <command|*> [<count>]`：之后 `count` 次（默认 1 次）收到该命令时不执行也不回复，
+//!   直接关闭连接（此前已执行的命令的回复照常写出）
+//! - `DEBUG CONTEND <ms>`：对当前数据库的全部分片加写锁并保持给定时长，期间访问该数据库的命令都要等待；
+//!   锁释放后才回复
+//! - `DEBUG FAULTS`：列出生效的延迟与断开设置
+//! - `DEBUG RESET`：清除全部设置
+//!
+//! 命令名与 [`Command::name`](crate::command::Command::name) 相同（小写，子命令形如 `config|get`），
+//! 只写主命令名时对其全部子命令生效。延迟与断开只作用于从网络读取的命令
+//! （见 [`Session::execute_with_args`](crate::session::Session::execute_with_args)），
+//! DEBUG 命令本身不受影响，因此总能清除设置。同一条命令同时设置了两者时先断开。
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+/// 所有命令
+pub const ANY: &str = "*";
+
+/// 执行命令前要注入的故障
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fault {
+    /// 等待给定时长后执行
+    Delay(Duration),
+    /// 不执行，关闭连接
+    Drop,
+}
+
+#[derive(Default)]
+struct State {
+    /// 命令名 -> 注入的延迟（毫秒）
+    delays: BTreeMap<String, u64>,
+    /// 命令名 -> 剩余的断开次数
+    drops: BTreeMap<String, u64>,
+}
+
+/// 生效的故障设置
+#[derive(Default)]
+pub struct Faults {
+    state: Mutex<State>,
+    /// 是否有任何设置，没有时执行命令前不加锁
+    active: AtomicBool,
+}
+
+impl Faults {
+    /// 设置命令的延迟，0 表示取消
+    pub fn set_delay(&self, command: &str, ms: u64) {
+        self.update(|state| {
+            if ms == 0 {
+                state.delays.remove(command);
+            } else {
+                state.delays.insert(command.to_string(), ms);
+            }
+        });
+    }
+
+    /// 之后 `count` 次收到该命令时断开连接，与已有的次数累加
+    pub fn add_drops(&self, command: &str, count: u64) {
+        self.update(|state| *state.drops.entry(command.to_string()).or_default() += count);
+    }
+
+    /// 清除全部设置
+    pub fn reset(&self) {
+        self.update(|state| *state = State::default());
+    }
+
+    /// 生效的设置，每项形如 `delay get 100` 或 `drop * 2`
+    pub fn list(&self) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        let delays = state.delays.iter().map(|(command, ms)| format!("delay {command} {ms}"));
+        let drops = state.drops.iter().map(|(command, count)| format!("drop {command} {count}"));
+        delays.chain(drops).collect()
+    }
+
+    /// 执行名为 `name` 的命令前调用，返回要注入的故障；断开会消耗一次次数
+    pub fn before(&self, name: &str) -> Option<Fault> {
+        if !self.active.load(Ordering::Relaxed) {
+            return None;
+        }
+        let mut state = self.state.lock().unwrap();
+        let parent = name.split('|').next().unwrap_or(name);
+        let matched = |map: &BTreeMap<String, u64>| {
+            [name, parent, ANY].into_iter().find(|pattern| map.contains_key(*pattern))
+        };
+
+        if let Some(pattern) = matched(&state.drops).map(str::to_string) {
+            let remaining = state.drops.get_mut(&pattern).expect("pattern was just found");
+            *remaining -= 1;
+            if *remaining == 0 {
+                state.drops.remove(&pattern);
+            }
+            self.refresh(&state);
+            return Some(Fault::Drop);
+        }
+        let ms = state.delays[matched(&state.delays)?];
+        Some(Fault::Delay(Duration::from_millis(ms)))
+    }
+
+    fn update(&self, f: impl FnOnce(&mut State)) {
+        let mut state = self.state.lock().unwrap();
+        f(&mut state);
+        self.refresh(&state);
+    }
+
+    fn refresh(&self, state: &State) {
+        let active = !state.delays.is_empty() || !state.drops.is_empty();
+        self.active.store(active, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_faults() {
+        let faults = Faults::default();
+        assert_eq!(faults.before("get"), None);
+
+        faults.set_delay("config", 20);
+        faults.set_delay(ANY, 5);
+        faults.add_drops("set", 1);
+        faults.add_drops("set", 1);
+        assert_eq!(faults.list(), ["delay * 5", "delay config 20", "drop set 2"]);
+
+        // 子命令按主命令名匹配，其余命令按 `*` 匹配
+        assert_eq!(faults.before("config|get"), Some(Fault::Delay(Duration::from_millis(20))));
+        assert_eq!(faults.before("get"), Some(Fault::Delay(Duration::from_millis(5))));
+        // 断开优先，次数用完后恢复为延迟
+        assert_eq!(faults.before("set"), Some(Fault::Drop));
+        assert_eq!(faults.before("set"), Some(Fault::Drop));
+        assert_eq!(faults.before("set"), Some(Fault::Delay(Duration::from_millis(5))));
+
+        faults.set_delay(ANY, 0);
+        assert_eq!(faults.before("get"), None);
+        faults.reset();
+        assert!(faults.list().is_empty());
+        assert_eq!(faults.before("config|get"), None);
+    }
+}
@@ -0,0 +1,478 @@
+//! 响应帧模块
+//!
+//! 定义命令执行结果的类型化表示，对应 Redis 协议中的各类回复。
+//! `Display` 实现按照 `redis-cli` 的风格输出，便于在测试和演示中直接打印；
+//! [`Frame::encode`] / [`Frame::decode`] 负责 RESP 协议的编码与解码，供网络层使用。
+//!
+//! 除 RESP2 的类型外还支持 RESP3 新增的映射、集合、浮点数、布尔值、推送消息与大整数。
+//! 处理函数总是返回类型化的回复，由 [`Frame::encode_with`] 按连接协商的协议版本（见 HELLO）编码：
+//! 在 RESP2 下映射展开为键值交替的数组，集合与推送消息编码为数组，浮点数与大整数编码为批量字符串，
+//! 布尔值编码为整数 1 / 0。`Display` 输出的是默认的 RESP2 客户端看到的形式。
+
+use std::fmt;
+
+/// 聚合类型允许的最大嵌套层数，防止恶意构造的深层嵌套耗尽解码时的栈空间
+pub const MAX_NESTING: usize = 128;
+
+/// 协议错误：收到的数据不符合 RESP 格式
+#[derive(Debug, PartialEq)]
+pub struct ProtocolError(pub String);
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ERR Protocol error: {}", self.0)
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+/// 协议版本
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Protocol {
+    #[default]
+    Resp2,
+    Resp3,
+}
+
+impl Protocol {
+    /// HELLO 命令中的协议版本号
+    pub fn version(self) -> i64 {
+        match self {
+            Protocol::Resp2 => 2,
+            Protocol::Resp3 => 3,
+        }
+    }
+}
+
+/// 命令执行后返回给客户端的回复
+#[derive(Clone, Debug, PartialEq)]
+pub enum Frame {
+    /// 简单字符串，例如 `OK`
+    Simple(String),
+    /// 错误信息，例如 `ERR unknown command`
+    Error(String),
+    /// 整数
+    Integer(i64),
+    /// 批量字符串（键或字段的值）
+    Bulk(String),
+    /// 内容不是 UTF-8 的批量字符串，例如位图
+    Binary(Vec<u8>),
+    /// 空值
+    Null,
+    /// 数组
+    Array(Vec<Frame>),
+    /// 映射（RESP3），按插入顺序保存键值对
+    Map(Vec<(Frame, Frame)>),
+    /// 集合（RESP3）
+    Set(Vec<Frame>),
+    /// 浮点数（RESP3）
+    Double(f64),
+    /// 布尔值（RESP3）
+    Boolean(bool),
+    /// 服务器主动推送的消息（RESP3），例如发布/订阅消息
+    Push(Vec<Frame>),
+    /// 超出 64 位整数范围的整数（RESP3），以十进制字符串保存
+    BigNumber(String),
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Frame::Simple(s) | Frame::Error(s) | Frame::Bulk(s) | Frame::BigNumber(s) => {
+                write!(f, "{s}")
+            }
+            Frame::Binary(bytes) => write!(f, "{}", String::from_utf8_lossy(bytes)),
+            Frame::Integer(n) => write!(f, "(integer) {n}"),
+            Frame::Boolean(b) => write!(f, "(integer) {}", i64::from(*b)),
+            Frame::Double(n) => write!(f, "{}", format_double(*n)),
+            Frame::Null => write!(f, "(nil)"),
+            Frame::Map(pairs) => {
+                let items = pairs.iter().flat_map(|(key, value)| [key, value]);
+                write_items(f, items)
+            }
+            Frame::Array(items) | Frame::Set(items) | Frame::Push(items) => {
+                write_items(f, items.iter())
+            }
+        }
+    }
+}
+
+/// 逐项输出 `1) item` 形式的列表，没有元素时输出 `(empty array)`
+fn write_items<'a>(
+    f: &mut fmt::Formatter<'_>,
+    items: impl Iterator<Item = &'a Frame>,
+) -> fmt::Result {
+    let mut empty = true;
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            writeln!(f)?;
+        }
+        write!(f, "{}) {item}", i + 1)?;
+        empty = false;
+    }
+    if empty { write!(f, "(empty array)") } else { Ok(()) }
+}
+
+/// 浮点数的文本形式，与 Redis 一致：无穷大写作 `inf` / `-inf`
+fn format_double(n: f64) -> String {
+    n.to_string()
+}
+
+impl Frame {
+    /// 按 RESP2 格式编码
+    pub fn encode(&self) -> Vec<u8> {
+        self.encode_with(Protocol::Resp2)
+    }
+
+    /// 按指定的协议版本编码
+    pub fn encode_with(&self, protocol: Protocol) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode_into(&mut buf, protocol);
+        buf
+    }
+
+    /// 按指定的协议版本编码并追加到缓冲区末尾
+    pub fn encode_into(&self, buf: &mut Vec<u8>, protocol: Protocol) {
+        let resp3 = protocol == Protocol::Resp3;
+        match self {
+            Frame::Simple(s) => buf.extend_from_slice(format!("+{s}\r\n").as_bytes()),
+            Frame::Error(s) => buf.extend_from_slice(format!("-{s}\r\n").as_bytes()),
+            Frame::Integer(n) => buf.extend_from_slice(format!(":{n}\r\n").as_bytes()),
+            Frame::Bulk(s) => encode_bulk(buf, s.as_bytes()),
+            Frame::Binary(bytes) => encode_bulk(buf, bytes),
+            Frame::Null if resp3 => buf.extend_from_slice(b"_\r\n"),
+            Frame::Null => buf.extend_from_slice(b"$-1\r\n"),
+            Frame::Array(items) => encode_aggregate(buf, '*', items, protocol),
+            Frame::Map(pairs) if resp3 => {
+                buf.extend_from_slice(format!("%{}\r\n", pairs.len()).as_bytes());
+                for (key, value) in pairs {
+                    key.encode_into(buf, protocol);
+                    value.encode_into(buf, protocol);
+                }
+            }
+            Frame::Map(pairs) => {
+                buf.extend_from_slice(format!("*{}\r\n", pairs.len() * 2).as_bytes());
+                for (key, value) in pairs {
+                    key.encode_into(buf, protocol);
+                    value.encode_into(buf, protocol);
+                }
+            }
+            Frame::Set(items) => {
+                encode_aggregate(buf, if resp3 { '~' } else { '*' }, items, protocol)
+            }
+            Frame::Push(items) => {
+                encode_aggregate(buf, if resp3 { '>' } else { '*' }, items, protocol)
+            }
+            Frame::Double(n) if resp3 => {
+                buf.extend_from_slice(format!(",{}\r\n", format_double(*n)).as_bytes())
+            }
+            Frame::Double(n) => encode_bulk(buf, format_double(*n).as_bytes()),
+            Frame::Boolean(b) if resp3 => {
+                buf.extend_from_slice(if *b { b"#t\r\n" } else { b"#f\r\n" })
+            }
+            Frame::Boolean(b) => {
+                buf.extend_from_slice(format!(":{}\r\n", i64::from(*b)).as_bytes())
+            }
+            Frame::BigNumber(n) if resp3 => buf.extend_from_slice(format!("({n}\r\n").as_bytes()),
+            Frame::BigNumber(n) => encode_bulk(buf, n.as_bytes()),
+        }
+    }
+
+    /// 从缓冲区开头解码一个完整的帧，返回帧与消耗的字节数；数据不完整时返回 `None`
+    pub fn decode(buf: &[u8]) -> Result<Option<(Frame, usize)>, ProtocolError> {
+        Self::decode_with(buf, usize::MAX, usize::MAX)
+    }
+
+    /// 与 [`Frame::decode`] 相同，但批量字符串超过 `max_bulk_len` 字节、聚合类型超过
+    /// `max_multibulk_len` 个元素时立即返回协议错误，不等待数据到达；用于解码客户端请求
+    pub fn decode_with(
+        buf: &[u8],
+        max_bulk_len: usize,
+        max_multibulk_len: usize,
+    ) -> Result<Option<(Frame, usize)>, ProtocolError> {
+        let limits = Limits { max_bulk_len, max_multibulk_len };
+        let mut pos = 0;
+        Ok(decode_at(buf, &mut pos, 0, limits)?.map(|frame| (frame, pos)))
+    }
+
+    /// 将由批量字符串组成的数组（客户端请求的格式）转换为参数列表
+    pub fn into_args(self) -> Result<Vec<String>, ProtocolError> {
+        let Frame::Array(items) = self else {
+            return Err(ProtocolError("expected an array of bulk strings".into()));
+        };
+        items
+            .into_iter()
+            .map(|item| match item {
+                Frame::Bulk(arg) => Ok(arg),
+                // 命令参数只支持 UTF-8
+                Frame::Binary(_) => Err(ProtocolError("bulk string is not valid UTF-8".into())),
+                _ => Err(ProtocolError("expected an array of bulk strings".into())),
+            })
+            .collect()
+    }
+}
+
+impl From<Vec<String>> for Frame {
+    /// 将参数列表转换为批量字符串数组（发送命令的格式）
+    fn from(args: Vec<String>) -> Self {
+        Frame::Array(args.into_iter().map(Frame::Bulk).collect())
+    }
+}
+
+fn encode_bulk(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend_from_slice(format!("${}\r\n", bytes.len()).as_bytes());
+    buf.extend_from_slice(bytes);
+    buf.extend_from_slice(b"\r\n");
+}
+
+fn encode_aggregate(buf: &mut Vec<u8>, kind: char, items: &[Frame], protocol: Protocol) {
+    buf.extend_from_slice(format!("{kind}{}\r\n", items.len()).as_bytes());
+    for item in items {
+        item.encode_into(buf, protocol);
+    }
+}
+
+/// 解码时对长度前缀的限制
+#[derive(Clone, Copy)]
+struct Limits {
+    max_bulk_len: usize,
+    max_multibulk_len: usize,
+}
+
+/// 解码 `pos` 处的一个帧，`depth` 为它所在的聚合类型的嵌套层数
+fn decode_at(
+    buf: &[u8],
+    pos: &mut usize,
+    depth: usize,
+    limits: Limits,
+) -> Result<Option<Frame>, ProtocolError> {
+    let Some(line) = read_line(buf, pos)? else { return Ok(None) };
+    // 类型字节不是 ASCII 时不能按字符边界切分
+    let Some((kind, rest)) = line.split_at_checked(1) else {
+        return Err(ProtocolError(format!("unexpected type byte '{}'", line.as_bytes()[0])));
+    };
+
+    let frame = match kind {
+        "+" => Frame::Simple(rest.to_string()),
+        "-" => Frame::Error(rest.to_string()),
+        ":" => Frame::Integer(parse_number(rest)?),
+        "$" => match parse_number(rest)? {
+            -1 => Frame::Null,
+            len if len < 0 => return Err(ProtocolError(format!("invalid bulk length {len}"))),
+            len => {
+                // 长度前缀由对端给出，不能假定加法不会溢出
+                let len = usize::try_from(len).ok().filter(|len| *len <= limits.max_bulk_len);
+                let end = len.and_then(|len| pos.checked_add(len));
+                let Some(end) = end.filter(|end| *end <= usize::MAX - 2) else {
+                    return Err(ProtocolError("invalid bulk length".into()));
+                };
+                if buf.len() < end + 2 {
+                    return Ok(None);
+                }
+                if &buf[end..end + 2] != b"\r\n" {
+                    return Err(ProtocolError("expected CRLF after bulk string".into()));
+                }
+                let data = buf[*pos..end].to_vec();
+                *pos = end + 2;
+                String::from_utf8(data).map_or_else(|e| Frame::Binary(e.into_bytes()), Frame::Bulk)
+            }
+        },
+        "*" => match parse_number(rest)? {
+            -1 => Frame::Null,
+            len => match decode_items(buf, pos, len, depth, limits)? {
+                Some(items) => Frame::Array(items),
+                None => return Ok(None),
+            },
+        },
+        "~" | ">" => match decode_items(buf, pos, parse_number(rest)?, depth, limits)? {
+            Some(items) if kind == "~" => Frame::Set(items),
+            Some(items) => Frame::Push(items),
+            None => return Ok(None),
+        },
+        "%" => {
+            let len = parse_number(rest)?;
+            let Some(items) = decode_items(buf, pos, len.saturating_mul(2), depth, limits)? else {
+                return Ok(None);
+            };
+            let mut items = items.into_iter();
+            let mut pairs = Vec::with_capacity(items.len() / 2);
+            while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                pairs.push((key, value));
+            }
+            Frame::Map(pairs)
+        }
+        "_" if rest.is_empty() => Frame::Null,
+        "#" => match rest {
+            "t" => Frame::Boolean(true),
+            "f" => Frame::Boolean(false),
+            _ => return Err(ProtocolError(format!("invalid boolean '{rest}'"))),
+        },
+        "," => Frame::Double(
+            rest.parse().map_err(|_| ProtocolError(format!("invalid double '{rest}'")))?,
+        ),
+        "(" if !rest.is_empty()
+            && rest.trim_start_matches('-').bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            Frame::BigNumber(rest.to_string())
+        }
+        _ => return Err(ProtocolError(format!("unexpected type byte '{kind}'"))),
+    };
+    Ok(Some(frame))
+}
+
+/// 解码聚合类型的 `len` 个元素，数据不完整时返回 `None`；`depth` 为该聚合类型所在的嵌套层数
+fn decode_items(
+    buf: &[u8],
+    pos: &mut usize,
+    len: i64,
+    depth: usize,
+    limits: Limits,
+) -> Result<Option<Vec<Frame>>, ProtocolError> {
+    if len < 0 {
+        return Err(ProtocolError(format!("invalid array length {len}")));
+    }
+    if len as u64 > limits.max_multibulk_len as u64 {
+        return Err(ProtocolError("invalid multibulk length".into()));
+    }
+    if depth >= MAX_NESTING {
+        return Err(ProtocolError("too many nested aggregates".into()));
+    }
+    let mut items = Vec::with_capacity(len.min(1024) as usize);
+    for _ in 0..len {
+        let Some(item) = decode_at(buf, pos, depth + 1, limits)? else { return Ok(None) };
+        items.push(item);
+    }
+    Ok(Some(items))
+}
+
+/// 读取一行（不含 CRLF），数据不完整时返回 `None`
+fn read_line<'a>(buf: &'a [u8], pos: &mut usize) -> Result<Option<&'a str>, ProtocolError> {
+    let rest = &buf[*pos..];
+    let Some(end) = rest.windows(2).position(|w| w == b"\r\n") else { return Ok(None) };
+    if end == 0 {
+        return Err(ProtocolError("empty line".into()));
+    }
+
+    let line = std::str::from_utf8(&rest[..end])
+        .map_err(|_| ProtocolError("line is not valid UTF-8".into()))?;
+    *pos += end + 2;
+    Ok(Some(line))
+}
+
+fn parse_number(s: &str) -> Result<i64, ProtocolError> {
+    s.parse().map_err(|_| ProtocolError(format!("invalid number '{s}'")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Frame, MAX_NESTING, Protocol, ProtocolError};
+
+    #[test]
+    fn test_display_scalars() {
+        assert_eq!(Frame::Simple("OK".into()).to_string(), "OK");
+        assert_eq!(Frame::Integer(3).to_string(), "(integer) 3");
+        assert_eq!(Frame::Null.to_string(), "(nil)");
+    }
+
+    #[test]
+    fn test_display_array() {
+        let frame = Frame::Array(vec![Frame::Bulk("a".into()), Frame::Bulk("b".into())]);
+
+        assert_eq!(frame.to_string(), "1) a\n2) b");
+        assert_eq!(Frame::Array(vec![]).to_string(), "(empty array)");
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        let frame = Frame::Array(vec![
+            Frame::Simple("OK".into()),
+            Frame::Error("ERR bad".into()),
+            Frame::Integer(-3),
+            Frame::Bulk("a b\r\nc".into()),
+            Frame::Binary(vec![0xff, 0x00]),
+            Frame::Null,
+            Frame::Array(vec![]),
+        ]);
+        let buf = frame.encode();
+
+        assert_eq!(Frame::decode(&buf), Ok(Some((frame, buf.len()))));
+        assert_eq!(Frame::decode(&buf[..buf.len() - 1]), Ok(None));
+        assert_eq!(Frame::decode(b""), Ok(None));
+    }
+
+    #[test]
+    fn test_resp3_encoding() {
+        let frame = Frame::Map(vec![
+            (Frame::Bulk("set".into()), Frame::Set(vec![Frame::Bulk("a".into())])),
+            (Frame::Bulk("score".into()), Frame::Double(1.5)),
+            (Frame::Bulk("ok".into()), Frame::Boolean(true)),
+            (Frame::Bulk("big".into()), Frame::BigNumber("123456789012345678901234".into())),
+            (Frame::Bulk("none".into()), Frame::Null),
+        ]);
+
+        let resp3 = frame.encode_with(Protocol::Resp3);
+        assert_eq!(
+            resp3,
+            b"%5\r\n$3\r\nset\r\n~1\r\n$1\r\na\r\n$5\r\nscore\r\n,1.5\r\n$2\r\nok\r\n#t\r\n\
+              $3\r\nbig\r\n(123456789012345678901234\r\n$4\r\nnone\r\n_\r\n"
+        );
+        assert_eq!(Frame::decode(&resp3), Ok(Some((frame.clone(), resp3.len()))));
+
+        // RESP2 下降级为数组、批量字符串与整数
+        let resp2 = frame.encode();
+        let Ok(Some((Frame::Array(items), _))) = Frame::decode(&resp2) else { panic!() };
+        assert_eq!(items.len(), 10);
+        assert_eq!(items[1], Frame::Array(vec![Frame::Bulk("a".into())]));
+        assert_eq!(items[3], Frame::Bulk("1.5".into()));
+        assert_eq!(items[5], Frame::Integer(1));
+        assert_eq!(items[9], Frame::Null);
+
+        let push = Frame::Push(vec![Frame::Bulk("message".into())]);
+        assert_eq!(push.encode_with(Protocol::Resp3), b">1\r\n$7\r\nmessage\r\n");
+        assert_eq!(Frame::Double(f64::INFINITY).encode_with(Protocol::Resp3), b",inf\r\n");
+        assert_eq!(frame.to_string().lines().next(), Some("1) set"));
+        assert_eq!(Frame::Double(2.0).to_string(), "2");
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert!(Frame::decode(b"?x\r\n").is_err());
+        assert!(Frame::decode(b":abc\r\n").is_err());
+        assert!(Frame::decode(b"$3\r\nabcd\r\n").is_err());
+        assert_eq!(Frame::decode(b"*-2\r\n"), Err(ProtocolError("invalid array length -2".into())));
+        assert!(Frame::decode("é\r\n".as_bytes()).is_err());
+        // 超大的长度前缀只是数据不完整，不会溢出
+        assert_eq!(Frame::decode(format!("${}\r\nabc", i64::MAX).as_bytes()), Ok(None));
+
+        // 请求的长度前缀超过限制时不等数据到达就报错
+        let request = b"*2\r\n$3\r\nget\r\n$100\r\n";
+        assert_eq!(Frame::decode_with(request, 1024, 8), Ok(None));
+        assert_eq!(
+            Frame::decode_with(request, 64, 8),
+            Err(ProtocolError("invalid bulk length".into()))
+        );
+        assert_eq!(
+            Frame::decode_with(request, 1024, 1),
+            Err(ProtocolError("invalid multibulk length".into()))
+        );
+
+        // 嵌套层数超过上限时报错而不是耗尽栈空间
+        let nested = |depth| "*1\r\n".repeat(depth) + ":1\r\n";
+        assert!(Frame::decode(nested(MAX_NESTING).as_bytes()).unwrap().is_some());
+        assert_eq!(
+            Frame::decode(nested(100_000).as_bytes()),
+            Err(ProtocolError("too many nested aggregates".into()))
+        );
+    }
+
+    #[test]
+    fn test_args() {
+        let args = vec!["set".to_string(), "k".into(), "v".into()];
+        let frame = Frame::from(args.clone());
+
+        assert_eq!(frame.clone().into_args(), Ok(args));
+        assert!(Frame::Integer(1).into_args().is_err());
+        let binary = Frame::Array(vec![Frame::Bulk("get".into()), Frame::Binary(vec![0xff])]);
+        assert_eq!(binary.into_args(), Err(ProtocolError("bulk string is not valid UTF-8".into())));
+    }
+}
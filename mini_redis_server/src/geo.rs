@@ -0,0 +1,280 @@
+//! 地理位置模块
+//!
+//! 地理位置保存在有序集合中：经纬度被编码为 52 位的 geohash 作为分值，
+//! 经度与纬度各占 26 位，按位交错排列，因此相邻的位置通常有相近的分值。
+//!
+//! 搜索时先按搜索范围选择合适精度的网格，取中心所在的格子及其周围 8 个格子，
+//! 每个格子对应一段连续的分值区间；再对区间内的成员逐个计算距离，过滤掉范围外的成员。
+
+/// 经度范围
+pub const LON_MIN: f64 = -180.0;
+pub const LON_MAX: f64 = 180.0;
+/// 纬度范围，与 Web 墨卡托投影一致
+pub const LAT_MIN: f64 = -85.05112878;
+pub const LAT_MAX: f64 = 85.05112878;
+
+/// 经度与纬度各自的编码位数
+const STEP: u32 = 26;
+/// 计算距离使用的地球半径（米），与 Redis 相同
+const EARTH_RADIUS: f64 = 6372797.560856;
+
+/// 距离单位
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Unit {
+    #[default]
+    M,
+    Km,
+    Ft,
+    Mi,
+}
+
+impl Unit {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "m" => Some(Unit::M),
+            "km" => Some(Unit::Km),
+            "ft" => Some(Unit::Ft),
+            "mi" => Some(Unit::Mi),
+            _ => None,
+        }
+    }
+
+    /// 一个单位对应的米数
+    pub fn meters(self) -> f64 {
+        match self {
+            Unit::M => 1.0,
+            Unit::Km => 1000.0,
+            Unit::Ft => 0.3048,
+            Unit::Mi => 1609.34,
+        }
+    }
+}
+
+/// 搜索范围，长度都以米为单位
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    /// BYRADIUS：以中心为圆心的圆
+    Radius(f64),
+    /// BYBOX：以中心为中心、边与经纬线平行的矩形
+    Box { width: f64, height: f64 },
+}
+
+impl Shape {
+    /// 位置在范围内时返回它到中心的距离（米）
+    pub fn contains(&self, center: (f64, f64), point: (f64, f64)) -> Option<f64> {
+        match *self {
+            Shape::Radius(radius) => {
+                let dist = distance(center, point);
+                (dist <= radius).then_some(dist)
+            }
+            Shape::Box { width, height } => {
+                // 分别检查南北方向与（位置所在纬度上的）东西方向的距离
+                let lat_dist = EARTH_RADIUS * (point.1.to_radians() - center.1.to_radians()).abs();
+                if lat_dist > height / 2.0 || distance((center.0, point.1), point) > width / 2.0 {
+                    return None;
+                }
+                Some(distance(center, point))
+            }
+        }
+    }
+
+    /// 范围在南北方向与东西方向上的半径（米）
+    fn half_extent(&self) -> (f64, f64) {
+        match *self {
+            Shape::Radius(radius) => (radius, radius),
+            Shape::Box { width, height } => (width / 2.0, height / 2.0),
+        }
+    }
+}
+
+/// 搜索的中心
+#[derive(Clone, Debug, PartialEq)]
+pub enum Origin {
+    /// FROMMEMBER：以有序集合中某个成员的位置为中心
+    Member(String),
+    /// FROMLONLAT：以给定的经纬度为中心
+    LonLat(f64, f64),
+}
+
+/// 结果按距离排序的方向
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// GEOSEARCH 的查询条件
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    pub origin: Origin,
+    pub shape: Shape,
+    /// `None` 表示不排序；带 COUNT 且没有 ANY 时按升序排序
+    pub order: Option<Order>,
+    pub count: Option<usize>,
+    /// ANY：找到 `count` 个结果后立即停止，不保证是最近的
+    pub any: bool,
+}
+
+/// 搜索结果中的一个成员
+#[derive(Clone, Debug, PartialEq)]
+pub struct Match {
+    pub member: String,
+    /// 到中心的距离（米）
+    pub dist: f64,
+    pub hash: u64,
+    pub lon: f64,
+    pub lat: f64,
+}
+
+/// 把经纬度编码为 52 位 geohash，超出范围时返回 `None`
+pub fn encode(lon: f64, lat: f64) -> Option<u64> {
+    if !(LON_MIN..=LON_MAX).contains(&lon) || !(LAT_MIN..=LAT_MAX).contains(&lat) {
+        return None;
+    }
+    Some(interleave(cell(lat, LAT_MIN, LAT_MAX, STEP), cell(lon, LON_MIN, LON_MAX, STEP)))
+}
+
+/// 把 geohash 解码为所在格子中心的经纬度
+pub fn decode(hash: u64) -> (f64, f64) {
+    let (lat, lon) = deinterleave(hash);
+    let center = |i: u32, min: f64, max: f64| {
+        let width = (max - min) / (1u64 << STEP) as f64;
+        (min + (i as f64 + 0.5) * width).clamp(min, max)
+    };
+    (center(lon, LON_MIN, LON_MAX), center(lat, LAT_MIN, LAT_MAX))
+}
+
+/// 两个位置之间的球面距离（米），位置为 `(lon, lat)`
+pub fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lat2) = (a.1.to_radians(), b.1.to_radians());
+    let u = ((lat2 - lat1) / 2.0).sin();
+    let v = ((b.0.to_radians() - a.0.to_radians()) / 2.0).sin();
+    let h = u * u + lat1.cos() * lat2.cos() * v * v;
+    2.0 * EARTH_RADIUS * h.sqrt().asin()
+}
+
+/// 覆盖搜索范围的分值区间 `[min, max)`，按分值升序排列且互不重叠
+pub fn search_ranges(center: (f64, f64), shape: &Shape) -> Vec<(u64, u64)> {
+    let all = vec![(0, 1u64 << (STEP * 2))];
+    let (lon_extent, lat_extent) = shape.half_extent();
+    let dlat = (lat_extent / EARTH_RADIUS).to_degrees();
+    // 东西方向的度数在离赤道最远的一侧最大
+    let farthest_lat = center.1.abs() + dlat;
+    if farthest_lat >= 90.0 {
+        return all;
+    }
+    let dlon = (lon_extent / (EARTH_RADIUS * farthest_lat.to_radians().cos())).to_degrees();
+
+    // 选择格子宽高都不小于搜索半径的最高精度，使周围 8 个格子足以覆盖整个范围
+    let fit =
+        |span: f64, extent: f64| if extent > 0.0 { (span / extent).log2().floor() } else { 64.0 };
+    let step = fit(LON_MAX - LON_MIN, dlon).min(fit(LAT_MAX - LAT_MIN, dlat)).min(STEP as f64);
+    if step < 1.0 {
+        return all;
+    }
+    let step = step as u32;
+
+    let cells = 1i64 << step;
+    let lon_cell = cell(center.0, LON_MIN, LON_MAX, step) as i64;
+    let lat_cell = cell(center.1, LAT_MIN, LAT_MAX, step) as i64;
+    let shift = (STEP - step) * 2;
+    let mut ranges = Vec::with_capacity(9);
+    for lat in (lat_cell - 1..=lat_cell + 1).filter(|lat| (0..cells).contains(lat)) {
+        for lon in lon_cell - 1..=lon_cell + 1 {
+            // 经度方向首尾相接
+            let hash = interleave(lat as u32, lon.rem_euclid(cells) as u32);
+            ranges.push((hash << shift, (hash + 1) << shift));
+        }
+    }
+
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+    for (min, max) in ranges {
+        match merged.last_mut() {
+            Some(last) if min <= last.1 => last.1 = last.1.max(max),
+            _ => merged.push((min, max)),
+        }
+    }
+    merged
+}
+
+/// 坐标在 `step` 位精度的网格中所在格子的编号
+fn cell(value: f64, min: f64, max: f64, step: u32) -> u32 {
+    let cells = 1u64 << step;
+    let offset = (value - min) / (max - min) * cells as f64;
+    (offset as u64).min(cells - 1) as u32
+}
+
+/// 交错排列两个坐标的二进制位：纬度占偶数位，经度占奇数位
+fn interleave(lat: u32, lon: u32) -> u64 {
+    (0..32).fold(0, |hash, i| {
+        let lat_bit = (lat as u64 >> i) & 1;
+        let lon_bit = (lon as u64 >> i) & 1;
+        hash | lat_bit << (2 * i) | lon_bit << (2 * i + 1)
+    })
+}
+
+/// [`interleave`] 的逆运算，返回 `(lat, lon)`
+fn deinterleave(hash: u64) -> (u32, u32) {
+    (0..32).fold((0, 0), |(lat, lon), i| {
+        let lat_bit = ((hash >> (2 * i)) & 1) as u32;
+        let lon_bit = ((hash >> (2 * i + 1)) & 1) as u32;
+        (lat | lat_bit << i, lon | lon_bit << i)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Shape, decode, distance, encode, search_ranges};
+
+    const PALERMO: (f64, f64) = (13.361389, 38.115556);
+    const CATANIA: (f64, f64) = (15.087269, 37.502669);
+
+    #[test]
+    fn test_encode_decode() {
+        // 与 Redis 对 Palermo 计算的分值一致
+        assert_eq!(encode(PALERMO.0, PALERMO.1), Some(3479099956230698));
+        let (lon, lat) = decode(3479099956230698);
+        assert!((lon - PALERMO.0).abs() < 1e-5 && (lat - PALERMO.1).abs() < 1e-5);
+
+        assert_eq!(encode(181.0, 0.0), None);
+        assert_eq!(encode(0.0, 86.0), None);
+        assert!(encode(180.0, 85.05112878).is_some());
+    }
+
+    #[test]
+    fn test_distance() {
+        let dist = distance(PALERMO, CATANIA);
+        assert!((dist - 166274.2578).abs() < 0.001, "{dist}");
+        assert_eq!(distance(PALERMO, PALERMO), 0.0);
+    }
+
+    #[test]
+    fn test_shape_contains() {
+        assert!(Shape::Radius(200_000.0).contains(PALERMO, CATANIA).is_some());
+        assert_eq!(Shape::Radius(100_000.0).contains(PALERMO, CATANIA), None);
+
+        // Catania 在 Palermo 以东约 150km、以南约 68km
+        let wide = Shape::Box { width: 400_000.0, height: 200_000.0 };
+        assert!(wide.contains(PALERMO, CATANIA).is_some());
+        let flat = Shape::Box { width: 400_000.0, height: 100_000.0 };
+        assert_eq!(flat.contains(PALERMO, CATANIA), None);
+    }
+
+    #[test]
+    fn test_search_ranges_cover_matches() {
+        let shape = Shape::Radius(200_000.0);
+        let ranges = search_ranges(PALERMO, &shape);
+        assert!(ranges.len() <= 9);
+        let hash = encode(CATANIA.0, CATANIA.1).unwrap();
+        assert!(ranges.iter().any(|(min, max)| (*min..*max).contains(&hash)));
+
+        // 范围跨越经度 180° 时另一侧的格子也被覆盖
+        let east = encode(179.9, 0.0).unwrap();
+        let ranges = search_ranges((-179.9, 0.0), &Shape::Radius(50_000.0));
+        assert!(ranges.iter().any(|(min, max)| (*min..*max).contains(&east)));
+
+        // 范围过大时扫描全部分值
+        assert_eq!(search_ranges((0.0, 0.0), &Shape::Radius(1e8)), vec![(0, 1 << 52)]);
+    }
+}
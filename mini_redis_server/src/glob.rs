@@ -0,0 +1,123 @@
+//! glob 风格的模式匹配
+//!
+//! 与 Redis `stringmatchlen` 的语义一致，供 KEYS 与 PSUBSCRIBE 等命令使用：
+//!
+//! - `*`：匹配任意长度（包括 0）的字符序列
+//! - `?`：匹配任意单个字符
+//! - `[abc]` / `[^abc]` / `[a-z]`：匹配（或排除）字符集合与范围
+//! - `\x`：匹配字面字符 `x`
+
+/// 判断 `text` 是否匹配 glob 模式 `pattern`
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    match_from(&pattern, &text)
+}
+
+fn match_from(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // 最近一个 `*` 之后的模式位置，以及它当前吞掉的文本位置，用于回溯
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, t));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('[') => match_class(&pattern[p..], text[t]),
+            Some('\\') if p + 1 < pattern.len() => (pattern[p + 1] == text[t]).then_some(2),
+            Some(&c) => (c == text[t]).then_some(1),
+            None => None,
+        };
+
+        match step {
+            Some(consumed) => {
+                p += consumed;
+                t += 1;
+            }
+            None => match backtrack {
+                // 让上一个 `*` 多吞掉一个字符后重试
+                Some((star_p, star_t)) => {
+                    backtrack = Some((star_p, star_t + 1));
+                    p = star_p;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// 匹配以 `[` 开头的字符集合，成功时返回集合在模式中占用的字符数
+fn match_class(pattern: &[char], c: char) -> Option<usize> {
+    let mut i = 1;
+    let negate = pattern.get(i) == Some(&'^');
+    if negate {
+        i += 1;
+    }
+
+    let mut matched = false;
+    while i < pattern.len() && pattern[i] != ']' {
+        if pattern[i] == '\\' && i + 1 < pattern.len() {
+            matched |= pattern[i + 1] == c;
+            i += 2;
+        } else if i + 2 < pattern.len() && pattern[i + 1] == '-' && pattern[i + 2] != ']' {
+            let (lo, hi) = if pattern[i] <= pattern[i + 2] {
+                (pattern[i], pattern[i + 2])
+            } else {
+                (pattern[i + 2], pattern[i])
+            };
+            matched |= (lo..=hi).contains(&c);
+            i += 3;
+        } else {
+            matched |= pattern[i] == c;
+            i += 1;
+        }
+    }
+
+    // 缺少 `]` 时与 Redis 一样，视为集合延伸到模式末尾
+    let len = (i + 1).min(pattern.len());
+    (matched != negate).then_some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches;
+
+    #[test]
+    fn test_literal_and_wildcards() {
+        assert!(matches("hello", "hello"));
+        assert!(!matches("hello", "hell"));
+        assert!(matches("h?llo", "hallo"));
+        assert!(matches("h*llo", "hllo"));
+        assert!(matches("h*llo", "heeeello"));
+        assert!(matches("*", ""));
+        assert!(matches("news.*", "news.tech"));
+        assert!(!matches("news.*", "sports.tech"));
+        assert!(matches("*a*b*", "xxaxxbxx"));
+        assert!(!matches("*a*b", "xxaxxbxx"));
+    }
+
+    #[test]
+    fn test_character_classes() {
+        assert!(matches("h[ae]llo", "hello"));
+        assert!(!matches("h[ae]llo", "hillo"));
+        assert!(matches("h[^e]llo", "hallo"));
+        assert!(!matches("h[^e]llo", "hello"));
+        assert!(matches("h[a-b]llo", "hbllo"));
+        assert!(matches("h[b-a]llo", "hbllo"));
+        assert!(!matches("h[a-b]llo", "hcllo"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert!(matches("h\\*llo", "h*llo"));
+        assert!(!matches("h\\*llo", "hello"));
+        assert!(matches("[\\]]", "]"));
+    }
+}
@@ -3,13 +3,44 @@
 //! 负责执行具体命令逻辑：
 //! 1. 解析输入字符串为 Command；
 //! 2. 调用数据库操作；
-//! 3. 返回结果字符串。
+//! 3. 返回结果。
 //!
 //! 模块设计目标：
 //! - 与 I/O 解耦（纯逻辑层）
 //! - 可独立单元测试
+//!
+//! 各命令的执行逻辑按类别分布在子模块中，通过命令注册表（见 [`Registry`]）分发。
+
+mod bitmap;
+mod connection;
+mod geo;
+mod hash;
+mod introspection;
+mod keyspace;
+mod pubsub;
+mod registry;
+mod sentinel;
+mod server;
+mod set;
+mod stream;
+mod string;
+mod zset;
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    bitmap::BitOp,
+    command::{Command, Expiry, GeoSearch, SetOptions},
+    db::{Db, DbError, KeyEvent, Storage, unix_time_ms},
+    frame::Frame,
+    geo::Match,
+    persistence::decode_json,
+    stream::{Entry, PendingSummary},
+};
 
-use crate::{command::Command, db::Db};
+pub use self::registry::{
+    BoxFuture, CommandHandler, Exec, Flag, Handler, Registry, Renames, Spec, spec, specs,
+};
 
 /// 处理一条命令行字符串，返回执行结果。
 ///
@@ -19,25 +50,498 @@ use crate::{command::Command, db::Db};
 ///
 /// # 返回
 /// * 返回 Redis 风格的字符串响应：例如 `"OK"` 或 `"ERR ..."`
-pub async fn process_command(db: &Db, input: &str) -> String {
-    let command: Command = Command::parse(input);
+pub async fn process_command<S: Storage>(db: &Db<S>, input: &str) -> String {
+    match Command::parse(input) {
+        Ok(command) => execute(db, command).await.to_string(),
+        Err(e) => e.to_string(),
+    }
+}
+
+/// 执行一条已解析的命令，返回类型化的回复帧。
+///
+/// 带 [`Flag::Write`] 的命令成功执行后会被传播到追加日志（见 `propagation`），
+/// 产生的键变更在释放全部锁之后通知给注册的观察者（见 `db::hooks`）。
+pub async fn execute<S: Storage>(db: &Db<S>, command: Command) -> Frame {
+    db.stats().command_processed();
+    let flags = db.commands().get(command.name()).map_or(&[][..], |handler| handler.flags());
+    if !flags.contains(&Flag::Write) {
+        return dispatch(db, command).await;
+    }
+
+    let observed = db.hooks().is_active().then(|| command.clone());
+    let reply = execute_write(db, command, flags).await;
+    if let Some(command) = observed {
+        let keys = changed_keys(db, &command, &reply);
+        db.record_changes(keys).await;
+    }
+    db.hooks().dispatch();
+    reply
+}
+
+/// 执行写命令并按命令的标志传播
+///
+/// 从执行到传播完成一直持有命令涉及的键的顺序锁（见 [`Db::lock_order`]），
+/// 同一个键上的写命令按执行的顺序进入追加日志与复制流。
+async fn execute_write<S: Storage>(db: &Db<S>, command: Command, flags: &[Flag]) -> Frame {
+    // 阻塞命令在等待期间、MIGRATE 在与目标节点通信期间不能持有写门闩与顺序锁，
+    // XREADGROUP / XCLAIM 的投递状态依赖当前时间，IMPORT 逐个键传播 RESTORE，
+    // 都由 `Db` 在修改数据时自行传播
+    if flags.contains(&Flag::SelfPropagating) {
+        return dispatch(db, command).await;
+    }
 
+    let _gate = db.enter_write().await;
+    let freed = if flags.contains(&Flag::DenyOom) { db.free_memory().await } else { Ok(()) };
+    if let Err(e) = freed {
+        return Frame::Error(e.to_string());
+    }
+    let _order = db.lock_order(&command.keys()).await;
+    let command = absolute_expiry(command);
+    let write = command.clone();
+    let reply = dispatch(db, command).await;
+
+    match propagation(&write, &reply, flags).map(|args| db.propagate(&args)) {
+        Some(Err(e)) => Frame::Error(DbError::from(e).to_string()),
+        _ => reply,
+    }
+}
+
+/// 执行命令本身，不做传播，并记录执行时间（见 `stats` 与 `latency` 模块）
+async fn dispatch<S: Storage>(db: &Db<S>, command: Command) -> Frame {
+    let name = command.name();
+    let Some(handler) = db.commands().get(name) else {
+        return Frame::Error(format!("ERR unknown command '{name}'"));
+    };
+    let started = Instant::now();
+    let result = handler.execute(db, command).await;
+    let elapsed = started.elapsed();
+    db.stats().command_called(name, elapsed);
+    let event = if handler.flags().contains(&Flag::Fast) { "fast-command" } else { "command" };
+    db.latency().record(event, elapsed);
+
+    result.unwrap_or_else(|e| Frame::Error(e.to_string()))
+}
+
+/// 写命令修改了当前数据库中的哪些键，没有产生修改时为空
+///
+/// 整体替换数据库的命令与 MOVE 的目标键不在返回值中，直接记录为事件。
+fn changed_keys<S: Storage>(db: &Db<S>, command: &Command, reply: &Frame) -> Vec<String> {
+    let modified = match command {
+        Command::BZPopMin(..)
+        | Command::BZPopMax(..)
+        | Command::XReadGroup(..)
+        | Command::XClaim(..)
+        | Command::Migrate(..) => {
+            !matches!(reply, Frame::Error(_) | Frame::Null)
+                && *reply != Frame::Array(Vec::new())
+                && *reply != Frame::Simple("NOKEY".into())
+        }
+        Command::Import(_) => !matches!(reply, Frame::Error(_)),
+        command => modified(command, reply),
+    };
+    if !modified {
+        return Vec::new();
+    }
+
+    let hooks = db.hooks();
     match command {
-        Command::Get(key) => match db.get(&key).await {
-            Some(value) => value,
-            None => "(nil)".into(),
+        Command::FlushDb(_) => {
+            hooks.push(KeyEvent::Flush(db.index()));
+            Vec::new()
+        }
+        Command::FlushAll(_) => {
+            (0..db.database_count()).for_each(|index| hooks.push(KeyEvent::Flush(index)));
+            Vec::new()
+        }
+        Command::SwapDb(a, b) => {
+            hooks.push(KeyEvent::Flush(*a));
+            hooks.push(KeyEvent::Flush(*b));
+            Vec::new()
+        }
+        Command::Move(key, index) => {
+            hooks.push(KeyEvent::Set(*index, key.clone()));
+            vec![key.clone()]
+        }
+        // 只有被弹出元素的键发生了变化
+        Command::BZPopMin(..) | Command::BZPopMax(..) => match reply {
+            Frame::Array(items) => items.first().map(Frame::to_string).into_iter().collect(),
+            _ => Vec::new(),
         },
-        Command::Set(key, value) => {
-            db.set(key, value).await;
-            "OK".into()
+        // 源键只被读取
+        Command::SInterStore(dest, _)
+        | Command::SUnionStore(dest, _)
+        | Command::SDiffStore(dest, _)
+        | Command::BitOp(_, dest, _) => vec![dest.clone()],
+        Command::Import(lines) => {
+            lines.iter().filter_map(|line| Some(decode_json(line).ok()?.0)).collect()
+        }
+        command => command.keys().into_iter().map(String::from).collect(),
+    }
+}
+
+/// 将相对过期时间换算为绝对时间，保证执行与传播使用同一个截止时间
+fn absolute_expiry(command: Command) -> Command {
+    let absolute = |expiry: Expiry| Expiry::UnixMillis(expiry.deadline_ms(unix_time_ms()) as i64);
+
+    match command {
+        Command::SetWith(key, value, options @ SetOptions { expiry: Some(expiry), .. }) => {
+            let options = SetOptions { expiry: Some(absolute(expiry)), ..options };
+            Command::SetWith(key, value, options)
+        }
+        Command::Expire(key, expiry, flags) => Command::Expire(key, absolute(expiry), flags),
+        Command::Restore(key, ttl, payload, replace, false) if ttl > 0 => {
+            Command::Restore(key, unix_time_ms().saturating_add(ttl), payload, replace, true)
+        }
+        command => command,
+    }
+}
+
+/// 计算写命令需要传播的参数（命令名 + 参数），执行失败或没有产生修改时返回 `None`
+///
+/// 带 [`Flag::Deterministic`] 的命令原样传播（见 `verbatim`），其他写命令带随机性、
+/// 依赖当前时间或浮点运算，改写为确定性的等价形式（见 `rewrite`），保证回放得到与原执行完全相同的结果。
+///
+/// 带 [`Flag::SelfPropagating`] 的命令由 `Db` 在执行过程中自行传播，不经过这里：
+/// BZPOPMIN / BZPOPMAX 由 [`Db::bzpop`] 在弹出时传播，
+/// XREADGROUP / XCLAIM 由 [`Db::xreadgroup`] / [`Db::xclaim`] 以 XCLAIM 的形式传播，
+/// MIGRATE 由 [`Db::migrate`] 以 DEL 的形式传播被迁走的键，IMPORT 由 [`Db::import`] 以 RESTORE 的形式传播。
+fn propagation(command: &Command, reply: &Frame, flags: &[Flag]) -> Option<Vec<String>> {
+    if !modified(command, reply) {
+        return None;
+    }
+    Some(if flags.contains(&Flag::Deterministic) {
+        verbatim(command)
+    } else {
+        rewrite(command, reply)
+    })
+}
+
+/// 写命令是否产生了修改：执行失败、条件不满足或没有删除、弹出任何内容时不传播
+fn modified(command: &Command, reply: &Frame) -> bool {
+    match command {
+        _ if matches!(reply, Frame::Error(_)) => false,
+        Command::Expire(..)
+        | Command::Persist(..)
+        | Command::XGroupDestroy(..)
+        | Command::XGroupCreateConsumer(..)
+        | Command::Move(..) => *reply == Frame::Integer(1),
+        Command::XAck(..) | Command::Del(..) | Command::Unlink(..) => {
+            matches!(reply, Frame::Integer(n) if *n > 0)
+        }
+        Command::SPop(..) => match reply {
+            Frame::Bulk(_) => true,
+            Frame::Array(members) => !members.is_empty(),
+            _ => false,
+        },
+        Command::ZPopMin(..) | Command::ZPopMax(..) => {
+            matches!(reply, Frame::Array(items) if !items.is_empty())
+        }
+        // 回复为计算后的值与实际的消息 ID，改写时需要
+        Command::HIncrByFloat(..) | Command::XAdd(..) => matches!(reply, Frame::Bulk(_)),
+        _ => true,
+    }
+}
+
+/// 确定性写命令的传播形式，与客户端发来的命令等价
+fn verbatim(command: &Command) -> Vec<String> {
+    let (name, args) = match command {
+        Command::Persist(key) => ("persist", vec![key.clone()]),
+        Command::Incr(key) => ("incr", vec![key.clone()]),
+        Command::IncrBy(key, delta) => ("incrby", vec![key.clone(), delta.to_string()]),
+        Command::SetBit(key, offset, value) => {
+            ("setbit", vec![key.clone(), offset.to_string(), u8::from(*value).to_string()])
+        }
+        Command::BitOp(op, dest, keys) => {
+            let op = match op {
+                BitOp::And => "and",
+                BitOp::Or => "or",
+                BitOp::Xor => "xor",
+                BitOp::Not => "not",
+            };
+            (
+                "bitop",
+                [op.to_string(), dest.clone()].into_iter().chain(keys.iter().cloned()).collect(),
+            )
+        }
+        Command::HSet(key, pairs) => {
+            let pairs = pairs.iter().flat_map(|(field, value)| [field.clone(), value.clone()]);
+            ("hset", std::iter::once(key.clone()).chain(pairs).collect())
+        }
+        Command::HIncrBy(key, field, delta) => {
+            ("hincrby", vec![key.clone(), field.clone(), delta.to_string()])
+        }
+        Command::SAdd(key, members) => ("sadd", prefixed(key, members)),
+        Command::SRem(key, members) => ("srem", prefixed(key, members)),
+        Command::SInterStore(dest, keys) => ("sinterstore", prefixed(dest, keys)),
+        Command::SUnionStore(dest, keys) => ("sunionstore", prefixed(dest, keys)),
+        Command::SDiffStore(dest, keys) => ("sdiffstore", prefixed(dest, keys)),
+        Command::ZAdd(key, flags, pairs) => {
+            let options = [
+                (flags.nx, "nx"),
+                (flags.xx, "xx"),
+                (flags.gt, "gt"),
+                (flags.lt, "lt"),
+                (flags.ch, "ch"),
+            ];
+            let options = options.into_iter().filter(|(set, _)| *set).map(|(_, o)| o.to_string());
+            let pairs =
+                pairs.iter().flat_map(|(score, member)| [score.to_string(), member.clone()]);
+            ("zadd", std::iter::once(key.clone()).chain(options).chain(pairs).collect())
+        }
+        // 分值由坐标确定地计算得出
+        Command::GeoAdd(key, flags, locations) => {
+            let options = [(flags.nx, "nx"), (flags.xx, "xx"), (flags.ch, "ch")];
+            let options = options.into_iter().filter(|(set, _)| *set).map(|(_, o)| o.to_string());
+            let locations = locations
+                .iter()
+                .flat_map(|(lon, lat, member)| [lon.to_string(), lat.to_string(), member.clone()]);
+            ("geoadd", std::iter::once(key.clone()).chain(options).chain(locations).collect())
+        }
+        Command::ZIncrBy(key, delta, member) => {
+            ("zincrby", vec![key.clone(), delta.to_string(), member.clone()])
+        }
+        Command::ZPopMin(key, count) => {
+            ("zpopmin", vec![key.clone(), count.unwrap_or(1).to_string()])
+        }
+        Command::ZPopMax(key, count) => {
+            ("zpopmax", vec![key.clone(), count.unwrap_or(1).to_string()])
+        }
+        // `$` 在副本上解析为同样的最后 ID
+        Command::XGroupCreate(key, group, id, mkstream) => {
+            let id = id.map_or("$".to_string(), |id| id.to_string());
+            let mut args = vec!["create".into(), key.clone(), group.clone(), id];
+            args.extend(mkstream.then(|| "mkstream".to_string()));
+            ("xgroup", args)
+        }
+        Command::XGroupSetId(key, group, id) => {
+            let id = id.map_or("$".to_string(), |id| id.to_string());
+            ("xgroup", vec!["setid".into(), key.clone(), group.clone(), id])
+        }
+        Command::XGroupDestroy(key, group) => {
+            ("xgroup", vec!["destroy".into(), key.clone(), group.clone()])
+        }
+        Command::XGroupCreateConsumer(key, group, consumer) => {
+            ("xgroup", vec!["createconsumer".into(), key.clone(), group.clone(), consumer.clone()])
+        }
+        Command::XAck(key, group, ids) => {
+            let ids = ids.iter().map(|id| id.to_string());
+            ("xack", [key.clone(), group.clone()].into_iter().chain(ids).collect())
+        }
+        Command::Del(keys) => ("del", keys.clone()),
+        Command::Unlink(keys) => ("unlink", keys.clone()),
+        Command::FlushDb(_) => ("flushdb", Vec::new()),
+        Command::FlushAll(_) => ("flushall", Vec::new()),
+        Command::Move(key, index) => ("move", vec![key.clone(), index.to_string()]),
+        Command::SwapDb(a, b) => ("swapdb", vec![a.to_string(), b.to_string()]),
+        command => unreachable!("{} is not a deterministic write command", command.name()),
+    };
+    std::iter::once(name.to_string()).chain(args).collect()
+}
+
+/// 不确定的写命令改写后的传播形式，`reply` 为执行的结果：
+/// - SET / EXPIRE / RESTORE 的过期时间已由 `absolute_expiry` 换算为绝对时间，
+///   改写为 SET PXAT、无条件的 PEXPIREAT 与 RESTORE ABSTTL
+/// - GETSET 改写为 SET
+/// - SPOP 改写为 SREM 被弹出的成员
+/// - HINCRBYFLOAT 改写为 HSET 计算后的值
+/// - XADD 改写为带实际消息 ID 的形式
+fn rewrite(command: &Command, reply: &Frame) -> Vec<String> {
+    let (name, args) = match (command, reply) {
+        (Command::Set(key, value), _) => ("set", vec![key.clone(), value.clone()]),
+        (Command::GetSet(key, value), _) => ("set", vec![key.clone(), value.clone()]),
+        // GET 只影响回复，不传播
+        (Command::SetWith(key, value, options), _) => {
+            let mut args = vec![key.clone(), value.clone()];
+            if let Some(expiry) = options.expiry {
+                args.extend(["pxat".into(), expiry.deadline_ms(unix_time_ms()).to_string()]);
+            } else if options.keep_ttl {
+                args.push("keepttl".into());
+            }
+            ("set", args)
+        }
+        // 条件已在执行时判断过，传播为无条件的 PEXPIREAT
+        (Command::Expire(key, expiry, _), _) => {
+            ("pexpireat", vec![key.clone(), expiry.deadline_ms(unix_time_ms()).to_string()])
+        }
+        (Command::Restore(key, ttl, payload, replace, absttl), _) => {
+            let mut args = vec![key.clone(), ttl.to_string(), payload.clone()];
+            args.extend(replace.then(|| "replace".to_string()));
+            args.extend(absttl.then(|| "absttl".to_string()));
+            ("restore", args)
+        }
+        (Command::SPop(key, _), Frame::Bulk(member)) => ("srem", vec![key.clone(), member.clone()]),
+        (Command::SPop(key, _), Frame::Array(members)) => {
+            let members: Vec<_> = members.iter().map(Frame::to_string).collect();
+            ("srem", prefixed(key, &members))
+        }
+        (Command::HIncrByFloat(key, field, _), Frame::Bulk(value)) => {
+            ("hset", vec![key.clone(), field.clone(), value.clone()])
+        }
+        (Command::XAdd(key, _, fields), Frame::Bulk(id)) => {
+            let fields = fields.iter().flat_map(|(field, value)| [field.clone(), value.clone()]);
+            ("xadd", [key.clone(), id.clone()].into_iter().chain(fields).collect())
+        }
+        (command, _) => unreachable!("{} has no deterministic rewrite", command.name()),
+    };
+    std::iter::once(name.to_string()).chain(args).collect()
+}
+
+/// `first` 后接 `rest` 组成的参数列表
+fn prefixed(first: &str, rest: &[String]) -> Vec<String> {
+    std::iter::once(first.to_string()).chain(rest.iter().cloned()).collect()
+}
+
+/// 将过期时间转换为 TTL / PTTL 的回复：键不存在返回 -2，没有过期时间返回 -1
+fn ttl_reply(expire_time: Option<Option<u64>>, unit_ms: u64) -> Frame {
+    let ttl = match expire_time {
+        None => -2,
+        Some(None) => -1,
+        Some(Some(at)) => ((at.saturating_sub(unix_time_ms()) + unit_ms / 2) / unit_ms) as i64,
+    };
+    Frame::Integer(ttl)
+}
+
+/// 将过期时间转换为 EXPIRETIME / PEXPIRETIME 的回复：键不存在返回 -2，没有过期时间返回 -1
+fn expire_time_reply(expire_time: Option<Option<u64>>, unit_ms: u64) -> Frame {
+    let at = match expire_time {
+        None => -2,
+        Some(None) => -1,
+        Some(Some(at)) => (at / unit_ms) as i64,
+    };
+    Frame::Integer(at)
+}
+
+/// `<command> HELP` 的回复：按命令表列出命令 `name`（如 `config|help`）所属的全部子命令及其简介
+fn help_reply(name: &str) -> Frame {
+    let parent = name.split('|').next().unwrap_or(name);
+    let mut lines = vec![format!(
+        "{} <subcommand> [<arg> [value] [opt] ...]. Subcommands are:",
+        parent.to_ascii_uppercase()
+    )];
+    for spec in specs() {
+        if let Some(sub) = spec.name.strip_prefix(parent).and_then(|name| name.strip_prefix('|')) {
+            lines.push(sub.to_ascii_uppercase());
+            lines.push(format!("    {}", spec.summary));
         }
-        Command::Unknown => "ERR unknown command".into(),
     }
+    Frame::Array(lines.into_iter().map(Frame::Simple).collect())
+}
+
+/// 将可选值转换为批量字符串或空值
+fn bulk_or_null(value: Option<String>) -> Frame {
+    value.map_or(Frame::Null, Frame::Bulk)
+}
+
+/// 将字符串值转换为批量字符串，不是 UTF-8 的内容（例如位图）原样按字节回复
+fn bulk_bytes(value: Vec<u8>) -> Frame {
+    String::from_utf8(value).map_or_else(|e| Frame::Binary(e.into_bytes()), Frame::Bulk)
+}
+
+/// 将字符串列表转换为批量字符串数组
+fn bulk_array(values: Vec<String>) -> Frame {
+    Frame::Array(values.into_iter().map(Frame::Bulk).collect())
+}
+
+/// 将字符串列表转换为批量字符串集合（RESP2 下与数组相同）
+fn bulk_set(values: Vec<String>) -> Frame {
+    Frame::Set(values.into_iter().map(Frame::Bulk).collect())
+}
+
+/// 将阻塞命令的超时秒数转换为 `Duration`，0 表示一直等待
+fn block_timeout(seconds: f64) -> Option<Duration> {
+    (seconds > 0.0).then(|| Duration::from_secs_f64(seconds))
+}
+
+/// 将 BZPOPMIN / BZPOPMAX 的结果转换为 `[key, member, score]`，超时返回空值
+fn blocking_pop_reply(popped: Option<(String, String, f64)>) -> Frame {
+    popped.map_or(Frame::Null, |(key, member, score)| {
+        Frame::Array(vec![Frame::Bulk(key), Frame::Bulk(member), Frame::Bulk(score.to_string())])
+    })
+}
+
+/// 将有序集合的 `(member, score)` 列表转换为数组，`with_scores` 为 `true` 时成员与分值交替排列
+fn scored_array(items: Vec<(String, f64)>, with_scores: bool) -> Frame {
+    let frames = items.into_iter().flat_map(|(member, score)| {
+        let score = with_scores.then(|| Frame::Bulk(score.to_string()));
+        std::iter::once(Frame::Bulk(member)).chain(score)
+    });
+    Frame::Array(frames.collect())
+}
+
+/// 将经纬度转换为 `[longitude, latitude]`
+fn coord((lon, lat): (f64, f64)) -> Frame {
+    Frame::Array(vec![Frame::Bulk(lon.to_string()), Frame::Bulk(lat.to_string())])
+}
+
+/// 将 GEOSEARCH 的结果转换为数组
+///
+/// 不带 WITH 选项时只返回成员名；否则每个成员为 `[member, dist?, hash?, [lon, lat]?]`，
+/// 距离使用搜索范围的单位并保留 4 位小数。
+fn geosearch_reply(matches: Vec<Match>, search: &GeoSearch) -> Frame {
+    if !(search.with_coord || search.with_dist || search.with_hash) {
+        return bulk_array(matches.into_iter().map(|m| m.member).collect());
+    }
+    let items = matches.into_iter().map(|m| {
+        let mut item = vec![Frame::Bulk(m.member)];
+        if search.with_dist {
+            item.push(Frame::Bulk(format!("{:.4}", m.dist / search.unit.meters())));
+        }
+        if search.with_hash {
+            item.push(Frame::Integer(m.hash as i64));
+        }
+        if search.with_coord {
+            item.push(coord((m.lon, m.lat)));
+        }
+        Frame::Array(item)
+    });
+    Frame::Array(items.collect())
+}
+
+/// 将流中的消息转换为数组，每条消息为 `[id, [field, value, ...]]`
+fn stream_entries(entries: Vec<Entry>) -> Frame {
+    let entries = entries.into_iter().map(|(id, fields)| {
+        let fields = fields.into_iter().flat_map(|(field, value)| [field, value]);
+        Frame::Array(vec![Frame::Bulk(id.to_string()), bulk_array(fields.collect())])
+    });
+    Frame::Array(entries.collect())
+}
+
+/// 将 XPENDING 的汇总信息转换为 `[count, min-id, max-id, [[consumer, count], ...]]`
+fn pending_summary(summary: PendingSummary) -> Frame {
+    let (min, max) = match summary.range {
+        Some((min, max)) => (Frame::Bulk(min.to_string()), Frame::Bulk(max.to_string())),
+        None => (Frame::Null, Frame::Null),
+    };
+    let consumers = summary
+        .consumers
+        .into_iter()
+        .map(|(name, count)| bulk_array(vec![name, count.to_string()]));
+    let consumers: Vec<_> = consumers.collect();
+    let consumers = if consumers.is_empty() { Frame::Null } else { Frame::Array(consumers) };
+    Frame::Array(vec![Frame::Integer(summary.count as i64), min, max, consumers])
+}
+
+/// 将 XREAD 的结果转换为 `[[key, entries], ...]`，没有任何新消息时返回空值
+fn xread_reply(streams: Vec<(String, Vec<Entry>)>) -> Frame {
+    if streams.is_empty() {
+        return Frame::Null;
+    }
+    let streams = streams
+        .into_iter()
+        .map(|(key, entries)| Frame::Array(vec![Frame::Bulk(key), stream_entries(entries)]));
+    Frame::Array(streams.collect())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{db::Db, handler::process_command};
+    use std::collections::BTreeSet;
+
+    use super::{Flag, absolute_expiry, dispatch, propagation};
+    use crate::{
+        command::Command,
+        db::Db,
+        handler::{process_command, specs},
+    };
 
     #[tokio::test]
     async fn test_get_missing_key() {
@@ -57,12 +561,543 @@ mod tests {
         assert_eq!(process_command(&db, "set foo bar").await, "OK");
 
         assert_eq!(process_command(&db, "get foo").await, "bar");
+
+        assert_eq!(process_command(&db, r#"set greeting "hello world""#).await, "OK");
+        assert_eq!(process_command(&db, "get greeting").await, "hello world");
     }
 
     #[tokio::test]
     async fn test_unknown() {
         let db = Db::new();
 
-        assert_eq!(process_command(&db, "???").await, "ERR unknown command");
+        assert_eq!(
+            process_command(&db, "??? a").await,
+            "ERR unknown command '???', with args beginning with: 'a' "
+        );
+        assert_eq!(
+            process_command(&db, "get").await,
+            "ERR wrong number of arguments for 'get' command"
+        );
+        assert_eq!(
+            process_command(&db, "set k v px 0").await,
+            "ERR invalid expire time in 'set' command"
+        );
+        assert_eq!(
+            process_command(&db, "config nosuch").await,
+            "ERR unknown subcommand 'nosuch'. Try CONFIG HELP."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_help() {
+        let db = Db::new();
+
+        assert_eq!(
+            process_command(&db, "OBJECT help").await,
+            "1) OBJECT <subcommand> [<arg> [value] [opt] ...]. Subcommands are:\n\
+             2) ENCODING\n3)     Returns the internal encoding of a Redis object.\n\
+             4) REFCOUNT\n5)     Returns the reference count of a value of a key.\n\
+             6) IDLETIME\n7)     Returns the time since the last access to a Redis object.\n\
+             8) HELP\n9)     Returns helpful text about the different subcommands."
+        );
+        // 每个带子命令的命令都有 HELP，列出命令表中的全部子命令
+        for parent in specs().filter_map(|spec| spec.name.split_once('|').map(|(parent, _)| parent))
+        {
+            let help = process_command(&db, &format!("{parent} help")).await;
+            for spec in specs().filter(|spec| spec.name.starts_with(&format!("{parent}|"))) {
+                let sub = spec.name.split_once('|').unwrap().1.to_ascii_uppercase();
+                assert!(help.contains(&format!(") {sub}\n")), "{parent}: {help}");
+            }
+        }
+        assert_eq!(
+            process_command(&db, "config help x").await,
+            "ERR wrong number of arguments for 'config|help' command"
+        );
+        // 没有子命令的命令不受影响
+        assert_eq!(process_command(&db, "get help").await, "(nil)");
+    }
+
+    #[tokio::test]
+    async fn test_hincrby_and_hincrbyfloat() {
+        let db = Db::new();
+
+        assert_eq!(process_command(&db, "incr n").await, "(integer) 1");
+        assert_eq!(process_command(&db, "incrby n -3").await, "(integer) -2");
+        assert_eq!(process_command(&db, "set s abc").await, "OK");
+        assert_eq!(
+            process_command(&db, "incr s").await,
+            "ERR value is not an integer or out of range"
+        );
+        assert_eq!(process_command(&db, "set big 9223372036854775807").await, "OK");
+        assert_eq!(
+            process_command(&db, "incr big").await,
+            "ERR increment or decrement would overflow"
+        );
+        assert_eq!(process_command(&db, "hincrby h n 5").await, "(integer) 5");
+        assert_eq!(process_command(&db, "hincrbyfloat h n 0.25").await, "5.25");
+        assert_eq!(process_command(&db, "hincrby h n 1").await, "ERR hash value is not an integer");
+        assert_eq!(
+            process_command(&db, "get h").await,
+            "WRONGTYPE Operation against a key holding the wrong kind of value"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hrandfield() {
+        let db = Db::with_seed(0);
+
+        assert_eq!(process_command(&db, "hrandfield h").await, "(nil)");
+        assert_eq!(process_command(&db, "hrandfield h 3").await, "(empty array)");
+
+        process_command(&db, "hset h a 1").await;
+        assert_eq!(process_command(&db, "hrandfield h").await, "a");
+        assert_eq!(process_command(&db, "hrandfield h 2 withvalues").await, "1) a\n2) 1");
+        assert_eq!(process_command(&db, "hrandfield h -2").await, "1) a\n2) a");
+    }
+
+    #[tokio::test]
+    async fn test_set_commands() {
+        let db = Db::new();
+
+        assert_eq!(process_command(&db, "sadd s b a b").await, "(integer) 2");
+        assert_eq!(process_command(&db, "smembers s").await, "1) a\n2) b");
+        assert_eq!(process_command(&db, "sismember s a").await, "(integer) 1");
+        assert_eq!(process_command(&db, "srem s a c").await, "(integer) 1");
+        assert_eq!(process_command(&db, "scard s").await, "(integer) 1");
+    }
+
+    #[tokio::test]
+    async fn test_zset_commands() {
+        let db = Db::new();
+
+        assert_eq!(process_command(&db, "zadd z 1 a 2 b 1.5 c").await, "(integer) 3");
+        assert_eq!(process_command(&db, "zscore z c").await, "1.5");
+        assert_eq!(process_command(&db, "zscore z x").await, "(nil)");
+        assert_eq!(process_command(&db, "zcard z").await, "(integer) 3");
+        assert_eq!(process_command(&db, "zrange z 0 -1").await, "1) a\n2) c\n3) b");
+        assert_eq!(process_command(&db, "zrange z 0 0 rev withscores").await, "1) b\n2) 2");
+    }
+
+    #[tokio::test]
+    async fn test_zset_range_commands() {
+        let db = Db::new();
+        process_command(&db, "zadd z 1 a 2 b 3 c").await;
+
+        assert_eq!(process_command(&db, "zrangebyscore z (1 +inf").await, "1) b\n2) c");
+        assert_eq!(process_command(&db, "zrangebylex z [b + limit 0 1").await, "1) b");
+        assert_eq!(process_command(&db, "zincrby z 10 a").await, "11");
+        assert_eq!(process_command(&db, "zrank z a").await, "(integer) 2");
+        assert_eq!(process_command(&db, "zrank z x").await, "(nil)");
+    }
+
+    #[tokio::test]
+    async fn test_zpop_commands() {
+        let db = Db::new();
+        process_command(&db, "zadd z 1 a 2 b 3 c").await;
+
+        assert_eq!(process_command(&db, "zpopmin z 0").await, "(empty array)");
+        assert_eq!(process_command(&db, "zpopmin z").await, "1) a\n2) 1");
+        assert_eq!(
+            process_command(&db, "zpopmax z 18446744073709551615").await,
+            "1) c\n2) 3\n3) b\n4) 2"
+        );
+        process_command(&db, "zadd z 2 b 3 c").await;
+        assert_eq!(process_command(&db, "zpopmax z 5").await, "1) c\n2) 3\n3) b\n4) 2");
+        assert_eq!(process_command(&db, "zpopmax z").await, "(empty array)");
+        assert_eq!(process_command(&db, "bzpopmin z 0.01").await, "(nil)");
+
+        process_command(&db, "zadd z 5 e").await;
+        assert_eq!(process_command(&db, "bzpopmin y z 0").await, "1) z\n2) e\n3) 5");
+    }
+
+    #[tokio::test]
+    async fn test_stream_commands() {
+        let db = Db::new();
+
+        assert_eq!(process_command(&db, "xadd s 1-1 name a").await, "1-1");
+        assert_eq!(process_command(&db, "xadd s 1-* name b n 2").await, "1-2");
+        assert_eq!(
+            process_command(&db, "xadd s 1 name c").await,
+            "ERR The ID specified in XADD is equal or smaller than the target stream top item"
+        );
+        assert_eq!(process_command(&db, "xlen s").await, "(integer) 2");
+        assert_eq!(
+            process_command(&db, "xrange s (1-1 + count 1").await,
+            "1) 1) 1-2\n2) 1) name\n2) b\n3) n\n4) 2"
+        );
+        assert_eq!(process_command(&db, "xrange s 2 +").await, "(empty array)");
+
+        assert_eq!(
+            process_command(&db, "xread streams s 1-1").await,
+            "1) 1) s\n2) 1) 1) 1-2\n2) 1) name\n2) b\n3) n\n4) 2"
+        );
+        assert_eq!(process_command(&db, "xread count 5 streams s missing $ 0").await, "(nil)");
+        assert_eq!(
+            process_command(&db, "xread streams s").await,
+            "ERR wrong number of arguments for 'xread' command"
+        );
+        assert_eq!(process_command(&db, "xread block 10 streams s $").await, "(nil)");
+        assert_eq!(
+            process_command(&db, "xread block 0 streams s 1-1").await,
+            "1) 1) s\n2) 1) 1) 1-2\n2) 1) name\n2) b\n3) n\n4) 2"
+        );
+        assert_eq!(process_command(&db, "object encoding s").await, "stream");
+    }
+
+    #[tokio::test]
+    async fn test_bitmap_commands() {
+        let db = Db::new();
+
+        assert_eq!(process_command(&db, "setbit b 1 1").await, "(integer) 0");
+        assert_eq!(process_command(&db, "setbit b 6 1").await, "(integer) 0");
+        assert_eq!(process_command(&db, "get b").await, "B");
+        assert_eq!(process_command(&db, "getbit b 6").await, "(integer) 1");
+        assert_eq!(process_command(&db, "bitcount b").await, "(integer) 2");
+        assert_eq!(process_command(&db, "bitcount b 2 5 bit").await, "(integer) 0");
+        assert_eq!(process_command(&db, "bitpos b 1 2 -1 bit").await, "(integer) 6");
+        assert_eq!(process_command(&db, "bitpos b 1 1").await, "(integer) -1");
+        assert_eq!(process_command(&db, "bitpos missing 0").await, "(integer) 0");
+
+        // 位运算的结果可以不是 UTF-8
+        assert_eq!(process_command(&db, "bitop not dest b").await, "(integer) 1");
+        assert_eq!(db.get("dest").await, Ok(Some(vec![!b'B'])));
+        assert_eq!(process_command(&db, "get dest").await, "\u{fffd}");
+        assert_eq!(process_command(&db, "bitop or dest missing").await, "(integer) 0");
+        assert_eq!(db.get("dest").await, Ok(None));
+
+        process_command(&db, "sadd s m").await;
+        assert_eq!(
+            process_command(&db, "setbit s 0 1").await,
+            "WRONGTYPE Operation against a key holding the wrong kind of value"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_geo_commands() {
+        let db = Db::new();
+
+        assert_eq!(
+            process_command(
+                &db,
+                "geoadd Sicily 13.361389 38.115556 Palermo 15.087269 37.502669 Catania"
+            )
+            .await,
+            "(integer) 2"
+        );
+        assert_eq!(
+            process_command(&db, "geoadd Sicily 200 38 bad").await,
+            "ERR invalid longitude,latitude pair 200.000000,38.000000"
+        );
+        assert_eq!(process_command(&db, "geodist Sicily Palermo Catania").await, "166274.1516");
+        assert_eq!(process_command(&db, "geodist Sicily Palermo Catania km").await, "166.2742");
+        assert_eq!(process_command(&db, "geodist Sicily Palermo missing").await, "(nil)");
+        assert_eq!(process_command(&db, "geopos Sicily missing").await, "1) (nil)");
+        assert_eq!(
+            process_command(&db, "geosearch Sicily fromlonlat 15 37 byradius 200 km asc").await,
+            "1) Catania\n2) Palermo"
+        );
+        assert_eq!(
+            process_command(&db, "geosearch Sicily frommember Palermo bybox 400 400 km desc count 1 withdist withhash").await,
+            "1) 1) Catania\n2) 166.2742\n3) (integer) 3479447370796909"
+        );
+        assert_eq!(
+            process_command(&db, "geosearch Sicily frommember missing byradius 1 km").await,
+            "ERR could not decode requested zset member"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_consumer_group_commands() {
+        let db = Db::new();
+
+        assert_eq!(
+            process_command(&db, "xgroup create s g $").await,
+            "ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may \
+             want to use the MKSTREAM option to create an empty stream automatically."
+        );
+        assert_eq!(process_command(&db, "xgroup create s g $ mkstream").await, "OK");
+        assert_eq!(
+            process_command(&db, "xgroup create s g 0").await,
+            "BUSYGROUP Consumer Group name already exists"
+        );
+        process_command(&db, "xadd s 1-1 a 1").await;
+        process_command(&db, "xadd s 1-2 b 2").await;
+
+        assert_eq!(
+            process_command(&db, "xreadgroup group g alice count 1 streams s >").await,
+            "1) 1) s\n2) 1) 1) 1-1\n2) 1) a\n2) 1"
+        );
+        assert_eq!(
+            process_command(&db, "xreadgroup group g bob streams s >").await,
+            "1) 1) s\n2) 1) 1) 1-2\n2) 1) b\n2) 2"
+        );
+        assert_eq!(process_command(&db, "xreadgroup group g bob streams s >").await, "(nil)");
+        assert_eq!(
+            process_command(&db, "xreadgroup group g bob block 10 streams s >").await,
+            "(nil)"
+        );
+        // 读取待确认消息时即使没有结果也返回该流
+        assert_eq!(
+            process_command(&db, "xreadgroup group g carol streams s 0").await,
+            "1) 1) s\n2) (empty array)"
+        );
+        assert_eq!(
+            process_command(&db, "xreadgroup group missing c streams s >").await,
+            "NOGROUP No such key 's' or consumer group 'missing'"
+        );
+
+        assert_eq!(
+            process_command(&db, "xpending s g").await,
+            "1) (integer) 2\n2) 1-1\n3) 1-2\n4) 1) 1) alice\n2) 1\n2) 1) bob\n2) 1"
+        );
+        assert_eq!(process_command(&db, "xclaim s g bob 0 1-1 justid").await, "1) 1-1");
+        let pending = process_command(&db, "xpending s g - + 10 bob").await;
+        assert!(pending.starts_with("1) 1) 1-1\n2) bob\n3) (integer) "), "{pending}");
+        assert!(pending.contains("4) (integer) 1\n2) 1) 1-2\n2) bob\n3) (integer) "), "{pending}");
+        assert_eq!(process_command(&db, "xack s g 1-1 1-2 9-9").await, "(integer) 2");
+        assert_eq!(process_command(&db, "xack s missing 1-1").await, "(integer) 0");
+        assert_eq!(
+            process_command(&db, "xpending s g").await,
+            "1) (integer) 0\n2) (nil)\n3) (nil)\n4) (nil)"
+        );
+
+        assert_eq!(process_command(&db, "xgroup createconsumer s g dave").await, "(integer) 1");
+        assert_eq!(process_command(&db, "xgroup createconsumer s g dave").await, "(integer) 0");
+        assert_eq!(process_command(&db, "xgroup setid s g 0").await, "OK");
+        assert_eq!(
+            process_command(&db, "xreadgroup group g dave noack streams s >").await,
+            "1) 1) s\n2) 1) 1) 1-1\n2) 1) a\n2) 1\n2) 1) 1-2\n2) 1) b\n2) 2"
+        );
+        assert_eq!(
+            process_command(&db, "xpending s g").await,
+            "1) (integer) 0\n2) (nil)\n3) (nil)\n4) (nil)"
+        );
+        assert_eq!(process_command(&db, "xgroup destroy s g").await, "(integer) 1");
+        assert_eq!(process_command(&db, "xgroup destroy s g").await, "(integer) 0");
+    }
+
+    #[tokio::test]
+    async fn test_set_sampling_commands() {
+        let db = Db::with_seed(0);
+
+        assert_eq!(process_command(&db, "spop s").await, "(nil)");
+        assert_eq!(process_command(&db, "spop s 2").await, "(empty array)");
+
+        process_command(&db, "sadd s a").await;
+        assert_eq!(process_command(&db, "srandmember s").await, "a");
+        assert_eq!(process_command(&db, "srandmember s -2").await, "1) a\n2) a");
+        assert_eq!(
+            process_command(&db, "smismember s a b").await,
+            "1) (integer) 1\n2) (integer) 0"
+        );
+        assert_eq!(process_command(&db, "spop s").await, "a");
+        assert_eq!(process_command(&db, "scard s").await, "(integer) 0");
+    }
+
+    #[tokio::test]
+    async fn test_set_algebra_commands() {
+        let db = Db::new();
+        process_command(&db, "sadd a 1 2 3").await;
+        process_command(&db, "sadd b 2 3 4").await;
+
+        assert_eq!(process_command(&db, "sinter a b").await, "1) 2\n2) 3");
+        assert_eq!(process_command(&db, "sunionstore u a b").await, "(integer) 4");
+        assert_eq!(process_command(&db, "sdiff u a").await, "1) 4");
+        assert_eq!(process_command(&db, "sintercard 2 a b limit 1").await, "(integer) 1");
+    }
+
+    #[tokio::test]
+    async fn test_keys_and_publish() {
+        let db = Db::new();
+        process_command(&db, "set user:1 a").await;
+        process_command(&db, "set user:2 b").await;
+
+        assert_eq!(process_command(&db, "keys user:*").await, "1) user:1\n2) user:2");
+        assert_eq!(process_command(&db, "keys nothing").await, "(empty array)");
+        assert_eq!(process_command(&db, "publish news hi").await, "(integer) 0");
+        assert!(process_command(&db, "psubscribe n*").await.starts_with("ERR"));
+    }
+
+    #[tokio::test]
+    async fn test_bgrewriteaof_without_aof() {
+        let db = Db::new();
+
+        assert_eq!(process_command(&db, "bgrewriteaof").await, "ERR AOF is not enabled");
+    }
+
+    #[tokio::test]
+    async fn test_expire_commands() {
+        let db = Db::new();
+
+        assert_eq!(process_command(&db, "ttl k").await, "(integer) -2");
+        assert_eq!(process_command(&db, "expire k 10").await, "(integer) 0");
+        process_command(&db, "set k v").await;
+        assert_eq!(process_command(&db, "ttl k").await, "(integer) -1");
+        assert_eq!(process_command(&db, "expire k 10").await, "(integer) 1");
+        assert_eq!(process_command(&db, "ttl k").await, "(integer) 10");
+
+        assert_eq!(process_command(&db, "set p v px 20").await, "OK");
+        assert!(process_command(&db, "pttl p").await.starts_with("(integer) "));
+        tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+        assert_eq!(process_command(&db, "get p").await, "(nil)");
+
+        assert_eq!(process_command(&db, "expire k -1").await, "(integer) 1");
+        assert_eq!(process_command(&db, "get k").await, "(nil)");
+
+        process_command(&db, "set k v").await;
+        assert_eq!(process_command(&db, "expiretime k").await, "(integer) -1");
+        assert_eq!(process_command(&db, "expireat k 4000000000 gt").await, "(integer) 0");
+        assert_eq!(process_command(&db, "expireat k 4000000000 nx").await, "(integer) 1");
+        assert_eq!(process_command(&db, "expiretime k").await, "(integer) 4000000000");
+        assert_eq!(process_command(&db, "pexpiretime k").await, "(integer) 4000000000000");
+        assert_eq!(process_command(&db, "expire k 10 gt").await, "(integer) 0");
+        assert_eq!(process_command(&db, "expire k 10 lt").await, "(integer) 1");
+        assert_eq!(process_command(&db, "ttl k").await, "(integer) 10");
+        assert_eq!(process_command(&db, "persist k").await, "(integer) 1");
+        assert_eq!(process_command(&db, "persist k").await, "(integer) 0");
+        assert_eq!(process_command(&db, "ttl k").await, "(integer) -1");
+        assert_eq!(process_command(&db, "pexpiretime missing").await, "(integer) -2");
+    }
+
+    #[tokio::test]
+    async fn test_del_and_restore() {
+        let db = Db::new();
+        process_command(&db, "hset h f v").await;
+        process_command(&db, "set s v").await;
+        let payload = process_command(&db, "dump h").await;
+        assert_eq!(Some(payload.clone()), db.dump("h").await.map(|(payload, _)| payload));
+        assert_eq!(process_command(&db, "dump missing").await, "(nil)");
+
+        assert_eq!(process_command(&db, "del h s missing").await, "(integer) 2");
+        process_command(&db, "set u v").await;
+        assert_eq!(process_command(&db, "unlink u missing").await, "(integer) 1");
+        assert_eq!(process_command(&db, &format!("restore h 5000 {payload}")).await, "OK");
+        assert_eq!(process_command(&db, "hget h f").await, "v");
+        assert_eq!(process_command(&db, "ttl h").await, "(integer) 5");
+
+        let busy = process_command(&db, &format!("restore h 0 {payload}")).await;
+        assert!(busy.starts_with("BUSYKEY"));
+        assert_eq!(process_command(&db, &format!("restore h 0 {payload} replace")).await, "OK");
+        assert_eq!(process_command(&db, "ttl h").await, "(integer) -1");
+        assert!(process_command(&db, "restore x 0 abcd").await.starts_with("ERR DUMP payload"));
+    }
+
+    #[tokio::test]
+    async fn test_object() {
+        let db = Db::new();
+        process_command(&db, "set n 42").await;
+        process_command(&db, "sadd s a").await;
+
+        assert_eq!(process_command(&db, "object encoding n").await, "int");
+        assert_eq!(process_command(&db, "object encoding s").await, "listpack");
+        assert_eq!(process_command(&db, "object refcount n").await, "(integer) 1");
+        assert_eq!(process_command(&db, "object idletime n").await, "(integer) 0");
+        assert_eq!(process_command(&db, "object encoding missing").await, "(nil)");
+    }
+
+    #[tokio::test]
+    async fn test_dbsize_and_flush() {
+        let db = Db::new();
+        process_command(&db, "set a 1").await;
+        process_command(&db, "sadd s x y").await;
+
+        assert_eq!(process_command(&db, "dbsize").await, "(integer) 2");
+        assert_eq!(process_command(&db, "touch a s missing").await, "(integer) 2");
+        assert!(["a", "s"].contains(&process_command(&db, "randomkey").await.as_str()));
+        assert_eq!(process_command(&db, "flushall async").await, "OK");
+        assert_eq!(process_command(&db, "dbsize").await, "(integer) 0");
+        assert_eq!(process_command(&db, "randomkey").await, "(nil)");
+        assert_eq!(process_command(&db, "get a").await, "(nil)");
+        assert_eq!(db.dirty(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_propagation_follows_flags() {
+        /// 按命令的标志执行并计算传播的参数，同时记录执行过的命令名
+        async fn propagated(
+            db: &Db,
+            names: &mut BTreeSet<&'static str>,
+            args: &[&str],
+        ) -> Option<Vec<String>> {
+            let command = Command::from_args(args).unwrap();
+            let flags = db.commands().get(command.name()).unwrap().flags();
+            names.insert(command.name());
+            let command = absolute_expiry(command);
+            let reply = dispatch(db, command.clone()).await;
+            propagation(&command, &reply, flags)
+        }
+
+        let db = Db::with_seed(1);
+        let mut names = BTreeSet::new();
+        // 确定性的命令原样传播，没有产生修改时不传播
+        for (input, expected) in [
+            ("set k v", "set k v"),
+            ("getset k w", "set k w"),
+            ("incrby n 2", "incrby n 2"),
+            ("incr n", "incr n"),
+            ("setbit b 1 1", "setbit b 1 1"),
+            ("bitop or d b", "bitop or d b"),
+            ("hset h f v", "hset h f v"),
+            ("hincrby h n 1", "hincrby h n 1"),
+            ("sadd s a b c", "sadd s a b c"),
+            ("srem s c", "srem s c"),
+            ("sinterstore d1 s", "sinterstore d1 s"),
+            ("sunionstore d2 s", "sunionstore d2 s"),
+            ("sdiffstore d3 s", "sdiffstore d3 s"),
+            ("zadd z nx 1 a 2 b", "zadd z nx 1 a 2 b"),
+            ("zincrby z 1 a", "zincrby z 1 a"),
+            ("zpopmin z", "zpopmin z 1"),
+            ("zpopmax z", "zpopmax z 1"),
+            ("zpopmax z", ""),
+            ("geoadd g 13 38 p", "geoadd g 13 38 p"),
+            ("xgroup create x g $ mkstream", "xgroup create x g $ mkstream"),
+            ("xgroup createconsumer x g c", "xgroup createconsumer x g c"),
+            ("xgroup setid x g 0", "xgroup setid x g 0-0"),
+            ("xack x g 1-1", ""),
+            ("xgroup destroy x g", "xgroup destroy x g"),
+            ("persist k", ""),
+            ("move d1 1", "move d1 1"),
+            ("unlink d2", "unlink d2"),
+            ("del d3 missing", "del d3 missing"),
+            ("del d3", ""),
+        ] {
+            let args: Vec<_> = input.split(' ').collect();
+            let actual = propagated(&db, &mut names, &args).await.map(|args| args.join(" "));
+            assert_eq!(actual, (!expected.is_empty()).then(|| expected.to_string()), "{input}");
+        }
+
+        // 其他写命令改写为确定性的形式
+        let payload = db.dump("h").await.unwrap().0;
+        for (args, expected) in [
+            (vec!["set", "k", "v", "ex", "10"], "set"),
+            (vec!["expire", "k", "10"], "pexpireat"),
+            (vec!["pexpire", "k", "10000"], "pexpireat"),
+            (vec!["expireat", "k", "4102444800"], "pexpireat"),
+            (vec!["pexpireat", "k", "4102444801000", "gt"], "pexpireat"),
+            (vec!["persist", "k"], "persist"),
+            (vec!["restore", "r", "10000", &payload], "restore"),
+            (vec!["spop", "s"], "srem"),
+            (vec!["hincrbyfloat", "h", "x", "1.5"], "hset"),
+            (vec!["xadd", "x", "*", "f", "v"], "xadd"),
+        ] {
+            let actual =
+                propagated(&db, &mut names, &args).await.unwrap_or_else(|| panic!("{args:?}"));
+            assert_eq!(actual[0], expected, "{args:?}");
+            assert_eq!(actual[1], args[1], "{args:?}");
+            assert!(!actual.iter().any(|arg| arg == "*" || arg == "ex"), "{args:?}");
+        }
+        for input in ["swapdb 0 1", "flushdb", "flushall"] {
+            let args: Vec<_> = input.split(' ').collect();
+            assert_eq!(propagated(&db, &mut names, &args).await, Some(to_strings(&args)));
+        }
+
+        // 覆盖全部由命令处理层传播的写命令
+        let writes =
+            specs().filter(|spec| spec.has(Flag::Write) && !spec.has(Flag::SelfPropagating));
+        assert_eq!(names, writes.map(|spec| spec.name).collect());
+    }
+
+    fn to_strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
     }
 }
@@ -0,0 +1,55 @@
+//! 位图命令
+
+use super::{BoxFuture, Flag, Spec};
+use crate::{
+    command::Command,
+    db::{Db, DbError, Storage},
+    frame::Frame,
+};
+
+pub(super) const GROUP: &str = "bitmap";
+
+pub(super) const COMMANDS: &[Spec] = &[
+    Spec::new("setbit", 4, &[Flag::Write, Flag::DenyOom, Flag::Deterministic])
+        .keys(1, 1, 1)
+        .summary("Sets or clears the bit at offset of the string value."),
+    Spec::new("getbit", 3, &[Flag::ReadOnly, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Returns a bit value by offset."),
+    Spec::new("bitcount", -2, &[Flag::ReadOnly])
+        .keys(1, 1, 1)
+        .summary("Counts the number of set bits (population counting) in a string."),
+    Spec::new("bitpos", -3, &[Flag::ReadOnly])
+        .keys(1, 1, 1)
+        .summary("Finds the first set (1) or clear (0) bit in a string."),
+    Spec::new("bitop", -4, &[Flag::Write, Flag::DenyOom, Flag::Deterministic])
+        .keys(2, -1, 1)
+        .summary("Performs bitwise operations on multiple strings, and stores the result."),
+];
+
+pub(super) fn execute<S: Storage>(
+    db: &Db<S>,
+    command: Command,
+) -> BoxFuture<'_, Result<Frame, DbError>> {
+    Box::pin(async move {
+        match command {
+            Command::SetBit(key, offset, value) => {
+                db.setbit(key, offset, value).await.map(|old| Frame::Integer(old as i64))
+            }
+            Command::GetBit(key, offset) => {
+                db.getbit(&key, offset).await.map(|bit| Frame::Integer(bit as i64))
+            }
+            Command::BitCount(key, range) => {
+                db.bitcount(&key, range.as_ref()).await.map(|n| Frame::Integer(n as i64))
+            }
+            Command::BitPos(key, bit, range) => db
+                .bitpos(&key, bit, range.as_ref())
+                .await
+                .map(|pos| Frame::Integer(pos.map_or(-1, |pos| pos as i64))),
+            Command::BitOp(op, dest, keys) => {
+                db.bitop(op, dest, &keys).await.map(|len| Frame::Integer(len as i64))
+            }
+            command => unreachable!("{} is not a bitmap command", command.name()),
+        }
+    })
+}
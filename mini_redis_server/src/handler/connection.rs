@@ -0,0 +1,68 @@
+//! 连接命令：PING / ECHO、认证、握手、选择数据库、CLIENT、READONLY / READWRITE 与 QUIT / RESET
+//!
+//! 除 PING、ECHO 与 CLIENT LIST 外，这些命令的状态属于连接，由 `Session` 执行，
+//! 在没有会话时返回错误（QUIT 直接回复 OK）。
+
+use super::{BoxFuture, Flag, Spec, help_reply};
+use crate::{
+    command::Command,
+    db::{Db, DbError, Storage},
+    frame::Frame,
+};
+
+pub(super) const GROUP: &str = "connection";
+
+pub(super) const COMMANDS: &[Spec] = &[
+    Spec::new("ping", -1, &[Flag::Fast]).summary("Returns the server's liveliness response."),
+    Spec::new("echo", 2, &[Flag::Fast]).summary("Returns the given string."),
+    Spec::new("quit", -1, &[Flag::NoAuth, Flag::Fast]).summary("Closes the connection."),
+    Spec::new("reset", 1, &[Flag::NoAuth, Flag::Fast]).summary("Resets the connection."),
+    Spec::new("readonly", 1, &[Flag::Fast]).summary("Rejects write commands on the connection."),
+    Spec::new("readwrite", 1, &[Flag::Fast])
+        .summary("Allows write commands on a read-only connection."),
+    Spec::new("auth", -2, &[Flag::NoAuth, Flag::Fast]).summary("Authenticates the connection."),
+    Spec::new("hello", -1, &[Flag::NoAuth, Flag::Fast])
+        .summary("Handshakes with the Redis server."),
+    Spec::new("select", 2, &[Flag::Fast]).summary("Changes the selected database."),
+    Spec::new("client|id", 2, &[Flag::Fast])
+        .summary("Returns the unique client ID of the connection."),
+    Spec::new("client|setname", 3, &[Flag::Fast]).summary("Sets the connection name."),
+    Spec::new("client|setinfo", 4, &[Flag::Fast])
+        .summary("Sets information specific to the client or connection."),
+    Spec::new("client|getname", 2, &[Flag::Fast]).summary("Returns the name of the connection."),
+    Spec::new("client|list", 2, &[Flag::Admin]).summary("Lists open connections."),
+    Spec::new("client|kill", -3, &[Flag::Admin]).summary("Terminates open connections."),
+    Spec::help("client|help"),
+];
+
+pub(super) fn execute<S: Storage>(
+    db: &Db<S>,
+    command: Command,
+) -> BoxFuture<'_, Result<Frame, DbError>> {
+    Box::pin(async move {
+        match command {
+            Command::Ping(None) => Ok(Frame::Simple("PONG".into())),
+            Command::Ping(Some(message)) | Command::Echo(message) => Ok(Frame::Bulk(message)),
+            Command::Quit => Ok(Frame::Simple("OK".into())),
+            Command::Reset => Ok(Frame::Error("ERR RESET requires a client session".into())),
+            Command::ClientList => Ok(Frame::Bulk(db.clients().list())),
+            Command::Auth(..) => Ok(Frame::Error("ERR AUTH requires a client session".into())),
+            Command::Hello(_) => Ok(Frame::Error("ERR HELLO requires a client session".into())),
+            Command::Select(_) => Ok(Frame::Error("ERR SELECT requires a client session".into())),
+            Command::ReadOnly => Ok(Frame::Error("ERR READONLY requires a client session".into())),
+            Command::ReadWrite => {
+                Ok(Frame::Error("ERR READWRITE requires a client session".into()))
+            }
+            Command::ClientId
+            | Command::ClientSetName(_)
+            | Command::ClientSetInfo(..)
+            | Command::ClientGetName
+            | Command::ClientKill(_)
+            | Command::ClientKillAddr(_) => {
+                Ok(Frame::Error("ERR CLIENT requires a client session".into()))
+            }
+            Command::Help(name) => Ok(help_reply(name)),
+            command => unreachable!("{} is not a connection command", command.name()),
+        }
+    })
+}
@@ -0,0 +1,55 @@
+//! 地理位置命令
+
+use super::{BoxFuture, Flag, Spec, coord, geosearch_reply};
+use crate::{
+    command::Command,
+    db::{Db, DbError, Storage},
+    frame::Frame,
+};
+
+pub(super) const GROUP: &str = "geo";
+
+pub(super) const COMMANDS: &[Spec] = &[
+    Spec::new("geoadd", -5, &[Flag::Write, Flag::DenyOom, Flag::Deterministic])
+        .keys(1, 1, 1)
+        .summary("Adds one or more members to a geospatial index."),
+    Spec::new("geodist", -4, &[Flag::ReadOnly])
+        .keys(1, 1, 1)
+        .summary("Returns the distance between two members of a geospatial index."),
+    Spec::new("geopos", -2, &[Flag::ReadOnly])
+        .keys(1, 1, 1)
+        .summary("Returns the longitude and latitude of members from a geospatial index."),
+    Spec::new("geosearch", -7, &[Flag::ReadOnly])
+        .keys(1, 1, 1)
+        .summary("Queries a geospatial index for members inside an area of a box or a circle."),
+];
+
+pub(super) fn execute<S: Storage>(
+    db: &Db<S>,
+    command: Command,
+) -> BoxFuture<'_, Result<Frame, DbError>> {
+    Box::pin(async move {
+        match command {
+            Command::GeoAdd(key, flags, locations) => {
+                db.geoadd(key, flags, locations).await.map(|n| Frame::Integer(n as i64))
+            }
+            Command::GeoDist(key, from, to, unit) => {
+                db.geodist(&key, &from, &to).await.map(|dist| {
+                    dist.map_or(Frame::Null, |dist| {
+                        Frame::Bulk(format!("{:.4}", dist / unit.meters()))
+                    })
+                })
+            }
+            Command::GeoPos(key, members) => db.geopos(&key, &members).await.map(|positions| {
+                let positions =
+                    positions.into_iter().map(|position| position.map_or(Frame::Null, coord));
+                Frame::Array(positions.collect())
+            }),
+            Command::GeoSearch(key, search) => db
+                .geosearch(&key, &search.query)
+                .await
+                .map(|matches| geosearch_reply(matches, &search)),
+            command => unreachable!("{} is not a geo command", command.name()),
+        }
+    })
+}
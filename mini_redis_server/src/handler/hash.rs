@@ -0,0 +1,63 @@
+//! 哈希命令
+
+use super::{BoxFuture, Flag, Spec, bulk_or_null};
+use crate::{
+    command::Command,
+    db::{Db, DbError, Storage},
+    frame::Frame,
+};
+
+pub(super) const GROUP: &str = "hash";
+
+pub(super) const COMMANDS: &[Spec] = &[
+    Spec::new("hset", -4, &[Flag::Write, Flag::DenyOom, Flag::Fast, Flag::Deterministic])
+        .keys(1, 1, 1)
+        .summary("Creates or modifies the value of a field in a hash."),
+    Spec::new("hget", 3, &[Flag::ReadOnly, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Returns the value of a field in a hash."),
+    Spec::new("hincrby", 4, &[Flag::Write, Flag::DenyOom, Flag::Fast, Flag::Deterministic])
+        .keys(1, 1, 1)
+        .summary("Increments the integer value of a field in a hash by a number."),
+    Spec::new("hincrbyfloat", 4, &[Flag::Write, Flag::DenyOom, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Increments the floating point value of a field by a number."),
+    Spec::new("hrandfield", -2, &[Flag::ReadOnly])
+        .keys(1, 1, 1)
+        .summary("Returns one or more random fields from a hash."),
+];
+
+pub(super) fn execute<S: Storage>(
+    db: &Db<S>,
+    command: Command,
+) -> BoxFuture<'_, Result<Frame, DbError>> {
+    Box::pin(async move {
+        match command {
+            Command::HSet(key, pairs) => {
+                db.hset(key, pairs).await.map(|n| Frame::Integer(n as i64))
+            }
+            Command::HGet(key, field) => db.hget(&key, &field).await.map(bulk_or_null),
+            Command::HIncrBy(key, field, delta) => {
+                db.hincrby(key, field, delta).await.map(Frame::Integer)
+            }
+            Command::HIncrByFloat(key, field, delta) => {
+                db.hincrbyfloat(key, field, delta).await.map(Frame::Bulk)
+            }
+            Command::HRandField(key, None, _) => db
+                .hrandfield(&key, None)
+                .await
+                .map(|pairs| pairs.into_iter().next().map(|(field, _)| field))
+                .map(bulk_or_null),
+            Command::HRandField(key, Some(count), with_values) => {
+                db.hrandfield(&key, Some(count)).await.map(|pairs| {
+                    let items = pairs.into_iter().flat_map(|(field, value)| {
+                        let value = with_values.then_some(Frame::Bulk(value));
+                        std::iter::once(Frame::Bulk(field)).chain(value)
+                    });
+                    Frame::Array(items.collect())
+                })
+            }
+            command => unreachable!("{} is not a hash command", command.name()),
+        }
+    })
+}
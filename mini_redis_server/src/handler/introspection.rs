@@ -0,0 +1,199 @@
+//! 命令内省：COMMAND、COMMAND COUNT / INFO / DOCS
+//!
+//! 回复由当前数据库的命令注册表生成。带子命令的命令（如 CONFIG）作为一条顶层命令，
+//! 子命令以 `config|get` 的形式列在其下；没有单独登记的容器命令参数个数为 -2、没有标志。
+
+use super::{BoxFuture, CommandHandler, Flag, Registry, Spec, help_reply};
+use crate::{
+    command::Command,
+    db::{Db, DbError, Storage},
+    frame::Frame,
+};
+
+pub(super) const GROUP: &str = "server";
+
+pub(super) const COMMANDS: &[Spec] = &[
+    Spec::new("command", -1, &[]).summary("Returns detailed information about all commands."),
+    Spec::new("command|count", 2, &[]).summary("Returns a count of commands."),
+    Spec::new("command|info", -2, &[])
+        .summary("Returns information about one, multiple or all commands."),
+    Spec::new("command|docs", -2, &[])
+        .summary("Returns documentary information about one, multiple or all commands."),
+    Spec::help("command|help"),
+];
+
+pub(super) fn execute<S: Storage>(
+    db: &Db<S>,
+    command: Command,
+) -> BoxFuture<'_, Result<Frame, DbError>> {
+    Box::pin(async move {
+        let registry = db.commands();
+        match command {
+            Command::Commands => Ok(Frame::Array(
+                top_level_names(registry)
+                    .into_iter()
+                    .filter_map(|name| command_info(registry, name))
+                    .collect(),
+            )),
+            Command::CommandCount => Ok(Frame::Integer(top_level_names(registry).len() as i64)),
+            Command::CommandInfo(names) if names.is_empty() => execute(db, Command::Commands).await,
+            Command::CommandInfo(names) => Ok(Frame::Array(
+                names
+                    .iter()
+                    .map(|name| {
+                        command_info(registry, &name.to_ascii_lowercase()).unwrap_or(Frame::Null)
+                    })
+                    .collect(),
+            )),
+            Command::CommandDocs(names) => {
+                let names: Vec<String> = if names.is_empty() {
+                    top_level_names(registry).into_iter().map(String::from).collect()
+                } else {
+                    names.iter().map(|name| name.to_ascii_lowercase()).collect()
+                };
+                // 不存在的命令不出现在回复中
+                let docs = names.into_iter().filter_map(|name| {
+                    command_docs(registry, &name).map(|docs| (Frame::Bulk(name), docs))
+                });
+                Ok(Frame::Map(docs.collect()))
+            }
+            Command::Help(name) => Ok(help_reply(name)),
+            command => unreachable!("{} is not an introspection command", command.name()),
+        }
+    })
+}
+
+/// 顶层命令名（不含子命令），按名称排序
+fn top_level_names<S: Storage>(registry: &Registry<S>) -> Vec<&'static str> {
+    let mut names: Vec<_> = registry
+        .iter()
+        .map(|handler| handler.name().split_once('|').map_or(handler.name(), |(name, _)| name))
+        .collect();
+    names.dedup();
+    names
+}
+
+/// `name` 的全部子命令
+fn subcommands<'a, S: Storage>(
+    registry: &'a Registry<S>,
+    name: &str,
+) -> Vec<&'a dyn CommandHandler<S>> {
+    registry
+        .iter()
+        .filter(|handler| handler.name().strip_prefix(name).is_some_and(|sub| sub.starts_with('|')))
+        .collect()
+}
+
+/// COMMAND INFO 中的一条命令，不存在时返回 `None`
+fn command_info<S: Storage>(registry: &Registry<S>, name: &str) -> Option<Frame> {
+    let subcommands: Vec<_> = subcommands(registry, name)
+        .into_iter()
+        .map(|handler| {
+            info_entry(
+                handler.name(),
+                handler.arity(),
+                handler.flags(),
+                handler.key_positions(),
+                Vec::new(),
+            )
+        })
+        .collect();
+    match registry.get(name) {
+        Some(handler) => Some(info_entry(
+            handler.name(),
+            handler.arity(),
+            handler.flags(),
+            handler.key_positions(),
+            subcommands,
+        )),
+        None if !subcommands.is_empty() => Some(info_entry(name, -2, &[], (0, 0, 0), subcommands)),
+        None => None,
+    }
+}
+
+/// `[名称, 参数个数, 标志, 第一个键, 最后一个键, 键的间隔, 子命令]`
+fn info_entry(
+    name: &str,
+    arity: i64,
+    flags: &[Flag],
+    (first, last, step): (i64, i64, i64),
+    subcommands: Vec<Frame>,
+) -> Frame {
+    Frame::Array(vec![
+        Frame::Bulk(name.into()),
+        Frame::Integer(arity),
+        Frame::Set(flags.iter().map(|flag| Frame::Simple(flag.as_str().into())).collect()),
+        Frame::Integer(first),
+        Frame::Integer(last),
+        Frame::Integer(step),
+        Frame::Array(subcommands),
+    ])
+}
+
+/// COMMAND DOCS 中的一条命令：简介、分组与子命令的文档，不存在时返回 `None`
+fn command_docs<S: Storage>(registry: &Registry<S>, name: &str) -> Option<Frame> {
+    let subcommands = subcommands(registry, name);
+    let (summary, group) = match (registry.get(name), subcommands.first()) {
+        (Some(handler), _) => (handler.summary().to_string(), handler.group()),
+        (None, Some(sub)) => {
+            (format!("A container for {} commands.", name.to_uppercase()), sub.group())
+        }
+        (None, None) => return None,
+    };
+
+    let mut fields = vec![
+        (Frame::Bulk("summary".into()), Frame::Bulk(summary)),
+        (Frame::Bulk("group".into()), Frame::Bulk(group.into())),
+    ];
+    if !subcommands.is_empty() {
+        let docs = subcommands.into_iter().filter_map(|handler| {
+            command_docs(registry, handler.name())
+                .map(|docs| (Frame::Bulk(handler.name().into()), docs))
+        });
+        fields.push((Frame::Bulk("subcommands".into()), Frame::Map(docs.collect())));
+    }
+    Some(Frame::Map(fields))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{db::Db, handler::process_command};
+
+    #[tokio::test]
+    async fn test_command_info() {
+        let db = Db::new();
+
+        let names = super::top_level_names(db.commands());
+        assert!(names.contains(&"config") && !names.contains(&"config|get"));
+        assert_eq!(
+            process_command(&db, "command count").await,
+            format!("(integer) {}", names.len())
+        );
+        assert_eq!(
+            process_command(&db, "command info GET nosuch").await,
+            "1) 1) get\n2) (integer) 2\n3) 1) readonly\n2) fast\n4) (integer) 1\n5) (integer) 1\n\
+             6) (integer) 1\n7) (empty array)\n2) (nil)"
+        );
+        let config = process_command(&db, "command info config").await;
+        assert!(config.starts_with("1) 1) config\n2) (integer) -2\n3) (empty array)"), "{config}");
+        assert!(config.contains("config|get"), "{config}");
+        assert_eq!(
+            process_command(&db, "command info").await,
+            process_command(&db, "command").await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_command_docs() {
+        let db = Db::new();
+
+        assert_eq!(
+            process_command(&db, "command docs zadd nosuch").await,
+            "1) zadd\n2) 1) summary\n2) Adds one or more members to a sorted set, or updates their \
+             scores.\n3) group\n4) sorted-set"
+        );
+        let docs = process_command(&db, "command docs xgroup").await;
+        assert!(docs.contains("A container for XGROUP commands."), "{docs}");
+        assert!(docs.contains("xgroup|create"), "{docs}");
+    }
+}
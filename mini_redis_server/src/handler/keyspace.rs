@@ -0,0 +1,195 @@
+//! 键空间命令：遍历、删除、过期、序列化与迁移、导出与导入、数据库管理、大键与热点键统计
+
+use super::{
+    BoxFuture, Flag, Spec, bulk_array, bulk_or_null, expire_time_reply, help_reply, ttl_reply,
+};
+use crate::{
+    command::Command,
+    db::{Db, DbError, Storage, unix_time_ms},
+    frame::Frame,
+};
+
+pub(super) const GROUP: &str = "generic";
+
+pub(super) const COMMANDS: &[Spec] = &[
+    Spec::new("keys", 2, &[Flag::ReadOnly]).summary("Returns all key names that match a pattern."),
+    Spec::new("scan", -2, &[Flag::ReadOnly])
+        .summary("Iterates over the key names in the database."),
+    Spec::new("del", -2, &[Flag::Write, Flag::Deterministic])
+        .keys(1, -1, 1)
+        .summary("Deletes one or more keys."),
+    Spec::new("unlink", -2, &[Flag::Write, Flag::Fast, Flag::Deterministic])
+        .keys(1, -1, 1)
+        .summary("Asynchronously deletes one or more keys."),
+    Spec::new("expire", -3, &[Flag::Write, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Sets the expiration time of a key in seconds."),
+    Spec::new("pexpire", -3, &[Flag::Write, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Sets the expiration time of a key in milliseconds."),
+    Spec::new("expireat", -3, &[Flag::Write, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Sets the expiration time of a key to a Unix timestamp."),
+    Spec::new("pexpireat", -3, &[Flag::Write, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Sets the expiration time of a key to a Unix milliseconds timestamp."),
+    Spec::new("persist", 2, &[Flag::Write, Flag::Fast, Flag::Deterministic])
+        .keys(1, 1, 1)
+        .summary("Removes the expiration time of a key."),
+    Spec::new("ttl", 2, &[Flag::ReadOnly, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Returns the expiration time in seconds of a key."),
+    Spec::new("pttl", 2, &[Flag::ReadOnly, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Returns the expiration time in milliseconds of a key."),
+    Spec::new("expiretime", 2, &[Flag::ReadOnly, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Returns the expiration time of a key as a Unix timestamp."),
+    Spec::new("pexpiretime", 2, &[Flag::ReadOnly, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Returns the expiration time of a key as a Unix milliseconds timestamp."),
+    Spec::new("object|encoding", 3, &[Flag::ReadOnly])
+        .keys(2, 2, 1)
+        .summary("Returns the internal encoding of a Redis object."),
+    Spec::new("object|refcount", 3, &[Flag::ReadOnly])
+        .keys(2, 2, 1)
+        .summary("Returns the reference count of a value of a key."),
+    Spec::new("object|idletime", 3, &[Flag::ReadOnly])
+        .keys(2, 2, 1)
+        .summary("Returns the time since the last access to a Redis object."),
+    Spec::help("object|help"),
+    Spec::new("dump", 2, &[Flag::ReadOnly])
+        .keys(1, 1, 1)
+        .summary("Returns a serialized representation of the value stored at a key."),
+    Spec::new("restore", -4, &[Flag::Write, Flag::DenyOom])
+        .keys(1, 1, 1)
+        .summary("Creates a key from the serialized representation of a value."),
+    Spec::new("migrate", -6, &[Flag::Write, Flag::SelfPropagating])
+        .keys(3, 3, 1)
+        .summary("Atomically transfers a key from one Redis instance to another."),
+    Spec::new("export", 1, &[Flag::ReadOnly, Flag::Admin])
+        .summary("Returns every key in the database as a line of JSON."),
+    Spec::new("import", -2, &[Flag::Write, Flag::DenyOom, Flag::Admin, Flag::SelfPropagating])
+        .summary("Creates keys from lines of JSON produced by EXPORT."),
+    Spec::new("dbsize", 1, &[Flag::ReadOnly, Flag::Fast])
+        .summary("Returns the number of keys in the database."),
+    Spec::new("randomkey", 1, &[Flag::ReadOnly])
+        .summary("Returns a random key name from the database."),
+    Spec::new("bigkeys", 1, &[Flag::ReadOnly])
+        .summary("Returns the biggest key of each type in the database."),
+    Spec::new("hotkeys", -1, &[Flag::ReadOnly])
+        .summary("Returns the most frequently accessed keys in the database."),
+    Spec::new("touch", -2, &[Flag::ReadOnly, Flag::Fast])
+        .keys(1, -1, 1)
+        .summary("Alters the last access time of one or more keys."),
+    Spec::new("flushdb", -1, &[Flag::Write, Flag::Deterministic])
+        .summary("Removes all keys from the current database."),
+    Spec::new("flushall", -1, &[Flag::Write, Flag::Deterministic])
+        .summary("Removes all keys from all databases."),
+    Spec::new("move", 3, &[Flag::Write, Flag::Fast, Flag::Deterministic])
+        .keys(1, 1, 1)
+        .summary("Moves a key to another database."),
+    Spec::new("swapdb", 3, &[Flag::Write, Flag::Fast, Flag::Deterministic])
+        .summary("Swaps two Redis databases."),
+];
+
+pub(super) fn execute<S: Storage>(
+    db: &Db<S>,
+    command: Command,
+) -> BoxFuture<'_, Result<Frame, DbError>> {
+    Box::pin(async move {
+        match command {
+            Command::Keys(pattern) => Ok(bulk_array(db.keys(&pattern).await)),
+            Command::Scan(scan) => {
+                let (cursor, keys) = db
+                    .scan(
+                        scan.cursor,
+                        scan.count,
+                        scan.pattern.as_deref(),
+                        scan.key_type.as_deref(),
+                    )
+                    .await;
+                Ok(Frame::Array(vec![Frame::Bulk(cursor.to_string()), bulk_array(keys)]))
+            }
+            Command::Del(keys) => Ok(Frame::Integer(db.del(&keys).await as i64)),
+            Command::Unlink(keys) => Ok(Frame::Integer(db.unlink(&keys).await as i64)),
+            Command::Expire(key, expiry, flags) => {
+                let at = expiry.deadline_ms(unix_time_ms());
+                Ok(Frame::Integer(db.expire_at_if(&key, at, flags).await as i64))
+            }
+            Command::Persist(key) => Ok(Frame::Integer(db.persist(&key).await as i64)),
+            Command::Ttl(key) => Ok(ttl_reply(db.expire_time(&key).await, 1000)),
+            Command::PTtl(key) => Ok(ttl_reply(db.expire_time(&key).await, 1)),
+            Command::ExpireTime(key) => Ok(expire_time_reply(db.expire_time(&key).await, 1000)),
+            Command::PExpireTime(key) => Ok(expire_time_reply(db.expire_time(&key).await, 1)),
+            Command::ObjectEncoding(key) => {
+                Ok(db.object_encoding(&key).await.map_or(Frame::Null, |e| Frame::Bulk(e.into())))
+            }
+            Command::ObjectRefCount(key) => {
+                Ok(db.object_refcount(&key).await.map_or(Frame::Null, |n| Frame::Integer(n as i64)))
+            }
+            Command::ObjectIdleTime(key) => {
+                Ok(db.object_idletime(&key).await.map_or(Frame::Null, |n| Frame::Integer(n as i64)))
+            }
+            Command::Dump(key) => {
+                Ok(db.dump(&key).await.map_or(Frame::Null, |(payload, _)| Frame::Bulk(payload)))
+            }
+            Command::Restore(key, ttl, payload, replace, absttl) => {
+                let expire_at = match ttl {
+                    0 => None,
+                    at if absttl => Some(at),
+                    ttl => Some(unix_time_ms().saturating_add(ttl)),
+                };
+                db.restore_key(key, &payload, expire_at, replace)
+                    .await
+                    .map(|()| Frame::Simple("OK".into()))
+            }
+            Command::Migrate(migrate) => db
+                .migrate(&migrate)
+                .await
+                .map(|migrated| Frame::Simple(if migrated { "OK" } else { "NOKEY" }.into())),
+            Command::Export => Ok(bulk_array(db.export().await)),
+            Command::Import(lines) => db.import(&lines).await.map(|n| Frame::Integer(n as i64)),
+            Command::DbSize => Ok(Frame::Integer(db.dbsize().await as i64)),
+            Command::RandomKey => Ok(bulk_or_null(db.randomkey().await)),
+            Command::BigKeys => Ok(Frame::Array(
+                db.big_keys()
+                    .await
+                    .into_iter()
+                    .map(|big| {
+                        Frame::Array(vec![
+                            Frame::Bulk(big.key_type.into()),
+                            Frame::Bulk(big.key),
+                            Frame::Integer(big.size as i64),
+                            Frame::Integer(big.keys as i64),
+                        ])
+                    })
+                    .collect(),
+            )),
+            Command::HotKeys(count) => Ok(Frame::Array(
+                db.hot_keys(count)
+                    .await
+                    .into_iter()
+                    .map(|(key, frequency)| {
+                        Frame::Array(vec![Frame::Bulk(key), Frame::Integer(frequency.into())])
+                    })
+                    .collect(),
+            )),
+            Command::Touch(keys) => Ok(Frame::Integer(db.touch(&keys).await as i64)),
+            Command::FlushDb(lazy) => {
+                db.flush(lazy).await;
+                Ok(Frame::Simple("OK".into()))
+            }
+            Command::FlushAll(lazy) => {
+                db.flush_all(lazy).await;
+                Ok(Frame::Simple("OK".into()))
+            }
+            Command::Move(key, index) => {
+                db.move_key(&key, index).await.map(|moved| Frame::Integer(moved as i64))
+            }
+            Command::SwapDb(a, b) => db.swap_db(a, b).await.map(|()| Frame::Simple("OK".into())),
+            Command::Help(name) => Ok(help_reply(name)),
+            command => unreachable!("{} is not a keyspace command", command.name()),
+        }
+    })
+}
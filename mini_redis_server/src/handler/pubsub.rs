@@ -0,0 +1,85 @@
+//! 发布/订阅命令
+
+use super::{BoxFuture, Flag, Spec, bulk_array, help_reply};
+use crate::{
+    command::Command,
+    db::{Db, DbError, Storage},
+    frame::Frame,
+};
+
+pub(super) const GROUP: &str = "pubsub";
+
+pub(super) const COMMANDS: &[Spec] = &[
+    Spec::new("publish", 3, &[Flag::PubSub, Flag::Fast]).summary("Posts a message to a channel."),
+    Spec::new("subscribe", -2, &[Flag::PubSub])
+        .summary("Listens for messages published to channels."),
+    Spec::new("unsubscribe", -1, &[Flag::PubSub])
+        .summary("Stops listening to messages posted to channels."),
+    Spec::new("psubscribe", -2, &[Flag::PubSub])
+        .summary("Listens for messages published to channels that match one or more patterns."),
+    Spec::new("punsubscribe", -1, &[Flag::PubSub]).summary(
+        "Stops listening to messages published to channels that match one or more patterns.",
+    ),
+    Spec::new("pubsub|channels", -2, &[Flag::PubSub]).summary("Returns the active channels."),
+    Spec::new("pubsub|numsub", -2, &[Flag::PubSub])
+        .summary("Returns a count of subscribers to channels."),
+    Spec::new("pubsub|numpat", 2, &[Flag::PubSub])
+        .summary("Returns a count of unique pattern subscriptions."),
+    Spec::new("spublish", 3, &[Flag::PubSub, Flag::Fast])
+        .summary("Post a message to a shard channel."),
+    Spec::new("ssubscribe", -2, &[Flag::PubSub])
+        .summary("Listens for messages published to shard channels."),
+    Spec::new("sunsubscribe", -1, &[Flag::PubSub])
+        .summary("Stops listening to messages posted to shard channels."),
+    Spec::new("pubsub|shardchannels", -2, &[Flag::PubSub])
+        .summary("Returns the active shard channels."),
+    Spec::new("pubsub|shardnumsub", -2, &[Flag::PubSub])
+        .summary("Returns the count of subscribers of shard channels."),
+    Spec::help("pubsub|help"),
+];
+
+pub(super) fn execute<S: Storage>(
+    db: &Db<S>,
+    command: Command,
+) -> BoxFuture<'_, Result<Frame, DbError>> {
+    Box::pin(async move {
+        match command {
+            Command::Publish(channel, message) => {
+                Ok(Frame::Integer(db.pubsub().publish(&channel, &message) as i64))
+            }
+            Command::PubSubChannels(pattern) => {
+                Ok(bulk_array(db.pubsub().channels(pattern.as_deref())))
+            }
+            Command::PubSubNumSub(channels) => Ok(counts_reply(db.pubsub().numsub(&channels))),
+            Command::PubSubNumPat => Ok(Frame::Integer(db.pubsub().numpat() as i64)),
+            Command::SPublish(channel, message) => {
+                Ok(Frame::Integer(db.pubsub().spublish(&channel, &message) as i64))
+            }
+            Command::PubSubShardChannels(pattern) => {
+                Ok(bulk_array(db.pubsub().shard_channels(pattern.as_deref())))
+            }
+            Command::PubSubShardNumSub(channels) => {
+                Ok(counts_reply(db.pubsub().shard_numsub(&channels)))
+            }
+            // 订阅状态属于连接，需通过 `Session` 执行
+            Command::Subscribe(_)
+            | Command::Unsubscribe(_)
+            | Command::PSubscribe(_)
+            | Command::PUnsubscribe(_)
+            | Command::SSubscribe(_)
+            | Command::SUnsubscribe(_) => {
+                Ok(Frame::Error("ERR subscription commands require a client session".into()))
+            }
+            Command::Help(name) => Ok(help_reply(name)),
+            command => unreachable!("{} is not a pubsub command", command.name()),
+        }
+    })
+}
+
+/// `[频道, 订阅者数量, ...]` 形式的回复
+fn counts_reply(counts: Vec<(String, usize)>) -> Frame {
+    let counts = counts
+        .into_iter()
+        .flat_map(|(channel, count)| [Frame::Bulk(channel), Frame::Integer(count as i64)]);
+    Frame::Array(counts.collect())
+}
@@ -0,0 +1,453 @@
+//! 命令注册表
+//!
+//! 每条命令由一个 [`CommandHandler`] 处理：它声明命令名、参数个数、标志、键的位置与简介，
+//! 并负责执行。内置命令按类别分布在 `handler` 的各个子模块中，每个模块提供分组名、
+//! 一张命令表（[`Spec`] 的列表）与一个执行函数，由 [`Registry::default`] 统一注册；
+//! 新增命令只需修改对应的模块。
+//!
+//! 命令表是静态的，解析层按它检查参数个数（见 [`spec`]），COMMAND 等内省命令也由它生成。
+//!
+//! 与 Redis 的 `rename-command` 一样，注册表可以带一张改名表（[`Renames`]）：
+//! 客户端发来的命令经 [`Registry::parse`] 解析，改名后的命令只能以新名称调用，
+//! 被禁用的命令视为不存在。改名只影响客户端，服务器内部（复制、AOF 等）仍使用原来的命令名。
+
+use std::{collections::BTreeMap, fmt, future::Future, pin::Pin};
+
+use super::{
+    bitmap, connection, geo, hash, introspection, keyspace, pubsub, sentinel, server, set, stream,
+    string, zset,
+};
+use crate::{
+    command::{Command, ParseError},
+    db::{Db, DbError, Storage},
+    frame::Frame,
+};
+
+/// 命令执行返回的 future
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// 命令的执行函数，只会收到命令表中登记的命令
+pub type Exec<S> = for<'a> fn(&'a Db<S>, Command) -> BoxFuture<'a, Result<Frame, DbError>>;
+
+/// 命令的属性标志，名称与 Redis 的 COMMAND INFO 一致；
+/// 控制写命令传播方式的 `self_propagating` 与 `deterministic` 是本实现特有的
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Flag {
+    /// 可能修改数据
+    Write,
+    /// 只读取数据
+    ReadOnly,
+    /// 可能增加内存占用，超出 maxmemory 时拒绝执行
+    DenyOom,
+    /// 管理命令
+    Admin,
+    /// 发布/订阅相关命令
+    PubSub,
+    /// 可能阻塞连接
+    Blocking,
+    /// 时间复杂度为 O(1) 或 O(log N) 的命令
+    Fast,
+    /// 认证前也可以执行
+    NoAuth,
+    /// 写命令在执行过程中由 `Db` 自行传播到追加日志与副本（如阻塞命令在弹出时），
+    /// 命令处理层不再传播；与 Redis 的 `may-replicate`（可能需要复制的非写命令）无关
+    SelfPropagating,
+    /// 对同样的数据总是产生同样的修改，原样传播；
+    /// 没有这个标志的写命令改写为确定性的等价形式后传播（如 SPOP 改写为 SREM）
+    Deterministic,
+}
+
+impl Flag {
+    /// 标志名（小写）
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Flag::Write => "write",
+            Flag::ReadOnly => "readonly",
+            Flag::DenyOom => "denyoom",
+            Flag::Admin => "admin",
+            Flag::PubSub => "pubsub",
+            Flag::Blocking => "blocking",
+            Flag::Fast => "fast",
+            Flag::NoAuth => "no_auth",
+            Flag::SelfPropagating => "self_propagating",
+            Flag::Deterministic => "deterministic",
+        }
+    }
+}
+
+impl fmt::Display for Flag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 命令表中的一项
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Spec {
+    /// 命令名（见 [`Command::name`]）
+    pub name: &'static str,
+    /// 参数个数要求，约定见 [`arity`](crate::command::arity)
+    pub arity: i64,
+    pub flags: &'static [Flag],
+    /// 第一个键、最后一个键的参数位置与键的间隔，见 [`Spec::keys`]
+    pub key_positions: (i64, i64, i64),
+    /// 一句话简介，用于 COMMAND DOCS
+    pub summary: &'static str,
+}
+
+impl Spec {
+    /// 不访问键的命令，简介为空
+    pub const fn new(name: &'static str, arity: i64, flags: &'static [Flag]) -> Self {
+        Self { name, arity, flags, key_positions: (0, 0, 0), summary: "" }
+    }
+
+    /// 设置键的位置：从第 `first` 个参数（命令名为第 0 个）起每隔 `step` 个参数一个键，
+    /// 直到第 `last` 个参数；`last` 为负数时从末尾数起（-1 为最后一个参数）
+    pub const fn keys(self, first: i64, last: i64, step: i64) -> Self {
+        Self { key_positions: (first, last, step), ..self }
+    }
+
+    /// 带子命令的命令的 HELP 子命令，如 `Spec::help("config|help")`
+    pub const fn help(name: &'static str) -> Self {
+        Self::new(name, 2, &[Flag::Fast])
+            .summary("Returns helpful text about the different subcommands.")
+    }
+
+    pub const fn summary(self, summary: &'static str) -> Self {
+        Self { summary, ..self }
+    }
+
+    /// 是否带有指定标志
+    pub fn has(&self, flag: Flag) -> bool {
+        self.flags.contains(&flag)
+    }
+}
+
+/// 处理一条命令
+pub trait CommandHandler<S: Storage>: Send + Sync {
+    /// 命令名（见 [`Command::name`]）
+    fn name(&self) -> &'static str;
+
+    /// 参数个数要求，约定见 [`arity`](crate::command::arity)
+    fn arity(&self) -> i64;
+
+    fn flags(&self) -> &'static [Flag];
+
+    /// 键的位置，约定见 [`Spec::keys`]
+    fn key_positions(&self) -> (i64, i64, i64);
+
+    /// 所属分组（如 `string`、`sorted-set`）
+    fn group(&self) -> &'static str;
+
+    fn summary(&self) -> &'static str;
+
+    /// 执行命令，返回回复帧；错误回复为 `Err`
+    fn execute<'a>(
+        &'a self,
+        db: &'a Db<S>,
+        command: Command,
+    ) -> BoxFuture<'a, Result<Frame, DbError>>;
+}
+
+/// 由命令表中的一项与执行函数组成的处理器，内置命令都使用它
+pub struct Handler<S: Storage> {
+    group: &'static str,
+    spec: &'static Spec,
+    exec: Exec<S>,
+}
+
+impl<S: Storage> Handler<S> {
+    pub fn new(group: &'static str, spec: &'static Spec, exec: Exec<S>) -> Self {
+        Self { group, spec, exec }
+    }
+}
+
+impl<S: Storage> CommandHandler<S> for Handler<S> {
+    fn name(&self) -> &'static str {
+        self.spec.name
+    }
+
+    fn arity(&self) -> i64 {
+        self.spec.arity
+    }
+
+    fn flags(&self) -> &'static [Flag] {
+        self.spec.flags
+    }
+
+    fn key_positions(&self) -> (i64, i64, i64) {
+        self.spec.key_positions
+    }
+
+    fn group(&self) -> &'static str {
+        self.group
+    }
+
+    fn summary(&self) -> &'static str {
+        self.spec.summary
+    }
+
+    fn execute<'a>(
+        &'a self,
+        db: &'a Db<S>,
+        command: Command,
+    ) -> BoxFuture<'a, Result<Frame, DbError>> {
+        (self.exec)(db, command)
+    }
+}
+
+/// 命令改名表：`rename-command` 配置的原命令名与客户端使用的新名称
+///
+/// 改名以顶层命令为单位，带子命令的命令（如 CONFIG）连同全部子命令一起改名；
+/// 新名称为空表示禁用该命令。
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Renames {
+    /// 按配置顺序记录的 `(原名, 新名)`，名称均为小写
+    entries: Vec<(&'static str, String)>,
+}
+
+impl Renames {
+    /// 把命令 `name` 改名为 `new_name`，`new_name` 为空时禁用该命令
+    pub fn rename(&mut self, name: &str, new_name: &str) -> Result<(), String> {
+        let name = name.to_ascii_lowercase();
+        let new_name = new_name.to_ascii_lowercase();
+        let original = specs()
+            .map(|spec| spec.name.split_once('|').map_or(spec.name, |(name, _)| name))
+            .find(|original| *original == name)
+            .filter(|original| self.resolve(original).is_some())
+            .ok_or_else(|| format!("no such command '{name}'"))?;
+        let taken = spec_exists(&new_name) || self.entries.iter().any(|(_, new)| *new == new_name);
+        if !new_name.is_empty() && taken {
+            return Err(format!("target command name '{new_name}' already exists"));
+        }
+        self.entries.push((original, new_name));
+        Ok(())
+    }
+
+    /// 客户端使用的命令名对应的原命令名；被禁用或已改名的原名称返回 `None`
+    pub fn resolve<'a>(&'a self, name: &'a str) -> Option<&'a str> {
+        if self.entries.is_empty() {
+            return Some(name);
+        }
+        let lower = name.to_ascii_lowercase();
+        if let Some((original, _)) = self.entries.iter().find(|(_, new)| *new == lower) {
+            return Some(original);
+        }
+        match self.entries.iter().any(|(original, _)| *original == lower) {
+            true => None,
+            false => Some(name),
+        }
+    }
+}
+
+/// 与配置文件的写法一致：`config abc123 flushall ""`
+impl fmt::Display for Renames {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (original, new_name)) in self.entries.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            match new_name.as_str() {
+                "" => write!(f, "{original} \"\"")?,
+                new_name => write!(f, "{original} {new_name}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 是否存在名为 `name` 的内置命令（不论是否被改名）
+fn spec_exists(name: &str) -> bool {
+    specs().any(|spec| spec.name.split_once('|').map_or(spec.name, |(name, _)| name) == name)
+}
+
+/// 按命令名索引的处理器
+pub struct Registry<S: Storage> {
+    handlers: BTreeMap<&'static str, Box<dyn CommandHandler<S>>>,
+    renames: Renames,
+}
+
+impl<S: Storage> Registry<S> {
+    /// 创建一个空的注册表
+    pub fn new() -> Self {
+        Self { handlers: BTreeMap::new(), renames: Renames::default() }
+    }
+
+    /// 设置改名表，之后由 [`Registry::parse`] 解析的命令按它改名
+    pub fn set_renames(&mut self, renames: Renames) {
+        self.renames = renames;
+    }
+
+    /// 解析客户端发来的命令：改名后的命令换回原名称再解析，
+    /// 被禁用或已改名的原名称与不存在的命令一样报错
+    pub fn parse(&self, parts: &[&str]) -> Result<Command, ParseError> {
+        let Some((&name, args)) = parts.split_first() else {
+            return Command::from_args(parts);
+        };
+        match self.renames.resolve(name) {
+            Some(original) if original == name => Command::from_args(parts),
+            Some(original) => {
+                let parts: Vec<_> = std::iter::once(original).chain(args.iter().copied()).collect();
+                Command::from_args(&parts)
+            }
+            None => Err(ParseError::UnknownCommand(
+                name.to_string(),
+                args.iter().map(|arg| arg.to_string()).collect(),
+            )),
+        }
+    }
+
+    /// 注册处理器，替换同名的已有处理器
+    pub fn register(&mut self, handler: impl CommandHandler<S> + 'static) {
+        self.handlers.insert(handler.name(), Box::new(handler));
+    }
+
+    /// 按命令名查找处理器
+    pub fn get(&self, name: &str) -> Option<&dyn CommandHandler<S>> {
+        self.handlers.get(name).map(Box::as_ref)
+    }
+
+    /// 按命令名排序遍历全部处理器
+    pub fn iter(&self) -> impl Iterator<Item = &dyn CommandHandler<S>> {
+        self.handlers.values().map(Box::as_ref)
+    }
+
+    /// 处理器的数量
+    pub fn len(&self) -> usize {
+        self.handlers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+}
+
+impl<S: Storage> Default for Registry<S> {
+    /// 注册全部内置命令
+    fn default() -> Self {
+        let modules: [(&'static str, &'static [Spec], Exec<S>); 13] = [
+            (string::GROUP, string::COMMANDS, string::execute),
+            (bitmap::GROUP, bitmap::COMMANDS, bitmap::execute),
+            (hash::GROUP, hash::COMMANDS, hash::execute),
+            (set::GROUP, set::COMMANDS, set::execute),
+            (zset::GROUP, zset::COMMANDS, zset::execute),
+            (geo::GROUP, geo::COMMANDS, geo::execute),
+            (stream::GROUP, stream::COMMANDS, stream::execute),
+            (keyspace::GROUP, keyspace::COMMANDS, keyspace::execute),
+            (pubsub::GROUP, pubsub::COMMANDS, pubsub::execute),
+            (server::GROUP, server::COMMANDS, server::execute),
+            (sentinel::GROUP, sentinel::COMMANDS, sentinel::execute),
+            (introspection::GROUP, introspection::COMMANDS, introspection::execute),
+            (connection::GROUP, connection::COMMANDS, connection::execute),
+        ];
+
+        let mut registry = Self::new();
+        for (group, specs, exec) in modules {
+            for spec in specs {
+                registry.register(Handler::new(group, spec, exec));
+            }
+        }
+        registry
+    }
+}
+
+/// 全部内置命令的命令表
+pub fn specs() -> impl Iterator<Item = &'static Spec> {
+    [
+        string::COMMANDS,
+        bitmap::COMMANDS,
+        hash::COMMANDS,
+        set::COMMANDS,
+        zset::COMMANDS,
+        geo::COMMANDS,
+        stream::COMMANDS,
+        keyspace::COMMANDS,
+        pubsub::COMMANDS,
+        server::COMMANDS,
+        sentinel::COMMANDS,
+        introspection::COMMANDS,
+        connection::COMMANDS,
+    ]
+    .into_iter()
+    .flatten()
+}
+
+/// 按命令名查找内置命令的命令表项
+pub fn spec(name: &str) -> Option<&'static Spec> {
+    specs().find(|spec| spec.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{Flag, Registry, Renames, spec, specs};
+    use crate::{command::Command, db::Keyspace};
+
+    #[test]
+    fn test_specs() {
+        let names: HashSet<_> = specs().map(|spec| spec.name).collect();
+        assert_eq!(names.len(), specs().count());
+
+        let registry = Registry::<Keyspace>::default();
+        assert_eq!(registry.len(), names.len());
+        assert!(registry.iter().map(|handler| handler.name()).is_sorted());
+
+        let set = registry.get("set").unwrap();
+        assert_eq!((set.arity(), set.flags()), (-3, &[Flag::Write, Flag::DenyOom][..]));
+        assert!(registry.get("nosuch").is_none());
+    }
+
+    #[test]
+    fn test_flags_match_commands() {
+        let inputs = [
+            "set k v ex 10",
+            "get k",
+            "hset h f v",
+            "srem s m",
+            "bzpopmin z 0",
+            "xack s g 1-1",
+            "xgroup create s g $",
+            "restore k 0 payload",
+            "del k",
+            "flushall",
+            "publish c m",
+        ];
+        for input in inputs {
+            let command = Command::parse(input).unwrap();
+            let spec = spec(command.name()).unwrap();
+            assert_eq!(spec.has(Flag::Write), command.is_write(), "{input}");
+            assert_eq!(spec.has(Flag::DenyOom), command.is_denyoom(), "{input}");
+        }
+
+        // 自行传播的命令不由命令处理层传播，两个标志互斥
+        assert!(spec("bzpopmin").unwrap().has(Flag::SelfPropagating));
+        assert!(!spec("spop").unwrap().has(Flag::Deterministic));
+        assert!(spec("srem").unwrap().has(Flag::Deterministic));
+        assert!(
+            specs().all(|spec| !(spec.has(Flag::SelfPropagating) && spec.has(Flag::Deterministic)))
+        );
+    }
+
+    #[test]
+    fn test_renames() {
+        let mut renames = Renames::default();
+        renames.rename("CONFIG", "cfg").unwrap();
+        renames.rename("flushall", "").unwrap();
+        assert!(renames.rename("config", "other").is_err());
+        assert!(renames.rename("nosuch", "x").is_err());
+        assert!(renames.rename("keys", "get").is_err());
+        assert!(renames.rename("keys", "cfg").is_err());
+        assert_eq!(renames.to_string(), "config cfg flushall \"\"");
+
+        let mut registry = Registry::<Keyspace>::default();
+        registry.set_renames(renames);
+        assert_eq!(registry.parse(&["CFG", "get", "port"]), Ok(Command::ConfigGet("port".into())));
+        assert_eq!(
+            registry.parse(&["config", "get", "port"]).unwrap_err().to_string(),
+            "ERR unknown command 'config', with args beginning with: 'get' 'port' "
+        );
+        assert!(registry.parse(&["FLUSHALL"]).is_err());
+        assert!(registry.parse(&["get", "k"]).is_ok());
+    }
+}
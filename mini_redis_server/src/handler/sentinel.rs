@@ -0,0 +1,66 @@
+//! 哨兵命令：SENTINEL 的各个子命令，监控与故障转移见 [`sentinel`](crate::sentinel)
+
+use super::{BoxFuture, Flag, Spec, bulk_array, help_reply};
+use crate::{
+    command::Command,
+    db::{Db, DbError, Storage},
+    frame::Frame,
+};
+
+pub(super) const GROUP: &str = "sentinel";
+
+pub(super) const COMMANDS: &[Spec] = &[
+    Spec::new("sentinel|monitor", 6, &[Flag::Admin])
+        .summary("Starts monitoring a master with the given name."),
+    Spec::new("sentinel|remove", 3, &[Flag::Admin]).summary("Stops monitoring a master."),
+    Spec::new("sentinel|set", -5, &[Flag::Admin])
+        .summary("Changes the monitoring parameters of a master."),
+    Spec::new("sentinel|masters", 2, &[Flag::Admin])
+        .summary("Returns the state of all monitored masters."),
+    Spec::new("sentinel|master", 3, &[Flag::Admin])
+        .summary("Returns the state of a monitored master."),
+    Spec::new("sentinel|replicas", 3, &[Flag::Admin])
+        .summary("Returns the replicas of a monitored master."),
+    Spec::new("sentinel|sentinels", 3, &[Flag::Admin])
+        .summary("Returns the other sentinels monitoring a master."),
+    Spec::new("sentinel|get-master-addr-by-name", 3, &[Flag::Admin])
+        .summary("Returns the address of a monitored master."),
+    Spec::new("sentinel|is-master-down-by-addr", 6, &[Flag::Admin])
+        .summary("Checks whether a master is down and requests a failover vote."),
+    Spec::new("sentinel|failover", 3, &[Flag::Admin])
+        .summary("Forces a failover of a monitored master."),
+    Spec::new("sentinel|myid", 2, &[Flag::Admin]).summary("Returns the sentinel's ID."),
+    Spec::help("sentinel|help"),
+];
+
+pub(super) fn execute<S: Storage>(
+    db: &Db<S>,
+    command: Command,
+) -> BoxFuture<'_, Result<Frame, DbError>> {
+    Box::pin(async move {
+        let sentinel = db.sentinel();
+        let ok = |()| Frame::Simple("OK".into());
+        match command {
+            Command::SentinelMonitor(name, host, port, quorum) => {
+                db.sentinel_monitor(name, host, port, quorum).map(ok)
+            }
+            Command::SentinelRemove(name) => sentinel.remove(&name).map(ok),
+            Command::SentinelSet(name, options) => sentinel.set(&name, &options).map(ok),
+            Command::SentinelMasters => Ok(sentinel.masters()),
+            Command::SentinelMaster(name) => sentinel.master(&name),
+            Command::SentinelReplicas(name) => sentinel.replicas(&name),
+            Command::SentinelSentinels(name) => sentinel.sentinels(&name),
+            Command::SentinelGetMasterAddr(name) => Ok(match sentinel.master_addr(&name) {
+                Some((host, port)) => bulk_array(vec![host, port.to_string()]),
+                None => Frame::Null,
+            }),
+            Command::SentinelIsMasterDownByAddr(host, port, epoch, runid) => {
+                Ok(sentinel.is_master_down_by_addr(&host, port, epoch, &runid))
+            }
+            Command::SentinelFailover(name) => sentinel.failover(&name).map(ok),
+            Command::SentinelMyId => Ok(Frame::Bulk(sentinel.myid().into())),
+            Command::Help(name) => Ok(help_reply(name)),
+            command => unreachable!("{} is not a sentinel command", command.name()),
+        }
+    })
+}
@@ -0,0 +1,183 @@
+//! 服务器管理命令：持久化、复制、配置、信息、延迟监控、故障注入与 ACL
+
+use std::time::Duration;
+
+use super::{BoxFuture, Flag, Spec, bulk_array, help_reply};
+use crate::{
+    command::Command,
+    db::{Db, DbError, Storage},
+    frame::Frame,
+    info,
+};
+
+pub(super) const GROUP: &str = "server";
+
+pub(super) const COMMANDS: &[Spec] = &[
+    Spec::new("bgrewriteaof", 1, &[Flag::Admin])
+        .summary("Asynchronously rewrites the append-only file to disk."),
+    Spec::new("save", 1, &[Flag::Admin]).summary("Synchronously saves the database(s) to disk."),
+    Spec::new("bgsave", 1, &[Flag::Admin]).summary("Asynchronously saves the database(s) to disk."),
+    Spec::new("lastsave", 1, &[Flag::Fast])
+        .summary("Returns the Unix timestamp of the last successful save to disk."),
+    Spec::new("replicaof", 3, &[Flag::Admin])
+        .summary("Configures a server as replica of another, or promotes it to a master."),
+    Spec::new("role", 1, &[Flag::Fast]).summary("Returns the replication role."),
+    Spec::new("replconf", -2, &[Flag::Admin])
+        .summary("An internal command for configuring the replication stream."),
+    Spec::new("psync", 3, &[Flag::Admin]).summary("An internal command used in replication."),
+    Spec::new("sync", 1, &[Flag::Admin]).summary("An internal command used in replication."),
+    Spec::new("wait", 3, &[])
+        .summary("Blocks until the preceding write commands of the connection are replicated."),
+    Spec::new("config|get", 3, &[Flag::Admin])
+        .summary("Returns the effective values of configuration parameters."),
+    Spec::new("config|set", -4, &[Flag::Admin]).summary("Sets configuration parameters in-flight."),
+    Spec::new("config|resetstat", 2, &[Flag::Admin]).summary("Resets the server's statistics."),
+    Spec::help("config|help"),
+    Spec::new("latency|latest", 2, &[Flag::Admin])
+        .summary("Returns the latest latency samples for all events."),
+    Spec::new("latency|history", 3, &[Flag::Admin])
+        .summary("Returns timestamp-latency samples for an event."),
+    Spec::new("latency|reset", -2, &[Flag::Admin])
+        .summary("Resets the latency data for one or more events."),
+    Spec::help("latency|help"),
+    Spec::new("debug|sleep", 3, &[Flag::Admin])
+        .summary("Blocks the connection for the given number of seconds."),
+    Spec::new("debug|delay", 4, &[Flag::Admin])
+        .summary("Injects latency before every execution of a command."),
+    Spec::new("debug|drop", -3, &[Flag::Admin])
+        .summary("Closes the connection instead of executing the next calls of a command."),
+    Spec::new("debug|contend", 3, &[Flag::Admin])
+        .summary("Holds the write locks of the current database for a while."),
+    Spec::new("debug|faults", 2, &[Flag::Admin]).summary("Lists the injected faults."),
+    Spec::new("debug|reset", 2, &[Flag::Admin]).summary("Removes all injected faults."),
+    Spec::help("debug|help"),
+    Spec::new("info", -1, &[]).summary("Returns information and statistics about the server."),
+    Spec::new("monitor", 1, &[Flag::Admin])
+        .summary("Listens for all requests received by the server in real-time."),
+    Spec::new("acl|setuser", -3, &[Flag::Admin])
+        .summary("Creates and modifies an ACL user and its rules."),
+    Spec::new("acl|getuser", 3, &[Flag::Admin]).summary("Lists the ACL rules of a user."),
+    Spec::new("acl|list", 2, &[Flag::Admin])
+        .summary("Dumps the effective rules in ACL file format."),
+    Spec::new("acl|whoami", 2, &[])
+        .summary("Returns the authenticated username of the current connection."),
+    Spec::help("acl|help"),
+];
+
+pub(super) fn execute<S: Storage>(
+    db: &Db<S>,
+    command: Command,
+) -> BoxFuture<'_, Result<Frame, DbError>> {
+    Box::pin(async move {
+        match command {
+            Command::BgRewriteAof => db
+                .bgrewriteaof()
+                .await
+                .map(|()| Frame::Simple("Background append only file rewriting started".into())),
+            Command::Save => db.save().await.map(|()| Frame::Simple("OK".into())),
+            Command::BgSave => {
+                db.bgsave().await.map(|()| Frame::Simple("Background saving started".into()))
+            }
+            Command::LastSave => Ok(Frame::Integer(db.lastsave() as i64)),
+            Command::ReplicaOf(Some((host, port))) => {
+                db.replicaof(host, port);
+                Ok(Frame::Simple("OK".into()))
+            }
+            Command::ReplicaOf(None) => {
+                db.replicaof_no_one();
+                Ok(Frame::Simple("OK".into()))
+            }
+            Command::Role => Ok(db.replication().role()),
+            Command::ReplConf(_) => Ok(Frame::Simple("OK".into())),
+            // 全量同步需要接管网络连接，由服务器处理
+            Command::Psync(..) | Command::Sync => {
+                Ok(Frame::Error("ERR PSYNC and SYNC require a network connection".into()))
+            }
+            Command::Wait(_, _) if db.replication().is_replica() => {
+                Ok(Frame::Error("ERR WAIT cannot be used with replica instances".into()))
+            }
+            Command::Wait(numreplicas, timeout) => {
+                let timeout = (timeout > 0).then(|| Duration::from_millis(timeout));
+                Ok(Frame::Integer(db.wait_for_replicas(numreplicas, timeout).await as i64))
+            }
+            Command::ConfigGet(pattern) => {
+                let pairs = db.config().get(&pattern).into_iter();
+                Ok(Frame::Map(
+                    pairs.map(|(name, value)| (Frame::Bulk(name), Frame::Bulk(value))).collect(),
+                ))
+            }
+            Command::ConfigSet(pairs) => db.config_set(&pairs).map(|()| Frame::Simple("OK".into())),
+            Command::ConfigResetStat => {
+                db.stats().reset();
+                Ok(Frame::Simple("OK".into()))
+            }
+            Command::LatencyLatest => {
+                let events = db.latency().latest().into_iter().map(|(event, sample, max)| {
+                    Frame::Array(vec![
+                        Frame::Bulk(event.into()),
+                        Frame::Integer(sample.time as i64),
+                        Frame::Integer(sample.latency as i64),
+                        Frame::Integer(max as i64),
+                    ])
+                });
+                Ok(Frame::Array(events.collect()))
+            }
+            Command::LatencyHistory(event) => {
+                let samples = db.latency().history(&event).into_iter().map(|sample| {
+                    Frame::Array(vec![
+                        Frame::Integer(sample.time as i64),
+                        Frame::Integer(sample.latency as i64),
+                    ])
+                });
+                Ok(Frame::Array(samples.collect()))
+            }
+            Command::LatencyReset(events) => Ok(Frame::Integer(db.latency().reset(&events) as i64)),
+            Command::DebugSleep(..)
+            | Command::DebugDelay(..)
+            | Command::DebugDrop(..)
+            | Command::DebugContend(..)
+            | Command::DebugFaults
+            | Command::DebugReset
+                if !cfg!(feature = "fault-injection") =>
+            {
+                Err(DbError::DebugDisabled)
+            }
+            Command::DebugSleep(seconds) => {
+                tokio::time::sleep(Duration::from_secs_f64(seconds)).await;
+                Ok(Frame::Simple("OK".into()))
+            }
+            Command::DebugDelay(command, ms) => {
+                db.faults().set_delay(&command, ms);
+                Ok(Frame::Simple("OK".into()))
+            }
+            Command::DebugDrop(command, count) => {
+                db.faults().add_drops(&command, count);
+                Ok(Frame::Simple("OK".into()))
+            }
+            Command::DebugContend(ms) => {
+                db.contend(Duration::from_millis(ms)).await;
+                Ok(Frame::Simple("OK".into()))
+            }
+            Command::DebugFaults => Ok(bulk_array(db.faults().list())),
+            Command::DebugReset => {
+                db.faults().reset();
+                Ok(Frame::Simple("OK".into()))
+            }
+            Command::Info(section) => Ok(Frame::Bulk(info::info(db, section.as_deref()).await)),
+            // 监视器与认证的用户属于连接，需通过 `Session` 执行
+            Command::Monitor => Ok(Frame::Error("ERR MONITOR requires a client session".into())),
+            Command::AclWhoAmI => {
+                Ok(Frame::Error("ERR ACL WHOAMI requires a client session".into()))
+            }
+            Command::AclSetUser(name, rules) => {
+                db.acl().set_user(&name, &rules).map(|()| Frame::Simple("OK".into()))
+            }
+            Command::AclGetUser(name) => {
+                Ok(db.acl().user(&name).map_or(Frame::Null, |u| u.to_frame()))
+            }
+            Command::AclList => Ok(bulk_array(db.acl().list())),
+            Command::Help(name) => Ok(help_reply(name)),
+            command => unreachable!("{} is not a server command", command.name()),
+        }
+    })
+}
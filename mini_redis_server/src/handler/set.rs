@@ -0,0 +1,108 @@
+//! 集合命令
+
+use super::{BoxFuture, Flag, Spec, bulk_array, bulk_or_null, bulk_set};
+use crate::{
+    command::Command,
+    db::{Db, DbError, Storage},
+    frame::Frame,
+};
+
+pub(super) const GROUP: &str = "set";
+
+pub(super) const COMMANDS: &[Spec] = &[
+    Spec::new("sadd", -3, &[Flag::Write, Flag::DenyOom, Flag::Fast, Flag::Deterministic])
+        .keys(1, 1, 1)
+        .summary("Adds one or more members to a set."),
+    Spec::new("srem", -3, &[Flag::Write, Flag::Fast, Flag::Deterministic])
+        .keys(1, 1, 1)
+        .summary("Removes one or more members from a set."),
+    Spec::new("smembers", 2, &[Flag::ReadOnly])
+        .keys(1, 1, 1)
+        .summary("Returns all members of a set."),
+    Spec::new("sismember", 3, &[Flag::ReadOnly, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Determines whether a member belongs to a set."),
+    Spec::new("scard", 2, &[Flag::ReadOnly, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Returns the number of members in a set."),
+    Spec::new("smismember", -3, &[Flag::ReadOnly, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Determines whether multiple members belong to a set."),
+    Spec::new("spop", -2, &[Flag::Write, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Returns one or more random members from a set after removing them."),
+    Spec::new("srandmember", -2, &[Flag::ReadOnly])
+        .keys(1, 1, 1)
+        .summary("Gets one or multiple random members from a set."),
+    Spec::new("sinter", -2, &[Flag::ReadOnly])
+        .keys(1, -1, 1)
+        .summary("Returns the intersect of multiple sets."),
+    Spec::new("sunion", -2, &[Flag::ReadOnly])
+        .keys(1, -1, 1)
+        .summary("Returns the union of multiple sets."),
+    Spec::new("sdiff", -2, &[Flag::ReadOnly])
+        .keys(1, -1, 1)
+        .summary("Returns the difference of multiple sets."),
+    Spec::new("sinterstore", -3, &[Flag::Write, Flag::DenyOom, Flag::Deterministic])
+        .keys(1, -1, 1)
+        .summary("Stores the intersect of multiple sets in a key."),
+    Spec::new("sunionstore", -3, &[Flag::Write, Flag::DenyOom, Flag::Deterministic])
+        .keys(1, -1, 1)
+        .summary("Stores the union of multiple sets in a key."),
+    Spec::new("sdiffstore", -3, &[Flag::Write, Flag::DenyOom, Flag::Deterministic])
+        .keys(1, -1, 1)
+        .summary("Stores the difference of multiple sets in a key."),
+    Spec::new("sintercard", -3, &[Flag::ReadOnly])
+        .summary("Returns the number of members of the intersect of multiple sets."),
+];
+
+pub(super) fn execute<S: Storage>(
+    db: &Db<S>,
+    command: Command,
+) -> BoxFuture<'_, Result<Frame, DbError>> {
+    Box::pin(async move {
+        match command {
+            Command::SAdd(key, members) => {
+                db.sadd(key, members).await.map(|n| Frame::Integer(n as i64))
+            }
+            Command::SRem(key, members) => {
+                db.srem(&key, &members).await.map(|n| Frame::Integer(n as i64))
+            }
+            Command::SMembers(key) => db.smembers(&key).await.map(bulk_set),
+            Command::SIsMember(key, member) => {
+                db.sismember(&key, &member).await.map(|found| Frame::Integer(found as i64))
+            }
+            Command::SCard(key) => db.scard(&key).await.map(|n| Frame::Integer(n as i64)),
+            Command::SMIsMember(key, members) => db.smismember(&key, &members).await.map(|found| {
+                Frame::Array(found.into_iter().map(|found| Frame::Integer(found as i64)).collect())
+            }),
+            Command::SPop(key, None) => {
+                db.spop(&key, 1).await.map(|members| bulk_or_null(members.into_iter().next()))
+            }
+            Command::SPop(key, Some(count)) => db.spop(&key, count).await.map(bulk_array),
+            Command::SRandMember(key, None) => db
+                .srandmember(&key, None)
+                .await
+                .map(|members| bulk_or_null(members.into_iter().next())),
+            Command::SRandMember(key, Some(count)) => {
+                db.srandmember(&key, Some(count)).await.map(bulk_array)
+            }
+            Command::SInter(keys) => db.sinter(&keys).await.map(bulk_set),
+            Command::SUnion(keys) => db.sunion(&keys).await.map(bulk_set),
+            Command::SDiff(keys) => db.sdiff(&keys).await.map(bulk_set),
+            Command::SInterStore(dest, keys) => {
+                db.sinterstore(dest, &keys).await.map(|n| Frame::Integer(n as i64))
+            }
+            Command::SUnionStore(dest, keys) => {
+                db.sunionstore(dest, &keys).await.map(|n| Frame::Integer(n as i64))
+            }
+            Command::SDiffStore(dest, keys) => {
+                db.sdiffstore(dest, &keys).await.map(|n| Frame::Integer(n as i64))
+            }
+            Command::SInterCard(keys, limit) => {
+                db.sintercard(&keys, limit).await.map(|n| Frame::Integer(n as i64))
+            }
+            command => unreachable!("{} is not a set command", command.name()),
+        }
+    })
+}
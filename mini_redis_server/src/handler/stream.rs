@@ -0,0 +1,140 @@
+//! 流与消费组命令
+
+use std::time::Duration;
+
+use super::{
+    BoxFuture, Flag, Spec, bulk_array, help_reply, pending_summary, stream_entries, xread_reply,
+};
+use crate::{
+    command::{Command, XPendingRange, XRead, XReadGroup},
+    db::{Db, DbError, Storage, unix_time_ms},
+    frame::Frame,
+};
+
+pub(super) const GROUP: &str = "stream";
+
+pub(super) const COMMANDS: &[Spec] = &[
+    Spec::new("xadd", -5, &[Flag::Write, Flag::DenyOom, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Appends a new message to a stream."),
+    Spec::new("xlen", 2, &[Flag::ReadOnly, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Return the number of messages in a stream."),
+    Spec::new("xrange", -4, &[Flag::ReadOnly])
+        .keys(1, 1, 1)
+        .summary("Returns the messages from a stream within a range of IDs."),
+    Spec::new("xread", -4, &[Flag::ReadOnly, Flag::Blocking])
+        .summary("Returns messages from multiple streams with IDs greater than the given ones."),
+    Spec::new("xgroup|create", -5, &[Flag::Write, Flag::DenyOom, Flag::Deterministic])
+        .keys(2, 2, 1)
+        .summary("Creates a consumer group."),
+    Spec::new("xgroup|setid", 5, &[Flag::Write, Flag::Deterministic])
+        .keys(2, 2, 1)
+        .summary("Sets the last-delivered ID of a consumer group."),
+    Spec::new("xgroup|destroy", 4, &[Flag::Write, Flag::Deterministic])
+        .keys(2, 2, 1)
+        .summary("Destroys a consumer group."),
+    Spec::new("xgroup|createconsumer", 5, &[Flag::Write, Flag::DenyOom, Flag::Deterministic])
+        .keys(2, 2, 1)
+        .summary("Creates a consumer in a consumer group."),
+    Spec::help("xgroup|help"),
+    Spec::new("xreadgroup", -7, &[Flag::Write, Flag::Blocking, Flag::SelfPropagating])
+        .summary("Returns new or historical messages from a stream for a consumer in a group."),
+    Spec::new("xack", -4, &[Flag::Write, Flag::Fast, Flag::Deterministic])
+        .keys(1, 1, 1)
+        .summary("Acknowledges messages delivered to a consumer group member of a stream."),
+    Spec::new("xpending", -3, &[Flag::ReadOnly])
+        .keys(1, 1, 1)
+        .summary("Returns the entries from a stream consumer group's pending entries list."),
+    Spec::new("xclaim", -6, &[Flag::Write, Flag::Fast, Flag::SelfPropagating])
+        .keys(1, 1, 1)
+        .summary("Changes, or acquires, ownership of a message in a consumer group."),
+];
+
+pub(super) fn execute<S: Storage>(
+    db: &Db<S>,
+    command: Command,
+) -> BoxFuture<'_, Result<Frame, DbError>> {
+    Box::pin(async move {
+        match command {
+            Command::XAdd(key, id, fields) => {
+                db.xadd(key, id, fields).await.map(|id| Frame::Bulk(id.to_string()))
+            }
+            Command::XLen(key) => db.xlen(&key).await.map(|n| Frame::Integer(n as i64)),
+            Command::XRange(key, range, count) => {
+                db.xrange(&key, &range, count).await.map(stream_entries)
+            }
+            Command::XRead(XRead { count, block: None, streams }) => {
+                db.xread(&streams, count).await.map(xread_reply)
+            }
+            Command::XRead(XRead { count, block: Some(ms), streams }) => {
+                let timeout = (ms > 0).then(|| Duration::from_millis(ms));
+                db.xread_block(&streams, count, timeout).await.map(xread_reply)
+            }
+            Command::XGroupCreate(key, group, id, mkstream) => db
+                .xgroup_create(key, &group, id, mkstream)
+                .await
+                .map(|_| Frame::Simple("OK".into())),
+            Command::XGroupSetId(key, group, id) => {
+                db.xgroup_setid(&key, &group, id).await.map(|_| Frame::Simple("OK".into()))
+            }
+            Command::XGroupDestroy(key, group) => db
+                .xgroup_destroy(&key, &group)
+                .await
+                .map(|destroyed| Frame::Integer(destroyed as i64)),
+            Command::XGroupCreateConsumer(key, group, consumer) => db
+                .xgroup_createconsumer(&key, &group, &consumer)
+                .await
+                .map(|created| Frame::Integer(created as i64)),
+            Command::XReadGroup(XReadGroup { group, consumer, count, block, noack, streams }) => {
+                match block {
+                    None => db.xreadgroup(&group, &consumer, &streams, count, noack).await,
+                    Some(ms) => {
+                        let timeout = (ms > 0).then(|| Duration::from_millis(ms));
+                        db.xreadgroup_block(&group, &consumer, &streams, count, noack, timeout)
+                            .await
+                    }
+                }
+                .map(xread_reply)
+            }
+            Command::XAck(key, group, ids) => {
+                db.xack(&key, &group, &ids).await.map(|n| Frame::Integer(n as i64))
+            }
+            Command::XPending(key, group, None) => {
+                db.xpending(&key, &group).await.map(pending_summary)
+            }
+            Command::XPending(
+                key,
+                group,
+                Some(XPendingRange { min_idle, range, count, consumer }),
+            ) => {
+                let pending =
+                    db.xpending_range(&key, &group, &range, count, consumer.as_deref(), min_idle);
+                let now = unix_time_ms();
+                pending.await.map(|pending| {
+                    let pending = pending.into_iter().map(|(id, pending)| {
+                        Frame::Array(vec![
+                            Frame::Bulk(id.to_string()),
+                            Frame::Bulk(pending.consumer),
+                            Frame::Integer(now.saturating_sub(pending.delivery_time) as i64),
+                            Frame::Integer(pending.delivery_count as i64),
+                        ])
+                    });
+                    Frame::Array(pending.collect())
+                })
+            }
+            Command::XClaim(key, group, consumer, ids, options) => {
+                let claimed = db.xclaim(&key, &group, &consumer, &ids, &options).await;
+                claimed.map(|claimed| {
+                    if options.just_id {
+                        bulk_array(claimed.into_iter().map(|(id, _)| id.to_string()).collect())
+                    } else {
+                        stream_entries(claimed)
+                    }
+                })
+            }
+            Command::Help(name) => Ok(help_reply(name)),
+            command => unreachable!("{} is not a stream command", command.name()),
+        }
+    })
+}
@@ -0,0 +1,65 @@
+//! 字符串命令
+
+use super::{BoxFuture, Flag, Spec, bulk_bytes};
+use crate::{
+    command::Command,
+    db::{Db, DbError, Storage, TtlPolicy, unix_time_ms},
+    frame::Frame,
+};
+
+pub(super) const GROUP: &str = "string";
+
+pub(super) const COMMANDS: &[Spec] = &[
+    Spec::new("get", 2, &[Flag::ReadOnly, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Returns the string value of a key."),
+    Spec::new("set", -3, &[Flag::Write, Flag::DenyOom])
+        .keys(1, 1, 1)
+        .summary("Sets the string value of a key, ignoring its type."),
+    Spec::new("getset", 3, &[Flag::Write, Flag::DenyOom, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Returns the previous string value of a key after setting it to a new value."),
+    Spec::new("incr", 2, &[Flag::Write, Flag::DenyOom, Flag::Fast, Flag::Deterministic])
+        .keys(1, 1, 1)
+        .summary("Increments the integer value of a key by one."),
+    Spec::new("incrby", 3, &[Flag::Write, Flag::DenyOom, Flag::Fast, Flag::Deterministic])
+        .keys(1, 1, 1)
+        .summary("Increments the integer value of a key by a number."),
+];
+
+pub(super) fn execute<S: Storage>(
+    db: &Db<S>,
+    command: Command,
+) -> BoxFuture<'_, Result<Frame, DbError>> {
+    Box::pin(async move {
+        match command {
+            Command::Get(key) => {
+                db.get(&key).await.map(|value| value.map_or(Frame::Null, bulk_bytes))
+            }
+            Command::Set(key, value) => {
+                db.set(key, value).await;
+                Ok(Frame::Simple("OK".into()))
+            }
+            Command::SetWith(key, value, options) => {
+                let policy = match options.expiry {
+                    Some(expiry) => TtlPolicy::ExpireAt(expiry.deadline_ms(unix_time_ms())),
+                    None if options.keep_ttl => TtlPolicy::Keep,
+                    None => TtlPolicy::Clear,
+                };
+                if options.get {
+                    let old = db.getset(key, value, policy).await?;
+                    return Ok(old.map_or(Frame::Null, bulk_bytes));
+                }
+                db.set_with_policy(key, value, policy).await;
+                Ok(Frame::Simple("OK".into()))
+            }
+            Command::GetSet(key, value) => {
+                let old = db.getset(key, value, TtlPolicy::Clear).await?;
+                Ok(old.map_or(Frame::Null, bulk_bytes))
+            }
+            Command::Incr(key) => db.incr_by(key, 1).await.map(Frame::Integer),
+            Command::IncrBy(key, delta) => db.incr_by(key, delta).await.map(Frame::Integer),
+            command => unreachable!("{} is not a string command", command.name()),
+        }
+    })
+}
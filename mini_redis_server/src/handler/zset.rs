@@ -0,0 +1,99 @@
+//! 有序集合命令
+
+use super::{BoxFuture, Flag, Spec, block_timeout, blocking_pop_reply, scored_array};
+use crate::{
+    command::Command,
+    db::{Db, DbError, Storage},
+    frame::Frame,
+};
+
+pub(super) const GROUP: &str = "sorted-set";
+
+pub(super) const COMMANDS: &[Spec] = &[
+    Spec::new("zadd", -4, &[Flag::Write, Flag::DenyOom, Flag::Fast, Flag::Deterministic])
+        .keys(1, 1, 1)
+        .summary("Adds one or more members to a sorted set, or updates their scores."),
+    Spec::new("zscore", 3, &[Flag::ReadOnly, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Returns the score of a member in a sorted set."),
+    Spec::new("zcard", 2, &[Flag::ReadOnly, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Returns the number of members in a sorted set."),
+    Spec::new("zrange", -4, &[Flag::ReadOnly])
+        .keys(1, 1, 1)
+        .summary("Returns members in a sorted set within a range of indexes."),
+    Spec::new("zrangebyscore", -4, &[Flag::ReadOnly])
+        .keys(1, 1, 1)
+        .summary("Returns members in a sorted set within a range of scores."),
+    Spec::new("zrangebylex", -4, &[Flag::ReadOnly])
+        .keys(1, 1, 1)
+        .summary("Returns members in a sorted set within a lexicographical range."),
+    Spec::new("zrank", 3, &[Flag::ReadOnly, Flag::Fast])
+        .keys(1, 1, 1)
+        .summary("Returns the index of a member in a sorted set ordered by ascending scores."),
+    Spec::new("zincrby", 4, &[Flag::Write, Flag::DenyOom, Flag::Fast, Flag::Deterministic])
+        .keys(1, 1, 1)
+        .summary("Increments the score of a member in a sorted set."),
+    Spec::new("zpopmin", -2, &[Flag::Write, Flag::Fast, Flag::Deterministic])
+        .keys(1, 1, 1)
+        .summary("Returns the lowest-scoring members from a sorted set after removing them."),
+    Spec::new("zpopmax", -2, &[Flag::Write, Flag::Fast, Flag::Deterministic])
+        .keys(1, 1, 1)
+        .summary("Returns the highest-scoring members from a sorted set after removing them."),
+    Spec::new("bzpopmin", -3, &[Flag::Write, Flag::Fast, Flag::Blocking, Flag::SelfPropagating])
+        .keys(1, -2, 1)
+        .summary("Blocks until a member is available, then pops the lowest-scoring one."),
+    Spec::new("bzpopmax", -3, &[Flag::Write, Flag::Fast, Flag::Blocking, Flag::SelfPropagating])
+        .keys(1, -2, 1)
+        .summary("Blocks until a member is available, then pops the highest-scoring one."),
+];
+
+pub(super) fn execute<S: Storage>(
+    db: &Db<S>,
+    command: Command,
+) -> BoxFuture<'_, Result<Frame, DbError>> {
+    Box::pin(async move {
+        match command {
+            Command::ZAdd(key, flags, pairs) => {
+                db.zadd(key, flags, pairs).await.map(|n| Frame::Integer(n as i64))
+            }
+            Command::ZScore(key, member) => {
+                db.zscore(&key, &member).await.map(|score| score.map_or(Frame::Null, Frame::Double))
+            }
+            Command::ZCard(key) => db.zcard(&key).await.map(|n| Frame::Integer(n as i64)),
+            Command::ZRange(key, start, stop, rev, with_scores) => db
+                .zrange(&key, start, stop, rev)
+                .await
+                .map(|items| scored_array(items, with_scores)),
+            Command::ZRangeByScore(key, range, with_scores, offset, count) => db
+                .zrange_by_score(&key, &range, offset, count)
+                .await
+                .map(|items| scored_array(items, with_scores)),
+            Command::ZRangeByLex(key, range, offset, count) => db
+                .zrange_by_lex(&key, &range, offset, count)
+                .await
+                .map(|items| scored_array(items, false)),
+            Command::ZRank(key, member) => db
+                .zrank(&key, &member)
+                .await
+                .map(|rank| rank.map_or(Frame::Null, |rank| Frame::Integer(rank as i64))),
+            Command::ZIncrBy(key, delta, member) => {
+                db.zincrby(key, delta, member).await.map(Frame::Double)
+            }
+            Command::ZPopMin(key, count) => db
+                .zpop(&key, count.unwrap_or(1), false)
+                .await
+                .map(|items| scored_array(items, true)),
+            Command::ZPopMax(key, count) => {
+                db.zpop(&key, count.unwrap_or(1), true).await.map(|items| scored_array(items, true))
+            }
+            Command::BZPopMin(keys, timeout) => {
+                db.bzpop(&keys, false, block_timeout(timeout)).await.map(blocking_pop_reply)
+            }
+            Command::BZPopMax(keys, timeout) => {
+                db.bzpop(&keys, true, block_timeout(timeout)).await.map(blocking_pop_reply)
+            }
+            command => unreachable!("{} is not a sorted set command", command.name()),
+        }
+    })
+}
@@ -0,0 +1,211 @@
+//! INFO 命令
+//!
+//! 按 Redis 的格式输出服务器状态：每个分节以 `# Name` 开头，后面是若干行 `field:value`，
+//! 分节之间以空行分隔，行尾为 `\r\n`。
+//!
+//! 支持的分节：server、clients、memory、stats、persistence、replication、commandstats；
+//! 不带参数或参数为 `default` 时输出除 commandstats 以外的分节，`all` / `everything` 输出全部分节。
+
+use std::{fmt::Write, process};
+
+use crate::db::{Db, Storage};
+
+/// 全部分节，按输出顺序排列
+const SECTIONS: [&str; 7] =
+    ["server", "clients", "memory", "stats", "persistence", "replication", "commandstats"];
+
+/// 默认输出的分节数：commandstats 每条命令一行，只在显式请求时输出
+const DEFAULT_SECTIONS: usize = 6;
+
+/// 生成 INFO 的输出；未知的分节输出为空字符串
+pub async fn info<S: Storage>(db: &Db<S>, section: Option<&str>) -> String {
+    let section = section.map(str::to_ascii_lowercase);
+    let selected: Vec<_> = match section.as_deref() {
+        None | Some("default") => SECTIONS[..DEFAULT_SECTIONS].to_vec(),
+        Some("all" | "everything") => SECTIONS.to_vec(),
+        Some(name) => SECTIONS.into_iter().filter(|s| *s == name).collect(),
+    };
+
+    let mut out = String::new();
+    for name in selected {
+        if !out.is_empty() {
+            out.push_str("\r\n");
+        }
+        let title = name[..1].to_ascii_uppercase() + &name[1..];
+        let _ = write!(out, "# {title}\r\n");
+        if name == "commandstats" {
+            write_command_stats(db, &mut out);
+            continue;
+        }
+        for (field, value) in fields(db, name).await {
+            let _ = write!(out, "{field}:{value}\r\n");
+        }
+    }
+    out
+}
+
+/// 一个分节中的字段
+async fn fields<S: Storage>(db: &Db<S>, section: &str) -> Vec<(&'static str, String)> {
+    let stats = db.stats();
+    match section {
+        "server" => {
+            let uptime = stats.uptime();
+            vec![
+                ("redis_version", env!("CARGO_PKG_VERSION").into()),
+                ("redis_mode", "standalone".into()),
+                ("process_id", process::id().to_string()),
+                ("tcp_port", db.config().current().port.to_string()),
+                ("hz", db.config().current().hz.to_string()),
+                ("uptime_in_seconds", uptime.to_string()),
+                ("uptime_in_days", (uptime / 86400).to_string()),
+            ]
+        }
+        "clients" => vec![
+            ("connected_clients", stats.connected_clients().to_string()),
+            ("maxclients", db.config().current().maxclients.to_string()),
+        ],
+        "memory" => {
+            let used = db.used_memory().await;
+            vec![
+                ("used_memory", used.to_string()),
+                ("used_memory_human", human_bytes(used)),
+                ("maxmemory", db.maxmemory().to_string()),
+                ("maxmemory_human", human_bytes(db.maxmemory())),
+                ("maxmemory_policy", db.eviction_policy().to_string()),
+            ]
+        }
+        "stats" => vec![
+            ("total_connections_received", stats.connections_received().to_string()),
+            ("total_commands_processed", stats.commands_processed().to_string()),
+            ("instantaneous_ops_per_sec", stats.instantaneous_ops_per_sec().to_string()),
+            ("rejected_connections", stats.rejected_connections().to_string()),
+            ("keyspace_hits", stats.keyspace_hits().to_string()),
+            ("keyspace_misses", stats.keyspace_misses().to_string()),
+            ("expired_keys", stats.expired_keys().to_string()),
+            ("evicted_keys", stats.evicted_keys().to_string()),
+            ("lazyfreed_objects", stats.lazyfreed_objects().to_string()),
+            (
+                "client_output_buffer_limit_disconnections",
+                stats.output_buffer_limit_disconnections().to_string(),
+            ),
+            ("throttled_commands", stats.throttled_commands().to_string()),
+        ],
+        "persistence" => {
+            let aof = db.aof();
+            let rewrite_ok = aof.is_none_or(|aof| aof.last_rewrite_ok());
+            vec![
+                ("rdb_changes_since_last_save", db.dirty().to_string()),
+                ("rdb_bgsave_in_progress", flag(db.is_saving())),
+                ("rdb_last_save_time", db.lastsave().to_string()),
+                ("aof_enabled", flag(aof.is_some())),
+                ("aof_rewrite_in_progress", flag(aof.is_some_and(|aof| aof.is_rewriting()))),
+                ("aof_last_bgrewrite_status", if rewrite_ok { "ok" } else { "err" }.into()),
+            ]
+        }
+        "replication" => db.replication().info(),
+        _ => Vec::new(),
+    }
+}
+
+/// commandstats 分节：每条调用过的命令一行，
+/// `cmdstat_<name>:calls=<n>,usec=<n>,usec_per_call=<n>,usec_max=<n>`
+fn write_command_stats<S: Storage>(db: &Db<S>, out: &mut String) {
+    for (name, stat) in db.stats().command_stats() {
+        let per_call = stat.usec as f64 / stat.calls as f64;
+        let _ = write!(
+            out,
+            "cmdstat_{name}:calls={},usec={},usec_per_call={per_call:.2},usec_max={}\r\n",
+            stat.calls, stat.usec, stat.usec_max
+        );
+    }
+}
+
+fn flag(value: bool) -> String {
+    u8::from(value).to_string()
+}
+
+/// 以 B / K / M / G 为单位的可读大小，与 Redis 的 `*_human` 字段一致
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [(u64, &str); 3] = [(1 << 30, "G"), (1 << 20, "M"), (1 << 10, "K")];
+    match UNITS.iter().find(|(size, _)| bytes >= *size) {
+        Some((size, unit)) => format!("{:.2}{unit}", bytes as f64 / *size as f64),
+        None => format!("{bytes}B"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::process_command;
+
+    fn field<'a>(info: &'a str, name: &str) -> Option<&'a str> {
+        info.lines().find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+    }
+
+    #[tokio::test]
+    async fn test_info_sections() {
+        let db = Db::new();
+
+        let all = info(&db, None).await;
+        let titles: Vec<_> = all.lines().filter(|line| line.starts_with('#')).collect();
+        assert_eq!(
+            titles,
+            ["# Server", "# Clients", "# Memory", "# Stats", "# Persistence", "# Replication"]
+        );
+        assert!(all.contains("\r\n\r\n# Clients\r\n"));
+
+        let memory = info(&db, Some("MEMORY")).await;
+        assert!(memory.starts_with("# Memory\r\n"));
+        assert_eq!(field(&memory, "maxmemory_policy"), Some("noeviction"));
+        assert_eq!(field(&memory, "connected_clients"), None);
+        assert_eq!(info(&db, Some("nosuch")).await, "");
+    }
+
+    #[tokio::test]
+    async fn test_info_stats() {
+        let db = Db::new();
+        process_command(&db, "set a 1").await;
+        process_command(&db, "get a").await;
+        process_command(&db, "get b").await;
+        process_command(&db, "smembers b").await;
+
+        let stats = info(&db, Some("stats")).await;
+        assert_eq!(field(&stats, "total_commands_processed"), Some("4"));
+        assert_eq!(field(&stats, "keyspace_hits"), Some("1"));
+        assert_eq!(field(&stats, "keyspace_misses"), Some("2"));
+
+        let persistence = info(&db, Some("persistence")).await;
+        assert_eq!(field(&persistence, "rdb_changes_since_last_save"), Some("1"));
+        assert_eq!(field(&persistence, "aof_enabled"), Some("0"));
+        assert_eq!(field(&info(&db, Some("replication")).await, "role"), Some("master"));
+    }
+
+    #[tokio::test]
+    async fn test_info_commandstats() {
+        let db = Db::new();
+        process_command(&db, "set a 1").await;
+        process_command(&db, "get a").await;
+        process_command(&db, "get b").await;
+        process_command(&db, "config get timeout").await;
+        assert!(!info(&db, None).await.contains("# Commandstats"));
+
+        let stats = info(&db, Some("commandstats")).await;
+        assert!(stats.starts_with("# Commandstats\r\ncmdstat_config|get:calls=1,"), "{stats}");
+        assert!(field(&stats, "cmdstat_get").unwrap().starts_with("calls=2,usec="));
+        assert!(field(&stats, "cmdstat_set").unwrap().contains(",usec_max="));
+        assert!(info(&db, Some("all")).await.contains("cmdstat_get:calls=2,"));
+
+        assert_eq!(process_command(&db, "config resetstat").await, "OK");
+        let stats = info(&db, Some("commandstats")).await;
+        assert!(stats.starts_with("# Commandstats\r\ncmdstat_config|resetstat:calls=1,"));
+        assert_eq!(stats.lines().count(), 2);
+        assert_eq!(field(&info(&db, Some("stats")).await, "total_commands_processed"), Some("0"));
+    }
+
+    #[test]
+    fn test_human_bytes() {
+        assert_eq!(human_bytes(512), "512B");
+        assert_eq!(human_bytes(1536), "1.50K");
+        assert_eq!(human_bytes(1 << 30), "1.00G");
+    }
+}
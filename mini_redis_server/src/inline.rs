@@ -0,0 +1,144 @@
+//! 内联协议
+//!
+//! 不以 `*` 开头的请求按 Redis 的内联格式解析：一行以空白分隔的参数，以 LF（或 CRLF）结尾，
+//! 便于用 `telnet` / `nc` 直接输入命令。参数可以用引号包裹，规则与 Redis 的 `sdssplitargs` 一致：
+//! - 双引号内支持 `\n` `\r` `\t` `\b` `\a` `\xHH` 等转义，其他字符前的反斜杠只保留该字符
+//! - 单引号内只有 `\'` 是转义
+//! - 右引号后面必须是空白或行尾
+//!
+//! [`Command::parse`](crate::command::Command::parse) 使用同样的规则切分文本命令。
+
+use crate::frame::ProtocolError;
+
+/// 内联请求一行的默认最大长度（`proto-max-inline-len`）
+pub const MAX_INLINE_LEN: usize = 64 * 1024;
+
+/// 从缓冲区开头解码一行内联请求，返回参数与消耗的字节数；一行还没有完整到达时返回 `None`
+///
+/// 没有读到行尾的数据已经超过 `max_len` 字节时视为协议错误。空行解码为空的参数列表，调用方应忽略。
+pub fn decode(buf: &[u8], max_len: usize) -> Result<Option<(Vec<String>, usize)>, ProtocolError> {
+    let Some(end) = buf.iter().position(|&b| b == b'\n') else {
+        if buf.len() > max_len {
+            return Err(ProtocolError("too big inline request".into()));
+        }
+        return Ok(None);
+    };
+
+    let line = buf[..end].strip_suffix(b"\r").unwrap_or(&buf[..end]);
+    let line = std::str::from_utf8(line)
+        .map_err(|_| ProtocolError("inline request is not valid UTF-8".into()))?;
+    let args =
+        split_args(line).ok_or_else(|| ProtocolError("unbalanced quotes in request".into()))?;
+    Ok(Some((args, end + 1)))
+}
+
+/// 把一行文本切分为参数；引号不匹配、`\x` 后不是两位十六进制数或转义得到的内容不是合法 UTF-8 时返回 `None`
+pub fn split_args(line: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else { return Some(args) };
+
+        let mut arg = Vec::new();
+        match first {
+            '"' => {
+                chars.next();
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            'x' => {
+                                let hex: String = [chars.next()?, chars.next()?].iter().collect();
+                                arg.push(u8::from_str_radix(&hex, 16).ok()?);
+                            }
+                            c => push_char(&mut arg, unescape(c)),
+                        },
+                        c => push_char(&mut arg, c),
+                    }
+                }
+                if chars.peek().is_some_and(|c| !c.is_whitespace()) {
+                    return None;
+                }
+            }
+            '\'' => {
+                chars.next();
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        '\\' if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            arg.push(b'\'');
+                        }
+                        c => push_char(&mut arg, c),
+                    }
+                }
+                if chars.peek().is_some_and(|c| !c.is_whitespace()) {
+                    return None;
+                }
+            }
+            _ => {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    push_char(&mut arg, c);
+                }
+            }
+        }
+        args.push(String::from_utf8(arg).ok()?);
+    }
+}
+
+/// 双引号内反斜杠后的字符对应的实际字符
+fn unescape(c: char) -> char {
+    match c {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        'b' => '\u{8}',
+        'a' => '\u{7}',
+        c => c,
+    }
+}
+
+fn push_char(buf: &mut Vec<u8>, c: char) {
+    buf.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_INLINE_LEN, decode, split_args};
+
+    #[test]
+    fn test_split_args() {
+        let args = |line| split_args(line).unwrap();
+
+        assert_eq!(args("  set foo  bar "), ["set", "foo", "bar"]);
+        assert_eq!(args(r#"set greeting "hello world""#), ["set", "greeting", "hello world"]);
+        assert_eq!(args(r#""a\"b\n\x41" 'it\'s' """#), ["a\"b\nA", "it's", ""]);
+        assert_eq!(args(r#"'no \n escape'"#), [r"no \n escape"]);
+        assert!(args("").is_empty());
+
+        assert_eq!(split_args(r#"get "foo"#), None);
+        assert_eq!(split_args(r#"get "foo"bar"#), None);
+        assert_eq!(split_args("get 'foo"), None);
+        assert_eq!(split_args(r#""\xzz""#), None);
+    }
+
+    #[test]
+    fn test_decode() {
+        let decode_default = |buf: &[u8]| decode(buf, MAX_INLINE_LEN);
+        assert_eq!(
+            decode_default(b"set a 1\r\nget a\n").unwrap(),
+            Some((vec!["set".into(), "a".into(), "1".into()], 9))
+        );
+        assert_eq!(decode_default(b"get a\n").unwrap(), Some((vec!["get".into(), "a".into()], 6)));
+        assert_eq!(decode_default(b"\r\n").unwrap(), Some((vec![], 2)));
+        assert_eq!(decode_default(b"get a").unwrap(), None);
+
+        let err = decode_default(b"get \"a\n").unwrap_err();
+        assert_eq!(err.to_string(), "ERR Protocol error: unbalanced quotes in request");
+        assert_eq!(decode(b"get a", 10).unwrap(), None);
+        let err = decode(b"get abcdefg", 10).unwrap_err();
+        assert_eq!(err.to_string(), "ERR Protocol error: too big inline request");
+    }
+}
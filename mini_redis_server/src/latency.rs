@@ -0,0 +1,166 @@
+//! 延迟监控（LATENCY 命令）
+//!
+//! 耗时达到 `latency-monitor-threshold`（毫秒，0 表示关闭）的操作按事件类别记录下来：
+//! - `command` / `fast-command`：命令的执行时间，带 `fast` 标志的命令单独归类
+//! - `snapshot`：为 RDB 快照、AOF 重写或全量复制复制数据集的时间，期间写命令被暂停
+//! - `expire-cycle`：一轮主动过期删除到期键的时间
+//!
+//! 与 Redis 一样，每个事件最多保留最近 [`HISTORY_LEN`] 个样本，同一秒内的多个样本
+//! 只保留最大值；另外记录该事件出现过的最大延迟。
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::db::unix_time_ms;
+
+/// 每个事件保留的样本数
+pub const HISTORY_LEN: usize = 160;
+
+/// 一个延迟样本
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Sample {
+    /// 发生时间（Unix 秒）
+    pub time: u64,
+    /// 延迟（毫秒）
+    pub latency: u64,
+}
+
+/// 一个事件的延迟记录
+#[derive(Default)]
+struct History {
+    samples: VecDeque<Sample>,
+    /// 出现过的最大延迟（毫秒）
+    max: u64,
+}
+
+/// 延迟监控
+#[derive(Default)]
+pub struct LatencyMonitor {
+    /// 记录的阈值（毫秒），0 表示不记录
+    threshold: AtomicU64,
+    events: Mutex<BTreeMap<&'static str, History>>,
+}
+
+impl LatencyMonitor {
+    /// 记录的阈值（毫秒），0 表示不记录
+    pub fn threshold(&self) -> u64 {
+        self.threshold.load(Ordering::Relaxed)
+    }
+
+    /// 设置记录的阈值（毫秒），0 表示不记录
+    pub fn set_threshold(&self, ms: u64) {
+        self.threshold.store(ms, Ordering::Relaxed);
+    }
+
+    /// 耗时达到阈值时为 `event` 记录一个样本
+    pub fn record(&self, event: &'static str, elapsed: Duration) {
+        let threshold = self.threshold();
+        let latency = elapsed.as_millis() as u64;
+        if threshold == 0 || latency < threshold {
+            return;
+        }
+
+        let time = unix_time_ms() / 1000;
+        let mut events = self.events.lock().unwrap();
+        let history = events.entry(event).or_default();
+        history.max = history.max.max(latency);
+        match history.samples.back_mut() {
+            Some(last) if last.time == time => last.latency = last.latency.max(latency),
+            _ => {
+                if history.samples.len() == HISTORY_LEN {
+                    history.samples.pop_front();
+                }
+                history.samples.push_back(Sample { time, latency });
+            }
+        }
+    }
+
+    /// LATENCY LATEST：每个事件的最近一个样本与最大延迟，按事件名排序
+    pub fn latest(&self) -> Vec<(&'static str, Sample, u64)> {
+        let events = self.events.lock().unwrap();
+        events
+            .iter()
+            .filter_map(|(event, history)| Some((*event, *history.samples.back()?, history.max)))
+            .collect()
+    }
+
+    /// LATENCY HISTORY：一个事件的全部样本，从旧到新
+    pub fn history(&self, event: &str) -> Vec<Sample> {
+        let events = self.events.lock().unwrap();
+        events
+            .get(event)
+            .map(|history| history.samples.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// LATENCY RESET：清除指定事件（为空时清除全部事件）的记录，返回清除的事件数
+    pub fn reset(&self, events: &[String]) -> usize {
+        let mut recorded = self.events.lock().unwrap();
+        if events.is_empty() {
+            let count = recorded.len();
+            recorded.clear();
+            return count;
+        }
+        events.iter().filter(|event| recorded.remove(event.as_str()).is_some()).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::Db, handler::process_command};
+
+    #[test]
+    fn test_record() {
+        let monitor = LatencyMonitor::default();
+        monitor.record("command", Duration::from_millis(50));
+        assert!(monitor.latest().is_empty());
+
+        monitor.set_threshold(10);
+        monitor.record("command", Duration::from_millis(5));
+        monitor.record("command", Duration::from_millis(20));
+        monitor.record("command", Duration::from_millis(15));
+        monitor.record("snapshot", Duration::from_millis(30));
+
+        // 同一秒内的样本合并为最大值
+        let history = monitor.history("command");
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].latency, 20);
+        let latest = monitor.latest();
+        assert_eq!(
+            latest.iter().map(|(event, ..)| *event).collect::<Vec<_>>(),
+            ["command", "snapshot"]
+        );
+        assert_eq!(latest[1].2, 30);
+
+        assert_eq!(monitor.reset(&["snapshot".into(), "nosuch".into()]), 1);
+        assert!(monitor.history("snapshot").is_empty());
+        assert_eq!(monitor.reset(&[]), 1);
+        assert!(monitor.latest().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_latency_commands() {
+        let db = Db::new();
+        assert_eq!(process_command(&db, "latency latest").await, "(empty array)");
+        assert_eq!(process_command(&db, "latency history command").await, "(empty array)");
+
+        assert_eq!(process_command(&db, "config set latency-monitor-threshold 1000").await, "OK");
+        assert_eq!(db.latency().threshold(), 1000);
+        db.latency().record("command", Duration::from_millis(1500));
+        let latest = process_command(&db, "latency latest").await;
+        assert!(latest.starts_with("1) 1) command\n2) (integer) "), "{latest}");
+        assert!(latest.ends_with("3) (integer) 1500\n4) (integer) 1500"), "{latest}");
+        let history = process_command(&db, "latency history command").await;
+        assert!(history.ends_with("2) (integer) 1500"), "{history}");
+
+        assert_eq!(process_command(&db, "latency reset").await, "(integer) 1");
+        assert_eq!(process_command(&db, "latency reset command").await, "(integer) 0");
+    }
+}
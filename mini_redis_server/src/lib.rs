@@ -1,3 +1,34 @@
+pub mod acl;
+pub mod audit;
+pub mod bitmap;
+pub mod client;
 pub mod command;
+pub mod config;
+pub mod connection;
 pub mod db;
+pub mod dict;
+pub mod fault;
+pub mod frame;
+pub mod geo;
+pub mod glob;
 pub mod handler;
+pub mod info;
+pub mod inline;
+pub mod latency;
+pub mod migrate;
+pub mod output;
+pub mod persistence;
+pub mod pubsub;
+pub mod random;
+pub mod ratelimit;
+pub mod replication;
+pub mod sentinel;
+pub mod server;
+pub mod session;
+pub mod slot;
+pub mod sorted_set;
+pub mod stats;
+pub mod stream;
+pub mod timer_wheel;
+
+pub use server::{Server, ServerBuilder};
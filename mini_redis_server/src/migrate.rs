@@ -0,0 +1,185 @@
+//! 键迁移模块（MIGRATE）
+//!
+//! 源节点把每个键序列化为 DUMP 载荷，以 `RESTORE key ttl payload [REPLACE]`
+//! 的形式批量发送给目标节点，全部发送后再依次读取回复（目标数据库不是 0 号时先发送 SELECT）；
+//! 未指定 COPY 时删除目标节点已接收的键，并以 DEL 的形式传播。
+//! 通信期间不持有锁，删除前会确认键自序列化以来没有被改写，被改写的键保留在源节点。
+
+use std::{io, time::Duration};
+
+use tokio::net::TcpStream;
+
+use crate::{
+    command::Migrate,
+    connection::Connection,
+    db::{Db, DbError, Storage, unix_time_ms},
+    frame::Frame,
+};
+
+/// 超时参数为 0 时使用的默认超时
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+
+impl<S: Storage> Db<S> {
+    /// 把键迁移到目标节点，所有键都不存在时返回 `false`
+    ///
+    /// 超时作用于整个迁移过程；目标节点拒绝某个键时返回它的第一条错误，
+    /// 已被接收的键仍按 COPY 选项处理。
+    pub async fn migrate(&self, migrate: &Migrate) -> Result<bool, DbError> {
+        let mut dumped = Vec::new();
+        for key in &migrate.keys {
+            if let Some((payload, expire_at)) = self.dump(key).await {
+                dumped.push((key.clone(), payload, expire_at));
+            }
+        }
+        if dumped.is_empty() {
+            return Ok(false);
+        }
+
+        let timeout = match migrate.timeout {
+            0 => DEFAULT_TIMEOUT,
+            ms => Duration::from_millis(ms),
+        };
+        let replies = tokio::time::timeout(timeout, send_restores(migrate, &dumped))
+            .await
+            .map_err(|_| DbError::MigrateIo("timeout".into()))?
+            .map_err(|e| DbError::MigrateIo(e.to_string()))?;
+
+        let mut moved = Vec::new();
+        let mut error = None;
+        for (entry, reply) in dumped.into_iter().zip(replies) {
+            match reply {
+                Frame::Error(e) => error = error.or(Some(e)),
+                _ => moved.push(entry),
+            }
+        }
+
+        if !migrate.copy && !moved.is_empty() {
+            // 与目标节点通信期间被改写的键保留在源节点，避免新写入丢失
+            let _gate = self.enter_write().await;
+            let _order = self.lock_order(&migrate.keys).await;
+            let removed = self.del_unchanged(&moved).await;
+            if !removed.is_empty() {
+                let args: Vec<_> = std::iter::once("del".to_string()).chain(removed).collect();
+                self.propagate(&args)?;
+            }
+        }
+        error.map_or(Ok(true), |e| Err(DbError::MigrateTarget(e)))
+    }
+}
+
+/// 连接目标节点，发送全部 RESTORE 命令并按顺序返回回复
+async fn send_restores(
+    migrate: &Migrate,
+    dumped: &[(String, String, Option<u64>)],
+) -> io::Result<Vec<Frame>> {
+    let stream = TcpStream::connect((migrate.host.as_str(), migrate.port)).await?;
+    let mut conn = Connection::new(stream);
+
+    // SELECT 失败时不能继续发送 RESTORE，否则键会写入目标节点的默认数据库
+    if migrate.db != 0 {
+        conn.write_frame(&Frame::from(vec!["select".to_string(), migrate.db.to_string()])).await?;
+        let reply = conn.read_frame().await?.ok_or(io::ErrorKind::UnexpectedEof)?;
+        if let Frame::Error(_) = reply {
+            return Ok(vec![reply; dumped.len()]);
+        }
+    }
+
+    // 使用相对 TTL，不依赖两个节点的时钟一致
+    let now = unix_time_ms();
+    for (key, payload, expire_at) in dumped {
+        let ttl = expire_at.map_or(0, |at| at.saturating_sub(now).max(1));
+        let mut args = vec!["restore".to_string(), key.clone(), ttl.to_string(), payload.clone()];
+        if migrate.replace {
+            args.push("replace".into());
+        }
+        conn.write_frame(&Frame::from(args)).await?;
+    }
+
+    let mut replies = Vec::with_capacity(dumped.len());
+    for _ in dumped {
+        let reply = conn.read_frame().await?.ok_or(io::ErrorKind::UnexpectedEof)?;
+        replies.push(reply);
+    }
+    Ok(replies)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use crate::{db::Db, handler::process_command, server};
+
+    async fn start_server(db: &Db) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(server::run(listener, db.clone()));
+        port
+    }
+
+    #[tokio::test]
+    async fn test_migrate() {
+        let source = Db::new();
+        let target = Db::new();
+        let port = start_server(&target).await;
+
+        process_command(&source, "set a 1 ex 100").await;
+        process_command(&source, "zadd z 1 m").await;
+        process_command(&source, "set c 3").await;
+
+        let reply = process_command(&source, &format!("migrate 127.0.0.1 {port} a 0 1000")).await;
+        assert_eq!(reply, "OK");
+        assert_eq!(process_command(&source, "get a").await, "(nil)");
+        assert_eq!(process_command(&target, "get a").await, "1");
+        let ttl: i64 = process_command(&target, "ttl a")
+            .await
+            .trim_start_matches("(integer) ")
+            .parse()
+            .unwrap();
+        assert!((99..=100).contains(&ttl));
+
+        let copy = format!("migrate 127.0.0.1 {port} z 0 1000 copy");
+        assert_eq!(process_command(&source, &copy).await, "OK");
+        assert_eq!(process_command(&source, "zscore z m").await, "1");
+        assert_eq!(process_command(&target, "zscore z m").await, "1");
+
+        // 目标键已存在时需要 REPLACE
+        let busy = process_command(&source, &copy).await;
+        assert!(busy.starts_with("ERR Target instance replied with error: BUSYKEY"), "{busy}");
+        let replace = format!("migrate 127.0.0.1 {port} z 0 1000 replace");
+        assert_eq!(process_command(&source, &replace).await, "OK");
+        assert_eq!(process_command(&source, "zscore z m").await, "(nil)");
+
+        let missing = format!("migrate 127.0.0.1 {port} missing 0 1000");
+        assert_eq!(process_command(&source, &missing).await, "NOKEY");
+    }
+
+    #[tokio::test]
+    async fn test_migrate_to_database() {
+        let source = Db::new();
+        let target = Db::new();
+        let port = start_server(&target).await;
+        process_command(&source, "set a 1").await;
+
+        let reply = process_command(&source, &format!("migrate 127.0.0.1 {port} a 3 1000")).await;
+        assert_eq!(reply, "OK");
+        assert_eq!(process_command(&target, "get a").await, "(nil)");
+        assert_eq!(process_command(&target.select(3).unwrap(), "get a").await, "1");
+
+        process_command(&source, "set b 2").await;
+        let reply = process_command(&source, &format!("migrate 127.0.0.1 {port} b 99 1000")).await;
+        assert_eq!(reply, "ERR Target instance replied with error: ERR DB index is out of range");
+        assert_eq!(process_command(&source, "get b").await, "2");
+    }
+
+    #[tokio::test]
+    async fn test_migrate_unreachable() {
+        let db = Db::new();
+        process_command(&db, "set a 1").await;
+
+        // 绑定后立即关闭，得到一个没有监听者的端口
+        let port = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+        let reply = process_command(&db, &format!("migrate 127.0.0.1 {port} a 0 200")).await;
+        assert!(reply.starts_with("IOERR"), "{reply}");
+        assert_eq!(process_command(&db, "get a").await, "1");
+    }
+}
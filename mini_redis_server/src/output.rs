@@ -0,0 +1,194 @@
+//! 客户端输出缓冲区
+//!
+//! 推送给客户端的数据（订阅消息、MONITOR 输出、转发给副本的命令流）先排入队列，
+//! 再由连接任务写出。客户端读取太慢时队列会不断增长，因此每个客户端用一个 [`OutputBuffer`]
+//! 记录已排队、尚未写出的字节数。
+//!
+//! 与 Redis 的 `client-output-buffer-limit` 一样，客户端按 [`ClientClass`] 分为三类，
+//! 每类有硬限制与软限制（[`OutputLimit`]）：超出硬限制时立即断开，超出软限制并持续
+//! `soft-seconds` 秒后断开。服务器的周期任务检查全部客户端（见
+//! [`Clients::enforce_output_limits`](crate::client::Clients::enforce_output_limits)）。
+
+use std::{
+    fmt,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+/// 一个客户端已排队、尚未写出的字节数
+///
+/// 生产方在放入队列前调用 [`queued`](Self::queued)，消费方取出后调用
+/// [`drained`](Self::drained)，因此计数不会出现负数。
+#[derive(Debug, Default)]
+pub struct OutputBuffer {
+    pending: AtomicUsize,
+}
+
+impl OutputBuffer {
+    /// 记录放入队列的字节数
+    pub fn queued(&self, bytes: usize) {
+        self.pending.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// 记录从队列中取出的字节数
+    pub fn drained(&self, bytes: usize) {
+        self.pending.fetch_sub(bytes, Ordering::Relaxed);
+    }
+
+    /// 当前排队的字节数
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Relaxed)
+    }
+}
+
+/// 客户端类别，决定适用的输出缓冲区限制；MONITOR 客户端属于 `Normal`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ClientClass {
+    #[default]
+    Normal,
+    /// 通过 PSYNC / SYNC 接收命令流的副本
+    Replica,
+    /// 处于订阅状态的客户端
+    PubSub,
+}
+
+impl FromStr for ClientClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "normal" => Ok(ClientClass::Normal),
+            "replica" | "slave" => Ok(ClientClass::Replica),
+            "pubsub" => Ok(ClientClass::PubSub),
+            _ => Err(format!("invalid client class '{s}'")),
+        }
+    }
+}
+
+impl fmt::Display for ClientClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ClientClass::Normal => "normal",
+            ClientClass::Replica => "replica",
+            ClientClass::PubSub => "pubsub",
+        })
+    }
+}
+
+/// 一类客户端的输出缓冲区限制，各项为 0 表示不限制
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OutputLimit {
+    /// 硬限制（字节）：达到后立即断开
+    pub hard: usize,
+    /// 软限制（字节）：持续达到 `soft_seconds` 秒后断开
+    pub soft: usize,
+    pub soft_seconds: u64,
+}
+
+impl OutputLimit {
+    /// 按排队的字节数判断是否应断开客户端
+    ///
+    /// `soft_since` 记录开始超出软限制的时间，降到软限制以下时清除。
+    pub fn exceeded(&self, pending: usize, soft_since: &mut Option<Instant>, now: Instant) -> bool {
+        if self.hard > 0 && pending >= self.hard {
+            return true;
+        }
+        if self.soft == 0 || pending < self.soft {
+            *soft_since = None;
+            return false;
+        }
+        let since = *soft_since.get_or_insert(now);
+        now.duration_since(since) >= Duration::from_secs(self.soft_seconds)
+    }
+}
+
+/// 各类客户端的输出缓冲区限制，默认值与 Redis 相同
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutputLimits {
+    pub normal: OutputLimit,
+    pub replica: OutputLimit,
+    pub pubsub: OutputLimit,
+}
+
+impl Default for OutputLimits {
+    fn default() -> Self {
+        Self {
+            normal: OutputLimit::default(),
+            replica: OutputLimit { hard: 256 << 20, soft: 64 << 20, soft_seconds: 60 },
+            pubsub: OutputLimit { hard: 32 << 20, soft: 8 << 20, soft_seconds: 60 },
+        }
+    }
+}
+
+impl OutputLimits {
+    /// 一类客户端的限制
+    pub fn get(&self, class: ClientClass) -> OutputLimit {
+        match class {
+            ClientClass::Normal => self.normal,
+            ClientClass::Replica => self.replica,
+            ClientClass::PubSub => self.pubsub,
+        }
+    }
+
+    /// 修改一类客户端的限制
+    pub fn set(&mut self, class: ClientClass, limit: OutputLimit) {
+        match class {
+            ClientClass::Normal => self.normal = limit,
+            ClientClass::Replica => self.replica = limit,
+            ClientClass::PubSub => self.pubsub = limit,
+        }
+    }
+}
+
+/// 与 CONFIG GET 的格式一致：`normal 0 0 0 replica 268435456 67108864 60 pubsub ...`
+impl fmt::Display for OutputLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let classes = [ClientClass::Normal, ClientClass::Replica, ClientClass::PubSub];
+        for (i, class) in classes.into_iter().enumerate() {
+            let limit = self.get(class);
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{class} {} {} {}", limit.hard, limit.soft, limit.soft_seconds)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exceeded() {
+        let limit = OutputLimit { hard: 100, soft: 10, soft_seconds: 5 };
+        let start = Instant::now();
+        let mut since = None;
+
+        assert!(!limit.exceeded(5, &mut since, start));
+        assert!(limit.exceeded(100, &mut since, start));
+
+        // 超出软限制后开始计时，期间降下来则重新计时
+        assert!(!limit.exceeded(10, &mut since, start));
+        assert_eq!(since, Some(start));
+        assert!(!limit.exceeded(50, &mut since, start + Duration::from_secs(4)));
+        assert!(!limit.exceeded(9, &mut since, start + Duration::from_secs(5)));
+        assert_eq!(since, None);
+        assert!(!limit.exceeded(10, &mut since, start + Duration::from_secs(6)));
+        assert!(limit.exceeded(10, &mut since, start + Duration::from_secs(11)));
+
+        let unlimited = OutputLimit::default();
+        assert!(!unlimited.exceeded(usize::MAX, &mut None, start));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            OutputLimits::default().to_string(),
+            "normal 0 0 0 replica 268435456 67108864 60 pubsub 33554432 8388608 60"
+        );
+        assert_eq!("SLAVE".parse(), Ok(ClientClass::Replica));
+        assert!("master".parse::<ClientClass>().is_err());
+    }
+}
@@ -0,0 +1,128 @@
+//! 持久化模块
+//!
+//! 提供两种持久化方式：
+//! - AOF（append-only file）：每条成功执行的写命令都以 RESP 数组的形式追加到日志文件，
+//!   启动时按顺序回放日志即可重建数据库
+//! - RDB 快照：把整个键空间（包括过期时间）序列化为带校验和的二进制文件
+//!
+//! 单个值的序列化格式（DUMP / RESTORE / MIGRATE 使用）与 RDB 快照共用值的编码。
+//! EXPORT / IMPORT 另用每键一行的 JSON 格式，便于与其他工具交换数据（见 `json` 模块）。
+
+mod aof;
+mod crc64;
+mod dump;
+mod json;
+mod rdb;
+
+use std::{io, path::PathBuf};
+
+use crate::db::{Db, Storage};
+
+pub(crate) use aof::claim_command;
+pub use aof::{Aof, FsyncPolicy, enable_aof};
+pub(crate) use crc64::crc64;
+pub(crate) use dump::{deserialize as deserialize_value, serialize as serialize_value};
+pub(crate) use json::{decode as decode_json, encode as encode_json};
+pub(crate) use rdb::{RdbState, encode as encode_rdb, load as load_rdb};
+pub use rdb::{SavePoint, enable_rdb};
+
+/// 启动时的持久化设置，字段名与默认值对应 Redis 的同名配置
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// 持久化文件所在目录
+    pub dir: PathBuf,
+    /// 是否开启 AOF
+    pub appendonly: bool,
+    /// AOF 文件名
+    pub appendfilename: String,
+    /// AOF 的 fsync 策略
+    pub appendfsync: FsyncPolicy,
+    /// RDB 快照文件名
+    pub dbfilename: String,
+    /// 自动快照条件，空列表表示关闭自动快照
+    pub save: Vec<SavePoint>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("."),
+            appendonly: false,
+            appendfilename: "appendonly.aof".into(),
+            appendfsync: FsyncPolicy::default(),
+            dbfilename: "dump.rdb".into(),
+            save: rdb::DEFAULT_SAVE_POINTS.to_vec(),
+        }
+    }
+}
+
+/// 服务器启动时调用：按配置从持久化文件恢复数据，并开启相应的持久化
+///
+/// 与 Redis 一致，开启 AOF 时只从 AOF 恢复（它比快照更新），否则加载 RDB 快照；
+/// 两种情况下 SAVE / BGSAVE 都写入 `dir/dbfilename`。
+pub async fn open<S: Storage>(db: &Db<S>, config: &Config) -> io::Result<()> {
+    db.set_save_points(config.save.clone());
+    let rdb_path = config.dir.join(&config.dbfilename);
+    if config.appendonly {
+        rdb::set_rdb_path(db, rdb_path);
+        enable_aof(db, config.dir.join(&config.appendfilename), config.appendfsync).await?;
+    } else {
+        enable_rdb(db, rdb_path).await?;
+    }
+    Ok(())
+}
+
+/// 把可在运行时修改的持久化设置（CONFIG SET）同步到 AOF 与 RDB 子系统
+pub(crate) fn apply<S: Storage>(db: &Db<S>, config: &Config) {
+    db.set_save_points(config.save.clone());
+    rdb::set_rdb_path(db, config.dir.join(&config.dbfilename));
+    if let Some(aof) = db.aof() {
+        aof.set_policy(config.appendfsync);
+    }
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, open};
+    use crate::{db::Db, handler::process_command};
+
+    #[tokio::test]
+    async fn test_open_appendonly() {
+        let dir = std::env::temp_dir().join(format!("mini-redis-{}-open", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = Config { dir: dir.clone(), appendonly: true, ..Config::default() };
+
+        let db = Db::new();
+        open(&db, &config).await.unwrap();
+        process_command(&db, "set foo bar").await;
+
+        let restarted = Db::new();
+        open(&restarted, &config).await.unwrap();
+        assert_eq!(process_command(&restarted, "get foo").await, "bar");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_open_loads_rdb() {
+        let dir = std::env::temp_dir().join(format!("mini-redis-{}-open-rdb", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = Config { dir: dir.clone(), ..Config::default() };
+
+        let db = Db::new();
+        open(&db, &config).await.unwrap();
+        process_command(&db, "set foo bar").await;
+        db.save().await.unwrap();
+        assert!(dir.join("dump.rdb").exists());
+
+        let restarted = Db::new();
+        open(&restarted, &config).await.unwrap();
+        assert_eq!(process_command(&restarted, "get foo").await, "bar");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
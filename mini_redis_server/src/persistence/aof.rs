@@ -0,0 +1,746 @@
+//! AOF 追加日志
+//!
+//! 日志由连续的 RESP 数组组成，每个数组是一条写命令的参数（命令名 + 参数），例如：
+//!
+//! ```text
+//! *3\r\n$3\r\nset\r\n$3\r\nfoo\r\n$3\r\nbar\r\n
+//! ```
+//!
+//! 写命令所在的数据库与上一条不同时，先写入一条 `SELECT`（见 [`Db::propagate`]），
+//! 回放时据此切换数据库。
+//!
+//! 写入后何时调用 fsync 由 [`FsyncPolicy`] 决定。
+//!
+//! 日志会随写入不断增长，重写（BGREWRITEAOF）用当前数据集生成最精简的命令流：
+//! 1. 暂停写命令，开始缓存此后追加的命令，并复制数据集；
+//! 2. 在后台线程中把数据集写入临时文件；
+//! 3. 追加缓存的增量命令，fsync 后通过 `rename` 原子地替换旧文件。
+
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        Arc, Mutex, Weak,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use super::{invalid_data, serialize_value};
+use crate::{
+    command::Command,
+    db::{Db, DbError, Snapshot, Storage, Value},
+    frame::Frame,
+    handler,
+    stream::{ConsumerGroup, Pending, StreamId},
+};
+
+/// fsync 策略，对应 Redis 的 `appendfsync` 配置
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FsyncPolicy {
+    /// 每次写入后立即 fsync，最安全也最慢
+    Always,
+    /// 后台线程每秒 fsync 一次，最多丢失约 1 秒的数据
+    #[default]
+    EverySec,
+    /// 从不主动 fsync，由操作系统决定何时落盘
+    No,
+}
+
+impl FromStr for FsyncPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "always" => Ok(FsyncPolicy::Always),
+            "everysec" => Ok(FsyncPolicy::EverySec),
+            "no" => Ok(FsyncPolicy::No),
+            _ => Err(format!("invalid fsync policy '{s}'")),
+        }
+    }
+}
+
+impl fmt::Display for FsyncPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FsyncPolicy::Always => "always",
+            FsyncPolicy::EverySec => "everysec",
+            FsyncPolicy::No => "no",
+        })
+    }
+}
+
+/// 追加日志文件
+pub struct Aof {
+    path: PathBuf,
+    state: Arc<Mutex<State>>,
+    /// 最近一次重写是否成功（尚未重写过时为 `true`）
+    last_rewrite_ok: AtomicBool,
+}
+
+/// 日志文件、fsync 策略及重写期间的增量缓冲
+struct State {
+    file: File,
+    /// 可通过 CONFIG SET appendfsync 在运行时修改
+    policy: FsyncPolicy,
+    /// 重写进行中时为 `Some`，缓存重写开始后追加的命令
+    rewrite_buffer: Option<Vec<u8>>,
+}
+
+impl Aof {
+    /// 以追加模式打开（不存在时创建）日志文件
+    pub fn open(path: impl AsRef<Path>, policy: FsyncPolicy) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let state = Arc::new(Mutex::new(State { file, policy, rewrite_buffer: None }));
+        spawn_fsync_thread(Arc::downgrade(&state));
+
+        Ok(Self { path, state, last_rewrite_ok: AtomicBool::new(true) })
+    }
+
+    /// 当前的 fsync 策略
+    pub fn policy(&self) -> FsyncPolicy {
+        self.state.lock().unwrap().policy
+    }
+
+    /// 修改 fsync 策略，对之后的写入生效
+    pub fn set_policy(&self, policy: FsyncPolicy) {
+        self.state.lock().unwrap().policy = policy;
+    }
+
+    /// 是否有重写正在进行
+    pub fn is_rewriting(&self) -> bool {
+        self.state.lock().unwrap().rewrite_buffer.is_some()
+    }
+
+    /// 追加一条命令
+    ///
+    /// 整条命令通过一次 `write_all` 写入，避免多条命令交错；
+    /// 重写进行中时同时写入增量缓冲，重写完成后追加到新文件末尾。
+    pub fn append(&self, args: &[String]) -> io::Result<()> {
+        let buf = encode(args);
+        let mut state = self.state.lock().unwrap();
+        state.file.write_all(&buf)?;
+
+        if let Some(rewrite_buffer) = &mut state.rewrite_buffer {
+            rewrite_buffer.extend_from_slice(&buf);
+        }
+        if state.policy == FsyncPolicy::Always {
+            state.file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// 最近一次重写是否成功
+    pub fn last_rewrite_ok(&self) -> bool {
+        self.last_rewrite_ok.load(Ordering::SeqCst)
+    }
+
+    /// 立即将已写入的数据落盘
+    pub fn sync(&self) -> io::Result<()> {
+        self.state.lock().unwrap().file.sync_data()
+    }
+
+    /// 开始重写：此后追加的命令同时写入增量缓冲
+    fn begin_rewrite(&self) -> Result<(), DbError> {
+        let mut state = self.state.lock().unwrap();
+        if state.rewrite_buffer.is_some() {
+            return Err(DbError::AofRewriteInProgress);
+        }
+        state.rewrite_buffer = Some(Vec::new());
+        Ok(())
+    }
+
+    /// 完成重写：将快照写入临时文件，追加增量缓冲后原子地替换旧文件
+    ///
+    /// 失败时丢弃增量缓冲，旧文件保持完整（重写期间的命令一直在正常追加）。
+    fn finish_rewrite(&self, snapshot: Snapshot) -> io::Result<()> {
+        let tmp = self.path.with_extension("rewrite.tmp");
+        let result = self.write_rewrite(&tmp, snapshot);
+
+        if let Err(e) = &result {
+            tracing::error!(path = %self.path.display(), error = %e, "AOF rewrite failed");
+            // 先记录结果再结束重写状态，看到重写结束时即可读到本次结果
+            self.last_rewrite_ok.store(false, Ordering::SeqCst);
+            self.state.lock().unwrap().rewrite_buffer = None;
+            let _ = fs::remove_file(&tmp);
+        }
+        result
+    }
+
+    fn write_rewrite(&self, tmp: &Path, snapshot: Snapshot) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(tmp)?);
+        for (index, records) in snapshot.into_iter().enumerate().filter(|(_, r)| !r.is_empty()) {
+            out.write_all(&encode(&["select".into(), index.to_string()]))?;
+            for args in
+                records.into_iter().flat_map(|(key, value, at)| rewrite_commands(key, value, at))
+            {
+                out.write_all(&encode(&args))?;
+            }
+        }
+        let mut out = out.into_inner().map_err(|e| e.into_error())?;
+
+        // 持有状态锁直到切换完成，期间的追加等待后写入新文件
+        let mut state = self.state.lock().unwrap();
+        let buffer = state.rewrite_buffer.take().unwrap_or_default();
+        out.write_all(&buffer)?;
+        out.sync_all()?;
+        fs::rename(tmp, &self.path)?;
+        state.file = OpenOptions::new().append(true).open(&self.path)?;
+        self.last_rewrite_ok.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// 回放日志文件中的全部命令，返回回放的命令数量
+    ///
+    /// 文件不存在视为空日志；末尾不完整的命令（例如写入过程中崩溃）会被忽略，
+    /// 其余格式错误或无法识别的命令返回 `InvalidData` 错误。
+    pub async fn replay<S: Storage>(path: impl AsRef<Path>, db: &Db<S>) -> io::Result<usize> {
+        replay_log(path.as_ref(), db).await.map(|(replayed, _)| replayed)
+    }
+}
+
+/// 回放日志，返回回放的命令数量以及最后一条完整命令结束处的偏移量
+async fn replay_log<S: Storage>(path: &Path, db: &Db<S>) -> io::Result<(usize, u64)> {
+    let buf = match std::fs::read(path) {
+        Ok(buf) => buf,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((0, 0)),
+        Err(e) => return Err(e),
+    };
+
+    let mut pos = 0;
+    let mut replayed = 0;
+    let mut selected = db.clone();
+    while let Some((args, len)) = decode(&buf[pos..])? {
+        pos += len;
+
+        let parts: Vec<_> = args.iter().map(String::as_str).collect();
+        let command = Command::from_args(&parts).map_err(|e| {
+            invalid_data(format!("invalid command in AOF: {}: {e}", args.join(" ")))
+        })?;
+        if let Command::Select(index) = command {
+            selected = db.select(index).map_err(|e| invalid_data(e.to_string()))?;
+            continue;
+        }
+        if let Frame::Error(e) = handler::execute(&selected, command).await {
+            return Err(invalid_data(format!("failed to replay AOF command: {e}")));
+        }
+        replayed += 1;
+    }
+
+    Ok((replayed, pos as u64))
+}
+
+/// 启动时调用：先回放已有的日志重建数据库，再开启追加写入，返回回放的命令数量
+///
+/// 末尾不完整的命令会被截掉，否则新命令会追加在残缺的字节之后，导致下次启动无法回放。
+pub async fn enable_aof<S: Storage>(
+    db: &Db<S>,
+    path: impl AsRef<Path>,
+    policy: FsyncPolicy,
+) -> io::Result<usize> {
+    let path = path.as_ref();
+    let (replayed, valid_len) = replay_log(path, db).await?;
+    // 回放的命令已经持久化过，不计入脏计数
+    db.clear_dirty(db.dirty());
+
+    if fs::metadata(path).is_ok_and(|meta| meta.len() > valid_len) {
+        tracing::warn!(
+            path = %path.display(),
+            valid_len,
+            "AOF ends with a truncated command, truncating"
+        );
+        OpenOptions::new().write(true).open(path)?.set_len(valid_len)?;
+    }
+    db.attach_aof(Aof::open(path, policy)?);
+    Ok(replayed)
+}
+
+impl<S: Storage> Db<S> {
+    /// 同步执行 AOF 重写：用当前数据集生成最精简的命令流替换日志文件
+    pub async fn rewrite_aof(&self) -> Result<(), DbError> {
+        let snapshot = self.begin_aof_rewrite().await?;
+        let db = self.clone();
+        let finish = move || db.aof().expect("AOF enabled").finish_rewrite(snapshot);
+
+        tokio::task::spawn_blocking(finish).await.expect("AOF rewrite task panicked")?;
+        Ok(())
+    }
+
+    /// 在后台执行 AOF 重写（BGREWRITEAOF），立即返回
+    pub async fn bgrewriteaof(&self) -> Result<(), DbError> {
+        let snapshot = self.begin_aof_rewrite().await?;
+        let db = self.clone();
+        // 失败时旧文件保持完整，可以再次发起重写；错误已由 finish_rewrite 输出并记录
+        tokio::task::spawn_blocking(move || {
+            db.aof().expect("AOF enabled").finish_rewrite(snapshot)
+        });
+        Ok(())
+    }
+
+    /// 暂停写命令，在同一时刻开始增量缓冲并复制数据集
+    async fn begin_aof_rewrite(&self) -> Result<Snapshot, DbError> {
+        let aof = self.aof().ok_or(DbError::AofDisabled)?;
+
+        let _paused = self.pause_writes().await;
+        aof.begin_rewrite()?;
+        Ok(self.snapshot().await)
+    }
+}
+
+/// 重写时每条命令最多携带的元素数量，避免生成过长的命令
+const REWRITE_ITEMS_PER_COMMAND: usize = 64;
+
+/// 生成重建一个键所需的最少命令（设置了过期时间时追加一条 PEXPIREAT）
+fn rewrite_commands(key: String, value: Value, expire_at: Option<u64>) -> Vec<Vec<String>> {
+    let mut commands = value_commands(&key, value);
+    if let Some(at) = expire_at {
+        commands.push(vec!["pexpireat".into(), key, at.to_string()]);
+    }
+    commands
+}
+
+fn value_commands(key: &str, value: Value) -> Vec<Vec<String>> {
+    let (name, items): (&str, Vec<Vec<String>>) = match value {
+        // 命令参数只能是 UTF-8，其他内容（例如位图）以 RESTORE 载荷重建
+        Value::String(value) => match String::from_utf8(value) {
+            Ok(value) => return vec![vec!["set".into(), key.into(), value]],
+            Err(e) => {
+                let payload = serialize_value(&Value::String(e.into_bytes()));
+                return vec![["restore", key, "0", &payload].map(String::from).to_vec()];
+            }
+        },
+        Value::Hash(hash) => ("hset", hash.into_iter().map(|(f, v)| vec![f, v]).collect()),
+        Value::Set(set) => ("sadd", set.into_iter().map(|member| vec![member]).collect()),
+        Value::ZSet(zset) => {
+            let items = zset.range(0, -1, false).into_iter();
+            ("zadd", items.map(|(member, score)| vec![score.to_string(), member]).collect())
+        }
+        // XADD 每条命令只能写入一条消息，以原 ID 逐条重建；
+        // 没有消费者组的空流无法用 XADD 重建，重写后不再保留
+        Value::Stream(stream) => {
+            let entries = stream.iter().map(|(id, fields)| {
+                let fields =
+                    fields.iter().flat_map(|(field, value)| [field.clone(), value.clone()]);
+                ["xadd".to_string(), key.to_string(), id.to_string()]
+                    .into_iter()
+                    .chain(fields)
+                    .collect()
+            });
+            let groups = stream.groups().flat_map(|(name, group)| group_commands(key, name, group));
+            return entries.chain(groups).collect();
+        }
+    };
+
+    items
+        .chunks(REWRITE_ITEMS_PER_COMMAND)
+        .map(|chunk| {
+            let head = [name.to_string(), key.to_string()];
+            head.into_iter().chain(chunk.iter().flatten().cloned()).collect()
+        })
+        .collect()
+}
+
+/// 重建一个消费者组的命令：创建组与消费者，再以 XCLAIM 逐条恢复待确认列表
+fn group_commands(key: &str, name: &str, group: &ConsumerGroup) -> Vec<Vec<String>> {
+    let last_delivered = group.last_delivered();
+    let create = ["xgroup", "create", key, name, &last_delivered.to_string(), "mkstream"];
+    let consumers = group.consumers().map(|(consumer, _)| {
+        ["xgroup", "createconsumer", key, name, consumer.as_str()].map(String::from)
+    });
+    let pending =
+        group.pending().map(|(id, pending)| claim_command(key, name, *id, pending, last_delivered));
+    std::iter::once(create.map(String::from).to_vec())
+        .chain(consumers.map(Vec::from))
+        .chain(pending)
+        .collect()
+}
+
+/// 以 XCLAIM 的形式表示消费者组中一条待确认消息的状态
+///
+/// 投递时间、投递次数与最后投递 ID 都写成绝对值，重放结果不依赖重放时的时间。
+pub(crate) fn claim_command(
+    key: &str,
+    group: &str,
+    id: StreamId,
+    pending: &Pending,
+    last_delivered: StreamId,
+) -> Vec<String> {
+    let head = ["xclaim", key, group, &pending.consumer, "0"].map(String::from);
+    let options = [
+        id.to_string(),
+        "time".into(),
+        pending.delivery_time.to_string(),
+        "retrycount".into(),
+        pending.delivery_count.to_string(),
+        "force".into(),
+        "justid".into(),
+        "lastid".into(),
+        last_delivered.to_string(),
+    ];
+    head.into_iter().chain(options).collect()
+}
+
+/// 在 everysec 策略下每秒执行一次 fsync，日志文件被关闭后线程自动退出
+fn spawn_fsync_thread(state: Weak<Mutex<State>>) {
+    thread::spawn(move || {
+        loop {
+            thread::sleep(Duration::from_secs(1));
+            let Some(state) = state.upgrade() else { break };
+            let state = state.lock().unwrap();
+            // 只在 everysec 策略下工作；失败时下一秒重试
+            if state.policy == FsyncPolicy::EverySec {
+                let _ = state.file.sync_data();
+            }
+        }
+    });
+}
+
+/// 将命令参数编码为 RESP 数组
+fn encode(args: &[String]) -> Vec<u8> {
+    Frame::from(args.to_vec()).encode()
+}
+
+/// 从缓冲区开头解码一条 RESP 数组，返回参数与消耗的字节数；数据不完整时返回 `None`
+fn decode(buf: &[u8]) -> io::Result<Option<(Vec<String>, usize)>> {
+    let Some((frame, len)) = Frame::decode(buf).map_err(|e| invalid_data(e.0))? else {
+        return Ok(None);
+    };
+    let args = frame.into_args().map_err(|e| invalid_data(e.0))?;
+    Ok(Some((args, len)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::process_command;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("mini-redis-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let args = vec!["set".to_string(), "key".into(), "a b\r\nc".into()];
+        let buf = encode(&args);
+
+        assert_eq!(decode(&buf).unwrap(), Some((args, buf.len())));
+        assert_eq!(decode(&buf[..buf.len() - 1]).unwrap(), None);
+        assert!(decode(b"+OK\r\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        assert_eq!("always".parse(), Ok(FsyncPolicy::Always));
+        assert_eq!("EVERYSEC".parse(), Ok(FsyncPolicy::EverySec));
+        assert_eq!("no".parse(), Ok(FsyncPolicy::No));
+        assert!("sometimes".parse::<FsyncPolicy>().is_err());
+        assert_eq!(FsyncPolicy::EverySec.to_string(), "everysec");
+    }
+
+    #[tokio::test]
+    async fn test_log_and_replay() {
+        let path = temp_path("replay.aof");
+
+        let db = Db::new();
+        assert_eq!(enable_aof(&db, &path, FsyncPolicy::Always).await.unwrap(), 0);
+        process_command(&db, "set foo bar").await;
+        process_command(&db, "get foo").await;
+        process_command(&db, "sadd s a b c").await;
+        process_command(&db, "spop s").await;
+        process_command(&db, "hincrbyfloat h f 1.5").await;
+        process_command(&db, "zadd z nx 1 a 2 b").await;
+        process_command(&db, "bzpopmin z 0").await;
+        // 自动生成的消息 ID 以实际值记录
+        process_command(&db, "xadd x * f v").await;
+        // 执行失败的写命令不会记录
+        process_command(&db, "sadd foo x").await;
+
+        let restored = Db::new();
+        assert_eq!(Aof::replay(&path, &restored).await.unwrap(), 7);
+        for query in
+            ["get foo", "smembers s", "hget h f", "zrange z 0 -1 withscores", "xrange x - +"]
+        {
+            assert_eq!(process_command(&restored, query).await, process_command(&db, query).await);
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_propagation_flags() {
+        let path = temp_path("flags.aof");
+
+        let db = Db::new();
+        enable_aof(&db, &path, FsyncPolicy::Always).await.unwrap();
+        process_command(&db, "sadd s a").await;
+        // SPOP 不是确定性的，改写为 SREM 被弹出的成员
+        process_command(&db, "spop s").await;
+        process_command(&db, "zadd z 1 a").await;
+        // BZPOPMIN 在弹出时自行传播，命令处理层不再记录一次
+        process_command(&db, "bzpopmin z 0").await;
+
+        let buf = std::fs::read(&path).unwrap();
+        let mut records = Vec::new();
+        let mut pos = 0;
+        while let Some((args, len)) = decode(&buf[pos..]).unwrap() {
+            records.push(args.join(" "));
+            pos += len;
+        }
+        assert_eq!(records, ["select 0", "sadd s a", "srem s a", "zadd z 1 a", "zpopmin z"]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_rewrite() {
+        let path = temp_path("rewrite.aof");
+
+        let db = Db::new();
+        enable_aof(&db, &path, FsyncPolicy::No).await.unwrap();
+        for i in 0..100 {
+            process_command(&db, &format!("hincrby h counter {i}")).await;
+            process_command(&db, &format!("sadd s m{i}")).await;
+        }
+        process_command(&db, "zadd z 1 a 2 b").await;
+        process_command(&db, "xadd x 1-1 a 1").await;
+        process_command(&db, "xadd x * b 2").await;
+        process_command(&db, "set foo bar").await;
+        process_command(&db, "set temp v ex 100").await;
+        process_command(&db, "setbit bits 0 1").await;
+        let before = std::fs::metadata(&path).unwrap().len();
+
+        db.rewrite_aof().await.unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() < before);
+        assert!(!db.aof().unwrap().is_rewriting());
+
+        // 重写后继续追加到新文件
+        process_command(&db, "set after rewrite").await;
+
+        let restored = Db::new();
+        // hset + 2 条 sadd（每条最多 64 个成员）+ zadd + 2 条 xadd + 2 条 set + pexpireat
+        // + 位图的 restore + 重写后的 set
+        assert_eq!(Aof::replay(&path, &restored).await.unwrap(), 11);
+        assert_eq!(restored.get("bits").await, Ok(Some(vec![0x80])));
+        assert_eq!(restored.expire_time("temp").await, db.expire_time("temp").await);
+        for query in [
+            "hget h counter",
+            "scard s",
+            "zrange z 0 -1 withscores",
+            "xrange x - +",
+            "get foo",
+            "get after",
+        ] {
+            assert_eq!(process_command(&restored, query).await, process_command(&db, query).await);
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_consumer_groups_replay_and_rewrite() {
+        let path = temp_path("groups.aof");
+
+        let db = Db::new();
+        enable_aof(&db, &path, FsyncPolicy::No).await.unwrap();
+        process_command(&db, "xadd s 1-1 a 1").await;
+        process_command(&db, "xadd s 1-2 b 2").await;
+        process_command(&db, "xgroup create s g $").await;
+        process_command(&db, "xadd s 1-3 c 3").await;
+        process_command(&db, "xgroup setid s g 0").await;
+        process_command(&db, "xreadgroup group g alice count 2 streams s >").await;
+        process_command(&db, "xreadgroup group g bob streams s >").await;
+        process_command(&db, "xclaim s g bob 0 1-1").await;
+        process_command(&db, "xack s g 1-2").await;
+        process_command(&db, "xgroup create empty g $ mkstream").await;
+
+        let queries = ["xpending s g", "xpending empty g", "xrange s - +"];
+        let restored = Db::new();
+        Aof::replay(&path, &restored).await.unwrap();
+        for query in queries {
+            assert_eq!(process_command(&restored, query).await, process_command(&db, query).await);
+        }
+
+        db.rewrite_aof().await.unwrap();
+        let restored = Db::new();
+        // 3 条 xadd + 2 个组的 xgroup create + 2 条 xgroup createconsumer + 2 条 xclaim
+        assert_eq!(Aof::replay(&path, &restored).await.unwrap(), 9);
+        for query in queries {
+            assert_eq!(process_command(&restored, query).await, process_command(&db, query).await);
+        }
+        let read = "xreadgroup group g carol streams s >";
+        assert_eq!(process_command(&restored, read).await, "(nil)");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_databases_replay_and_rewrite() {
+        let path = temp_path("databases.aof");
+
+        let db = Db::new();
+        enable_aof(&db, &path, FsyncPolicy::No).await.unwrap();
+        let other = db.select(5).unwrap();
+        process_command(&db, "set a 0").await;
+        process_command(&other, "set a 5").await;
+        process_command(&db, "set b 0").await;
+
+        let restored = Db::new();
+        assert_eq!(Aof::replay(&path, &restored).await.unwrap(), 3);
+        assert_eq!(process_command(&restored, "get a").await, "0");
+        assert_eq!(process_command(&restored.select(5).unwrap(), "get a").await, "5");
+
+        db.rewrite_aof().await.unwrap();
+        process_command(&other, "set c 5").await;
+        let restored = Db::new();
+        assert_eq!(Aof::replay(&path, &restored).await.unwrap(), 4);
+        assert_eq!(process_command(&restored, "dbsize").await, "(integer) 2");
+        assert_eq!(process_command(&restored.select(5).unwrap(), "dbsize").await, "(integer) 2");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_buffers_concurrent_writes() {
+        let path = temp_path("rewrite-buffer.aof");
+
+        let db = Db::new();
+        enable_aof(&db, &path, FsyncPolicy::Always).await.unwrap();
+        process_command(&db, "set a 1").await;
+
+        let snapshot = db.begin_aof_rewrite().await.unwrap();
+        assert_eq!(db.begin_aof_rewrite().await, Err(DbError::AofRewriteInProgress));
+        process_command(&db, "set b 2").await;
+        db.aof().unwrap().finish_rewrite(snapshot).unwrap();
+
+        let restored = Db::new();
+        assert_eq!(Aof::replay(&path, &restored).await.unwrap(), 2);
+        assert_eq!(process_command(&restored, "get b").await, "2");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_bgrewriteaof_failure_is_recorded() {
+        let dir =
+            std::env::temp_dir().join(format!("mini-redis-{}-rewrite-fail", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let db = Db::new();
+        enable_aof(&db, dir.join("fail.aof"), FsyncPolicy::No).await.unwrap();
+        assert!(db.aof().unwrap().last_rewrite_ok());
+
+        // 目录被删除后无法创建临时文件，重写失败
+        std::fs::remove_dir_all(&dir).unwrap();
+        db.bgrewriteaof().await.unwrap();
+        while db.aof().unwrap().is_rewriting() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert!(!db.aof().unwrap().last_rewrite_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_disabled() {
+        assert_eq!(Db::new().rewrite_aof().await, Err(DbError::AofDisabled));
+    }
+
+    #[tokio::test]
+    async fn test_replay_truncated_and_corrupted() {
+        let path = temp_path("truncated.aof");
+        let mut buf = encode(&["set".into(), "a".into(), "1".into()]);
+        buf.extend_from_slice(b"*3\r\n$3\r\nset\r\n$1\r\nb");
+        std::fs::write(&path, &buf).unwrap();
+
+        let db = Db::new();
+        assert_eq!(Aof::replay(&path, &db).await.unwrap(), 1);
+        assert_eq!(process_command(&db, "get a").await, "1");
+        assert_eq!(process_command(&db, "get b").await, "(nil)");
+
+        std::fs::write(&path, encode(&["nope".into()])).unwrap();
+        let err = Aof::replay(&path, &db).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(Aof::replay(&path, &db).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_restart_after_torn_write() {
+        let path = temp_path("torn.aof");
+        let mut buf = encode(&["set".into(), "a".into(), "1".into()]);
+        buf.extend_from_slice(b"*3\r\n$3\r\nset\r\n$1\r\nb");
+        std::fs::write(&path, &buf).unwrap();
+
+        // 第一次重启：截掉残缺的命令后继续追加
+        let db = Db::new();
+        assert_eq!(enable_aof(&db, &path, FsyncPolicy::Always).await.unwrap(), 1);
+        process_command(&db, "set c 3").await;
+        drop(db);
+
+        // 第二次重启：日志完整，新旧命令都能回放
+        let db = Db::new();
+        assert_eq!(enable_aof(&db, &path, FsyncPolicy::Always).await.unwrap(), 2);
+        process_command(&db, "set d 4").await;
+        drop(db);
+
+        let db = Db::new();
+        assert_eq!(Aof::replay(&path, &db).await.unwrap(), 3);
+        assert_eq!(process_command(&db, "get a").await, "1");
+        assert_eq!(process_command(&db, "get b").await, "(nil)");
+        assert_eq!(process_command(&db, "get c").await, "3");
+        assert_eq!(process_command(&db, "get d").await, "4");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_concurrent_writes_replay_in_order() {
+        const TASKS: usize = 8;
+        const ROUNDS: usize = 200;
+        let path = temp_path("concurrent.aof");
+
+        let db = Db::new();
+        enable_aof(&db, &path, FsyncPolicy::No).await.unwrap();
+        // 每一轮所有任务同时修改同一个键，最后一次修改与传播的先后决定回放的结果
+        let barrier = Arc::new(tokio::sync::Barrier::new(TASKS));
+        let tasks: Vec<_> = (0..TASKS)
+            .map(|task| {
+                let (db, barrier) = (db.clone(), barrier.clone());
+                tokio::spawn(async move {
+                    for round in 0..ROUNDS {
+                        barrier.wait().await;
+                        let command = match (task + round) % 4 {
+                            0 => format!("del k{round}"),
+                            1 => format!("expire k{round} {}", 100 + task),
+                            _ => format!("set k{round} {task}"),
+                        };
+                        process_command(&db, &command).await;
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let restored = Db::new();
+        Aof::replay(&path, &restored).await.unwrap();
+        for round in 0..ROUNDS {
+            for query in [format!("get k{round}"), format!("pexpiretime k{round}")] {
+                assert_eq!(
+                    process_command(&restored, &query).await,
+                    process_command(&db, &query).await,
+                    "{query}"
+                );
+            }
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,100 @@
+//! 单个值的序列化格式（DUMP / RESTORE / MIGRATE）
+//!
+//! 载荷布局与 Redis 的 DUMP 格式一致：
+//! `<类型字节><值的 RDB 编码><RDB 版本 u16><CRC64 校验和>`，整数均为小端序。
+//! 值的编码与 RDB 快照共用，因此同一版本的快照能读取的值都能被恢复。
+//!
+//! 命令参数与回复都是 UTF-8 字符串，所以二进制载荷以小写十六进制文本表示。
+
+use std::io;
+
+use super::{
+    crc64::crc64,
+    invalid_data,
+    rdb::{Reader, VERSION, value_type, write_value},
+};
+use crate::db::Value;
+
+/// 将值序列化为十六进制载荷
+pub(crate) fn serialize(value: &Value) -> String {
+    let mut buf = vec![value_type(value)];
+    write_value(&mut buf, value);
+    buf.extend_from_slice(&VERSION.to_le_bytes());
+    let checksum = crc64(&buf);
+    buf.extend_from_slice(&checksum.to_le_bytes());
+
+    buf.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// 校验并解析十六进制载荷
+pub(crate) fn deserialize(payload: &str) -> io::Result<Value> {
+    let buf = decode_hex(payload).ok_or_else(|| invalid_data("payload is not valid hex"))?;
+
+    let body_len = buf.len().checked_sub(10).ok_or_else(|| invalid_data("payload too short"))?;
+    let (body, trailer) = buf.split_at(body_len + 2);
+    if crc64(body).to_le_bytes() != trailer {
+        return Err(invalid_data("payload checksum mismatch"));
+    }
+    let version = u16::from_le_bytes([body[body_len], body[body_len + 1]]);
+    if version > VERSION {
+        return Err(invalid_data(format!("unsupported payload version {version}")));
+    }
+
+    let mut reader = Reader::new(&body[..body_len]);
+    let value_type = reader.u8()?;
+    let value = reader.value(value_type)?;
+    if !reader.is_empty() {
+        return Err(invalid_data("trailing bytes in payload"));
+    }
+    Ok(value)
+}
+
+pub(super) fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use super::{deserialize, serialize};
+    use crate::{
+        db::Value,
+        sorted_set::SortedSet,
+        stream::{Stream, StreamId},
+    };
+
+    #[test]
+    fn test_roundtrip() {
+        let mut zset = SortedSet::new();
+        zset.insert("a".into(), -2.5);
+        let mut stream = Stream::new();
+        stream.insert(StreamId::new(1, 2), vec![("f".into(), "v".into())]);
+        let values = [
+            Value::String("hello".into()),
+            Value::Hash(HashMap::from([("f".into(), "v".into())])),
+            Value::Set(HashSet::from(["x".into(), "y".into()])),
+            Value::ZSet(zset),
+            Value::Stream(stream),
+        ];
+
+        for value in values {
+            assert_eq!(deserialize(&serialize(&value)).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_rejects_corruption() {
+        let payload = serialize(&Value::String("hello".into()));
+
+        // 修改值中的一个字节
+        let corrupted = format!("{}{}", &payload[..4], "ff") + &payload[6..];
+        assert!(deserialize(&corrupted).is_err());
+        assert!(deserialize(&payload[..payload.len() - 2]).is_err());
+        assert!(deserialize("zz").is_err());
+        assert!(deserialize("").is_err());
+    }
+}
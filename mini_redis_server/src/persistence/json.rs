@@ -0,0 +1,519 @@
+//! 键值的 JSON 行格式（EXPORT / IMPORT）
+//!
+//! 每个键一行 JSON 对象，可以用 jq 等工具直接查看，也便于与其他 Redis 实现交换数据：
+//!
+//! ```text
+//! {"type":"string","key":"greeting","ttl":-1,"value":"hello"}
+//! {"type":"hash","key":"user:1","ttl":59000,"value":{"name":"alice"}}
+//! {"type":"set","key":"tags","ttl":-1,"value":["a","b"]}
+//! {"type":"zset","key":"rank","ttl":-1,"value":[["alice",1.5],["bob","inf"]]}
+//! {"type":"stream","key":"events","ttl":-1,"value":[{"id":"1-0","fields":["f","v"]}]}
+//! ```
+//!
+//! - `ttl` 为剩余的毫秒数，-1 表示没有过期时间（与 PTTL 相同）
+//! - 不是 UTF-8 的字符串值（例如位图）以十六进制表示，并带有 `"encoding":"hex"`
+//! - 哈希与集合按字段或成员排序，有序集合按分值排列，±inf 写作字符串 `"inf"` / `"-inf"`
+//! - 流只包含消息，不包含消费者组
+//!
+//! 解析时字段顺序任意，未知的字段被忽略。
+
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+};
+
+use super::{dump::decode_hex, invalid_data};
+use crate::{
+    db::Value,
+    sorted_set::SortedSet,
+    stream::{Stream, StreamId},
+};
+
+/// 解析后的 JSON 值，对象保留字段的原始顺序
+#[derive(Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+/// 把一个键编码为一行 JSON（不含换行符），`ttl` 为剩余的毫秒数
+pub(crate) fn encode(key: &str, value: &Value, ttl: Option<u64>) -> String {
+    let mut out = String::from("{\"type\":");
+    push_string(&mut out, value.type_name());
+    out.push_str(",\"key\":");
+    push_string(&mut out, key);
+    out.push_str(&format!(",\"ttl\":{}", ttl.map_or(-1, |ttl| ttl as i64)));
+    match value {
+        Value::String(bytes) => match std::str::from_utf8(bytes) {
+            Ok(s) => {
+                out.push_str(",\"value\":");
+                push_string(&mut out, s);
+            }
+            Err(_) => {
+                out.push_str(",\"encoding\":\"hex\",\"value\":\"");
+                out.extend(bytes.iter().map(|byte| format!("{byte:02x}")));
+                out.push('"');
+            }
+        },
+        Value::Hash(hash) => {
+            let mut fields: Vec<_> = hash.iter().collect();
+            fields.sort();
+            out.push_str(",\"value\":{");
+            for (i, (field, value)) in fields.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                push_string(&mut out, field);
+                out.push(':');
+                push_string(&mut out, value);
+            }
+            out.push('}');
+        }
+        Value::Set(set) => {
+            let mut members: Vec<_> = set.iter().collect();
+            members.sort();
+            out.push_str(",\"value\":[");
+            push_strings(&mut out, members);
+            out.push(']');
+        }
+        Value::ZSet(zset) => {
+            out.push_str(",\"value\":[");
+            for (i, (member, score)) in zset.range(0, -1, false).into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push('[');
+                push_string(&mut out, &member);
+                match score {
+                    f64::INFINITY => out.push_str(",\"inf\"]"),
+                    f64::NEG_INFINITY => out.push_str(",\"-inf\"]"),
+                    score => out.push_str(&format!(",{score}]")),
+                }
+            }
+            out.push(']');
+        }
+        Value::Stream(stream) => {
+            out.push_str(",\"value\":[");
+            for (i, (id, fields)) in stream.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&format!("{{\"id\":\"{id}\",\"fields\":["));
+                push_strings(&mut out, fields.iter().flat_map(|(field, value)| [field, value]));
+                out.push_str("]}");
+            }
+            out.push(']');
+        }
+    }
+    out.push('}');
+    out
+}
+
+/// 解析一行 JSON，返回键、值与剩余的毫秒数
+pub(crate) fn decode(line: &str) -> io::Result<(String, Value, Option<u64>)> {
+    let mut parser = Parser { input: line, pos: 0 };
+    let json = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < line.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    let Json::Object(fields) = json else {
+        return Err(invalid_data("record is not a JSON object"));
+    };
+    let field = |name: &str| fields.iter().find(|(k, _)| k == name).map(|(_, v)| v);
+
+    let key = match field("key") {
+        Some(Json::String(key)) => key.clone(),
+        _ => return Err(invalid_data("'key' must be a string")),
+    };
+    let ttl = match field("ttl") {
+        None | Some(Json::Null) => None,
+        Some(Json::Number(ttl)) if *ttl < 0.0 => None,
+        Some(Json::Number(ttl)) if ttl.fract() == 0.0 => Some(*ttl as u64),
+        _ => return Err(invalid_data("'ttl' must be an integer")),
+    };
+    let hex = match field("encoding") {
+        None | Some(Json::Null) => false,
+        Some(Json::String(encoding)) if encoding == "hex" => true,
+        _ => return Err(invalid_data("'encoding' must be \"hex\"")),
+    };
+    let Some(Json::String(value_type)) = field("type") else {
+        return Err(invalid_data("'type' must be a string"));
+    };
+    let value = field("value").ok_or_else(|| invalid_data("missing 'value'"))?;
+
+    let value = match (value_type.as_str(), value) {
+        ("string", Json::String(s)) if hex => {
+            Value::String(decode_hex(s).ok_or_else(|| invalid_data("'value' is not valid hex"))?)
+        }
+        ("string", Json::String(s)) => Value::String(s.clone().into_bytes()),
+        ("hash", Json::Object(fields)) if !fields.is_empty() => Value::Hash(
+            fields
+                .iter()
+                .map(|(field, value)| Ok((field.clone(), string(value)?)))
+                .collect::<io::Result<HashMap<_, _>>>()?,
+        ),
+        ("set", Json::Array(members)) if !members.is_empty() => {
+            Value::Set(members.iter().map(string).collect::<io::Result<HashSet<_>>>()?)
+        }
+        ("zset", Json::Array(items)) if !items.is_empty() => {
+            let mut zset = SortedSet::new();
+            for item in items {
+                let Json::Array(pair) = item else {
+                    return Err(invalid_data("zset items must be [member, score] pairs"));
+                };
+                let [member, score] = pair.as_slice() else {
+                    return Err(invalid_data("zset items must be [member, score] pairs"));
+                };
+                zset.insert(string(member)?, score_value(score)?);
+            }
+            Value::ZSet(zset)
+        }
+        ("stream", Json::Array(entries)) => {
+            let mut stream = Stream::new();
+            for entry in entries {
+                let Json::Object(entry) = entry else {
+                    return Err(invalid_data("stream entries must be objects"));
+                };
+                let field = |name: &str| entry.iter().find(|(k, _)| k == name).map(|(_, v)| v);
+                let id = field("id")
+                    .and_then(|id| match id {
+                        Json::String(id) => StreamId::parse(id, 0),
+                        _ => None,
+                    })
+                    .ok_or_else(|| invalid_data("invalid stream entry id"))?;
+                let Some(Json::Array(items)) = field("fields") else {
+                    return Err(invalid_data("stream entry 'fields' must be an array"));
+                };
+                if items.len() % 2 != 0 {
+                    return Err(invalid_data("stream entry 'fields' must have an even length"));
+                }
+                let fields = items
+                    .chunks(2)
+                    .map(|pair| Ok((string(&pair[0])?, string(&pair[1])?)))
+                    .collect::<io::Result<Vec<_>>>()?;
+                stream.insert(id, fields);
+            }
+            Value::Stream(stream)
+        }
+        ("string" | "hash" | "set" | "zset" | "stream", _) => {
+            return Err(invalid_data(format!("invalid or empty 'value' for type {value_type}")));
+        }
+        _ => return Err(invalid_data(format!("unknown type '{value_type}'"))),
+    };
+    Ok((key, value, ttl))
+}
+
+fn string(json: &Json) -> io::Result<String> {
+    match json {
+        Json::String(s) => Ok(s.clone()),
+        _ => Err(invalid_data("expected a string")),
+    }
+}
+
+/// 有序集合的分值：数字，或 `"inf"` / `"-inf"` 等可以解析为浮点数的字符串
+fn score_value(json: &Json) -> io::Result<f64> {
+    let score = match json {
+        Json::Number(score) => Some(*score),
+        Json::String(s) => s.parse().ok(),
+        _ => None,
+    };
+    score.filter(|score| !score.is_nan()).ok_or_else(|| invalid_data("invalid zset score"))
+}
+
+/// 输出带引号的 JSON 字符串，转义引号、反斜杠与控制字符
+fn push_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// 输出以逗号分隔的字符串列表
+fn push_strings<'a>(out: &mut String, items: impl IntoIterator<Item = &'a String>) {
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        push_string(out, item);
+    }
+}
+
+/// 递归下降的 JSON 解析器
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, msg: &str) -> io::Error {
+        invalid_data(format!("{msg} at offset {}", self.pos))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    /// 跳过空白后读取给定的字符
+    fn expect(&mut self, byte: u8) -> io::Result<()> {
+        self.skip_whitespace();
+        if self.peek() != Some(byte) {
+            return Err(self.error(&format!("expected '{}'", byte as char)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn value(&mut self) -> io::Result<Json> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(self.error("unexpected character")),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> io::Result<Json> {
+        if !self.input[self.pos..].starts_with(word) {
+            return Err(self.error("unexpected character"));
+        }
+        self.pos += word.len();
+        Ok(value)
+    }
+
+    fn number(&mut self) -> io::Result<Json> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        self.input[start..self.pos]
+            .parse()
+            .map(Json::Number)
+            .map_err(|_| self.error("invalid number"))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        self.expect(b'"')?;
+        let mut out = String::new();
+        loop {
+            let rest = &self.input[self.pos..];
+            let Some(end) = rest.find(['"', '\\']) else {
+                return Err(self.error("unterminated string"));
+            };
+            out.push_str(&rest[..end]);
+            self.pos += end + 1;
+            if rest.as_bytes()[end] == b'"' {
+                return Ok(out);
+            }
+            let c = match self.peek() {
+                Some(b'"') => '"',
+                Some(b'\\') => '\\',
+                Some(b'/') => '/',
+                Some(b'b') => '\u{8}',
+                Some(b'f') => '\u{c}',
+                Some(b'n') => '\n',
+                Some(b'r') => '\r',
+                Some(b't') => '\t',
+                Some(b'u') => {
+                    self.pos += 1;
+                    out.push(self.unicode_escape()?);
+                    continue;
+                }
+                _ => return Err(self.error("invalid escape")),
+            };
+            self.pos += 1;
+            out.push(c);
+        }
+    }
+
+    /// `\u` 之后的 4 位十六进制数，代理对需要紧跟第二个 `\u` 转义
+    fn unicode_escape(&mut self) -> io::Result<char> {
+        let high = self.hex4()?;
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if !self.input[self.pos..].starts_with("\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+            self.pos += 2;
+            let low = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("unpaired surrogate"));
+            }
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> io::Result<u32> {
+        let code = self
+            .input
+            .get(self.pos..self.pos + 4)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn array(&mut self) -> io::Result<Json> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> io::Result<Json> {
+        self.expect(b'{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let name = self.string()?;
+            self.expect(b':')?;
+            fields.push((name, self.value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let mut zset = SortedSet::new();
+        zset.insert("b".into(), f64::INFINITY);
+        zset.insert("a".into(), 1.5);
+        let mut stream = Stream::new();
+        stream.insert(StreamId::new(1, 0), vec![("f".into(), "v".into())]);
+
+        assert_eq!(
+            encode("k", &Value::String("say \"hi\"\n".into()), Some(500)),
+            r#"{"type":"string","key":"k","ttl":500,"value":"say \"hi\"\n"}"#
+        );
+        assert_eq!(
+            encode("bits", &Value::String(vec![0xff, 0x00]), None),
+            r#"{"type":"string","key":"bits","ttl":-1,"encoding":"hex","value":"ff00"}"#
+        );
+        assert_eq!(
+            encode("h", &Value::Hash(HashMap::from([("f".into(), "v".into())])), None),
+            r#"{"type":"hash","key":"h","ttl":-1,"value":{"f":"v"}}"#
+        );
+        assert_eq!(
+            encode("s", &Value::Set(HashSet::from(["y".into(), "x".into()])), None),
+            r#"{"type":"set","key":"s","ttl":-1,"value":["x","y"]}"#
+        );
+        assert_eq!(
+            encode("z", &Value::ZSet(zset), None),
+            r#"{"type":"zset","key":"z","ttl":-1,"value":[["a",1.5],["b","inf"]]}"#
+        );
+        assert_eq!(
+            encode("x", &Value::Stream(stream), None),
+            r#"{"type":"stream","key":"x","ttl":-1,"value":[{"id":"1-0","fields":["f","v"]}]}"#
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut zset = SortedSet::new();
+        zset.insert("a".into(), -2.5);
+        zset.insert("b".into(), f64::NEG_INFINITY);
+        let mut stream = Stream::new();
+        stream.insert(StreamId::new(1, 2), vec![("f".into(), "v".into())]);
+        let values = [
+            Value::String("héllo \u{1}\u{1f600}".into()),
+            Value::String(vec![0x80, 0x81]),
+            Value::Hash(HashMap::from([("f".into(), "v".into()), ("g".into(), "".into())])),
+            Value::Set(HashSet::from(["x".into(), "y\\".into()])),
+            Value::ZSet(zset),
+            Value::Stream(stream),
+        ];
+
+        for value in values {
+            let line = encode("key", &value, Some(42));
+            assert_eq!(decode(&line).unwrap(), ("key".to_string(), value, Some(42)));
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        // 字段顺序任意，允许空白、转义与未知字段
+        let line = r#" { "value" : "é😀\/" , "key":"k", "type":"string",
+                         "ttl": null, "extra": [1, true, {}] } "#;
+        assert_eq!(decode(line).unwrap(), ("k".into(), Value::String("é😀/".into()), None));
+        let zset = decode(r#"{"type":"zset","key":"z","ttl":1e3,"value":[["m","-inf"]]}"#);
+        assert_eq!(zset.unwrap().2, Some(1000));
+
+        for bad in [
+            "",
+            "[]",
+            r#"{"type":"string","key":"k"}"#,
+            r#"{"type":"string","key":1,"value":"v"}"#,
+            r#"{"type":"list","key":"k","value":[]}"#,
+            r#"{"type":"set","key":"k","value":[]}"#,
+            r#"{"type":"set","key":"k","value":[1]}"#,
+            r#"{"type":"string","key":"k","ttl":1.5,"value":"v"}"#,
+            r#"{"type":"string","key":"k","encoding":"hex","value":"zz"}"#,
+            r#"{"type":"zset","key":"k","value":[["m","nan"]]}"#,
+            r#"{"type":"stream","key":"k","value":[{"id":"x","fields":[]}]}"#,
+            r#"{"type":"string","key":"k","value":"v"} x"#,
+            r#"{"type":"string","key":"k","value":"\ud800"}"#,
+            r#"{"type":"string","key":"k","value":"v}"#,
+        ] {
+            assert!(decode(bad).is_err(), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,715 @@
+//! RDB 快照
+//!
+//! 文件格式（整数均为小端序）：
+//!
+//! ```text
+//! "MINIREDIS" | version: u16 | database* | 0xFF | crc64: u64
+//! database = 0xFE index: u32 record*
+//! record = [0xFC expire_at_ms: u64] type: u8 key value
+//! ```
+//!
+//! - 只写出非空的数据库；没有 0xFE 前缀的记录属于 0 号数据库
+//! - 字符串以 `u32` 长度前缀编码
+//! - 哈希 / 集合 / 有序集合先写元素数量（`u32`），再依次写出元素，分值以 `f64` 编码
+//! - 末尾的 CRC64 覆盖之前的全部字节，加载时校验
+//!
+//! 写入先落到临时文件，fsync 后通过 `rename` 原子地替换旧快照。
+//!
+//! 除了 SAVE / BGSAVE 手动保存，还可以配置自动快照条件（[`SavePoint`]）：
+//! 距上次保存超过指定秒数且脏计数（见 [`Db::dirty`]）达到指定次数时自动发起 BGSAVE，
+//! 由服务器的周期任务（见 [`server`](crate::server)）调用 [`Db::check_save_points`] 检查。
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+};
+
+use super::{crc64::crc64, invalid_data};
+use crate::{
+    db::{Db, DbError, Snapshot, Storage, Value, unix_time_ms},
+    sorted_set::SortedSet,
+    stream::{ConsumerGroup, Pending, Stream, StreamId},
+};
+
+const MAGIC: &[u8] = b"MINIREDIS";
+pub(super) const VERSION: u16 = 1;
+
+const OPCODE_EXPIRE_MS: u8 = 0xfc;
+const OPCODE_SELECTDB: u8 = 0xfe;
+const OPCODE_EOF: u8 = 0xff;
+
+const TYPE_STRING: u8 = 0;
+const TYPE_HASH: u8 = 1;
+const TYPE_SET: u8 = 2;
+const TYPE_ZSET: u8 = 3;
+const TYPE_STREAM: u8 = 4;
+
+/// 自动快照的触发条件：距上次保存至少 `seconds` 秒且至少发生了 `changes` 次写入
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SavePoint {
+    pub seconds: u64,
+    pub changes: u64,
+}
+
+impl SavePoint {
+    /// 解析 `save` 配置，例如 `"3600 1 300 100"`；空字符串表示关闭自动快照
+    pub fn parse_list(s: &str) -> Option<Vec<SavePoint>> {
+        let numbers = s.split_whitespace().map(|n| n.parse().ok()).collect::<Option<Vec<u64>>>()?;
+        if !numbers.len().is_multiple_of(2) {
+            return None;
+        }
+        Some(
+            numbers
+                .chunks(2)
+                .map(|pair| SavePoint { seconds: pair[0], changes: pair[1] })
+                .collect(),
+        )
+    }
+}
+
+/// 与 Redis 相同的默认自动快照条件
+pub(crate) const DEFAULT_SAVE_POINTS: [SavePoint; 3] = [
+    SavePoint { seconds: 3600, changes: 1 },
+    SavePoint { seconds: 300, changes: 100 },
+    SavePoint { seconds: 60, changes: 10000 },
+];
+
+/// 上次后台快照失败后，至少间隔这么多秒才再次自动尝试
+const BGSAVE_RETRY_DELAY: u64 = 5;
+
+/// 快照状态：文件路径、自动快照条件、最近一次保存的时间与结果
+pub(crate) struct RdbState {
+    path: Mutex<PathBuf>,
+    save_points: Mutex<Vec<SavePoint>>,
+    /// 最近一次成功保存的时间（Unix 秒）
+    last_save: AtomicU64,
+    /// 最近一次尝试后台保存的时间（Unix 秒）
+    last_bgsave_try: AtomicU64,
+    last_bgsave_ok: AtomicBool,
+    saving: AtomicBool,
+}
+
+impl Default for RdbState {
+    fn default() -> Self {
+        Self {
+            path: Mutex::new(PathBuf::from("dump.rdb")),
+            save_points: Mutex::new(DEFAULT_SAVE_POINTS.to_vec()),
+            last_save: AtomicU64::new(unix_time_ms() / 1000),
+            last_bgsave_try: AtomicU64::new(0),
+            last_bgsave_ok: AtomicBool::new(true),
+            saving: AtomicBool::new(false),
+        }
+    }
+}
+
+impl RdbState {
+    fn path(&self) -> PathBuf {
+        self.path.lock().unwrap().clone()
+    }
+}
+
+/// 启动时调用：设置快照文件路径，文件存在时加载其中的数据，返回加载的键数量
+pub async fn enable_rdb<S: Storage>(db: &Db<S>, path: impl AsRef<Path>) -> io::Result<usize> {
+    let path = path.as_ref().to_path_buf();
+    set_rdb_path(db, path.clone());
+
+    let buf = match fs::read(&path) {
+        Ok(buf) => buf,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+
+    load(db, &buf).await
+}
+
+/// 设置 SAVE / BGSAVE 写入的快照文件路径，不加载文件内容
+pub(crate) fn set_rdb_path<S: Storage>(db: &Db<S>, path: PathBuf) {
+    *db.rdb().path.lock().unwrap() = path;
+}
+
+/// 用快照内容替换所有数据库，返回加载的键数量
+///
+/// 加载时丢弃已经过期的键；快照中的数据库编号超出配置的数据库数量时返回错误。
+/// 复制时副本也用它载入主节点发送的快照。
+pub(crate) async fn load<S: Storage>(db: &Db<S>, buf: &[u8]) -> io::Result<usize> {
+    let now = unix_time_ms();
+    let mut snapshot = decode(buf)?;
+    if snapshot.len() > db.database_count() {
+        return Err(invalid_data(format!(
+            "RDB file contains database {} but only {} databases are configured",
+            snapshot.len() - 1,
+            db.database_count()
+        )));
+    }
+    for records in &mut snapshot {
+        records.retain(|(.., at)| at.is_none_or(|at| at > now));
+    }
+    let loaded = snapshot.iter().map(Vec::len).sum();
+    db.restore(snapshot).await;
+    Ok(loaded)
+}
+
+/// 是否满足任一自动快照条件
+fn save_point_reached(points: &[SavePoint], dirty: u64, elapsed: u64) -> bool {
+    points.iter().any(|point| dirty >= point.changes && elapsed >= point.seconds)
+}
+
+impl<S: Storage> Db<S> {
+    /// 同步保存快照（SAVE），完成后返回
+    ///
+    /// 与 BGSAVE 共用 `saving` 标志，同一时刻只有一个保存在写临时文件。
+    pub async fn save(&self) -> Result<(), DbError> {
+        if self.rdb().saving.swap(true, Ordering::SeqCst) {
+            return Err(DbError::BgSaveInProgress);
+        }
+
+        let (records, dirty) = self.snapshot_with_dirty().await;
+        let path = self.rdb().path();
+        let result = tokio::task::spawn_blocking(move || write_snapshot(&path, &records))
+            .await
+            .expect("RDB save task panicked");
+        if result.is_ok() {
+            self.saved(dirty);
+        }
+        self.rdb().saving.store(false, Ordering::SeqCst);
+
+        result.map_err(|e| DbError::Save(e.to_string()))
+    }
+
+    /// 在后台保存快照（BGSAVE），立即返回
+    pub async fn bgsave(&self) -> Result<(), DbError> {
+        if self.rdb().saving.swap(true, Ordering::SeqCst) {
+            return Err(DbError::BgSaveInProgress);
+        }
+
+        let (records, dirty) = self.snapshot_with_dirty().await;
+        let path = self.rdb().path();
+        let db = self.clone();
+        self.rdb().last_bgsave_try.store(unix_time_ms() / 1000, Ordering::SeqCst);
+        tokio::task::spawn_blocking(move || {
+            // 失败时保留旧快照，LASTSAVE 与脏计数不变
+            let ok = write_snapshot(&path, &records).is_ok();
+            if ok {
+                db.saved(dirty);
+            }
+            db.rdb().last_bgsave_ok.store(ok, Ordering::SeqCst);
+            db.rdb().saving.store(false, Ordering::SeqCst);
+        });
+        Ok(())
+    }
+
+    /// 最近一次成功保存快照的时间（Unix 秒），从未保存时为启动时间
+    pub fn lastsave(&self) -> u64 {
+        self.rdb().last_save.load(Ordering::SeqCst)
+    }
+
+    /// 是否有快照保存（SAVE 或 BGSAVE）正在进行
+    pub fn is_saving(&self) -> bool {
+        self.rdb().saving.load(Ordering::SeqCst)
+    }
+
+    /// 当前的自动快照条件
+    pub fn save_points(&self) -> Vec<SavePoint> {
+        self.rdb().save_points.lock().unwrap().clone()
+    }
+
+    /// 设置自动快照条件，空列表表示关闭自动快照
+    pub fn set_save_points(&self, points: Vec<SavePoint>) {
+        *self.rdb().save_points.lock().unwrap() = points;
+    }
+
+    /// 满足自动快照条件时发起后台快照，返回是否发起
+    ///
+    /// 上次后台快照失败时，至少间隔 `BGSAVE_RETRY_DELAY` 秒才再次尝试。
+    pub async fn check_save_points(&self) -> bool {
+        let rdb = self.rdb();
+        if self.is_saving() {
+            return false;
+        }
+
+        let now = unix_time_ms() / 1000;
+        let retry_allowed = rdb.last_bgsave_ok.load(Ordering::SeqCst)
+            || now.saturating_sub(rdb.last_bgsave_try.load(Ordering::SeqCst)) >= BGSAVE_RETRY_DELAY;
+        let elapsed = now.saturating_sub(self.lastsave());
+
+        retry_allowed
+            && save_point_reached(&self.save_points(), self.dirty(), elapsed)
+            && self.bgsave().await.is_ok()
+    }
+
+    /// 暂停写命令，复制数据集并记录此刻的脏计数
+    async fn snapshot_with_dirty(&self) -> (Snapshot, u64) {
+        let _paused = self.pause_writes().await;
+        (self.snapshot().await, self.dirty())
+    }
+
+    /// 保存成功：更新 LASTSAVE，并扣除快照中已包含的写入次数
+    fn saved(&self, dirty: u64) {
+        self.rdb().last_save.store(unix_time_ms() / 1000, Ordering::SeqCst);
+        self.clear_dirty(dirty);
+    }
+}
+
+/// 写入临时文件后原子地替换旧快照
+fn write_snapshot(path: &Path, snapshot: &Snapshot) -> io::Result<()> {
+    let tmp = path.with_extension("rdb.tmp");
+    let result = fs::write(&tmp, encode(snapshot))
+        .and_then(|()| fs::File::open(&tmp)?.sync_all())
+        .and_then(|()| fs::rename(&tmp, path));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// 将所有数据库的记录编码为快照文件内容
+pub(crate) fn encode(snapshot: &Snapshot) -> Vec<u8> {
+    let mut buf = MAGIC.to_vec();
+    buf.extend_from_slice(&VERSION.to_le_bytes());
+
+    for (index, records) in snapshot.iter().enumerate().filter(|(_, r)| !r.is_empty()) {
+        buf.push(OPCODE_SELECTDB);
+        buf.extend_from_slice(&(index as u32).to_le_bytes());
+        for (key, value, expire_at) in records {
+            if let Some(at) = expire_at {
+                buf.push(OPCODE_EXPIRE_MS);
+                buf.extend_from_slice(&at.to_le_bytes());
+            }
+            buf.push(value_type(value));
+            write_string(&mut buf, key);
+            write_value(&mut buf, value);
+        }
+    }
+
+    buf.push(OPCODE_EOF);
+    let checksum = crc64(&buf);
+    buf.extend_from_slice(&checksum.to_le_bytes());
+    buf
+}
+
+/// 校验并解码快照文件内容，结果的长度为出现过的最大数据库编号加一
+fn decode(buf: &[u8]) -> io::Result<Snapshot> {
+    let body_len = buf.len().checked_sub(8).ok_or_else(|| invalid_data("RDB file too short"))?;
+    let (body, checksum) = buf.split_at(body_len);
+    if crc64(body).to_le_bytes() != checksum {
+        return Err(invalid_data("RDB checksum mismatch"));
+    }
+
+    let mut reader = Reader::new(body);
+    if reader.bytes(MAGIC.len())? != MAGIC {
+        return Err(invalid_data("not an RDB file"));
+    }
+    let version = u16::from_le_bytes(reader.array()?);
+    if version > VERSION {
+        return Err(invalid_data(format!("unsupported RDB version {version}")));
+    }
+
+    let mut snapshot = vec![Vec::new()];
+    let mut index = 0;
+    loop {
+        let mut opcode = reader.u8()?;
+        if opcode == OPCODE_SELECTDB {
+            index = u32::from_le_bytes(reader.array()?) as usize;
+            if snapshot.len() <= index {
+                snapshot.resize_with(index + 1, Vec::new);
+            }
+            continue;
+        }
+        let mut expire_at = None;
+        if opcode == OPCODE_EXPIRE_MS {
+            expire_at = Some(u64::from_le_bytes(reader.array()?));
+            opcode = reader.u8()?;
+        }
+        if opcode == OPCODE_EOF {
+            break;
+        }
+
+        let key = reader.string()?;
+        let value = reader.value(opcode)?;
+        snapshot[index].push((key, value, expire_at));
+    }
+
+    Ok(snapshot)
+}
+
+pub(super) fn value_type(value: &Value) -> u8 {
+    match value {
+        Value::String(_) => TYPE_STRING,
+        Value::Hash(_) => TYPE_HASH,
+        Value::Set(_) => TYPE_SET,
+        Value::ZSet(_) => TYPE_ZSET,
+        Value::Stream(_) => TYPE_STREAM,
+    }
+}
+
+fn write_string(buf: &mut Vec<u8>, s: &str) {
+    write_bytes(buf, s.as_bytes());
+}
+
+fn write_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+pub(super) fn write_value(buf: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::String(s) => write_bytes(buf, s),
+        Value::Hash(hash) => {
+            buf.extend_from_slice(&(hash.len() as u32).to_le_bytes());
+            for (field, value) in hash {
+                write_string(buf, field);
+                write_string(buf, value);
+            }
+        }
+        Value::Set(set) => {
+            buf.extend_from_slice(&(set.len() as u32).to_le_bytes());
+            for member in set {
+                write_string(buf, member);
+            }
+        }
+        Value::ZSet(zset) => {
+            buf.extend_from_slice(&(zset.len() as u32).to_le_bytes());
+            for (member, score) in zset.range(0, -1, false) {
+                write_string(buf, &member);
+                buf.extend_from_slice(&score.to_le_bytes());
+            }
+        }
+        Value::Stream(stream) => {
+            buf.extend_from_slice(&(stream.len() as u32).to_le_bytes());
+            for (id, fields) in stream.iter() {
+                write_stream_id(buf, *id);
+                buf.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+                for (field, value) in fields {
+                    write_string(buf, field);
+                    write_string(buf, value);
+                }
+            }
+            buf.extend_from_slice(&(stream.groups().count() as u32).to_le_bytes());
+            for (name, group) in stream.groups() {
+                write_group(buf, name, group);
+            }
+        }
+    }
+}
+
+fn write_stream_id(buf: &mut Vec<u8>, id: StreamId) {
+    buf.extend_from_slice(&id.ms.to_le_bytes());
+    buf.extend_from_slice(&id.seq.to_le_bytes());
+}
+
+/// 消费者组：组名、最后投递 ID、待确认列表，最后是全部消费者的名称
+fn write_group(buf: &mut Vec<u8>, name: &str, group: &ConsumerGroup) {
+    write_string(buf, name);
+    write_stream_id(buf, group.last_delivered());
+    buf.extend_from_slice(&(group.pending().count() as u32).to_le_bytes());
+    for (id, pending) in group.pending() {
+        write_stream_id(buf, *id);
+        write_string(buf, &pending.consumer);
+        buf.extend_from_slice(&pending.delivery_time.to_le_bytes());
+        buf.extend_from_slice(&pending.delivery_count.to_le_bytes());
+    }
+    buf.extend_from_slice(&(group.consumers().count() as u32).to_le_bytes());
+    for (consumer, _) in group.consumers() {
+        write_string(buf, consumer);
+    }
+}
+
+/// 快照内容的顺序读取器
+pub(super) struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(super) fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    /// 是否已经读完全部内容
+    pub(super) fn is_empty(&self) -> bool {
+        self.pos == self.buf.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .buf
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| invalid_data("unexpected end of RDB file"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.bytes(N)?.try_into().expect("length checked"))
+    }
+
+    pub(super) fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn len(&mut self) -> io::Result<usize> {
+        Ok(u32::from_le_bytes(self.array()?) as usize)
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn stream_id(&mut self) -> io::Result<StreamId> {
+        Ok(StreamId::new(self.u64()?, self.u64()?))
+    }
+
+    fn group(&mut self) -> io::Result<(String, ConsumerGroup)> {
+        let name = self.string()?;
+        let mut group = ConsumerGroup::new(self.stream_id()?);
+        for _ in 0..self.len()? {
+            let id = self.stream_id()?;
+            let consumer = self.string()?;
+            let (delivery_time, delivery_count) = (self.u64()?, self.u64()?);
+            group.assign(id, Pending { consumer, delivery_time, delivery_count });
+        }
+        for _ in 0..self.len()? {
+            group.create_consumer(&self.string()?);
+        }
+        Ok((name, group))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        String::from_utf8(self.byte_string()?).map_err(|e| invalid_data(e.to_string()))
+    }
+
+    fn byte_string(&mut self) -> io::Result<Vec<u8>> {
+        let len = self.len()?;
+        Ok(self.bytes(len)?.to_vec())
+    }
+
+    pub(super) fn value(&mut self, value_type: u8) -> io::Result<Value> {
+        let value = match value_type {
+            TYPE_STRING => Value::String(self.byte_string()?),
+            TYPE_HASH => {
+                let len = self.len()?;
+                let hash = (0..len)
+                    .map(|_| Ok((self.string()?, self.string()?)))
+                    .collect::<io::Result<HashMap<_, _>>>()?;
+                Value::Hash(hash)
+            }
+            TYPE_SET => {
+                let len = self.len()?;
+                Value::Set((0..len).map(|_| self.string()).collect::<io::Result<HashSet<_>>>()?)
+            }
+            TYPE_ZSET => {
+                let mut zset = SortedSet::new();
+                for _ in 0..self.len()? {
+                    let member = self.string()?;
+                    zset.insert(member, f64::from_le_bytes(self.array()?));
+                }
+                Value::ZSet(zset)
+            }
+            TYPE_STREAM => {
+                let mut stream = Stream::new();
+                for _ in 0..self.len()? {
+                    let id = self.stream_id()?;
+                    let len = self.len()?;
+                    let fields = (0..len)
+                        .map(|_| Ok((self.string()?, self.string()?)))
+                        .collect::<io::Result<Vec<_>>>()?;
+                    stream.insert(id, fields);
+                }
+                for _ in 0..self.len()? {
+                    let (name, group) = self.group()?;
+                    stream.create_group(&name, group);
+                }
+                Value::Stream(stream)
+            }
+            other => return Err(invalid_data(format!("unknown RDB value type {other}"))),
+        };
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::process_command;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("mini-redis-{}-{name}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let mut zset = SortedSet::new();
+        zset.insert("a".into(), 1.5);
+        let mut stream = Stream::new();
+        stream.insert(StreamId::new(5, 1), vec![("f".into(), "v".into())]);
+        let mut group = ConsumerGroup::new(StreamId::new(5, 1));
+        group.create_consumer("idle");
+        let pending = Pending { consumer: "c".into(), delivery_time: 9, delivery_count: 2 };
+        group.assign(StreamId::new(5, 1), pending);
+        stream.create_group("g", group);
+        let snapshot: Snapshot = vec![
+            vec![
+                ("h".into(), Value::Hash(HashMap::from([("f".into(), "v".into())])), None),
+                ("s".into(), Value::Set(HashSet::from(["m".into()])), Some(42)),
+            ],
+            vec![],
+            vec![
+                ("str".into(), Value::String("value".into()), None),
+                ("z".into(), Value::ZSet(zset), Some(7)),
+                ("x".into(), Value::Stream(stream), None),
+            ],
+        ];
+
+        let buf = encode(&snapshot);
+        assert!(buf.starts_with(MAGIC));
+        assert_eq!(decode(&buf).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn test_decode_rejects_corruption() {
+        let mut buf = encode(&vec![vec![("k".into(), Value::String("v".into()), None)]]);
+
+        let last = buf.len() - 9;
+        buf[last] ^= 1;
+        assert_eq!(decode(&buf).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(decode(b"short").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load() {
+        let path = temp_path("save.rdb");
+
+        let db = Db::new();
+        assert_eq!(enable_rdb(&db, &path).await.unwrap(), 0);
+        process_command(&db, "set a 1").await;
+        process_command(&db, "set b 2 ex 100").await;
+        process_command(&db, "zadd z 1 x 2 y").await;
+        process_command(&db, "sadd s m").await;
+        db.save().await.unwrap();
+
+        let restored = Db::new();
+        assert_eq!(enable_rdb(&restored, &path).await.unwrap(), 4);
+        for query in ["get a", "get b", "zrange z 0 -1 withscores", "smembers s"] {
+            assert_eq!(process_command(&restored, query).await, process_command(&db, query).await);
+        }
+        assert_eq!(restored.expire_time("b").await, db.expire_time("b").await);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load_databases() {
+        let path = temp_path("databases.rdb");
+
+        let db = Db::new();
+        enable_rdb(&db, &path).await.unwrap();
+        db.select(3).unwrap().set("a".into(), "3".into()).await;
+        db.save().await.unwrap();
+
+        let restored = Db::new();
+        assert_eq!(enable_rdb(&restored, &path).await.unwrap(), 1);
+        assert_eq!(restored.get("a").await, Ok(None));
+        assert_eq!(restored.select(3).unwrap().get("a").await, Ok(Some("3".into())));
+
+        // 数据库数量不足时拒绝加载
+        let config = crate::config::Config { databases: 2, ..Default::default() };
+        let small = Db::with_config(config);
+        let err = enable_rdb(&small, &path).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_save() {
+        let path = temp_path("concurrent.rdb");
+
+        let db = Db::new();
+        enable_rdb(&db, &path).await.unwrap();
+        process_command(&db, "set a 1").await;
+
+        let (first, second) = tokio::join!(db.save(), db.save());
+        assert_eq!(first, Ok(()));
+        assert_eq!(second, Err(DbError::BgSaveInProgress));
+        assert!(!db.is_saving());
+        assert_eq!(db.bgsave().await, Ok(()));
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_parse_save_points() {
+        assert_eq!(
+            SavePoint::parse_list("3600 1 300 100"),
+            Some(vec![
+                SavePoint { seconds: 3600, changes: 1 },
+                SavePoint { seconds: 300, changes: 100 }
+            ])
+        );
+        assert_eq!(SavePoint::parse_list(""), Some(vec![]));
+        assert_eq!(SavePoint::parse_list("3600"), None);
+        assert_eq!(SavePoint::parse_list("60 many"), None);
+    }
+
+    #[test]
+    fn test_save_point_reached() {
+        let points =
+            [SavePoint { seconds: 60, changes: 10 }, SavePoint { seconds: 5, changes: 100 }];
+
+        assert!(!save_point_reached(&points, 9, 120));
+        assert!(save_point_reached(&points, 10, 60));
+        assert!(!save_point_reached(&points, 50, 30));
+        assert!(save_point_reached(&points, 100, 5));
+        assert!(!save_point_reached(&[], 1000, 1000));
+    }
+
+    #[tokio::test]
+    async fn test_save_points_trigger_bgsave() {
+        let path = temp_path("savepoint.rdb");
+
+        let db = Db::new();
+        enable_rdb(&db, &path).await.unwrap();
+        db.set_save_points(vec![SavePoint { seconds: 0, changes: 2 }]);
+
+        process_command(&db, "set a 1").await;
+        assert_eq!(db.dirty(), 1);
+        assert!(!db.check_save_points().await);
+
+        process_command(&db, "set b 2").await;
+        assert!(db.check_save_points().await);
+        while db.is_saving() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(db.dirty(), 0);
+        assert_eq!(enable_rdb(&Db::new(), &path).await.unwrap(), 2);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_bgsave() {
+        let path = temp_path("bgsave.rdb");
+
+        let db = Db::new();
+        enable_rdb(&db, &path).await.unwrap();
+        process_command(&db, "set a 1").await;
+        db.bgsave().await.unwrap();
+        while db.is_saving() {
+            tokio::task::yield_now().await;
+        }
+
+        let restored = Db::new();
+        assert_eq!(enable_rdb(&restored, &path).await.unwrap(), 1);
+        assert!(restored.lastsave() > 0);
+
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,510 @@
+//! 发布/订阅模块
+//!
+//! 维护全局的订阅注册表：
+//! - 频道订阅：`channel -> 订阅者`
+//! - 模式订阅：`pattern -> 订阅者`，按 [`glob`](crate::glob) 规则匹配频道名
+//! - 分片频道订阅（SSUBSCRIBE）：与普通频道互不相通的另一组频道，每个频道像键一样
+//!   属于一个[哈希槽](crate::slot)，集群模式下只由负责该槽的节点投递
+//!
+//! 每个订阅者（通常对应一个客户端会话）拥有一个无界通道，
+//! 发布的消息被投递到通道中，由会话异步取出后推送给客户端。
+//! 通道中尚未取出的字节数记录在订阅者的[输出缓冲区](crate::output)中，
+//! 读取太慢的客户端因此可以按 `client-output-buffer-limit` 断开。
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::{frame::Frame, glob, output::OutputBuffer};
+
+/// 投递给订阅者的消息
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    /// 通过频道订阅收到的消息
+    Channel { channel: String, payload: String },
+    /// 通过模式订阅收到的消息，包含匹配到的模式
+    Pattern { pattern: String, channel: String, payload: String },
+    /// 通过分片频道订阅收到的消息
+    Shard { channel: String, payload: String },
+}
+
+impl Message {
+    /// 消息占用的字节数：各部分长度之和
+    pub fn size(&self) -> usize {
+        match self {
+            Message::Channel { channel, payload } | Message::Shard { channel, payload } => {
+                channel.len() + payload.len()
+            }
+            Message::Pattern { pattern, channel, payload } => {
+                pattern.len() + channel.len() + payload.len()
+            }
+        }
+    }
+}
+
+impl From<Message> for Frame {
+    fn from(message: Message) -> Self {
+        let parts = match message {
+            Message::Channel { channel, payload } => vec!["message".into(), channel, payload],
+            Message::Pattern { pattern, channel, payload } => {
+                vec!["pmessage".into(), pattern, channel, payload]
+            }
+            Message::Shard { channel, payload } => vec!["smessage".into(), channel, payload],
+        };
+        Frame::Push(parts.into_iter().map(Frame::Bulk).collect())
+    }
+}
+
+/// 订阅者 id -> 消息发送端
+type Subscribers = HashMap<u64, Outbox>;
+
+/// 订阅者的消息发送端，投递时把消息的字节数计入订阅者的输出缓冲区
+#[derive(Clone)]
+struct Outbox {
+    sender: UnboundedSender<Message>,
+    output: Arc<OutputBuffer>,
+}
+
+impl Outbox {
+    /// 投递消息，订阅者已经销毁时返回 `false`
+    fn send(&self, message: Message) -> bool {
+        let size = message.size();
+        self.output.queued(size);
+        let sent = self.sender.send(message).is_ok();
+        if !sent {
+            self.output.drained(size);
+        }
+        sent
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    channels: HashMap<String, Subscribers>,
+    patterns: HashMap<String, Subscribers>,
+    shard_channels: HashMap<String, Subscribers>,
+    next_id: u64,
+}
+
+/// 可共享的发布/订阅注册表
+#[derive(Clone, Default)]
+pub struct PubSub {
+    inner: Arc<Mutex<Registry>>,
+}
+
+impl PubSub {
+    /// 创建一个新的订阅者
+    pub fn subscriber(&self) -> Subscriber {
+        self.subscriber_with_output(Arc::default())
+    }
+
+    /// 创建一个新的订阅者，未取出的消息计入给定的输出缓冲区（通常属于订阅者所在的客户端）
+    pub fn subscriber_with_output(&self, output: Arc<OutputBuffer>) -> Subscriber {
+        let id = {
+            let mut registry = self.inner.lock().unwrap();
+            registry.next_id += 1;
+            registry.next_id
+        };
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        Subscriber {
+            id,
+            pubsub: self.clone(),
+            outbox: Outbox { sender, output },
+            receiver,
+            channels: BTreeSet::new(),
+            patterns: BTreeSet::new(),
+            shard_channels: BTreeSet::new(),
+        }
+    }
+
+    /// 向频道发布消息，返回收到消息的订阅数量（频道订阅与模式订阅分别计数）
+    pub fn publish(&self, channel: &str, payload: &str) -> usize {
+        let registry = self.inner.lock().unwrap();
+        let mut received = 0;
+
+        for outbox in registry.channels.get(channel).into_iter().flat_map(HashMap::values) {
+            let message = Message::Channel { channel: channel.into(), payload: payload.into() };
+            received += usize::from(outbox.send(message));
+        }
+
+        for (pattern, subscribers) in &registry.patterns {
+            if !glob::matches(pattern, channel) {
+                continue;
+            }
+            for outbox in subscribers.values() {
+                let message = Message::Pattern {
+                    pattern: pattern.clone(),
+                    channel: channel.into(),
+                    payload: payload.into(),
+                };
+                received += usize::from(outbox.send(message));
+            }
+        }
+
+        received
+    }
+
+    /// 向分片频道发布消息，返回收到消息的订阅数量；分片频道不匹配模式订阅
+    pub fn spublish(&self, channel: &str, payload: &str) -> usize {
+        let registry = self.inner.lock().unwrap();
+        let subscribers =
+            registry.shard_channels.get(channel).into_iter().flat_map(HashMap::values);
+        subscribers
+            .filter(|outbox| {
+                let message = Message::Shard { channel: channel.into(), payload: payload.into() };
+                outbox.send(message)
+            })
+            .count()
+    }
+
+    /// 当前至少有一个订阅者的频道（按字典序），可按 glob 模式过滤
+    pub fn channels(&self, pattern: Option<&str>) -> Vec<String> {
+        active_channels(&self.inner.lock().unwrap().channels, pattern)
+    }
+
+    /// 当前至少有一个订阅者的分片频道（按字典序），可按 glob 模式过滤
+    pub fn shard_channels(&self, pattern: Option<&str>) -> Vec<String> {
+        active_channels(&self.inner.lock().unwrap().shard_channels, pattern)
+    }
+
+    /// 各频道的订阅者数量（不含模式订阅）
+    pub fn numsub(&self, channels: &[String]) -> Vec<(String, usize)> {
+        subscriber_counts(&self.inner.lock().unwrap().channels, channels)
+    }
+
+    /// 各分片频道的订阅者数量
+    pub fn shard_numsub(&self, channels: &[String]) -> Vec<(String, usize)> {
+        subscriber_counts(&self.inner.lock().unwrap().shard_channels, channels)
+    }
+
+    /// 被订阅的模式数量（同一模式被多个订阅者订阅只计一次）
+    pub fn numpat(&self) -> usize {
+        self.inner.lock().unwrap().patterns.len()
+    }
+}
+
+/// 一个订阅者：记录自身订阅的频道、模式与分片频道，并接收投递的消息
+///
+/// 被丢弃时自动从注册表中注销全部订阅。
+pub struct Subscriber {
+    id: u64,
+    pubsub: PubSub,
+    outbox: Outbox,
+    receiver: UnboundedReceiver<Message>,
+    channels: BTreeSet<String>,
+    patterns: BTreeSet<String>,
+    shard_channels: BTreeSet<String>,
+}
+
+impl Subscriber {
+    /// 当前订阅总数（频道 + 模式），不含分片频道
+    pub fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    /// 已订阅的分片频道数量
+    pub fn shard_count(&self) -> usize {
+        self.shard_channels.len()
+    }
+
+    /// 是否订阅了任何频道、模式或分片频道
+    pub fn is_active(&self) -> bool {
+        self.count() + self.shard_count() > 0
+    }
+
+    /// 已订阅的频道
+    pub fn channels(&self) -> Vec<String> {
+        self.channels.iter().cloned().collect()
+    }
+
+    /// 已订阅的模式
+    pub fn patterns(&self) -> Vec<String> {
+        self.patterns.iter().cloned().collect()
+    }
+
+    /// 已订阅的分片频道
+    pub fn shard_channels(&self) -> Vec<String> {
+        self.shard_channels.iter().cloned().collect()
+    }
+
+    /// 订阅频道，返回订阅后的订阅总数
+    pub fn subscribe(&mut self, channel: &str) -> usize {
+        if self.channels.insert(channel.to_string()) {
+            let mut registry = self.pubsub.inner.lock().unwrap();
+            registry
+                .channels
+                .entry(channel.to_string())
+                .or_default()
+                .insert(self.id, self.outbox.clone());
+        }
+        self.count()
+    }
+
+    /// 退订频道，返回退订后的订阅总数
+    pub fn unsubscribe(&mut self, channel: &str) -> usize {
+        if self.channels.remove(channel) {
+            let mut registry = self.pubsub.inner.lock().unwrap();
+            remove_subscriber(&mut registry.channels, channel, self.id);
+        }
+        self.count()
+    }
+
+    /// 订阅模式，返回订阅后的订阅总数
+    pub fn psubscribe(&mut self, pattern: &str) -> usize {
+        if self.patterns.insert(pattern.to_string()) {
+            let mut registry = self.pubsub.inner.lock().unwrap();
+            registry
+                .patterns
+                .entry(pattern.to_string())
+                .or_default()
+                .insert(self.id, self.outbox.clone());
+        }
+        self.count()
+    }
+
+    /// 退订模式，返回退订后的订阅总数
+    pub fn punsubscribe(&mut self, pattern: &str) -> usize {
+        if self.patterns.remove(pattern) {
+            let mut registry = self.pubsub.inner.lock().unwrap();
+            remove_subscriber(&mut registry.patterns, pattern, self.id);
+        }
+        self.count()
+    }
+
+    /// 订阅分片频道，返回订阅后的分片频道数量
+    pub fn ssubscribe(&mut self, channel: &str) -> usize {
+        if self.shard_channels.insert(channel.to_string()) {
+            let mut registry = self.pubsub.inner.lock().unwrap();
+            registry
+                .shard_channels
+                .entry(channel.to_string())
+                .or_default()
+                .insert(self.id, self.outbox.clone());
+        }
+        self.shard_count()
+    }
+
+    /// 退订分片频道，返回退订后的分片频道数量
+    pub fn sunsubscribe(&mut self, channel: &str) -> usize {
+        if self.shard_channels.remove(channel) {
+            let mut registry = self.pubsub.inner.lock().unwrap();
+            remove_subscriber(&mut registry.shard_channels, channel, self.id);
+        }
+        self.shard_count()
+    }
+
+    /// 等待下一条投递的消息
+    pub async fn recv(&mut self) -> Option<Message> {
+        let message = self.receiver.recv().await?;
+        self.outbox.output.drained(message.size());
+        Some(message)
+    }
+
+    /// 非阻塞地取出一条已投递的消息
+    pub fn try_recv(&mut self) -> Option<Message> {
+        let message = self.receiver.try_recv().ok()?;
+        self.outbox.output.drained(message.size());
+        Some(message)
+    }
+}
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        let mut registry = self.pubsub.inner.lock().unwrap();
+        for channel in &self.channels {
+            remove_subscriber(&mut registry.channels, channel, self.id);
+        }
+        for pattern in &self.patterns {
+            remove_subscriber(&mut registry.patterns, pattern, self.id);
+        }
+        for channel in &self.shard_channels {
+            remove_subscriber(&mut registry.shard_channels, channel, self.id);
+        }
+        drop(registry);
+        // 尚未取出的消息不会再写出，从输出缓冲区中扣除
+        while self.try_recv().is_some() {}
+    }
+}
+
+/// 至少有一个订阅者的频道（按字典序），可按 glob 模式过滤
+fn active_channels(map: &HashMap<String, Subscribers>, pattern: Option<&str>) -> Vec<String> {
+    let mut channels: Vec<_> = map
+        .keys()
+        .filter(|channel| pattern.is_none_or(|pattern| glob::matches(pattern, channel)))
+        .cloned()
+        .collect();
+    channels.sort();
+    channels
+}
+
+/// 各频道的订阅者数量
+fn subscriber_counts(
+    map: &HashMap<String, Subscribers>,
+    channels: &[String],
+) -> Vec<(String, usize)> {
+    channels
+        .iter()
+        .map(|channel| (channel.clone(), map.get(channel).map_or(0, HashMap::len)))
+        .collect()
+}
+
+/// 从注册表中移除订阅者，频道/模式没有订阅者后一并移除
+fn remove_subscriber(map: &mut HashMap<String, Subscribers>, name: &str, id: u64) {
+    if let Some(subscribers) = map.get_mut(name) {
+        subscribers.remove(&id);
+        if subscribers.is_empty() {
+            map.remove(name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{Message, PubSub};
+    use crate::output::OutputBuffer;
+
+    #[test]
+    fn test_publish_to_channel() {
+        let pubsub = PubSub::default();
+        let mut subscriber = pubsub.subscriber();
+
+        assert_eq!(subscriber.subscribe("news"), 1);
+        assert_eq!(pubsub.publish("news", "hello"), 1);
+        assert_eq!(pubsub.publish("other", "hello"), 0);
+
+        let expected = Message::Channel { channel: "news".into(), payload: "hello".into() };
+        assert_eq!(subscriber.try_recv(), Some(expected));
+        assert_eq!(subscriber.try_recv(), None);
+    }
+
+    #[test]
+    fn test_publish_to_pattern() {
+        let pubsub = PubSub::default();
+        let mut subscriber = pubsub.subscriber();
+
+        assert_eq!(subscriber.psubscribe("news.*"), 1);
+        assert_eq!(subscriber.subscribe("news.tech"), 2);
+
+        // 同时命中频道订阅与模式订阅时会收到两条消息
+        assert_eq!(pubsub.publish("news.tech", "rust"), 2);
+        assert_eq!(pubsub.publish("sports", "ball"), 0);
+
+        assert_eq!(
+            subscriber.try_recv(),
+            Some(Message::Channel { channel: "news.tech".into(), payload: "rust".into() })
+        );
+        assert_eq!(
+            subscriber.try_recv(),
+            Some(Message::Pattern {
+                pattern: "news.*".into(),
+                channel: "news.tech".into(),
+                payload: "rust".into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_unsubscribe_and_drop() {
+        let pubsub = PubSub::default();
+        let mut subscriber = pubsub.subscriber();
+        subscriber.subscribe("a");
+        subscriber.psubscribe("b*");
+
+        assert_eq!(subscriber.punsubscribe("b*"), 1);
+        assert_eq!(subscriber.punsubscribe("b*"), 1);
+        assert_eq!(pubsub.publish("bar", "x"), 0);
+
+        drop(subscriber);
+        assert_eq!(pubsub.publish("a", "x"), 0);
+    }
+
+    #[test]
+    fn test_introspection() {
+        let pubsub = PubSub::default();
+        let mut first = pubsub.subscriber();
+        let mut second = pubsub.subscriber();
+        first.subscribe("news.tech");
+        first.subscribe("sports");
+        second.subscribe("news.tech");
+        first.psubscribe("news.*");
+        second.psubscribe("news.*");
+
+        assert_eq!(pubsub.channels(None), vec!["news.tech", "sports"]);
+        assert_eq!(pubsub.channels(Some("news.*")), vec!["news.tech"]);
+        assert_eq!(
+            pubsub.numsub(&["news.tech".into(), "none".into()]),
+            vec![("news.tech".into(), 2), ("none".into(), 0)]
+        );
+        assert_eq!(pubsub.numpat(), 1);
+
+        drop(second);
+        assert_eq!(pubsub.numsub(&["news.tech".into()]), vec![("news.tech".into(), 1)]);
+    }
+
+    #[test]
+    fn test_shard_channels() {
+        let pubsub = PubSub::default();
+        let mut subscriber = pubsub.subscriber();
+        subscriber.subscribe("news");
+
+        // 分片频道与普通频道、模式互不相通
+        assert_eq!(subscriber.ssubscribe("news"), 1);
+        assert_eq!(subscriber.ssubscribe("{news}.tech"), 2);
+        assert_eq!(subscriber.psubscribe("*"), 2);
+        assert_eq!(pubsub.spublish("news", "hi"), 1);
+        assert_eq!(
+            subscriber.try_recv(),
+            Some(Message::Shard { channel: "news".into(), payload: "hi".into() })
+        );
+        assert_eq!(subscriber.try_recv(), None);
+        assert_eq!(pubsub.publish("{news}.tech", "x"), 1);
+
+        assert_eq!(pubsub.shard_channels(None), vec!["news", "{news}.tech"]);
+        assert_eq!(pubsub.shard_channels(Some("{*")), vec!["{news}.tech"]);
+        assert_eq!(pubsub.channels(None), vec!["news"]);
+        assert_eq!(
+            pubsub.shard_numsub(&["news".into(), "none".into()]),
+            vec![("news".into(), 1), ("none".into(), 0)]
+        );
+
+        assert_eq!(subscriber.sunsubscribe("news"), 1);
+        assert_eq!(pubsub.spublish("news", "hi"), 0);
+        assert!(subscriber.is_active());
+        drop(subscriber);
+        assert!(pubsub.shard_channels(None).is_empty());
+    }
+
+    #[test]
+    fn test_output_buffer() {
+        let pubsub = PubSub::default();
+        let output = Arc::new(OutputBuffer::default());
+        let mut subscriber = pubsub.subscriber_with_output(output.clone());
+        subscriber.subscribe("news");
+        subscriber.psubscribe("n*");
+
+        pubsub.publish("news", "hello");
+        assert_eq!(output.pending(), 9 + 11);
+        subscriber.try_recv();
+        assert_eq!(output.pending(), 11);
+
+        // 销毁时扣除尚未取出的消息
+        drop(subscriber);
+        assert_eq!(output.pending(), 0);
+    }
+
+    #[test]
+    fn test_message_frame() {
+        use crate::frame::Frame;
+
+        let message =
+            Message::Pattern { pattern: "n*".into(), channel: "news".into(), payload: "hi".into() };
+
+        assert_eq!(Frame::from(message).to_string(), "1) pmessage\n2) n*\n3) news\n4) hi");
+    }
+}
@@ -0,0 +1,143 @@
+//! 随机数模块
+//!
+//! 提供一个基于 SplitMix64 的轻量级伪随机数生成器，
+//! 供 HRANDFIELD、SPOP、SRANDMEMBER 等需要随机采样的命令使用。
+//!
+//! 生成器可以通过固定种子创建，使测试结果可复现。
+
+use std::hash::{BuildHasher, RandomState};
+
+/// 允许重复的随机采样（负数 count）一次最多返回的数量
+///
+/// 回复需要整体构造在内存中，过大的 count 会在持锁期间耗尽内存，因此直接拒绝。
+pub const MAX_REPEATED_SAMPLES: u64 = 1 << 24;
+
+/// 随机采样类命令的 count 是否在允许范围内（见 [`MAX_REPEATED_SAMPLES`]）
+pub fn count_in_range(count: Option<i64>) -> bool {
+    count.is_none_or(|count| count >= 0 || count.unsigned_abs() <= MAX_REPEATED_SAMPLES)
+}
+
+/// 可设置种子的伪随机数生成器
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// 使用固定种子创建生成器，相同种子产生相同的随机序列
+    pub fn seeded(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// 使用系统提供的随机源创建生成器
+    pub fn from_entropy() -> Self {
+        Self::seeded(RandomState::new().hash_one(0u64))
+    }
+
+    /// 生成下一个 `u64` 随机数
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// 生成 `[0, n)` 范围内的随机下标
+    ///
+    /// # Panics
+    /// `n` 为 0 时 panic
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "Rng::below called with n = 0");
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// 从 `[0, len)` 中不重复地随机选出 `count` 个下标（`count` 超过 `len` 时返回全部下标）
+    pub fn sample_indices(&mut self, len: usize, count: usize) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..len).collect();
+        let count = count.min(len);
+
+        // 部分 Fisher-Yates 洗牌：只需打乱前 count 个位置
+        for i in 0..count {
+            let j = i + self.below(len - i);
+            indices.swap(i, j);
+        }
+
+        indices.truncate(count);
+        indices
+    }
+
+    /// 按 Redis 随机采样类命令（HRANDFIELD / SRANDMEMBER）的 count 语义选出下标
+    ///
+    /// - `count` 为 `None`：选出一个下标
+    /// - `count` 为非负数：最多选出 `count` 个互不相同的下标
+    /// - `count` 为负数：选出 `|count|` 个下标，允许重复
+    ///
+    /// `len` 为 0 时返回空列表。调用方需先用 [`count_in_range`] 校验 `count`。
+    pub fn sample_with_count(&mut self, len: usize, count: Option<i64>) -> Vec<usize> {
+        if len == 0 {
+            return Vec::new();
+        }
+
+        match count {
+            None => vec![self.below(len)],
+            Some(count) if count >= 0 => self.sample_indices(len, count as usize),
+            Some(count) => (0..count.unsigned_abs()).map(|_| self.below(len)).collect(),
+        }
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::from_entropy()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_REPEATED_SAMPLES, Rng, count_in_range};
+
+    #[test]
+    fn test_seeded_rng_is_deterministic() {
+        let mut a = Rng::seeded(42);
+        let mut b = Rng::seeded(42);
+
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn test_sample_indices_distinct() {
+        let mut rng = Rng::seeded(7);
+
+        let mut indices = rng.sample_indices(10, 5);
+        assert_eq!(indices.len(), 5);
+
+        indices.sort();
+        indices.dedup();
+        assert_eq!(indices.len(), 5);
+        assert!(indices.iter().all(|&i| i < 10));
+
+        assert_eq!(rng.sample_indices(3, 10).len(), 3);
+    }
+
+    #[test]
+    fn test_sample_with_count() {
+        let mut rng = Rng::seeded(7);
+
+        assert_eq!(rng.sample_with_count(3, None).len(), 1);
+        assert_eq!(rng.sample_with_count(3, Some(5)).len(), 3);
+        assert_eq!(rng.sample_with_count(3, Some(-5)).len(), 5);
+        assert!(rng.sample_with_count(0, Some(-5)).is_empty());
+    }
+
+    #[test]
+    fn test_count_in_range() {
+        assert!(count_in_range(None));
+        assert!(count_in_range(Some(i64::MAX)));
+        assert!(count_in_range(Some(-(MAX_REPEATED_SAMPLES as i64))));
+        assert!(!count_in_range(Some(-(MAX_REPEATED_SAMPLES as i64) - 1)));
+        assert!(!count_in_range(Some(i64::MIN)));
+    }
+}
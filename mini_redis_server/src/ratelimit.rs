@@ -0,0 +1,248 @@
+//! 客户端限流
+//!
+//! 按令牌桶限制每个客户端每秒执行的命令数（`client-rate-limit`，0 表示不限制）：
+//! 桶的容量为 `client-rate-limit-burst`（0 表示与速率相同），令牌按速率持续补充，
+//! 每条命令消耗一个令牌。令牌不足时按 `client-rate-limit-mode` 处理：
+//! - `reject`：命令不执行，回复 THROTTLED 错误
+//! - `delay`：预支令牌并等待到令牌补足后再执行，连接在等待期间不读取新的请求
+//!
+//! `client-rate-limit-key` 决定按什么计数：`client` 为每个连接一个桶，`addr` 为同一 IP
+//! 地址的全部连接共用一个桶（不经过网络的会话仍按连接计数）。
+//! 长时间空闲的桶已经补满，与新建的桶没有区别，由服务器的周期任务清理（见 [`RateLimiter::prune`]）。
+
+use std::{
+    collections::HashMap,
+    fmt,
+    net::IpAddr,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// 限流设置
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RateLimit {
+    /// 每秒允许的命令数，0 表示不限制
+    pub rate: u64,
+    /// 桶的容量，即允许突发的命令数；0 表示与 `rate` 相同
+    pub burst: u64,
+    pub key: RateLimitKey,
+    pub mode: RateLimitMode,
+}
+
+impl RateLimit {
+    fn capacity(&self) -> f64 {
+        if self.burst > 0 { self.burst as f64 } else { self.rate as f64 }
+    }
+}
+
+/// 限流的计数单位
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RateLimitKey {
+    /// 每个连接单独计数
+    #[default]
+    Client,
+    /// 同一 IP 地址的连接共同计数
+    Addr,
+}
+
+impl FromStr for RateLimitKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "client" => Ok(RateLimitKey::Client),
+            "addr" => Ok(RateLimitKey::Addr),
+            _ => Err("argument must be 'client' or 'addr'".into()),
+        }
+    }
+}
+
+impl fmt::Display for RateLimitKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RateLimitKey::Client => "client",
+            RateLimitKey::Addr => "addr",
+        })
+    }
+}
+
+/// 超出速率时的处理方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RateLimitMode {
+    /// 拒绝执行并回复错误
+    #[default]
+    Reject,
+    /// 等待令牌补足后执行
+    Delay,
+}
+
+impl FromStr for RateLimitMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "reject" => Ok(RateLimitMode::Reject),
+            "delay" => Ok(RateLimitMode::Delay),
+            _ => Err("argument must be 'reject' or 'delay'".into()),
+        }
+    }
+}
+
+impl fmt::Display for RateLimitMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RateLimitMode::Reject => "reject",
+            RateLimitMode::Delay => "delay",
+        })
+    }
+}
+
+/// 一条命令的准入结果
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Admission {
+    /// 立即执行
+    Allowed,
+    /// 等待给定时长后执行
+    Delayed(Duration),
+    /// 拒绝执行
+    Rejected,
+}
+
+/// 计数的对象
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Client(u64),
+    Addr(IpAddr),
+}
+
+/// 令牌桶，令牌数在 delay 模式下可以为负（已预支）
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    /// 按经过的时间补充令牌，不超过容量
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.rate as f64).min(limit.capacity());
+        self.updated = now;
+    }
+}
+
+#[derive(Default)]
+struct State {
+    limit: RateLimit,
+    buckets: HashMap<Key, Bucket>,
+}
+
+/// 全部客户端的令牌桶
+#[derive(Default)]
+pub struct RateLimiter {
+    state: Mutex<State>,
+}
+
+impl RateLimiter {
+    /// 当前的限流设置
+    pub fn limit(&self) -> RateLimit {
+        self.state.lock().unwrap().limit
+    }
+
+    /// 修改限流设置，设置变化时所有桶重新开始计数
+    pub fn set_limit(&self, limit: RateLimit) {
+        let mut state = self.state.lock().unwrap();
+        if state.limit != limit {
+            state.limit = limit;
+            state.buckets.clear();
+        }
+    }
+
+    /// 为客户端的一条命令申请令牌，`addr` 为客户端的对端地址
+    pub fn acquire(&self, client: u64, addr: Option<IpAddr>, now: Instant) -> Admission {
+        let mut state = self.state.lock().unwrap();
+        let limit = state.limit;
+        if limit.rate == 0 {
+            return Admission::Allowed;
+        }
+
+        let key = match (limit.key, addr) {
+            (RateLimitKey::Addr, Some(addr)) => Key::Addr(addr),
+            _ => Key::Client(client),
+        };
+        let bucket =
+            state.buckets.entry(key).or_insert(Bucket { tokens: limit.capacity(), updated: now });
+        bucket.refill(&limit, now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Admission::Allowed;
+        }
+        match limit.mode {
+            RateLimitMode::Reject => Admission::Rejected,
+            RateLimitMode::Delay => {
+                let wait = (1.0 - bucket.tokens) / limit.rate as f64;
+                bucket.tokens -= 1.0;
+                Admission::Delayed(Duration::from_secs_f64(wait))
+            }
+        }
+    }
+
+    /// 移除已经补满的桶，它们与新建的桶没有区别
+    pub fn prune(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        let limit = state.limit;
+        state.buckets.retain(|_, bucket| {
+            bucket.refill(&limit, now);
+            bucket.tokens < limit.capacity()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(limit: RateLimit) -> RateLimiter {
+        let limiter = RateLimiter::default();
+        limiter.set_limit(limit);
+        limiter
+    }
+
+    #[test]
+    fn test_reject() {
+        let limiter = limiter(RateLimit { rate: 10, burst: 2, ..RateLimit::default() });
+        let now = Instant::now();
+
+        assert_eq!(limiter.acquire(1, None, now), Admission::Allowed);
+        assert_eq!(limiter.acquire(1, None, now), Admission::Allowed);
+        assert_eq!(limiter.acquire(1, None, now), Admission::Rejected);
+        // 每个连接单独计数，令牌按速率补充
+        assert_eq!(limiter.acquire(2, None, now), Admission::Allowed);
+        let later = now + Duration::from_millis(100);
+        assert_eq!(limiter.acquire(1, None, later), Admission::Allowed);
+        assert_eq!(limiter.acquire(1, None, later), Admission::Rejected);
+
+        limiter.prune(now + Duration::from_secs(1));
+        assert!(limiter.state.lock().unwrap().buckets.is_empty());
+    }
+
+    #[test]
+    fn test_delay_by_addr() {
+        let limit =
+            RateLimit { rate: 10, burst: 1, key: RateLimitKey::Addr, mode: RateLimitMode::Delay };
+        let limiter = limiter(limit);
+        let now = Instant::now();
+        let addr = Some("10.0.0.1".parse().unwrap());
+
+        // 同一地址的连接共用一个桶，预支的令牌依次排队
+        assert_eq!(limiter.acquire(1, addr, now), Admission::Allowed);
+        assert_eq!(limiter.acquire(2, addr, now), Admission::Delayed(Duration::from_millis(100)));
+        assert_eq!(limiter.acquire(1, addr, now), Admission::Delayed(Duration::from_millis(200)));
+        assert_eq!(limiter.acquire(3, None, now), Admission::Allowed);
+
+        let unlimited = RateLimiter::default();
+        assert!((0..1000).all(|_| unlimited.acquire(1, None, now) == Admission::Allowed));
+    }
+}
@@ -0,0 +1,766 @@
+//! 主从复制模块
+//!
+//! 主节点：
+//! - 副本连接后发送 `PSYNC`（或 `SYNC`），主节点暂停写命令、复制数据集并登记副本，
+//!   回复 `+FULLRESYNC <replid> <offset>`，再以二进制载荷发送 RDB 快照；
+//! - 此后每条传播的写命令（见 `Db::propagate`）都以 RESP 数组的形式转发给所有副本，
+//!   复制偏移量按转发的字节数累加；
+//! - 命令流同时写入积压缓冲区。副本重连时发送 `PSYNC <replid> <offset>`，
+//!   复制 id 一致且偏移量之后的数据仍在缓冲区中时回复 `+CONTINUE <replid>`，只补发缺失的部分；
+//! - 副本每秒以及收到 `REPLCONF GETACK` 时回复 `REPLCONF ACK <offset>`，
+//!   主节点据此记录每个副本确认的偏移量，供 `WAIT` 使用。
+//!   GETACK 只发给已连接的副本，不计入复制偏移量，也不写入积压缓冲区。
+//! - 尚未写给副本的命令流计入副本连接的输出缓冲区，超出 `client-output-buffer-limit`
+//!   中 replica 类的限制时断开副本（见 [`output`](crate::output)）。
+//!
+//! 副本：
+//! - `REPLICAOF host port` 启动后台任务连接主节点，完成全量同步后持续应用命令流；
+//! - 连接断开后每秒重连一次，并尝试从已应用的偏移量继续（部分重同步）；
+//! - `REPLICAOF NO ONE` 停止复制并恢复为主节点；
+//! - 副本默认拒绝客户端的写命令（`replica-read-only`），暂不支持级联复制（成为副本时断开自己的副本）。
+
+mod backlog;
+
+use std::{
+    collections::HashMap,
+    io,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+    sync::{
+        Notify,
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+    },
+    task::JoinHandle,
+    time::Instant,
+};
+
+use crate::{
+    client::Client,
+    command::Command,
+    connection::Connection,
+    db::{Db, Storage},
+    frame::Frame,
+    handler,
+    output::{ClientClass, OutputBuffer},
+    persistence::{encode_rdb, load_rdb},
+    random::Rng,
+};
+
+use self::backlog::Backlog;
+pub use self::backlog::DEFAULT_BACKLOG_SIZE;
+
+/// 副本与主节点断开后的重连间隔
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// 副本主动向主节点确认偏移量的间隔
+const ACK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 副本与主节点之间的连接状态，对应 ROLE 命令中的状态字段
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LinkState {
+    /// 等待（重新）连接
+    Connect,
+    /// 正在建立连接并握手
+    Connecting,
+    /// 正在接收全量快照
+    Sync,
+    /// 已同步，正在接收命令流
+    Connected,
+}
+
+impl LinkState {
+    /// INFO 中的 `master_link_status`：已同步时为 `up`，否则为 `down`
+    fn link_status(self) -> &'static str {
+        if self == LinkState::Connected { "up" } else { "down" }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            LinkState::Connect => "connect",
+            LinkState::Connecting => "connecting",
+            LinkState::Sync => "sync",
+            LinkState::Connected => "connected",
+        }
+    }
+}
+
+/// 副本接收命令流的通道
+type CommandStream = UnboundedReceiver<Arc<Vec<u8>>>;
+
+/// 已连接的副本
+struct ReplicaInfo {
+    ip: IpAddr,
+    /// 副本通过 `REPLCONF listening-port` 告知的监听端口
+    port: Option<u16>,
+    /// 副本通过 `REPLCONF ACK` 确认已应用的偏移量
+    ack: u64,
+    sender: UnboundedSender<Arc<Vec<u8>>>,
+    /// 副本连接的输出缓冲区，记录已发送到通道、尚未写出的字节数
+    output: Arc<OutputBuffer>,
+}
+
+impl ReplicaInfo {
+    /// 把命令流的一段发送给副本，副本已断开时返回 `false`
+    fn send(&self, bytes: &Arc<Vec<u8>>) -> bool {
+        self.output.queued(bytes.len());
+        let sent = self.sender.send(bytes.clone()).is_ok();
+        if !sent {
+            self.output.drained(bytes.len());
+        }
+        sent
+    }
+}
+
+/// 本节点作为副本时与主节点的连接
+struct MasterLink {
+    host: String,
+    port: u16,
+    state: LinkState,
+    task: JoinHandle<()>,
+}
+
+struct State {
+    /// 复制 id，标识一段连续的命令流
+    replid: String,
+    /// 复制偏移量：命令流中已产生（主节点）或已应用（副本）的字节数
+    offset: u64,
+    /// 副本应用命令流时选中的数据库，部分重同步后从这里继续
+    applied_db: usize,
+    /// 本节点的监听端口，成为副本时告知主节点
+    listening_port: Option<u16>,
+    /// 作为副本时是否拒绝客户端的写命令
+    read_only: bool,
+    /// 命令流的积压缓冲区，用于部分重同步
+    backlog: Backlog,
+    replicas: HashMap<u64, ReplicaInfo>,
+    next_id: u64,
+    master: Option<MasterLink>,
+}
+
+impl State {
+    /// 登记一个副本，返回 id 与接收命令流的通道
+    fn add_replica(
+        &mut self,
+        ip: IpAddr,
+        port: Option<u16>,
+        output: Arc<OutputBuffer>,
+    ) -> (u64, CommandStream) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        self.next_id += 1;
+        self.replicas.insert(self.next_id, ReplicaInfo { ip, port, ack: 0, sender, output });
+        (self.next_id, receiver)
+    }
+}
+
+/// 可共享的复制状态
+pub struct Replication {
+    state: Mutex<State>,
+    /// 收到副本确认时唤醒等待中的 WAIT
+    acked: Notify,
+}
+
+impl Default for Replication {
+    fn default() -> Self {
+        let state = State {
+            replid: new_replid(),
+            offset: 0,
+            applied_db: 0,
+            listening_port: None,
+            read_only: true,
+            backlog: Backlog::new(DEFAULT_BACKLOG_SIZE, 0),
+            replicas: HashMap::new(),
+            next_id: 0,
+            master: None,
+        };
+        Self { state: Mutex::new(state), acked: Notify::new() }
+    }
+}
+
+impl Replication {
+    /// 本节点是否为副本
+    pub fn is_replica(&self) -> bool {
+        self.state.lock().unwrap().master.is_some()
+    }
+
+    /// 本节点是否为只读副本：客户端的写命令应以 `-READONLY` 拒绝
+    pub fn is_read_only(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.master.is_some() && state.read_only
+    }
+
+    /// 设置作为副本时是否拒绝客户端的写命令（`replica-read-only`）
+    pub fn set_read_only(&self, read_only: bool) {
+        self.state.lock().unwrap().read_only = read_only;
+    }
+
+    /// 当前的复制 id 与偏移量
+    pub fn position(&self) -> (String, u64) {
+        let state = self.state.lock().unwrap();
+        (state.replid.clone(), state.offset)
+    }
+
+    /// 已连接的副本数量
+    pub fn replica_count(&self) -> usize {
+        self.state.lock().unwrap().replicas.len()
+    }
+
+    /// 记录本节点的监听端口
+    pub fn set_listening_port(&self, port: u16) {
+        self.state.lock().unwrap().listening_port = Some(port);
+    }
+
+    /// 本节点的监听端口，尚未开始监听时为 `None`
+    pub fn listening_port(&self) -> Option<u16> {
+        self.state.lock().unwrap().listening_port
+    }
+
+    /// 设置积压缓冲区的大小（字节）
+    pub fn set_backlog_size(&self, size: usize) {
+        self.state.lock().unwrap().backlog.resize(size);
+    }
+
+    /// INFO replication 分节的字段
+    pub fn info(&self) -> Vec<(&'static str, String)> {
+        let state = self.state.lock().unwrap();
+
+        let mut fields = match &state.master {
+            Some(link) => vec![
+                ("role", "slave".to_string()),
+                ("master_host", link.host.clone()),
+                ("master_port", link.port.to_string()),
+                ("master_link_status", link.state.link_status().into()),
+                ("slave_read_only", u8::from(state.read_only).to_string()),
+            ],
+            None => vec![("role", "master".to_string())],
+        };
+        fields.extend([
+            ("connected_slaves", state.replicas.len().to_string()),
+            ("master_replid", state.replid.clone()),
+            ("master_repl_offset", state.offset.to_string()),
+        ]);
+        fields
+    }
+
+    /// ROLE 命令的回复
+    pub fn role(&self) -> Frame {
+        let state = self.state.lock().unwrap();
+
+        match &state.master {
+            Some(link) => Frame::Array(vec![
+                Frame::Bulk("slave".into()),
+                Frame::Bulk(link.host.clone()),
+                Frame::Integer(link.port.into()),
+                Frame::Bulk(link.state.as_str().into()),
+                Frame::Integer(state.offset as i64),
+            ]),
+            None => {
+                let replicas = state.replicas.values().map(|replica| {
+                    let port = replica.port.map_or_else(String::new, |port| port.to_string());
+                    Frame::Array(vec![
+                        Frame::Bulk(replica.ip.to_string()),
+                        Frame::Bulk(port),
+                        Frame::Bulk(replica.ack.to_string()),
+                    ])
+                });
+                Frame::Array(vec![
+                    Frame::Bulk("master".into()),
+                    Frame::Integer(state.offset as i64),
+                    Frame::Array(replicas.collect()),
+                ])
+            }
+        }
+    }
+
+    /// 将一条写命令转发给所有副本（仅主节点），并累加复制偏移量
+    pub(crate) fn feed(&self, args: &[String]) {
+        let mut state = self.state.lock().unwrap();
+        if state.master.is_some() {
+            return;
+        }
+
+        let bytes = Frame::from(args.to_vec()).encode();
+        state.offset += bytes.len() as u64;
+        state.backlog.push(&bytes);
+
+        let bytes = Arc::new(bytes);
+        state.replicas.retain(|_, replica| replica.send(&bytes));
+    }
+
+    /// 为全量同步登记一个副本，返回 id、接收命令流的通道以及同步起点
+    fn register_replica(
+        &self,
+        ip: IpAddr,
+        port: Option<u16>,
+        output: Arc<OutputBuffer>,
+    ) -> (u64, CommandStream, String, u64) {
+        let mut state = self.state.lock().unwrap();
+        let (id, receiver) = state.add_replica(ip, port, output);
+        (id, receiver, state.replid.clone(), state.offset)
+    }
+
+    /// 尝试部分重同步：复制 id 一致且积压缓冲区包含 `offset` 之后的全部数据时登记副本，
+    /// 返回 id、接收命令流的通道以及需要补发的数据
+    fn continue_replica(
+        &self,
+        ip: IpAddr,
+        port: Option<u16>,
+        replid: &str,
+        offset: i64,
+        output: Arc<OutputBuffer>,
+    ) -> Option<(u64, CommandStream, Vec<u8>)> {
+        let mut state = self.state.lock().unwrap();
+        if state.master.is_some() || replid != state.replid {
+            return None;
+        }
+
+        let missing = state.backlog.range_from(u64::try_from(offset).ok()?)?;
+        let (id, receiver) = state.add_replica(ip, port, output);
+        Some((id, receiver, missing))
+    }
+
+    /// 确认偏移量不小于 `offset` 的副本数量
+    fn acked_replicas(&self, offset: u64) -> usize {
+        let state = self.state.lock().unwrap();
+        state.replicas.values().filter(|replica| replica.ack >= offset).count()
+    }
+
+    /// 记录副本确认的偏移量
+    fn ack(&self, id: u64, offset: u64) {
+        if let Some(replica) = self.state.lock().unwrap().replicas.get_mut(&id) {
+            replica.ack = offset;
+        }
+        self.acked.notify_waiters();
+    }
+
+    /// 要求所有副本立即确认偏移量
+    fn request_acks(&self) {
+        let getack = Arc::new(Frame::from(to_args(&["replconf", "getack", "*"])).encode());
+        let state = self.state.lock().unwrap();
+        for replica in state.replicas.values() {
+            replica.send(&getack);
+        }
+    }
+
+    fn unregister_replica(&self, id: u64) {
+        self.state.lock().unwrap().replicas.remove(&id);
+    }
+
+    fn set_link_state(&self, link_state: LinkState) {
+        if let Some(link) = &mut self.state.lock().unwrap().master {
+            link.state = link_state;
+        }
+    }
+
+    fn advance(&self, len: u64) {
+        self.state.lock().unwrap().offset += len;
+    }
+}
+
+impl<S: Storage> Db<S> {
+    /// 成为指定主节点的副本（REPLICAOF host port）
+    ///
+    /// 断开本节点已有的副本，并在后台连接主节点进行同步。
+    pub fn replicaof(&self, host: String, port: u16) {
+        let mut state = self.replication().state.lock().unwrap();
+        if let Some(link) = state.master.take() {
+            link.task.abort();
+        }
+        state.replicas.clear();
+
+        let task = tokio::spawn(replicate(self.clone(), host.clone(), port));
+        state.master = Some(MasterLink { host, port, state: LinkState::Connect, task });
+    }
+
+    /// 等待至少 `numreplicas` 个副本确认此前的全部写命令（WAIT），返回已确认的副本数量
+    ///
+    /// 超过 `timeout` 仍未达到数量时返回当时的确认数量，`None` 表示一直等待。
+    pub async fn wait_for_replicas(&self, numreplicas: usize, timeout: Option<Duration>) -> usize {
+        let replication = self.replication();
+        let offset = replication.position().1;
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        let mut acked = replication.acked_replicas(offset);
+        if acked >= numreplicas {
+            return acked;
+        }
+        replication.request_acks();
+
+        loop {
+            // 先注册通知再检查，避免错过检查与等待之间到达的确认
+            let notified = replication.acked.notified();
+            acked = replication.acked_replicas(offset);
+            if acked >= numreplicas {
+                return acked;
+            }
+            match deadline {
+                Some(deadline) => {
+                    if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                        return replication.acked_replicas(offset);
+                    }
+                }
+                None => notified.await,
+            }
+        }
+    }
+
+    /// 停止复制并恢复为主节点（REPLICAOF NO ONE），保留已同步的数据
+    pub fn replicaof_no_one(&self) {
+        let mut state = self.replication().state.lock().unwrap();
+        if let Some(link) = state.master.take() {
+            link.task.abort();
+            // 开始一段新的命令流
+            state.replid = new_replid();
+            let offset = state.offset;
+            state.backlog.reset(offset);
+        }
+    }
+}
+
+/// 为一个发送了 PSYNC / SYNC 的连接提供同步与命令流，直到连接断开
+///
+/// `psync` 为 PSYNC 携带的复制 id 与偏移量，SYNC 为 `None`（总是全量同步）。
+/// `client` 为连接在客户端注册表中的登记，未写出的命令流计入它的输出缓冲区；
+/// `killed` 完成（CLIENT KILL 或超出输出缓冲区限制）时即使正在写出也立即断开。
+pub(crate) async fn serve_replica<S: Storage, T: AsyncRead + AsyncWrite + Unpin>(
+    mut conn: Connection<T>,
+    db: Db<S>,
+    client: &Client,
+    killed: impl Future<Output = ()>,
+    ip: IpAddr,
+    port: Option<u16>,
+    psync: Option<(String, i64)>,
+) -> io::Result<()> {
+    client.set_class(ClientClass::Replica);
+    let output = client.output();
+    tokio::pin!(killed);
+    let replication = db.replication();
+    let resumed = psync.and_then(|(replid, offset)| {
+        let resumed = replication.continue_replica(ip, port, &replid, offset, output.clone());
+        resumed.map(|resumed| (replid, resumed))
+    });
+
+    if let Some((replid, (id, receiver, missing))) = resumed {
+        let result = async {
+            conn.write_frame(&Frame::Simple(format!("CONTINUE {replid}"))).await?;
+            conn.write_bytes(&missing).await?;
+            stream_commands(&mut conn, replication, id, receiver, &output).await
+        };
+        let result = tokio::select! {
+            result = result => result,
+            () = &mut killed => Ok(()),
+        };
+        replication.unregister_replica(id);
+        return result;
+    }
+
+    // 暂停写命令，保证快照与之后转发的命令流首尾相接
+    let (records, (id, receiver, replid, offset)) = {
+        let _paused = db.pause_writes().await;
+        (db.snapshot().await, replication.register_replica(ip, port, output.clone()))
+    };
+
+    let result = async {
+        conn.write_frame(&Frame::Simple(format!("FULLRESYNC {replid} {offset}"))).await?;
+        let payload = tokio::task::spawn_blocking(move || encode_rdb(&records))
+            .await
+            .expect("RDB encode task panicked");
+        conn.write_payload(&payload).await?;
+        stream_commands(&mut conn, replication, id, receiver, &output).await
+    };
+    let result = tokio::select! {
+        result = result => result,
+        () = &mut killed => Ok(()),
+    };
+
+    replication.unregister_replica(id);
+    result
+}
+
+/// 把命令流转发给副本并记录副本的确认，直到任意一方断开
+async fn stream_commands<S: AsyncRead + AsyncWrite + Unpin>(
+    conn: &mut Connection<S>,
+    replication: &Replication,
+    id: u64,
+    mut receiver: CommandStream,
+    output: &OutputBuffer,
+) -> io::Result<()> {
+    loop {
+        tokio::select! {
+            bytes = receiver.recv() => match bytes {
+                Some(bytes) => {
+                    conn.write_bytes(&bytes).await?;
+                    output.drained(bytes.len());
+                }
+                // 本节点成为副本时断开所有副本
+                None => return Ok(()),
+            },
+            frame = conn.read_frame() => {
+                let Some(frame) = frame? else { return Ok(()) };
+                let args = frame.into_args().unwrap_or_default();
+                if let [replconf, ack, offset] = args.as_slice()
+                    && replconf.eq_ignore_ascii_case("replconf")
+                    && ack.eq_ignore_ascii_case("ack")
+                    && let Ok(offset) = offset.parse()
+                {
+                    replication.ack(id, offset);
+                }
+            }
+        }
+    }
+}
+
+/// 副本的后台任务：同步失败或连接断开后等待片刻重连
+async fn replicate<S: Storage>(db: Db<S>, host: String, port: u16) {
+    loop {
+        // 错误只影响本次同步，状态通过 ROLE 可见
+        let _ = sync_with_master(&db, &host, port).await;
+        db.replication().set_link_state(LinkState::Connect);
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// 连接主节点完成一次全量同步，然后持续应用命令流直到连接断开
+async fn sync_with_master<S: Storage>(db: &Db<S>, host: &str, port: u16) -> io::Result<()> {
+    let replication = db.replication();
+    replication.set_link_state(LinkState::Connecting);
+    let mut conn = Connection::new(TcpStream::connect((host, port)).await?);
+
+    let listening_port = replication.state.lock().unwrap().listening_port;
+    if let Some(listening_port) = listening_port {
+        let args = ["replconf", "listening-port", &listening_port.to_string()];
+        request(&mut conn, &args).await?;
+    }
+
+    // 带上已应用的位置，主节点据此决定部分重同步还是全量同步
+    let (replid, offset) = replication.position();
+    let reply = request(&mut conn, &["psync", &replid, &offset.to_string()]).await?;
+    match parse_psync_reply(&reply) {
+        Some(PsyncReply::Continue) => {}
+        Some(PsyncReply::FullResync(replid, offset)) => {
+            replication.set_link_state(LinkState::Sync);
+            let payload = conn.read_payload().await?;
+            load_rdb(db, &payload).await?;
+
+            let mut state = replication.state.lock().unwrap();
+            state.replid = replid;
+            state.offset = offset;
+            state.applied_db = 0;
+        }
+        None => return Err(io::Error::new(io::ErrorKind::InvalidData, "unexpected PSYNC reply")),
+    }
+    replication.set_link_state(LinkState::Connected);
+
+    let applied_db = replication.state.lock().unwrap().applied_db;
+    let mut selected = db.select(applied_db).map_err(|e| io::Error::other(e.to_string()))?;
+    let mut ack_interval = tokio::time::interval(ACK_INTERVAL);
+    loop {
+        let frame = tokio::select! {
+            frame = conn.read_frame() => frame?,
+            _ = ack_interval.tick() => {
+                send_ack(&mut conn, replication).await?;
+                continue;
+            }
+        };
+        let Some(frame) = frame else { return Ok(()) };
+
+        let len = frame.encode().len() as u64;
+        let args = frame.into_args().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let parts: Vec<_> = args.iter().map(String::as_str).collect();
+        match Command::from_args(&parts) {
+            Ok(Command::ReplConf(args)) if args[0].eq_ignore_ascii_case("getack") => {
+                send_ack(&mut conn, replication).await?;
+            }
+            Ok(Command::Select(index)) => {
+                selected = db.select(index).map_err(|e| io::Error::other(e.to_string()))?;
+                replication.state.lock().unwrap().applied_db = index;
+                replication.advance(len);
+            }
+            Ok(command) => {
+                handler::execute(&selected, command).await;
+                replication.advance(len);
+            }
+            // 主节点只传播执行成功的命令，无法解析时跳过，但仍计入偏移量
+            Err(_) => replication.advance(len),
+        }
+    }
+}
+
+/// 向主节点确认已应用的偏移量
+async fn send_ack(conn: &mut Connection, replication: &Replication) -> io::Result<()> {
+    let offset = replication.position().1.to_string();
+    conn.write_frame(&Frame::from(to_args(&["replconf", "ack", &offset]))).await
+}
+
+/// 发送一条命令并读取回复，错误回复转换为 `io::Error`
+pub(crate) async fn request(conn: &mut Connection, args: &[&str]) -> io::Result<Frame> {
+    conn.write_frame(&Frame::from(to_args(args))).await?;
+
+    match conn.read_frame().await? {
+        Some(Frame::Error(e)) => Err(io::Error::other(e)),
+        Some(frame) => Ok(frame),
+        None => Err(io::ErrorKind::UnexpectedEof.into()),
+    }
+}
+
+/// 主节点对 PSYNC 的回复
+#[derive(Debug, PartialEq)]
+enum PsyncReply {
+    /// `+FULLRESYNC <replid> <offset>`，随后发送 RDB 快照
+    FullResync(String, u64),
+    /// `+CONTINUE <replid>`，随后直接补发缺失的命令流
+    Continue,
+}
+
+fn parse_psync_reply(reply: &Frame) -> Option<PsyncReply> {
+    let Frame::Simple(line) = reply else { return None };
+    match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+        ["FULLRESYNC", replid, offset] => {
+            Some(PsyncReply::FullResync(replid.to_string(), offset.parse().ok()?))
+        }
+        ["CONTINUE", ..] => Some(PsyncReply::Continue),
+        _ => None,
+    }
+}
+
+fn to_args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// 生成 40 位十六进制的随机 id，用作复制 id 与哨兵 id
+pub(crate) fn new_replid() -> String {
+    let mut rng = Rng::from_entropy();
+    (0..5).map(|_| format!("{:08x}", rng.next_u64() as u32)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler::process_command, server};
+
+    async fn wait_for(mut condition: impl AsyncFnMut() -> bool) {
+        for _ in 0..200 {
+            if condition().await {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("condition not reached in time");
+    }
+
+    async fn start_server(db: &Db) -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(server::run(listener, db.clone()));
+        port
+    }
+
+    #[test]
+    fn test_parse_psync_reply() {
+        let reply = Frame::Simple("FULLRESYNC abc 42".into());
+
+        assert_eq!(parse_psync_reply(&reply), Some(PsyncReply::FullResync("abc".into(), 42)));
+        assert_eq!(
+            parse_psync_reply(&Frame::Simple("CONTINUE abc".into())),
+            Some(PsyncReply::Continue)
+        );
+        assert_eq!(parse_psync_reply(&Frame::Simple("OK".into())), None);
+        assert_eq!(new_replid().len(), 40);
+    }
+
+    #[tokio::test]
+    async fn test_partial_resync() {
+        let master = Db::new();
+        let port = start_server(&master).await;
+        let (replid, offset) = master.replication().position();
+        process_command(&master, "set a 1").await;
+        process_command(&master, "sadd s x").await;
+
+        let mut conn = Connection::new(TcpStream::connect(("127.0.0.1", port)).await.unwrap());
+        let reply = request(&mut conn, &["psync", &replid, &offset.to_string()]).await.unwrap();
+        assert_eq!(reply, Frame::Simple(format!("CONTINUE {replid}")));
+        // 命令流以选中数据库的 SELECT 开始
+        assert_eq!(conn.read_frame().await.unwrap().unwrap().to_string(), "1) select\n2) 0");
+        assert_eq!(conn.read_frame().await.unwrap().unwrap().to_string(), "1) set\n2) a\n3) 1");
+        assert_eq!(conn.read_frame().await.unwrap().unwrap().to_string(), "1) sadd\n2) s\n3) x");
+
+        // 补发之后继续接收新的命令
+        process_command(&master, "set b 2").await;
+        assert_eq!(conn.read_frame().await.unwrap().unwrap().to_string(), "1) set\n2) b\n3) 2");
+
+        // 偏移量已被积压缓冲区丢弃或复制 id 不一致时退回全量同步
+        master.replication().set_backlog_size(16);
+        for (replid, offset) in [(replid.as_str(), "0"), ("unknown", "0")] {
+            let mut conn = Connection::new(TcpStream::connect(("127.0.0.1", port)).await.unwrap());
+            let reply = request(&mut conn, &["psync", replid, offset]).await.unwrap();
+            assert!(matches!(parse_psync_reply(&reply), Some(PsyncReply::FullResync(..))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_acks() {
+        let master = Db::new();
+        let master_port = start_server(&master).await;
+        assert_eq!(process_command(&master, "wait 1 50").await, "(integer) 0");
+
+        let replica = Db::new();
+        process_command(&replica, &format!("replicaof 127.0.0.1 {master_port}")).await;
+        wait_for(async || master.replication().replica_count() == 1).await;
+
+        process_command(&master, "set a 1").await;
+        assert_eq!(process_command(&master, "wait 1 0").await, "(integer) 1");
+        assert_eq!(process_command(&replica, "get a").await, "1");
+        assert_eq!(process_command(&master, "wait 2 50").await, "(integer) 1");
+
+        let offset = master.replication().position().1;
+        assert!(process_command(&master, "role").await.ends_with(&format!("3) {offset}")));
+        assert!(process_command(&replica, "wait 1 0").await.starts_with("ERR"));
+    }
+
+    #[tokio::test]
+    async fn test_full_sync_and_stream() {
+        let master = Db::new();
+        process_command(&master, "set before sync").await;
+        process_command(&master, "zadd z 1 a").await;
+        let master_port = start_server(&master).await;
+
+        let replica = Db::new();
+        start_server(&replica).await;
+        process_command(&replica, &format!("replicaof 127.0.0.1 {master_port}")).await;
+
+        wait_for(async || process_command(&replica, "get before").await == "sync").await;
+        assert_eq!(process_command(&replica, "zscore z a").await, "1");
+        assert_eq!(master.replication().replica_count(), 1);
+
+        process_command(&master, "set after sync").await;
+        process_command(&master.select(2).unwrap(), "set other db").await;
+        process_command(&master, "sadd s x").await;
+        wait_for(async || process_command(&replica, "smembers s").await == "1) x").await;
+        assert_eq!(process_command(&replica.select(2).unwrap(), "get other").await, "db");
+        assert_eq!(process_command(&replica, "get other").await, "(nil)");
+        assert_eq!(process_command(&replica, "get after").await, "sync");
+        assert_eq!(replica.replication().position(), master.replication().position());
+
+        // 副本拒绝客户端写入
+        let mut session = crate::session::Session::new(replica.clone());
+        let reply = session.execute(Command::parse("set x 1").unwrap()).await;
+        assert!(reply[0].to_string().starts_with("READONLY"));
+        process_command(&replica, "config set replica-read-only no").await;
+        assert_eq!(session.execute(Command::parse("set x 1").unwrap()).await[0].to_string(), "OK");
+        assert!(process_command(&replica, "info replication").await.contains("slave_read_only:0"));
+
+        let role = process_command(&replica, "role").await;
+        assert!(role.starts_with("1) slave\n2) 127.0.0.1\n"), "{role}");
+        assert!(role.contains("4) connected"), "{role}");
+
+        process_command(&replica, "replicaof no one").await;
+        assert!(process_command(&replica, "role").await.starts_with("1) master"));
+        assert_eq!(process_command(&replica, "get after").await, "sync");
+    }
+}
@@ -0,0 +1,91 @@
+//! 复制积压缓冲区
+//!
+//! 以环形缓冲区保存命令流中最近的若干字节。副本短暂断开后带着自己的偏移量重连，
+//! 只要该偏移量之后的数据仍在缓冲区中，主节点就可以只补发缺失的部分，而不必重新全量同步。
+
+use std::collections::VecDeque;
+
+/// 默认的积压缓冲区大小（1 MiB）
+pub const DEFAULT_BACKLOG_SIZE: usize = 1024 * 1024;
+
+/// 复制积压缓冲区
+pub(crate) struct Backlog {
+    buf: VecDeque<u8>,
+    capacity: usize,
+    /// 缓冲区第一个字节在命令流中的偏移量
+    start: u64,
+}
+
+impl Backlog {
+    /// 创建一个从偏移量 `start` 开始记录的空缓冲区
+    pub fn new(capacity: usize, start: u64) -> Self {
+        Self { buf: VecDeque::new(), capacity, start }
+    }
+
+    /// 调整容量，缩小时丢弃最旧的数据
+    pub fn resize(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.trim();
+    }
+
+    /// 清空缓冲区，之后从偏移量 `start` 开始记录
+    pub fn reset(&mut self, start: u64) {
+        self.buf.clear();
+        self.start = start;
+    }
+
+    /// 追加命令流中的字节，超出容量时丢弃最旧的数据
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend(bytes);
+        self.trim();
+    }
+
+    /// 返回从偏移量 `offset` 到末尾的数据；这段数据已经被丢弃或尚未产生时返回 `None`
+    pub fn range_from(&self, offset: u64) -> Option<Vec<u8>> {
+        let end = self.start + self.buf.len() as u64;
+        if offset < self.start || offset > end {
+            return None;
+        }
+        Some(self.buf.range((offset - self.start) as usize..).copied().collect())
+    }
+
+    fn trim(&mut self) {
+        let excess = self.buf.len().saturating_sub(self.capacity);
+        self.buf.drain(..excess);
+        self.start += excess as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Backlog;
+
+    #[test]
+    fn test_range_from() {
+        let mut backlog = Backlog::new(8, 100);
+        backlog.push(b"abcd");
+
+        assert_eq!(backlog.range_from(100), Some(b"abcd".to_vec()));
+        assert_eq!(backlog.range_from(102), Some(b"cd".to_vec()));
+        assert_eq!(backlog.range_from(104), Some(Vec::new()));
+        assert_eq!(backlog.range_from(99), None);
+        assert_eq!(backlog.range_from(105), None);
+    }
+
+    #[test]
+    fn test_wraps_around() {
+        let mut backlog = Backlog::new(4, 0);
+        backlog.push(b"abc");
+        backlog.push(b"def");
+
+        assert_eq!(backlog.range_from(1), None);
+        assert_eq!(backlog.range_from(2), Some(b"cdef".to_vec()));
+
+        backlog.resize(2);
+        assert_eq!(backlog.range_from(3), None);
+        assert_eq!(backlog.range_from(4), Some(b"ef".to_vec()));
+
+        backlog.reset(10);
+        assert_eq!(backlog.range_from(10), Some(Vec::new()));
+    }
+}
@@ -0,0 +1,850 @@
+//! 哨兵：监控主节点，在主节点故障时自动把副本提升为新的主节点
+//!
+//! 任何实例执行 `SENTINEL MONITOR <name> <ip> <port> <quorum>` 后即开始监控该主节点，
+//! 为它启动一个后台任务，每 [`TICK`] 执行一轮：
+//! - 每隔 `min(down-after-milliseconds, 1s)` PING 主节点与已知副本，超过 `down-after-milliseconds`
+//!   没有收到有效回复即为主观下线（`s_down`）
+//! - 每秒向主节点与副本发送 ROLE：从主节点的回复中发现副本，从副本的回复中取得复制偏移量与角色
+//! - 每 2 秒向主节点与副本的 `__sentinel__:hello` 频道发布自己的地址、id 与当前配置，
+//!   同时订阅这些频道，从而发现监控同一主节点的其他哨兵，并采用纪元更新的配置
+//! - 主观下线时用 `SENTINEL IS-MASTER-DOWN-BY-ADDR` 询问其他哨兵，连同自己达到 `quorum`
+//!   个哨兵认为下线即为客观下线（`o_down`）
+//!
+//! 客观下线后开始故障转移：递增当前纪元并请求其他哨兵投票，每个哨兵在一个纪元内只投给第一个
+//! 请求者；得票既达到 `quorum` 又超过哨兵总数一半的哨兵成为领导者。领导者选出复制偏移量最大的
+//! 在线副本执行 `REPLICAOF NO ONE`，再让其余副本复制新的主节点；旧主节点恢复后同样被改为副本。
+//! 没有选出领导者或提升失败时，等待 `failover-timeout` 后重试。`SENTINEL FAILOVER` 跳过下线判断
+//! 与选举，立即执行一次故障转移。
+//!
+//! 主节点地址变化时在本实例的 [`SWITCH_MASTER_CHANNEL`] 频道发布
+//! `<name> <old-ip> <old-port> <new-ip> <new-port>`，客户端订阅它即可得知新的地址，
+//! 也可以随时用 `SENTINEL GET-MASTER-ADDR-BY-NAME` 查询。下线状态的变化同样以
+//! `+sdown` / `-sdown` / `+odown` / `-odown` 频道发布 `master <name> <ip> <port>`。
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io, mem,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use tokio::{
+    net::TcpStream,
+    sync::mpsc::{self, UnboundedSender},
+    task::JoinHandle,
+    time::Instant,
+};
+
+use crate::{
+    connection::Connection,
+    db::{Db, DbError, Storage},
+    frame::Frame,
+    replication::{new_replid, request},
+};
+
+/// 监控任务每轮的间隔
+pub const TICK: Duration = Duration::from_millis(100);
+/// PING 的最长间隔，`down-after-milliseconds` 更短时按它的间隔 PING
+const PING_PERIOD: Duration = Duration::from_secs(1);
+/// 发送 ROLE 的间隔
+const INFO_PERIOD: Duration = Duration::from_secs(1);
+/// 发布 hello 消息的间隔
+const HELLO_PERIOD: Duration = Duration::from_secs(2);
+/// 向实例发送一条命令的超时时间
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(500);
+/// hello 订阅断开后的重连间隔
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// 主节点与副本上交换哨兵信息的频道
+const HELLO_CHANNEL: &str = "__sentinel__:hello";
+/// 主节点地址变化时发布通知的频道
+pub const SWITCH_MASTER_CHANNEL: &str = "+switch-master";
+
+const DEFAULT_DOWN_AFTER: Duration = Duration::from_secs(30);
+const DEFAULT_FAILOVER_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// 实例地址
+type Addr = (String, u16);
+
+/// 哨兵状态：监控的主节点及其副本、其他哨兵与投票
+pub struct Sentinel {
+    /// 本哨兵的 id，在 hello 消息与投票中标识自己
+    myid: String,
+    state: Mutex<State>,
+}
+
+impl Default for Sentinel {
+    fn default() -> Self {
+        Self { myid: new_replid(), state: Mutex::default() }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// 当前纪元：发起故障转移时递增，看到更大的纪元时跟进
+    current_epoch: u64,
+    masters: BTreeMap<String, Master>,
+}
+
+/// 一个被监控的主节点
+struct Master {
+    addr: Addr,
+    quorum: usize,
+    down_after: Duration,
+    failover_timeout: Duration,
+    /// 当前配置（主节点地址）产生时的纪元
+    config_epoch: u64,
+    /// 最近一次收到有效 PING 回复的时间
+    last_ok: Instant,
+    sdown: bool,
+    odown: bool,
+    replicas: BTreeMap<Addr, Replica>,
+    /// 监控同一主节点的其他哨兵：id -> 地址
+    sentinels: BTreeMap<String, Addr>,
+    /// 本哨兵投出的票：纪元与领导者 id
+    vote: Option<(u64, String)>,
+    /// 最近一次发起或参与故障转移的时间，重试前至少间隔 `failover_timeout`
+    failover_started: Option<Instant>,
+    /// SENTINEL FAILOVER 请求的故障转移，尚未执行
+    forced: bool,
+    task: JoinHandle<()>,
+}
+
+impl Drop for Master {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct Replica {
+    /// 最近一次收到有效 PING 回复的时间
+    last_ok: Instant,
+    /// ROLE 报告的复制偏移量
+    offset: u64,
+    /// ROLE 报告自己是主节点：旧主节点恢复后尚未改为副本
+    is_master: bool,
+}
+
+impl Master {
+    fn flags(&self) -> String {
+        let mut flags = "master".to_string();
+        for (set, flag) in [(self.sdown, ",s_down"), (self.odown, ",o_down")] {
+            if set {
+                flags.push_str(flag);
+            }
+        }
+        flags
+    }
+
+    /// 可以提升为主节点的副本：在线、不是主节点，复制偏移量最大，相同时取地址最小的
+    fn best_replica(&self) -> Option<Addr> {
+        self.replicas
+            .iter()
+            .filter(|(_, replica)| {
+                !replica.is_master && replica.last_ok.elapsed() <= self.down_after
+            })
+            .max_by_key(|(addr, replica)| (replica.offset, std::cmp::Reverse(*addr)))
+            .map(|(addr, _)| addr.clone())
+    }
+
+    /// 把主节点切换到 `addr`：原来的主节点留作副本，恢复后被改为复制新的主节点
+    fn switch_to(&mut self, addr: Addr, epoch: u64) -> Addr {
+        let old = mem::replace(&mut self.addr, addr);
+        self.replicas.remove(&self.addr);
+        let replica = Replica { last_ok: self.last_ok, offset: 0, is_master: true };
+        self.replicas.insert(old.clone(), replica);
+        self.config_epoch = epoch;
+        self.last_ok = Instant::now();
+        self.sdown = false;
+        self.odown = false;
+        self.forced = false;
+        old
+    }
+}
+
+impl Sentinel {
+    /// 本哨兵的 id
+    pub fn myid(&self) -> &str {
+        &self.myid
+    }
+
+    /// 主节点的当前地址
+    pub fn master_addr(&self, name: &str) -> Option<(String, u16)> {
+        self.state().masters.get(name).map(|master| master.addr.clone())
+    }
+
+    /// SENTINEL REMOVE：停止监控主节点
+    pub fn remove(&self, name: &str) -> Result<(), DbError> {
+        self.state().masters.remove(name).map(drop).ok_or(DbError::NoSuchMaster)
+    }
+
+    /// SENTINEL SET：修改监控参数，全部校验通过后才生效
+    pub fn set(&self, name: &str, options: &[(String, String)]) -> Result<(), DbError> {
+        let mut state = self.state();
+        let master = state.masters.get_mut(name).ok_or(DbError::NoSuchMaster)?;
+        let (mut quorum, mut down_after, mut failover_timeout) =
+            (master.quorum, master.down_after, master.failover_timeout);
+        for (option, value) in options {
+            let invalid = || DbError::InvalidSentinelOption(option.clone());
+            let n: u64 = value.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?;
+            match option.to_ascii_lowercase().as_str() {
+                "quorum" => quorum = n as usize,
+                "down-after-milliseconds" => down_after = Duration::from_millis(n),
+                "failover-timeout" => failover_timeout = Duration::from_millis(n),
+                _ => return Err(invalid()),
+            }
+        }
+        (master.quorum, master.down_after, master.failover_timeout) =
+            (quorum, down_after, failover_timeout);
+        Ok(())
+    }
+
+    /// SENTINEL FAILOVER：不经下线判断与选举，尽快执行一次故障转移
+    pub fn failover(&self, name: &str) -> Result<(), DbError> {
+        let mut state = self.state();
+        let master = state.masters.get_mut(name).ok_or(DbError::NoSuchMaster)?;
+        if master.forced {
+            return Err(DbError::FailoverInProgress);
+        }
+        if master.best_replica().is_none() {
+            return Err(DbError::NoGoodReplica);
+        }
+        master.forced = true;
+        master.failover_started = None;
+        Ok(())
+    }
+
+    /// SENTINEL MASTERS：全部主节点的状态
+    pub fn masters(&self) -> Frame {
+        let state = self.state();
+        Frame::Array(state.masters.iter().map(|(name, master)| master_info(name, master)).collect())
+    }
+
+    /// SENTINEL MASTER：一个主节点的状态
+    pub fn master(&self, name: &str) -> Result<Frame, DbError> {
+        let state = self.state();
+        let master = state.masters.get(name).ok_or(DbError::NoSuchMaster)?;
+        Ok(master_info(name, master))
+    }
+
+    /// SENTINEL REPLICAS：主节点的副本
+    pub fn replicas(&self, name: &str) -> Result<Frame, DbError> {
+        let state = self.state();
+        let master = state.masters.get(name).ok_or(DbError::NoSuchMaster)?;
+        let replicas = master.replicas.iter().map(|((ip, port), replica)| {
+            let mut flags = if replica.is_master { "master" } else { "slave" }.to_string();
+            if replica.last_ok.elapsed() > master.down_after {
+                flags.push_str(",s_down");
+            }
+            info_map([
+                ("name", format!("{ip}:{port}")),
+                ("ip", ip.clone()),
+                ("port", port.to_string()),
+                ("flags", flags),
+                ("slave-repl-offset", replica.offset.to_string()),
+            ])
+        });
+        Ok(Frame::Array(replicas.collect()))
+    }
+
+    /// SENTINEL SENTINELS：监控同一主节点的其他哨兵
+    pub fn sentinels(&self, name: &str) -> Result<Frame, DbError> {
+        let state = self.state();
+        let master = state.masters.get(name).ok_or(DbError::NoSuchMaster)?;
+        let sentinels = master.sentinels.iter().map(|(runid, (ip, port))| {
+            info_map([
+                ("name", runid.clone()),
+                ("ip", ip.clone()),
+                ("port", port.to_string()),
+                ("runid", runid.clone()),
+            ])
+        });
+        Ok(Frame::Array(sentinels.collect()))
+    }
+
+    /// SENTINEL IS-MASTER-DOWN-BY-ADDR：回复本哨兵是否认为该地址的主节点主观下线
+    ///
+    /// `runid` 不为 `*` 时同时请求投票：本纪元还没有投过票时投给它。
+    /// 回复为 `[是否下线, 领导者 id, 领导者纪元]`，没有投票时领导者为 `*`。
+    pub fn is_master_down_by_addr(&self, host: &str, port: u16, epoch: u64, runid: &str) -> Frame {
+        let mut state = self.state();
+        let State { current_epoch, masters } = &mut *state;
+        let Some(master) = masters.values_mut().find(|m| m.addr.0 == host && m.addr.1 == port)
+        else {
+            return down_reply(false, None);
+        };
+        if runid != "*" {
+            *current_epoch = (*current_epoch).max(epoch);
+            if master.vote.as_ref().is_none_or(|(voted, _)| *voted < epoch) {
+                master.vote = Some((epoch, runid.to_string()));
+                // 投票后推迟自己发起故障转移，避免与领导者竞争
+                master.failover_started = Some(Instant::now());
+            }
+        }
+        let vote = if runid == "*" { None } else { master.vote.clone() };
+        down_reply(master.sdown, vote)
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    fn with_master<T>(&self, name: &str, f: impl FnOnce(&mut Master) -> T) -> Option<T> {
+        self.state().masters.get_mut(name).map(f)
+    }
+
+    /// 记录实例对 PING 的有效回复
+    fn instance_ok(&self, name: &str, addr: &Addr) {
+        self.with_master(name, |master| {
+            if master.addr == *addr {
+                master.last_ok = Instant::now();
+            } else if let Some(replica) = master.replicas.get_mut(addr) {
+                replica.last_ok = Instant::now();
+            }
+        });
+    }
+
+    /// 按实例的 ROLE 回复更新副本列表、复制偏移量与角色
+    fn update_role(&self, name: &str, addr: &Addr, role: Frame) {
+        let Frame::Array(items) = role else { return };
+        self.with_master(name, |master| match items.as_slice() {
+            [Frame::Bulk(role), _, Frame::Array(replicas)] if role == "master" => {
+                if master.addr == *addr {
+                    for replica in replicas {
+                        if let Frame::Array(fields) = replica
+                            && let [Frame::Bulk(ip), Frame::Bulk(port), ..] = fields.as_slice()
+                            && let Ok(port) = port.parse()
+                        {
+                            master.replicas.entry((ip.clone(), port)).or_insert(Replica {
+                                last_ok: Instant::now(),
+                                offset: 0,
+                                is_master: false,
+                            });
+                        }
+                    }
+                } else if let Some(replica) = master.replicas.get_mut(addr) {
+                    replica.is_master = true;
+                }
+            }
+            [Frame::Bulk(role), .., Frame::Integer(offset)] if role == "slave" => {
+                if let Some(replica) = master.replicas.get_mut(addr) {
+                    replica.offset = *offset as u64;
+                    replica.is_master = false;
+                }
+            }
+            _ => {}
+        });
+    }
+
+    /// 本哨兵关于主节点的 hello 消息：
+    /// `<ip>,<port>,<runid>,<current-epoch>,<name>,<master-ip>,<master-port>,<config-epoch>`
+    fn hello(&self, name: &str, ip: &str, port: u16) -> Option<String> {
+        let state = self.state();
+        let master = state.masters.get(name)?;
+        let (master_ip, master_port) = &master.addr;
+        Some(format!(
+            "{ip},{port},{},{},{name},{master_ip},{master_port},{}",
+            self.myid, state.current_epoch, master.config_epoch
+        ))
+    }
+
+    /// 处理其他哨兵的 hello 消息：记录哨兵，采用纪元更新的主节点地址，返回需要发布的切换通知
+    fn process_hello(&self, hello: &str) -> Option<String> {
+        let [ip, port, runid, epoch, name, master_ip, master_port, config_epoch] =
+            <[&str; 8]>::try_from(hello.split(',').collect::<Vec<_>>()).ok()?;
+        let (port, epoch, master_port, config_epoch): (u16, u64, u16, u64) = (
+            port.parse().ok()?,
+            epoch.parse().ok()?,
+            master_port.parse().ok()?,
+            config_epoch.parse().ok()?,
+        );
+        if runid == self.myid {
+            return None;
+        }
+
+        let mut state = self.state();
+        state.current_epoch = state.current_epoch.max(epoch);
+        let master = state.masters.get_mut(name)?;
+        // 同一地址重启后的哨兵使用新的 id
+        master.sentinels.retain(|id, addr| id == runid || addr.0 != ip || addr.1 != port);
+        master.sentinels.insert(runid.to_string(), (ip.to_string(), port));
+
+        let addr = (master_ip.to_string(), master_port);
+        if config_epoch <= master.config_epoch || addr == master.addr {
+            return None;
+        }
+        let old = master.switch_to(addr, config_epoch);
+        Some(switch_message(name, &old, &master.addr))
+    }
+}
+
+impl<S: Storage> Db<S> {
+    /// SENTINEL MONITOR：开始监控主节点
+    pub fn sentinel_monitor(
+        &self,
+        name: String,
+        host: String,
+        port: u16,
+        quorum: usize,
+    ) -> Result<(), DbError> {
+        if quorum == 0 {
+            return Err(DbError::InvalidQuorum);
+        }
+        // 持有锁时启动任务：任务的第一轮要等到主节点登记后才能读到它
+        let mut state = self.sentinel().state();
+        if state.masters.contains_key(&name) {
+            return Err(DbError::DuplicateMaster);
+        }
+        let task = tokio::spawn(monitor(self.clone(), name.clone()));
+        let master = Master {
+            addr: (host, port),
+            quorum,
+            down_after: DEFAULT_DOWN_AFTER,
+            failover_timeout: DEFAULT_FAILOVER_TIMEOUT,
+            config_epoch: 0,
+            last_ok: Instant::now(),
+            sdown: false,
+            odown: false,
+            replicas: BTreeMap::new(),
+            sentinels: BTreeMap::new(),
+            vote: None,
+            failover_started: None,
+            forced: false,
+            task,
+        };
+        state.masters.insert(name, master);
+        Ok(())
+    }
+
+    /// 在本实例上发布哨兵事件
+    fn sentinel_event(&self, channel: &str, message: String) {
+        tracing::info!(event = channel, "{message}");
+        self.pubsub().publish(channel, &message);
+    }
+}
+
+/// 与一个实例的连接：发送命令的连接断开后按需重连；监控的实例还有一个订阅 hello 频道的后台任务
+struct Link {
+    conn: Option<Connection>,
+    /// 连接的本端 IP，在 hello 消息中作为自己的地址
+    local_ip: Option<String>,
+    hello: Option<JoinHandle<()>>,
+}
+
+impl Link {
+    fn new(addr: &Addr, hellos: Option<UnboundedSender<String>>) -> Self {
+        let hello = hellos.map(|hellos| tokio::spawn(listen_hellos(addr.clone(), hellos)));
+        Self { conn: None, local_ip: None, hello }
+    }
+
+    /// 发送一条命令并读取回复；超时或出错时断开连接，下次重新连接
+    async fn request(&mut self, addr: &Addr, args: &[&str]) -> io::Result<Frame> {
+        let exchange = async {
+            if self.conn.is_none() {
+                let stream = TcpStream::connect((addr.0.as_str(), addr.1)).await?;
+                self.local_ip = Some(stream.local_addr()?.ip().to_string());
+                self.conn = Some(Connection::new(stream));
+            }
+            request(self.conn.as_mut().expect("connected above"), args).await
+        };
+        let result = tokio::time::timeout(REQUEST_TIMEOUT, exchange)
+            .await
+            .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()));
+        if result.is_err() {
+            self.conn = None;
+        }
+        result
+    }
+}
+
+impl Drop for Link {
+    fn drop(&mut self) {
+        if let Some(hello) = &self.hello {
+            hello.abort();
+        }
+    }
+}
+
+/// 订阅实例的 hello 频道，把收到的消息转发给监控任务；连接断开后等待片刻重连
+async fn listen_hellos(addr: Addr, hellos: UnboundedSender<String>) {
+    loop {
+        let _ = async {
+            let mut conn = Connection::new(TcpStream::connect((addr.0.as_str(), addr.1)).await?);
+            let subscribe = vec!["subscribe".to_string(), HELLO_CHANNEL.to_string()];
+            conn.write_frame(&Frame::from(subscribe)).await?;
+            while let Some(frame) = conn.read_frame().await? {
+                if let Ok(args) = frame.into_args()
+                    && let [kind, _, payload] = args.as_slice()
+                    && kind == "message"
+                {
+                    let _ = hellos.send(payload.clone());
+                }
+            }
+            io::Result::Ok(())
+        }
+        .await;
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// 一个主节点的监控任务，SENTINEL REMOVE 时被终止
+async fn monitor<S: Storage>(db: Db<S>, name: String) {
+    let sentinel = db.sentinel();
+    let (hello_sender, mut hello_receiver) = mpsc::unbounded_channel();
+    let mut links: HashMap<Addr, Link> = HashMap::new();
+    let mut peer_links: HashMap<Addr, Link> = HashMap::new();
+    let (mut last_ping, mut last_info, mut last_hello) = (None, None, None);
+    let due =
+        |last: Option<Instant>, period| last.is_none_or(|last: Instant| last.elapsed() >= period);
+
+    let mut interval = tokio::time::interval(TICK);
+    loop {
+        interval.tick().await;
+        let Some((addr, replicas, down_after)) = sentinel.with_master(&name, |master| {
+            (
+                master.addr.clone(),
+                master.replicas.keys().cloned().collect::<Vec<_>>(),
+                master.down_after,
+            )
+        }) else {
+            return;
+        };
+        let instances: Vec<Addr> = [addr.clone()].into_iter().chain(replicas).collect();
+        links.retain(|addr, _| instances.contains(addr));
+        for addr in &instances {
+            links
+                .entry(addr.clone())
+                .or_insert_with(|| Link::new(addr, Some(hello_sender.clone())));
+        }
+
+        if due(last_ping, down_after.min(PING_PERIOD)) {
+            last_ping = Some(Instant::now());
+            for (addr, link) in &mut links {
+                if matches!(link.request(addr, &["ping"]).await, Ok(Frame::Simple(pong)) if pong == "PONG")
+                {
+                    sentinel.instance_ok(&name, addr);
+                }
+            }
+        }
+        if due(last_info, INFO_PERIOD) {
+            last_info = Some(Instant::now());
+            for (addr, link) in &mut links {
+                if let Ok(role) = link.request(addr, &["role"]).await {
+                    sentinel.update_role(&name, addr, role);
+                }
+            }
+            reconfigure_stale_masters(sentinel, &name, &mut links).await;
+        }
+        if due(last_hello, HELLO_PERIOD)
+            && let Some(port) = db.replication().listening_port()
+        {
+            last_hello = Some(Instant::now());
+            for (addr, link) in &mut links {
+                let Some(ip) = link.local_ip.clone() else { continue };
+                if let Some(hello) = sentinel.hello(&name, &ip, port) {
+                    let _ = link.request(addr, &["publish", HELLO_CHANNEL, &hello]).await;
+                }
+            }
+        }
+        while let Ok(hello) = hello_receiver.try_recv() {
+            if let Some(message) = sentinel.process_hello(&hello) {
+                db.sentinel_event(SWITCH_MASTER_CHANNEL, message);
+            }
+        }
+
+        check_down(&db, &name, &mut peer_links).await;
+        try_failover(&db, &name, &mut links, &mut peer_links).await;
+    }
+}
+
+/// 把报告自己是主节点的副本（通常是恢复后的旧主节点）改为复制当前的主节点
+///
+/// 当前主节点下线时不做修改：报告为主节点的副本可能刚被其他哨兵提升，新的配置稍后随 hello 到达。
+async fn reconfigure_stale_masters(
+    sentinel: &Sentinel,
+    name: &str,
+    links: &mut HashMap<Addr, Link>,
+) {
+    let Some((addr, stale)) = sentinel.with_master(name, |master| {
+        let stale: Vec<Addr> = master
+            .replicas
+            .iter()
+            .filter(|(_, replica)| {
+                replica.is_master && replica.last_ok.elapsed() <= master.down_after
+            })
+            .map(|(addr, _)| addr.clone())
+            .collect();
+        (master.addr.clone(), if master.sdown { Vec::new() } else { stale })
+    }) else {
+        return;
+    };
+    for replica in stale {
+        if let Some(link) = links.get_mut(&replica) {
+            let _ = link.request(&replica, &["replicaof", &addr.0, &addr.1.to_string()]).await;
+        }
+    }
+}
+
+/// 判断主观下线，主观下线时询问其他哨兵判断客观下线，状态变化时发布事件
+async fn check_down<S: Storage>(db: &Db<S>, name: &str, peer_links: &mut HashMap<Addr, Link>) {
+    let sentinel = db.sentinel();
+    let Some((addr, sdown, changed, peers, quorum)) = sentinel.with_master(name, |master| {
+        let sdown = master.last_ok.elapsed() > master.down_after;
+        let changed = mem::replace(&mut master.sdown, sdown) != sdown;
+        let peers: Vec<Addr> = master.sentinels.values().cloned().collect();
+        (master.addr.clone(), sdown, changed, peers, master.quorum)
+    }) else {
+        return;
+    };
+    let event = format!("master {name} {} {}", addr.0, addr.1);
+    if changed {
+        db.sentinel_event(if sdown { "+sdown" } else { "-sdown" }, event.clone());
+    }
+
+    let mut odown = false;
+    if sdown {
+        let port = addr.1.to_string();
+        let mut agreed = 1;
+        for peer in peers {
+            let link = peer_links.entry(peer.clone()).or_insert_with(|| Link::new(&peer, None));
+            let args = ["sentinel", "is-master-down-by-addr", &addr.0, &port, "0", "*"];
+            if let Ok(Frame::Array(reply)) = link.request(&peer, &args).await
+                && let Some(Frame::Integer(1)) = reply.first()
+            {
+                agreed += 1;
+            }
+        }
+        odown = agreed >= quorum;
+    }
+    let changed = sentinel.with_master(name, |master| {
+        master.addr == addr && mem::replace(&mut master.odown, odown) != odown
+    });
+    if changed == Some(true) {
+        db.sentinel_event(if odown { "+odown" } else { "-odown" }, event);
+    }
+}
+
+/// 主节点客观下线（或请求了强制故障转移）时发起故障转移
+async fn try_failover<S: Storage>(
+    db: &Db<S>,
+    name: &str,
+    links: &mut HashMap<Addr, Link>,
+    peer_links: &mut HashMap<Addr, Link>,
+) {
+    let sentinel = db.sentinel();
+    let start = sentinel.with_master(name, |master| {
+        (master.odown || master.forced)
+            && master
+                .failover_started
+                .is_none_or(|started| started.elapsed() > master.failover_timeout)
+    });
+    if start != Some(true) {
+        return;
+    }
+
+    // 新的纪元，先投票给自己
+    let Some((epoch, forced, addr, peers, needed)) = ({
+        let mut state = sentinel.state();
+        state.current_epoch += 1;
+        let epoch = state.current_epoch;
+        state.masters.get_mut(name).map(|master| {
+            master.vote = Some((epoch, sentinel.myid.clone()));
+            master.failover_started = Some(Instant::now());
+            let peers: Vec<Addr> = master.sentinels.values().cloned().collect();
+            // 包括自己在内的哨兵总数的多数
+            let total = peers.len() + 1;
+            let needed = master.quorum.max(total / 2 + 1);
+            (epoch, mem::take(&mut master.forced), master.addr.clone(), peers, needed)
+        })
+    }) else {
+        return;
+    };
+
+    if !forced {
+        let (port, epoch_arg) = (addr.1.to_string(), epoch.to_string());
+        let mut votes = 1;
+        for peer in peers {
+            let link = peer_links.entry(peer.clone()).or_insert_with(|| Link::new(&peer, None));
+            let args =
+                ["sentinel", "is-master-down-by-addr", &addr.0, &port, &epoch_arg, sentinel.myid()];
+            if let Ok(Frame::Array(reply)) = link.request(&peer, &args).await
+                && let [_, Frame::Bulk(leader), Frame::Integer(leader_epoch)] = reply.as_slice()
+                && leader == sentinel.myid()
+                && *leader_epoch as u64 == epoch
+            {
+                votes += 1;
+            }
+        }
+        if votes < needed {
+            tracing::info!(master = name, epoch, votes, needed, "failover election lost");
+            return;
+        }
+    }
+
+    let Some(Some(promoted)) = sentinel.with_master(name, |master| master.best_replica()) else {
+        tracing::warn!(master = name, "no good replica to promote");
+        return;
+    };
+    let link = links.entry(promoted.clone()).or_insert_with(|| Link::new(&promoted, None));
+    if let Err(e) = link.request(&promoted, &["replicaof", "no", "one"]).await {
+        tracing::warn!(master = name, error = %e, "failed to promote replica");
+        return;
+    }
+    let (host, port) = (promoted.0.clone(), promoted.1.to_string());
+    for (addr, link) in links.iter_mut().filter(|(addr, _)| **addr != promoted) {
+        let _ = link.request(addr, &["replicaof", &host, &port]).await;
+    }
+
+    let message = sentinel.with_master(name, |master| {
+        let old = master.switch_to(promoted, epoch);
+        switch_message(name, &old, &master.addr)
+    });
+    if let Some(message) = message {
+        db.sentinel_event(SWITCH_MASTER_CHANNEL, message);
+    }
+}
+
+fn switch_message(name: &str, old: &Addr, new: &Addr) -> String {
+    format!("{name} {} {} {} {}", old.0, old.1, new.0, new.1)
+}
+
+fn down_reply(down: bool, vote: Option<(u64, String)>) -> Frame {
+    let (epoch, leader) = vote.unwrap_or_else(|| (0, "*".to_string()));
+    Frame::Array(vec![
+        Frame::Integer(i64::from(down)),
+        Frame::Bulk(leader),
+        Frame::Integer(epoch as i64),
+    ])
+}
+
+fn master_info(name: &str, master: &Master) -> Frame {
+    info_map([
+        ("name", name.to_string()),
+        ("ip", master.addr.0.clone()),
+        ("port", master.addr.1.to_string()),
+        ("flags", master.flags()),
+        ("num-slaves", master.replicas.len().to_string()),
+        ("num-other-sentinels", master.sentinels.len().to_string()),
+        ("quorum", master.quorum.to_string()),
+        ("config-epoch", master.config_epoch.to_string()),
+        ("down-after-milliseconds", master.down_after.as_millis().to_string()),
+        ("failover-timeout", master.failover_timeout.as_millis().to_string()),
+    ])
+}
+
+fn info_map<const N: usize>(fields: [(&str, String); N]) -> Frame {
+    Frame::Map(
+        fields
+            .into_iter()
+            .map(|(name, value)| (Frame::Bulk(name.into()), Frame::Bulk(value)))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handler::process_command, pubsub::Message, server};
+
+    async fn wait_for(mut condition: impl AsyncFnMut() -> bool) {
+        for _ in 0..300 {
+            if condition().await {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("condition not reached in time");
+    }
+
+    async fn start_server(db: &Db) -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(server::run(listener, db.clone()));
+        port
+    }
+
+    #[tokio::test]
+    async fn test_monitor_and_vote() {
+        let db = Db::new();
+        assert_eq!(process_command(&db, "sentinel monitor m 127.0.0.1 1 2").await, "OK");
+        assert_eq!(
+            process_command(&db, "sentinel monitor m 127.0.0.1 1 2").await,
+            "ERR Duplicated master name"
+        );
+        assert!(process_command(&db, "sentinel monitor n 127.0.0.1 1 0").await.starts_with("ERR"));
+        assert_eq!(
+            process_command(&db, "sentinel get-master-addr-by-name m").await,
+            "1) 127.0.0.1\n2) 1"
+        );
+        assert_eq!(process_command(&db, "sentinel get-master-addr-by-name n").await, "(nil)");
+
+        // SENTINEL SET 全部校验通过后才生效
+        assert_eq!(
+            process_command(&db, "sentinel set m quorum 3 down-after-milliseconds 0").await,
+            "ERR Invalid argument 'down-after-milliseconds' for SENTINEL SET"
+        );
+        assert_eq!(process_command(&db, "sentinel set m quorum 3 failover-timeout 10").await, "OK");
+        let info = process_command(&db, "sentinel master m").await;
+        assert!(info.contains("quorum\n") && info.contains("3\n"), "{info}");
+        assert!(info.contains("30000"), "{info}");
+
+        // 每个纪元只投一票，投给第一个请求者
+        let sentinel = db.sentinel();
+        let vote = |epoch, runid| sentinel.is_master_down_by_addr("127.0.0.1", 1, epoch, runid);
+        assert_eq!(vote(0, "*"), down_reply(false, None));
+        assert_eq!(vote(1, "a"), down_reply(false, Some((1, "a".into()))));
+        assert_eq!(vote(1, "b"), down_reply(false, Some((1, "a".into()))));
+        assert_eq!(vote(2, "b"), down_reply(false, Some((2, "b".into()))));
+        assert_eq!(
+            sentinel.is_master_down_by_addr("127.0.0.1", 2, 3, "c"),
+            down_reply(false, None)
+        );
+
+        assert_eq!(
+            process_command(&db, "sentinel failover m").await.split(' ').next(),
+            Some("NOGOODSLAVE")
+        );
+        assert_eq!(process_command(&db, "sentinel remove m").await, "OK");
+        assert_eq!(
+            process_command(&db, "sentinel remove m").await,
+            "ERR No such master with that name"
+        );
+        assert_eq!(process_command(&db, "sentinel masters").await, "(empty array)");
+    }
+
+    #[tokio::test]
+    async fn test_forced_failover() {
+        let master = Db::new();
+        let master_port = start_server(&master).await;
+        let replica = Db::new();
+        let replica_port = start_server(&replica).await;
+        process_command(&replica, &format!("replicaof 127.0.0.1 {master_port}")).await;
+        process_command(&master, "set a 1").await;
+        wait_for(async || process_command(&replica, "get a").await == "1").await;
+
+        let db = Db::new();
+        start_server(&db).await;
+        let mut subscriber = db.pubsub().subscriber();
+        subscriber.subscribe(SWITCH_MASTER_CHANNEL);
+        let monitor = format!("sentinel monitor m 127.0.0.1 {master_port} 1");
+        assert_eq!(process_command(&db, &monitor).await, "OK");
+        wait_for(async || db.sentinel().with_master("m", |m| m.best_replica()).flatten().is_some())
+            .await;
+
+        assert_eq!(process_command(&db, "sentinel failover m").await, "OK");
+        assert!(process_command(&db, "sentinel failover m").await.starts_with("INPROG"));
+        wait_for(async || db.sentinel().master_addr("m").unwrap().1 == replica_port).await;
+
+        let message = format!("m 127.0.0.1 {master_port} 127.0.0.1 {replica_port}");
+        assert_eq!(
+            subscriber.try_recv(),
+            Some(Message::Channel { channel: SWITCH_MASTER_CHANNEL.into(), payload: message })
+        );
+        assert!(process_command(&replica, "role").await.starts_with("1) master"));
+        let role = format!("1) slave\n2) 127.0.0.1\n3) (integer) {replica_port}");
+        wait_for(async || process_command(&master, "role").await.starts_with(&role)).await;
+        let replicas = process_command(&db, "sentinel replicas m").await;
+        assert!(replicas.contains(&master_port.to_string()), "{replicas}");
+        assert!(process_command(&db, "sentinel master m").await.contains("config-epoch\n"));
+        assert_eq!(process_command(&db, "sentinel myid").await, db.sentinel().myid());
+    }
+}
@@ -0,0 +1,833 @@
+//! 服务器模块
+//!
+//! 接受 TCP 连接，为每个连接启动一个任务：
+//! - 读取 RESP 数组或内联格式的命令，交给连接自己的 [`Session`] 执行并写回回复；
+//!   流水线发送的命令在一批中依次执行，回复合并后一次写出。命令名按 `rename-command`
+//!   的改名表解析（见 [`Registry::parse`](crate::handler::Registry::parse)）
+//! - 订阅或监视（MONITOR）状态下同时等待推送的消息
+//! - 收到 `PSYNC` / `SYNC` 时把连接交给复制模块，作为副本连接处理
+//! - 连接数超过 `maxclients` 时回复错误并关闭新连接
+//! - 同时服务的连接数达到 `max-connections` 时暂停接受新连接，新连接在内核的监听队列中等待，
+//!   直到有连接关闭；该上限可以通过 CONFIG SET 调整
+//! - 新连接按 [`SocketOptions`] 设置 TCP_NODELAY、keepalive 与收发缓冲区大小
+//!
+//! 服务器运行期间还有一个周期任务（cron，对应 Redis 的 serverCron），每秒执行 `hz` 次，
+//! 增量地完成后台工作：主动过期、内存淘汰、自动快照、统计采样，以及关闭空闲超时（`timeout`）
+//! 或输出缓冲区超出限制的客户端。
+//!
+//! 其他程序可以通过 [`Server::builder`] 在进程内启动并停止服务器（见 [`Server`]）。
+//!
+//! 启用 `io-uring` feature 时，Linux 上还可以用基于 io_uring 的 [`uring`] 前端运行服务器，
+//! 它与默认的 epoll 前端共用帧、会话与命令处理层，只替换网络 I/O。
+//!
+//! 每个连接的处理过程位于一个 `connection` span 中（记录对端地址），
+//! 其中每条命令的执行位于 `command` span 中（见 [`Session::execute`]）。
+
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use socket2::{SockRef, TcpKeepalive};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpListener,
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::Instant,
+};
+use tracing::Instrument;
+
+use crate::{
+    command::Command,
+    connection::Connection,
+    db::{Db, Storage},
+    frame::Frame,
+    replication,
+    session::Session,
+};
+
+mod embedded;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub mod uring;
+
+pub use embedded::{Server, ServerBuilder};
+
+/// 在已绑定的监听器上运行服务器，直到接受连接失败
+pub async fn run<S: Storage>(listener: TcpListener, db: Db<S>) -> io::Result<()> {
+    db.replication().set_listening_port(listener.local_addr()?.port());
+
+    let cron = tokio::spawn(cron(db.clone()));
+    let result = accept_loop(listener, db).await;
+    cron.abort();
+    result
+}
+
+/// 周期任务，每次依次：
+/// - 主动删除到期的键
+/// - 内存占用超出上限时主动淘汰
+/// - 满足自动快照条件时发起 BGSAVE
+/// - 采样统计，用于计算瞬时指标
+/// - 关闭空闲超时与输出缓冲区超出限制的客户端
+/// - 清理限流的空闲令牌桶
+///
+/// 通过 CONFIG SET 修改 `hz` 后从下一次执行起按新的频率运行。
+async fn cron<S: Storage>(db: Db<S>) {
+    let mut hz = db.config().current().hz;
+    let mut interval = tokio::time::interval(cron_interval(hz));
+    loop {
+        interval.tick().await;
+        if let Err(e) = db.active_expire_cycle().await {
+            tracing::error!(error = %e, "active expire cycle failed");
+        }
+        db.evict_over_limit().await;
+        db.check_save_points().await;
+
+        let now = std::time::Instant::now();
+        db.stats().sample(now);
+        let config = db.config().current();
+        if config.timeout > 0 {
+            db.clients().close_idle(Duration::from_secs(config.timeout), now);
+        }
+        let closed = db.clients().enforce_output_limits(&config.client_output_buffer_limit);
+        db.stats().output_limit_exceeded(closed as u64);
+        db.rate_limiter().prune(now);
+
+        if config.hz != hz {
+            hz = config.hz;
+            let period = cron_interval(hz);
+            interval = tokio::time::interval_at(Instant::now() + period, period);
+        }
+    }
+}
+
+/// 每秒执行 `hz` 次时的间隔
+fn cron_interval(hz: u64) -> Duration {
+    Duration::from_secs(1) / hz.max(1) as u32
+}
+
+/// 接受连接，为每个连接启动一个任务
+///
+/// 每个连接占用一个许可，许可用完时先等待有连接关闭再继续 accept。
+async fn accept_loop<S: Storage>(listener: TcpListener, db: Db<S>) -> io::Result<()> {
+    let mut config = db.config().subscribe();
+    let limit = Arc::new(ConnectionLimit::new(config.borrow_and_update().max_connections));
+    loop {
+        let permit = tokio::select! {
+            permit = limit.acquire() => permit,
+            Ok(()) = config.changed() => {
+                limit.resize(config.borrow_and_update().max_connections);
+                continue;
+            }
+        };
+        let (socket, addr) = listener.accept().await?;
+        let span = tracing::debug_span!("connection", %addr);
+        if let Err(e) = config.borrow().socket.apply(&socket) {
+            span.in_scope(|| tracing::warn!(error = %e, "failed to set socket options"));
+        }
+        tokio::spawn(serve(socket, addr, db.clone(), permit).instrument(span));
+    }
+}
+
+/// 服务一个已接受的连接直到关闭，连接数超过 `maxclients` 时回复错误后关闭
+async fn serve<S: Storage, T: AsyncRead + AsyncWrite + Unpin>(
+    socket: T,
+    addr: SocketAddr,
+    db: Db<S>,
+    permit: ConnectionPermit,
+) {
+    db.stats().connection_opened();
+    if db.stats().connected_clients() > db.config().current().maxclients {
+        db.stats().connection_rejected();
+        tracing::warn!("max number of clients reached, connection rejected");
+        let error = Frame::Error("ERR max number of clients reached".into());
+        let _ = Connection::new(socket).write_frame(&error).await;
+    } else {
+        tracing::debug!("connection accepted");
+        // 单个连接的 I/O 错误只影响该连接
+        match handle_connection(socket, addr, db.clone()).await {
+            Ok(()) => tracing::debug!("connection closed"),
+            Err(e) => tracing::debug!(error = %e, "connection closed with error"),
+        }
+    }
+    db.stats().connection_closed();
+    drop(permit);
+}
+
+/// 新连接的套接字选项
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SocketOptions {
+    /// 是否关闭 Nagle 算法（`tcp-nodelay`），关闭后小的回复不会被延迟合并
+    pub nodelay: bool,
+    /// 空闲多少秒后开始发送 keepalive 探测（`tcp-keepalive`），0 表示不启用
+    pub keepalive: u64,
+    /// 接收缓冲区大小（`socket-rcvbuf`，字节），0 表示使用系统默认值
+    pub recv_buffer_size: usize,
+    /// 发送缓冲区大小（`socket-sndbuf`，字节），0 表示使用系统默认值
+    pub send_buffer_size: usize,
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self { nodelay: true, keepalive: 300, recv_buffer_size: 0, send_buffer_size: 0 }
+    }
+}
+
+impl SocketOptions {
+    /// 把选项应用到一个已建立的连接上；应用到监听套接字时由之后接受的连接继承
+    pub fn apply<'s>(&self, socket: impl Into<SockRef<'s>>) -> io::Result<()> {
+        let socket = socket.into();
+        socket.set_tcp_nodelay(self.nodelay)?;
+        if self.keepalive > 0 {
+            let keepalive = TcpKeepalive::new().with_time(Duration::from_secs(self.keepalive));
+            socket.set_tcp_keepalive(&keepalive)?;
+        }
+        if self.recv_buffer_size > 0 {
+            socket.set_recv_buffer_size(self.recv_buffer_size)?;
+        }
+        if self.send_buffer_size > 0 {
+            socket.set_send_buffer_size(self.send_buffer_size)?;
+        }
+        Ok(())
+    }
+}
+
+/// 同时服务的连接数上限（`max-connections`）
+struct ConnectionLimit {
+    semaphore: Arc<Semaphore>,
+    /// 当前上限
+    limit: AtomicUsize,
+    /// 调低上限时仍被连接占用、还没有收回的许可数
+    debt: AtomicUsize,
+}
+
+impl ConnectionLimit {
+    fn new(limit: u64) -> Self {
+        let limit = limit as usize;
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit)),
+            limit: AtomicUsize::new(limit),
+            debt: AtomicUsize::new(0),
+        }
+    }
+
+    /// 等待一个空闲的许可
+    async fn acquire(self: &Arc<Self>) -> ConnectionPermit {
+        let permit = self.semaphore.clone().acquire_owned().await;
+        let permit = permit.expect("connection semaphore is never closed");
+        ConnectionPermit { permit: Some(permit), limit: self.clone() }
+    }
+
+    /// 调整上限：调高时先抵消未收回的许可，调低时收回空闲的许可，不足的部分在连接关闭时收回
+    fn resize(&self, limit: u64) {
+        let limit = limit as usize;
+        let old = self.limit.swap(limit, Ordering::SeqCst);
+        if limit > old {
+            let grow = limit - old;
+            let debt = self.debt.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |debt| {
+                Some(debt - debt.min(grow))
+            });
+            let paid = debt.unwrap_or_default().min(grow);
+            self.semaphore.add_permits(grow - paid);
+        } else {
+            let shrink = old - limit;
+            let forgotten = self.semaphore.forget_permits(shrink);
+            self.debt.fetch_add(shrink - forgotten, Ordering::SeqCst);
+        }
+    }
+}
+
+/// 一个连接占用的许可，连接结束时归还或用于抵消调低上限欠下的许可
+struct ConnectionPermit {
+    permit: Option<OwnedSemaphorePermit>,
+    limit: Arc<ConnectionLimit>,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let debt = &self.limit.debt;
+        if debt.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |debt| debt.checked_sub(1)).is_ok()
+            && let Some(permit) = self.permit.take()
+        {
+            permit.forget();
+        }
+    }
+}
+
+/// 处理一个客户端连接，直到对端关闭
+async fn handle_connection<S: Storage, T: AsyncRead + AsyncWrite + Unpin>(
+    socket: T,
+    addr: SocketAddr,
+    db: Db<S>,
+) -> io::Result<()> {
+    let mut conn = Connection::new(socket);
+    let mut session = Session::with_addr(db.clone(), addr);
+
+    let mut config = db.config().subscribe();
+    conn.set_limits(config.borrow_and_update().limits);
+    // CLIENT KILL、空闲超时与输出缓冲区超限都通过这个通知关闭连接
+    let killed = session.killed();
+    tokio::pin!(killed);
+
+    loop {
+        let frame = tokio::select! {
+            frame = conn.read_request() => frame,
+            Some(message) = session.next_message(), if pushes_messages(&session) => {
+                // 读取太慢的客户端可能长时间阻塞在写出上，超出输出缓冲区限制时要能立即断开
+                tokio::select! {
+                    result = conn.write_frame(&message) => result?,
+                    () = &mut killed => return Ok(()),
+                }
+                continue;
+            }
+            () = &mut killed => return Ok(()),
+            Ok(()) = config.changed() => {
+                conn.set_limits(config.borrow_and_update().limits);
+                continue;
+            }
+        };
+
+        let mut frame = match frame {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return Ok(()),
+            Err(e) => return protocol_error(&mut conn, e).await,
+        };
+
+        // 依次执行缓冲区中已经完整到达的命令，回复先写入发送缓冲区，整批处理完再 flush
+        loop {
+            let args = match frame.into_args() {
+                Ok(args) => args,
+                Err(e) => return conn.write_frame(&Frame::Error(e.to_string())).await,
+            };
+            let parts: Vec<_> = args.iter().map(String::as_str).collect();
+
+            // 全量同步不经过会话执行，在这里检查权限
+            let command = match db.commands().parse(&parts) {
+                Ok(command @ (Command::Psync(..) | Command::Sync)) => {
+                    session.check_permission(&command).map(|()| command).map_err(|e| e.to_string())
+                }
+                command => command.map_err(|e| e.to_string()),
+            };
+            match command {
+                Err(e) => conn.feed_frame(&Frame::Error(e)).await?,
+                Ok(Command::Psync(replid, offset)) => {
+                    let port = session.listening_port();
+                    let psync = Some((replid, offset));
+                    let client = session.client();
+                    return replication::serve_replica(
+                        conn,
+                        db,
+                        client,
+                        killed,
+                        addr.ip(),
+                        port,
+                        psync,
+                    )
+                    .await;
+                }
+                Ok(Command::Sync) => {
+                    let port = session.listening_port();
+                    let client = session.client();
+                    return replication::serve_replica(
+                        conn,
+                        db,
+                        client,
+                        killed,
+                        addr.ip(),
+                        port,
+                        None,
+                    )
+                    .await;
+                }
+                Ok(command) => {
+                    let replies = session.execute_with_args(command, &args).await;
+                    // HELLO 的回复已经使用新的协议版本
+                    conn.set_protocol(session.protocol());
+                    for reply in replies {
+                        conn.feed_frame(&reply).await?;
+                    }
+                    // QUIT：写出回复后关闭连接，之后缓冲的命令不再执行
+                    if session.is_closing() {
+                        return conn.flush().await;
+                    }
+                }
+            }
+
+            frame = match conn.try_read_request() {
+                Ok(Some(frame)) => frame,
+                Ok(None) => break,
+                Err(e) => return protocol_error(&mut conn, e).await,
+            };
+        }
+        conn.flush().await?;
+    }
+}
+
+/// 读取命令失败：协议错误时回复错误后关闭连接（已缓冲的回复一并写出），其他错误直接返回
+async fn protocol_error<T: AsyncRead + AsyncWrite + Unpin>(
+    conn: &mut Connection<T>,
+    e: io::Error,
+) -> io::Result<()> {
+    if e.kind() == io::ErrorKind::InvalidData {
+        conn.write_frame(&Frame::Error(e.to_string())).await
+    } else {
+        Err(e)
+    }
+}
+
+/// 连接是否处于订阅或监视状态，需要等待推送的消息
+fn pushes_messages<S: Storage>(session: &Session<S>) -> bool {
+    session.is_subscribed() || session.is_monitoring()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use socket2::SockRef;
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::{ConnectionLimit, SocketOptions, run};
+    use crate::{
+        config::Config,
+        connection::Connection,
+        db::Db,
+        frame::Frame,
+        persistence::{SavePoint, enable_rdb},
+    };
+
+    async fn connect(db: &Db) -> Connection {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(run(listener, db.clone()));
+        Connection::new(TcpStream::connect(addr).await.unwrap())
+    }
+
+    async fn request(conn: &mut Connection, input: &str) -> String {
+        request_frame(conn, input).await.to_string()
+    }
+
+    async fn request_frame(conn: &mut Connection, input: &str) -> Frame {
+        let args: Vec<String> = input.split_whitespace().map(String::from).collect();
+        conn.write_frame(&Frame::from(args)).await.unwrap();
+        conn.read_frame().await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_commands_over_tcp() {
+        let db = Db::new();
+        let mut conn = connect(&db).await;
+
+        assert_eq!(request(&mut conn, "set foo bar").await, "OK");
+        assert_eq!(request(&mut conn, "get foo").await, "bar");
+        assert_eq!(
+            request(&mut conn, "nosuchcommand").await,
+            "ERR unknown command 'nosuchcommand', with args beginning with: "
+        );
+        assert_eq!(
+            request(&mut conn, "set foo").await,
+            "ERR wrong number of arguments for 'set' command"
+        );
+        assert_eq!(db.get("foo").await, Ok(Some("bar".into())));
+    }
+
+    #[tokio::test]
+    async fn test_pubsub_over_tcp() {
+        let db = Db::new();
+        let mut subscriber = connect(&db).await;
+        let mut publisher = connect(&db).await;
+
+        assert_eq!(
+            request(&mut subscriber, "subscribe ch").await,
+            "1) subscribe\n2) ch\n3) (integer) 1"
+        );
+        assert_eq!(request(&mut publisher, "publish ch hi").await, "(integer) 1");
+        let message = subscriber.read_frame().await.unwrap().unwrap();
+        assert_eq!(message.to_string(), "1) message\n2) ch\n3) hi");
+    }
+
+    #[tokio::test]
+    async fn test_cron_triggers_save_point() {
+        let path = std::env::temp_dir().join(format!("mini-redis-{}-cron.rdb", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let db = Db::new();
+        enable_rdb(&db, &path).await.unwrap();
+        db.set_save_points(vec![SavePoint { seconds: 0, changes: 1 }]);
+        let mut conn = connect(&db).await;
+        assert_eq!(request(&mut conn, "set foo bar").await, "OK");
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while db.dirty() > 0 || db.is_saving() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("save point not triggered");
+        assert!(path.exists());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_protocol_error_closes_connection() {
+        let db = Db::new();
+        let mut conn = connect(&db).await;
+
+        conn.write_bytes(b"*1\r\n:1\r\n").await.unwrap();
+        let reply = conn.read_frame().await.unwrap().unwrap();
+        assert!(reply.to_string().starts_with("ERR Protocol error"), "{reply}");
+        assert!(conn.read_frame().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_protocol_limits() {
+        let db = Db::new();
+        let mut conn = connect(&db).await;
+
+        assert_eq!(request(&mut conn, "config set proto-max-bulk-len 1kb").await, "OK");
+        assert_eq!(
+            request(&mut conn, "config get proto-max-bulk-len").await,
+            "1) proto-max-bulk-len\n2) 1024"
+        );
+        assert_eq!(request(&mut conn, "config set proto-max-multibulk-len 3").await, "OK");
+        assert_eq!(request(&mut conn, "set foo bar").await, "OK");
+        assert_eq!(
+            request(&mut conn, "del a b c").await,
+            "ERR Protocol error: invalid multibulk length"
+        );
+        assert!(conn.read_frame().await.unwrap().is_none());
+
+        // 声明的长度超过限制时不等数据到达就关闭连接
+        let mut conn = connect(&db).await;
+        conn.write_bytes(b"*3\r\n$3\r\nset\r\n$1\r\nk\r\n$2000\r\n").await.unwrap();
+        let reply = conn.read_frame().await.unwrap().unwrap();
+        assert_eq!(reply.to_string(), "ERR Protocol error: invalid bulk length");
+        assert!(conn.read_frame().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_socket_options() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+
+        let options = SocketOptions { recv_buffer_size: 64 * 1024, ..SocketOptions::default() };
+        options.apply(&socket).unwrap();
+        let sock = SockRef::from(&socket);
+        assert!(socket.nodelay().unwrap());
+        assert!(sock.keepalive().unwrap());
+        assert_eq!(sock.tcp_keepalive_time().unwrap(), Duration::from_secs(300));
+        // Linux 会把设置的值翻倍
+        assert!(sock.recv_buffer_size().unwrap() >= 64 * 1024);
+
+        let options = SocketOptions { nodelay: false, keepalive: 0, ..options };
+        options.apply(&socket).unwrap();
+        assert!(!socket.nodelay().unwrap());
+        drop(client);
+    }
+
+    #[tokio::test]
+    async fn test_quit_closes_connection() {
+        let db = Db::new();
+        let mut conn = connect(&db).await;
+
+        // QUIT 之后同一批次中的命令不再执行
+        let mut batch = Frame::from(vec!["quit".to_string()]).encode();
+        batch.extend(Frame::from(vec!["set".into(), "a".into(), "1".to_string()]).encode());
+        conn.write_bytes(&batch).await.unwrap();
+        assert_eq!(conn.read_frame().await.unwrap().unwrap().to_string(), "OK");
+        assert!(conn.read_frame().await.unwrap().is_none());
+        assert_eq!(db.get("a").await, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_commands() {
+        let db = Db::new();
+        let mut conn = connect(&db).await;
+
+        let mut batch = Vec::new();
+        for i in 0..1000 {
+            batch.extend(
+                Frame::from(vec!["set".to_string(), format!("k{i}"), i.to_string()]).encode(),
+            );
+        }
+        conn.write_bytes(&batch).await.unwrap();
+
+        for _ in 0..1000 {
+            assert_eq!(conn.read_frame().await.unwrap().unwrap().to_string(), "OK");
+        }
+        assert_eq!(db.dbsize().await, 1000);
+        assert_eq!(request(&mut conn, "get k999").await, "999");
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_with_protocol_error() {
+        let db = Db::new();
+        let mut conn = connect(&db).await;
+
+        // 协议错误之前的命令照常执行并回复
+        conn.write_bytes(b"*3\r\n$3\r\nset\r\n$1\r\na\r\n$1\r\n1\r\n*1\r\n:1\r\n").await.unwrap();
+        assert_eq!(conn.read_frame().await.unwrap().unwrap().to_string(), "OK");
+        let reply = conn.read_frame().await.unwrap().unwrap();
+        assert!(reply.to_string().starts_with("ERR Protocol error"), "{reply}");
+        assert!(conn.read_frame().await.unwrap().is_none());
+        assert_eq!(db.get("a").await, Ok(Some("1".into())));
+    }
+
+    #[tokio::test]
+    async fn test_hello_switches_protocol() {
+        let db = Db::new();
+        let mut conn = connect(&db).await;
+        request(&mut conn, "sadd s a").await;
+
+        assert!(matches!(request_frame(&mut conn, "hello 3").await, Frame::Map(_)));
+        assert_eq!(
+            request_frame(&mut conn, "smembers s").await,
+            Frame::Set(vec![Frame::Bulk("a".into())])
+        );
+        assert_eq!(request_frame(&mut conn, "get missing").await, Frame::Null);
+
+        let Frame::Array(items) = request_frame(&mut conn, "hello 2").await else { panic!() };
+        assert_eq!(items[4..6], [Frame::Bulk("proto".into()), Frame::Integer(2)]);
+        assert_eq!(
+            request_frame(&mut conn, "smembers s").await,
+            Frame::Array(vec![Frame::Bulk("a".into())])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_inline_commands() {
+        let db = Db::new();
+        let mut conn = connect(&db).await;
+
+        conn.write_bytes(b"set greeting \"hello world\"\r\nget greeting\r\n").await.unwrap();
+        assert_eq!(conn.read_frame().await.unwrap().unwrap().to_string(), "OK");
+        assert_eq!(conn.read_frame().await.unwrap().unwrap().to_string(), "hello world");
+
+        conn.write_bytes(b"get 'greeting\r\n").await.unwrap();
+        let reply = conn.read_frame().await.unwrap().unwrap().to_string();
+        assert_eq!(reply, "ERR Protocol error: unbalanced quotes in request");
+        assert!(conn.read_frame().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_maxclients() {
+        let db = Db::new();
+        let mut first = connect(&db).await;
+        assert_eq!(request(&mut first, "config set maxclients 1").await, "OK");
+
+        let mut second = connect(&db).await;
+        let reply = second.read_frame().await.unwrap().unwrap();
+        assert_eq!(reply.to_string(), "ERR max number of clients reached");
+        assert!(second.read_frame().await.unwrap().is_none());
+        assert_eq!(db.stats().rejected_connections(), 1);
+        assert_eq!(request(&mut first, "get foo").await, "(nil)");
+    }
+
+    #[tokio::test]
+    async fn test_max_connections() {
+        let db = Db::new();
+        db.config_set(&[("max-connections".into(), "1".into())]).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(run(listener, db.clone()));
+
+        // 第二个连接在第一个关闭之前得不到服务
+        let mut first = Connection::new(TcpStream::connect(addr).await.unwrap());
+        assert_eq!(request(&mut first, "set foo bar").await, "OK");
+        let mut second = Connection::new(TcpStream::connect(addr).await.unwrap());
+        let args = vec!["get".to_string(), "foo".into()];
+        second.write_frame(&Frame::from(args)).await.unwrap();
+        let waiting = tokio::time::timeout(Duration::from_millis(100), second.read_frame()).await;
+        assert!(waiting.is_err());
+
+        drop(first);
+        let reply = tokio::time::timeout(Duration::from_secs(5), second.read_frame()).await;
+        assert_eq!(reply.expect("connection not accepted").unwrap().unwrap().to_string(), "bar");
+
+        // 调高上限后立即接受等待中的连接
+        let mut third = Connection::new(TcpStream::connect(addr).await.unwrap());
+        assert_eq!(request(&mut second, "config set max-connections 2").await, "OK");
+        let reply = tokio::time::timeout(Duration::from_secs(5), request(&mut third, "get foo"));
+        assert_eq!(reply.await.expect("connection not accepted"), "bar");
+    }
+
+    #[tokio::test]
+    async fn test_connection_limit_resize() {
+        let limit = Arc::new(ConnectionLimit::new(2));
+        let first = limit.acquire().await;
+        let second = limit.acquire().await;
+
+        // 调低上限时许可都被占用，关闭的连接不再归还许可
+        limit.resize(1);
+        drop(first);
+        assert_eq!(limit.semaphore.available_permits(), 0);
+        drop(second);
+        assert_eq!(limit.semaphore.available_permits(), 1);
+
+        let third = limit.acquire().await;
+        limit.resize(0);
+        limit.resize(3);
+        assert_eq!(limit.semaphore.available_permits(), 2);
+        drop(third);
+        assert_eq!(limit.semaphore.available_permits(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_client_kill_closes_connection() {
+        let db = Db::new();
+        let mut admin = connect(&db).await;
+        let mut victim = connect(&db).await;
+        let id = request(&mut victim, "client id").await;
+        let id = id.trim_start_matches("(integer) ");
+
+        assert_eq!(request(&mut admin, &format!("client kill id {id}")).await, "(integer) 1");
+        assert!(victim.read_frame().await.unwrap().is_none());
+        assert_eq!(request(&mut admin, "client kill id 99").await, "(integer) 0");
+    }
+
+    #[tokio::test]
+    async fn test_monitor_over_tcp() {
+        let db = Db::new();
+        let mut monitor = connect(&db).await;
+        let mut client = connect(&db).await;
+        assert_eq!(request(&mut monitor, "monitor").await, "OK");
+
+        assert_eq!(request(&mut client, "set foo bar").await, "OK");
+        let line = monitor.read_frame().await.unwrap().unwrap().to_string();
+        assert!(line.ends_with(r#"] "set" "foo" "bar""#), "{line}");
+    }
+
+    #[tokio::test]
+    async fn test_output_buffer_limit_closes_slow_subscriber() {
+        let db = Db::new();
+        let mut subscriber = connect(&db).await;
+        request(&mut subscriber, "subscribe ch").await;
+        db.config_set(&[("client-output-buffer-limit".into(), "pubsub 1mb 0 0".into())]).unwrap();
+
+        // 订阅者不读取，消息先填满套接字缓冲区，再在输出缓冲区中堆积
+        let payload = "x".repeat(64 * 1024);
+        for _ in 0..2000 {
+            if db.stats().output_buffer_limit_disconnections() > 0 {
+                break;
+            }
+            db.pubsub().publish("ch", &payload);
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        assert_eq!(db.stats().output_buffer_limit_disconnections(), 1);
+
+        let drained = tokio::time::timeout(Duration::from_secs(5), async {
+            while let Ok(Some(_)) = subscriber.read_frame().await {}
+        });
+        drained.await.expect("slow subscriber not closed");
+    }
+
+    #[tokio::test]
+    async fn test_renamed_commands() {
+        let mut config = Config::default();
+        config.rename_command.rename("config", "cfg").unwrap();
+        config.rename_command.rename("flushall", "").unwrap();
+        let db = Db::with_config(config);
+        let mut conn = connect(&db).await;
+
+        assert_eq!(request(&mut conn, "cfg get timeout").await, "1) timeout\n2) 0");
+        assert_eq!(
+            request(&mut conn, "config get timeout").await,
+            "ERR unknown command 'config', with args beginning with: 'get' 'timeout' "
+        );
+        assert!(request(&mut conn, "FLUSHALL").await.starts_with("ERR unknown command"));
+        assert_eq!(request(&mut conn, "set k v").await, "OK");
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout() {
+        let db = Db::new();
+        let mut idle = connect(&db).await;
+        let mut subscriber = connect(&db).await;
+        request(&mut subscriber, "subscribe ch").await;
+        let mut blocked = connect(&db).await;
+        let args = ["bzpopmin", "z", "0"].map(String::from).to_vec();
+        blocked.write_frame(&Frame::from(args)).await.unwrap();
+        assert_eq!(request(&mut idle, "config set timeout 1").await, "OK");
+
+        let closed = tokio::time::timeout(Duration::from_secs(5), idle.read_frame()).await;
+        assert!(closed.expect("idle connection not closed").unwrap().is_none());
+
+        // 订阅状态与阻塞在命令上的连接不受空闲超时影响
+        let mut publisher = connect(&db).await;
+        assert_eq!(request(&mut publisher, "publish ch hi").await, "(integer) 1");
+        let message = subscriber.read_frame().await.unwrap().unwrap();
+        assert_eq!(message.to_string(), "1) message\n2) ch\n3) hi");
+        assert_eq!(request(&mut publisher, "zadd z 1 a").await, "(integer) 1");
+        let popped = blocked.read_frame().await.unwrap().unwrap();
+        assert_eq!(popped.to_string(), "1) z\n2) a\n3) 1");
+    }
+
+    #[tokio::test]
+    async fn test_cron() {
+        let db = Db::with_shards(1);
+        let mut conn = connect(&db).await;
+        assert_eq!(request(&mut conn, "config set hz 100").await, "OK");
+        assert_eq!(request(&mut conn, "config get hz").await, "1) hz\n2) 100");
+        for i in 0..10 {
+            request(&mut conn, &format!("set key:{i} {}", "x".repeat(100))).await;
+        }
+
+        // 调低内存上限后，不需要新的写命令也会淘汰到上限以内
+        let maxmemory = db.used_memory().await / 2;
+        request(&mut conn, "config set maxmemory-policy allkeys-lru").await;
+        request(&mut conn, &format!("config set maxmemory {maxmemory}")).await;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while db.used_memory().await > maxmemory {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("keys not evicted");
+        assert!(db.stats().evicted_keys() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_fault_injection() {
+        let db = Db::new();
+        let mut conn = connect(&db).await;
+        if !cfg!(feature = "fault-injection") {
+            assert_eq!(
+                request(&mut conn, "debug faults").await,
+                "ERR DEBUG command not allowed, rebuild with the fault-injection feature"
+            );
+            return;
+        }
+
+        assert_eq!(request(&mut conn, "debug delay get 100").await, "OK");
+        let started = std::time::Instant::now();
+        assert_eq!(request(&mut conn, "get foo").await, "(nil)");
+        assert!(started.elapsed() >= Duration::from_millis(100));
+
+        // 持有写锁期间其他连接的命令等待
+        let mut contender = connect(&db).await;
+        let contend = Frame::from(vec!["debug".into(), "contend".into(), "200".to_string()]);
+        contender.write_frame(&contend).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let started = std::time::Instant::now();
+        assert_eq!(request(&mut conn, "set foo bar").await, "OK");
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        assert_eq!(contender.read_frame().await.unwrap().unwrap().to_string(), "OK");
+
+        // 断开连接而不执行命令，次数用完后恢复正常
+        assert_eq!(request(&mut conn, "debug drop set").await, "OK");
+        assert_eq!(request(&mut conn, "debug faults").await, "1) delay get 100\n2) drop set 1");
+        let set = Frame::from(vec!["set".into(), "foo".into(), "baz".to_string()]);
+        conn.write_frame(&set).await.unwrap();
+        assert!(conn.read_frame().await.unwrap().is_none());
+        let mut conn = connect(&db).await;
+        assert_eq!(request(&mut conn, "debug reset").await, "OK");
+        assert_eq!(request(&mut conn, "get foo").await, "bar");
+        assert_eq!(request(&mut conn, "debug faults").await, "(empty array)");
+    }
+}
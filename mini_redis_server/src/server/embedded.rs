@@ -0,0 +1,176 @@
+//! 嵌入式服务器
+//!
+//! 在其他 Rust 程序（以及集成测试）的进程内启动一个完整的 mini-redis 实例，不需要启动二进制：
+//!
+//! ```no_run
+//! # async fn example() -> std::io::Result<()> {
+//! use mini_redis_server::Server;
+//!
+//! let server = Server::builder().bind("127.0.0.1:0").start().await?;
+//! println!("listening on {}", server.local_addr());
+//! server.shutdown().await
+//! # }
+//! ```
+//!
+//! 服务器运行在当前 tokio 运行时的任务中，与二进制使用同一套连接处理与周期任务（见 [`run`]）。
+//! [`Server::shutdown`] 或销毁 [`Server`] 时停止接受连接，并关闭所有已连接的客户端。
+
+use std::{io, net::SocketAddr};
+
+use tokio::{net::TcpListener, task::JoinHandle};
+
+use super::run;
+use crate::{
+    command::ClientKill,
+    config::Config,
+    db::{Db, Keyspace, Storage},
+    persistence,
+};
+
+/// 运行中的嵌入式服务器
+pub struct Server<S: Storage = Keyspace> {
+    addr: SocketAddr,
+    db: Db<S>,
+    task: JoinHandle<io::Result<()>>,
+}
+
+/// [`Server`] 的构建器
+pub struct ServerBuilder<S: Storage = Keyspace> {
+    addr: String,
+    db: Option<Db<S>>,
+    persistence: Option<persistence::Config>,
+}
+
+impl Server {
+    /// 创建构建器：默认监听 `127.0.0.1` 上的随机端口，使用空的内存数据库，不开启持久化
+    pub fn builder() -> ServerBuilder {
+        ServerBuilder { addr: "127.0.0.1:0".into(), db: None, persistence: None }
+    }
+}
+
+impl<S: Storage> Server<S> {
+    /// 实际监听的地址，绑定端口 0 时可由此得到分配的端口
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 服务器使用的数据库，可以绕过网络直接读写
+    pub fn db(&self) -> &Db<S> {
+        &self.db
+    }
+
+    /// 停止服务器：不再接受连接，关闭所有客户端，开启 AOF 时把已写入的数据落盘
+    pub async fn shutdown(mut self) -> io::Result<()> {
+        self.stop();
+        let result = match (&mut self.task).await {
+            Ok(result) => result,
+            Err(e) if e.is_cancelled() => Ok(()),
+            Err(e) => Err(io::Error::other(e)),
+        };
+        if let Some(aof) = self.db.aof() {
+            aof.sync()?;
+        }
+        result
+    }
+
+    fn stop(&self) {
+        self.task.abort();
+        self.db.clients().kill(&ClientKill::default(), 0);
+    }
+}
+
+impl<S: Storage> Drop for Server<S> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl ServerBuilder {
+    /// 按启动配置构建：数据库按 [`Db::with_config`] 创建，监听 `bind:port`，并按其中的持久化设置
+    /// 恢复数据、开启持久化
+    pub fn config(self, config: Config) -> Self {
+        Self {
+            addr: format!("{}:{}", config.bind, config.port),
+            persistence: Some(config.persistence.clone()),
+            db: Some(Db::with_config(config)),
+        }
+    }
+}
+
+impl<S: Storage> ServerBuilder<S> {
+    /// 监听地址，形如 `127.0.0.1:6379`
+    pub fn bind(mut self, addr: impl Into<String>) -> Self {
+        self.addr = addr.into();
+        self
+    }
+
+    /// 使用给定的数据库（可以是其他存储后端），服务器与调用方共享其中的数据
+    pub fn db<T: Storage>(self, db: Db<T>) -> ServerBuilder<T> {
+        ServerBuilder { addr: self.addr, db: Some(db), persistence: self.persistence }
+    }
+
+    /// 启动时按给定设置从持久化文件恢复数据，并开启相应的持久化（见 [`persistence::open`]）
+    pub fn persistence(mut self, config: persistence::Config) -> Self {
+        self.persistence = Some(config);
+        self
+    }
+
+    /// 绑定地址、恢复数据后在后台任务中运行服务器，必须在 tokio 运行时中调用
+    pub async fn start(self) -> io::Result<Server<S>> {
+        let db = self.db.unwrap_or_default();
+        if let Some(config) = &self.persistence {
+            persistence::open(&db, config).await?;
+        }
+        let listener = TcpListener::bind(self.addr.as_str()).await?;
+        let addr = listener.local_addr()?;
+        let task = tokio::spawn(run(listener, db.clone()));
+        Ok(Server { addr, db, task })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpStream;
+
+    use super::*;
+    use crate::{connection::Connection, frame::Frame, handler::process_command};
+
+    async fn request(conn: &mut Connection, args: &[&str]) -> Option<Frame> {
+        let frame = Frame::from(args.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+        conn.write_frame(&frame).await.unwrap();
+        conn.read_frame().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_start_and_shutdown() {
+        let server = Server::builder().start().await.unwrap();
+        let addr = server.local_addr();
+        let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+        assert_eq!(request(&mut conn, &["set", "a", "1"]).await, Some(Frame::Simple("OK".into())));
+        assert_eq!(process_command(server.db(), "get a").await, "1");
+
+        // 停止后已有连接被关闭，新连接被拒绝
+        server.shutdown().await.unwrap();
+        assert_eq!(request(&mut conn, &["get", "a"]).await, None);
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_persistence() {
+        let dir = std::env::temp_dir().join(format!("mini-redis-{}-embedded", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let persistence =
+            persistence::Config { dir: dir.clone(), appendonly: true, ..Default::default() };
+
+        let server = Server::builder().persistence(persistence.clone()).start().await.unwrap();
+        process_command(server.db(), "set foo bar").await;
+        server.shutdown().await.unwrap();
+
+        // 重启后从 AOF 恢复数据
+        let config = Config { persistence, ..Config::default() };
+        let server = Server::builder().config(config).bind("127.0.0.1:0").start().await.unwrap();
+        assert_eq!(process_command(server.db(), "get foo").await, "bar");
+        drop(server);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
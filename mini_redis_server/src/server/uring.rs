@@ -0,0 +1,243 @@
+//! io_uring 前端
+//!
+//! 在 [tokio-uring](tokio_uring) 运行时上接受连接并收发数据：读写作为 io_uring 的提交项交给内核，
+//! 完成后再通知任务，不经过 epoll 的就绪通知与随后的 read / write 系统调用。
+//! 每个连接包装成实现 `AsyncRead` / `AsyncWrite` 的 [`UringStream`]，其上的帧解码、会话、
+//! 命令执行、连接数限制与空闲超时都与默认前端相同（见 [`server`](super)）。
+//!
+//! tokio-uring 运行时只使用当前线程：[`run`] 阻塞调用它的线程直到服务器退出，
+//! 不能在 tokio 运行时的线程中直接调用。套接字选项设置在监听套接字上，由之后接受的连接继承，
+//! CONFIG SET 修改后对新连接生效。
+
+use std::{
+    io::{self, IoSlice},
+    mem,
+    net::{Shutdown, TcpListener as StdTcpListener},
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+    task::{Context, Poll, ready},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_uring::net::{TcpListener, TcpStream};
+use tracing::Instrument;
+
+use super::{ConnectionLimit, cron, serve};
+use crate::db::{Db, Storage};
+
+/// 每次读取时缓冲区的容量
+const READ_CHUNK: usize = 16 * 1024;
+
+/// 进行中的读或写：完成时交还缓冲区
+type BufOp = Pin<Box<dyn Future<Output = (io::Result<usize>, Vec<u8>)>>>;
+
+/// 在 io_uring 运行时上运行服务器，阻塞当前线程直到接受连接失败
+pub fn run<S: Storage>(listener: StdTcpListener, db: Db<S>) -> io::Result<()> {
+    tokio_uring::start(async move {
+        db.replication().set_listening_port(listener.local_addr()?.port());
+
+        let cron = tokio::spawn(cron(db.clone()));
+        let result = accept_loop(listener, db).await;
+        cron.abort();
+        result
+    })
+}
+
+/// 接受连接，为每个连接启动一个本地任务；连接数限制与默认前端相同
+async fn accept_loop<S: Storage>(listener: StdTcpListener, db: Db<S>) -> io::Result<()> {
+    // 保留一个标准库的句柄，用来在监听套接字上设置选项
+    let options_socket = listener.try_clone()?;
+    let listener = TcpListener::from_std(listener);
+    let mut config = db.config().subscribe();
+    let limit = Arc::new(ConnectionLimit::new(config.borrow_and_update().max_connections));
+    let mut applied = None;
+    loop {
+        let permit = tokio::select! {
+            permit = limit.acquire() => permit,
+            Ok(()) = config.changed() => {
+                limit.resize(config.borrow_and_update().max_connections);
+                continue;
+            }
+        };
+        let options = config.borrow().socket;
+        if applied != Some(options) {
+            if let Err(e) = options.apply(&options_socket) {
+                tracing::warn!(error = %e, "failed to set socket options");
+            }
+            applied = Some(options);
+        }
+
+        let (socket, addr) = listener.accept().await?;
+        let span = tracing::debug_span!("connection", %addr);
+        if let Err(e) = socket.set_nodelay(options.nodelay) {
+            span.in_scope(|| tracing::warn!(error = %e, "failed to set socket options"));
+        }
+        let socket = UringStream::new(socket);
+        tokio_uring::spawn(serve(socket, addr, db.clone(), permit).instrument(span));
+    }
+}
+
+/// 适配为 `AsyncRead` / `AsyncWrite` 的 tokio-uring TCP 流
+///
+/// io_uring 的读写在完成前占有缓冲区，因此每个方向保留一个进行中的操作：
+/// - 读取完成后数据留在读缓冲区中，分一次或几次交给调用方，取完后再提交下一次读取。
+///   调用方不再等待（如 `select!` 的其他分支先完成）时操作仍然保留，下次读取时继续等待它，
+///   已经读到的数据不会丢失
+/// - 写入时把数据复制到写缓冲区后提交，操作完成前调用方重复调用 `poll_write` 时继续等待
+///   同一个操作，完成后返回实际写出的字节数
+pub struct UringStream {
+    stream: Rc<TcpStream>,
+    /// 已读到、尚未交给调用方的数据为 `read_buf[read_pos..]`
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    reading: Option<BufOp>,
+    /// 写缓冲区，写入进行中时由操作占有
+    write_buf: Vec<u8>,
+    writing: Option<BufOp>,
+}
+
+impl UringStream {
+    pub fn new(stream: TcpStream) -> Self {
+        Self {
+            stream: Rc::new(stream),
+            read_buf: Vec::with_capacity(READ_CHUNK),
+            read_pos: 0,
+            reading: None,
+            write_buf: Vec::new(),
+            writing: None,
+        }
+    }
+}
+
+impl AsyncRead for UringStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        if this.read_pos == this.read_buf.len() {
+            let reading = this.reading.get_or_insert_with(|| {
+                let mut read_buf = mem::take(&mut this.read_buf);
+                this.read_pos = 0;
+                read_buf.clear();
+                read_buf.reserve(READ_CHUNK);
+                let stream = this.stream.clone();
+                Box::pin(async move { stream.read(read_buf).await })
+            });
+            let (result, read_buf) = ready!(reading.as_mut().poll(cx));
+            this.reading = None;
+            this.read_buf = read_buf;
+            this.read_pos = 0;
+            // 读到 0 字节（对端关闭）时不填充 `buf`，调用方据此判断 EOF
+            result?;
+        }
+
+        let n = buf.remaining().min(this.read_buf.len() - this.read_pos);
+        buf.put_slice(&this.read_buf[this.read_pos..this.read_pos + n]);
+        this.read_pos += n;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for UringStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_write_vectored(cx, &[IoSlice::new(buf)])
+    }
+
+    /// 多个切片复制到同一个缓冲区，一次提交写出
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let writing = this.writing.get_or_insert_with(|| {
+            let mut write_buf = mem::take(&mut this.write_buf);
+            write_buf.clear();
+            for buf in bufs {
+                write_buf.extend_from_slice(buf);
+            }
+            let stream = this.stream.clone();
+            Box::pin(async move { stream.write(write_buf).submit().await })
+        });
+        let (result, write_buf) = ready!(writing.as_mut().poll(cx));
+        this.writing = None;
+        this.write_buf = write_buf;
+        Poll::Ready(result)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    /// 写入在 `poll_write` 返回前已经完成，没有需要 flush 的数据
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(self.stream.shutdown(Shutdown::Write))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use tokio::net::TcpStream;
+
+    use super::*;
+    use crate::{connection::Connection, frame::Frame, handler::process_command};
+
+    #[test]
+    fn test_serve_over_io_uring() {
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async {
+            let db = Db::new();
+            let listener = StdTcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            thread::spawn({
+                let db = db.clone();
+                move || run(listener, db)
+            });
+
+            let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+            let request =
+                |args: &[&str]| Frame::from(args.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+            conn.write_frame(&request(&["set", "a", "1"])).await.unwrap();
+            assert_eq!(conn.read_frame().await.unwrap(), Some(Frame::Simple("OK".into())));
+
+            // 流水线：一次写出多条命令，回复按顺序到达
+            let value = "x".repeat(3 * READ_CHUNK);
+            conn.feed_frame(&request(&["set", "big", &value])).await.unwrap();
+            for _ in 0..100 {
+                conn.feed_frame(&request(&["incr", "n"])).await.unwrap();
+            }
+            conn.feed_frame(&request(&["get", "big"])).await.unwrap();
+            conn.flush().await.unwrap();
+            assert_eq!(conn.read_frame().await.unwrap(), Some(Frame::Simple("OK".into())));
+            for i in 1..=100 {
+                assert_eq!(conn.read_frame().await.unwrap(), Some(Frame::Integer(i)));
+            }
+            assert_eq!(conn.read_frame().await.unwrap(), Some(Frame::Bulk(value)));
+            assert_eq!(process_command(&db, "get a").await, "1");
+
+            // 订阅状态下同时等待推送的消息
+            conn.write_frame(&request(&["subscribe", "news"])).await.unwrap();
+            conn.read_frame().await.unwrap();
+            assert_eq!(process_command(&db, "publish news hi").await, "(integer) 1");
+            let message = conn.read_frame().await.unwrap().unwrap();
+            assert_eq!(message.to_string(), "1) message\n2) news\n3) hi");
+
+            conn.write_frame(&request(&["quit"])).await.unwrap();
+            conn.read_frame().await.unwrap();
+            assert_eq!(conn.read_frame().await.unwrap(), None);
+        });
+    }
+}
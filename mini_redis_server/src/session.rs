@@ -0,0 +1,884 @@
+//! 客户端会话模块
+//!
+//! 保存单个客户端连接的状态（认证的用户、选中的数据库、协议版本、发布/订阅状态、监视状态、
+//! 只读标记、副本告知的监听端口、在客户端注册表中的登记），有状态的命令在这里执行，其余命令转交给
+//! [`handler::execute`](crate::handler::execute)。RESET 将这些状态恢复为初始值。
+//!
+//! 每条命令执行前先按当前用户的 ACL 规则检查权限（见 [`acl`](crate::acl)），
+//! 尚未认证的连接只能执行 AUTH、带 AUTH 选项的 HELLO、QUIT 与 RESET。从网络读取的命令还要经过限流
+//! （见 [`ratelimit`](crate::ratelimit)）与故障注入（见 [`fault`](crate::fault)）。写命令执行后记录到审计日志（见 [`audit`](crate::audit)）。
+//!
+//! 与 `handler` 一样不涉及 I/O：网络层只需把解析出的命令交给会话，
+//! 并把返回的回复帧以及 [`Session::next_message`] 推送的消息（订阅消息与 MONITOR 输出）写回客户端。
+
+use std::{future::Future, net::SocketAddr, time::Instant};
+
+use tracing::Instrument;
+
+use crate::{
+    acl::DEFAULT_USER,
+    audit::Entry,
+    client::{Client, Monitor},
+    command::{ClientKill, Command, Hello},
+    db::{Db, DbError, Keyspace, Storage},
+    fault::Fault,
+    frame::{Frame, Protocol},
+    handler::{self, Flag},
+    output::ClientClass,
+    pubsub::Subscriber,
+    ratelimit::Admission,
+    slot,
+};
+
+/// 一个客户端会话
+pub struct Session<S: Storage = Keyspace> {
+    /// 指向当前选中数据库的句柄
+    db: Db<S>,
+    /// 认证的用户，`None` 表示尚未认证
+    user: Option<String>,
+    /// 通过 HELLO 协商的协议版本
+    protocol: Protocol,
+    subscriber: Subscriber,
+    /// 进入 MONITOR 状态后接收命令流的通道
+    monitor: Option<Monitor>,
+    /// 通过 READONLY 标记为只读，拒绝写命令；把读流量分给副本的客户端用它防止误写
+    readonly: bool,
+    /// 对端作为副本时通过 `REPLCONF listening-port` 告知的端口
+    listening_port: Option<u16>,
+    /// 在客户端注册表中的登记，会话销毁时注销
+    client: Client,
+    /// 收到 QUIT 后置位，网络层写出回复后关闭连接
+    closing: bool,
+}
+
+impl<S: Storage> Session<S> {
+    /// 基于共享数据库创建会话
+    pub fn new(db: Db<S>) -> Self {
+        Self::register(db, None)
+    }
+
+    /// 为来自 `addr` 的网络连接创建会话
+    pub fn with_addr(db: Db<S>, addr: SocketAddr) -> Self {
+        Self::register(db, Some(addr))
+    }
+
+    fn register(db: Db<S>, addr: Option<SocketAddr>) -> Self {
+        let client = db.clients().register(addr);
+        let subscriber = db.pubsub().subscriber_with_output(client.output());
+        let user = db.acl().default_login();
+        let protocol = Protocol::default();
+        Self {
+            db,
+            user,
+            protocol,
+            subscriber,
+            monitor: None,
+            readonly: false,
+            listening_port: None,
+            client,
+            closing: false,
+        }
+    }
+
+    /// 会话在客户端注册表中的登记
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// 等待 CLIENT KILL 关闭本连接的通知，返回的 future 不借用会话
+    pub fn killed(&self) -> impl Future<Output = ()> + Send + use<S> {
+        self.client.killed()
+    }
+
+    /// 会话是否处于订阅状态（至少订阅了一个频道、模式或分片频道）
+    pub fn is_subscribed(&self) -> bool {
+        self.subscriber.is_active()
+    }
+
+    /// 连接使用的协议版本，网络层按它编码回复
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    /// 会话是否处于 MONITOR 状态
+    pub fn is_monitoring(&self) -> bool {
+        self.monitor.is_some()
+    }
+
+    /// 是否已收到 QUIT，网络层写出回复后应关闭连接
+    pub fn is_closing(&self) -> bool {
+        self.closing
+    }
+
+    /// 对端作为副本时的监听端口
+    pub fn listening_port(&self) -> Option<u16> {
+        self.listening_port
+    }
+
+    /// 执行从网络读取的命令：先按速率限制拒绝或推迟命令，再注入设置的延迟或断开，
+    /// 然后把原始参数推送给 MONITOR，最后与 [`Session::execute`] 一样执行
+    ///
+    /// 没有权限的命令不推送；AUTH、带 AUTH 选项的 HELLO 与 ACL SETUSER 含有密码，也不推送。
+    pub async fn execute_with_args(&mut self, command: Command, args: &[String]) -> Vec<Frame> {
+        let addr = self.client.addr().map(|addr| addr.ip());
+        match self.db.rate_limiter().acquire(self.client.id(), addr, Instant::now()) {
+            Admission::Allowed => {}
+            Admission::Delayed(wait) => tokio::time::sleep(wait).await,
+            Admission::Rejected => {
+                self.db.stats().command_throttled();
+                return vec![Frame::Error(DbError::Throttled.to_string())];
+            }
+        }
+        if !command.name().starts_with("debug|") {
+            match self.db.faults().before(command.name()) {
+                Some(Fault::Delay(wait)) => tokio::time::sleep(wait).await,
+                Some(Fault::Drop) => {
+                    self.closing = true;
+                    return Vec::new();
+                }
+                None => {}
+            }
+        }
+
+        if !matches!(
+            command,
+            Command::Auth(..)
+                | Command::AclSetUser(..)
+                | Command::Hello(Hello { auth: Some(_), .. })
+        ) && self.check_permission(&command).is_ok()
+        {
+            self.db.clients().feed_monitors(&self.client, self.db.index(), args);
+        }
+        self.execute(command).await
+    }
+
+    /// 执行一条命令，返回需要依次写回客户端的回复帧
+    ///
+    /// 订阅类命令对每个频道/模式各回复一帧，因此返回值是帧的列表。
+    /// 执行过程位于 `command` span 中，结束时以 TRACE 级别记录耗时与结果。
+    pub async fn execute(&mut self, command: Command) -> Vec<Frame> {
+        let span = tracing::trace_span!(
+            "command",
+            cmd = command.name(),
+            key = command.keys().first().copied(),
+            db = self.db.index(),
+        );
+        let started = Instant::now();
+        let subscribed = self.is_subscribed();
+        // 命令执行时被移走，先取出审计日志需要的命令名与键
+        let audited = (command.is_write() && self.db.audit().is_enabled()).then(|| {
+            let keys: Vec<_> = command.keys().into_iter().map(String::from).collect();
+            (command.name(), keys)
+        });
+        let db = self.db.index();
+        let replies = self.dispatch(command).instrument(span.clone()).await;
+        self.client.command_done();
+        // 进入或退出订阅状态时切换适用的输出缓冲区限制
+        if self.is_subscribed() != subscribed {
+            let class = if subscribed { ClientClass::Normal } else { ClientClass::PubSub };
+            self.client.set_class(class);
+        }
+
+        let _entered = span.enter();
+        let elapsed_us = started.elapsed().as_micros() as u64;
+        let error = replies.iter().find_map(|reply| match reply {
+            Frame::Error(e) => Some(e),
+            _ => None,
+        });
+        match error {
+            Some(error) => tracing::trace!(elapsed_us, outcome = "error", %error),
+            None => tracing::trace!(elapsed_us, outcome = "ok"),
+        }
+        if let Some((cmd, keys)) = audited {
+            self.db.audit().record(&Entry {
+                client: &self.client,
+                user: self.user.as_deref(),
+                db,
+                cmd,
+                keys: &keys,
+                ok: error.is_none(),
+            });
+        }
+        replies
+    }
+
+    async fn dispatch(&mut self, command: Command) -> Vec<Frame> {
+        self.client.record_command(command.name());
+        if let Err(e) = self.check_permission(&command) {
+            return vec![Frame::Error(e.to_string())];
+        }
+
+        match command {
+            Command::Auth(user, password) => {
+                let user = user.unwrap_or_else(|| DEFAULT_USER.to_string());
+                match self.db.acl().authenticate(&user, &password) {
+                    Ok(()) => {
+                        self.user = Some(user);
+                        vec![Frame::Simple("OK".into())]
+                    }
+                    Err(e) => vec![Frame::Error(e.to_string())],
+                }
+            }
+            Command::AclWhoAmI => {
+                vec![Frame::Bulk(self.user.clone().expect("checked by check_permission"))]
+            }
+            // 依次认证、设置名称、切换协议，任何一步失败时之后的步骤都不生效
+            Command::Hello(hello) => {
+                let protocol = match hello.protover {
+                    None => self.protocol,
+                    Some(2) => Protocol::Resp2,
+                    Some(3) => Protocol::Resp3,
+                    Some(_) => {
+                        return vec![Frame::Error("NOPROTO unsupported protocol version".into())];
+                    }
+                };
+                match hello.auth {
+                    Some((user, password)) => match self.db.acl().authenticate(&user, &password) {
+                        Ok(()) => self.user = Some(user),
+                        Err(e) => return vec![Frame::Error(e.to_string())],
+                    },
+                    None if self.user.is_none() => {
+                        return vec![Frame::Error(
+                            "NOAUTH HELLO must be called with the client already authenticated, \
+                             otherwise the HELLO <proto> AUTH <user> <pass> option can be used to \
+                             authenticate the client and select the RESP protocol version at the \
+                             same time"
+                                .into(),
+                        )];
+                    }
+                    None => {}
+                }
+                if let Some(name) = hello.name
+                    && let Err(e) = self.client.set_name(&name)
+                {
+                    return vec![Frame::Error(e.to_string())];
+                }
+                self.protocol = protocol;
+                vec![self.hello_reply()]
+            }
+            Command::Subscribe(channels) => channels
+                .into_iter()
+                .map(|channel| {
+                    let count = self.subscriber.subscribe(&channel);
+                    subscription_reply("subscribe", Some(channel), count)
+                })
+                .collect(),
+            Command::Unsubscribe(channels) => {
+                let channels =
+                    if channels.is_empty() { self.subscriber.channels() } else { channels };
+                self.unsubscribe_all("unsubscribe", channels, Subscriber::unsubscribe)
+            }
+            // 分片频道像键一样属于哈希槽，一条命令只能涉及同一个槽
+            Command::SSubscribe(channels) | Command::SUnsubscribe(channels)
+                if !slot::same_slot(channels.iter().map(String::as_str)) =>
+            {
+                vec![Frame::Error(DbError::CrossSlot.to_string())]
+            }
+            Command::SSubscribe(channels) => channels
+                .into_iter()
+                .map(|channel| {
+                    let count = self.subscriber.ssubscribe(&channel);
+                    subscription_reply("ssubscribe", Some(channel), count)
+                })
+                .collect(),
+            Command::SUnsubscribe(channels) => {
+                let channels =
+                    if channels.is_empty() { self.subscriber.shard_channels() } else { channels };
+                self.unsubscribe_all("sunsubscribe", channels, Subscriber::sunsubscribe)
+            }
+            Command::PSubscribe(patterns) => patterns
+                .into_iter()
+                .map(|pattern| {
+                    let count = self.subscriber.psubscribe(&pattern);
+                    subscription_reply("psubscribe", Some(pattern), count)
+                })
+                .collect(),
+            Command::PUnsubscribe(patterns) => {
+                let patterns =
+                    if patterns.is_empty() { self.subscriber.patterns() } else { patterns };
+                self.unsubscribe_all("punsubscribe", patterns, Subscriber::punsubscribe)
+            }
+            Command::Quit => {
+                self.closing = true;
+                vec![Frame::Simple("OK".into())]
+            }
+            Command::Reset => {
+                self.reset();
+                vec![Frame::Simple("RESET".into())]
+            }
+            // RESP2 的订阅状态下 PING 以数组回复，与推送的消息区分
+            Command::Ping(message) if self.is_subscribed() && self.protocol == Protocol::Resp2 => {
+                vec![Frame::Array(vec![
+                    Frame::Bulk("pong".into()),
+                    Frame::Bulk(message.unwrap_or_default()),
+                ])]
+            }
+            // 订阅状态下只允许执行订阅相关命令与 PING
+            ref command if self.is_subscribed() && !matches!(command, Command::Ping(_)) => {
+                vec![Frame::Error(
+                    "ERR only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed \
+                     in this context"
+                        .into(),
+                )]
+            }
+            Command::ReplConf(args) => {
+                if let [option, port] = args.as_slice()
+                    && option.eq_ignore_ascii_case("listening-port")
+                {
+                    match port.parse() {
+                        Ok(port) => self.listening_port = Some(port),
+                        Err(_) => return vec![Frame::Error("ERR value is out of range".into())],
+                    }
+                }
+                vec![Frame::Simple("OK".into())]
+            }
+            Command::Select(index) => match self.db.select(index) {
+                Ok(db) => {
+                    self.db = db;
+                    self.client.set_db(index);
+                    vec![Frame::Simple("OK".into())]
+                }
+                Err(e) => vec![Frame::Error(e.to_string())],
+            },
+            Command::Monitor => {
+                self.monitor = Some(self.client.monitor());
+                vec![Frame::Simple("OK".into())]
+            }
+            Command::ClientId => vec![Frame::Integer(self.client.id() as i64)],
+            Command::ClientSetName(name) => match self.client.set_name(&name) {
+                Ok(()) => vec![Frame::Simple("OK".into())],
+                Err(e) => vec![Frame::Error(e.to_string())],
+            },
+            Command::ClientSetInfo(attr, value) => match self.client.set_lib_info(&attr, &value) {
+                Ok(()) => vec![Frame::Simple("OK".into())],
+                Err(e) => vec![Frame::Error(e.to_string())],
+            },
+            Command::ClientGetName => match self.client.name() {
+                name if name.is_empty() => vec![Frame::Null],
+                name => vec![Frame::Bulk(name)],
+            },
+            Command::ClientKill(filter) => {
+                let killed = self.db.clients().kill(&filter, self.client.id());
+                vec![Frame::Integer(killed as i64)]
+            }
+            Command::ClientKillAddr(addr) => {
+                let filter = ClientKill { addr: Some(addr), ..ClientKill::default() };
+                match self.db.clients().kill(&filter, self.client.id()) {
+                    0 => vec![Frame::Error(DbError::NoSuchClient.to_string())],
+                    _ => vec![Frame::Simple("OK".into())],
+                }
+            }
+            Command::ReadOnly | Command::ReadWrite => {
+                self.readonly = matches!(command, Command::ReadOnly);
+                vec![Frame::Simple("OK".into())]
+            }
+            command if command.is_write() && self.readonly => {
+                vec![Frame::Error(
+                    "READONLY You can't write against a read only connection.".into(),
+                )]
+            }
+            // 只读副本只接受主节点同步过来的写命令
+            command if command.is_write() && self.db.replication().is_read_only() => {
+                vec![Frame::Error("READONLY You can't write against a read only replica.".into())]
+            }
+            command => vec![handler::execute(&self.db, command).await],
+        }
+    }
+
+    /// HELLO 的回复：服务器与连接的基本信息
+    fn hello_reply(&self) -> Frame {
+        let role = if self.db.replication().is_replica() { "replica" } else { "master" };
+        let fields = [
+            ("server", Frame::Bulk("redis".into())),
+            ("version", Frame::Bulk(env!("CARGO_PKG_VERSION").into())),
+            ("proto", Frame::Integer(self.protocol.version())),
+            ("id", Frame::Integer(self.client.id() as i64)),
+            ("mode", Frame::Bulk("standalone".into())),
+            ("role", Frame::Bulk(role.into())),
+            ("modules", Frame::Array(Vec::new())),
+        ];
+        Frame::Map(
+            fields.into_iter().map(|(name, value)| (Frame::Bulk(name.into()), value)).collect(),
+        )
+    }
+
+    /// RESET：退出订阅与监视状态，清除只读标记，选中 0 号数据库，协议版本、连接名称与用户恢复默认；
+    /// 客户端库的信息保留
+    fn reset(&mut self) {
+        self.subscriber = self.db.pubsub().subscriber_with_output(self.client.output());
+        self.monitor = None;
+        self.readonly = false;
+        self.protocol = Protocol::default();
+        self.db = self.db.select(0).expect("database 0 always exists");
+        self.client.set_db(0);
+        self.client.set_name("").expect("empty name is valid");
+        self.user = self.db.acl().default_login();
+    }
+
+    /// 检查当前用户能否执行命令，带 `no_auth` 标志的命令（AUTH、HELLO、QUIT、RESET）总是允许，
+    /// HELLO 由执行时检查是否已认证或带有 AUTH 选项
+    pub fn check_permission(&self, command: &Command) -> Result<(), DbError> {
+        let no_auth = handler::spec(command.name()).is_some_and(|spec| spec.has(Flag::NoAuth));
+        match (&self.user, command) {
+            _ if no_auth => Ok(()),
+            (None, _) => Err(DbError::NoAuth),
+            (Some(user), command) => self.db.acl().check(user, command),
+        }
+    }
+
+    /// 等待下一条推送：订阅消息转换为 `message` / `pmessage` / `smessage` 帧，
+    /// MONITOR 输出为简单字符串
+    pub async fn next_message(&mut self) -> Option<Frame> {
+        let subscribed = self.is_subscribed();
+        let Some(monitor) = &mut self.monitor else {
+            return self.subscriber.recv().await.map(Frame::from);
+        };
+        tokio::select! {
+            message = self.subscriber.recv(), if subscribed => message.map(Frame::from),
+            line = monitor.recv() => line.map(Frame::Simple),
+        }
+    }
+
+    /// 逐个退订，没有任何可退订的目标时也回复一帧（名称为空）
+    fn unsubscribe_all(
+        &mut self,
+        kind: &str,
+        names: Vec<String>,
+        unsubscribe: fn(&mut Subscriber, &str) -> usize,
+    ) -> Vec<Frame> {
+        if names.is_empty() {
+            let count = match kind {
+                "sunsubscribe" => self.subscriber.shard_count(),
+                _ => self.subscriber.count(),
+            };
+            return vec![subscription_reply(kind, None, count)];
+        }
+
+        names
+            .into_iter()
+            .map(|name| {
+                let count = unsubscribe(&mut self.subscriber, &name);
+                subscription_reply(kind, Some(name), count)
+            })
+            .collect()
+    }
+}
+
+/// 构造 `[kind, name, count]` 形式的订阅确认帧（RESP3 下为推送消息）
+fn subscription_reply(kind: &str, name: Option<String>, count: usize) -> Frame {
+    Frame::Push(vec![
+        Frame::Bulk(kind.into()),
+        name.map_or(Frame::Null, Frame::Bulk),
+        Frame::Integer(count as i64),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Session;
+    use crate::{
+        command::Command,
+        db::Db,
+        frame::{Frame, Protocol},
+        handler::process_command,
+    };
+
+    async fn run(session: &mut Session, input: &str) -> Vec<String> {
+        match Command::parse(input) {
+            Ok(command) => session.execute(command).await.iter().map(Frame::to_string).collect(),
+            Err(e) => vec![e.to_string()],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_and_receive() {
+        let db = Db::new();
+        let mut session = Session::new(db.clone());
+
+        assert_eq!(
+            run(&mut session, "subscribe a b").await,
+            vec!["1) subscribe\n2) a\n3) (integer) 1", "1) subscribe\n2) b\n3) (integer) 2"]
+        );
+        assert_eq!(process_command(&db, "publish b hi").await, "(integer) 1");
+        assert_eq!(session.next_message().await.unwrap().to_string(), "1) message\n2) b\n3) hi");
+    }
+
+    #[tokio::test]
+    async fn test_psubscribe_and_receive() {
+        let db = Db::new();
+        let mut session = Session::new(db.clone());
+
+        assert_eq!(
+            run(&mut session, "psubscribe news.*").await,
+            vec!["1) psubscribe\n2) news.*\n3) (integer) 1"]
+        );
+        assert_eq!(process_command(&db, "publish news.tech rust").await, "(integer) 1");
+        assert_eq!(process_command(&db, "publish sports ball").await, "(integer) 0");
+        assert_eq!(
+            session.next_message().await.unwrap().to_string(),
+            "1) pmessage\n2) news.*\n3) news.tech\n4) rust"
+        );
+
+        assert_eq!(
+            run(&mut session, "punsubscribe").await,
+            vec!["1) punsubscribe\n2) news.*\n3) (integer) 0"]
+        );
+        assert_eq!(process_command(&db, "publish news.tech rust").await, "(integer) 0");
+        assert_eq!(
+            run(&mut session, "punsubscribe").await,
+            vec!["1) punsubscribe\n2) (nil)\n3) (integer) 0"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ssubscribe_and_receive() {
+        let db = Db::new();
+        let mut session = Session::new(db.clone());
+
+        assert_eq!(
+            run(&mut session, "ssubscribe {user}:1 {user}:2").await,
+            vec![
+                "1) ssubscribe\n2) {user}:1\n3) (integer) 1",
+                "1) ssubscribe\n2) {user}:2\n3) (integer) 2"
+            ]
+        );
+        assert!(run(&mut session, "ssubscribe a b").await[0].starts_with("CROSSSLOT"));
+        assert!(session.is_subscribed());
+        assert!(run(&mut session, "get a").await[0].starts_with("ERR only (P|S)SUBSCRIBE"));
+
+        // 普通频道与分片频道互不相通
+        assert_eq!(process_command(&db, "publish {user}:1 hi").await, "(integer) 0");
+        assert_eq!(process_command(&db, "spublish {user}:1 hi").await, "(integer) 1");
+        assert_eq!(
+            session.next_message().await.unwrap().to_string(),
+            "1) smessage\n2) {user}:1\n3) hi"
+        );
+        assert_eq!(process_command(&db, "pubsub shardchannels").await, "1) {user}:1\n2) {user}:2");
+        assert_eq!(
+            process_command(&db, "pubsub shardnumsub {user}:2 x").await,
+            "1) {user}:2\n2) (integer) 1\n3) x\n4) (integer) 0"
+        );
+
+        run(&mut session, "subscribe ch").await;
+        assert_eq!(
+            run(&mut session, "sunsubscribe").await,
+            vec![
+                "1) sunsubscribe\n2) {user}:1\n3) (integer) 1",
+                "1) sunsubscribe\n2) {user}:2\n3) (integer) 0"
+            ]
+        );
+        assert_eq!(
+            run(&mut session, "sunsubscribe").await,
+            vec!["1) sunsubscribe\n2) (nil)\n3) (integer) 0"]
+        );
+        run(&mut session, "unsubscribe").await;
+        assert!(!session.is_subscribed());
+        assert_eq!(process_command(&db, "pubsub shardchannels").await, "(empty array)");
+    }
+
+    #[tokio::test]
+    async fn test_pubsub_introspection() {
+        let db = Db::new();
+        let mut session = Session::new(db.clone());
+        run(&mut session, "subscribe news sports").await;
+        run(&mut session, "psubscribe n*").await;
+
+        assert_eq!(process_command(&db, "pubsub channels").await, "1) news\n2) sports");
+        assert_eq!(process_command(&db, "pubsub channels s*").await, "1) sports");
+        assert_eq!(
+            process_command(&db, "pubsub numsub news other").await,
+            "1) news\n2) (integer) 1\n3) other\n4) (integer) 0"
+        );
+        assert_eq!(process_command(&db, "pubsub numpat").await, "(integer) 1");
+
+        drop(session);
+        assert_eq!(process_command(&db, "pubsub channels").await, "(empty array)");
+        assert_eq!(process_command(&db, "pubsub numpat").await, "(integer) 0");
+    }
+
+    #[tokio::test]
+    async fn test_subscribed_context_rejects_commands() {
+        let db = Db::new();
+        let mut session = Session::new(db);
+
+        assert_eq!(run(&mut session, "set a 1").await, vec!["OK"]);
+        run(&mut session, "subscribe ch").await;
+        assert!(run(&mut session, "get a").await[0].starts_with("ERR only"));
+
+        run(&mut session, "unsubscribe").await;
+        assert_eq!(run(&mut session, "get a").await, vec!["1"]);
+    }
+
+    #[tokio::test]
+    async fn test_ping_and_echo() {
+        let mut session = Session::new(Db::new());
+
+        assert_eq!(run(&mut session, "ping").await, vec!["PONG"]);
+        assert_eq!(run(&mut session, "echo hi").await, vec!["hi"]);
+        run(&mut session, "subscribe ch").await;
+        assert_eq!(run(&mut session, "ping").await, vec!["1) pong\n2) "]);
+        assert_eq!(run(&mut session, "ping hi").await, vec!["1) pong\n2) hi"]);
+        assert!(run(&mut session, "echo hi").await[0].starts_with("ERR only"));
+    }
+
+    #[tokio::test]
+    async fn test_quit_and_reset() {
+        let db = Db::new();
+        let mut session = Session::new(db.clone());
+        run(&mut session, "hello 3").await;
+        run(&mut session, "select 2").await;
+        run(&mut session, "client setname conn").await;
+        run(&mut session, "subscribe ch").await;
+        run(&mut session, "monitor").await;
+
+        assert_eq!(run(&mut session, "reset").await, vec!["RESET"]);
+        assert!(!session.is_subscribed() && !session.is_monitoring());
+        assert_eq!(session.protocol(), Protocol::Resp2);
+        assert_eq!(process_command(&db, "pubsub numsub ch").await, "1) ch\n2) (integer) 0");
+        assert_eq!(run(&mut session, "client getname").await, vec!["(nil)"]);
+        assert_eq!(run(&mut session, "set a 1").await, vec!["OK"]);
+        assert_eq!(process_command(&db, "get a").await, "1");
+
+        assert!(!session.is_closing());
+        assert_eq!(run(&mut session, "quit").await, vec!["OK"]);
+        assert!(session.is_closing());
+    }
+
+    #[tokio::test]
+    async fn test_readonly() {
+        let db = Db::new();
+        let mut session = Session::new(db.clone());
+
+        assert_eq!(run(&mut session, "readonly").await, vec!["OK"]);
+        assert!(run(&mut session, "set a 1").await[0].starts_with("READONLY "));
+        assert_eq!(run(&mut session, "get a").await, vec!["(nil)"]);
+        assert_eq!(run(&mut session, "readwrite").await, vec!["OK"]);
+        assert_eq!(run(&mut session, "set a 1").await, vec!["OK"]);
+
+        run(&mut session, "readonly").await;
+        run(&mut session, "reset").await;
+        assert_eq!(run(&mut session, "del a").await, vec!["(integer) 1"]);
+    }
+
+    #[tokio::test]
+    async fn test_select() {
+        let db = Db::new();
+        let mut session = Session::new(db.clone());
+
+        assert_eq!(run(&mut session, "select 1").await, vec!["OK"]);
+        assert_eq!(run(&mut session, "set a 1").await, vec!["OK"]);
+        assert_eq!(process_command(&db, "get a").await, "(nil)");
+        assert_eq!(run(&mut session, "move a 0").await, vec!["(integer) 1"]);
+        assert_eq!(process_command(&db, "get a").await, "1");
+        assert_eq!(run(&mut session, "select 16").await, vec!["ERR DB index is out of range"]);
+        assert_eq!(run(&mut session, "get a").await, vec!["(nil)"]);
+    }
+
+    #[tokio::test]
+    async fn test_acl_enforcement() {
+        let db = Db::new();
+        let mut admin = Session::new(db.clone());
+        assert_eq!(run(&mut admin, "acl whoami").await, vec!["default"]);
+        let setuser = "acl setuser alice on >secret ~app:* +@read +@connection +set";
+        assert_eq!(run(&mut admin, setuser).await, vec!["OK"]);
+
+        let mut session = Session::new(db.clone());
+        assert!(run(&mut session, "auth alice wrong").await[0].starts_with("WRONGPASS"));
+        assert_eq!(run(&mut session, "auth alice secret").await, vec!["OK"]);
+        assert_eq!(run(&mut session, "acl whoami").await, vec!["alice"]);
+        assert_eq!(run(&mut session, "set app:1 v").await, vec!["OK"]);
+        assert_eq!(run(&mut session, "get app:1").await, vec!["v"]);
+        assert_eq!(
+            run(&mut session, "get other").await,
+            vec!["NOPERM No permissions to access a key"]
+        );
+        assert_eq!(
+            run(&mut session, "del app:1").await,
+            vec!["NOPERM User alice has no permissions to run the 'del' command"]
+        );
+
+        // default 用户设置密码后，新连接需要先认证
+        run(&mut admin, "acl setuser default resetpass >pw").await;
+        let mut session = Session::new(db);
+        assert_eq!(run(&mut session, "get app:1").await, vec!["NOAUTH Authentication required."]);
+        assert_eq!(run(&mut session, "auth pw").await, vec!["OK"]);
+        assert_eq!(run(&mut session, "get app:1").await, vec!["v"]);
+        // RESET 取消认证，未认证时仍可执行 RESET 与 QUIT
+        assert_eq!(run(&mut session, "reset").await, vec!["RESET"]);
+        assert_eq!(run(&mut session, "ping").await, vec!["NOAUTH Authentication required."]);
+        assert_eq!(run(&mut session, "reset").await, vec!["RESET"]);
+
+        // HELLO 在一次往返中认证、设置名称并切换协议，认证失败时都不生效
+        assert!(run(&mut session, "hello 3").await[0].starts_with("NOAUTH HELLO must be called"));
+        let wrong = run(&mut session, "hello 3 auth default wrong setname app").await;
+        assert!(wrong[0].starts_with("WRONGPASS"));
+        assert_eq!((session.protocol(), session.client.name()), (Protocol::Resp2, String::new()));
+        let reply = session.execute(Command::parse("hello 3 auth default pw setname app").unwrap());
+        assert!(matches!(reply.await[0], Frame::Map(_)));
+        assert_eq!((session.protocol(), session.client.name()), (Protocol::Resp3, "app".into()));
+        assert_eq!(run(&mut session, "get app:1").await, vec!["v"]);
+        assert_eq!(run(&mut session, "quit").await, vec!["OK"]);
+    }
+
+    #[tokio::test]
+    async fn test_client_commands() {
+        let db = Db::new();
+        let mut session = Session::new(db.clone());
+        let mut other = Session::with_addr(db.clone(), "127.0.0.1:5000".parse().unwrap());
+
+        assert_eq!(run(&mut session, "client id").await, vec!["(integer) 1"]);
+        assert_eq!(run(&mut other, "client id").await, vec!["(integer) 2"]);
+        assert_eq!(run(&mut session, "client getname").await, vec!["(nil)"]);
+        assert_eq!(run(&mut session, "client setname app").await, vec!["OK"]);
+        assert_eq!(run(&mut session, "client getname").await, vec!["app"]);
+        let invalid = session.execute(Command::ClientSetName("a b".into())).await;
+        assert!(invalid[0].to_string().starts_with("ERR Client names"));
+        assert_eq!(run(&mut session, "client setinfo lib-ver 1.0").await, vec!["OK"]);
+        let invalid =
+            session.execute(Command::ClientSetInfo("lib-name".into(), "a b".into())).await;
+        assert!(invalid[0].to_string().starts_with("ERR lib-name cannot contain"));
+        assert_eq!(run(&mut session, "client setinfo LIB-NAME mini").await, vec!["OK"]);
+
+        run(&mut other, "select 2").await;
+        let list = process_command(&db, "client list").await;
+        assert_eq!(
+            list,
+            "id=1 addr= name=app age=0 idle=0 db=0 omem=0 cmd=client|setinfo lib-name=mini \
+             lib-ver=1.0\n\
+             id=2 addr=127.0.0.1:5000 name= age=0 idle=0 db=2 omem=0 cmd=select lib-name= \
+             lib-ver=\n"
+        );
+
+        // 新写法默认不关闭自身，旧写法找不到连接时报错
+        assert_eq!(run(&mut session, "client kill id 1").await, vec!["(integer) 0"]);
+        assert_eq!(run(&mut session, "client kill 127.0.0.1:1").await, vec!["ERR No such client"]);
+        assert_eq!(run(&mut session, "client kill 127.0.0.1:5000").await, vec!["OK"]);
+        other.killed().await;
+
+        drop(other);
+        assert!(process_command(&db, "client list").await.starts_with("id=1 "));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let db = Db::new();
+        let mut session = Session::with_addr(db.clone(), "127.0.0.1:5000".parse().unwrap());
+        let mut other = Session::with_addr(db.clone(), "127.0.0.1:5001".parse().unwrap());
+        db.config_set(&[("client-rate-limit".into(), "2".into())]).unwrap();
+        async fn ping(session: &mut Session) -> String {
+            let args = ["ping".to_string()];
+            session.execute_with_args(Command::Ping(None), &args).await[0].to_string()
+        }
+
+        assert_eq!(ping(&mut session).await, "PONG");
+        assert_eq!(ping(&mut session).await, "PONG");
+        assert!(ping(&mut session).await.starts_with("THROTTLED"));
+        assert_eq!(ping(&mut other).await, "PONG");
+        assert_eq!(db.stats().throttled_commands(), 1);
+
+        // 按地址计数时同一 IP 的连接共用令牌；delay 模式下等待令牌补足
+        let pairs = [
+            ("client-rate-limit", "10"),
+            ("client-rate-limit-burst", "1"),
+            ("client-rate-limit-key", "addr"),
+            ("client-rate-limit-mode", "delay"),
+        ];
+        db.config_set(&pairs.map(|(name, value)| (name.into(), value.into()))).unwrap();
+        let started = std::time::Instant::now();
+        for _ in 0..3 {
+            assert_eq!(ping(&mut session).await, "PONG");
+            assert_eq!(ping(&mut other).await, "PONG");
+        }
+        let elapsed = started.elapsed();
+        assert!(elapsed >= std::time::Duration::from_millis(450), "{elapsed:?}");
+        assert_eq!(db.stats().throttled_commands(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() {
+        let dir =
+            std::env::temp_dir().join(format!("mini-redis-{}-session-audit", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit.log").to_str().unwrap().to_string();
+        let db = Db::new();
+        let mut session = Session::with_addr(db.clone(), "127.0.0.1:5000".parse().unwrap());
+        db.config_set(&[("audit-log-file".into(), path.clone())]).unwrap();
+
+        run(&mut session, "client setname app").await;
+        run(&mut session, "sunionstore a b c").await;
+        run(&mut session, "get a").await;
+        run(&mut session, "select 1").await;
+        run(&mut session, "incr a").await;
+        run(&mut session, "readonly").await;
+        run(&mut session, "del b").await;
+
+        // 只记录写命令，被拒绝的写命令结果为 err
+        let log = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = log.lines().map(|line| line.split_once(' ').unwrap().1).collect();
+        let prefix = "id=1 addr=127.0.0.1:5000 name=app user=default";
+        assert_eq!(
+            lines,
+            [
+                format!(r#"{prefix} db=0 cmd=sunionstore result=ok "a" "b" "c""#),
+                format!(r#"{prefix} db=1 cmd=incr result=ok "a""#),
+                format!(r#"{prefix} db=1 cmd=del result=err "b""#),
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_monitor() {
+        let db = Db::new();
+        let mut monitor = Session::new(db.clone());
+        let mut session = Session::with_addr(db, "127.0.0.1:5000".parse().unwrap());
+        assert_eq!(run(&mut monitor, "monitor").await, vec!["OK"]);
+        assert!(monitor.is_monitoring());
+
+        for input in ["set k v", "auth secret", "select 1", "get k"] {
+            let args: Vec<String> = input.split_whitespace().map(String::from).collect();
+            session.execute_with_args(Command::parse(input).unwrap(), &args).await;
+        }
+
+        // AUTH 不推送，SELECT 之后的命令显示新的数据库
+        for expected in [
+            r#"[0 127.0.0.1:5000] "set" "k" "v""#,
+            r#"[0 127.0.0.1:5000] "select" "1""#,
+            r#"[1 127.0.0.1:5000] "get" "k""#,
+        ] {
+            let line = monitor.next_message().await.unwrap().to_string();
+            assert!(line.ends_with(expected), "{line}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hello() {
+        let mut session = Session::new(Db::new());
+        assert_eq!(session.protocol(), Protocol::Resp2);
+
+        let reply = session.execute(Command::parse("hello 3").unwrap()).await.remove(0);
+        let Frame::Map(fields) = reply else { panic!("{reply:?}") };
+        assert_eq!(fields[2], (Frame::Bulk("proto".into()), Frame::Integer(3)));
+        assert_eq!(fields[3], (Frame::Bulk("id".into()), Frame::Integer(1)));
+        assert_eq!(session.protocol(), Protocol::Resp3);
+
+        // 不带版本号时保持当前协议
+        run(&mut session, "hello").await;
+        assert_eq!(session.protocol(), Protocol::Resp3);
+        assert_eq!(
+            run(&mut session, "hello 4").await,
+            vec!["NOPROTO unsupported protocol version"]
+        );
+        assert_eq!(session.protocol(), Protocol::Resp3);
+    }
+
+    #[tokio::test]
+    async fn test_replconf_listening_port() {
+        let mut session = Session::new(Db::new());
+
+        assert_eq!(run(&mut session, "replconf listening-port 6380").await, vec!["OK"]);
+        assert_eq!(session.listening_port(), Some(6380));
+        assert_eq!(run(&mut session, "replconf capa psync2").await, vec!["OK"]);
+        assert!(run(&mut session, "replconf listening-port x").await[0].starts_with("ERR"));
+    }
+}
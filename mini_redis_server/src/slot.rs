@@ -0,0 +1,60 @@
+//! 哈希槽
+//!
+//! 与 Redis Cluster 相同，键与分片频道按 `CRC16(name) mod 16384` 映射到 [`SLOTS`] 个槽之一。
+//! 名称中含有非空的 `{...}` 时只对第一对花括号内的部分（hash tag）计算，
+//! 使 `{user}:1` 与 `{user}:2` 落在同一个槽中。
+
+/// 哈希槽的数量
+pub const SLOTS: u16 = 16384;
+
+/// 名称所属的哈希槽
+pub fn key_slot(name: &str) -> u16 {
+    crc16(hash_tag(name).as_bytes()) % SLOTS
+}
+
+/// 全部名称是否属于同一个槽
+pub fn same_slot<'a>(names: impl IntoIterator<Item = &'a str>) -> bool {
+    let mut slots = names.into_iter().map(key_slot);
+    let first = slots.next();
+    slots.all(|slot| Some(slot) == first)
+}
+
+/// 参与计算的部分：第一对花括号之间非空时取其内容，否则取整个名称
+fn hash_tag(name: &str) -> &str {
+    name.split_once('{')
+        .and_then(|(_, rest)| rest.split_once('}'))
+        .map(|(tag, _)| tag)
+        .filter(|tag| !tag.is_empty())
+        .unwrap_or(name)
+}
+
+/// CRC16-CCITT（XMODEM）：多项式 0x1021，初始值 0
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ (u16::from(byte) << 8), |crc, _| {
+            if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_slot() {
+        assert_eq!(crc16(b"123456789"), 0x31C3);
+        assert_eq!(key_slot("foo"), 12182);
+        assert_eq!(key_slot(""), 0);
+
+        assert_eq!(key_slot("{user1000}.following"), key_slot("user1000"));
+        assert_eq!(key_slot("foo{}{bar}"), key_slot("foo{}{bar}"));
+        assert_ne!(key_slot("foo{}{bar}"), key_slot("bar"));
+        assert_eq!(key_slot("foo{{bar}}zap"), key_slot("{bar"));
+        assert_eq!(key_slot("foo{bar}{zap}"), key_slot("bar"));
+
+        assert!(same_slot(["{a}1", "{a}2", "a"]));
+        assert!(!same_slot(["foo", "bar"]));
+        assert!(same_slot([]));
+    }
+}
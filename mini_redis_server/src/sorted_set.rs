@@ -0,0 +1,455 @@
+//! 有序集合模块
+//!
+//! 有序集合由两部分组成：
+//! - `HashMap<member, score>`：O(1) 查询成员的分值
+//! - [`SkipList`]：按 `(score, member)` 排序，支持按排名、按分值的范围查询
+//!
+//! 两个结构始终保持同步，所有修改都通过 [`SortedSet`] 的方法完成。
+
+mod skiplist;
+
+use std::collections::HashMap;
+
+pub use skiplist::SkipList;
+
+/// ZADD 的条件选项
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AddFlags {
+    /// 只添加新成员，不更新已有成员
+    pub nx: bool,
+    /// 只更新已有成员，不添加新成员
+    pub xx: bool,
+    /// 只在新分值大于旧分值时更新
+    pub gt: bool,
+    /// 只在新分值小于旧分值时更新
+    pub lt: bool,
+    /// 返回值统计被修改的成员数量（新增 + 更新），而不仅是新增数量
+    pub ch: bool,
+}
+
+/// 分值范围，对应 ZRANGEBYSCORE 的 `min` / `max` 参数
+///
+/// 参数形如 `1.5`（闭区间）、`(1.5`（开区间）、`-inf`、`+inf`。
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScoreRange {
+    pub min: f64,
+    pub max: f64,
+    pub min_exclusive: bool,
+    pub max_exclusive: bool,
+}
+
+impl ScoreRange {
+    /// 解析 `min` 与 `max` 参数，格式不合法时返回 `None`
+    pub fn parse(min: &str, max: &str) -> Option<Self> {
+        fn bound(arg: &str) -> Option<(f64, bool)> {
+            let (arg, exclusive) = match arg.strip_prefix('(') {
+                Some(rest) => (rest, true),
+                None => (arg, false),
+            };
+            arg.parse::<f64>().ok().filter(|v| !v.is_nan()).map(|v| (v, exclusive))
+        }
+
+        let (min, min_exclusive) = bound(min)?;
+        let (max, max_exclusive) = bound(max)?;
+        Some(Self { min, max, min_exclusive, max_exclusive })
+    }
+
+    /// 分值是否位于下界之下
+    fn below_min(&self, score: f64) -> bool {
+        if self.min_exclusive { score <= self.min } else { score < self.min }
+    }
+
+    /// 分值是否不超过上界
+    fn within_max(&self, score: f64) -> bool {
+        if self.max_exclusive { score < self.max } else { score <= self.max }
+    }
+}
+
+/// 字典序范围的边界
+#[derive(Clone, Debug, PartialEq)]
+pub enum LexBound {
+    /// `-`：负无穷
+    NegInf,
+    /// `+`：正无穷
+    PosInf,
+    /// `[member`：包含该成员
+    Inclusive(String),
+    /// `(member`：不包含该成员
+    Exclusive(String),
+}
+
+impl LexBound {
+    fn parse(arg: &str) -> Option<Self> {
+        match arg {
+            "-" => Some(LexBound::NegInf),
+            "+" => Some(LexBound::PosInf),
+            _ => match arg.split_at_checked(1)? {
+                ("[", member) => Some(LexBound::Inclusive(member.to_string())),
+                ("(", member) => Some(LexBound::Exclusive(member.to_string())),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// 字典序范围，对应 ZRANGEBYLEX 的 `min` / `max` 参数
+///
+/// 与 Redis 一致，字典序范围查询假定所有成员的分值相同。
+#[derive(Clone, Debug, PartialEq)]
+pub struct LexRange {
+    pub min: LexBound,
+    pub max: LexBound,
+}
+
+impl LexRange {
+    /// 解析 `min` 与 `max` 参数，格式不合法时返回 `None`
+    pub fn parse(min: &str, max: &str) -> Option<Self> {
+        Some(Self { min: LexBound::parse(min)?, max: LexBound::parse(max)? })
+    }
+
+    /// 成员是否位于下界之下
+    fn below_min(&self, member: &str) -> bool {
+        match &self.min {
+            LexBound::NegInf => false,
+            LexBound::PosInf => true,
+            LexBound::Inclusive(min) => member < min.as_str(),
+            LexBound::Exclusive(min) => member <= min.as_str(),
+        }
+    }
+
+    /// 成员是否不超过上界
+    fn within_max(&self, member: &str) -> bool {
+        match &self.max {
+            LexBound::NegInf => false,
+            LexBound::PosInf => true,
+            LexBound::Inclusive(max) => member <= max.as_str(),
+            LexBound::Exclusive(max) => member < max.as_str(),
+        }
+    }
+}
+
+/// 单个成员写入的结果
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AddOutcome {
+    /// 新增了成员
+    Added,
+    /// 更新了已有成员的分值
+    Updated,
+    /// 成员已存在且分值未变，或因条件选项被跳过
+    Unchanged,
+}
+
+/// 有序集合
+#[derive(Clone, Debug, Default)]
+pub struct SortedSet {
+    scores: HashMap<String, f64>,
+    list: SkipList,
+}
+
+impl PartialEq for SortedSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.scores == other.scores
+    }
+}
+
+impl SortedSet {
+    /// 创建一个空的有序集合
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 成员数量
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// 查询成员的分值
+    pub fn score(&self, member: &str) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// 按 ZADD 的条件选项写入成员
+    pub fn add(&mut self, member: String, score: f64, flags: AddFlags) -> AddOutcome {
+        match self.scores.get(&member).copied() {
+            None if flags.xx => AddOutcome::Unchanged,
+            None => {
+                self.insert(member, score);
+                AddOutcome::Added
+            }
+            Some(_) if flags.nx => AddOutcome::Unchanged,
+            Some(old) if old == score || (flags.gt && score < old) || (flags.lt && score > old) => {
+                AddOutcome::Unchanged
+            }
+            Some(_) => {
+                self.insert(member, score);
+                AddOutcome::Updated
+            }
+        }
+    }
+
+    /// 无条件写入成员的分值，成员是新增的返回 `true`
+    pub fn insert(&mut self, member: String, score: f64) -> bool {
+        let old = self.scores.insert(member.clone(), score);
+        if let Some(old) = old {
+            self.list.remove(old, &member);
+        }
+        self.list.insert(score, member);
+        old.is_none()
+    }
+
+    /// 将成员的分值加上 `delta`，成员不存在时视为 0；结果为 NaN 时不做修改并返回 `None`
+    pub fn incr(&mut self, member: String, delta: f64) -> Option<f64> {
+        let score = self.score(&member).unwrap_or(0.0) + delta;
+        if score.is_nan() {
+            return None;
+        }
+
+        self.insert(member, score);
+        Some(score)
+    }
+
+    /// 返回成员按分值从小到大的排名（从 0 开始）
+    pub fn rank(&self, member: &str) -> Option<usize> {
+        self.list.rank(self.score(member)?, member)
+    }
+
+    /// 删除成员，成员不存在时返回 `false`
+    pub fn remove(&mut self, member: &str) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => self.list.remove(score, member),
+            None => false,
+        }
+    }
+
+    /// 按排名范围返回成员及分值，`start` / `stop` 为闭区间，负数表示从末尾倒数
+    ///
+    /// `rev` 为 `true` 时按分值从大到小排名。
+    pub fn range(&self, start: i64, stop: i64, rev: bool) -> Vec<(String, f64)> {
+        let len = self.len() as i64;
+        let start = if start < 0 { (len + start).max(0) } else { start };
+        let stop = if stop < 0 { len + stop } else { stop.min(len - 1) };
+        if start > stop || start >= len {
+            return Vec::new();
+        }
+
+        let count = (stop - start + 1) as usize;
+        let iter = if rev {
+            self.list.rev_iter_from(start as usize)
+        } else {
+            self.list.iter_from(start as usize)
+        };
+        iter.take(count).map(|(member, score)| (member.to_string(), score)).collect()
+    }
+
+    /// 弹出分值最小（`max` 为 `true` 时为最大）的最多 `count` 个成员
+    pub fn pop(&mut self, count: usize, max: bool) -> Vec<(String, f64)> {
+        // 先截断到集合大小再转换，避免 count 为 0 时得到 -1（即“到末尾”）或大数溢出
+        let count = count.min(self.len());
+        if count == 0 {
+            return Vec::new();
+        }
+
+        let popped = self.range(0, count as i64 - 1, max);
+        for (member, _) in &popped {
+            self.remove(member);
+        }
+        popped
+    }
+
+    /// 返回分值位于 `range` 内的成员，`offset` / `count` 对应 `LIMIT offset count`
+    ///
+    /// `offset` 为负数时返回空列表，`count` 为负数表示不限数量。
+    pub fn range_by_score(
+        &self,
+        range: &ScoreRange,
+        offset: i64,
+        count: i64,
+    ) -> Vec<(String, f64)> {
+        let start = self.list.count_while(|score, _| range.below_min(score));
+        self.collect_from(start, offset, count, |score, _| range.within_max(score))
+    }
+
+    /// 返回字典序位于 `range` 内的成员，`offset` / `count` 的语义同 [`SortedSet::range_by_score`]
+    pub fn range_by_lex(&self, range: &LexRange, offset: i64, count: i64) -> Vec<(String, f64)> {
+        let start = self.list.count_while(|_, member| range.below_min(member));
+        self.collect_from(start, offset, count, |_, member| range.within_max(member))
+    }
+
+    /// 从排名 `start` 开始跳过 `offset` 个成员，收集满足 `within` 的成员
+    fn collect_from(
+        &self,
+        start: usize,
+        offset: i64,
+        count: i64,
+        within: impl Fn(f64, &str) -> bool,
+    ) -> Vec<(String, f64)> {
+        let Ok(offset) = usize::try_from(offset) else {
+            return Vec::new();
+        };
+        let count = usize::try_from(count).unwrap_or(usize::MAX);
+
+        self.list
+            .iter_from(start)
+            .take_while(|&(member, score)| within(score, member))
+            .skip(offset)
+            .take(count)
+            .map(|(member, score)| (member.to_string(), score))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AddFlags, AddOutcome, LexBound, LexRange, ScoreRange, SortedSet};
+
+    fn sample() -> SortedSet {
+        let mut zset = SortedSet::new();
+        zset.insert("a".into(), 1.0);
+        zset.insert("b".into(), 2.0);
+        zset.insert("c".into(), 3.0);
+        zset
+    }
+
+    fn names(items: Vec<(String, f64)>) -> Vec<String> {
+        items.into_iter().map(|(member, _)| member).collect()
+    }
+
+    #[test]
+    fn test_insert_updates_score() {
+        let mut zset = sample();
+
+        assert!(!zset.insert("a".into(), 5.0));
+        assert_eq!(zset.score("a"), Some(5.0));
+        assert_eq!(zset.len(), 3);
+        assert_eq!(names(zset.range(0, -1, false)), ["b", "c", "a"]);
+    }
+
+    #[test]
+    fn test_add_flags() {
+        let mut zset = sample();
+        let nx = AddFlags { nx: true, ..Default::default() };
+        let xx = AddFlags { xx: true, ..Default::default() };
+        let gt = AddFlags { gt: true, ..Default::default() };
+
+        assert_eq!(zset.add("a".into(), 9.0, nx), AddOutcome::Unchanged);
+        assert_eq!(zset.add("d".into(), 4.0, nx), AddOutcome::Added);
+        assert_eq!(zset.add("e".into(), 4.0, xx), AddOutcome::Unchanged);
+        assert_eq!(zset.add("b".into(), 7.0, xx), AddOutcome::Updated);
+        assert_eq!(zset.add("b".into(), 1.0, gt), AddOutcome::Unchanged);
+        assert_eq!(zset.add("b".into(), 7.0, AddFlags::default()), AddOutcome::Unchanged);
+
+        assert_eq!(zset.score("a"), Some(1.0));
+        assert_eq!(zset.score("b"), Some(7.0));
+        assert_eq!(zset.score("e"), None);
+    }
+
+    #[test]
+    fn test_range() {
+        let zset = sample();
+
+        assert_eq!(names(zset.range(0, -1, false)), ["a", "b", "c"]);
+        assert_eq!(names(zset.range(-2, 10, false)), ["b", "c"]);
+        assert_eq!(names(zset.range(0, 1, true)), ["c", "b"]);
+        assert_eq!(zset.range(2, 1, false), vec![]);
+        assert_eq!(zset.range(5, 10, false), vec![]);
+        assert_eq!(zset.range(0, -5, false), vec![]);
+    }
+
+    #[test]
+    fn test_score_range_parse() {
+        assert_eq!(
+            ScoreRange::parse("(1", "+inf"),
+            Some(ScoreRange {
+                min: 1.0,
+                max: f64::INFINITY,
+                min_exclusive: true,
+                max_exclusive: false
+            })
+        );
+        assert_eq!(ScoreRange::parse("a", "1"), None);
+        assert_eq!(ScoreRange::parse("1", "nan"), None);
+    }
+
+    #[test]
+    fn test_range_by_score() {
+        let zset = sample();
+
+        let range = |min, max| ScoreRange::parse(min, max).unwrap();
+        assert_eq!(names(zset.range_by_score(&range("-inf", "+inf"), 0, -1)), ["a", "b", "c"]);
+        assert_eq!(names(zset.range_by_score(&range("(1", "3"), 0, -1)), ["b", "c"]);
+        assert_eq!(names(zset.range_by_score(&range("1", "(3"), 0, -1)), ["a", "b"]);
+        assert_eq!(names(zset.range_by_score(&range("1", "3"), 1, 1)), ["b"]);
+        assert_eq!(zset.range_by_score(&range("1", "3"), -1, 1), vec![]);
+        assert_eq!(zset.range_by_score(&range("3", "1"), 0, -1), vec![]);
+    }
+
+    #[test]
+    fn test_range_by_lex() {
+        let mut zset = SortedSet::new();
+        for member in ["a", "b", "c", "d"] {
+            zset.insert(member.into(), 0.0);
+        }
+
+        let range = |min, max| LexRange::parse(min, max).unwrap();
+        assert_eq!(names(zset.range_by_lex(&range("-", "+"), 0, -1)), ["a", "b", "c", "d"]);
+        assert_eq!(names(zset.range_by_lex(&range("[b", "(d"), 0, -1)), ["b", "c"]);
+        assert_eq!(names(zset.range_by_lex(&range("(a", "[c"), 1, 5)), ["c"]);
+        assert_eq!(LexRange::parse("a", "+"), None);
+        assert_eq!(
+            LexRange::parse("(", "+"),
+            Some(LexRange { min: LexBound::Exclusive(String::new()), max: LexBound::PosInf })
+        );
+    }
+
+    #[test]
+    fn test_incr_and_rank() {
+        let mut zset = sample();
+
+        assert_eq!(zset.incr("a".into(), 2.5), Some(3.5));
+        assert_eq!(zset.incr("new".into(), -1.0), Some(-1.0));
+        assert_eq!(zset.rank("new"), Some(0));
+        assert_eq!(zset.rank("a"), Some(3));
+        assert_eq!(zset.rank("x"), None);
+
+        zset.insert("inf".into(), f64::INFINITY);
+        assert_eq!(zset.incr("inf".into(), f64::NEG_INFINITY), None);
+        assert_eq!(zset.score("inf"), Some(f64::INFINITY));
+    }
+
+    #[test]
+    fn test_pop() {
+        let mut zset = sample();
+
+        assert_eq!(zset.pop(0, false), vec![]);
+        assert_eq!(zset.len(), 3);
+        assert_eq!(zset.pop(2, true), vec![("c".into(), 3.0), ("b".into(), 2.0)]);
+        assert_eq!(zset.pop(5, false), vec![("a".into(), 1.0)]);
+        assert_eq!(zset.pop(1, false), vec![]);
+        assert_eq!(zset.pop(0, false), vec![]);
+        assert!(zset.is_empty());
+    }
+
+    #[test]
+    fn test_pop_oversized_count() {
+        let mut zset = sample();
+        assert_eq!(names(zset.pop(usize::MAX, false)), ["a", "b", "c"]);
+
+        let mut zset = sample();
+        assert_eq!(names(zset.pop(1 << 63, true)), ["c", "b", "a"]);
+        assert!(zset.is_empty());
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut zset = sample();
+
+        assert!(zset.remove("b"));
+        assert!(!zset.remove("b"));
+        assert_eq!(names(zset.range(0, -1, false)), ["a", "c"]);
+    }
+}
@@ -0,0 +1,427 @@
+//! 跳表（Skip List）
+//!
+//! 按 `(score, member)` 升序保存有序集合的成员，参考 Redis `t_zset.c` 的实现：
+//!
+//! - 每个节点拥有随机层数（晋升概率 1/4，最多 32 层）
+//! - 每一层的链接记录跨度（span），用于在 O(log n) 时间内计算排名
+//! - 第 0 层额外维护后退指针，支持从尾部反向遍历
+//!
+//! 节点保存在 `Vec` 中并以下标互相引用（arena 方式），避免使用 `unsafe` 指针；
+//! 被删除节点的槽位会放入空闲列表中复用。
+
+use crate::random::Rng;
+
+/// 最大层数
+const MAX_LEVEL: usize = 32;
+/// 头节点在 arena 中的下标
+const HEAD: usize = 0;
+
+/// 节点在某一层的链接
+#[derive(Clone, Debug)]
+struct Level {
+    /// 该层的下一个节点
+    next: Option<usize>,
+    /// 到下一个节点跨越的节点数；下一个节点不存在时为其后剩余的节点数
+    span: usize,
+}
+
+/// 跳表节点
+#[derive(Clone, Debug)]
+struct Node {
+    score: f64,
+    member: String,
+    levels: Vec<Level>,
+    /// 第 0 层的前一个节点（头节点之后的第一个节点为 `None`）
+    prev: Option<usize>,
+}
+
+/// 按 `(score, member)` 排序的跳表
+#[derive(Clone, Debug)]
+pub struct SkipList {
+    nodes: Vec<Node>,
+    /// 可复用的空闲槽位
+    free: Vec<usize>,
+    tail: Option<usize>,
+    /// 当前最高层数
+    level: usize,
+    len: usize,
+    /// 用于生成节点层数
+    rng: Rng,
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SkipList {
+    /// 创建一个空跳表
+    pub fn new() -> Self {
+        let head = Node {
+            score: 0.0,
+            member: String::new(),
+            levels: vec![Level { next: None, span: 0 }; MAX_LEVEL],
+            prev: None,
+        };
+
+        // 层数只影响性能不影响结果，使用固定种子让结构可复现
+        Self {
+            nodes: vec![head],
+            free: Vec::new(),
+            tail: None,
+            level: 1,
+            len: 0,
+            rng: Rng::seeded(0),
+        }
+    }
+
+    /// 成员数量
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 插入一个成员；调用方需保证该成员当前不在跳表中
+    pub fn insert(&mut self, score: f64, member: String) {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0usize; MAX_LEVEL];
+
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            rank[i] = if i == self.level - 1 { 0 } else { rank[i + 1] };
+            while let Some(next) = self.nodes[x].levels[i].next {
+                if !self.node_less(next, score, &member) {
+                    break;
+                }
+                rank[i] += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            update[i] = x;
+        }
+
+        let level = self.random_level();
+        if level > self.level {
+            for i in self.level..level {
+                rank[i] = 0;
+                update[i] = HEAD;
+                self.nodes[HEAD].levels[i].span = self.len;
+            }
+            self.level = level;
+        }
+
+        let node =
+            Node { score, member, levels: vec![Level { next: None, span: 0 }; level], prev: None };
+        let new = self.alloc(node);
+
+        for i in 0..level {
+            let u = update[i];
+            let skipped = rank[0] - rank[i];
+            self.nodes[new].levels[i].next = self.nodes[u].levels[i].next;
+            self.nodes[new].levels[i].span = self.nodes[u].levels[i].span - skipped;
+            self.nodes[u].levels[i].next = Some(new);
+            self.nodes[u].levels[i].span = skipped + 1;
+        }
+        for (i, &u) in update.iter().enumerate().take(self.level).skip(level) {
+            self.nodes[u].levels[i].span += 1;
+        }
+
+        self.nodes[new].prev = (update[0] != HEAD).then_some(update[0]);
+        match self.nodes[new].levels[0].next {
+            Some(next) => self.nodes[next].prev = Some(new),
+            None => self.tail = Some(new),
+        }
+        self.len += 1;
+    }
+
+    /// 删除指定成员，成员不存在时返回 `false`
+    pub fn remove(&mut self, score: f64, member: &str) -> bool {
+        let mut update = [HEAD; MAX_LEVEL];
+
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].next {
+                if !self.node_less(next, score, member) {
+                    break;
+                }
+                x = next;
+            }
+            update[i] = x;
+        }
+
+        match self.nodes[x].levels[0].next {
+            Some(target) if self.node_eq(target, score, member) => {
+                self.unlink(target, &update);
+                self.release(target);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 返回成员的排名（从 0 开始），成员不存在时返回 `None`
+    pub fn rank(&self, score: f64, member: &str) -> Option<usize> {
+        let mut rank = 0;
+
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].next {
+                if !self.node_less(next, score, member) && !self.node_eq(next, score, member) {
+                    break;
+                }
+                rank += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            if x != HEAD && self.node_eq(x, score, member) {
+                return Some(rank - 1);
+            }
+        }
+        None
+    }
+
+    /// 统计排在最前面、连续满足 `before` 的成员数量，即第一个不满足 `before` 的成员的排名
+    ///
+    /// `before` 必须与跳表顺序一致：对前一段成员返回 `true`，对其余成员返回 `false`，
+    /// 这样才能借助高层链接跳过大段成员，在 O(log n) 时间内完成查找。
+    pub fn count_while(&self, before: impl Fn(f64, &str) -> bool) -> usize {
+        let mut rank = 0;
+
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].next {
+                let node = &self.nodes[next];
+                if !before(node.score, &node.member) {
+                    break;
+                }
+                rank += self.nodes[x].levels[i].span;
+                x = next;
+            }
+        }
+        rank
+    }
+
+    /// 从排名 `rank`（从 0 开始）处开始正向遍历
+    pub fn iter_from(&self, rank: usize) -> Iter<'_> {
+        Iter { list: self, next: self.node_at(rank), forward: true }
+    }
+
+    /// 从倒数排名 `rank`（从 0 开始，0 表示最后一个成员）处开始反向遍历
+    pub fn rev_iter_from(&self, rank: usize) -> Iter<'_> {
+        let next = (rank < self.len).then(|| self.node_at(self.len - 1 - rank)).flatten();
+        Iter { list: self, next, forward: false }
+    }
+
+    /// 按排名（从 0 开始）查找节点
+    fn node_at(&self, rank: usize) -> Option<usize> {
+        if rank >= self.len {
+            return None;
+        }
+
+        let target = rank + 1;
+        let mut traversed = 0;
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.nodes[x].levels[i].next {
+                if traversed + self.nodes[x].levels[i].span > target {
+                    break;
+                }
+                traversed += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            if traversed == target {
+                return Some(x);
+            }
+        }
+        None
+    }
+
+    /// 节点是否排在 `(score, member)` 之前
+    fn node_less(&self, node: usize, score: f64, member: &str) -> bool {
+        let node = &self.nodes[node];
+        node.score < score || (node.score == score && node.member.as_str() < member)
+    }
+
+    /// 节点是否就是 `(score, member)`
+    fn node_eq(&self, node: usize, score: f64, member: &str) -> bool {
+        let node = &self.nodes[node];
+        node.score == score && node.member == member
+    }
+
+    /// 从各层链表中摘除节点，`update` 为每一层中位于该节点之前的节点
+    fn unlink(&mut self, target: usize, update: &[usize; MAX_LEVEL]) {
+        for (i, &u) in update.iter().enumerate().take(self.level) {
+            if self.nodes[u].levels[i].next == Some(target) {
+                self.nodes[u].levels[i].span += self.nodes[target].levels[i].span;
+                self.nodes[u].levels[i].span -= 1;
+                self.nodes[u].levels[i].next = self.nodes[target].levels[i].next;
+            } else {
+                self.nodes[u].levels[i].span -= 1;
+            }
+        }
+
+        let prev = self.nodes[target].prev;
+        match self.nodes[target].levels[0].next {
+            Some(next) => self.nodes[next].prev = prev,
+            None => self.tail = prev,
+        }
+
+        while self.level > 1 && self.nodes[HEAD].levels[self.level - 1].next.is_none() {
+            self.level -= 1;
+        }
+        self.len -= 1;
+    }
+
+    /// 随机生成新节点的层数：每多一层的概率为 1/4
+    fn random_level(&mut self) -> usize {
+        let mut level = 1;
+        while level < MAX_LEVEL && self.rng.next_u64() & 3 == 0 {
+            level += 1;
+        }
+        level
+    }
+
+    /// 分配一个节点槽位，优先复用空闲槽位
+    fn alloc(&mut self, node: Node) -> usize {
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// 回收节点槽位
+    fn release(&mut self, index: usize) {
+        let node = &mut self.nodes[index];
+        node.member = String::new();
+        node.levels = Vec::new();
+        self.free.push(index);
+    }
+}
+
+/// 跳表迭代器，产出 `(member, score)`
+pub struct Iter<'a> {
+    list: &'a SkipList,
+    next: Option<usize>,
+    forward: bool,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a str, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = &self.list.nodes[self.next?];
+        self.next = if self.forward { node.levels[0].next } else { node.prev };
+        Some((node.member.as_str(), node.score))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SkipList;
+
+    fn members(list: &SkipList) -> Vec<(String, f64)> {
+        list.iter_from(0).map(|(m, s)| (m.to_string(), s)).collect()
+    }
+
+    #[test]
+    fn test_insert_keeps_order() {
+        let mut list = SkipList::new();
+        list.insert(3.0, "c".into());
+        list.insert(1.0, "b".into());
+        list.insert(1.0, "a".into());
+        list.insert(2.0, "d".into());
+
+        assert_eq!(list.len(), 4);
+        assert_eq!(
+            members(&list),
+            vec![("a".into(), 1.0), ("b".into(), 1.0), ("d".into(), 2.0), ("c".into(), 3.0)]
+        );
+    }
+
+    #[test]
+    fn test_rank_and_iter_from() {
+        let mut list = SkipList::new();
+        for i in 0..100 {
+            list.insert(i as f64, format!("m{i}"));
+        }
+
+        assert_eq!(list.rank(0.0, "m0"), Some(0));
+        assert_eq!(list.rank(42.0, "m42"), Some(42));
+        assert_eq!(list.rank(42.0, "nope"), None);
+
+        assert_eq!(list.iter_from(97).map(|(m, _)| m).collect::<Vec<_>>(), ["m97", "m98", "m99"]);
+        assert_eq!(
+            list.rev_iter_from(0).take(2).map(|(m, _)| m).collect::<Vec<_>>(),
+            ["m99", "m98"]
+        );
+        assert_eq!(list.iter_from(100).count(), 0);
+        assert_eq!(list.rev_iter_from(100).count(), 0);
+    }
+
+    #[test]
+    fn test_count_while() {
+        let mut list = SkipList::new();
+        for i in 0..100 {
+            list.insert(i as f64, format!("m{i}"));
+        }
+
+        assert_eq!(list.count_while(|score, _| score < 42.5), 43);
+        assert_eq!(list.count_while(|_, _| true), 100);
+        assert_eq!(list.count_while(|_, _| false), 0);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut list = SkipList::new();
+        for i in 0..50 {
+            list.insert(i as f64, format!("m{i}"));
+        }
+
+        assert!(list.remove(10.0, "m10"));
+        assert!(!list.remove(10.0, "m10"));
+        assert!(!list.remove(11.0, "m12"));
+
+        assert_eq!(list.len(), 49);
+        assert_eq!(list.rank(11.0, "m11"), Some(10));
+        assert_eq!(list.rev_iter_from(48).next(), Some(("m0", 0.0)));
+
+        for i in (0..50).filter(|&i| i != 10) {
+            assert!(list.remove(i as f64, &format!("m{i}")));
+        }
+        assert!(list.is_empty());
+        assert_eq!(list.iter_from(0).count(), 0);
+    }
+
+    #[test]
+    fn test_ranks_consistent_after_mixed_operations() {
+        let mut list = SkipList::new();
+        let mut expected: Vec<(f64, String)> = Vec::new();
+
+        for i in 0..300u64 {
+            let score = ((i * 7919) % 101) as f64;
+            let member = format!("m{i}");
+            list.insert(score, member.clone());
+            expected.push((score, member));
+
+            if i % 3 == 0 {
+                let (score, member) = expected.remove((i as usize * 31) % expected.len());
+                assert!(list.remove(score, &member));
+            }
+        }
+
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for (rank, (score, member)) in expected.iter().enumerate() {
+            assert_eq!(list.rank(*score, member), Some(rank));
+            assert_eq!(list.iter_from(rank).next(), Some((member.as_str(), *score)));
+        }
+    }
+}
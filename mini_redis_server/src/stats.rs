@@ -0,0 +1,281 @@
+//! 服务器运行统计
+//!
+//! 各模块在处理连接、执行命令、查找键时更新计数，INFO 命令读取（见 `info` 模块）。
+//! 计数都是原子变量，更新时不需要加锁；按命令名统计的调用次数与耗时保存在一张加锁的表中。
+//!
+//! 瞬时指标（如每秒执行的命令数）由服务器的周期任务定期采样（见 [`Stats::sample`]），
+//! 按最近若干个样本计算。
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// 计算瞬时指标时保留的样本数，与 Redis 相同
+const SAMPLES: usize = 16;
+
+/// 一条命令的调用统计（INFO commandstats）
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct CommandStat {
+    /// 调用次数
+    pub calls: u64,
+    /// 累计执行时间（微秒）
+    pub usec: u64,
+    /// 单次执行的最长时间（微秒）
+    pub usec_max: u64,
+}
+
+/// 运行统计
+pub struct Stats {
+    started: Instant,
+    connections_received: AtomicU64,
+    connected_clients: AtomicU64,
+    rejected_connections: AtomicU64,
+    commands_processed: AtomicU64,
+    keyspace_hits: AtomicU64,
+    keyspace_misses: AtomicU64,
+    expired_keys: AtomicU64,
+    evicted_keys: AtomicU64,
+    lazyfreed_objects: AtomicU64,
+    output_buffer_limit_disconnections: AtomicU64,
+    throttled_commands: AtomicU64,
+    commands: Mutex<BTreeMap<&'static str, CommandStat>>,
+    /// 最近的采样时间与当时累计执行的命令数
+    samples: Mutex<VecDeque<(Instant, u64)>>,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            connections_received: AtomicU64::new(0),
+            connected_clients: AtomicU64::new(0),
+            rejected_connections: AtomicU64::new(0),
+            commands_processed: AtomicU64::new(0),
+            keyspace_hits: AtomicU64::new(0),
+            keyspace_misses: AtomicU64::new(0),
+            expired_keys: AtomicU64::new(0),
+            evicted_keys: AtomicU64::new(0),
+            lazyfreed_objects: AtomicU64::new(0),
+            output_buffer_limit_disconnections: AtomicU64::new(0),
+            throttled_commands: AtomicU64::new(0),
+            commands: Mutex::default(),
+            samples: Mutex::default(),
+        }
+    }
+}
+
+impl Stats {
+    /// 记录一个新连接
+    pub fn connection_opened(&self) {
+        self.connections_received.fetch_add(1, Ordering::Relaxed);
+        self.connected_clients.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录一个连接关闭
+    pub fn connection_closed(&self) {
+        self.connected_clients.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// 记录一个因超出 `maxclients` 被拒绝的连接
+    pub fn connection_rejected(&self) {
+        self.rejected_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录执行了一条命令
+    pub fn command_processed(&self) {
+        self.commands_processed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录一次命令调用及其执行时间，`name` 为命令名（子命令为 `config|get` 的形式）
+    pub fn command_called(&self, name: &'static str, elapsed: Duration) {
+        let usec = elapsed.as_micros() as u64;
+        let mut commands = self.commands.lock().unwrap();
+        let stat = commands.entry(name).or_default();
+        stat.calls += 1;
+        stat.usec += usec;
+        stat.usec_max = stat.usec_max.max(usec);
+    }
+
+    /// 记录读命令查找键的结果
+    pub fn keyspace_lookup(&self, hit: bool) {
+        let counter = if hit { &self.keyspace_hits } else { &self.keyspace_misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录主动过期删除的键
+    pub fn keys_expired(&self, count: u64) {
+        self.expired_keys.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// 记录一个因 maxmemory 被淘汰的键
+    pub fn key_evicted(&self) {
+        self.evicted_keys.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录交给后台释放的值的数量
+    pub fn objects_lazyfreed(&self, count: u64) {
+        self.lazyfreed_objects.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// 记录因输出缓冲区超出限制而关闭的客户端
+    pub fn output_limit_exceeded(&self, count: u64) {
+        self.output_buffer_limit_disconnections.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// 记录一条因超出速率限制被拒绝的命令
+    pub fn command_throttled(&self) {
+        self.throttled_commands.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// CONFIG RESETSTAT：清零累计的统计，当前连接数与启动时间不受影响
+    pub fn reset(&self) {
+        for counter in [
+            &self.connections_received,
+            &self.rejected_connections,
+            &self.commands_processed,
+            &self.keyspace_hits,
+            &self.keyspace_misses,
+            &self.expired_keys,
+            &self.evicted_keys,
+            &self.lazyfreed_objects,
+            &self.output_buffer_limit_disconnections,
+            &self.throttled_commands,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+        self.commands.lock().unwrap().clear();
+        self.samples.lock().unwrap().clear();
+    }
+
+    /// 周期任务调用：记录一个样本，只保留最近的 [`SAMPLES`] 个
+    pub fn sample(&self, now: Instant) {
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() == SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back((now, self.commands_processed()));
+    }
+
+    /// 按最近的样本计算的每秒命令数，样本不足两个时为 0
+    pub fn instantaneous_ops_per_sec(&self) -> u64 {
+        let samples = self.samples.lock().unwrap();
+        let (Some((first, from)), Some((last, to))) = (samples.front(), samples.back()) else {
+            return 0;
+        };
+        let elapsed = last.duration_since(*first).as_secs_f64();
+        if elapsed == 0.0 { 0 } else { (to.saturating_sub(*from) as f64 / elapsed).round() as u64 }
+    }
+
+    /// 启动以来经过的秒数
+    pub fn uptime(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+
+    /// 累计接受的连接数
+    pub fn connections_received(&self) -> u64 {
+        self.connections_received.load(Ordering::Relaxed)
+    }
+
+    /// 当前的连接数
+    pub fn connected_clients(&self) -> u64 {
+        self.connected_clients.load(Ordering::Relaxed)
+    }
+
+    /// 累计拒绝的连接数
+    pub fn rejected_connections(&self) -> u64 {
+        self.rejected_connections.load(Ordering::Relaxed)
+    }
+
+    /// 累计执行的命令数
+    pub fn commands_processed(&self) -> u64 {
+        self.commands_processed.load(Ordering::Relaxed)
+    }
+
+    /// 读命令找到键的次数
+    pub fn keyspace_hits(&self) -> u64 {
+        self.keyspace_hits.load(Ordering::Relaxed)
+    }
+
+    /// 读命令没有找到键的次数
+    pub fn keyspace_misses(&self) -> u64 {
+        self.keyspace_misses.load(Ordering::Relaxed)
+    }
+
+    /// 主动过期删除的键数
+    pub fn expired_keys(&self) -> u64 {
+        self.expired_keys.load(Ordering::Relaxed)
+    }
+
+    /// 因 maxmemory 被淘汰的键数
+    pub fn evicted_keys(&self) -> u64 {
+        self.evicted_keys.load(Ordering::Relaxed)
+    }
+
+    /// 交给后台释放的值的数量
+    pub fn lazyfreed_objects(&self) -> u64 {
+        self.lazyfreed_objects.load(Ordering::Relaxed)
+    }
+
+    /// 因输出缓冲区超出限制而关闭的客户端数
+    pub fn output_buffer_limit_disconnections(&self) -> u64 {
+        self.output_buffer_limit_disconnections.load(Ordering::Relaxed)
+    }
+
+    /// 因超出速率限制被拒绝的命令数
+    pub fn throttled_commands(&self) -> u64 {
+        self.throttled_commands.load(Ordering::Relaxed)
+    }
+
+    /// 调用过的命令及其统计，按命令名排序
+    pub fn command_stats(&self) -> Vec<(&'static str, CommandStat)> {
+        let commands = self.commands.lock().unwrap();
+        commands.iter().map(|(name, stat)| (*name, *stat)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_stats() {
+        let stats = Stats::default();
+        stats.command_called("get", Duration::from_micros(10));
+        stats.command_called("get", Duration::from_micros(30));
+        stats.command_called("config|get", Duration::from_micros(5));
+        stats.command_processed();
+
+        assert_eq!(
+            stats.command_stats(),
+            [
+                ("config|get", CommandStat { calls: 1, usec: 5, usec_max: 5 }),
+                ("get", CommandStat { calls: 2, usec: 40, usec_max: 30 }),
+            ]
+        );
+
+        stats.reset();
+        assert!(stats.command_stats().is_empty());
+        assert_eq!(stats.commands_processed(), 0);
+    }
+
+    #[test]
+    fn test_instantaneous_ops() {
+        let stats = Stats::default();
+        let start = Instant::now();
+        stats.sample(start);
+        assert_eq!(stats.instantaneous_ops_per_sec(), 0);
+
+        // 只按最近的样本计算：早期的高峰在样本移出后不再计入
+        (0..1000).for_each(|_| stats.command_processed());
+        for i in 1..=SAMPLES as u64 {
+            (0..10).for_each(|_| stats.command_processed());
+            stats.sample(start + Duration::from_millis(100 * i));
+        }
+        assert_eq!(stats.instantaneous_ops_per_sec(), 100);
+    }
+}
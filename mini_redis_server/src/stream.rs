@@ -0,0 +1,639 @@
+//! 流模块
+//!
+//! 流是只追加的消息日志：每条消息由一个 ID 和若干 field-value 对组成。
+//! ID 形如 `<ms>-<seq>`，`ms` 为毫秒时间戳，`seq` 区分同一毫秒内的消息，ID 在流内严格递增。
+//!
+//! 消息按 ID 保存在 `BTreeMap` 中，支持按 ID 区间查询（XRANGE）与读取某个 ID 之后的消息（XREAD）。
+//!
+//! 流上可以创建消费者组（[`ConsumerGroup`]）：组内的消费者分摊读取新消息，
+//! 已投递但尚未确认的消息记录在组的待确认列表（PEL）中，同时按消费者分别索引；
+//! 消息被确认（XACK）后移出列表，长时间未确认的消息可以被其他消费者认领（XCLAIM）。
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    ops::Bound::{self, Excluded, Included, Unbounded},
+};
+
+use crate::db::DbError;
+
+/// 消息 ID，按 `(ms, seq)` 排序
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId { ms: u64::MAX, seq: u64::MAX };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        Self { ms, seq }
+    }
+
+    /// 解析 `<ms>-<seq>`；只给出 `<ms>` 时序号取 `missing_seq`
+    pub fn parse(s: &str, missing_seq: u64) -> Option<Self> {
+        match s.split_once('-') {
+            Some((ms, seq)) => Some(Self::new(ms.parse().ok()?, seq.parse().ok()?)),
+            None => Some(Self::new(s.parse().ok()?, missing_seq)),
+        }
+    }
+
+    /// 紧随其后的 ID，已经是最大 ID 时返回 `None`
+    fn next(self) -> Option<Self> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(Self::new(self.ms, seq)),
+            None => self.ms.checked_add(1).map(|ms| Self::new(ms, 0)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+/// XADD 的 ID 参数
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IdSpec {
+    /// `*`：由服务器根据当前时间生成
+    Auto,
+    /// `<ms>-*`：毫秒部分由客户端指定，序号由服务器生成
+    Seq(u64),
+    /// `<ms>-<seq>`：完全由客户端指定
+    Explicit(StreamId),
+}
+
+impl IdSpec {
+    /// 解析 XADD 的 ID 参数，格式不合法时返回 `None`
+    pub fn parse(s: &str) -> Option<Self> {
+        if s == "*" {
+            return Some(IdSpec::Auto);
+        }
+        match s.strip_suffix("-*") {
+            Some(ms) => ms.parse().ok().map(IdSpec::Seq),
+            None => StreamId::parse(s, 0).map(IdSpec::Explicit),
+        }
+    }
+}
+
+/// ID 区间，对应 XRANGE 的 `start` / `end` 参数
+///
+/// 参数形如 `1-1`（闭区间）、`(1-1`（开区间）、`-`（最小）、`+`（最大）；
+/// 只给出毫秒部分时，起点的序号取 0，终点的序号取最大值。
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IdRange {
+    pub start: Bound<StreamId>,
+    pub end: Bound<StreamId>,
+}
+
+impl IdRange {
+    /// 解析 `start` 与 `end` 参数，格式不合法时返回 `None`
+    pub fn parse(start: &str, end: &str) -> Option<Self> {
+        fn bound(arg: &str, unbounded: &str, missing_seq: u64) -> Option<Bound<StreamId>> {
+            if arg == unbounded {
+                return Some(Unbounded);
+            }
+            match arg.strip_prefix('(') {
+                Some(id) => StreamId::parse(id, missing_seq).map(Excluded),
+                None => StreamId::parse(arg, missing_seq).map(Included),
+            }
+        }
+
+        Some(Self { start: bound(start, "-", 0)?, end: bound(end, "+", u64::MAX)? })
+    }
+
+    /// ID 是否位于终点之后
+    fn past_end(&self, id: &StreamId) -> bool {
+        match self.end {
+            Included(end) => *id > end,
+            Excluded(end) => *id >= end,
+            Unbounded => false,
+        }
+    }
+}
+
+/// 一条消息的 field-value 对
+pub type Fields = Vec<(String, String)>;
+
+/// 一条消息：ID 与 field-value 对
+pub type Entry = (StreamId, Fields);
+
+/// 待确认列表中的一项
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pending {
+    /// 消息当前所属的消费者
+    pub consumer: String,
+    /// 最近一次投递的 Unix 毫秒时间
+    pub delivery_time: u64,
+    /// 投递次数
+    pub delivery_count: u64,
+}
+
+/// XCLAIM 的选项
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClaimOptions {
+    /// 只认领空闲时间（毫秒）不小于该值的消息
+    pub min_idle: u64,
+    /// IDLE：把认领后的空闲时间设为该值（毫秒）
+    pub idle: Option<u64>,
+    /// TIME：把认领后的投递时间设为该 Unix 毫秒时间
+    pub time: Option<u64>,
+    /// RETRYCOUNT：把投递次数设为该值
+    pub retry_count: Option<u64>,
+    /// FORCE：消息不在待确认列表中时也创建一项
+    pub force: bool,
+    /// JUSTID：只返回 ID，且不增加投递次数
+    pub just_id: bool,
+    /// LASTID：把组的最后投递 ID 推进到该值
+    pub last_id: Option<StreamId>,
+}
+
+/// XPENDING 汇总形式的结果
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PendingSummary {
+    /// 待确认消息总数
+    pub count: usize,
+    /// 待确认消息的最小与最大 ID
+    pub range: Option<(StreamId, StreamId)>,
+    /// 有待确认消息的消费者及其消息数量，按名称排序
+    pub consumers: Vec<(String, usize)>,
+}
+
+/// 消费者组
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConsumerGroup {
+    /// 最近一次投递给组内消费者的新消息 ID
+    last_delivered: StreamId,
+    /// 待确认列表
+    pending: BTreeMap<StreamId, Pending>,
+    /// 消费者及其名下的待确认消息
+    consumers: BTreeMap<String, BTreeSet<StreamId>>,
+}
+
+impl ConsumerGroup {
+    pub fn new(last_delivered: StreamId) -> Self {
+        Self { last_delivered, ..Self::default() }
+    }
+
+    /// 最近一次投递的新消息 ID
+    pub fn last_delivered(&self) -> StreamId {
+        self.last_delivered
+    }
+
+    /// 设置最后投递 ID（XGROUP SETID）
+    pub fn set_last_delivered(&mut self, id: StreamId) {
+        self.last_delivered = id;
+    }
+
+    /// 创建消费者，已存在时返回 `false`
+    pub fn create_consumer(&mut self, name: &str) -> bool {
+        if self.consumers.contains_key(name) {
+            return false;
+        }
+        self.consumers.insert(name.to_string(), BTreeSet::new());
+        true
+    }
+
+    /// 按名称排序的消费者及其待确认消息数量
+    pub fn consumers(&self) -> impl Iterator<Item = (&String, usize)> {
+        self.consumers.iter().map(|(name, ids)| (name, ids.len()))
+    }
+
+    /// 按 ID 升序遍历待确认列表
+    pub fn pending(&self) -> impl Iterator<Item = (&StreamId, &Pending)> {
+        self.pending.iter()
+    }
+
+    /// 待确认列表中的一项
+    pub fn pending_entry(&self, id: &StreamId) -> Option<&Pending> {
+        self.pending.get(id)
+    }
+
+    /// 把消息登记到 `pending.consumer` 名下，已属于其他消费者时转移过去；消费者不存在时创建
+    pub fn assign(&mut self, id: StreamId, pending: Pending) {
+        if let Some(old) = self.pending.get(&id)
+            && let Some(ids) = self.consumers.get_mut(&old.consumer)
+        {
+            ids.remove(&id);
+        }
+        self.consumers.entry(pending.consumer.clone()).or_default().insert(id);
+        self.pending.insert(id, pending);
+    }
+
+    /// 确认消息，返回实际移出待确认列表的数量
+    pub fn ack(&mut self, ids: &[StreamId]) -> usize {
+        let mut acked = 0;
+        for id in ids {
+            if let Some(pending) = self.pending.remove(id) {
+                if let Some(ids) = self.consumers.get_mut(&pending.consumer) {
+                    ids.remove(id);
+                }
+                acked += 1;
+            }
+        }
+        acked
+    }
+
+    /// 待确认列表的汇总信息（XPENDING 的汇总形式）
+    pub fn summary(&self) -> PendingSummary {
+        let first = self.pending.keys().next();
+        let last = self.pending.keys().next_back();
+        let consumers = self.consumers().filter(|(_, count)| *count > 0);
+        PendingSummary {
+            count: self.pending.len(),
+            range: first.zip(last).map(|(first, last)| (*first, *last)),
+            consumers: consumers.map(|(name, count)| (name.clone(), count)).collect(),
+        }
+    }
+
+    /// 按 ID 升序返回区间内的待确认消息（XPENDING 的扩展形式）
+    ///
+    /// 可以只返回某个消费者名下的消息，或只返回空闲时间不小于 `min_idle` 毫秒的消息。
+    pub fn pending_range(
+        &self,
+        range: &IdRange,
+        count: usize,
+        consumer: Option<&str>,
+        min_idle: Option<u64>,
+        now_ms: u64,
+    ) -> Vec<(StreamId, &Pending)> {
+        self.pending
+            .range((range.start, Unbounded))
+            .take_while(|(id, _)| !range.past_end(id))
+            .filter(|(_, pending)| consumer.is_none_or(|name| pending.consumer == name))
+            .filter(|(_, pending)| {
+                min_idle.is_none_or(|idle| now_ms.saturating_sub(pending.delivery_time) >= idle)
+            })
+            .take(count)
+            .map(|(id, pending)| (*id, pending))
+            .collect()
+    }
+}
+
+/// 流
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, Fields>,
+    /// 最近一次添加的消息 ID，新消息的 ID 必须大于它
+    last_id: StreamId,
+    /// 消费者组，按组名索引
+    groups: BTreeMap<String, ConsumerGroup>,
+}
+
+impl Stream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 消息数量
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 最近一次添加的消息 ID，空流为 `0-0`
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    /// 按 `spec` 确定 ID 并追加一条消息，返回消息 ID
+    ///
+    /// `now_ms` 为当前 Unix 毫秒时间，用于生成 `*` 的 ID；时钟回拨时沿用最后一个 ID 的毫秒部分。
+    pub fn add(&mut self, spec: IdSpec, now_ms: u64, fields: Fields) -> Result<StreamId, DbError> {
+        let id = match spec {
+            IdSpec::Auto if now_ms > self.last_id.ms => Some(StreamId::new(now_ms, 0)),
+            IdSpec::Auto => self.last_id.next(),
+            IdSpec::Seq(ms) if ms > self.last_id.ms => Some(StreamId::new(ms, 0)),
+            IdSpec::Seq(ms) if ms == self.last_id.ms => {
+                self.last_id.seq.checked_add(1).map(|seq| StreamId::new(ms, seq))
+            }
+            IdSpec::Seq(_) => None,
+            IdSpec::Explicit(StreamId::MIN) => return Err(DbError::StreamIdZero),
+            IdSpec::Explicit(id) => Some(id).filter(|id| *id > self.last_id),
+        };
+        // 空流的 `0-*` 不能生成 0-0
+        let id = id.filter(|id| *id != StreamId::MIN).ok_or(DbError::StreamIdTooSmall)?;
+
+        self.insert(id, fields);
+        Ok(id)
+    }
+
+    /// 以给定的 ID 写入一条消息，用于从快照恢复
+    pub fn insert(&mut self, id: StreamId, fields: Fields) {
+        self.last_id = self.last_id.max(id);
+        self.entries.insert(id, fields);
+    }
+
+    /// 按 ID 升序返回区间内的消息，`count` 限制返回的数量
+    pub fn range(&self, range: &IdRange, count: Option<usize>) -> Vec<Entry> {
+        // 起点大于终点时 `BTreeMap::range` 会 panic，因此只限定起点，终点逐个判断
+        self.entries
+            .range((range.start, Unbounded))
+            .take_while(|(id, _)| !range.past_end(id))
+            .take(count.unwrap_or(usize::MAX))
+            .map(|(id, fields)| (*id, fields.clone()))
+            .collect()
+    }
+
+    /// ID 大于 `after` 的消息，`count` 限制返回的数量
+    pub fn read_after(&self, after: StreamId, count: Option<usize>) -> Vec<Entry> {
+        let range = IdRange { start: Excluded(after), end: Unbounded };
+        self.range(&range, count)
+    }
+
+    /// 按 ID 升序遍历全部消息
+    pub fn iter(&self) -> impl Iterator<Item = (&StreamId, &Fields)> {
+        self.entries.iter()
+    }
+
+    /// 按组名排序的消费者组
+    pub fn groups(&self) -> impl Iterator<Item = (&String, &ConsumerGroup)> {
+        self.groups.iter()
+    }
+
+    pub fn group(&self, name: &str) -> Option<&ConsumerGroup> {
+        self.groups.get(name)
+    }
+
+    pub fn group_mut(&mut self, name: &str) -> Option<&mut ConsumerGroup> {
+        self.groups.get_mut(name)
+    }
+
+    /// 添加消费者组，同名的组已存在时返回 `false`
+    pub fn create_group(&mut self, name: &str, group: ConsumerGroup) -> bool {
+        if self.groups.contains_key(name) {
+            return false;
+        }
+        self.groups.insert(name.to_string(), group);
+        true
+    }
+
+    /// 删除消费者组，组不存在时返回 `false`
+    pub fn destroy_group(&mut self, name: &str) -> bool {
+        self.groups.remove(name).is_some()
+    }
+
+    /// 以组内消费者的身份读取消息（XREADGROUP），组不存在时返回 `None`
+    ///
+    /// - `after` 为 `None`（即 `>`）时读取从未投递给本组的新消息并推进组的最后投递 ID，
+    ///   除非 `noack` 为 `true`，读到的消息都登记到该消费者名下
+    /// - 否则重新读取该消费者名下 ID 大于 `after` 的待确认消息，投递次数加一
+    pub fn read_group(
+        &mut self,
+        name: &str,
+        consumer: &str,
+        after: Option<StreamId>,
+        count: Option<usize>,
+        noack: bool,
+        now_ms: u64,
+    ) -> Option<Vec<Entry>> {
+        let entries = &self.entries;
+        let group = self.groups.get_mut(name)?;
+        group.create_consumer(consumer);
+        let count = count.unwrap_or(usize::MAX);
+
+        let Some(after) = after else {
+            let new: Vec<Entry> = entries
+                .range((Excluded(group.last_delivered), Unbounded))
+                .take(count)
+                .map(|(id, fields)| (*id, fields.clone()))
+                .collect();
+            for (id, _) in &new {
+                group.last_delivered = *id;
+                if !noack {
+                    let pending = Pending {
+                        consumer: consumer.to_string(),
+                        delivery_time: now_ms,
+                        delivery_count: 1,
+                    };
+                    group.assign(*id, pending);
+                }
+            }
+            return Some(new);
+        };
+
+        let ids: Vec<StreamId> = group.consumers[consumer]
+            .range((Excluded(after), Unbounded))
+            .take(count)
+            .copied()
+            .collect();
+        let history = ids.into_iter().map(|id| {
+            let pending = group.pending.get_mut(&id).expect("consumer entries are pending");
+            pending.delivery_time = now_ms;
+            pending.delivery_count += 1;
+            (id, entries.get(&id).cloned().unwrap_or_default())
+        });
+        Some(history.collect())
+    }
+
+    /// 把消息转移到 `consumer` 名下（XCLAIM），返回被认领的消息；组不存在时返回 `None`
+    ///
+    /// 不在待确认列表中的消息（除非带 FORCE）、空闲时间不足的消息以及流中已不存在的消息被跳过。
+    pub fn claim(
+        &mut self,
+        name: &str,
+        consumer: &str,
+        ids: &[StreamId],
+        options: &ClaimOptions,
+        now_ms: u64,
+    ) -> Option<Vec<Entry>> {
+        let entries = &self.entries;
+        let group = self.groups.get_mut(name)?;
+        group.create_consumer(consumer);
+        if let Some(last_id) = options.last_id {
+            group.last_delivered = group.last_delivered.max(last_id);
+        }
+        let delivery_time =
+            options.time.unwrap_or_else(|| now_ms.saturating_sub(options.idle.unwrap_or(0)));
+
+        let mut claimed = Vec::new();
+        for id in ids {
+            let Some(fields) = entries.get(id) else { continue };
+            let delivery_count = match group.pending.get(id) {
+                Some(pending)
+                    if now_ms.saturating_sub(pending.delivery_time) < options.min_idle =>
+                {
+                    continue;
+                }
+                Some(pending) => pending.delivery_count,
+                None if options.force => 0,
+                None => continue,
+            };
+            let delivery_count = options.retry_count.unwrap_or(if options.just_id {
+                delivery_count
+            } else {
+                delivery_count + 1
+            });
+            let pending = Pending { consumer: consumer.to_string(), delivery_time, delivery_count };
+            group.assign(*id, pending);
+            claimed.push((*id, fields.clone()));
+        }
+        Some(claimed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClaimOptions, ConsumerGroup, IdRange, IdSpec, Pending, Stream, StreamId};
+    use crate::db::DbError;
+
+    fn fields(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(f, v)| (f.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(StreamId::parse("5-3", 0), Some(StreamId::new(5, 3)));
+        assert_eq!(StreamId::parse("5", u64::MAX), Some(StreamId::new(5, u64::MAX)));
+        assert_eq!(StreamId::parse("5-x", 0), None);
+        assert_eq!(StreamId::parse("-1", 0), None);
+
+        assert_eq!(IdSpec::parse("*"), Some(IdSpec::Auto));
+        assert_eq!(IdSpec::parse("7-*"), Some(IdSpec::Seq(7)));
+        assert_eq!(IdSpec::parse("7"), Some(IdSpec::Explicit(StreamId::new(7, 0))));
+        assert_eq!(IdSpec::parse("*-1"), None);
+
+        assert!(IdRange::parse("-", "+").is_some());
+        assert!(IdRange::parse("(1-0", "2").is_some());
+        assert!(IdRange::parse("+", "-").is_none());
+    }
+
+    #[test]
+    fn test_add_generates_increasing_ids() {
+        let mut stream = Stream::new();
+        let a = fields(&[("f", "v")]);
+
+        assert_eq!(stream.add(IdSpec::Seq(0), 100, a.clone()), Ok(StreamId::new(0, 1)));
+        assert_eq!(stream.add(IdSpec::Auto, 100, a.clone()), Ok(StreamId::new(100, 0)));
+        assert_eq!(stream.add(IdSpec::Auto, 100, a.clone()), Ok(StreamId::new(100, 1)));
+        // 时钟回拨时沿用最后一个 ID 的毫秒部分
+        assert_eq!(stream.add(IdSpec::Auto, 50, a.clone()), Ok(StreamId::new(100, 2)));
+        assert_eq!(stream.add(IdSpec::Seq(100), 0, a.clone()), Ok(StreamId::new(100, 3)));
+        assert_eq!(stream.add(IdSpec::Seq(200), 0, a.clone()), Ok(StreamId::new(200, 0)));
+
+        let explicit = |ms, seq| IdSpec::Explicit(StreamId::new(ms, seq));
+        assert_eq!(stream.add(explicit(200, 0), 0, a.clone()), Err(DbError::StreamIdTooSmall));
+        assert_eq!(stream.add(IdSpec::Seq(99), 0, a.clone()), Err(DbError::StreamIdTooSmall));
+        assert_eq!(stream.add(explicit(0, 0), 0, a.clone()), Err(DbError::StreamIdZero));
+        assert_eq!(stream.add(explicit(300, 5), 0, a), Ok(StreamId::new(300, 5)));
+        assert_eq!(stream.len(), 7);
+        assert_eq!(stream.last_id(), StreamId::new(300, 5));
+    }
+
+    #[test]
+    fn test_range_and_read_after() {
+        let mut stream = Stream::new();
+        for (ms, seq) in [(1, 0), (1, 1), (2, 0), (3, 0)] {
+            stream.insert(StreamId::new(ms, seq), fields(&[("n", &ms.to_string())]));
+        }
+        let ids = |entries: Vec<(StreamId, _)>| {
+            entries.into_iter().map(|(id, _)| id.to_string()).collect::<Vec<_>>()
+        };
+        let range = |start, end| IdRange::parse(start, end).unwrap();
+
+        assert_eq!(ids(stream.range(&range("-", "+"), None)), ["1-0", "1-1", "2-0", "3-0"]);
+        assert_eq!(ids(stream.range(&range("1", "1"), None)), ["1-0", "1-1"]);
+        assert_eq!(ids(stream.range(&range("(1-0", "(3-0"), None)), ["1-1", "2-0"]);
+        assert_eq!(ids(stream.range(&range("-", "+"), Some(1))), ["1-0"]);
+        assert!(stream.range(&range("3", "1"), None).is_empty());
+        assert!(stream.range(&range("(2-0", "(2-0"), None).is_empty());
+
+        assert_eq!(ids(stream.read_after(StreamId::new(1, 1), None)), ["2-0", "3-0"]);
+        assert!(stream.read_after(stream.last_id(), None).is_empty());
+    }
+
+    #[test]
+    fn test_consumer_group() {
+        let mut stream = Stream::new();
+        for ms in 1..=3 {
+            stream.insert(StreamId::new(ms, 0), fields(&[("n", &ms.to_string())]));
+        }
+        assert!(stream.create_group("g", ConsumerGroup::new(StreamId::MIN)));
+        assert!(!stream.create_group("g", ConsumerGroup::new(StreamId::MIN)));
+        assert_eq!(stream.read_group("missing", "c", None, None, false, 0), None);
+
+        let ids = |entries: Option<Vec<(StreamId, _)>>| {
+            entries.unwrap().into_iter().map(|(id, _)| id.to_string()).collect::<Vec<_>>()
+        };
+        // 新消息分摊给不同的消费者
+        assert_eq!(ids(stream.read_group("g", "alice", None, Some(2), false, 100)), ["1-0", "2-0"]);
+        assert_eq!(ids(stream.read_group("g", "bob", None, None, false, 100)), ["3-0"]);
+        assert!(ids(stream.read_group("g", "bob", None, None, false, 100)).is_empty());
+
+        // 重新读取自己名下的待确认消息，投递次数加一
+        assert_eq!(
+            ids(stream.read_group("g", "alice", Some(StreamId::MIN), None, false, 200)),
+            ["1-0", "2-0"]
+        );
+        let group = stream.group("g").unwrap();
+        assert_eq!(group.last_delivered(), StreamId::new(3, 0));
+        let first = group.pending_entry(&StreamId::new(1, 0)).unwrap();
+        assert_eq!((first.delivery_time, first.delivery_count), (200, 2));
+        assert_eq!(
+            group.consumers().collect::<Vec<_>>(),
+            [(&"alice".to_string(), 2), (&"bob".to_string(), 1)]
+        );
+        let summary = group.summary();
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.range, Some((StreamId::new(1, 0), StreamId::new(3, 0))));
+        assert_eq!(summary.consumers, [("alice".to_string(), 2), ("bob".to_string(), 1)]);
+
+        // 空闲时间不足的消息不能被认领
+        let claim = |min_idle| ClaimOptions { min_idle, ..ClaimOptions::default() };
+        let all = [StreamId::new(1, 0), StreamId::new(2, 0), StreamId::new(3, 0)];
+        assert!(ids(stream.claim("g", "bob", &all[..2], &claim(150), 300)).is_empty());
+        assert_eq!(ids(stream.claim("g", "bob", &all[..1], &claim(100), 300)), ["1-0"]);
+        let pending = stream.group("g").unwrap().pending_entry(&all[0]).unwrap();
+        assert_eq!(
+            pending,
+            &Pending { consumer: "bob".into(), delivery_time: 300, delivery_count: 3 }
+        );
+
+        let group = stream.group_mut("g").unwrap();
+        assert_eq!(group.ack(&all), 3);
+        assert_eq!(group.ack(&all), 0);
+        assert_eq!(group.pending().count(), 0);
+        assert_eq!(group.summary().range, None);
+        assert!(group.summary().consumers.is_empty());
+        assert!(stream.destroy_group("g"));
+        assert!(!stream.destroy_group("g"));
+    }
+
+    #[test]
+    fn test_claim_options_and_pending_range() {
+        let mut stream = Stream::new();
+        stream.insert(StreamId::new(1, 0), fields(&[("a", "1")]));
+        stream.insert(StreamId::new(2, 0), fields(&[("b", "2")]));
+        stream.create_group("g", ConsumerGroup::new(StreamId::MIN));
+
+        // FORCE 为不在待确认列表中的消息创建一项，流中不存在的消息被跳过
+        let options = ClaimOptions {
+            time: Some(50),
+            retry_count: Some(5),
+            force: true,
+            just_id: true,
+            last_id: Some(StreamId::new(1, 0)),
+            ..ClaimOptions::default()
+        };
+        let ids = [StreamId::new(1, 0), StreamId::new(9, 0)];
+        assert_eq!(stream.claim("g", "c", &ids, &options, 1000).unwrap().len(), 1);
+        let group = stream.group("g").unwrap();
+        assert_eq!(group.last_delivered(), StreamId::new(1, 0));
+        let pending = group.pending_entry(&ids[0]).unwrap();
+        assert_eq!((pending.delivery_time, pending.delivery_count), (50, 5));
+
+        stream.read_group("g", "d", None, None, false, 900);
+        let group = stream.group("g").unwrap();
+        let all = IdRange::parse("-", "+").unwrap();
+        assert_eq!(group.pending_range(&all, 10, None, None, 1000).len(), 2);
+        assert_eq!(group.pending_range(&all, 1, None, None, 1000)[0].0, StreamId::new(1, 0));
+        assert_eq!(group.pending_range(&all, 10, Some("d"), None, 1000)[0].0, StreamId::new(2, 0));
+        assert_eq!(group.pending_range(&all, 10, None, Some(500), 1000)[0].1.consumer, "c");
+    }
+}
@@ -0,0 +1,300 @@
+//! 分层时间轮
+//!
+//! 按到期时间（Unix 毫秒）把条目分桶，插入、删除与推进时间的开销都与条目总数无关，
+//! 主动过期不需要扫描整张过期时间表。结构与 Linux 内核和 tokio 的定时器相同：
+//! - 共 [`LEVELS`] 层，每层 64 个槽；第 `n` 层的一个槽覆盖 `64^n` 毫秒，整层覆盖 `64^(n+1)` 毫秒
+//! - 条目按到期时间与当前时间最高的不同位所在的层放入对应的槽：到期时间越远，所在的层越高
+//! - 推进时间时依次取出到期的槽：最低层的条目已经到期，较高层的条目按剩余时间重新放入更低的层
+//! - 超出最高层范围（约 2.2 年）的条目放在最高层，轮转到时再重新放入
+//!
+//! 每层用一个 64 位掩码记录非空的槽，推进时直接跳到下一个非空的槽，不需要逐毫秒前进。
+//! 到期的条目（包括插入时到期时间已经过去的）进入待处理集合，由 [`TimerWheel::pop_expired`]
+//! 分批取出，调用方可以限制每次处理的数量。
+
+use std::{
+    borrow::Borrow,
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+use crate::db::unix_time_ms;
+
+/// 每层的槽数为 `2^LEVEL_BITS`
+const LEVEL_BITS: usize = 6;
+const SLOTS: usize = 1 << LEVEL_BITS;
+/// 层数
+pub const LEVELS: usize = 6;
+/// 最高层覆盖的时间跨度（毫秒）
+const MAX_SPAN: u64 = 1 << (LEVEL_BITS * LEVELS);
+
+/// 条目所在的位置
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Location {
+    Slot {
+        level: usize,
+        slot: usize,
+    },
+    /// 已到期，等待取出
+    Expired,
+}
+
+struct Level<K> {
+    slots: Vec<HashSet<K>>,
+    /// 非空的槽
+    occupied: u64,
+}
+
+/// 分层时间轮
+pub struct TimerWheel<K> {
+    /// 时间轮的当前时间（Unix 毫秒），只会前进
+    elapsed: u64,
+    /// 各层的槽，第一次插入时才分配
+    levels: Vec<Level<K>>,
+    /// 条目 -> (到期时间, 位置)
+    entries: HashMap<K, (u64, Location)>,
+    /// 已到期、等待取出的条目
+    expired: HashSet<K>,
+}
+
+impl<K: Hash + Eq + Clone> Default for TimerWheel<K> {
+    /// 以当前的 Unix 毫秒时间为起点
+    fn default() -> Self {
+        Self::new(unix_time_ms())
+    }
+}
+
+impl<K: Hash + Eq + Clone> TimerWheel<K> {
+    /// 创建一个以 `now` 为当前时间的空时间轮
+    pub fn new(now: u64) -> Self {
+        Self { elapsed: now, levels: Vec::new(), entries: HashMap::new(), expired: HashSet::new() }
+    }
+
+    /// 条目数（包括已到期、尚未取出的条目）
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 是否没有任何条目
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 条目的到期时间
+    pub fn deadline<Q>(&self, key: &Q) -> Option<u64>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries.get(key).map(|(when, _)| *when)
+    }
+
+    /// 插入条目或修改已有条目的到期时间
+    pub fn insert(&mut self, key: K, when: u64) {
+        self.remove(&key);
+        let location = self.place(key.clone(), when);
+        self.entries.insert(key, (when, location));
+    }
+
+    /// 删除条目，返回它的到期时间
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<u64>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (when, location) = self.entries.remove(key)?;
+        match location {
+            Location::Slot { level, slot } => {
+                let level = &mut self.levels[level];
+                level.slots[slot].remove(key);
+                if level.slots[slot].is_empty() {
+                    level.occupied &= !(1 << slot);
+                }
+            }
+            Location::Expired => {
+                self.expired.remove(key);
+            }
+        }
+        Some(when)
+    }
+
+    /// 删除全部条目
+    pub fn clear(&mut self) {
+        self.levels.clear();
+        self.entries.clear();
+        self.expired.clear();
+    }
+
+    /// 把时间推进到 `now`，期间到期的条目进入待处理集合；`now` 早于当前时间时不做任何事
+    pub fn advance(&mut self, now: u64) {
+        while let Some((level, slot, deadline)) = self.next_expiration() {
+            if deadline > now {
+                break;
+            }
+            self.elapsed = deadline;
+            let keys = std::mem::take(&mut self.levels[level].slots[slot]);
+            self.levels[level].occupied &= !(1 << slot);
+            // 最低层的条目恰好在此时到期，较高层的条目按剩余时间放入更低的层
+            for key in keys {
+                let when = self.entries[&key].0;
+                let location = self.place(key.clone(), when);
+                self.entries.get_mut(&key).expect("entry is in the wheel").1 = location;
+            }
+        }
+        self.elapsed = self.elapsed.max(now);
+    }
+
+    /// 取出最多 `limit` 个已到期的条目（需要先 [`advance`](Self::advance)），返回条目与到期时间
+    pub fn pop_expired(&mut self, limit: usize) -> Vec<(K, u64)> {
+        let keys: Vec<K> = self.expired.iter().take(limit).cloned().collect();
+        keys.into_iter()
+            .map(|key| {
+                let when = self.remove(&key).expect("expired entry is in the wheel");
+                (key, when)
+            })
+            .collect()
+    }
+
+    /// 按到期时间把条目放入对应的槽，已到期的放入待处理集合
+    fn place(&mut self, key: K, when: u64) -> Location {
+        if when <= self.elapsed {
+            self.expired.insert(key);
+            return Location::Expired;
+        }
+        if self.levels.is_empty() {
+            self.levels = (0..LEVELS)
+                .map(|_| Level { slots: (0..SLOTS).map(|_| HashSet::new()).collect(), occupied: 0 })
+                .collect();
+        }
+        let level = level_for(self.elapsed, when);
+        let slot = (when >> (level * LEVEL_BITS)) as usize % SLOTS;
+        self.levels[level].slots[slot].insert(key);
+        self.levels[level].occupied |= 1 << slot;
+        Location::Slot { level, slot }
+    }
+
+    /// 下一个到期的槽：`(层, 槽, 槽的起始时间)`
+    ///
+    /// 低层的条目总是比高层的条目先到期，因此返回最低的非空层中当前时间之后的第一个非空槽。
+    fn next_expiration(&self) -> Option<(usize, usize, u64)> {
+        self.levels.iter().enumerate().find_map(|(n, level)| {
+            if level.occupied == 0 {
+                return None;
+            }
+            let slot_span = 1u64 << (n * LEVEL_BITS);
+            let level_span = slot_span << LEVEL_BITS;
+            let now_slot = (self.elapsed / slot_span) as usize % SLOTS;
+            let slot = (level.occupied.rotate_right(now_slot as u32).trailing_zeros() as usize
+                + now_slot)
+                % SLOTS;
+
+            let mut deadline = (self.elapsed & !(level_span - 1)) + slot as u64 * slot_span;
+            // 只有最高层放着超出范围的条目时，非空槽才可能位于当前时间之前，要等到下一轮
+            if deadline <= self.elapsed {
+                deadline += level_span;
+            }
+            Some((n, slot, deadline))
+        })
+    }
+}
+
+/// 到期时间为 `when` 的条目在当前时间为 `elapsed` 时所在的层
+fn level_for(elapsed: u64, when: u64) -> usize {
+    let masked = ((elapsed ^ when) | (SLOTS as u64 - 1)).min(MAX_SPAN - 1);
+    let significant = 63 - masked.leading_zeros() as usize;
+    significant / LEVEL_BITS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn popped(wheel: &mut TimerWheel<&'static str>) -> Vec<&'static str> {
+        let mut keys: Vec<_> = wheel.pop_expired(usize::MAX).into_iter().map(|(k, _)| k).collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn test_level_for() {
+        assert_eq!(level_for(0, 1), 0);
+        assert_eq!(level_for(0, 63), 0);
+        assert_eq!(level_for(0, 64), 1);
+        assert_eq!(level_for(0, 4095), 1);
+        assert_eq!(level_for(0, 4096), 2);
+        assert_eq!(level_for(0, u64::MAX), LEVELS - 1);
+    }
+
+    #[test]
+    fn test_advance() {
+        let start = 1_700_000_000_000;
+        let mut wheel = TimerWheel::new(start);
+        wheel.insert("a", start + 10);
+        wheel.insert("b", start + 5_000);
+        wheel.insert("c", start + 3_600_000);
+        wheel.insert("d", start + 5_000);
+        wheel.insert("past", start - 1);
+        assert_eq!(wheel.len(), 5);
+        assert_eq!(wheel.deadline("b"), Some(start + 5_000));
+
+        assert_eq!(popped(&mut wheel), ["past"]);
+        wheel.advance(start + 9);
+        assert!(popped(&mut wheel).is_empty());
+        wheel.advance(start + 10);
+        assert_eq!(popped(&mut wheel), ["a"]);
+
+        // 修改与删除
+        wheel.insert("d", start + 6_000);
+        wheel.advance(start + 5_999);
+        assert_eq!(popped(&mut wheel), ["b"]);
+        assert_eq!(wheel.remove("d"), Some(start + 6_000));
+        wheel.advance(start + 3_599_999);
+        assert!(popped(&mut wheel).is_empty());
+        wheel.advance(start + 4_000_000);
+        assert_eq!(popped(&mut wheel), ["c"]);
+        assert!(wheel.is_empty());
+    }
+
+    #[test]
+    fn test_every_deadline_fires_on_time() {
+        let start = 12_345;
+        let mut wheel = TimerWheel::new(start);
+        let deadlines: Vec<u64> = (0..2000).map(|i| start + 1 + i * i * 37 % 300_000).collect();
+        for (i, when) in deadlines.iter().enumerate() {
+            wheel.insert(i, *when);
+        }
+
+        // 按不规则的步长推进，每个条目恰好在到期的那一步被取出
+        let mut now = start;
+        while !wheel.is_empty() {
+            let prev = now;
+            now += 1 + now % 997;
+            wheel.advance(now);
+            for (i, when) in wheel.pop_expired(usize::MAX) {
+                assert_eq!(when, deadlines[i]);
+                assert!(prev < when && when <= now, "{when} fired in ({prev}, {now}]");
+            }
+        }
+    }
+
+    #[test]
+    fn test_beyond_max_span() {
+        let mut wheel = TimerWheel::new(0);
+        wheel.insert("far", MAX_SPAN * 3 + 5);
+        wheel.advance(MAX_SPAN * 3);
+        assert!(wheel.pop_expired(10).is_empty());
+        wheel.advance(MAX_SPAN * 3 + 5);
+        assert_eq!(wheel.pop_expired(10), [("far", MAX_SPAN * 3 + 5)]);
+    }
+
+    #[test]
+    fn test_pop_expired_limit() {
+        let mut wheel = TimerWheel::new(0);
+        for key in ["a", "b", "c"] {
+            wheel.insert(key, 1);
+        }
+        wheel.advance(1);
+        assert_eq!(wheel.pop_expired(2).len(), 2);
+        assert_eq!(wheel.len(), 1);
+        assert_eq!(wheel.pop_expired(2).len(), 1);
+    }
+}
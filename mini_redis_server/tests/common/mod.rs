@@ -0,0 +1,163 @@
+//! 集成测试的公共设施，各测试文件通过 `mod common;` 引入
+//!
+//! - [`TestServer`]：在随机端口上启动一个嵌入式服务器，每个测试各用一份独立的数据
+//! - [`TestClient`]：按行发送命令并读取回复，支持流水线与读取推送的消息
+//! - [`assert_reply!`]：发送一条命令并断言回复
+//! - [`ok`]、[`bulk`] 等：构造期望的回复
+//!
+//! 读取回复有超时（[`REPLY_TIMEOUT`]），命令被意外阻塞时测试失败而不是挂起。
+
+#![allow(dead_code)]
+
+use std::{net::SocketAddr, time::Duration};
+
+use mini_redis_server::{Server, connection::Connection, db::Db, frame::Frame};
+use tokio::{net::TcpStream, time::timeout};
+
+/// 等待一个回复的最长时间
+pub const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 发送一条命令并断言回复：`assert_reply!(client, "set foo bar", ok())`
+macro_rules! assert_reply {
+    ($client:expr, $command:expr, $expected:expr $(,)?) => {
+        assert_eq!($client.command($command).await, $expected, "reply to `{}`", $command)
+    };
+}
+pub(crate) use assert_reply;
+
+/// 测试用的服务器，销毁时停止
+pub struct TestServer {
+    server: Server,
+}
+
+impl TestServer {
+    /// 使用空数据库启动
+    pub async fn start() -> Self {
+        Self::with_db(Db::new()).await
+    }
+
+    /// 使用给定的数据库启动，测试可以预先写入数据或修改配置
+    pub async fn with_db(db: Db) -> Self {
+        let server = Server::builder().db(db).start().await.expect("failed to start server");
+        Self { server }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.server.local_addr()
+    }
+
+    /// 服务器使用的数据库，可以绕过网络检查数据
+    pub fn db(&self) -> &Db {
+        self.server.db()
+    }
+
+    /// 建立一个新连接
+    pub async fn client(&self) -> TestClient {
+        TestClient::connect(self.addr()).await
+    }
+
+    pub async fn shutdown(self) {
+        self.server.shutdown().await.expect("failed to shut down server");
+    }
+}
+
+/// 测试用的客户端
+pub struct TestClient {
+    conn: Connection,
+}
+
+impl TestClient {
+    pub async fn connect(addr: SocketAddr) -> Self {
+        let stream = TcpStream::connect(addr).await.expect("failed to connect");
+        Self { conn: Connection::new(stream) }
+    }
+
+    /// 发送一条命令并读取回复，命令按空白切分为参数（参数中不能含空白，否则用 [`Self::command_args`]）
+    pub async fn command(&mut self, line: &str) -> Frame {
+        self.send(line).await;
+        self.read().await
+    }
+
+    /// 按参数发送一条命令并读取回复
+    pub async fn command_args(&mut self, args: &[&str]) -> Frame {
+        self.send_args(args).await;
+        self.read().await
+    }
+
+    /// 只发送命令，不等待回复，用于阻塞命令与订阅
+    pub async fn send(&mut self, line: &str) {
+        self.send_args(&line.split_whitespace().collect::<Vec<_>>()).await;
+    }
+
+    pub async fn send_args(&mut self, args: &[&str]) {
+        self.conn.write_frame(&request(args)).await.expect("failed to send command");
+    }
+
+    /// 流水线：一次写入全部命令，再依次读取各自的回复
+    pub async fn pipeline(&mut self, lines: &[&str]) -> Vec<Frame> {
+        for line in lines {
+            let args: Vec<_> = line.split_whitespace().collect();
+            self.conn.feed_frame(&request(&args)).await.expect("failed to send command");
+        }
+        self.conn.flush().await.expect("failed to send command");
+        let mut replies = Vec::new();
+        for _ in lines {
+            replies.push(self.read().await);
+        }
+        replies
+    }
+
+    /// 读取下一个回复或推送的消息，超时或连接关闭时测试失败
+    pub async fn read(&mut self) -> Frame {
+        match timeout(REPLY_TIMEOUT, self.conn.read_frame()).await {
+            Ok(Ok(Some(frame))) => frame,
+            Ok(Ok(None)) => panic!("connection closed by server"),
+            Ok(Err(e)) => panic!("failed to read reply: {e}"),
+            Err(_) => panic!("no reply within {REPLY_TIMEOUT:?}"),
+        }
+    }
+
+    /// 断言 `wait` 时间内没有收到任何帧，例如阻塞命令仍在等待
+    pub async fn assert_silent(&mut self, wait: Duration) {
+        if let Ok(frame) = timeout(wait, self.conn.read_frame()).await {
+            panic!("unexpected frame: {frame:?}");
+        }
+    }
+}
+
+fn request(args: &[&str]) -> Frame {
+    Frame::from(args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+}
+
+pub fn ok() -> Frame {
+    Frame::Simple("OK".into())
+}
+
+pub fn simple(s: &str) -> Frame {
+    Frame::Simple(s.into())
+}
+
+pub fn bulk(s: &str) -> Frame {
+    Frame::Bulk(s.into())
+}
+
+pub fn int(n: i64) -> Frame {
+    Frame::Integer(n)
+}
+
+pub fn nil() -> Frame {
+    Frame::Null
+}
+
+pub fn err(message: &str) -> Frame {
+    Frame::Error(message.into())
+}
+
+pub fn array(items: impl IntoIterator<Item = Frame>) -> Frame {
+    Frame::Array(items.into_iter().collect())
+}
+
+/// 由批量字符串组成的数组
+pub fn bulks(items: &[&str]) -> Frame {
+    array(items.iter().map(|item| bulk(item)))
+}
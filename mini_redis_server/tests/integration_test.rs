@@ -1,3 +1,8 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{TestServer, array, assert_reply, bulk, bulks, err, int, nil, ok};
 use mini_redis_server::db::Db;
 use mini_redis_server::handler::process_command;
 
@@ -11,3 +16,66 @@ async fn test_end_to_end() {
 
     assert_eq!(result, "42");
 }
+
+#[tokio::test]
+async fn test_embedded_server() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    assert_reply!(client, "SET foo 42", ok());
+    assert_eq!(process_command(server.db(), "GET foo").await, "42");
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_pipeline() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+
+    let replies = client.pipeline(&["set a 1", "incr a", "get a", "get missing", "incr"]).await;
+    assert_eq!(
+        replies,
+        [ok(), int(2), bulk("2"), nil(), err("ERR wrong number of arguments for 'incr' command")]
+    );
+}
+
+#[tokio::test]
+async fn test_pubsub() {
+    let server = TestServer::start().await;
+    let mut subscriber = server.client().await;
+    let mut publisher = server.client().await;
+
+    assert_reply!(subscriber, "subscribe news", array([bulk("subscribe"), bulk("news"), int(1)]));
+    assert_reply!(publisher, "publish news hello", int(1));
+    assert_reply!(publisher, "publish other hello", int(0));
+    assert_eq!(subscriber.read().await, bulks(&["message", "news", "hello"]));
+}
+
+#[tokio::test]
+async fn test_blocking_command() {
+    let server = TestServer::start().await;
+    let mut waiter = server.client().await;
+    let mut writer = server.client().await;
+
+    waiter.send("bzpopmin z 0").await;
+    waiter.assert_silent(Duration::from_millis(50)).await;
+    assert_reply!(writer, "zadd z 1 m", int(1));
+    assert_eq!(waiter.read().await, bulks(&["z", "m", "1"]));
+    assert_reply!(writer, "zcard z", int(0));
+}
+
+#[tokio::test]
+async fn test_isolated_servers() {
+    let first = TestServer::start().await;
+    let second = TestServer::start().await;
+    let (mut a, mut b) = (first.client().await, second.client().await);
+
+    assert_reply!(a, "set key first", ok());
+    assert_reply!(b, "get key", nil());
+    assert_reply!(
+        a,
+        "sadd key x",
+        err("WRONGTYPE Operation against a key holding the wrong kind of value")
+    );
+}